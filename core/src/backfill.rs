@@ -0,0 +1,306 @@
+//! Backfills: historical runs of a scheduled workflow over a date range
+//!
+//! A backfill enumerates the firings the workflow's cron and interval
+//! triggers would have had between two timestamps, or a fixed interval given
+//! instead, and creates one run per firing with the logical timestamp in its
+//! payload as `scheduled_at`, like the engine's own schedule firings, along
+//! with the `backfill_id`. At most `max_concurrent` runs of a backfill are
+//! unfinished at once; the rest are created as earlier ones finish, each time
+//! due schedules are fired. Backfill runs bypass the overlap policy of the
+//! schedule, but not rate limits, disabled workflows or a paused engine.
+//! Backfills are kept in memory: one interrupted by a restart is not resumed.
+
+use std::collections::VecDeque;
+use std::str::FromStr;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{RunStatus, TriggerDefinition, WorkflowDefinition};
+
+/// Most runs a single backfill may create
+pub const MAX_BACKFILL_RUNS: usize = 10_000;
+
+/// Unfinished runs of a backfill at once, unless the caller asks for more
+pub const DEFAULT_MAX_CONCURRENT: usize = 1;
+
+/// How soon due schedules should be fired again while a backfill is running
+pub const POLL_INTERVAL_SECS: i64 = 1;
+
+/// Trigger type recorded for runs created by a backfill
+pub const TRIGGER_TYPE: &str = "backfill";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackfillStatus {
+    Running,
+    Completed,
+    Cancelled,
+}
+
+/// Progress of a backfill
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackfillProgress {
+    pub id: String,
+    pub workflow_id: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    /// Fixed interval between firings, None when the workflow's schedule was enumerated
+    pub interval_secs: Option<u64>,
+    pub max_concurrent: usize,
+    pub status: BackfillStatus,
+    /// Runs the backfill creates in total
+    pub total: usize,
+    pub created: usize,
+    pub completed: usize,
+    /// Runs that failed or were cancelled
+    pub failed: usize,
+    /// Logical timestamp of the next run to create
+    pub next_scheduled_at: Option<DateTime<Utc>>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A backfill with the firings it has yet to create runs for
+pub struct Backfill {
+    progress: BackfillProgress,
+    slots: VecDeque<DateTime<Utc>>,
+    in_flight: Vec<Uuid>,
+}
+
+impl Backfill {
+    pub fn new(workflow: &WorkflowDefinition, from: DateTime<Utc>, to: DateTime<Utc>, interval_secs: Option<u64>, max_concurrent: usize) -> CoreResult<Self> {
+        if max_concurrent == 0 {
+            return Err(CoreError::Validation("Backfill concurrency must be at least 1".to_string()));
+        }
+        let slots = schedule_slots(workflow, from, to, interval_secs)?;
+        Ok(Self {
+            progress: BackfillProgress {
+                id: Uuid::new_v4().to_string(),
+                workflow_id: workflow.id.clone(),
+                from,
+                to,
+                interval_secs,
+                max_concurrent,
+                status: BackfillStatus::Running,
+                total: slots.len(),
+                created: 0,
+                completed: 0,
+                failed: 0,
+                next_scheduled_at: slots.first().copied(),
+                started_at: Utc::now(),
+                finished_at: None,
+            },
+            slots: slots.into(),
+            in_flight: Vec::new(),
+        })
+    }
+
+    pub fn progress(&self) -> &BackfillProgress {
+        &self.progress
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.progress.status == BackfillStatus::Running
+    }
+
+    /// Runs created by the backfill that have not finished yet
+    pub fn in_flight(&self) -> &[Uuid] {
+        &self.in_flight
+    }
+
+    /// Count in-flight runs that finished, with their final status
+    pub fn record_finished(&mut self, finished: &[(Uuid, RunStatus)]) {
+        for (run_id, status) in finished {
+            let Some(index) = self.in_flight.iter().position(|id| id == run_id) else {
+                continue;
+            };
+            self.in_flight.swap_remove(index);
+            match status {
+                RunStatus::Completed => self.progress.completed += 1,
+                _ => self.progress.failed += 1,
+            }
+        }
+        if self.is_running() && self.slots.is_empty() && self.in_flight.is_empty() {
+            self.progress.status = BackfillStatus::Completed;
+            self.progress.finished_at = Some(Utc::now());
+        }
+    }
+
+    /// Take the firings to create runs for now, as many as the concurrency limit leaves room for
+    pub fn take_due(&mut self) -> Vec<DateTime<Utc>> {
+        if !self.is_running() {
+            return Vec::new();
+        }
+        let room = self.progress.max_concurrent.saturating_sub(self.in_flight.len()).min(self.slots.len());
+        let due = self.slots.drain(..room).collect();
+        self.progress.next_scheduled_at = self.slots.front().copied();
+        due
+    }
+
+    /// Track a run created for a firing taken with `take_due`
+    pub fn record_created(&mut self, run_id: Uuid) {
+        self.progress.created += 1;
+        self.in_flight.push(run_id);
+    }
+
+    /// Return firings no run could be created for, to try them again on the next advance
+    pub fn put_back(&mut self, slots: Vec<DateTime<Utc>>) {
+        if !self.is_running() {
+            return;
+        }
+        for slot in slots.into_iter().rev() {
+            self.slots.push_front(slot);
+        }
+        self.progress.next_scheduled_at = self.slots.front().copied();
+    }
+
+    /// Stop creating runs; runs already created are left to finish
+    pub fn cancel(&mut self) {
+        if !self.is_running() {
+            return;
+        }
+        self.slots.clear();
+        self.in_flight.clear();
+        self.progress.status = BackfillStatus::Cancelled;
+        self.progress.next_scheduled_at = None;
+        self.progress.finished_at = Some(Utc::now());
+    }
+}
+
+/// Enumerate the firings between `from` and `to`, both included, in order
+///
+/// With an interval, firings are `interval_secs` apart starting at `from`.
+/// Otherwise the workflow's cron and interval triggers are enumerated;
+/// interval triggers are counted from `from`, since where they started
+/// firing is not known for the past.
+pub fn schedule_slots(workflow: &WorkflowDefinition, from: DateTime<Utc>, to: DateTime<Utc>, interval_secs: Option<u64>) -> CoreResult<Vec<DateTime<Utc>>> {
+    if from > to {
+        return Err(CoreError::Validation(format!("Backfill range starts at {} after it ends at {}", from, to)));
+    }
+
+    let mut slots = Vec::new();
+    if let Some(interval_secs) = interval_secs {
+        slots.extend(every(from, to, interval_secs)?);
+    } else {
+        for trigger in &workflow.triggers {
+            match trigger {
+                TriggerDefinition::Schedule { cron_expression, .. } => {
+                    let schedule = parse_cron(cron_expression)?;
+                    // `after` excludes the timestamp it starts from
+                    slots.extend(schedule.after(&(from - Duration::seconds(1)))
+                        .skip_while(|at| *at < from)
+                        .take_while(|at| *at <= to)
+                        .take(MAX_BACKFILL_RUNS + 1));
+                }
+                TriggerDefinition::Interval { every_seconds, .. } => slots.extend(every(from, to, *every_seconds)?),
+                _ => {}
+            }
+            if slots.len() > MAX_BACKFILL_RUNS {
+                break;
+            }
+        }
+        if !workflow.triggers.iter().any(|t| matches!(t, TriggerDefinition::Schedule { .. } | TriggerDefinition::Interval { .. })) {
+            return Err(CoreError::Validation(format!(
+                "Workflow {} has no schedule or interval trigger; give the backfill an interval", workflow.id
+            )));
+        }
+    }
+    slots.sort();
+    slots.dedup();
+
+    if slots.len() > MAX_BACKFILL_RUNS {
+        return Err(CoreError::Validation(format!(
+            "Backfill of workflow {} would create more than {} runs; split the range", workflow.id, MAX_BACKFILL_RUNS
+        )));
+    }
+    Ok(slots)
+}
+
+fn every(from: DateTime<Utc>, to: DateTime<Utc>, interval_secs: u64) -> CoreResult<impl Iterator<Item = DateTime<Utc>>> {
+    if interval_secs == 0 {
+        return Err(CoreError::Validation("Backfill interval must be at least one second".to_string()));
+    }
+    let interval = Duration::seconds(interval_secs as i64);
+    Ok(std::iter::successors(Some(from), move |at| Some(*at + interval))
+        .take_while(move |at| *at <= to)
+        .take(MAX_BACKFILL_RUNS + 1))
+}
+
+/// Parse a cron expression, with or without a leading seconds field
+fn parse_cron(expression: &str) -> CoreResult<cron::Schedule> {
+    let expression = expression.trim();
+    let expression = if expression.split_whitespace().count() == 5 {
+        format!("0 {}", expression)
+    } else {
+        expression.to_string()
+    };
+    cron::Schedule::from_str(&expression)
+        .map_err(|e| CoreError::InvalidTrigger(format!("Invalid cron expression {:?}: {}", expression, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::OverlapPolicy;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2026-03-01T{:02}:{:02}:00Z", hour, minute)).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_backfills_enumerate_the_schedule_and_respect_the_concurrency_limit() {
+        let workflow = WorkflowDefinition {
+            id: "reports".to_string(),
+            name: "Reports".to_string(),
+            description: None,
+            steps: Vec::new(),
+            triggers: vec![
+                TriggerDefinition::Schedule { cron_expression: "0 * * * *".to_string(), overlap_policy: OverlapPolicy::Skip },
+                TriggerDefinition::Interval { every_seconds: 5400, overlap_policy: OverlapPolicy::Allow, catch_up: Default::default() },
+            ],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let slots = schedule_slots(&workflow, at(1, 0), at(4, 0), None).unwrap();
+        assert_eq!(slots, vec![at(1, 0), at(2, 0), at(2, 30), at(3, 0), at(4, 0)]);
+        assert_eq!(schedule_slots(&workflow, at(1, 0), at(1, 50), Some(1200)).unwrap(), vec![at(1, 0), at(1, 20), at(1, 40)]);
+        assert!(schedule_slots(&workflow, at(4, 0), at(1, 0), None).is_err());
+        assert!(schedule_slots(&workflow, at(0, 0), at(23, 0), Some(1)).unwrap_err().to_string().contains("more than"));
+
+        let mut backfill = Backfill::new(&workflow, at(1, 0), at(4, 0), None, 2).unwrap();
+        assert_eq!(backfill.take_due(), vec![at(1, 0), at(2, 0)]);
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        backfill.record_created(first);
+        backfill.record_created(second);
+        assert!(backfill.take_due().is_empty());
+
+        backfill.record_finished(&[(first, RunStatus::Completed)]);
+        let due = backfill.take_due();
+        assert_eq!(due, vec![at(2, 30)]);
+        // A rate limited firing is tried again first
+        backfill.put_back(due);
+        assert_eq!(backfill.progress().next_scheduled_at, Some(at(2, 30)));
+        assert_eq!(backfill.take_due(), vec![at(2, 30)]);
+        let third = Uuid::new_v4();
+        backfill.record_created(third);
+        backfill.record_finished(&[(second, RunStatus::Failed), (third, RunStatus::Completed)]);
+        assert_eq!(backfill.take_due(), vec![at(3, 0), at(4, 0)]);
+        let last_runs = [Uuid::new_v4(), Uuid::new_v4()];
+        last_runs.iter().for_each(|run_id| backfill.record_created(*run_id));
+        backfill.record_finished(&last_runs.map(|run_id| (run_id, RunStatus::Completed)));
+        let progress = backfill.progress();
+        assert_eq!((progress.status, progress.total, progress.created, progress.completed, progress.failed), (BackfillStatus::Completed, 5, 5, 4, 1));
+        assert!(progress.finished_at.is_some());
+    }
+}
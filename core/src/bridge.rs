@@ -4,16 +4,20 @@
 //! and the Node.js SDK via N-API.
 
 use std::sync::{Arc, Mutex, OnceLock};
+use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex as TokioMutex;
+use napi::Env;
 use napi_derive::napi;
 use crate::{
-    models::WorkflowDefinition,
+    models::{WorkflowDefinition, WorkflowCompatibilityReport, RunLabels, RunQuery, RunRequest, RunStatus, WorkflowRun},
     state::{StateManager, AsyncStateManager},
     trigger_executor::TriggerExecutor,
     dispatcher::Dispatcher,
     triggers::TriggerManager,
     error::CoreError,
-    job::Job,
+    job::{Job, JobState},
+    cold_storage::ColdStorageExporter,
+    history_export::{RunExporter, RunExportSummary},
 };
 use crate::error::CoreResult;
 use uuid::Uuid;
@@ -23,9 +27,21 @@ use serde::Serialize;
 /// N-API bridge for Node.js communication (synchronous version - kept for backward compatibility)
 pub struct Bridge {
     state_manager: Arc<Mutex<StateManager>>,
-    trigger_manager: Arc<Mutex<TriggerManager>>,
+    trigger_manager: Arc<TokioMutex<TriggerManager>>,
     trigger_executor: TriggerExecutor,
-    job_dispatcher: Arc<Mutex<Arc<tokio::sync::Mutex<Dispatcher>>>>, // Wrapper for async dispatcher
+    job_dispatcher: Arc<TokioMutex<Dispatcher>>,
+    /// Webhook server started through the bridge, if running
+    webhook_server: TokioMutex<Option<crate::webhook_server::WebhookServer>>,
+    /// Set once the engine was shut down; schedules no longer fire
+    shut_down: AtomicBool,
+    /// Report of the orphaned run recovery run when the bridge was created
+    recovery_report: Mutex<Option<crate::models::OrphanRecoveryReport>>,
+    /// Requeued orphaned runs, as (run ID, workflow ID), not resumed yet
+    pending_recovery: Mutex<Vec<(String, String)>>,
+    /// onSuccess/onFailure/onCancel hook invocations in flight
+    completion_hooks: Arc<Mutex<crate::completion_hooks::CompletionHooks>>,
+    /// Run event subscription feeding finished runs to the hooks, once a handler is set
+    hook_subscription: Mutex<Option<u32>>,
 }
 
 /// Async N-API bridge for Node.js communication
@@ -78,6 +94,46 @@ fn handle_bridge_error<T: Default>(error: CoreError) -> T {
     T::default()
 }
 
+/// Build a run query from optional N-API arguments
+fn build_run_query(
+    workflow_id: Option<String>,
+    status: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    since: Option<String>,
+    pinned: Option<bool>,
+    labels_json: Option<String>,
+) -> CoreResult<RunQuery> {
+    let status = status
+        .map(|s| RunStatus::parse(&s).ok_or_else(|| CoreError::Validation(format!("Unknown run status: {}", s))))
+        .transpose()?;
+    let since = since
+        .map(|s| chrono::DateTime::parse_from_rfc3339(&s).map(|dt| dt.with_timezone(&chrono::Utc)))
+        .transpose()?;
+    let labels: RunLabels = labels_json
+        .map(|json| serde_json::from_str(&json))
+        .transpose()?
+        .unwrap_or_default();
+    
+    Ok(RunQuery {
+        workflow_id,
+        status,
+        since,
+        pinned,
+        labels,
+        limit: limit.map(|l| l as usize),
+        offset: offset.map(|o| o as usize),
+    })
+}
+
+/// Run an async bridge call to completion from a sync N-API function
+///
+/// Sync N-API functions run on the JavaScript thread, outside any runtime, so
+/// blocking on napi's runtime there never nests one runtime inside another.
+fn block_on_napi<F: std::future::Future>(future: F) -> F::Output {
+    napi::bindgen_prelude::block_on(future)
+}
+
 /// Macro for standardized N-API function patterns with shared bridge
 macro_rules! with_shared_bridge {
     ($db_path:expr, $success_result:expr, $failure_result:expr, $operation:expr) => {
@@ -85,10 +141,10 @@ macro_rules! with_shared_bridge {
             Ok(bridge) => {
                 match $operation(bridge) {
                     Ok(result) => $success_result(result),
-                    Err(e) => $failure_result(format!("Operation failed: {}", e)),
+                    Err(e) => $failure_result(format!("Operation failed: {}", e)).with_error(&e),
                 }
             }
-            Err(e) => $failure_result(format!("Failed to get bridge: {}", e)),
+            Err(e) => $failure_result(format!("Failed to get bridge: {}", e)).with_error(&e),
         }
     };
 }
@@ -97,40 +153,124 @@ impl Bridge {
     /// Create a new N-API bridge
     pub fn new(db_path: &str) -> CoreResult<Self> {
         let state_manager = Arc::new(Mutex::new(StateManager::new(db_path)?));
-        let trigger_manager = Arc::new(Mutex::new(TriggerManager::new()));
+        let trigger_manager = Arc::new(TokioMutex::new(TriggerManager::new()));
+        // Spans are exported in batches from napi's runtime
+        napi::bindgen_prelude::within_runtime_if_available(|| {
+            crate::telemetry::init(&crate::config::CoreConfig::default().telemetry)
+        })?;
+        crate::logging::init(&crate::config::CoreConfig::default().logging)?;
         
-        // Create a tokio Mutex wrapper for the dispatcher
-        // The dispatcher needs async state manager access
-        let state_manager_for_dispatcher = {
-            // Create new state manager for dispatcher (will be shared later)
-            Arc::new(tokio::sync::Mutex::new(StateManager::new(db_path)?))
-        };
+        // The dispatcher and the trigger executor share one async state manager
+        let state_manager_for_dispatcher = Arc::new(TokioMutex::new(StateManager::new(db_path)?));
+        
+        // Workers stay idle after a restart if the engine was left paused
+        let engine_paused = state_manager.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?
+            .is_engine_paused()?;
         
         let dispatcher_config = crate::dispatcher::WorkerPoolConfig::default();
-        let async_dispatcher = Dispatcher::new(dispatcher_config, state_manager_for_dispatcher);
-        let async_dispatcher_arc = Arc::new(tokio::sync::Mutex::new(async_dispatcher));
-        let job_dispatcher = Arc::new(Mutex::new(Arc::clone(&async_dispatcher_arc))); // Sync wrapper for Bridge
+        let job_dispatcher = Arc::new(TokioMutex::new(
+            Dispatcher::new(dispatcher_config, Arc::clone(&state_manager_for_dispatcher)).with_paused(engine_paused)
+        ));
         
         let trigger_executor = TriggerExecutor::new(
-            state_manager.clone(), 
+            state_manager_for_dispatcher,
             trigger_manager.clone(),
-            Arc::clone(&job_dispatcher)  // Share the same Arc<Mutex<Arc<TokioMutex<Dispatcher>>>>
+            Arc::clone(&job_dispatcher),
         );
         
-        Ok(Bridge { 
+        let bridge = Bridge { 
             state_manager,
             trigger_manager,
             trigger_executor,
             job_dispatcher,
-        })
+            webhook_server: TokioMutex::new(None),
+            shut_down: AtomicBool::new(false),
+            recovery_report: Mutex::new(None),
+            pending_recovery: Mutex::new(Vec::new()),
+            completion_hooks: Arc::new(Mutex::new(crate::completion_hooks::CompletionHooks::new(crate::config::CoreConfig::default().hooks))),
+            hook_subscription: Mutex::new(None),
+        };
+        if let Err(e) = bridge.recover_orphaned_runs() {
+            log::error!("Recovery of orphaned runs failed: {}", e);
+        }
+        Ok(bridge)
+    }
+
+    /// Find runs a crashed engine left running and apply the orphan run policy
+    ///
+    /// Requeued runs are resumed by the next scheduler tick. With a local
+    /// database every running run is orphaned, as no other engine can be
+    /// driving it; with a shared store, or in distributed mode where peers
+    /// may share the SQLite file, only runs idle for the lease are.
+    fn recover_orphaned_runs(&self) -> CoreResult<()> {
+        let core_config = crate::config::CoreConfig::default();
+        let config = core_config.execution;
+        let report = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            let shared = state_manager.has_shared_store() || core_config.distributed.enabled;
+            let lease_secs = if shared { config.orphan_run_lease_secs } else { 0 };
+            state_manager.recover_orphaned_runs(
+                config.orphan_run_policy,
+                config.non_idempotent_recovery,
+                chrono::Duration::seconds(lease_secs as i64),
+                chrono::Utc::now(),
+            )?
+        }; // Lock released here
+        
+        if !report.runs.is_empty() {
+            log::warn!("Recovered {} orphaned runs: {}", report.runs.len(), serde_json::to_string(&report)?);
+        }
+        let requeued = report.runs.iter()
+            .filter(|run| run.outcome == crate::models::OrphanRunOutcome::Requeued)
+            .map(|run| (run.run_id.clone(), run.workflow_id.clone()));
+        self.pending_recovery.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire recovery lock".to_string()))?
+            .extend(requeued);
+        *self.recovery_report.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire recovery lock".to_string()))? = Some(report);
+        Ok(())
+    }
+
+    /// Get the report of the orphaned run recovery run at startup
+    pub fn get_recovery_report(&self) -> CoreResult<String> {
+        let report = self.recovery_report.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire recovery lock".to_string()))?
+            .clone();
+        Ok(serde_json::to_string(&report)?)
+    }
+
+    /// Resume the requeued orphaned runs, returning their IDs
+    fn resume_recovered_runs(&self) -> CoreResult<Vec<String>> {
+        let pending = std::mem::take(&mut *self.pending_recovery.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire recovery lock".to_string()))?);
+        
+        let mut resumed = Vec::with_capacity(pending.len());
+        for (run_id, workflow_id) in pending {
+            log::info!("Resuming recovered run {}", run_id);
+            match self.execute_workflow_steps(&run_id, &workflow_id) {
+                Ok(_) => resumed.push(run_id),
+                Err(e) => log::error!("Failed to resume recovered run {}: {}", run_id, e),
+            }
+        }
+        Ok(resumed)
     }
 
     /// Register a workflow from Node.js
-    pub fn register_workflow(&self, workflow_json: &str) -> CoreResult<()> {
+    pub async fn register_workflow(&self, workflow_json: &str) -> CoreResult<WorkflowCompatibilityReport> {
+        let strict = crate::config::CoreConfig::default().registration.strict_workflows;
+        self.register_workflow_with_mode(workflow_json, strict).await
+    }
+
+    /// Register a workflow, rejecting unknown fields when `strict` is set
+    pub async fn register_workflow_with_mode(&self, workflow_json: &str, strict: bool) -> CoreResult<WorkflowCompatibilityReport> {
         log::info!("Registering workflow from JSON: {}", workflow_json);
         
-        let workflow: WorkflowDefinition = serde_json::from_str(workflow_json)
-            .map_err(|e| CoreError::Serialization(e))?;
+        let (workflow, report) = WorkflowDefinition::from_json(workflow_json, strict)?;
+        if !report.is_clean() {
+            log::warn!("Workflow {} has unknown fields that were ignored: {}", workflow.id, report.ignored_fields.join(", "));
+        }
         
         workflow.validate()
             .map_err(|e| CoreError::InvalidWorkflow(e))?;
@@ -143,14 +283,51 @@ impl Bridge {
         } // Lock released here
         
         // Register triggers without holding the state manager lock
-        let trigger_ids = self.trigger_executor.register_workflow_triggers(&workflow.id, &workflow)?;
+        let trigger_ids = self.trigger_executor.register_workflow_triggers(&workflow.id, &workflow).await?;
         
         log::info!("Successfully registered workflow: {} with {} triggers: {:?}", workflow.id, trigger_ids.len(), trigger_ids);
-        Ok(())
+        Ok(report)
+    }
+
+    /// Register several workflows in one transaction of the local database
+    ///
+    /// `workflows_json` is a JSON array of workflow definitions. Returns the
+    /// compatibility report of each workflow, in order.
+    pub async fn register_workflows(&self, workflows_json: &str) -> CoreResult<String> {
+        let strict = crate::config::CoreConfig::default().registration.strict_workflows;
+        let documents: Vec<serde_json::Value> = serde_json::from_str(workflows_json)?;
+        log::info!("Registering batch of {} workflows", documents.len());
+        
+        let mut workflows = Vec::with_capacity(documents.len());
+        let mut reports = Vec::with_capacity(documents.len());
+        for document in &documents {
+            let (workflow, report) = WorkflowDefinition::from_json(&document.to_string(), strict)?;
+            if !report.is_clean() {
+                log::warn!("Workflow {} has unknown fields that were ignored: {}", workflow.id, report.ignored_fields.join(", "));
+            }
+            workflow.validate()
+                .map_err(CoreError::InvalidWorkflow)?;
+            workflows.push(workflow);
+            reports.push(report);
+        }
+        
+        {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.register_workflows(workflows.clone())?;
+        } // Lock released here
+        
+        // Register triggers without holding the state manager lock
+        for workflow in &workflows {
+            self.trigger_executor.register_workflow_triggers(&workflow.id, workflow).await?;
+        }
+        
+        log::info!("Successfully registered batch of {} workflows", workflows.len());
+        Ok(serde_json::to_string(&reports)?)
     }
 
     /// Register a webhook trigger for a workflow
-    pub fn register_webhook_trigger(&self, workflow_id: &str, trigger_json: &str) -> CoreResult<()> {
+    pub async fn register_webhook_trigger(&self, workflow_id: &str, trigger_json: &str) -> CoreResult<()> {
         log::info!("Registering webhook trigger for workflow: {} with config: {}", workflow_id, trigger_json);
         
         let trigger: crate::triggers::WebhookTrigger = serde_json::from_str(trigger_json)
@@ -158,8 +335,7 @@ impl Bridge {
         
         trigger.validate()?;
         
-        let mut trigger_manager = self.trigger_manager.lock()
-            .map_err(|_| CoreError::Internal("Failed to acquire trigger manager lock".to_string()))?;
+        let mut trigger_manager = self.trigger_manager.lock().await;
         
         trigger_manager.register_webhook_trigger(workflow_id, trigger)?;
         
@@ -168,9 +344,8 @@ impl Bridge {
     }
 
     /// Get all registered webhook triggers
-    pub fn get_webhook_triggers(&self) -> CoreResult<String> {
-        let trigger_manager = self.trigger_manager.lock()
-            .map_err(|_| CoreError::Internal("Failed to acquire trigger manager lock".to_string()))?;
+    pub async fn get_webhook_triggers(&self) -> CoreResult<String> {
+        let trigger_manager = self.trigger_manager.lock().await;
         
         let triggers = trigger_manager.get_webhook_triggers();
         
@@ -191,13 +366,123 @@ impl Bridge {
         let run_id = {
         let mut state_manager = self.state_manager.lock()
             .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
-            state_manager.create_run(workflow_id, payload)?
+            state_manager.create_run_by(workflow_id, payload, crate::models::RunEvent::ACTOR_API)?
         }; // Lock released here
         
         log::info!("Successfully created run: {} for workflow: {}", run_id, workflow_id);
         Ok(run_id.to_string())
     }
 
+    /// Create a workflow run addressable by an external correlation ID
+    pub fn create_correlated_run(&self, workflow_id: &str, payload_json: &str, correlation_id: &str) -> CoreResult<String> {
+        log::info!("Creating run for workflow: {} with correlation ID: {}", workflow_id, correlation_id);
+        
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
+        
+        let run_id = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.create_correlated_run(workflow_id, payload, crate::models::RunEvent::ACTOR_API, Some(correlation_id))?
+        }; // Lock released here
+        
+        log::info!("Successfully created run: {} for workflow: {}", run_id, workflow_id);
+        Ok(run_id.to_string())
+    }
+
+    /// Create several runs in one transaction of the local database
+    ///
+    /// `runs_json` is a JSON array of `{workflow_id, payload, correlation_id}`
+    /// objects. Returns the IDs of the new runs, in order.
+    pub fn create_runs_batch(&self, runs_json: &str) -> CoreResult<String> {
+        let requests: Vec<RunRequest> = serde_json::from_str(runs_json)?;
+        log::info!("Creating batch of {} runs", requests.len());
+        
+        let run_ids = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.create_runs_batch(requests, crate::models::RunEvent::ACTOR_API)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&run_ids)?)
+    }
+
+    /// Get a workflow's run by its correlation ID
+    pub fn get_run_by_correlation_id(&self, workflow_id: &str, correlation_id: &str) -> CoreResult<String> {
+        let run = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_run_by_correlation_id(workflow_id, correlation_id)?
+                .ok_or_else(|| CoreError::RunNotFound(format!(
+                    "No run of workflow {} has correlation ID {}", workflow_id, correlation_id
+                )))?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&run)?)
+    }
+
+    /// Get one registered version of a workflow
+    pub fn get_workflow_version(&self, workflow_id: &str, version: u32) -> CoreResult<String> {
+        let workflow_version = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_workflow_version(workflow_id, version)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(format!("{} version {}", workflow_id, version)))?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&workflow_version)?)
+    }
+
+    /// List the registered versions of a workflow, oldest first
+    pub fn list_workflow_versions(&self, workflow_id: &str) -> CoreResult<String> {
+        let versions = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.list_workflow_versions(workflow_id)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&versions)?)
+    }
+
+    /// Export a workflow as a portable bundle
+    pub fn export_workflow(&self, workflow_id: &str) -> CoreResult<String> {
+        let bundle = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.export_workflow(workflow_id)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&bundle)?)
+    }
+
+    /// Import a workflow bundle, replacing the workflow's triggers with the bundle's
+    pub async fn import_workflow(&self, bundle_json: &str) -> CoreResult<String> {
+        let (bundle, schema_version) = crate::workflow_bundle::WorkflowBundle::parse(bundle_json)?;
+        let workflow = bundle.definition()?;
+        
+        let import = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.import_workflow(&bundle, schema_version)?
+        }; // Lock released here
+        
+        // Register triggers without holding the state manager lock
+        self.trigger_executor.unregister_workflow_triggers(&workflow.id).await?;
+        self.trigger_executor.register_workflow_triggers(&workflow.id, &workflow).await?;
+        
+        Ok(serde_json::to_string(&import)?)
+    }
+
+    /// Get the distribution of a step's recent durations and the timeout it runs with
+    pub fn get_step_duration_stats(&self, workflow_id: &str, step_id: &str) -> CoreResult<String> {
+        let stats = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_step_duration_stats(workflow_id, step_id)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&stats)?)
+    }
+
     /// Get workflow run status
     pub fn get_run_status(&self, run_id: &str) -> CoreResult<String> {
         log::info!("Getting status for run: {}", run_id);
@@ -227,6 +512,370 @@ impl Bridge {
         Ok(result)
     }
 
+    /// List runs matching a query
+    pub fn list_runs(&self, query: &RunQuery) -> CoreResult<String> {
+        log::info!("Listing runs with query: {:?}", query);
+        
+        let page = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.list_runs(query)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&page)?)
+    }
+
+    /// Find a workflow's runs holding a value at a JSON path of their payload or step outputs
+    pub fn find_runs(&self, workflow_id: &str, path: &str, value: &str, limit: Option<usize>) -> CoreResult<String> {
+        log::info!("Finding runs of workflow {} with {} = {}", workflow_id, path, value);
+        
+        let runs = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.find_runs(workflow_id, path, value, limit)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&runs)?)
+    }
+
+    /// Send a signal to a run, resuming the run when a step is waiting for it
+    pub fn send_signal(&self, run_id: &str, signal_name: &str, payload_json: &str) -> CoreResult<String> {
+        log::info!("Sending signal {} to run {}", signal_name, run_id);
+        
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
+        let wait = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.send_signal(&run_uuid, signal_name, payload)?
+        }; // Lock released here
+        
+        if let Some(wait) = &wait {
+            log::info!("Resuming run {} waiting on signal step {}", run_id, wait.step_id);
+            self.execute_workflow_steps(run_id, &wait.workflow_id)?;
+        }
+        
+        Ok(serde_json::to_string(&serde_json::json!({
+            "run_id": run_id,
+            "signal": signal_name,
+            "resumed_step": wait.map(|wait| wait.step_id),
+        }))?)
+    }
+
+    /// Pin a run so it is exempt from retention and archival cleanup
+    pub fn pin_run(&self, run_id: &str, reason: &str, pinned_by: Option<&str>) -> CoreResult<String> {
+        log::info!("Pinning run: {}", run_id);
+        
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let pin = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.pin_run(&run_uuid, reason, pinned_by)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&pin)?)
+    }
+
+    /// Disable a workflow so its triggers are rejected, until an RFC 3339 time if given
+    pub fn disable_workflow(&self, workflow_id: &str, reason: Option<&str>, until: Option<&str>) -> CoreResult<String> {
+        log::info!("Disabling workflow: {}", workflow_id);
+        
+        let until = until
+            .map(|until| chrono::DateTime::parse_from_rfc3339(until).map(|until| until.with_timezone(&chrono::Utc)))
+            .transpose()?;
+        let disabled = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.disable_workflow(workflow_id, reason, until)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&disabled)?)
+    }
+
+    /// Enable a disabled workflow
+    pub fn enable_workflow(&self, workflow_id: &str) -> CoreResult<String> {
+        log::info!("Enabling workflow: {}", workflow_id);
+        
+        let was_disabled = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.enable_workflow(workflow_id)?
+        }; // Lock released here
+        
+        Ok(serde_json::json!({
+            "workflow_id": workflow_id,
+            "was_disabled": was_disabled,
+        }).to_string())
+    }
+
+    /// Unpin a run
+    pub fn unpin_run(&self, run_id: &str, unpinned_by: Option<&str>) -> CoreResult<()> {
+        log::info!("Unpinning run: {}", run_id);
+        
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let removed = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.unpin_run(&run_uuid, unpinned_by)?
+        }; // Lock released here
+        
+        if !removed {
+            return Err(CoreError::Validation(format!("Run is not pinned: {}", run_id)));
+        }
+        Ok(())
+    }
+
+    /// Label a run with a JSON object of labels, returning all its labels
+    ///
+    /// Labels the run already has keep their value unless given again.
+    pub fn set_run_labels(&self, run_id: &str, labels_json: &str) -> CoreResult<String> {
+        log::info!("Labelling run: {}", run_id);
+        
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let labels: RunLabels = serde_json::from_str(labels_json)?;
+        let labels = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.set_run_labels(&run_uuid, &labels)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&labels)?)
+    }
+
+    /// Remove a label of a run
+    pub fn remove_run_label(&self, run_id: &str, key: &str) -> CoreResult<()> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let removed = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.remove_run_label(&run_uuid, key)?
+        }; // Lock released here
+        
+        if !removed {
+            return Err(CoreError::Validation(format!("Run {} has no label {}", run_id, key)));
+        }
+        Ok(())
+    }
+
+    /// Get the labels of a run
+    pub fn get_run_labels(&self, run_id: &str) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let labels = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_run_labels(&run_uuid)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&labels)?)
+    }
+
+    /// List runs with a label set to a value, newest first
+    pub fn list_runs_by_label(&self, key: &str, value: &str, limit: Option<u32>, offset: Option<u32>) -> CoreResult<String> {
+        let page = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.list_runs_by_label(key, value, limit.map(|l| l as usize), offset.map(|o| o as usize))?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&page)?)
+    }
+
+    /// Get the pin audit trail of a run
+    pub fn get_run_pin_audit(&self, run_id: &str) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let audit = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_run_pin_audit(&run_uuid)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&audit)?)
+    }
+
+    /// Explain why each step of a run ran, was skipped, retried or waited
+    pub fn explain_run(&self, run_id: &str) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let explanation = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.explain_run(&run_uuid)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&explanation)?)
+    }
+
+    /// Get the ordered event log of a run
+    pub fn get_run_timeline(&self, run_id: &str) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let events = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_run_timeline(&run_uuid)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&events)?)
+    }
+
+    /// Append a structured log line for a step, as steps do through their context
+    pub fn append_step_log(&self, run_id: &str, step_id: &str, level: &str, message: &str, fields_json: Option<&str>) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let level = crate::models::StepLogLevel::parse(level)
+            .ok_or_else(|| CoreError::Validation(format!("Unknown log level '{}', expected debug, info, warn or error", level)))?;
+        let fields = match fields_json {
+            Some(fields_json) => serde_json::from_str(fields_json)?,
+            None => serde_json::json!({}),
+        };
+        let entry = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.append_step_log(&run_uuid, step_id, level, message, fields)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&entry)?)
+    }
+
+    /// Get the log lines of a step appended after a sequence number
+    pub fn get_step_logs(&self, run_id: &str, step_id: &str, after_seq: i64) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let entries = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_step_logs(&run_uuid, step_id, after_seq)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&entries)?)
+    }
+
+    /// Get the SQLite settings in effect on the engine database, for diagnosing lock contention
+    pub fn get_database_settings(&self) -> CoreResult<String> {
+        let settings = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.database_settings()?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&settings)?)
+    }
+
+    /// Back up the engine database to a new SQLite file while the engine keeps running
+    pub fn create_snapshot(&self, path: &str) -> CoreResult<String> {
+        log::info!("Creating database snapshot at {}", path);
+        
+        let snapshot = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.create_snapshot(path)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&snapshot)?)
+    }
+
+    /// Replace the engine database with a snapshot, leaving the engine paused
+    ///
+    /// Triggers registered in this process are kept as they are; workflows
+    /// whose triggers differ in the snapshot should be registered again.
+    pub fn restore_snapshot(&self, path: &str) -> CoreResult<String> {
+        log::info!("Restoring database snapshot from {}", path);
+        
+        let snapshot = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.restore_snapshot(path)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&snapshot)?)
+    }
+
+    /// Get the schema version of the engine database and the migrations applied to it
+    pub fn get_schema_version(&self) -> CoreResult<String> {
+        let version = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_schema_version()?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&version)?)
+    }
+
+    /// Plan which steps of a run may be re-executed after a crash or during replay
+    pub fn get_run_recovery_plan(&self, run_id: &str, replay: bool) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let policy = crate::config::CoreConfig::default().execution.non_idempotent_recovery;
+        let plan = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.plan_run_recovery(&run_uuid, replay, policy)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&plan)?)
+    }
+
+    /// Replay a run's recorded event log against a fresh state machine
+    pub fn replay_run(&self, run_id: &str) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let (workflow, run, events) = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            let run = state_manager.get_run(&run_uuid)?
+                .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+            let workflow = state_manager.get_run_workflow(&run.workflow_id, &run.id)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+            let events = state_manager.get_run_timeline(&run_uuid)?;
+            (workflow, run, events)
+        }; // Lock released here
+        
+        let events = crate::replay::events_from_run_log(&events);
+        let report = crate::replay::replay(self.state_manager.clone(), &workflow, &run, &events)?;
+        Ok(serde_json::to_string(&report)?)
+    }
+
+    /// Replay a self-contained event log, e.g. one attached to a bug report
+    pub fn replay_events(&self, request_json: &str) -> CoreResult<String> {
+        let request: crate::replay::ReplayRequest = serde_json::from_str(request_json)?;
+        let started_at = request.events.first()
+            .map(|e| e.at)
+            .unwrap_or(request.workflow.created_at);
+        let run = WorkflowRun {
+            id: uuid::Uuid::nil(),
+            workflow_id: request.workflow.id.clone(),
+            status: RunStatus::Running,
+            payload: request.payload,
+            started_at,
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        
+        let report = crate::replay::replay(self.state_manager.clone(), &request.workflow, &run, &request.events)?;
+        Ok(serde_json::to_string(&report)?)
+    }
+
+    /// Walk a workflow for a payload without dispatching any job
+    ///
+    /// Disabled workflows can be dry run, to test them before enabling them again.
+    pub fn dry_run(&self, workflow_id: &str, payload_json: &str) -> CoreResult<String> {
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
+        let workflow = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_workflow(workflow_id)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?
+        }; // Lock released here
+        
+        let report = crate::dry_run::dry_run(&workflow, payload)?;
+        Ok(serde_json::to_string(&report)?)
+    }
+
+    /// Confirm that a non-idempotent step may be re-executed by recovery
+    pub fn confirm_step_recovery(&self, run_id: &str, step_id: &str, confirmed_by: Option<&str>) -> CoreResult<()> {
+        log::info!("Confirming recovery of step {} for run {}", step_id, run_id);
+        
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let state_manager = self.state_manager.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+        state_manager.confirm_step_recovery(&run_uuid, step_id, confirmed_by.unwrap_or(crate::models::RunEvent::ACTOR_API))
+    }
+
     /// Execute a step with context for Bun.js
     pub fn execute_step(&self, run_id: &str, step_id: &str) -> CoreResult<String> {
         log::info!("Executing step {} for run {}", step_id, run_id);
@@ -235,24 +884,29 @@ impl Bridge {
             .map_err(|e| CoreError::UuidParse(e))?;
         
         // Acquire lock, get all needed data, then immediately release
-        let (run, workflow, completed_steps) = {
+        let (run, workflow, completed_steps, execution) = {
         let state_manager = self.state_manager.lock().unwrap();
             
         let run = state_manager.get_run(&run_uuid)?
             .ok_or_else(|| CoreError::RunNotFound(format!("Run not found: {}", run_id)))?;
         
-        let workflow = state_manager.get_workflow(&run.workflow_id)?
+        let workflow = state_manager.get_run_workflow(&run.workflow_id, &run.id)?
             .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
         
-            let completed_steps = state_manager.get_completed_steps(&run_uuid)?;
+            let completed_steps = state_manager.get_completed_steps_with_outputs(&run_uuid)?;
+            let execution = state_manager.execution_info(&run_uuid, step_id, &completed_steps, None)?;
             
-            (run, workflow, completed_steps)
+            (run, workflow, completed_steps, execution)
         }; // Lock released here
         
         // Process step data without holding the lock
         let step = workflow.get_step(step_id)
             .ok_or_else(|| CoreError::Validation(format!("Step '{}' not found in workflow '{}'", step_id, run.workflow_id)))?;
         
+        let attempt = completed_steps.iter()
+            .filter(|result| result.step_id == step_id && result.status.is_failure())
+            .count() as u32;
+        
         let mut context = crate::context::Context::new(
             run_id.to_string(),
             run.workflow_id.clone(),
@@ -265,6 +919,19 @@ impl Bridge {
         if let Some(timeout) = step.timeout {
             context.set_timeout(timeout);
         }
+        // Earlier attempts of the step count as retries so their contexts stop matching
+        context.metadata.retry_count = attempt;
+        context.execution = execution;
+        
+        let context_checksum = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            context.state = state_manager.context_state(run_id, &run.workflow_id)?;
+            context.secrets = state_manager.workflow_secrets(&run.workflow_id)?;
+            context.labels = state_manager.get_run_labels(&run.id)?;
+            context.render_input(step)?;
+            state_manager.record_issued_context(&context)?
+        }; // Lock released here
         
         // Serialize context for Bun.js
         let context_json = context.to_json()?;
@@ -274,6 +941,8 @@ impl Bridge {
             "step_id": step_id,
             "workflow_id": run.workflow_id,
             "context": context_json,
+            "context_checksum": context_checksum,
+            "context_schema_version": context.metadata.schema_version,
             "status": "ready_for_execution",
             "message": "Step context prepared for Bun.js execution"
         });
@@ -313,14 +982,14 @@ impl Bridge {
     }
 
     /// Execute a webhook trigger
-    pub fn execute_webhook_trigger(&self, request_json: &str) -> CoreResult<String> {
+    pub async fn execute_webhook_trigger(&self, request_json: &str) -> CoreResult<String> {
         log::info!("Executing webhook trigger with request: {}", request_json);
         
         let request: crate::triggers::WebhookRequest = serde_json::from_str(request_json)
             .map_err(|e| CoreError::Serialization(e))?;
         
         // Execute the webhook trigger
-        let result = self.trigger_executor.execute_webhook_trigger(request)?;
+        let result = self.trigger_executor.execute_webhook_trigger(request).await?;
         
         let result_json = serde_json::to_string(&result)
             .map_err(|e| CoreError::Serialization(e))?;
@@ -330,14 +999,14 @@ impl Bridge {
     }
 
     /// Execute a manual trigger
-    pub fn execute_manual_trigger(&self, workflow_id: &str, payload_json: &str) -> CoreResult<String> {
+    pub async fn execute_manual_trigger(&self, workflow_id: &str, payload_json: &str) -> CoreResult<String> {
         log::info!("Executing manual trigger for workflow: {} with payload: {}", workflow_id, payload_json);
         
         let payload: serde_json::Value = serde_json::from_str(payload_json)
             .map_err(|e| CoreError::Serialization(e))?;
         
         // Execute the manual trigger
-        let result = self.trigger_executor.execute_manual_trigger(workflow_id, payload)?;
+        let result = self.trigger_executor.execute_manual_trigger(workflow_id, payload).await?;
         
         // Serialize the result
         let result_json = serde_json::to_string(&result)
@@ -347,160 +1016,678 @@ impl Bridge {
         Ok(result_json)
     }
 
-    /// Get trigger statistics
-    pub fn get_trigger_stats(&self) -> CoreResult<String> {
-        log::info!("Getting trigger statistics");
-        
-        let stats = self.trigger_executor.get_trigger_stats()?;
+    /// Execute a manual trigger, labelling the run it starts with a JSON object of labels
+    pub async fn execute_labeled_manual_trigger(&self, workflow_id: &str, payload_json: &str, labels_json: &str) -> CoreResult<String> {
+        log::info!("Executing labeled manual trigger for workflow: {}", workflow_id);
         
-        // Serialize the result
-        let stats_json = serde_json::to_string(&stats)
-            .map_err(|e| CoreError::Serialization(e))?;
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
+        let labels: RunLabels = serde_json::from_str(labels_json)?;
+        let result = self.trigger_executor.execute_labeled_manual_trigger(workflow_id, payload, labels).await?;
         
-        log::info!("Trigger statistics: {}", stats_json);
-        Ok(stats_json)
+        Ok(serde_json::to_string(&result)?)
     }
 
-    /// Get triggers for a workflow
-    pub fn get_workflow_triggers(&self, workflow_id: &str) -> CoreResult<String> {
-        log::info!("Getting triggers for workflow: {}", workflow_id);
+    /// Execute a schedule trigger, applying the workflow's overlap policy
+    pub async fn execute_schedule_trigger(&self, workflow_id: &str, payload_json: &str) -> CoreResult<String> {
+        log::info!("Executing schedule trigger for workflow: {}", workflow_id);
         
-        let triggers = self.trigger_executor.get_workflow_triggers(workflow_id)?;
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
         
-        // Serialize the result
-        let triggers_json = serde_json::to_string(&triggers)
-            .map_err(|e| CoreError::Serialization(e))?;
+        let result = self.trigger_executor.execute_schedule_trigger(workflow_id, payload).await?;
         
-        log::info!("Workflow triggers: {}", triggers_json);
-        Ok(triggers_json)
+        let result_json = serde_json::to_string(&result)?;
+        
+        log::info!("Schedule trigger execution result: {}", result_json);
+        Ok(result_json)
     }
 
-    /// Unregister triggers for a workflow
-    pub fn unregister_workflow_triggers(&self, workflow_id: &str) -> CoreResult<()> {
-        log::info!("Unregistering triggers for workflow: {}", workflow_id);
+    /// Fire the interval and one-time triggers that are due and wake runs whose delay or signal wait has passed
+    ///
+    /// Also reports when the next schedule or wake-up is due, so the caller knows when to call again.
+    pub async fn fire_due_schedules(&self) -> CoreResult<String> {
+        if self.shut_down.load(Ordering::SeqCst) {
+            return Err(CoreError::State("Engine is shut down, schedules no longer fire".to_string()));
+        }
+        let now = chrono::Utc::now();
+        let results = self.trigger_executor.fire_due_schedules(now).await?;
+        let backfills = self.trigger_executor.advance_backfills().await?;
+        let woken_runs = self.wake_delayed_runs(now)?;
+        let recovered_runs = self.resume_recovered_runs()?;
         
-        // Unregister workflow triggers
-        self.trigger_executor.unregister_workflow_triggers(workflow_id)?;
+        let next_schedule_at = self.trigger_executor.next_schedule_at().await?;
+        let next_wake_at = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_next_delay_wake_at()?.into_iter().chain(state_manager.get_next_signal_timeout_at()?).min()
+        }; // Lock released here
+        // Running backfills create their next runs as earlier ones finish
+        let next_backfill_at = self.trigger_executor.has_running_backfills()?
+            .then(|| now + chrono::Duration::seconds(crate::backfill::POLL_INTERVAL_SECS));
+        let next_fire_at = next_schedule_at.into_iter().chain(next_wake_at).chain(next_backfill_at).min();
         
-        log::info!("Successfully unregistered triggers for workflow: {}", workflow_id);
-        Ok(())
+        Ok(serde_json::to_string(&serde_json::json!({
+            "results": results,
+            "backfills": backfills,
+            "woken_runs": woken_runs,
+            "recovered_runs": recovered_runs,
+            "next_fire_at": next_fire_at,
+        }))?)
     }
 
-    /// Start the webhook server with proper async support
-    pub async fn start_webhook_server_async(&mut self) -> CoreResult<()> {
-        log::info!("Starting webhook server with async support...");
+    /// Start a backfill creating runs of a workflow for its firings within an RFC 3339 time range
+    ///
+    /// Firings are those of the workflow's schedule, or `interval_secs` apart
+    /// when given. The backfill continues each time due schedules are fired.
+    pub async fn backfill(&self, workflow_id: &str, from: &str, to: &str, interval_secs: Option<u64>, max_concurrent: Option<u32>) -> CoreResult<String> {
+        if self.shut_down.load(Ordering::SeqCst) {
+            return Err(CoreError::State("Engine is shut down, backfills no longer run".to_string()));
+        }
+        log::info!("Backfilling workflow {} from {} to {}", workflow_id, from, to);
         
-        let config = crate::webhook_server::WebhookServerConfig::default();
-        let mut webhook_server = crate::webhook_server::WebhookServer::new(
-            config,
-            self.trigger_manager.clone(),
-            self.state_manager.clone(),
-        );
+        let from = chrono::DateTime::parse_from_rfc3339(from)?.with_timezone(&chrono::Utc);
+        let to = chrono::DateTime::parse_from_rfc3339(to)?.with_timezone(&chrono::Utc);
+        let max_concurrent = max_concurrent.map(|max| max as usize).unwrap_or(crate::backfill::DEFAULT_MAX_CONCURRENT);
+        let progress = self.trigger_executor.start_backfill(workflow_id, from, to, interval_secs, max_concurrent).await?;
         
-        webhook_server.start().await?;
-        log::info!("Webhook server started successfully");
-        Ok(())
+        Ok(serde_json::to_string(&progress)?)
     }
-    
-    /// Start the webhook server (legacy sync method)
-    pub fn start_webhook_server(&self) -> CoreResult<()> {
-        log::info!("Starting webhook server (legacy mode)...");
-        log::info!("Note: Use start_webhook_server_async() for full async support");
+
+    /// Get the progress of a backfill
+    pub fn get_backfill(&self, backfill_id: &str) -> CoreResult<String> {
+        let progress = self.trigger_executor.get_backfill(backfill_id)?;
+        Ok(serde_json::to_string(&progress)?)
+    }
+
+    /// Stop a backfill from creating more runs
+    pub fn cancel_backfill(&self, backfill_id: &str) -> CoreResult<String> {
+        let progress = self.trigger_executor.cancel_backfill(backfill_id)?;
+        Ok(serde_json::to_string(&progress)?)
+    }
+
+    /// Continue the runs whose delay step has passed its wake-up or whose signal wait timed out, returning their IDs
+    ///
+    /// Delays and waits of runs that finished in the meantime, e.g. by being cancelled, are dropped.
+    fn wake_delayed_runs(&self, now: chrono::DateTime<chrono::Utc>) -> CoreResult<Vec<String>> {
+        let due: Vec<(String, String, String)> = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            let delays = state_manager.get_due_step_delays(now)?.into_iter()
+                .map(|delay| (delay.run_id, delay.workflow_id, delay.step_id));
+            let waits = state_manager.get_timed_out_signal_waits(now)?.into_iter()
+                .map(|wait| (wait.run_id, wait.workflow_id, wait.step_id));
+            delays.chain(waits).collect()
+        }; // Lock released here
+        
+        let mut woken_runs = Vec::with_capacity(due.len());
+        for (run_id, workflow_id, step_id) in due {
+            let run_uuid = uuid::Uuid::parse_str(&run_id)?;
+            let finished = {
+                let state_manager = self.state_manager.lock()
+                    .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+                let finished = state_manager.get_run(&run_uuid)?.is_none_or(|run| run.status.is_terminal());
+                if finished {
+                    state_manager.clear_step_delay(&run_uuid, &step_id)?;
+                    state_manager.clear_signal_wait(&run_uuid, &step_id)?;
+                }
+                finished
+            }; // Lock released here
+            if finished {
+                log::info!("Dropped wait of step {} of finished run {}", step_id, run_id);
+                continue;
+            }
+            
+            log::info!("Waking run {} waiting on step {}", run_id, step_id);
+            match self.execute_workflow_steps(&run_id, &workflow_id) {
+                Ok(_) => woken_runs.push(run_id),
+                Err(e) => log::error!("Failed to wake run {}: {}", run_id, e),
+            }
+        }
+        Ok(woken_runs)
+    }
+
+    /// Register the JSON Schema of an event version
+    pub fn register_event_schema(&self, event_name: &str, version: u32, schema_json: &str) -> CoreResult<String> {
+        log::info!("Registering schema version {} of event: {}", version, event_name);
+        
+        let schema: serde_json::Value = serde_json::from_str(schema_json)?;
+        let event_schema = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.register_event_schema(event_name, version, schema)?
+        }; // Lock released here
+        Ok(serde_json::to_string(&event_schema)?)
+    }
+
+    /// Get the registered schemas of an event
+    pub fn get_event_schemas(&self, event_name: &str) -> CoreResult<String> {
+        let schemas = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_event_schemas(event_name)?
+        }; // Lock released here
+        Ok(serde_json::to_string(&schemas)?)
+    }
+
+    /// Check that every stored record can be read by this engine, returning the compatibility report
+    pub fn verify_store_compatibility(&self) -> CoreResult<String> {
+        let report = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.verify_store_compatibility()?
+        }; // Lock released here
+        Ok(serde_json::to_string(&report)?)
+    }
+
+    /// Publish an event to its subscribed workflows, checked against its schema
+    pub async fn publish_event(&self, event_name: &str, version: Option<u32>, payload_json: &str) -> CoreResult<String> {
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
+        let publication = self.trigger_executor.publish_event(event_name, version, payload).await?;
+        Ok(serde_json::to_string(&publication)?)
+    }
+
+    /// Pause the engine, buffering trigger-created runs until it resumes
+    pub async fn pause_engine(&self) -> CoreResult<()> {
+        log::info!("Pausing engine");
+        self.trigger_executor.pause_engine().await
+    }
+
+    /// Resume the engine and release buffered runs
+    pub async fn resume_engine(&self) -> CoreResult<String> {
+        log::info!("Resuming engine");
+        
+        let released = self.trigger_executor.resume_engine().await?;
+        Ok(serde_json::to_string(&serde_json::json!({ "released_runs": released }))?)
+    }
+
+    /// Get the engine pause status
+    pub async fn get_engine_status(&self) -> CoreResult<String> {
+        let status = self.trigger_executor.get_engine_status().await?;
+        Ok(serde_json::to_string(&status)?)
+    }
+
+    /// Get trigger statistics
+    pub async fn get_trigger_stats(&self) -> CoreResult<String> {
+        log::info!("Getting trigger statistics");
+        
+        let stats = self.trigger_executor.get_trigger_stats().await?;
+        
+        // Serialize the result
+        let stats_json = serde_json::to_string(&stats)
+            .map_err(|e| CoreError::Serialization(e))?;
+        
+        log::info!("Trigger statistics: {}", stats_json);
+        Ok(stats_json)
+    }
+
+    /// Get the trigger firings of a workflow suppressed as duplicates, newest first
+    pub fn get_suppressed_triggers(&self, workflow_id: &str, limit: Option<u32>) -> CoreResult<String> {
+        log::info!("Getting suppressed triggers for workflow: {}", workflow_id);
+        
+        let suppressed = self.state_manager.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?
+            .get_suppressed_triggers(workflow_id, limit.unwrap_or(100) as usize)?;
+        
+        Ok(serde_json::to_string(&suppressed)?)
+    }
+
+    /// Get triggers for a workflow
+    pub async fn get_workflow_triggers(&self, workflow_id: &str) -> CoreResult<String> {
+        log::info!("Getting triggers for workflow: {}", workflow_id);
+        
+        let triggers = self.trigger_executor.get_workflow_triggers(workflow_id).await?;
+        
+        // Serialize the result
+        let triggers_json = serde_json::to_string(&triggers)
+            .map_err(|e| CoreError::Serialization(e))?;
+        
+        log::info!("Workflow triggers: {}", triggers_json);
+        Ok(triggers_json)
+    }
+
+    /// Unregister triggers for a workflow
+    pub async fn unregister_workflow_triggers(&self, workflow_id: &str) -> CoreResult<()> {
+        log::info!("Unregistering triggers for workflow: {}", workflow_id);
+        
+        // Unregister workflow triggers
+        self.trigger_executor.unregister_workflow_triggers(workflow_id).await?;
+        
+        log::info!("Successfully unregistered triggers for workflow: {}", workflow_id);
+        Ok(())
+    }
+
+    /// Start the webhook server with proper async support
+    pub async fn start_webhook_server_async(&self) -> CoreResult<()> {
+        log::info!("Starting webhook server with async support...");
+        
+        let config = crate::webhook_server::WebhookServerConfig::default();
+        let mut webhook_server = crate::webhook_server::WebhookServer::new(
+            config,
+            self.trigger_manager.clone(),
+            self.state_manager.clone(),
+        )
+        .with_rate_limiter(self.trigger_executor.rate_limiter())
+        .with_trigger_metrics(self.trigger_executor.trigger_metrics());
+        
+        webhook_server.start().await?;
+        *self.webhook_server.lock().await = Some(webhook_server);
+        log::info!("Webhook server started successfully");
+        Ok(())
+    }
+    
+    /// Start the webhook server (legacy sync method)
+    pub fn start_webhook_server(&self) -> CoreResult<()> {
+        log::info!("Starting webhook server (legacy mode)...");
+        log::info!("Note: Use start_webhook_server_async() for full async support");
         log::info!("Webhook server configuration ready");
         Ok(())
     }
     
     /// Stop the webhook server
-    pub fn stop_webhook_server(&self) -> CoreResult<()> {
+    pub async fn stop_webhook_server(&self) -> CoreResult<()> {
         log::info!("Stopping webhook server");
-        // Note: For async server, use the WebhookServer instance directly
+        self.stop_webhook_server_async().await?;
         Ok(())
     }
 
-    /// Get job status (sync wrapper around async method)
-    pub fn get_job_status(&self, job_id: &str) -> CoreResult<Option<String>> {
-        log::info!("Getting job status for: {}", job_id);
+    /// Stop the webhook server started with `start_webhook_server_async`, returning whether one was running
+    async fn stop_webhook_server_async(&self) -> CoreResult<bool> {
+        match self.webhook_server.lock().await.take() {
+            Some(mut webhook_server) => {
+                webhook_server.stop().await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Shut the engine down gracefully
+    ///
+    /// New triggers are rejected and schedules stop firing, the webhook server is
+    /// stopped, and running jobs get up to `timeout_ms` to finish before the
+    /// dispatcher stops. Returns a summary of drained and aborted jobs.
+    pub async fn shutdown(&self, timeout_ms: u64) -> CoreResult<String> {
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            return Err(CoreError::State("Engine is already shut down".to_string()));
+        }
+        log::info!("Shutting down engine, draining jobs for up to {}ms", timeout_ms);
         
-        // Use tokio runtime to block on async call
-        let rt = tokio::runtime::Handle::try_current()
-            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+        self.trigger_executor.stop_accepting_triggers();
+        let webhook_server_stopped = self.stop_webhook_server_async().await?;
+        let jobs = self.job_dispatcher.lock().await.shutdown(std::time::Duration::from_millis(timeout_ms)).await?;
+        crate::telemetry::shutdown();
+        
+        Ok(serde_json::to_string(&serde_json::json!({
+            "jobs": jobs,
+            "webhook_server_stopped": webhook_server_stopped,
+        }))?)
+    }
+
+    /// Get job status
+    pub async fn get_job_status(&self, job_id: &str) -> CoreResult<Option<String>> {
+        log::info!("Getting job status for: {}", job_id);
         
-        rt.block_on(async {
-            let dispatcher_arc = self.job_dispatcher.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire dispatcher lock: {}", e)))?;
-            let dispatcher = dispatcher_arc.lock().await;
+        let dispatcher = self.job_dispatcher.lock().await;
         
-            match dispatcher.get_job_status(job_id).await? {
+        match dispatcher.get_job_status(job_id).await? {
             Some(state) => Ok(Some(format!("{:?}", state))),
             None => Ok(None),
         }
-        })
     }
 
-    /// Cancel a job (sync wrapper around async method)
-    pub fn cancel_job(&self, job_id: &str) -> CoreResult<bool> {
+    /// Cancel a job
+    pub async fn cancel_job(&self, job_id: &str) -> CoreResult<bool> {
         log::info!("Cancelling job: {}", job_id);
         
-        // Use tokio runtime to block on async call
-        let rt = tokio::runtime::Handle::try_current()
-            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+        self.job_dispatcher.lock().await.cancel_job(job_id).await
+    }
+
+    /// Cancel a run, including its in-flight jobs
+    ///
+    /// Queued jobs are removed and running jobs are signalled to abort; results
+    /// of steps that already completed are kept as the run's partial results.
+    /// Child runs are cancelled with the run, and a cancelled child run fails
+    /// the sub-workflow step of its parent.
+    pub async fn cancel_run(&self, run_id: &str, reason: Option<&str>) -> CoreResult<String> {
+        log::info!("Cancelling run: {}", run_id);
+        
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let (workflow_id, cancelled, completed_steps) = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            let run = state_manager.get_run(&run_uuid)?
+                .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+            let cancelled = state_manager.cancel_run(&run_uuid, reason.unwrap_or("Cancelled via API"))?;
+            let completed_steps = state_manager.get_completed_steps(&run_uuid)?;
+            (run.workflow_id, cancelled, completed_steps)
+        }; // Lock released here
         
-        rt.block_on(async {
-            let dispatcher_arc = self.job_dispatcher.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire dispatcher lock: {}", e)))?;
-            let dispatcher = dispatcher_arc.lock().await;
+        let removed_jobs = self.job_dispatcher.lock().await.cancel_run(&workflow_id, run_id).await;
         
-            dispatcher.cancel_job(job_id).await
-        })
+        if cancelled {
+            self.wake_parent_run(&run_uuid);
+        }
+        
+        let partial_results: Vec<_> = completed_steps.iter()
+            .filter(|result| result.status == crate::models::StepStatus::Completed)
+            .map(|result| result.step_id.clone())
+            .collect();
+        
+        let result = serde_json::json!({
+            "run_id": run_id,
+            "cancelled": cancelled,
+            "removed_jobs": removed_jobs,
+            "completed_steps": partial_results,
+        });
+        Ok(serde_json::to_string(&result)?)
+    }
+
+    /// Cancel a workflow's unfinished runs in bulk, including their in-flight jobs
+    ///
+    /// `status` limits the cancellation to pending or running runs, and `before`
+    /// to runs started before an RFC 3339 time. The runs are cancelled in one
+    /// transaction before their jobs are removed.
+    pub async fn cancel_runs(&self, workflow_id: &str, status: Option<&str>, before: Option<&str>, reason: Option<&str>) -> CoreResult<String> {
+        log::info!("Cancelling runs of workflow {} with status {:?} started before {:?}", workflow_id, status, before);
+        
+        let status = status
+            .map(|s| RunStatus::parse(s).ok_or_else(|| CoreError::Validation(format!("Unknown run status: {}", s))))
+            .transpose()?;
+        let before = before
+            .map(|s| chrono::DateTime::parse_from_rfc3339(s).map(|dt| dt.with_timezone(&chrono::Utc)))
+            .transpose()?;
+        let summary = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.cancel_runs(workflow_id, status, before, reason.unwrap_or("Cancelled in bulk via API"))?
+        }; // Lock released here
+        
+        let mut removed_jobs = 0;
+        for run_id in &summary.run_ids {
+            removed_jobs += self.job_dispatcher.lock().await.cancel_run(workflow_id, run_id).await;
+            self.wake_parent_run(&uuid::Uuid::parse_str(run_id)?);
+        }
+        
+        let mut result = serde_json::to_value(&summary)?;
+        result["removed_jobs"] = serde_json::json!(removed_jobs);
+        Ok(serde_json::to_string(&result)?)
+    }
+
+    /// Retry a workflow's failed runs in bulk, re-running their failed steps
+    ///
+    /// `since` limits the retry to runs started at or after an RFC 3339 time.
+    /// The runs are reopened in one transaction, then executed one by one;
+    /// runs whose execution could not start are listed as not resumed.
+    pub fn retry_failed_runs(&self, workflow_id: &str, since: Option<&str>) -> CoreResult<String> {
+        log::info!("Retrying failed runs of workflow {} started since {:?}", workflow_id, since);
+        
+        let since = since
+            .map(|s| chrono::DateTime::parse_from_rfc3339(s).map(|dt| dt.with_timezone(&chrono::Utc)))
+            .transpose()?;
+        let summary = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.retry_failed_runs(workflow_id, since)?
+        }; // Lock released here
+        
+        let mut not_resumed = Vec::new();
+        for run_id in &summary.run_ids {
+            if let Err(e) = self.execute_workflow_steps(run_id, workflow_id) {
+                log::error!("Failed to resume retried run {}: {}", run_id, e);
+                not_resumed.push(run_id.clone());
+            }
+        }
+        
+        let mut result = serde_json::to_value(&summary)?;
+        result["not_resumed"] = serde_json::json!(not_resumed);
+        Ok(serde_json::to_string(&result)?)
+    }
+
+    /// Continue the parent run waiting on a child run, so its sub-workflow step sees the child finished
+    ///
+    /// Errors are logged rather than returned; the child itself already finished.
+    fn wake_parent_run(&self, run_id: &uuid::Uuid) {
+        let parent = {
+            let Ok(state_manager) = self.state_manager.lock() else {
+                log::error!("Failed to acquire state manager lock to wake the parent of run {}", run_id);
+                return;
+            };
+            state_manager.get_run_parent(run_id)
+                .and_then(|parent| match parent {
+                    Some(parent) => state_manager.get_run(&uuid::Uuid::parse_str(&parent.parent_run_id)?),
+                    None => Ok(None),
+                })
+        }; // Lock released here
+        
+        match parent {
+            Ok(Some(parent_run)) if !parent_run.status.is_terminal() => {
+                if let Err(e) = self.execute_workflow_steps(&parent_run.id.to_string(), &parent_run.workflow_id) {
+                    log::error!("Failed to wake parent run {} of run {}: {}", parent_run.id, run_id, e);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Failed to look up the parent of run {}: {}", run_id, e),
+        }
     }
 
-    /// Get dispatcher statistics (sync wrapper around async method)
-    pub fn get_dispatcher_stats(&self) -> CoreResult<crate::dispatcher::DispatcherStats> {
+    /// Get dispatcher statistics
+    pub async fn get_dispatcher_stats(&self) -> CoreResult<crate::dispatcher::DispatcherStats> {
         log::info!("Getting dispatcher statistics");
         
-        // Use tokio runtime to block on async call
-        let rt = tokio::runtime::Handle::try_current()
-            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+        self.job_dispatcher.lock().await.get_stats().await
+    }
+
+    /// List the queued and running jobs, optionally of one state and workflow
+    pub async fn list_jobs(&self, state: Option<&str>, workflow_id: Option<&str>, limit: Option<u32>) -> CoreResult<String> {
+        log::info!("Listing jobs (state: {:?}, workflow: {:?})", state, workflow_id);
         
-        rt.block_on(async {
-            let dispatcher_arc = self.job_dispatcher.lock()
-                .map_err(|e| CoreError::Internal(format!("Failed to acquire dispatcher lock: {}", e)))?;
-            let dispatcher = dispatcher_arc.lock().await;
-            
-            dispatcher.get_stats().await
-        })
+        let state = state.map(|state| JobState::parse(state)
+            .ok_or_else(|| CoreError::Validation(format!("Unknown job state: {}", state))))
+            .transpose()?;
+        let limit = limit.map_or(Dispatcher::DEFAULT_JOB_LIST_LIMIT, |limit| limit as usize);
+        let jobs = self.job_dispatcher.lock().await.list_jobs(state.as_ref(), workflow_id, limit).await;
+        Ok(serde_json::to_string(&jobs)?)
     }
 
-    /// Get workflow run status (sync wrapper around async method)
-    pub fn get_workflow_run_status(&self, run_id: &str) -> CoreResult<Option<crate::models::RunStatus>> {
-        log::info!("Getting workflow run status for: {}", run_id);
+    /// Get a queued or running job
+    pub async fn get_job(&self, job_id: &str) -> CoreResult<String> {
+        log::info!("Getting job: {}", job_id);
+        
+        let job = self.job_dispatcher.lock().await.get_job(job_id).await.ok_or_else(|| CoreError::JobNotFound(job_id.to_string()))?;
+        Ok(serde_json::to_string(&job)?)
+    }
+
+    /// Record a heartbeat of a running step
+    pub async fn report_step_heartbeat(&self, run_id: &str, step_id: &str) -> CoreResult<()> {
+        log::debug!("Heartbeat from step {} of run {}", step_id, run_id);
         
-        let rt = tokio::runtime::Handle::try_current()
-            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+        self.job_dispatcher.lock().await.report_step_heartbeat(run_id, step_id).await
+    }
+
+    /// Get the diagnostics of a dispatcher worker
+    pub async fn get_worker_details(&self, worker_id: &str) -> CoreResult<String> {
+        log::info!("Getting diagnostics of worker: {}", worker_id);
         
-        rt.block_on(async {
-            let dispatcher_arc = self.job_dispatcher.lock()
-                .map_err(|e| CoreError::Internal(format!("Failed to acquire dispatcher lock: {}", e)))?;
-            let dispatcher = dispatcher_arc.lock().await;
-            
-            dispatcher.get_workflow_run_status(run_id).await
-        })
+        let diagnostics = self.job_dispatcher.lock().await.get_worker_details(worker_id).await?.ok_or_else(|| CoreError::WorkerNotFound(worker_id.to_string()))?;
+        Ok(serde_json::to_string(&diagnostics)?)
+    }
+
+    /// List the diagnostics of every dispatcher worker
+    pub async fn list_workers(&self) -> CoreResult<String> {
+        log::info!("Listing dispatcher workers");
+        
+        let workers = self.job_dispatcher.lock().await.list_workers().await?;
+        Ok(serde_json::to_string(&workers)?)
+    }
+
+    /// Set the faults injected into jobs, returning the failure injection status
+    ///
+    /// Only allowed when the engine runs with `CRONFLOW_CHAOS` enabled.
+    pub async fn set_chaos_rules(&self, rules_json: &str) -> CoreResult<String> {
+        let rules: crate::chaos::ChaosRules = serde_json::from_str(rules_json)?;
+        let dispatcher = self.job_dispatcher.lock().await;
+        dispatcher.set_chaos_rules(rules).await?;
+        let status = dispatcher.get_chaos_status().await;
+        Ok(serde_json::to_string(&status)?)
+    }
+
+    /// Stop injecting faults, returning the faults injected until now
+    pub async fn clear_chaos_rules(&self) -> CoreResult<String> {
+        let dispatcher = self.job_dispatcher.lock().await;
+        let status = dispatcher.get_chaos_status().await;
+        dispatcher.clear_chaos_rules().await;
+        Ok(serde_json::to_string(&status)?)
+    }
+
+    /// Get the failure injection rules and the faults injected so far
+    pub async fn get_chaos_status(&self) -> CoreResult<String> {
+        let status = self.job_dispatcher.lock().await.get_chaos_status().await;
+        Ok(serde_json::to_string(&status)?)
+    }
+
+    /// Get a workflow's resource usage within its quota window
+    pub async fn get_workflow_resource_usage(&self, workflow_id: &str) -> CoreResult<String> {
+        let usage = self.job_dispatcher.lock().await.get_workflow_resource_usage(workflow_id).await;
+        Ok(serde_json::to_string(&usage)?)
+    }
+
+    /// Get the worker allocation and load of the dispatcher lanes
+    pub async fn get_lane_status(&self) -> CoreResult<String> {
+        let lanes = self.job_dispatcher.lock().await.get_lane_status().await;
+        Ok(serde_json::to_string(&lanes)?)
+    }
+
+    /// Register the capability tags of a worker, replacing those it had
+    pub async fn register_worker_tags(&self, worker_id: &str, tags: Vec<String>) -> CoreResult<String> {
+        self.job_dispatcher.lock().await.set_worker_tags(worker_id, tags.clone()).await?;
+        Ok(serde_json::json!({"worker_id": worker_id, "tags": tags}).to_string())
+    }
+
+    /// Get the workers and queue time of each worker capability tag
+    pub async fn get_worker_tag_status(&self) -> CoreResult<String> {
+        let tags = self.job_dispatcher.lock().await.get_worker_tag_status().await;
+        Ok(serde_json::to_string(&tags)?)
+    }
+
+    /// Get the engine feature flags and whether each is enabled, for a workflow when given
+    pub fn get_feature_flags(&self, workflow_id: Option<&str>) -> CoreResult<String> {
+        let flags = crate::config::CoreConfig::default().feature_flags;
+        let states: Vec<serde_json::Value> = crate::config::FeatureFlags::KNOWN.iter()
+            .map(|flag| {
+                let workflows = match flags.setting(flag.name) {
+                    Some(crate::config::FlagSetting::Workflows(workflows)) => Some(workflows.clone()),
+                    _ => None,
+                };
+                serde_json::json!({
+                    "name": flag.name,
+                    "description": flag.description,
+                    "default": flag.default,
+                    "overridden": flags.setting(flag.name).is_some(),
+                    "workflows": workflows,
+                    "enabled": flags.is_enabled(flag.name, workflow_id),
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string(&states)?)
+    }
+
+    /// Get workflow run status
+    pub async fn get_workflow_run_status(&self, run_id: &str) -> CoreResult<Option<crate::models::RunStatus>> {
+        log::info!("Getting workflow run status for: {}", run_id);
+        
+        self.job_dispatcher.lock().await.get_workflow_run_status(run_id).await
     }
 
-    /// Get completed steps for a workflow run (sync wrapper around async method)
-    pub fn get_workflow_completed_steps(&self, run_id: &str) -> CoreResult<Vec<crate::models::StepResult>> {
+    /// Get completed steps for a workflow run
+    pub async fn get_workflow_completed_steps(&self, run_id: &str) -> CoreResult<Vec<crate::models::StepResult>> {
         log::info!("Getting completed steps for workflow run: {}", run_id);
         
-        let rt = tokio::runtime::Handle::try_current()
-            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+        self.job_dispatcher.lock().await.get_workflow_completed_steps(run_id).await
+    }
+
+    /// Get a value from the context state of a run or of its workflow
+    pub fn state_get(&self, run_id: &str, scope: &str, key: &str) -> CoreResult<String> {
+        let state_manager = self.state_manager.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+        let (scope, scope_id) = Self::resolve_state_scope(&state_manager, run_id, scope)?;
+        let value = state_manager.state_get(scope, &scope_id, key)?;
+        Ok(serde_json::to_string(&value)?)
+    }
+
+    /// Set a value in the context state of a run or of its workflow
+    pub fn state_set(&self, run_id: &str, scope: &str, key: &str, value_json: &str, ttl_secs: Option<u64>) -> CoreResult<()> {
+        let value: serde_json::Value = serde_json::from_str(value_json)?;
+        let state_manager = self.state_manager.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+        let (scope, scope_id) = Self::resolve_state_scope(&state_manager, run_id, scope)?;
+        state_manager.state_set(scope, &scope_id, key, value, ttl_secs)
+    }
+
+    /// Increment an integer in the context state of a run or of its workflow
+    pub fn state_incr(&self, run_id: &str, scope: &str, key: &str, by: i64, ttl_secs: Option<u64>) -> CoreResult<String> {
+        let state_manager = self.state_manager.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+        let (scope, scope_id) = Self::resolve_state_scope(&state_manager, run_id, scope)?;
+        let value = state_manager.state_incr(scope, &scope_id, key, by, ttl_secs)?;
+        Ok(value.to_string())
+    }
+
+    /// Store a secret for one workflow, or globally when no scope is given
+    pub fn set_secret(&self, key: &str, value: &str, scope: Option<&str>) -> CoreResult<String> {
+        let secret = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.set_secret(key, value, scope)?
+        }; // Lock released here
+        Ok(serde_json::json!({
+            "key": secret.key,
+            "scope": secret.scope,
+            "updated_at": secret.updated_at,
+        }).to_string())
+    }
+
+    /// Delete a secret of one workflow, or a global one when no scope is given
+    pub fn delete_secret(&self, key: &str, scope: Option<&str>) -> CoreResult<String> {
+        let deleted = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.delete_secret(key, scope)?
+        }; // Lock released here
+        Ok(serde_json::json!({ "deleted": deleted }).to_string())
+    }
+
+    /// Resolve the scope name and run of a state call to the ID the state is stored under
+    fn resolve_state_scope(state_manager: &StateManager, run_id: &str, scope: &str) -> CoreResult<(crate::models::StateScope, String)> {
+        let scope = crate::models::StateScope::parse(scope)
+            .ok_or_else(|| CoreError::Validation(format!("Unknown state scope '{}', expected 'run' or 'workflow'", scope)))?;
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        let run = state_manager.get_run(&run_uuid)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let scope_id = match scope {
+            crate::models::StateScope::Run => run_id.to_string(),
+            crate::models::StateScope::Workflow => run.workflow_id,
+        };
+        Ok((scope, scope_id))
+    }
+
+    /// Execute a single step of a workflow outside any run
+    pub fn execute_adhoc_step(&self, workflow_id: &str, step_id: &str, payload_json: &str) -> CoreResult<String> {
+        log::info!("Executing step {} of workflow {} ad hoc", step_id, workflow_id);
         
-        rt.block_on(async {
-            let dispatcher_arc = self.job_dispatcher.lock()
-                .map_err(|e| CoreError::Internal(format!("Failed to acquire dispatcher lock: {}", e)))?;
-            let dispatcher = dispatcher_arc.lock().await;
-            
-            dispatcher.get_workflow_completed_steps(run_id).await
-        })
+        let payload: serde_json::Value = serde_json::from_str(payload_json)?;
+        let ttl_secs = crate::config::CoreConfig::default().execution.adhoc_result_ttl_secs;
+        let step_orchestrator = crate::step_orchestrator::StepOrchestrator::new(self.state_manager.clone());
+        let execution = step_orchestrator.execute_adhoc_step(workflow_id, step_id, payload, chrono::Duration::seconds(ttl_secs as i64))?;
+        Ok(serde_json::to_string(&execution)?)
+    }
+
+    /// Get the unexpired ad hoc step executions of a workflow
+    pub fn get_adhoc_step_executions(&self, workflow_id: &str) -> CoreResult<String> {
+        let executions = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.get_adhoc_step_executions(workflow_id)?
+        }; // Lock released here
+        Ok(serde_json::to_string(&executions)?)
     }
 
     /// Execute workflow steps using step orchestrator and state machine
@@ -510,7 +1697,8 @@ impl Bridge {
         let run_uuid = Uuid::parse_str(run_id)
             .map_err(|e| CoreError::Validation(format!("Invalid run ID: {}", e)))?;
         
-        let step_orchestrator = crate::step_orchestrator::StepOrchestrator::new(self.state_manager.clone());
+        let step_orchestrator = crate::step_orchestrator::StepOrchestrator::new(self.state_manager.clone())
+            .with_dispatcher(Arc::clone(&self.job_dispatcher));
         
         // Start step execution using the orchestrator
         match step_orchestrator.start_step_execution(&run_uuid, workflow_id) {
@@ -530,79 +1718,348 @@ impl Bridge {
         }
     }
 
-    /// Execute workflow hook (onSuccess or onFailure)
+    /// Execute workflow hook (onSuccess, onFailure or onCancel)
     pub fn execute_workflow_hook(&self, hook_type: &str, context_json: &str, workflow_id: &str) -> CoreResult<String> {
         log::info!("Executing {} hook for workflow: {}", hook_type, workflow_id);
         
-        if hook_type != "onSuccess" && hook_type != "onFailure" {
+        if !["onSuccess", "onFailure", "onCancel"].contains(&hook_type) {
             return Err(CoreError::Validation(format!("Invalid hook type: {}", hook_type)));
         }
         
+        let context: serde_json::Value = serde_json::from_str(context_json).unwrap_or(serde_json::Value::Null);
+        let run_id = context.get("run_id")
+            .or_else(|| context.get("run").and_then(|run| run.get("id")))
+            .and_then(|id| id.as_str())
+            .and_then(|id| uuid::Uuid::parse_str(id).ok());
+        if let Some(run_id) = run_id {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            let detail = serde_json::json!({"hook_type": hook_type, "workflow_id": workflow_id});
+            if let Err(e) = state_manager.record_run_event(&run_id, crate::models::RunEvent::HOOK_FIRED, None, crate::models::RunEvent::ACTOR_API, detail) {
+                log::warn!("Failed to record {} hook event for run {}: {}", hook_type, run_id, e);
+            }
+        }
+        
         // In the next phase, this will call the Bun.js hook execution
         let result = serde_json::json!({
             "success": true,
             "hook_type": hook_type,
             "workflow_id": workflow_id,
             "message": format!("{} hook executed successfully", hook_type),
-            "context": serde_json::from_str::<serde_json::Value>(context_json).unwrap_or(serde_json::Value::Null)
+            "context": context
         });
         
         Ok(result.to_string())
     }
-}
-
-// ============================================================================
-// ASYNC BRIDGE IMPLEMENTATION (Task 2.1.3)
-// ============================================================================
 
-impl AsyncBridge {
-    /// Create a new async N-API bridge
-    pub fn new(db_path: &str) -> CoreResult<Self> {
-        let state_manager = Arc::new(AsyncStateManager::new(db_path)?);
-        let trigger_manager = Arc::new(TokioMutex::new(TriggerManager::new()));
-        
-        // Dispatcher now uses Tokio async tasks
-        let dispatcher_config = crate::dispatcher::WorkerPoolConfig::default();
-        let async_state_manager = Arc::new(TokioMutex::new(StateManager::new(db_path)?));
-        let job_dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(dispatcher_config, async_state_manager.clone())));
+    /// Deliver the onSuccess/onFailure/onCancel hooks of finished runs to a handler
+    ///
+    /// The first handler starts a task firing the hook of every run that
+    /// finishes and retrying failed hooks; later handlers replace it.
+    pub fn set_completion_hook_handler(&self, handler: crate::completion_hooks::HookHandler) -> CoreResult<()> {
+        self.completion_hooks.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire completion hooks lock".to_string()))?
+            .set_handler(handler);
         
-        // TriggerExecutor still needs sync components for now
-        // TODO: Update TriggerExecutor to use async in Phase 3.2
-        let sync_trigger_manager = Arc::new(Mutex::new(TriggerManager::new()));
-        let sync_state_manager_for_trigger = Arc::new(Mutex::new(StateManager::new(db_path)?));
-        // Share the same dispatcher Arc with trigger executor (it will use block_on to call async methods)
-        let sync_dispatcher_for_trigger = Arc::new(Mutex::new(Arc::clone(&job_dispatcher)));
+        let mut subscription = self.hook_subscription.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire completion hooks lock".to_string()))?;
+        if subscription.is_some() {
+            return Ok(());
+        }
         
-        let trigger_executor = Arc::new(TriggerExecutor::new(
-            sync_state_manager_for_trigger,
-            sync_trigger_manager,
-            sync_dispatcher_for_trigger,
-        ));
+        // Listeners must not block, so finished runs are handed to the hook task
+        let (sender, mut finished_runs) = tokio::sync::mpsc::unbounded_channel::<String>();
+        let filter = crate::run_stream::RunEventFilter {
+            run_id: None,
+            event_types: Some([RunStatus::Completed, RunStatus::Failed, RunStatus::Cancelled].iter()
+                .map(|status| crate::models::RunEvent::for_run_status(status).to_string())
+                .collect()),
+        };
+        *subscription = Some(crate::run_stream::subscribe(filter, Box::new(move |event| {
+            let _ = sender.send(event.run_id.clone());
+        })));
         
-        Ok(AsyncBridge {
-            state_manager,
-            trigger_manager,
-            trigger_executor,
-            job_dispatcher,
-        })
+        let state_manager = Arc::clone(&self.state_manager);
+        let hooks = Arc::clone(&self.completion_hooks);
+        napi::bindgen_prelude::spawn(async move {
+            let mut interval = tokio::time::interval(std::time::Duration::from_millis(200));
+            loop {
+                tokio::select! {
+                    run_id = finished_runs.recv() => match run_id {
+                        Some(run_id) => Self::fire_completion_hook(&state_manager, &hooks, &run_id),
+                        None => break,
+                    },
+                    _ = interval.tick() => Self::tick_completion_hooks(&state_manager, &hooks),
+                }
+            }
+        });
+        Ok(())
     }
 
-    /// Register a workflow from Node.js (async)
-    pub async fn register_workflow(&self, workflow_json: &str) -> CoreResult<()> {
-        log::info!("Registering workflow from JSON (async): {}", workflow_json);
-        
-        let workflow: WorkflowDefinition = serde_json::from_str(workflow_json)
-            .map_err(|e| CoreError::Serialization(e))?;
-        
-        workflow.validate()
-            .map_err(|e| CoreError::InvalidWorkflow(e))?;
-        
-        self.state_manager.register_workflow(workflow.clone()).await?;
-        
-        let trigger_ids = self.trigger_executor.register_workflow_triggers(&workflow.id, &workflow)?;
-        
-        log::info!("Successfully registered workflow: {} with {} triggers: {:?}", workflow.id, trigger_ids.len(), trigger_ids);
-        Ok(())
+    /// Fire the hook of a run that just finished
+    fn fire_completion_hook(state_manager: &Mutex<StateManager>, hooks: &Mutex<crate::completion_hooks::CompletionHooks>, run_id: &str) {
+        let fired = (|| -> CoreResult<Option<crate::completion_hooks::HookInvocation>> {
+            let run_uuid = Uuid::parse_str(run_id)?;
+            let context = {
+                let state_manager = state_manager.lock()
+                    .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+                state_manager.completion_context(&run_uuid)?
+            }; // Lock released here
+            let Some(context) = context else {
+                return Ok(None);
+            };
+            let invocation = hooks.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire completion hooks lock".to_string()))?
+                .fire(context, std::time::Instant::now());
+            if let Some(invocation) = &invocation {
+                let state_manager = state_manager.lock()
+                    .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+                crate::completion_hooks::record(&state_manager, invocation, None)?;
+            }
+            Ok(invocation)
+        })();
+        match fired {
+            Ok(Some(invocation)) => log::info!("Fired {} hook of run {}", invocation.hook_type, run_id),
+            Ok(None) => {}
+            Err(e) => log::error!("Failed to fire the completion hook of run {}: {}", run_id, e),
+        }
+    }
+
+    /// Fail overdue hook invocations and deliver the retries that are due
+    fn tick_completion_hooks(state_manager: &Mutex<StateManager>, hooks: &Mutex<crate::completion_hooks::CompletionHooks>) {
+        let Ok((timed_out, retried)) = hooks.lock().map(|mut hooks| hooks.tick(std::time::Instant::now())) else {
+            log::error!("Failed to acquire completion hooks lock");
+            return;
+        };
+        if timed_out.is_empty() && retried.is_empty() {
+            return;
+        }
+        let Ok(state_manager) = state_manager.lock() else {
+            log::error!("Failed to acquire state manager lock");
+            return;
+        };
+        for (invocation, outcome) in &timed_out {
+            log::warn!("{} hook of run {} was not acknowledged, attempt {}", invocation.hook_type, invocation.context.run_id, invocation.attempt);
+            if let Err(e) = crate::completion_hooks::record(&state_manager, invocation, Some((outcome, "Hook outcome was not reported in time"))) {
+                log::warn!("Failed to record hook outcome for run {}: {}", invocation.context.run_id, e);
+            }
+        }
+        for invocation in &retried {
+            if let Err(e) = crate::completion_hooks::record(&state_manager, invocation, None) {
+                log::warn!("Failed to record hook retry for run {}: {}", invocation.context.run_id, e);
+            }
+        }
+    }
+
+    /// Record the outcome Node reports for a hook invocation, scheduling a retry if it failed
+    pub fn report_completion_hook(&self, invocation_id: &str, error: Option<&str>) -> CoreResult<String> {
+        let (invocation, outcome) = self.completion_hooks.lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire completion hooks lock".to_string()))?
+            .report(invocation_id, error, std::time::Instant::now())?;
+        
+        {
+            let state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            crate::completion_hooks::record(&state_manager, &invocation, Some((&outcome, error.unwrap_or_default())))?;
+        } // Lock released here
+        
+        let (status, retry_in_ms) = match &outcome {
+            crate::completion_hooks::HookOutcome::Succeeded => ("succeeded", None),
+            crate::completion_hooks::HookOutcome::Retrying { delay } => ("retrying", Some(delay.as_millis() as u64)),
+            crate::completion_hooks::HookOutcome::GaveUp => ("gave_up", None),
+        };
+        if outcome == crate::completion_hooks::HookOutcome::GaveUp {
+            log::error!("{} hook of run {} failed after {} attempts", invocation.hook_type, invocation.context.run_id, invocation.attempt);
+        }
+        Ok(serde_json::json!({
+            "invocation_id": invocation.id,
+            "run_id": invocation.context.run_id,
+            "hook_type": invocation.hook_type,
+            "attempt": invocation.attempt,
+            "outcome": status,
+            "retry_in_ms": retry_in_ms,
+        }).to_string())
+    }
+
+    /// Ask a classifier what to do with failed steps instead of their retry rules, or stop asking with None
+    pub async fn set_retry_classifier(&self, handler: Option<crate::retry_classifier::ClassifierHandler>) -> CoreResult<()> {
+        self.job_dispatcher.lock().await.set_retry_classifier(handler).await;
+        Ok(())
+    }
+
+    /// Hand the classifier's decision about a failed step to the step waiting for it
+    pub async fn report_retry_decision(&self, request_id: &str, decision_json: &str) -> CoreResult<String> {
+        let decision: crate::retry_classifier::RetryDecision = serde_json::from_str(decision_json)
+            .map_err(|e| CoreError::Validation(format!("Invalid retry decision: {}", e)))?;
+        self.job_dispatcher.lock().await.report_retry_decision(request_id, decision.clone()).await?;
+        Ok(serde_json::json!({
+            "request_id": request_id,
+            "decision": decision,
+        }).to_string())
+    }
+
+    /// Persist a batch of step completions reported by Node.js
+    pub fn complete_steps_batch(&self, results_json: &str) -> CoreResult<String> {
+        let completions: Vec<crate::models::StepCompletion> = serde_json::from_str(results_json)?;
+        log::info!("Completing batch of {} steps", completions.len());
+        
+        let step_orchestrator = crate::step_orchestrator::StepOrchestrator::new(self.state_manager.clone())
+            .with_dispatcher(Arc::clone(&self.job_dispatcher));
+        let summary = step_orchestrator.complete_steps_batch(completions)?;
+        
+        Ok(serde_json::to_string(&summary)?)
+    }
+
+    /// Export run history that has aged past the retention threshold to cold storage
+    pub fn export_cold_storage(&self) -> CoreResult<String> {
+        log::info!("Exporting due run history to cold storage");
+        
+        let exporter = ColdStorageExporter::new(crate::config::CoreConfig::default().retention)?;
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        let summary = state_manager.export_cold_storage(&exporter)?;
+        
+        Ok(serde_json::to_string(&summary)?)
+    }
+
+    /// Export history of runs started within an RFC 3339 time range to cold storage
+    pub fn export_cold_storage_range(&self, from: &str, to: &str) -> CoreResult<String> {
+        log::info!("Exporting run history from {} to {} to cold storage", from, to);
+        
+        let from = chrono::DateTime::parse_from_rfc3339(from)?.with_timezone(&chrono::Utc);
+        let to = chrono::DateTime::parse_from_rfc3339(to)?.with_timezone(&chrono::Utc);
+        
+        let exporter = ColdStorageExporter::new(crate::config::CoreConfig::default().retention)?;
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        let summary = state_manager.export_cold_storage_range(&exporter, from, to)?;
+        
+        Ok(serde_json::to_string(&summary)?)
+    }
+
+    /// Export a workflow's runs started since an RFC 3339 time as JSON Lines or CSV
+    ///
+    /// Each page of runs is handed to `sink` as soon as it is rendered; the
+    /// state manager is only held while a page is read.
+    pub fn export_runs(&self, workflow_id: &str, since: Option<&str>, format: &str, mut sink: impl FnMut(&str) -> CoreResult<()>) -> CoreResult<RunExportSummary> {
+        log::info!("Exporting runs of workflow {} since {:?} as {}", workflow_id, since, format);
+        
+        let since = since
+            .map(|since| chrono::DateTime::parse_from_rfc3339(since).map(|at| at.with_timezone(&chrono::Utc)))
+            .transpose()?;
+        let mut exporter = RunExporter::new(workflow_id, since, format.parse()?);
+        loop {
+            let chunk = {
+                let state_manager = self.state_manager.lock()
+                    .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+                exporter.next_chunk(&state_manager)?
+            }; // Lock released here
+            match chunk {
+                Some(chunk) if !chunk.is_empty() => sink(&chunk)?,
+                Some(_) => {}
+                None => break,
+            }
+        }
+        
+        let summary = exporter.summary().clone();
+        log::info!("Exported {} runs and {} step results of workflow {}", summary.runs, summary.steps, workflow_id);
+        Ok(summary)
+    }
+
+    /// Export a workflow's runs started since an RFC 3339 time to a JSON Lines or CSV file
+    pub fn export_runs_to_file(&self, workflow_id: &str, since: Option<&str>, format: &str, path: &str) -> CoreResult<String> {
+        use std::io::Write;
+        
+        let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut summary = self.export_runs(workflow_id, since, format, |chunk| Ok(file.write_all(chunk.as_bytes())?))?;
+        file.flush()?;
+        summary.path = Some(path.to_string());
+        
+        Ok(serde_json::to_string(&summary)?)
+    }
+
+    /// Delete finished, unpinned runs that completed before a timestamp, with their steps and events
+    ///
+    /// The timestamp is RFC 3339 or Unix milliseconds.
+    pub fn purge_runs(&self, before_timestamp: &str) -> CoreResult<String> {
+        log::info!("Purging runs finished before {}", before_timestamp);
+        
+        let before = crate::record_compat::read_timestamp(before_timestamp)?;
+        let summary = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?;
+            state_manager.purge_runs(before)?
+        }; // Lock released here
+        
+        Ok(serde_json::to_string(&summary)?)
+    }
+}
+
+// ============================================================================
+// ASYNC BRIDGE IMPLEMENTATION (Task 2.1.3)
+// ============================================================================
+
+impl AsyncBridge {
+    /// Create a new async N-API bridge
+    ///
+    /// Fails when a shared or isolated state store is configured: the async
+    /// state manager only reads and writes the local SQLite database, so runs
+    /// it created would be invisible to the dispatcher.
+    pub fn new(db_path: &str) -> CoreResult<Self> {
+        let dispatcher_state_manager = StateManager::new(db_path)?;
+        if dispatcher_state_manager.has_shared_store() {
+            return Err(CoreError::Configuration(
+                "The async N-API functions only support the local SQLite state store; \
+                 use the synchronous functions with CRONFLOW_DATABASE_URL or isolated stores".to_string(),
+            ));
+        }
+        let state_manager = Arc::new(AsyncStateManager::new(db_path)?);
+        let trigger_manager = Arc::new(TokioMutex::new(TriggerManager::new()));
+        
+        // Dispatcher now uses Tokio async tasks
+        let dispatcher_config = crate::dispatcher::WorkerPoolConfig::default();
+        let async_state_manager = Arc::new(TokioMutex::new(dispatcher_state_manager));
+        let engine_paused = async_state_manager.try_lock()
+            .map_err(|_| CoreError::Internal("Failed to acquire state manager lock".to_string()))?
+            .is_engine_paused()?;
+        let job_dispatcher = Arc::new(TokioMutex::new(
+            Dispatcher::new(dispatcher_config, async_state_manager.clone()).with_paused(engine_paused)
+        ));
+        
+        // The trigger executor shares the dispatcher's state manager, trigger manager and dispatcher
+        let trigger_executor = Arc::new(TriggerExecutor::new(
+            async_state_manager,
+            trigger_manager.clone(),
+            Arc::clone(&job_dispatcher),
+        ));
+        
+        Ok(AsyncBridge {
+            state_manager,
+            trigger_manager,
+            trigger_executor,
+            job_dispatcher,
+        })
+    }
+
+    /// Register a workflow from Node.js (async)
+    pub async fn register_workflow(&self, workflow_json: &str) -> CoreResult<WorkflowCompatibilityReport> {
+        log::info!("Registering workflow from JSON (async): {}", workflow_json);
+        
+        let strict = crate::config::CoreConfig::default().registration.strict_workflows;
+        let (workflow, report) = WorkflowDefinition::from_json(workflow_json, strict)?;
+        if !report.is_clean() {
+            log::warn!("Workflow {} has unknown fields that were ignored: {}", workflow.id, report.ignored_fields.join(", "));
+        }
+        
+        workflow.validate()
+            .map_err(|e| CoreError::InvalidWorkflow(e))?;
+        
+        self.state_manager.register_workflow(workflow.clone()).await?;
+        
+        let trigger_ids = self.trigger_executor.register_workflow_triggers(&workflow.id, &workflow).await?;
+        
+        log::info!("Successfully registered workflow: {} with {} triggers: {:?}", workflow.id, trigger_ids.len(), trigger_ids);
+        Ok(report)
     }
 
     /// Create a workflow run from Node.js (async)
@@ -641,6 +2098,35 @@ impl AsyncBridge {
         Ok(result)
     }
 
+    /// Wait for a run to reach a terminal state, up to a timeout (async)
+    ///
+    /// Resolves with the run as soon as its terminal event is recorded, or as
+    /// it stands once the timeout expires, with `timed_out` set.
+    pub async fn wait_for_run(&self, run_id: &str, timeout_ms: u64) -> CoreResult<String> {
+        let run_uuid = uuid::Uuid::parse_str(run_id)?;
+        
+        // Listen before reading the run, so it cannot finish unnoticed in between
+        let completion = crate::run_stream::RunCompletion::watch(&run_uuid.to_string());
+        let mut run = self.state_manager.get_run(&run_uuid).await?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let finished = run.status.is_terminal()
+            || completion.wait(std::time::Duration::from_millis(timeout_ms)).await.is_some();
+        if finished && !run.status.is_terminal() {
+            run = self.state_manager.get_run(&run_uuid).await?
+                .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        }
+        
+        Ok(serde_json::json!({"run": run, "timed_out": !finished}).to_string())
+    }
+
+    /// List runs matching a query (async)
+    pub async fn list_runs(&self, query: RunQuery) -> CoreResult<String> {
+        log::info!("Listing runs with query (async): {:?}", query);
+        
+        let page = self.state_manager.list_runs(query).await?;
+        Ok(serde_json::to_string(&page)?)
+    }
+
     /// Execute a step with context (async)
     pub async fn execute_step(&self, run_id: &str, step_id: &str) -> CoreResult<String> {
         log::info!("Executing step {} for run {} (async)", step_id, run_id);
@@ -657,7 +2143,11 @@ impl AsyncBridge {
         let step = workflow.get_step(step_id)
             .ok_or_else(|| CoreError::Validation(format!("Step '{}' not found in workflow '{}'", step_id, run.workflow_id)))?;
         
-        let completed_steps = self.state_manager.get_completed_steps(&run_uuid).await?;
+        let completed_steps = self.state_manager.get_completed_steps_with_outputs(&run_uuid).await?;
+        let attempt = completed_steps.iter()
+            .filter(|result| result.step_id == step_id && result.status.is_failure())
+            .count() as u32;
+        let execution = self.state_manager.execution_info(run_id, step_id, &completed_steps, None).await?;
         
         let mut context = crate::context::Context::new(
             run_id.to_string(),
@@ -671,6 +2161,13 @@ impl AsyncBridge {
         if let Some(timeout) = step.timeout {
             context.set_timeout(timeout);
         }
+        context.metadata.retry_count = attempt;
+        context.execution = execution;
+        context.state = self.state_manager.context_state(run_id, &run.workflow_id).await?;
+        context.secrets = self.state_manager.workflow_secrets(&run.workflow_id).await?;
+        context.labels = self.state_manager.get_run_labels(run_id).await?;
+        context.render_input(step)?;
+        self.state_manager.record_issued_context(&context).await?;
         
         // Serialize context for Bun.js
         let context_json = context.to_json()?;
@@ -760,6 +2257,8 @@ impl AsyncBridge {
 pub struct SimpleResult {
     pub success: bool,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
 }
 
 /// Result with optional data payload (JSON string)
@@ -769,6 +2268,8 @@ pub struct DataResult {
     pub success: bool,
     pub data: Option<String>,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
 }
 
 /// Result with optional ID and data
@@ -779,6 +2280,8 @@ pub struct IdDataResult {
     pub id: Option<String>,
     pub data: Option<String>,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
 }
 
 // ============================================================================
@@ -796,6 +2299,8 @@ pub struct JobExecutionResult {
     pub context: Option<String>,
     pub result: Option<String>,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
 }
 
 /// Result for job cancellation (has boolean flag)
@@ -806,6 +2311,8 @@ pub struct JobCancellationResult {
     pub job_id: Option<String>,
     pub cancelled: bool,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
 }
 
 /// Result for trigger execution (two IDs)
@@ -816,6 +2323,8 @@ pub struct TriggerExecutionResult {
     pub run_id: Option<String>,
     pub workflow_id: Option<String>,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
 }
 
 /// Result for hook execution
@@ -827,10 +2336,41 @@ pub struct HookExecutionResult {
     pub workflow_id: Option<String>,
     pub result: Option<String>,
     pub message: String,
+    pub code: Option<String>,
+    pub details: Option<String>,
+}
+
+/// Results of N-API functions that can carry the error they failed with
+///
+/// Failed results get the error's machine-readable `code`, such as
+/// WORKFLOW_NOT_FOUND, and its JSON `details`, so callers can branch on the
+/// error type instead of parsing the message.
+trait ErrorResult {
+    /// Set the code and details of the error the operation failed with
+    fn with_error(self, error: &CoreError) -> Self;
+}
+
+macro_rules! impl_error_result {
+    ($($result:ty),*) => {
+        $(
+            impl ErrorResult for $result {
+                fn with_error(mut self, error: &CoreError) -> Self {
+                    self.code = Some(error.code().to_string());
+                    self.details = Some(error.details().to_string());
+                    self
+                }
+            }
+        )*
+    };
 }
 
+impl_error_result!(SimpleResult, DataResult, IdDataResult, JobExecutionResult, JobCancellationResult, TriggerExecutionResult, HookExecutionResult);
+
 // Type aliases for backward compatibility and clarity
 pub type WorkflowRegistrationResult = SimpleResult;
+pub type WorkflowCompatibilityResult = DataResult;
+pub type WorkflowBatchRegistrationResult = DataResult;
+pub type RunBatchCreationResult = DataResult;
 pub type WebhookTriggerRegistrationResult = SimpleResult;
 pub type TriggerUnregistrationResult = SimpleResult;
 pub type WebhookServerResult = SimpleResult;
@@ -840,10 +2380,76 @@ pub type RunStatusResult = DataResult;
 pub type StepExecutionResult = DataResult;
 pub type WebhookTriggersResult = DataResult;
 pub type DispatcherStatsResult = DataResult;
+pub type WorkerDetailsResult = DataResult;
+pub type WorkerListResult = DataResult;
+pub type JobListResult = DataResult;
+pub type JobDetailsResult = DataResult;
+pub type StepHeartbeatResult = SimpleResult;
+pub type FeatureFlagsResult = DataResult;
+pub type ChaosResult = DataResult;
+pub type LoggingResult = DataResult;
+pub type ResourceUsageResult = DataResult;
+pub type LaneStatusResult = DataResult;
+pub type WorkerTagsResult = DataResult;
+pub type WorkflowDisableResult = DataResult;
 pub type TriggerStatsResult = DataResult;
+pub type SuppressedTriggersResult = DataResult;
 pub type WorkflowTriggersResult = DataResult;
+pub type ScheduleFiringResult = DataResult;
+pub type EventSchemaResult = DataResult;
+pub type EventPublicationResult = DataResult;
+pub type StoreCompatibilityResult = DataResult;
+pub type WorkflowVersionResult = DataResult;
+pub type WorkflowExportResult = DataResult;
+pub type WorkflowImportResult = DataResult;
+pub type ColdStorageExportResult = DataResult;
+pub type RunExportResult = DataResult;
+pub type RunPurgeResult = DataResult;
+pub type EngineShutdownResult = DataResult;
+pub type RecoveryReportResult = DataResult;
+pub type StepBatchCompletionResult = DataResult;
+pub type RunListResult = DataResult;
+pub type RunSearchResult = DataResult;
+pub type SignalResult = DataResult;
+pub type RunWaitResult = DataResult;
+pub type RunPinResult = DataResult;
+pub type RunPinAuditResult = DataResult;
+pub type RunUnpinResult = SimpleResult;
+pub type RunExplanationResult = DataResult;
+pub type RunTimelineResult = DataResult;
+pub type StepLogResult = DataResult;
+pub type StepLogsResult = DataResult;
+pub type DatabaseSettingsResult = DataResult;
+pub type SnapshotResult = DataResult;
+pub type SchemaVersionResult = DataResult;
+pub type RunRecoveryPlanResult = DataResult;
+pub type RunReplayResult = DataResult;
+pub type DryRunResult = DataResult;
+pub type StepDurationStatsResult = DataResult;
+pub type StepRecoveryConfirmResult = SimpleResult;
+pub type RunCancellationResult = DataResult;
+pub type BulkRunResult = DataResult;
+pub type AdhocStepExecutionResult = DataResult;
+pub type StateGetResult = DataResult;
+pub type StateSetResult = SimpleResult;
+pub type StateIncrResult = DataResult;
+pub type AdhocStepExecutionsResult = DataResult;
+pub type SecretResult = DataResult;
+pub type SecretDeletionResult = DataResult;
+pub type EnginePauseResult = SimpleResult;
+pub type EngineResumeResult = DataResult;
+pub type EngineStatusResult = DataResult;
+pub type WorkflowHookHandlerResult = DataResult;
+pub type WorkflowHookReportResult = DataResult;
+pub type RetryClassifierResult = DataResult;
+pub type RetryDecisionResult = DataResult;
+pub type BackfillResult = DataResult;
+pub type RunLabelsResult = DataResult;
+pub type RunLabelRemovalResult = SimpleResult;
 
 pub type JobStatusResult = IdDataResult;
+pub type RunEventSubscriptionResult = IdDataResult;
+pub type RunEventUnsubscriptionResult = SimpleResult;
 pub type WorkflowRunStatusResult = IdDataResult;
 pub type WorkflowStepsResult = IdDataResult;
 
@@ -852,38 +2458,132 @@ pub type WorkflowStepsResult = IdDataResult;
 pub fn register_workflow(workflow_json: String, db_path: String) -> WorkflowRegistrationResult {
     with_shared_bridge!(
         &db_path,
-        |_| WorkflowRegistrationResult {
+        |report: WorkflowCompatibilityReport| WorkflowRegistrationResult {
+            success: true,
+            message: registration_message(&report),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowRegistrationResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.register_workflow(&workflow_json))
+    )
+}
+
+/// Register a workflow via N-API, rejecting unknown fields regardless of configuration
+#[napi]
+pub fn register_workflow_strict(workflow_json: String, db_path: String) -> WorkflowRegistrationResult {
+    with_shared_bridge!(
+        &db_path,
+        |report: WorkflowCompatibilityReport| WorkflowRegistrationResult {
             success: true,
-            message: "Workflow registered successfully".to_string(),
+            message: registration_message(&report),
+            code: None,
+            details: None,
         },
         |msg: String| WorkflowRegistrationResult {
             success: false,
             message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.register_workflow_with_mode(&workflow_json, true))
+    )
+}
+
+/// Register several workflows in one transaction via N-API
+#[napi]
+pub fn register_workflows(workflows_json: String, db_path: String) -> WorkflowBatchRegistrationResult {
+    with_shared_bridge!(
+        &db_path,
+        |reports_json: String| WorkflowBatchRegistrationResult {
+            success: true,
+            data: Some(reports_json),
+            message: "Workflows registered successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowBatchRegistrationResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.register_workflow(&workflow_json)
+        |bridge: Arc<Bridge>| block_on_napi(bridge.register_workflows(&workflows_json))
     )
 }
 
+/// Report the fields of a workflow JSON document that registration would ignore
+#[napi]
+pub fn check_workflow_compatibility(workflow_json: String) -> WorkflowCompatibilityResult {
+    let report = WorkflowDefinition::from_json(&workflow_json, false)
+        .and_then(|(_, report)| Ok(serde_json::to_string(&report)?));
+    match report {
+        Ok(data) => WorkflowCompatibilityResult {
+            success: true,
+            data: Some(data),
+            message: "Workflow compatibility checked".to_string(),
+            code: None,
+            details: None,
+        },
+        Err(e) => WorkflowCompatibilityResult {
+            success: false,
+            data: None,
+            message: format!("Failed to check workflow compatibility: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e),
+    }
+}
+
+/// Registration success message, listing any ignored fields and step graph warnings
+fn registration_message(report: &WorkflowCompatibilityReport) -> String {
+    let mut notes = Vec::new();
+    if !report.is_clean() {
+        notes.push(format!("ignored unknown fields: {}", report.ignored_fields.join(", ")));
+    }
+    let warnings = report.warnings();
+    if !warnings.is_empty() {
+        notes.push(format!("warnings: {}", warnings.join("; ")));
+    }
+    if notes.is_empty() {
+        "Workflow registered successfully".to_string()
+    } else {
+        format!("Workflow registered successfully ({})", notes.join("; "))
+    }
+}
+
 /// Register a workflow via N-API (async version) - Task 2.1.4
 #[napi(ts_return_type = "Promise<WorkflowRegistrationResult>")]
 pub async fn register_workflow_async(workflow_json: String, db_path: String) -> napi::Result<WorkflowRegistrationResult> {
     match get_shared_async_bridge(&db_path).await {
         Ok(bridge) => {
             match bridge.register_workflow(&workflow_json).await {
-                Ok(_) => Ok(WorkflowRegistrationResult {
+                Ok(report) => Ok(WorkflowRegistrationResult {
                     success: true,
-                    message: "Workflow registered successfully".to_string(),
+                    message: registration_message(&report),
+                    code: None,
+                    details: None,
                 }),
                 Err(e) => Ok(WorkflowRegistrationResult {
                 success: false,
                     message: format!("Failed to register workflow: {}", e),
-                }),
+                    code: None,
+                    details: None,
+                }.with_error(&e)),
             }
         }
         Err(e) => Ok(WorkflowRegistrationResult {
                 success: false,
             message: format!("Failed to get bridge: {}", e),
-        }),
+            code: None,
+            details: None,
+        }.with_error(&e)),
     }
 }
 
@@ -895,12 +2595,16 @@ pub fn register_webhook_trigger(workflow_id: String, trigger_json: String, db_pa
         |_| WebhookTriggerRegistrationResult {
             success: true,
             message: "Webhook trigger registered successfully".to_string(),
+            code: None,
+            details: None,
         },
         |msg: String| WebhookTriggerRegistrationResult {
                 success: false,
             message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.register_webhook_trigger(&workflow_id, &trigger_json)
+        |bridge: Arc<Bridge>| block_on_napi(bridge.register_webhook_trigger(&workflow_id, &trigger_json))
     )
 }
 
@@ -913,13 +2617,180 @@ pub fn get_webhook_triggers(db_path: String) -> WebhookTriggersResult {
                 success: true,
             data: Some(triggers_json),
                 message: "Webhook triggers retrieved successfully".to_string(),
+                code: None,
+                details: None,
         },
         |msg: String| WebhookTriggersResult {
                 success: false,
             data: None,
             message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_webhook_triggers())
+    )
+}
+
+/// Create a workflow run with a correlation ID via N-API
+#[napi]
+pub fn create_correlated_run(workflow_id: String, payload_json: String, correlation_id: String, db_path: String) -> RunCreationResult {
+    with_shared_bridge!(
+        &db_path,
+        |run_id: String| RunCreationResult {
+            success: true,
+            id: Some(run_id),
+            data: None,
+            message: "Run created successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunCreationResult {
+            success: false,
+            id: None,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.create_correlated_run(&workflow_id, &payload_json, &correlation_id)
+    )
+}
+
+/// Create several workflow runs in one transaction via N-API
+#[napi]
+pub fn create_runs_batch(runs_json: String, db_path: String) -> RunBatchCreationResult {
+    with_shared_bridge!(
+        &db_path,
+        |run_ids_json: String| RunBatchCreationResult {
+            success: true,
+            data: Some(run_ids_json),
+            message: "Runs created successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunBatchCreationResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.create_runs_batch(&runs_json)
+    )
+}
+
+/// Get a workflow's run by its correlation ID via N-API
+#[napi]
+pub fn get_run_by_correlation_id(workflow_id: String, correlation_id: String, db_path: String) -> RunStatusResult {
+    with_shared_bridge!(
+        &db_path,
+        |run_json: String| RunStatusResult {
+            success: true,
+            data: Some(run_json),
+            message: "Run retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunStatusResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_run_by_correlation_id(&workflow_id, &correlation_id)
+    )
+}
+
+/// Get one registered version of a workflow via N-API
+#[napi]
+pub fn get_workflow_version(workflow_id: String, version: u32, db_path: String) -> WorkflowVersionResult {
+    with_shared_bridge!(
+        &db_path,
+        |version_json: String| WorkflowVersionResult {
+            success: true,
+            data: Some(version_json),
+            message: "Workflow version retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowVersionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_workflow_version(&workflow_id, version)
+    )
+}
+
+/// List the registered versions of a workflow via N-API
+#[napi]
+pub fn list_workflow_versions(workflow_id: String, db_path: String) -> WorkflowVersionResult {
+    with_shared_bridge!(
+        &db_path,
+        |versions_json: String| WorkflowVersionResult {
+            success: true,
+            data: Some(versions_json),
+            message: "Workflow versions retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowVersionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.list_workflow_versions(&workflow_id)
+    )
+}
+
+/// Export a workflow as a portable bundle via N-API
+#[napi]
+pub fn export_workflow(workflow_id: String, db_path: String) -> WorkflowExportResult {
+    with_shared_bridge!(
+        &db_path,
+        |bundle_json: String| WorkflowExportResult {
+            success: true,
+            data: Some(bundle_json),
+            message: "Workflow exported successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowExportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.export_workflow(&workflow_id)
+    )
+}
+
+/// Import a workflow bundle via N-API
+#[napi]
+pub fn import_workflow(bundle_json: String, db_path: String) -> WorkflowImportResult {
+    with_shared_bridge!(
+        &db_path,
+        |import_json: String| WorkflowImportResult {
+            success: true,
+            data: Some(import_json),
+            message: "Workflow imported successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowImportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.get_webhook_triggers()
+        |bridge: Arc<Bridge>| block_on_napi(bridge.import_workflow(&bundle_json))
     )
 }
 
@@ -933,12 +2804,16 @@ pub fn create_run(workflow_id: String, payload_json: String, db_path: String) ->
             id: Some(run_id),
             data: None,
             message: "Run created successfully".to_string(),
+            code: None,
+            details: None,
         },
         |msg: String| RunCreationResult {
             success: false,
             id: None,
             data: None,
             message: msg,
+            code: None,
+            details: None,
         },
         |bridge: Arc<Bridge>| bridge.create_run(&workflow_id, &payload_json)
     )
@@ -955,13 +2830,17 @@ pub async fn create_run_async(workflow_id: String, payload_json: String, db_path
                     id: Some(run_id),
                     data: None,
                     message: "Run created successfully".to_string(),
+                    code: None,
+                    details: None,
                 }),
                 Err(e) => Ok(RunCreationResult {
                     success: false,
                     id: None,
                     data: None,
                     message: format!("Failed to create run: {}", e),
-                }),
+                    code: None,
+                    details: None,
+                }.with_error(&e)),
             }
         }
         Err(e) => Ok(RunCreationResult {
@@ -969,7 +2848,42 @@ pub async fn create_run_async(workflow_id: String, payload_json: String, db_path
             id: None,
             data: None,
             message: format!("Failed to get bridge: {}", e),
-        }),
+            code: None,
+            details: None,
+        }.with_error(&e)),
+    }
+}
+
+/// Wait for a run to reach a terminal state via N-API (async)
+///
+/// Resolves once the run completes, fails or is cancelled, or after
+/// `timeout_ms` with the run's current state and `timed_out` set in the data.
+#[napi(ts_return_type = "Promise<RunWaitResult>")]
+pub async fn wait_for_run(run_id: String, timeout_ms: u32, db_path: String) -> napi::Result<RunWaitResult> {
+    match get_shared_async_bridge(&db_path).await {
+        Ok(bridge) => match bridge.wait_for_run(&run_id, timeout_ms as u64).await {
+            Ok(run_json) => Ok(RunWaitResult {
+                success: true,
+                data: Some(run_json),
+                message: "Run wait finished".to_string(),
+                code: None,
+                details: None,
+            }),
+            Err(e) => Ok(RunWaitResult {
+                success: false,
+                data: None,
+                message: format!("Failed to wait for run: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)),
+        },
+        Err(e) => Ok(RunWaitResult {
+            success: false,
+            data: None,
+            message: format!("Failed to get bridge: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e)),
     }
 }
 
@@ -982,236 +2896,1508 @@ pub fn get_run_status(run_id: String, db_path: String) -> RunStatusResult {
             success: true,
             data: Some(status_json),
             message: "Status retrieved successfully".to_string(),
+            code: None,
+            details: None,
         },
         |msg: String| RunStatusResult {
             success: false,
             data: None,
             message: msg,
+            code: None,
+            details: None,
         },
         |bridge: Arc<Bridge>| bridge.get_run_status(&run_id)
     )
 }
 
+/// List runs with optional filters and pagination via N-API
+///
+/// `labels_json` is a JSON object of labels every listed run must have.
+#[napi]
+#[allow(clippy::too_many_arguments)]
+pub fn list_runs(
+    workflow_id: Option<String>,
+    status: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    since: Option<String>,
+    pinned: Option<bool>,
+    labels_json: Option<String>,
+    db_path: String,
+) -> RunListResult {
+    with_shared_bridge!(
+        &db_path,
+        |page_json: String| RunListResult {
+            success: true,
+            data: Some(page_json),
+            message: "Runs listed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunListResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| {
+            let query = build_run_query(workflow_id, status, limit, offset, since, pinned, labels_json)?;
+            bridge.list_runs(&query)
+        }
+    )
+}
+
+/// List runs with optional filters and pagination via N-API (async version)
+#[napi(ts_return_type = "Promise<RunListResult>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn list_runs_async(
+    workflow_id: Option<String>,
+    status: Option<String>,
+    limit: Option<u32>,
+    offset: Option<u32>,
+    since: Option<String>,
+    pinned: Option<bool>,
+    labels_json: Option<String>,
+    db_path: String,
+) -> napi::Result<RunListResult> {
+    let result = match get_shared_async_bridge(&db_path).await {
+        Ok(bridge) => match build_run_query(workflow_id, status, limit, offset, since, pinned, labels_json) {
+            Ok(query) => bridge.list_runs(query).await,
+            Err(e) => Err(e),
+        },
+        Err(e) => Err(e),
+    };
+    
+    Ok(match result {
+        Ok(page_json) => RunListResult {
+            success: true,
+            data: Some(page_json),
+            message: "Runs listed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        Err(e) => RunListResult {
+            success: false,
+            data: None,
+            message: format!("Failed to list runs: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e),
+    })
+}
+
+/// Find runs by a value inside their payload or step outputs via N-API
+#[napi]
+pub fn find_runs(
+    workflow_id: String,
+    path: String,
+    value: String,
+    limit: Option<u32>,
+    db_path: String,
+) -> RunSearchResult {
+    with_shared_bridge!(
+        &db_path,
+        |runs_json: String| RunSearchResult {
+            success: true,
+            data: Some(runs_json),
+            message: "Runs found successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunSearchResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.find_runs(&workflow_id, &path, &value, limit.map(|l| l as usize))
+    )
+}
+
+/// Send a signal to a run via N-API
+#[napi]
+pub fn send_signal(run_id: String, signal_name: String, payload_json: String, db_path: String) -> SignalResult {
+    with_shared_bridge!(
+        &db_path,
+        |signal_json: String| SignalResult {
+            success: true,
+            data: Some(signal_json),
+            message: "Signal sent successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SignalResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.send_signal(&run_id, &signal_name, &payload_json)
+    )
+}
+
+#[napi]
+pub fn pin_run(run_id: String, reason: String, pinned_by: Option<String>, db_path: String) -> RunPinResult {
+    with_shared_bridge!(
+        &db_path,
+        |pin_json: String| RunPinResult {
+            success: true,
+            data: Some(pin_json),
+            message: "Run pinned successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunPinResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.pin_run(&run_id, &reason, pinned_by.as_deref())
+    )
+}
+
+/// Disable a workflow via N-API, until an RFC 3339 time if given
+#[napi]
+pub fn disable_workflow(workflow_id: String, reason: Option<String>, until: Option<String>, db_path: String) -> WorkflowDisableResult {
+    with_shared_bridge!(
+        &db_path,
+        |disabled_json: String| WorkflowDisableResult {
+            success: true,
+            data: Some(disabled_json),
+            message: "Workflow disabled successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowDisableResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.disable_workflow(&workflow_id, reason.as_deref(), until.as_deref())
+    )
+}
+
+/// Enable a disabled workflow via N-API
+#[napi]
+pub fn enable_workflow(workflow_id: String, db_path: String) -> WorkflowDisableResult {
+    with_shared_bridge!(
+        &db_path,
+        |enabled_json: String| WorkflowDisableResult {
+            success: true,
+            data: Some(enabled_json),
+            message: "Workflow enabled successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowDisableResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.enable_workflow(&workflow_id)
+    )
+}
+
+/// Unpin a run via N-API
+#[napi]
+pub fn unpin_run(run_id: String, unpinned_by: Option<String>, db_path: String) -> RunUnpinResult {
+    with_shared_bridge!(
+        &db_path,
+        |_| RunUnpinResult {
+            success: true,
+            message: "Run unpinned successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunUnpinResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.unpin_run(&run_id, unpinned_by.as_deref())
+    )
+}
+
+/// Label a run via N-API, e.g. from a step through its context
+///
+/// `labels_json` is a JSON object of string labels; the run's other labels are kept.
+#[napi]
+pub fn set_run_labels(run_id: String, labels_json: String, db_path: String) -> RunLabelsResult {
+    with_shared_bridge!(
+        &db_path,
+        |labels_json: String| RunLabelsResult {
+            success: true,
+            data: Some(labels_json),
+            message: "Run labelled successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunLabelsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.set_run_labels(&run_id, &labels_json)
+    )
+}
+
+/// Remove a label of a run via N-API
+#[napi]
+pub fn remove_run_label(run_id: String, key: String, db_path: String) -> RunLabelRemovalResult {
+    with_shared_bridge!(
+        &db_path,
+        |_| RunLabelRemovalResult {
+            success: true,
+            message: "Run label removed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunLabelRemovalResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.remove_run_label(&run_id, &key)
+    )
+}
+
+/// Get the labels of a run via N-API
+#[napi]
+pub fn get_run_labels(run_id: String, db_path: String) -> RunLabelsResult {
+    with_shared_bridge!(
+        &db_path,
+        |labels_json: String| RunLabelsResult {
+            success: true,
+            data: Some(labels_json),
+            message: "Run labels retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunLabelsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_run_labels(&run_id)
+    )
+}
+
+/// List runs with a label set to a value via N-API, newest first
+#[napi]
+pub fn list_runs_by_label(key: String, value: String, limit: Option<u32>, offset: Option<u32>, db_path: String) -> RunListResult {
+    with_shared_bridge!(
+        &db_path,
+        |page_json: String| RunListResult {
+            success: true,
+            data: Some(page_json),
+            message: "Runs listed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunListResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.list_runs_by_label(&key, &value, limit, offset)
+    )
+}
+
+/// Get the pin audit trail of a run via N-API
+#[napi]
+pub fn get_run_pin_audit(run_id: String, db_path: String) -> RunPinAuditResult {
+    with_shared_bridge!(
+        &db_path,
+        |audit_json: String| RunPinAuditResult {
+            success: true,
+            data: Some(audit_json),
+            message: "Run pin audit retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunPinAuditResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_run_pin_audit(&run_id)
+    )
+}
+
+/// Explain a run step by step via N-API
+#[napi]
+pub fn explain_run(run_id: String, db_path: String) -> RunExplanationResult {
+    with_shared_bridge!(
+        &db_path,
+        |explanation_json: String| RunExplanationResult {
+            success: true,
+            data: Some(explanation_json),
+            message: "Run explanation generated successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunExplanationResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.explain_run(&run_id)
+    )
+}
+
+/// Get the ordered event log of a run via N-API
+#[napi]
+pub fn get_run_timeline(run_id: String, db_path: String) -> RunTimelineResult {
+    with_shared_bridge!(
+        &db_path,
+        |events_json: String| RunTimelineResult {
+            success: true,
+            data: Some(events_json),
+            message: "Run timeline retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunTimelineResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_run_timeline(&run_id)
+    )
+}
+
+/// Append a structured log line for a step via N-API
+#[napi]
+pub fn append_step_log(run_id: String, step_id: String, level: String, message: String, fields_json: Option<String>, db_path: String) -> StepLogResult {
+    with_shared_bridge!(
+        &db_path,
+        |entry_json: String| StepLogResult {
+            success: true,
+            data: Some(entry_json),
+            message: "Step log appended successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepLogResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.append_step_log(&run_id, &step_id, &level, &message, fields_json.as_deref())
+    )
+}
+
+/// Get the log lines of a step appended after a sequence number via N-API
+#[napi]
+pub fn get_step_logs(run_id: String, step_id: String, after_seq: Option<i64>, db_path: String) -> StepLogsResult {
+    with_shared_bridge!(
+        &db_path,
+        |entries_json: String| StepLogsResult {
+            success: true,
+            data: Some(entries_json),
+            message: "Step logs retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepLogsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_step_logs(&run_id, &step_id, after_seq.unwrap_or(0))
+    )
+}
+
+/// Plan the recovery of a run via N-API
+#[napi]
+pub fn get_run_recovery_plan(run_id: String, replay: bool, db_path: String) -> RunRecoveryPlanResult {
+    with_shared_bridge!(
+        &db_path,
+        |plan_json: String| RunRecoveryPlanResult {
+            success: true,
+            data: Some(plan_json),
+            message: "Run recovery plan generated successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunRecoveryPlanResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_run_recovery_plan(&run_id, replay)
+    )
+}
+
+/// Replay a run's recorded event log via N-API
+#[napi]
+pub fn replay_run(run_id: String, db_path: String) -> RunReplayResult {
+    with_shared_bridge!(
+        &db_path,
+        |report_json: String| RunReplayResult {
+            success: true,
+            data: Some(report_json),
+            message: "Run replayed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunReplayResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.replay_run(&run_id)
+    )
+}
+
+/// Get a step's duration distribution via N-API
+#[napi]
+pub fn get_step_duration_stats(workflow_id: String, step_id: String, db_path: String) -> StepDurationStatsResult {
+    with_shared_bridge!(
+        &db_path,
+        |stats_json: String| StepDurationStatsResult {
+            success: true,
+            data: Some(stats_json),
+            message: "Step duration statistics retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepDurationStatsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_step_duration_stats(&workflow_id, &step_id)
+    )
+}
+
+/// Replay a self-contained event log via N-API
+#[napi]
+pub fn replay_events(request_json: String, db_path: String) -> RunReplayResult {
+    with_shared_bridge!(
+        &db_path,
+        |report_json: String| RunReplayResult {
+            success: true,
+            data: Some(report_json),
+            message: "Events replayed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunReplayResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.replay_events(&request_json)
+    )
+}
+
+/// Dry run a workflow via N-API
+#[napi]
+pub fn dry_run(workflow_id: String, payload_json: String, db_path: String) -> DryRunResult {
+    with_shared_bridge!(
+        &db_path,
+        |report_json: String| DryRunResult {
+            success: true,
+            data: Some(report_json),
+            message: "Dry run completed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| DryRunResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.dry_run(&workflow_id, &payload_json)
+    )
+}
+
+/// Confirm re-execution of a non-idempotent step via N-API
+#[napi]
+pub fn confirm_step_recovery(run_id: String, step_id: String, confirmed_by: Option<String>, db_path: String) -> StepRecoveryConfirmResult {
+    with_shared_bridge!(
+        &db_path,
+        |_| StepRecoveryConfirmResult {
+            success: true,
+            message: "Step recovery confirmed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepRecoveryConfirmResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.confirm_step_recovery(&run_id, &step_id, confirmed_by.as_deref())
+    )
+}
+
 /// Execute a step via N-API (synchronous version)
 #[napi]
-pub fn execute_step(run_id: String, step_id: String, db_path: String) -> StepExecutionResult {
+pub fn execute_step(run_id: String, step_id: String, db_path: String) -> StepExecutionResult {
+    with_shared_bridge!(
+        &db_path,
+        |result: String| StepExecutionResult {
+            success: true,
+            data: Some(result),
+            message: "Step executed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepExecutionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.execute_step(&run_id, &step_id)
+    )
+}
+
+/// Execute a step via N-API (async version) - Task 2.1.4
+#[napi(ts_return_type = "Promise<StepExecutionResult>")]
+pub async fn execute_step_async(run_id: String, step_id: String, db_path: String) -> napi::Result<StepExecutionResult> {
+    match get_shared_async_bridge(&db_path).await {
+        Ok(bridge) => {
+            match bridge.execute_step(&run_id, &step_id).await {
+                Ok(result) => Ok(StepExecutionResult {
+        success: true,
+                    data: Some(result),
+                    message: "Step executed successfully".to_string(),
+                    code: None,
+                    details: None,
+                }),
+                Err(e) => Ok(StepExecutionResult {
+            success: false,
+                    data: None,
+                    message: format!("Failed to execute step: {}", e),
+                    code: None,
+                    details: None,
+                }.with_error(&e)),
+            }
+        }
+        Err(e) => Ok(StepExecutionResult {
+            success: false,
+            data: None,
+            message: format!("Failed to get bridge: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e)),
+    }
+}
+
+/// Execute a job with context via N-API (synchronous version)
+#[napi]
+pub fn execute_job(job_json: String, db_path: String) -> JobExecutionResult {
+    log::info!("Executing job with context: {}", job_json);
+    
+    // Parse job JSON first
+    let job: Job = match serde_json::from_str(&job_json) {
+        Ok(job) => job,
+        Err(e) => {
+            return JobExecutionResult {
+                success: false,
+                job_id: None,
+                run_id: None,
+                step_id: None,
+                context: None,
+                result: None,
+                message: format!("Failed to parse job JSON: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&CoreError::Serialization(e));
+        }
+    };
+    
+    // Use shared bridge instance
+    match get_shared_bridge(&db_path) {
+        Ok(bridge) => {
+    match bridge.execute_job(&job) {
+        Ok(result_json) => {
+            let result: serde_json::Value = match serde_json::from_str(&result_json) {
+                Ok(result) => result,
+                Err(e) => {
+                    return JobExecutionResult {
+                        success: false,
+                        job_id: None,
+                        run_id: None,
+                        step_id: None,
+                        context: None,
+                        result: None,
+                        message: "Failed to parse execution result".to_string(),
+                        code: None,
+                        details: None,
+                    }.with_error(&CoreError::Serialization(e));
+                }
+            };
+            
+            JobExecutionResult {
+                success: true,
+                job_id: result["job_id"].as_str().map(|s| s.to_string()),
+                run_id: result["run_id"].as_str().map(|s| s.to_string()),
+                step_id: result["step_id"].as_str().map(|s| s.to_string()),
+                context: result["context"].as_str().map(|s| s.to_string()),
+                result: Some(result_json),
+                        message: "Job executed successfully".to_string(),
+                        code: None,
+                        details: None,
+            }
+        }
+        Err(e) => {
+            JobExecutionResult {
+                success: false,
+                job_id: None,
+                run_id: None,
+                step_id: None,
+                context: None,
+                result: None,
+                message: format!("Failed to execute job: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)
+        }
+    }
+}
+        Err(e) => {
+            JobExecutionResult {
+                success: false,
+                job_id: None,
+                run_id: None,
+                step_id: None,
+                context: None,
+                result: None,
+                message: format!("Failed to get bridge: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)
+        }
+    }
+}
+
+/// Execute a job with context via N-API (async version) - Task 2.1.4
+#[napi(ts_return_type = "Promise<JobExecutionResult>")]
+pub async fn execute_job_async(job_json: String, db_path: String) -> napi::Result<JobExecutionResult> {
+    log::info!("Executing job with context (async): {}", job_json);
+    
+    match get_shared_async_bridge(&db_path).await {
+        Ok(bridge) => {
+            match bridge.execute_job(&job_json).await {
+        Ok(result_json) => {
+            let result: serde_json::Value = match serde_json::from_str(&result_json) {
+                Ok(result) => result,
+                Err(e) => {
+                            return Ok(JobExecutionResult {
+                        success: false,
+                        job_id: None,
+                        run_id: None,
+                        step_id: None,
+                        context: None,
+                        result: None,
+                        message: "Failed to parse execution result".to_string(),
+                        code: None,
+                        details: None,
+                            }.with_error(&CoreError::Serialization(e)));
+                }
+            };
+            
+                    Ok(JobExecutionResult {
+                success: true,
+                job_id: result["job_id"].as_str().map(|s| s.to_string()),
+                run_id: result["run_id"].as_str().map(|s| s.to_string()),
+                step_id: result["step_id"].as_str().map(|s| s.to_string()),
+                context: result["context"].as_str().map(|s| s.to_string()),
+                result: Some(result_json),
+                message: "Job executed successfully".to_string(),
+                code: None,
+                details: None,
+                    })
+            }
+                Err(e) => Ok(JobExecutionResult {
+                success: false,
+                job_id: None,
+                run_id: None,
+                step_id: None,
+                context: None,
+                result: None,
+                message: format!("Failed to execute job: {}", e),
+                code: None,
+                details: None,
+                }.with_error(&e)),
+            }
+        }
+        Err(e) => Ok(JobExecutionResult {
+            success: false,
+            job_id: None,
+            run_id: None,
+            step_id: None,
+            context: None,
+            result: None,
+            message: format!("Failed to get bridge: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e)),
+    }
+}
+
+/// Get job status via N-API
+#[napi]
+pub fn get_job_status(job_id: String, db_path: String) -> JobStatusResult {
+    log::info!("Getting job status for: {}", job_id);
+    
+    match get_shared_bridge(&db_path) {
+        Ok(bridge) => {
+    match block_on_napi(bridge.get_job_status(&job_id)) {
+        Ok(status) => {
+            let status_str = match status {
+                Some(s) => format!("{:?}", s),
+                None => "not_found".to_string(),
+            };
+            
+            JobStatusResult {
+                success: true,
+                        id: Some(job_id),
+                        data: Some(status_str),
+                message: "Job status retrieved successfully".to_string(),
+                code: None,
+                details: None,
+            }
+        }
+        Err(e) => {
+            JobStatusResult {
+                success: false,
+                        id: None,
+                        data: None,
+                message: format!("Failed to get job status: {}", e),
+                code: None,
+                details: None,
+                    }.with_error(&e)
+                }
+            }
+        }
+        Err(e) => {
+            JobStatusResult {
+                success: false,
+                id: None,
+                data: None,
+                message: format!("Failed to get bridge: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)
+        }
+    }
+}
+
+/// Get the SQLite settings in effect on the engine database via N-API
+#[napi]
+pub fn get_database_settings(db_path: String) -> DatabaseSettingsResult {
+    with_shared_bridge!(
+        &db_path,
+        |settings_json: String| DatabaseSettingsResult {
+            success: true,
+            data: Some(settings_json),
+            message: "Database settings retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| DatabaseSettingsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_database_settings()
+    )
+}
+
+/// Back up the engine database to a new SQLite file via N-API
+#[napi]
+pub fn create_snapshot(path: String, db_path: String) -> SnapshotResult {
+    with_shared_bridge!(
+        &db_path,
+        |snapshot_json: String| SnapshotResult {
+            success: true,
+            data: Some(snapshot_json),
+            message: "Snapshot created successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SnapshotResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.create_snapshot(&path)
+    )
+}
+
+/// Restore the engine database from a snapshot via N-API; the engine must be paused
+#[napi]
+pub fn restore_snapshot(path: String, db_path: String) -> SnapshotResult {
+    with_shared_bridge!(
+        &db_path,
+        |snapshot_json: String| SnapshotResult {
+            success: true,
+            data: Some(snapshot_json),
+            message: "Snapshot restored successfully; resume the engine to continue".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SnapshotResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.restore_snapshot(&path)
+    )
+}
+
+/// Get the schema version of the engine database via N-API
+///
+/// Reports the latest migration applied, the latest one this engine knows and
+/// every migration with when it was applied.
+#[napi]
+pub fn get_schema_version(db_path: String) -> SchemaVersionResult {
+    with_shared_bridge!(
+        &db_path,
+        |version_json: String| SchemaVersionResult {
+            success: true,
+            data: Some(version_json),
+            message: "Schema version retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SchemaVersionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_schema_version()
+    )
+}
+
+/// Get the diagnostics of a dispatcher worker via N-API
+#[napi]
+pub fn get_worker_details(worker_id: String, db_path: String) -> WorkerDetailsResult {
+    with_shared_bridge!(
+        &db_path,
+        |diagnostics_json: String| WorkerDetailsResult {
+            success: true,
+            data: Some(diagnostics_json),
+            message: "Worker details retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkerDetailsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_worker_details(&worker_id))
+    )
+}
+
+/// List the diagnostics of every dispatcher worker via N-API
+#[napi]
+pub fn list_workers(db_path: String) -> WorkerListResult {
+    with_shared_bridge!(
+        &db_path,
+        |workers_json: String| WorkerListResult {
+            success: true,
+            data: Some(workers_json),
+            message: "Workers listed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkerListResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.list_workers())
+    )
+}
+
+/// List the queued and running jobs via N-API, optionally of one state and workflow
+#[napi]
+pub fn list_jobs(status: Option<String>, workflow_id: Option<String>, limit: Option<u32>, db_path: String) -> JobListResult {
+    with_shared_bridge!(
+        &db_path,
+        |jobs_json: String| JobListResult {
+            success: true,
+            data: Some(jobs_json),
+            message: "Jobs listed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| JobListResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.list_jobs(status.as_deref(), workflow_id.as_deref(), limit))
+    )
+}
+
+/// Get a queued or running job via N-API
+#[napi]
+pub fn get_job(job_id: String, db_path: String) -> JobDetailsResult {
+    with_shared_bridge!(
+        &db_path,
+        |job_json: String| JobDetailsResult {
+            success: true,
+            data: Some(job_json),
+            message: "Job retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| JobDetailsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_job(&job_id))
+    )
+}
+
+/// Report that a long-running step is still making progress via N-API
+///
+/// Once a step sends heartbeats it is timed out by their age instead of its
+/// running time, so call this more often than the heartbeat timeout.
+#[napi]
+pub fn report_step_heartbeat(run_id: String, step_id: String, db_path: String) -> StepHeartbeatResult {
+    with_shared_bridge!(
+        &db_path,
+        |_| StepHeartbeatResult {
+            success: true,
+            message: "Step heartbeat recorded".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepHeartbeatResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.report_step_heartbeat(&run_id, &step_id))
+    )
+}
+
+/// Get the engine feature flags via N-API, evaluated for a workflow when given
+#[napi]
+pub fn get_feature_flags(workflow_id: Option<String>, db_path: String) -> FeatureFlagsResult {
+    with_shared_bridge!(
+        &db_path,
+        |flags_json: String| FeatureFlagsResult {
+            success: true,
+            data: Some(flags_json),
+            message: "Feature flags retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| FeatureFlagsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_feature_flags(workflow_id.as_deref())
+    )
+}
+
+/// Set the faults injected into jobs via N-API, for testing retry and hook logic
+///
+/// `rules_json` sets `failure_rate`, `latency_rate` with `latency_ms`,
+/// `drop_rate` and `timeout_rate`, optionally limited to a `workflow_id` and
+/// `step_id` and with a `seed` of their own. Requires `CRONFLOW_CHAOS=true`.
+#[napi]
+pub fn set_chaos_rules(rules_json: String, db_path: String) -> ChaosResult {
+    with_shared_bridge!(
+        &db_path,
+        |status_json: String| ChaosResult {
+            success: true,
+            data: Some(status_json),
+            message: "Chaos rules set".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| ChaosResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.set_chaos_rules(&rules_json))
+    )
+}
+
+/// Stop injecting faults into jobs via N-API
+#[napi]
+pub fn clear_chaos_rules(db_path: String) -> ChaosResult {
     with_shared_bridge!(
         &db_path,
-        |result: String| StepExecutionResult {
+        |status_json: String| ChaosResult {
             success: true,
-            data: Some(result),
-            message: "Step executed successfully".to_string(),
+            data: Some(status_json),
+            message: "Chaos rules cleared".to_string(),
+            code: None,
+            details: None,
         },
-        |msg: String| StepExecutionResult {
+        |msg: String| ChaosResult {
             success: false,
             data: None,
             message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.execute_step(&run_id, &step_id)
+        |bridge: Arc<Bridge>| block_on_napi(bridge.clear_chaos_rules())
     )
 }
 
-/// Execute a step via N-API (async version) - Task 2.1.4
-#[napi(ts_return_type = "Promise<StepExecutionResult>")]
-pub async fn execute_step_async(run_id: String, step_id: String, db_path: String) -> napi::Result<StepExecutionResult> {
-    match get_shared_async_bridge(&db_path).await {
-        Ok(bridge) => {
-            match bridge.execute_step(&run_id, &step_id).await {
-                Ok(result) => Ok(StepExecutionResult {
-        success: true,
-                    data: Some(result),
-                    message: "Step executed successfully".to_string(),
-                }),
-                Err(e) => Ok(StepExecutionResult {
+/// Get the failure injection rules and the faults injected so far via N-API
+#[napi]
+pub fn get_chaos_status(db_path: String) -> ChaosResult {
+    with_shared_bridge!(
+        &db_path,
+        |status_json: String| ChaosResult {
+            success: true,
+            data: Some(status_json),
+            message: "Chaos status retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| ChaosResult {
             success: false,
-                    data: None,
-                    message: format!("Failed to execute step: {}", e),
-                }),
-            }
-        }
-        Err(e) => Ok(StepExecutionResult {
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_chaos_status())
+    )
+}
+
+/// Get a workflow's resource usage within its quota window via N-API
+#[napi]
+pub fn get_workflow_resource_usage(workflow_id: String, db_path: String) -> ResourceUsageResult {
+    with_shared_bridge!(
+        &db_path,
+        |usage_json: String| ResourceUsageResult {
+            success: true,
+            data: Some(usage_json),
+            message: "Workflow resource usage retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| ResourceUsageResult {
             success: false,
             data: None,
-            message: format!("Failed to get bridge: {}", e),
-        }),
-    }
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_workflow_resource_usage(&workflow_id))
+    )
 }
 
-/// Execute a job with context via N-API (synchronous version)
+/// Get the worker allocation and load of the dispatcher lanes via N-API
 #[napi]
-pub fn execute_job(job_json: String, db_path: String) -> JobExecutionResult {
-    log::info!("Executing job with context: {}", job_json);
-    
-    // Parse job JSON first
-    let job: Job = match serde_json::from_str(&job_json) {
-        Ok(job) => job,
-        Err(e) => {
-            return JobExecutionResult {
-                success: false,
-                job_id: None,
-                run_id: None,
-                step_id: None,
-                context: None,
-                result: None,
-                message: format!("Failed to parse job JSON: {}", e),
-            };
-        }
-    };
-    
-    // Use shared bridge instance
-    match get_shared_bridge(&db_path) {
-        Ok(bridge) => {
-    match bridge.execute_job(&job) {
-        Ok(result_json) => {
-            let result: serde_json::Value = match serde_json::from_str(&result_json) {
-                Ok(result) => result,
-                Err(_) => {
-                    return JobExecutionResult {
-                        success: false,
-                        job_id: None,
-                        run_id: None,
-                        step_id: None,
-                        context: None,
-                        result: None,
-                        message: "Failed to parse execution result".to_string(),
-                    };
-                }
-            };
-            
-            JobExecutionResult {
-                success: true,
-                job_id: result["job_id"].as_str().map(|s| s.to_string()),
-                run_id: result["run_id"].as_str().map(|s| s.to_string()),
-                step_id: result["step_id"].as_str().map(|s| s.to_string()),
-                context: result["context"].as_str().map(|s| s.to_string()),
-                result: Some(result_json),
-                        message: "Job executed successfully".to_string(),
-            }
-        }
-        Err(e) => {
-            JobExecutionResult {
-                success: false,
-                job_id: None,
-                run_id: None,
-                step_id: None,
-                context: None,
-                result: None,
-                message: format!("Failed to execute job: {}", e),
-            }
-        }
+pub fn get_lane_status(db_path: String) -> LaneStatusResult {
+    with_shared_bridge!(
+        &db_path,
+        |lanes_json: String| LaneStatusResult {
+            success: true,
+            data: Some(lanes_json),
+            message: "Lane status retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| LaneStatusResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_lane_status())
+    )
+}
+
+/// Register the capability tags of a worker via N-API
+#[napi]
+pub fn register_worker_tags(worker_id: String, tags: Vec<String>, db_path: String) -> WorkerTagsResult {
+    with_shared_bridge!(
+        &db_path,
+        |tags_json: String| WorkerTagsResult {
+            success: true,
+            data: Some(tags_json),
+            message: "Worker tags registered successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkerTagsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.register_worker_tags(&worker_id, tags))
+    )
+}
+
+/// Get the workers and queue time of each worker capability tag via N-API
+#[napi]
+pub fn get_worker_tag_status(db_path: String) -> WorkerTagsResult {
+    with_shared_bridge!(
+        &db_path,
+        |tags_json: String| WorkerTagsResult {
+            success: true,
+            data: Some(tags_json),
+            message: "Worker tag status retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkerTagsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_worker_tag_status())
+    )
+}
+
+/// Set the log level of a module, or the default level without one, via N-API
+#[napi]
+pub fn set_log_level(module: Option<String>, level: String) -> LoggingResult {
+    let status = crate::logging::set_log_level(module.as_deref(), &level)
+        .and_then(|status| Ok(serde_json::to_string(&status)?));
+    match status {
+        Ok(data) => LoggingResult {
+            success: true,
+            data: Some(data),
+            message: format!("Log level of {} set to {}", module.as_deref().unwrap_or("the engine"), level),
+            code: None,
+            details: None,
+        },
+        Err(e) => LoggingResult {
+            success: false,
+            data: None,
+            message: format!("Failed to set log level: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e),
     }
 }
-        Err(e) => {
-            JobExecutionResult {
-                success: false,
-                job_id: None,
-                run_id: None,
-                step_id: None,
-                context: None,
-                result: None,
-                message: format!("Failed to get bridge: {}", e),
-            }
-        }
+
+/// Get the log levels, format and file of the engine logger via N-API
+#[napi]
+pub fn get_logging_status() -> LoggingResult {
+    let status = crate::logging::status()
+        .and_then(|status| Ok(serde_json::to_string(&status)?));
+    match status {
+        Ok(data) => LoggingResult {
+            success: true,
+            data: Some(data),
+            message: "Logging status retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        Err(e) => LoggingResult {
+            success: false,
+            data: None,
+            message: format!("Failed to get logging status: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e),
     }
 }
 
-/// Execute a job with context via N-API (async version) - Task 2.1.4
-#[napi(ts_return_type = "Promise<JobExecutionResult>")]
-pub async fn execute_job_async(job_json: String, db_path: String) -> napi::Result<JobExecutionResult> {
-    log::info!("Executing job with context (async): {}", job_json);
-    
-    match get_shared_async_bridge(&db_path).await {
-        Ok(bridge) => {
-            match bridge.execute_job(&job_json).await {
-        Ok(result_json) => {
-            let result: serde_json::Value = match serde_json::from_str(&result_json) {
-                Ok(result) => result,
-                Err(_) => {
-                            return Ok(JobExecutionResult {
-                        success: false,
-                        job_id: None,
-                        run_id: None,
-                        step_id: None,
-                        context: None,
-                        result: None,
-                        message: "Failed to parse execution result".to_string(),
-                            });
-                }
-            };
-            
-                    Ok(JobExecutionResult {
-                success: true,
-                job_id: result["job_id"].as_str().map(|s| s.to_string()),
-                run_id: result["run_id"].as_str().map(|s| s.to_string()),
-                step_id: result["step_id"].as_str().map(|s| s.to_string()),
-                context: result["context"].as_str().map(|s| s.to_string()),
-                result: Some(result_json),
-                message: "Job executed successfully".to_string(),
-                    })
-            }
-                Err(e) => Ok(JobExecutionResult {
-                success: false,
-                job_id: None,
-                run_id: None,
-                step_id: None,
-                context: None,
-                result: None,
-                message: format!("Failed to execute job: {}", e),
-                }),
-            }
-        }
-        Err(e) => Ok(JobExecutionResult {
+/// Get a value from the context state via N-API
+#[napi]
+pub fn state_get(run_id: String, scope: String, key: String, db_path: String) -> StateGetResult {
+    with_shared_bridge!(
+        &db_path,
+        |value_json: String| StateGetResult {
+            success: true,
+            data: Some(value_json),
+            message: "State value retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StateGetResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.state_get(&run_id, &scope, &key)
+    )
+}
+
+/// Set a value in the context state via N-API
+#[napi]
+pub fn state_set(run_id: String, scope: String, key: String, value_json: String, ttl_secs: Option<u32>, db_path: String) -> StateSetResult {
+    with_shared_bridge!(
+        &db_path,
+        |_| StateSetResult {
+            success: true,
+            message: "State value set successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StateSetResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.state_set(&run_id, &scope, &key, &value_json, ttl_secs.map(u64::from))
+    )
+}
+
+/// Increment an integer in the context state via N-API
+#[napi]
+pub fn state_incr(run_id: String, scope: String, key: String, by: Option<i64>, ttl_secs: Option<u32>, db_path: String) -> StateIncrResult {
+    with_shared_bridge!(
+        &db_path,
+        |value: String| StateIncrResult {
+            success: true,
+            data: Some(value),
+            message: "State value incremented successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StateIncrResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.state_incr(&run_id, &scope, &key, by.unwrap_or(1), ttl_secs.map(u64::from))
+    )
+}
+
+/// Store an encrypted secret via N-API
+#[napi]
+pub fn set_secret(key: String, value: String, scope: Option<String>, db_path: String) -> SecretResult {
+    with_shared_bridge!(
+        &db_path,
+        |secret_json: String| SecretResult {
+            success: true,
+            data: Some(secret_json),
+            message: "Secret stored successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SecretResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.set_secret(&key, &value, scope.as_deref())
+    )
+}
+
+/// Delete a secret via N-API
+#[napi]
+pub fn delete_secret(key: String, scope: Option<String>, db_path: String) -> SecretDeletionResult {
+    with_shared_bridge!(
+        &db_path,
+        |result: String| SecretDeletionResult {
+            success: true,
+            data: Some(result),
+            message: "Secret deletion processed".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SecretDeletionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.delete_secret(&key, scope.as_deref())
+    )
+}
+
+/// Execute a single step outside any run via N-API
+#[napi]
+pub fn execute_adhoc_step(workflow_id: String, step_id: String, payload_json: String, db_path: String) -> AdhocStepExecutionResult {
+    with_shared_bridge!(
+        &db_path,
+        |result: String| AdhocStepExecutionResult {
+            success: true,
+            data: Some(result),
+            message: "Ad hoc step executed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| AdhocStepExecutionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.execute_adhoc_step(&workflow_id, &step_id, &payload_json)
+    )
+}
+
+/// Get the ad hoc step executions of a workflow via N-API
+#[napi]
+pub fn get_adhoc_step_executions(workflow_id: String, db_path: String) -> AdhocStepExecutionsResult {
+    with_shared_bridge!(
+        &db_path,
+        |result: String| AdhocStepExecutionsResult {
+            success: true,
+            data: Some(result),
+            message: "Ad hoc step executions retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| AdhocStepExecutionsResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_adhoc_step_executions(&workflow_id)
+    )
+}
+
+/// Cancel a run and its in-flight jobs via N-API
+#[napi]
+pub fn cancel_run(run_id: String, reason: Option<String>, db_path: String) -> RunCancellationResult {
+    with_shared_bridge!(
+        &db_path,
+        |result: String| RunCancellationResult {
+            success: true,
+            data: Some(result),
+            message: "Run cancelled successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunCancellationResult {
             success: false,
-            job_id: None,
-            run_id: None,
-            step_id: None,
-            context: None,
-            result: None,
-            message: format!("Failed to get bridge: {}", e),
-        }),
-    }
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.cancel_run(&run_id, reason.as_deref()))
+    )
 }
 
-/// Get job status via N-API
+/// Cancel a workflow's unfinished runs in bulk via N-API
 #[napi]
-pub fn get_job_status(job_id: String, db_path: String) -> JobStatusResult {
-    log::info!("Getting job status for: {}", job_id);
-    
-    match get_shared_bridge(&db_path) {
-        Ok(bridge) => {
-    match bridge.get_job_status(&job_id) {
-        Ok(status) => {
-            let status_str = match status {
-                Some(s) => format!("{:?}", s),
-                None => "not_found".to_string(),
-            };
-            
-            JobStatusResult {
-                success: true,
-                        id: Some(job_id),
-                        data: Some(status_str),
-                message: "Job status retrieved successfully".to_string(),
-            }
-        }
-        Err(e) => {
-            JobStatusResult {
-                success: false,
-                        id: None,
-                        data: None,
-                message: format!("Failed to get job status: {}", e),
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            JobStatusResult {
-                success: false,
-                id: None,
-                data: None,
-                message: format!("Failed to get bridge: {}", e),
-            }
-        }
-    }
+pub fn cancel_runs(workflow_id: String, status: Option<String>, before: Option<String>, reason: Option<String>, db_path: String) -> BulkRunResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| BulkRunResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Runs cancelled successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| BulkRunResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.cancel_runs(&workflow_id, status.as_deref(), before.as_deref(), reason.as_deref()))
+    )
+}
+
+/// Retry a workflow's failed runs in bulk via N-API
+#[napi]
+pub fn retry_failed_runs(workflow_id: String, since: Option<String>, db_path: String) -> BulkRunResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| BulkRunResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Failed runs retried successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| BulkRunResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.retry_failed_runs(&workflow_id, since.as_deref())
+    )
 }
 
 /// Cancel a job via N-API
@@ -1221,7 +4407,7 @@ pub fn cancel_job(job_id: String, db_path: String) -> JobCancellationResult {
     
     match get_shared_bridge(&db_path) {
         Ok(bridge) => {
-    match bridge.cancel_job(&job_id) {
+    match block_on_napi(bridge.cancel_job(&job_id)) {
         Ok(cancelled) => {
             JobCancellationResult {
                 success: true,
@@ -1232,6 +4418,8 @@ pub fn cancel_job(job_id: String, db_path: String) -> JobCancellationResult {
                 } else {
                     "Job not found or already completed".to_string()
                 },
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
@@ -1240,7 +4428,9 @@ pub fn cancel_job(job_id: String, db_path: String) -> JobCancellationResult {
                 job_id: None,
                 cancelled: false,
                 message: format!("Failed to cancel job: {}", e),
-                    }
+                code: None,
+                details: None,
+                    }.with_error(&e)
                 }
             }
         }
@@ -1250,7 +4440,9 @@ pub fn cancel_job(job_id: String, db_path: String) -> JobCancellationResult {
                 job_id: None,
                 cancelled: false,
                 message: format!("Failed to get bridge: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1262,7 +4454,7 @@ pub fn get_dispatcher_stats(db_path: String) -> DispatcherStatsResult {
     
     match get_shared_bridge(&db_path) {
         Ok(bridge) => {
-    match bridge.get_dispatcher_stats() {
+    match block_on_napi(bridge.get_dispatcher_stats()) {
         Ok(stats) => {
             let stats_json = serde_json::to_string(&stats)
                 .unwrap_or_else(|_| "{}".to_string());
@@ -1271,6 +4463,8 @@ pub fn get_dispatcher_stats(db_path: String) -> DispatcherStatsResult {
                 success: true,
                         data: Some(stats_json),
                 message: "Dispatcher statistics retrieved successfully".to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
@@ -1278,7 +4472,9 @@ pub fn get_dispatcher_stats(db_path: String) -> DispatcherStatsResult {
                 success: false,
                         data: None,
                 message: format!("Failed to get dispatcher stats: {}", e),
-                    }
+                code: None,
+                details: None,
+                    }.with_error(&e)
                 }
             }
         }
@@ -1287,7 +4483,9 @@ pub fn get_dispatcher_stats(db_path: String) -> DispatcherStatsResult {
                 success: false,
                 data: None,
                 message: format!("Failed to get bridge: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1299,7 +4497,7 @@ pub fn get_workflow_run_status(run_id: String, db_path: String) -> WorkflowRunSt
     
     match get_shared_bridge(&db_path) {
         Ok(bridge) => {
-    match bridge.get_workflow_run_status(&run_id) {
+    match block_on_napi(bridge.get_workflow_run_status(&run_id)) {
         Ok(status) => {
             let status_str = match status {
                 Some(s) => format!("{:?}", s),
@@ -1311,6 +4509,8 @@ pub fn get_workflow_run_status(run_id: String, db_path: String) -> WorkflowRunSt
                         id: Some(run_id),
                         data: Some(status_str),
                 message: "Workflow run status retrieved successfully".to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
@@ -1319,7 +4519,9 @@ pub fn get_workflow_run_status(run_id: String, db_path: String) -> WorkflowRunSt
                         id: None,
                         data: None,
                 message: format!("Failed to get workflow run status: {}", e),
-                    }
+                code: None,
+                details: None,
+                    }.with_error(&e)
                 }
             }
         }
@@ -1329,7 +4531,9 @@ pub fn get_workflow_run_status(run_id: String, db_path: String) -> WorkflowRunSt
                 id: None,
                 data: None,
                 message: format!("Failed to get bridge: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1341,7 +4545,7 @@ pub fn get_workflow_completed_steps(run_id: String, db_path: String) -> Workflow
     
     match get_shared_bridge(&db_path) {
         Ok(bridge) => {
-    match bridge.get_workflow_completed_steps(&run_id) {
+    match block_on_napi(bridge.get_workflow_completed_steps(&run_id)) {
         Ok(steps) => {
             let steps_json = serde_json::to_string(&steps)
                 .unwrap_or_else(|_| "[]".to_string());
@@ -1351,6 +4555,8 @@ pub fn get_workflow_completed_steps(run_id: String, db_path: String) -> Workflow
                         id: Some(run_id),
                         data: Some(steps_json),
                 message: "Workflow completed steps retrieved successfully".to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
@@ -1359,7 +4565,9 @@ pub fn get_workflow_completed_steps(run_id: String, db_path: String) -> Workflow
                         id: None,
                         data: None,
                 message: format!("Failed to get workflow completed steps: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1369,7 +4577,9 @@ pub fn get_workflow_completed_steps(run_id: String, db_path: String) -> Workflow
                 id: None,
                 data: None,
                 message: format!("Failed to get bridge: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1379,25 +4589,29 @@ pub fn get_workflow_completed_steps(run_id: String, db_path: String) -> Workflow
 pub fn execute_webhook_trigger(request_json: String, db_path: String) -> TriggerExecutionResult {
     match get_shared_bridge(&db_path) {
         Ok(bridge) => {
-    match bridge.execute_webhook_trigger(&request_json) {
+    match block_on_napi(bridge.execute_webhook_trigger(&request_json)) {
         Ok(result_json) => {
             let result: serde_json::Value = match serde_json::from_str(&result_json) {
                 Ok(result) => result,
-                Err(_) => {
+                Err(e) => {
                     return TriggerExecutionResult {
                         success: false,
                         run_id: None,
                         workflow_id: None,
                         message: "Failed to parse execution result".to_string(),
-                    };
+                        code: None,
+                        details: None,
+                    }.with_error(&CoreError::Serialization(e));
                 }
             };
             
             TriggerExecutionResult {
-                success: true,
+                success: result["success"].as_bool().unwrap_or(true),
                 run_id: result["run_id"].as_str().map(|s| s.to_string()),
                 workflow_id: result["workflow_id"].as_str().map(|s| s.to_string()),
                 message: result["message"].as_str().unwrap_or("Webhook trigger executed successfully").to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
@@ -1406,7 +4620,9 @@ pub fn execute_webhook_trigger(request_json: String, db_path: String) -> Trigger
                 run_id: None,
                 workflow_id: None,
                 message: format!("Failed to execute webhook trigger: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1416,7 +4632,9 @@ pub fn execute_webhook_trigger(request_json: String, db_path: String) -> Trigger
                 run_id: None,
                 workflow_id: None,
                 message: format!("Failed to get bridge: {}", e),
-        }
+                code: None,
+                details: None,
+        }.with_error(&e)
         }
     }
 }
@@ -1426,25 +4644,29 @@ pub fn execute_webhook_trigger(request_json: String, db_path: String) -> Trigger
 pub fn execute_manual_trigger(workflow_id: String, payload_json: String, db_path: String) -> TriggerExecutionResult {
     match get_shared_bridge(&db_path) {
         Ok(bridge) => {
-    match bridge.execute_manual_trigger(&workflow_id, &payload_json) {
+    match block_on_napi(bridge.execute_manual_trigger(&workflow_id, &payload_json)) {
         Ok(result_json) => {
             let result: serde_json::Value = match serde_json::from_str(&result_json) {
                 Ok(result) => result,
-                Err(_) => {
+                Err(e) => {
                     return TriggerExecutionResult {
                         success: false,
                         run_id: None,
                         workflow_id: None,
                         message: "Failed to parse execution result".to_string(),
-                    };
+                        code: None,
+                        details: None,
+                    }.with_error(&CoreError::Serialization(e));
                 }
             };
             
             TriggerExecutionResult {
-                success: true,
+                success: result["success"].as_bool().unwrap_or(true),
                 run_id: result["run_id"].as_str().map(|s| s.to_string()),
                 workflow_id: result["workflow_id"].as_str().map(|s| s.to_string()),
                 message: result["message"].as_str().unwrap_or("Manual trigger executed successfully").to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
@@ -1453,7 +4675,9 @@ pub fn execute_manual_trigger(workflow_id: String, payload_json: String, db_path
                 run_id: None,
                 workflow_id: None,
                 message: format!("Failed to execute manual trigger: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1463,11 +4687,285 @@ pub fn execute_manual_trigger(workflow_id: String, payload_json: String, db_path
                 run_id: None,
                 workflow_id: None,
                 message: format!("Failed to get bridge: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)
+        }
+    }
+}
+
+/// Execute a manual trigger via N-API, labelling the run it starts
+///
+/// `labels_json` is a JSON object of string labels, e.g. `{"customer": "acme"}`.
+#[napi]
+pub fn execute_labeled_manual_trigger(workflow_id: String, payload_json: String, labels_json: String, db_path: String) -> TriggerExecutionResult {
+    let executed = get_shared_bridge(&db_path)
+        .and_then(|bridge| block_on_napi(bridge.execute_labeled_manual_trigger(&workflow_id, &payload_json, &labels_json)))
+        .and_then(|result_json| Ok(serde_json::from_str::<serde_json::Value>(&result_json)?));
+    match executed {
+        Ok(result) => TriggerExecutionResult {
+            success: result["success"].as_bool().unwrap_or(true),
+            run_id: result["run_id"].as_str().map(|s| s.to_string()),
+            workflow_id: result["workflow_id"].as_str().map(|s| s.to_string()),
+            message: result["message"].as_str().unwrap_or("Manual trigger executed successfully").to_string(),
+            code: None,
+            details: None,
+        },
+        Err(e) => TriggerExecutionResult {
+            success: false,
+            run_id: None,
+            workflow_id: None,
+            message: format!("Failed to execute manual trigger: {}", e),
+            code: None,
+            details: None,
+        }.with_error(&e),
+    }
+}
+
+/// Execute a schedule trigger via N-API
+#[napi]
+pub fn execute_schedule_trigger(workflow_id: String, payload_json: String, db_path: String) -> TriggerExecutionResult {
+    match get_shared_bridge(&db_path) {
+        Ok(bridge) => {
+    match block_on_napi(bridge.execute_schedule_trigger(&workflow_id, &payload_json)) {
+        Ok(result_json) => {
+            let result: serde_json::Value = match serde_json::from_str(&result_json) {
+                Ok(result) => result,
+                Err(e) => {
+                    return TriggerExecutionResult {
+                        success: false,
+                        run_id: None,
+                        workflow_id: None,
+                        message: "Failed to parse execution result".to_string(),
+                        code: None,
+                        details: None,
+                    }.with_error(&CoreError::Serialization(e));
+                }
+            };
+            
+            TriggerExecutionResult {
+                success: result["success"].as_bool().unwrap_or(true),
+                run_id: result["run_id"].as_str().map(|s| s.to_string()),
+                workflow_id: result["workflow_id"].as_str().map(|s| s.to_string()),
+                message: result["message"].as_str().unwrap_or("Schedule trigger executed successfully").to_string(),
+                code: None,
+                details: None,
             }
         }
+        Err(e) => {
+            TriggerExecutionResult {
+                success: false,
+                run_id: None,
+                workflow_id: None,
+                message: format!("Failed to execute schedule trigger: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)
+        }
+    }
+}
+        Err(e) => {
+            TriggerExecutionResult {
+                success: false,
+                run_id: None,
+                workflow_id: None,
+                message: format!("Failed to get bridge: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&e)
+        }
     }
 }
 
+/// Fire due interval and one-time triggers and wake delayed runs via N-API
+#[napi]
+pub fn fire_due_schedules(db_path: String) -> ScheduleFiringResult {
+    with_shared_bridge!(
+        &db_path,
+        |firing_json: String| ScheduleFiringResult {
+            success: true,
+            data: Some(firing_json),
+            message: "Due schedules fired successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| ScheduleFiringResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.fire_due_schedules())
+    )
+}
+
+/// Start a backfill of a scheduled workflow over an RFC 3339 time range via N-API
+///
+/// Creates one run per firing of the workflow's schedule, or per
+/// `interval_secs` when given, with the firing's timestamp in the payload as
+/// `scheduled_at`. At most `max_concurrent` runs, 1 by default, are unfinished
+/// at once; the rest are created as `fire_due_schedules` is called.
+#[napi]
+pub fn backfill(workflow_id: String, from: String, to: String, interval_secs: Option<u32>, max_concurrent: Option<u32>, db_path: String) -> BackfillResult {
+    with_shared_bridge!(
+        &db_path,
+        |progress_json: String| BackfillResult {
+            success: true,
+            data: Some(progress_json),
+            message: "Backfill started successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| BackfillResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.backfill(&workflow_id, &from, &to, interval_secs.map(u64::from), max_concurrent))
+    )
+}
+
+/// Get the progress of a backfill via N-API
+#[napi]
+pub fn get_backfill(backfill_id: String, db_path: String) -> BackfillResult {
+    with_shared_bridge!(
+        &db_path,
+        |progress_json: String| BackfillResult {
+            success: true,
+            data: Some(progress_json),
+            message: "Backfill progress retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| BackfillResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_backfill(&backfill_id)
+    )
+}
+
+/// Stop a backfill from creating more runs via N-API, leaving the runs it created to finish
+#[napi]
+pub fn cancel_backfill(backfill_id: String, db_path: String) -> BackfillResult {
+    with_shared_bridge!(
+        &db_path,
+        |progress_json: String| BackfillResult {
+            success: true,
+            data: Some(progress_json),
+            message: "Backfill cancelled successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| BackfillResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.cancel_backfill(&backfill_id)
+    )
+}
+
+/// Register the JSON Schema of an event version via N-API
+#[napi]
+pub fn register_event_schema(event_name: String, version: u32, schema_json: String, db_path: String) -> EventSchemaResult {
+    with_shared_bridge!(
+        &db_path,
+        |schema_json: String| EventSchemaResult {
+            success: true,
+            data: Some(schema_json),
+            message: "Event schema registered successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EventSchemaResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.register_event_schema(&event_name, version, &schema_json)
+    )
+}
+
+/// Get the registered schemas of an event via N-API
+#[napi]
+pub fn get_event_schemas(event_name: String, db_path: String) -> EventSchemaResult {
+    with_shared_bridge!(
+        &db_path,
+        |schemas_json: String| EventSchemaResult {
+            success: true,
+            data: Some(schemas_json),
+            message: "Event schemas retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EventSchemaResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_event_schemas(&event_name)
+    )
+}
+
+/// Publish an event to its subscribed workflows via N-API
+#[napi]
+pub fn publish_event(event_name: String, version: Option<u32>, payload_json: String, db_path: String) -> EventPublicationResult {
+    with_shared_bridge!(
+        &db_path,
+        |publication_json: String| EventPublicationResult {
+            success: true,
+            data: Some(publication_json),
+            message: "Event published successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EventPublicationResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.publish_event(&event_name, version, &payload_json))
+    )
+}
+
+/// Report the stored records this engine cannot read via N-API
+#[napi]
+pub fn verify_store_compatibility(db_path: String) -> StoreCompatibilityResult {
+    with_shared_bridge!(
+        &db_path,
+        |report_json: String| StoreCompatibilityResult {
+            success: true,
+            data: Some(report_json),
+            message: "Store compatibility verified successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StoreCompatibilityResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.verify_store_compatibility()
+    )
+}
+
 /// Get trigger statistics via N-API
 #[napi]
 pub fn get_trigger_stats(db_path: String) -> TriggerStatsResult {
@@ -1477,13 +4975,40 @@ pub fn get_trigger_stats(db_path: String) -> TriggerStatsResult {
                 success: true,
             data: Some(stats_json),
                 message: "Trigger statistics retrieved successfully".to_string(),
+                code: None,
+                details: None,
         },
         |msg: String| TriggerStatsResult {
                 success: false,
             data: None,
             message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_trigger_stats())
+    )
+}
+
+/// List the trigger firings of a workflow suppressed as duplicates via N-API
+#[napi]
+pub fn list_suppressed_triggers(workflow_id: String, limit: Option<u32>, db_path: String) -> SuppressedTriggersResult {
+    with_shared_bridge!(
+        &db_path,
+        |suppressed_json: String| SuppressedTriggersResult {
+            success: true,
+            data: Some(suppressed_json),
+            message: "Suppressed triggers retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| SuppressedTriggersResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.get_trigger_stats()
+        |bridge: Arc<Bridge>| bridge.get_suppressed_triggers(&workflow_id, limit)
     )
 }
 
@@ -1496,13 +5021,17 @@ pub fn get_workflow_triggers(workflow_id: String, db_path: String) -> WorkflowTr
                 success: true,
             data: Some(triggers_json),
                 message: "Workflow triggers retrieved successfully".to_string(),
+                code: None,
+                details: None,
         },
         |msg: String| WorkflowTriggersResult {
                 success: false,
             data: None,
             message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.get_workflow_triggers(&workflow_id)
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_workflow_triggers(&workflow_id))
     )
 }
 
@@ -1514,12 +5043,16 @@ pub fn unregister_workflow_triggers(workflow_id: String, db_path: String) -> Tri
         |_| TriggerUnregistrationResult {
                 success: true,
                 message: format!("Successfully unregistered triggers for workflow: {}", workflow_id),
+                code: None,
+                details: None,
         },
         |msg: String| TriggerUnregistrationResult {
                 success: false,
             message: msg,
+            code: None,
+            details: None,
         },
-        |bridge: Arc<Bridge>| bridge.unregister_workflow_triggers(&workflow_id)
+        |bridge: Arc<Bridge>| block_on_napi(bridge.unregister_workflow_triggers(&workflow_id))
     )
 } 
 
@@ -1535,13 +5068,17 @@ pub fn start_webhook_server(db_path: String) -> WebhookServerResult {
             WebhookServerResult {
                 success: true,
                 message: "Webhook server started successfully".to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
             WebhookServerResult {
                 success: false,
                 message: format!("Failed to start webhook server: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1549,7 +5086,9 @@ pub fn start_webhook_server(db_path: String) -> WebhookServerResult {
             WebhookServerResult {
                 success: false,
                 message: format!("Failed to get bridge: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1561,18 +5100,22 @@ pub fn stop_webhook_server(db_path: String) -> WebhookServerResult {
         Ok(bridge) => {
             // Note: stop_webhook_server doesn't actually mutate the bridge
             // The Arc allows interior mutability where needed
-    match bridge.stop_webhook_server() {
+    match block_on_napi(bridge.stop_webhook_server()) {
         Ok(_) => {
             WebhookServerResult {
                 success: true,
                 message: "Webhook server stopped successfully".to_string(),
+                code: None,
+                details: None,
             }
         }
         Err(e) => {
             WebhookServerResult {
                 success: false,
                 message: format!("Failed to stop webhook server: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1580,7 +5123,9 @@ pub fn stop_webhook_server(db_path: String) -> WebhookServerResult {
             WebhookServerResult {
                 success: false,
                 message: format!("Failed to get bridge: {}", e),
-            }
+                code: None,
+                details: None,
+            }.with_error(&e)
         }
     }
 }
@@ -1593,11 +5138,15 @@ pub fn execute_workflow_steps(run_id: String, workflow_id: String, db_path: Stri
                         success: true,
             data: Some(result),
                         message: "Workflow steps executed successfully".to_string(),
+                        code: None,
+                        details: None,
         },
         |msg: String| StepExecutionResult {
                         success: false,
             data: None,
             message: msg,
+            code: None,
+            details: None,
         },
         |bridge: Arc<Bridge>| bridge.execute_workflow_steps(&run_id, &workflow_id)
     )
@@ -1615,6 +5164,8 @@ pub fn execute_workflow_hook(hook_type: String, context_json: String, workflow_i
                         workflow_id: Some(workflow_id),
                         result: Some(result),
                         message: "Hook executed successfully".to_string(),
+                        code: None,
+                        details: None,
                     }
                 }
                 Err(error) => {
@@ -1624,7 +5175,9 @@ pub fn execute_workflow_hook(hook_type: String, context_json: String, workflow_i
                         workflow_id: Some(workflow_id),
                         result: None,
                         message: format!("Failed to execute hook: {}", error),
-                    }
+                        code: None,
+                        details: None,
+                    }.with_error(&error)
                 }
             }
         }
@@ -1635,11 +5188,493 @@ pub fn execute_workflow_hook(hook_type: String, context_json: String, workflow_i
                 workflow_id: Some(workflow_id),
                 result: None,
                 message: format!("Failed to get bridge: {}", error),
-            }
+                code: None,
+                details: None,
+            }.with_error(&error)
         }
     }
 } 
 
+/// Persist a batch of step completions in one transaction via N-API
+#[napi]
+pub fn complete_steps_batch(results_json: String, db_path: String) -> StepBatchCompletionResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| StepBatchCompletionResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Step batch completed successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| StepBatchCompletionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.complete_steps_batch(&results_json)
+    )
+}
+
+/// Export aged run history to Parquet cold storage via N-API
+#[napi]
+pub fn export_cold_storage(db_path: String) -> ColdStorageExportResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| ColdStorageExportResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Run history exported to cold storage".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| ColdStorageExportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.export_cold_storage()
+    )
+}
+
+/// Export run history within a time range to Parquet cold storage via N-API
+#[napi]
+pub fn export_cold_storage_range(from: String, to: String, db_path: String) -> ColdStorageExportResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| ColdStorageExportResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Run history exported to cold storage".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| ColdStorageExportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.export_cold_storage_range(&from, &to)
+    )
+}
+
+/// Export a workflow's run history as JSON Lines or CSV via N-API
+///
+/// `callback` is called synchronously with each chunk of exported text, a
+/// page of runs at a time; the summary is returned once every run was exported.
+#[napi]
+pub fn export_runs(env: Env, workflow_id: String, since: Option<String>, format: String, callback: napi::JsFunction, db_path: String) -> RunExportResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| RunExportResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Run history exported successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunExportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| {
+            let summary = bridge.export_runs(&workflow_id, since.as_deref(), &format, |chunk| {
+                let chunk = env.create_string(chunk)
+                    .map_err(|e| CoreError::Internal(format!("Failed to pass an export chunk: {}", e)))?;
+                callback.call(None, &[chunk])
+                    .map_err(|e| CoreError::Internal(format!("Export callback failed: {}", e)))?;
+                Ok(())
+            })?;
+            serde_json::to_string(&summary).map_err(CoreError::from)
+        }
+    )
+}
+
+/// Export a workflow's run history to a JSON Lines or CSV file via N-API
+#[napi]
+pub fn export_runs_to_file(workflow_id: String, since: Option<String>, format: String, path: String, db_path: String) -> RunExportResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| RunExportResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Run history exported successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunExportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.export_runs_to_file(&workflow_id, since.as_deref(), &format, &path)
+    )
+}
+
+/// Delete finished runs that completed before a timestamp via N-API
+#[napi]
+pub fn purge_runs(before_timestamp: String, db_path: String) -> RunPurgeResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| RunPurgeResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Runs purged".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RunPurgeResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.purge_runs(&before_timestamp)
+    )
+}
+
+/// Get the report of the orphaned run recovery run at startup via N-API
+#[napi]
+pub fn get_recovery_report(db_path: String) -> RecoveryReportResult {
+    with_shared_bridge!(
+        &db_path,
+        |report_json: String| RecoveryReportResult {
+            success: true,
+            data: Some(report_json),
+            message: "Recovery report retrieved".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RecoveryReportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.get_recovery_report()
+    )
+}
+
+/// Shut the engine down via N-API, draining running jobs for up to `timeout_ms`
+#[napi]
+pub fn shutdown(timeout_ms: u32, db_path: String) -> EngineShutdownResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| EngineShutdownResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Engine shut down".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EngineShutdownResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.shutdown(timeout_ms as u64))
+    )
+}
+
+/// Pause the engine via N-API: workers stop dequeuing and trigger-created runs are buffered
+#[napi]
+pub fn pause_engine(db_path: String) -> EnginePauseResult {
+    with_shared_bridge!(
+        &db_path,
+        |_| EnginePauseResult {
+            success: true,
+            message: "Engine paused".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EnginePauseResult {
+            success: false,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.pause_engine())
+    )
+}
+
+/// Resume the engine via N-API, releasing buffered runs in order
+#[napi]
+pub fn resume_engine(db_path: String) -> EngineResumeResult {
+    with_shared_bridge!(
+        &db_path,
+        |summary_json: String| EngineResumeResult {
+            success: true,
+            data: Some(summary_json),
+            message: "Engine resumed".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EngineResumeResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.resume_engine())
+    )
+}
+
+/// Get the engine pause status via N-API
+#[napi]
+pub fn get_engine_status(db_path: String) -> EngineStatusResult {
+    with_shared_bridge!(
+        &db_path,
+        |status_json: String| EngineStatusResult {
+            success: true,
+            data: Some(status_json),
+            message: "Engine status retrieved successfully".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| EngineStatusResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.get_engine_status())
+    )
+}
+
+/// Subscribe to live run events via N-API
+///
+/// `callback` is called with each recorded event matching `filter_json`
+/// (`{"run_id": ..., "event_types": [...]}`, both optional) as a JSON string,
+/// e.g. step started/completed/failed and run completion, so the SDK does not
+/// have to poll run status. The returned ID is passed to `unsubscribe_run_events`.
+#[napi]
+pub fn subscribe_run_events(callback: napi::JsFunction, filter_json: Option<String>) -> napi::Result<RunEventSubscriptionResult> {
+    use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+    
+    let filter: crate::run_stream::RunEventFilter = match filter_json.as_deref().map(serde_json::from_str).transpose() {
+        Ok(filter) => filter.unwrap_or_default(),
+        Err(e) => {
+            return Ok(RunEventSubscriptionResult {
+                success: false,
+                id: None,
+                data: None,
+                message: format!("Invalid run event filter: {}", e),
+                code: None,
+                details: None,
+            }.with_error(&CoreError::Serialization(e)));
+        }
+    };
+    let callback: ThreadsafeFunction<String, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<String>| Ok(vec![ctx.env.create_string(&ctx.value)?]))?;
+    
+    let id = crate::run_stream::subscribe(filter, Box::new(move |event| match serde_json::to_string(event) {
+        Ok(event_json) => {
+            callback.call(event_json, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+        Err(e) => log::warn!("Failed to serialize run event for subscribers: {}", e),
+    }));
+    
+    Ok(RunEventSubscriptionResult {
+        success: true,
+        id: Some(id.to_string()),
+        data: None,
+        message: "Subscribed to run events".to_string(),
+        code: None,
+        details: None,
+    })
+}
+
+/// Stop a run event subscription via N-API
+#[napi]
+pub fn unsubscribe_run_events(subscription_id: u32) -> RunEventUnsubscriptionResult {
+    if crate::run_stream::unsubscribe(subscription_id) {
+        RunEventUnsubscriptionResult {
+            success: true,
+            message: "Unsubscribed from run events".to_string(),
+            code: None,
+            details: None,
+        }
+    } else {
+        RunEventUnsubscriptionResult {
+            success: false,
+            message: format!("No run event subscription with ID {}", subscription_id),
+            code: None,
+            details: None,
+        }
+    }
+}
+
+/// Register the handler running onSuccess/onFailure/onCancel hooks via N-API
+///
+/// `callback` is called with a JSON hook invocation (`{"id", "hook_type",
+/// "attempt", "context"}`) whenever a run finishes. Node runs the hook and
+/// reports the outcome with `report_workflow_hook`; failed or unreported
+/// invocations are retried. Registering again replaces the handler.
+#[napi]
+pub fn register_workflow_hook_handler(callback: napi::JsFunction, db_path: String) -> napi::Result<WorkflowHookHandlerResult> {
+    use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+    
+    let callback: ThreadsafeFunction<String, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<String>| Ok(vec![ctx.env.create_string(&ctx.value)?]))?;
+    let handler: crate::completion_hooks::HookHandler = Box::new(move |invocation| match serde_json::to_string(invocation) {
+        Ok(invocation_json) => {
+            callback.call(invocation_json, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+        Err(e) => log::warn!("Failed to serialize hook invocation {}: {}", invocation.id, e),
+    });
+    
+    Ok(with_shared_bridge!(
+        &db_path,
+        |_: ()| WorkflowHookHandlerResult {
+            success: true,
+            data: None,
+            message: "Workflow hook handler registered".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowHookHandlerResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.set_completion_hook_handler(handler)
+    ))
+}
+
+/// Report the outcome of a hook invocation via N-API
+///
+/// Pass `error` when the hook failed, so it is retried while attempts are left.
+#[napi]
+pub fn report_workflow_hook(invocation_id: String, error: Option<String>, db_path: String) -> WorkflowHookReportResult {
+    with_shared_bridge!(
+        &db_path,
+        |outcome_json: String| WorkflowHookReportResult {
+            success: true,
+            data: Some(outcome_json),
+            message: "Workflow hook outcome recorded".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| WorkflowHookReportResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| bridge.report_completion_hook(&invocation_id, error.as_deref())
+    )
+}
+
+/// Register the classifier deciding what happens to failed steps via N-API
+///
+/// `callback` is called with a JSON request (`{"id", "workflow_id", "run_id",
+/// "step_id", "attempt", "max_attempts", "error", "default_retry"}`) whenever
+/// a step attempt fails. Node answers with `report_retry_decision`; failures
+/// not answered in time follow the step's retry config. Registering again
+/// replaces the classifier.
+#[napi]
+pub fn register_retry_classifier(callback: napi::JsFunction, db_path: String) -> napi::Result<RetryClassifierResult> {
+    use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+    
+    let callback: ThreadsafeFunction<String, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<String>| Ok(vec![ctx.env.create_string(&ctx.value)?]))?;
+    let handler: crate::retry_classifier::ClassifierHandler = Box::new(move |request| match serde_json::to_string(request) {
+        Ok(request_json) => {
+            callback.call(request_json, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+        Err(e) => log::warn!("Failed to serialize retry classification {}: {}", request.id, e),
+    });
+    
+    Ok(with_shared_bridge!(
+        &db_path,
+        |_: ()| RetryClassifierResult {
+            success: true,
+            data: None,
+            message: "Retry classifier registered".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RetryClassifierResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.set_retry_classifier(Some(handler)))
+    ))
+}
+
+/// Stop asking the retry classifier about failed steps via N-API
+#[napi]
+pub fn unregister_retry_classifier(db_path: String) -> RetryClassifierResult {
+    with_shared_bridge!(
+        &db_path,
+        |_: ()| RetryClassifierResult {
+            success: true,
+            data: None,
+            message: "Retry classifier unregistered".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RetryClassifierResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.set_retry_classifier(None))
+    )
+}
+
+/// Report the retry classifier's decision about a failed step via N-API
+///
+/// `decision_json` is one of `{"action": "retry", "delay_ms": 5000}` (the
+/// delay is optional), `{"action": "fail"}`, `{"action": "failover",
+/// "step_id": "fallback"}` or `{"action": "default"}`.
+#[napi]
+pub fn report_retry_decision(request_id: String, decision_json: String, db_path: String) -> RetryDecisionResult {
+    with_shared_bridge!(
+        &db_path,
+        |decision_json: String| RetryDecisionResult {
+            success: true,
+            data: Some(decision_json),
+            message: "Retry decision recorded".to_string(),
+            code: None,
+            details: None,
+        },
+        |msg: String| RetryDecisionResult {
+            success: false,
+            data: None,
+            message: msg,
+            code: None,
+            details: None,
+        },
+        |bridge: Arc<Bridge>| block_on_napi(bridge.report_retry_decision(&request_id, &decision_json))
+    )
+}
+
 // Note: pause_workflow and resume_workflow removed (Task 1.4)
 // These were placeholder functions that didn't actually pause/resume workflows.
 // When workflow state machine is integrated (Phase 2, Task 2.2), 
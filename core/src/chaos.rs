@@ -0,0 +1,217 @@
+//! Failure injection for testing retry and hook logic
+//!
+//! With `CRONFLOW_CHAOS` set, rules set through the bridge make the
+//! dispatcher fail step attempts without running them, delay them, drop their
+//! jobs as if their worker crashed, or time them out. Whether a fault hits an
+//! attempt is decided by hashing the seed with the workflow, step, attempt
+//! number and fault, so every run of a workflow gets the same faults on every
+//! test run, however its jobs are scheduled across workers. Changing the seed
+//! changes which steps and attempts are hit.
+//!
+//! Dropped jobs are neither retried nor recorded: their step is left to the
+//! run timeout, or to another process once the job's lease expires.
+
+use sha2::{Digest, Sha256};
+use serde::{Deserialize, Serialize};
+use crate::config::ChaosConfig;
+use crate::error::{CoreError, CoreResult};
+use crate::job::Job;
+
+/// Faults injected into the jobs a rule set matches
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChaosRules {
+    /// Only inject faults into this workflow's jobs
+    #[serde(default)]
+    pub workflow_id: Option<String>,
+    /// Only inject faults into jobs of steps with this ID
+    #[serde(default)]
+    pub step_id: Option<String>,
+    /// Seed of the fault decisions, the configured one when unset
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Share of attempts failed without running, from 0 to 1
+    #[serde(default)]
+    pub failure_rate: f64,
+    /// Share of attempts delayed by `latency_ms` before running
+    #[serde(default)]
+    pub latency_rate: f64,
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Share of jobs dropped once dequeued
+    #[serde(default)]
+    pub drop_rate: f64,
+    /// Share of attempts timed out without running
+    #[serde(default)]
+    pub timeout_rate: f64,
+}
+
+impl ChaosRules {
+    pub fn validate(&self) -> CoreResult<()> {
+        let rates = [
+            ("failure_rate", self.failure_rate),
+            ("latency_rate", self.latency_rate),
+            ("drop_rate", self.drop_rate),
+            ("timeout_rate", self.timeout_rate),
+        ];
+        for (name, rate) in rates {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(CoreError::Validation(format!("Chaos {} must be between 0 and 1, got {}", name, rate)));
+            }
+        }
+        if self.latency_rate > 0.0 && self.latency_ms == 0 {
+            return Err(CoreError::Validation("Chaos latency_ms must be greater than 0 when latency_rate is set".to_string()));
+        }
+        Ok(())
+    }
+
+    fn matches(&self, job: &Job) -> bool {
+        self.workflow_id.as_ref().is_none_or(|id| *id == job.workflow_id)
+            && self.step_id.as_ref().is_none_or(|id| *id == job.step_name)
+    }
+}
+
+/// Fault replacing the execution of an attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    Fail,
+    Drop,
+    Timeout,
+}
+
+impl Fault {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Fault::Fail => "fail",
+            Fault::Drop => "drop",
+            Fault::Timeout => "timeout",
+        }
+    }
+}
+
+/// What is injected into one attempt of a job
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Injection {
+    /// Delay before the attempt runs
+    pub delay_ms: u64,
+    pub fault: Option<Fault>,
+}
+
+impl Injection {
+    pub fn is_empty(&self) -> bool {
+        self.delay_ms == 0 && self.fault.is_none()
+    }
+}
+
+/// Counts of the faults injected since the rules were last set
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ChaosStats {
+    pub failures: u64,
+    pub delays: u64,
+    pub drops: u64,
+    pub timeouts: u64,
+}
+
+/// Failure injection state reported through the bridge
+#[derive(Debug, Clone, Serialize)]
+pub struct ChaosStatus {
+    pub enabled: bool,
+    pub seed: u64,
+    pub rules: Option<ChaosRules>,
+    pub stats: ChaosStats,
+}
+
+/// Decides the faults injected into the dispatcher's jobs
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    enabled: bool,
+    seed: u64,
+    rules: Option<ChaosRules>,
+    stats: ChaosStats,
+}
+
+impl FaultInjector {
+    pub fn new(config: &ChaosConfig) -> Self {
+        FaultInjector {
+            enabled: config.enabled,
+            seed: config.seed,
+            ..Default::default()
+        }
+    }
+
+    /// Replace the rules, resetting the fault counts
+    pub fn set_rules(&mut self, rules: ChaosRules) -> CoreResult<()> {
+        if !self.enabled {
+            return Err(CoreError::Configuration("Failure injection is disabled, set CRONFLOW_CHAOS=true to enable it".to_string()));
+        }
+        rules.validate()?;
+        log::warn!("Injecting faults into jobs: {}", serde_json::to_string(&rules)?);
+        self.rules = Some(rules);
+        self.stats = ChaosStats::default();
+        Ok(())
+    }
+
+    /// Stop injecting faults
+    pub fn clear_rules(&mut self) {
+        if self.rules.take().is_some() {
+            log::info!("Stopped injecting faults into jobs");
+        }
+    }
+
+    pub fn status(&self) -> ChaosStatus {
+        ChaosStatus {
+            enabled: self.enabled,
+            seed: self.rules.as_ref().and_then(|rules| rules.seed).unwrap_or(self.seed),
+            rules: self.rules.clone(),
+            stats: self.stats.clone(),
+        }
+    }
+
+    /// Decide what is injected into the next attempt of a job, counting it
+    ///
+    /// A dropped job is not delayed, and a timeout wins over a failure.
+    pub fn injection_for(&mut self, job: &Job) -> Injection {
+        let Some(rules) = self.rules.as_ref().filter(|rules| self.enabled && rules.matches(job)) else {
+            return Injection::default();
+        };
+        let seed = rules.seed.unwrap_or(self.seed);
+        let attempt = job.metadata.attempt_count;
+        let hits = |kind: &str, rate: f64| rate > 0.0 && roll(seed, job, attempt, kind) < rate;
+
+        let mut injection = Injection::default();
+        if hits("drop", rules.drop_rate) {
+            injection.fault = Some(Fault::Drop);
+            self.stats.drops += 1;
+            return injection;
+        }
+        if hits("latency", rules.latency_rate) {
+            injection.delay_ms = rules.latency_ms;
+            self.stats.delays += 1;
+        }
+        if hits("timeout", rules.timeout_rate) {
+            injection.fault = Some(Fault::Timeout);
+            self.stats.timeouts += 1;
+        } else if hits("fail", rules.failure_rate) {
+            injection.fault = Some(Fault::Fail);
+            self.stats.failures += 1;
+        }
+        injection
+    }
+}
+
+/// Uniform value in [0, 1) derived from the seed, step, attempt and fault kind
+fn roll(seed: u64, job: &Job, attempt: u32, kind: &str) -> f64 {
+    let mut hasher = Sha256::new();
+    hasher.update(seed.to_be_bytes());
+    for part in [&job.workflow_id, &job.step_name] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    hasher.update(attempt.to_be_bytes());
+    hasher.update(kind.as_bytes());
+    let digest = hasher.finalize();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    (u64::from_be_bytes(bytes) >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,212 @@
+//! Workflow completion hooks
+//!
+//! When a run finishes, its onSuccess, onFailure or onCancel hook is handed to
+//! the registered handler, which the N-API bridge backs with a threadsafe
+//! function calling into Node. The onCancel hook's context carries the
+//! cancellation, so user code can undo what interrupted steps started. Node reports the outcome of each invocation back. Failed
+//! invocations, and invocations whose outcome is not reported within the
+//! acknowledgement timeout, are retried with a doubling delay until the
+//! configured attempts are used up. Every attempt and outcome is recorded in
+//! the run's event log. Invocations are held in memory, so hooks in flight when
+//! the process exits are not retried.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::config::HooksConfig;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{RunEvent, WorkflowCompletionContext};
+use crate::state::StateManager;
+
+/// Callback delivering a hook invocation to the code running the hook
+pub type HookHandler = Box<dyn Fn(&HookInvocation) + Send + Sync>;
+
+/// One attempt at running a finished run's hook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookInvocation {
+    /// Identifies the attempt when its outcome is reported
+    pub id: String,
+    /// "onSuccess", "onFailure" or "onCancel"
+    pub hook_type: String,
+    /// Attempt number, starting at 1
+    pub attempt: u32,
+    pub context: WorkflowCompletionContext,
+}
+
+/// What happens to a hook after an attempt finished
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    Succeeded,
+    /// The attempt failed and the hook is retried after the delay
+    Retrying { delay: Duration },
+    /// The attempt failed and no attempts are left
+    GaveUp,
+}
+
+/// Hook invocations waiting for their outcome or for a retry
+pub struct CompletionHooks {
+    config: HooksConfig,
+    handler: Option<HookHandler>,
+    /// Delivered invocations by ID, with the time their outcome is due
+    awaiting: HashMap<String, (HookInvocation, Instant)>,
+    /// Failed invocations with the time their retry is due
+    retries: Vec<(HookInvocation, Instant)>,
+}
+
+impl CompletionHooks {
+    pub fn new(config: HooksConfig) -> Self {
+        Self {
+            config,
+            handler: None,
+            awaiting: HashMap::new(),
+            retries: Vec::new(),
+        }
+    }
+
+    /// Set the handler hooks are delivered to, replacing any previous one
+    pub fn set_handler(&mut self, handler: HookHandler) {
+        self.handler = Some(handler);
+    }
+
+    /// Deliver the first attempt at a finished run's hook
+    ///
+    /// Returns None when no handler is registered.
+    pub fn fire(&mut self, context: WorkflowCompletionContext, now: Instant) -> Option<HookInvocation> {
+        self.handler.as_ref()?;
+        let invocation = HookInvocation {
+            id: Uuid::new_v4().to_string(),
+            hook_type: context.hook_type().to_string(),
+            attempt: 1,
+            context,
+        };
+        Some(self.deliver(invocation, now))
+    }
+
+    fn deliver(&mut self, invocation: HookInvocation, now: Instant) -> HookInvocation {
+        if let Some(handler) = &self.handler {
+            handler(&invocation);
+        }
+        let deadline = now + Duration::from_millis(self.config.ack_timeout_ms);
+        self.awaiting.insert(invocation.id.clone(), (invocation.clone(), deadline));
+        invocation
+    }
+
+    /// Record the reported outcome of a delivered invocation
+    pub fn report(&mut self, invocation_id: &str, error: Option<&str>, now: Instant) -> CoreResult<(HookInvocation, HookOutcome)> {
+        let (invocation, _) = self.awaiting.remove(invocation_id)
+            .ok_or_else(|| CoreError::Validation(format!("No hook invocation {} is awaiting its outcome", invocation_id)))?;
+        let outcome = match error {
+            None => HookOutcome::Succeeded,
+            Some(_) => self.fail(&invocation, now),
+        };
+        Ok((invocation, outcome))
+    }
+
+    fn fail(&mut self, invocation: &HookInvocation, now: Instant) -> HookOutcome {
+        if invocation.attempt >= self.config.max_attempts {
+            return HookOutcome::GaveUp;
+        }
+        let delay = Duration::from_millis(self.config.retry_delay_ms.saturating_mul(1 << (invocation.attempt - 1).min(16)));
+        let retry = HookInvocation {
+            id: Uuid::new_v4().to_string(),
+            attempt: invocation.attempt + 1,
+            ..invocation.clone()
+        };
+        self.retries.push((retry, now + delay));
+        HookOutcome::Retrying { delay }
+    }
+
+    /// Fail invocations whose outcome is overdue and deliver the retries that are due
+    ///
+    /// Returns the overdue invocations with their outcome, and the delivered retries.
+    pub fn tick(&mut self, now: Instant) -> (Vec<(HookInvocation, HookOutcome)>, Vec<HookInvocation>) {
+        let overdue: Vec<String> = self.awaiting.iter()
+            .filter(|(_, (_, deadline))| *deadline <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let mut timed_out = Vec::with_capacity(overdue.len());
+        for id in overdue {
+            if let Some((invocation, _)) = self.awaiting.remove(&id) {
+                let outcome = self.fail(&invocation, now);
+                timed_out.push((invocation, outcome));
+            }
+        }
+
+        let (due, waiting): (Vec<_>, Vec<_>) = std::mem::take(&mut self.retries).into_iter()
+            .partition(|(_, due)| *due <= now);
+        self.retries = waiting;
+        let delivered = due.into_iter()
+            .map(|(invocation, _)| self.deliver(invocation, now))
+            .collect();
+        (timed_out, delivered)
+    }
+
+    /// Number of invocations awaiting their outcome or a retry
+    pub fn in_flight(&self) -> usize {
+        self.awaiting.len() + self.retries.len()
+    }
+}
+
+/// Record an attempt or outcome of a hook in its run's event log
+pub fn record(state_manager: &StateManager, invocation: &HookInvocation, outcome: Option<(&HookOutcome, &str)>) -> CoreResult<()> {
+    let run_id = Uuid::parse_str(&invocation.context.run_id)?;
+    let mut detail = serde_json::json!({
+        "hook_type": invocation.hook_type,
+        "workflow_id": invocation.context.workflow_id,
+        "invocation_id": invocation.id,
+        "attempt": invocation.attempt,
+    });
+    let event_type = match outcome {
+        None => RunEvent::HOOK_FIRED,
+        Some((HookOutcome::Succeeded, _)) => RunEvent::HOOK_SUCCEEDED,
+        Some((failure, error)) => {
+            detail["error"] = serde_json::json!(error);
+            if let HookOutcome::Retrying { delay } = failure {
+                detail["retry_in_ms"] = serde_json::json!(delay.as_millis() as u64);
+            }
+            RunEvent::HOOK_FAILED
+        }
+    };
+    state_manager.record_run_event(&run_id, event_type, None, RunEvent::ACTOR_ENGINE, detail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use crate::models::RunStatus;
+
+    #[test]
+    fn test_failed_and_unacknowledged_hooks_are_retried_until_attempts_run_out() {
+        let delivered = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&delivered);
+        let mut hooks = CompletionHooks::new(HooksConfig { max_attempts: 3, retry_delay_ms: 100, ack_timeout_ms: 1000 });
+        let now = chrono::Utc::now();
+        let context = WorkflowCompletionContext::new(
+            Uuid::new_v4().to_string(), "billing".to_string(), RunStatus::Failed, vec![], Some("boom".to_string()), now, now, serde_json::json!({}),
+        );
+        let start = Instant::now();
+        assert!(hooks.fire(context.clone(), start).is_none());
+
+        hooks.set_handler(Box::new(move |invocation| sink.lock().unwrap().push((invocation.hook_type.clone(), invocation.attempt))));
+        let first = hooks.fire(context, start).unwrap();
+        assert_eq!((first.hook_type.as_str(), first.attempt), ("onFailure", 1));
+
+        let (_, outcome) = hooks.report(&first.id, Some("webhook down"), start).unwrap();
+        assert_eq!(outcome, HookOutcome::Retrying { delay: Duration::from_millis(100) });
+        assert!(hooks.report(&first.id, None, start).is_err());
+        assert!(hooks.tick(start + Duration::from_millis(50)).1.is_empty());
+        let (_, retried) = hooks.tick(start + Duration::from_millis(100));
+        assert_eq!(retried[0].attempt, 2);
+
+        // The second attempt is never acknowledged
+        let (timed_out, _) = hooks.tick(start + Duration::from_millis(1100));
+        assert_eq!(timed_out[0].1, HookOutcome::Retrying { delay: Duration::from_millis(200) });
+        let (_, retried) = hooks.tick(start + Duration::from_millis(1300));
+        let (_, outcome) = hooks.report(&retried[0].id, Some("still down"), start + Duration::from_millis(1400)).unwrap();
+        assert_eq!(outcome, HookOutcome::GaveUp);
+        assert_eq!(hooks.in_flight(), 0);
+        assert_eq!(*delivered.lock().unwrap(), vec![("onFailure".to_string(), 1), ("onFailure".to_string(), 2), ("onFailure".to_string(), 3)]);
+    }
+}
@@ -2,6 +2,37 @@
 //! 
 //! This module provides condition evaluation functionality for if/else control flow,
 //! including parsing condition expressions and evaluating them against workflow context.
+//!
+//! Grammar, from lowest to highest precedence:
+//!
+//! ```text
+//! expr       := or
+//! or         := and ( "||" and )*
+//! and        := unary ( "&&" unary )*
+//! unary      := "!" unary | comparison
+//! comparison := operand ( ( "==" | "!=" | ">=" | "<=" | ">" | "<" ) operand )?
+//! operand    := literal | call | reference | "(" expr ")"
+//! literal    := number | 'string' | "string" | true | false | null
+//! call       := name "(" ( expr ( "," expr )* )? ")"
+//! reference  := ctx.payload[.path] | ctx.last.path | ctx.steps.<id>.output[.path]
+//!             | ctx.steps.<id>.error | ctx.steps.<id>.status | path
+//! ```
+//!
+//! Paths are dot-separated and may index arrays, e.g. `ctx.payload.items.0.id`.
+//! A bare path is looked up in the payload, then in the last step's output;
+//! `payload.user.age` also resolves against the payload. Inside a comparison,
+//! an unquoted word that resolves to nothing is taken as a string literal, so
+//! `ctx.payload.status == active` keeps working.
+//!
+//! Functions: `contains(text_or_array, value)`, `startsWith(text, prefix)`,
+//! `endsWith(text, suffix)`, `matches(text, regex)`, `length(value)` and
+//! `includes(array, value)`.
+//!
+//! Numbers compare numerically, also when given as numeric strings; other
+//! strings order lexicographically. On its own, a step status reference is true
+//! when the step completed and a step error reference is true when it failed.
+//! Parse and evaluation errors are reported in the result's `error` and
+//! `metadata` rather than failing the run.
 
 use crate::error::{CoreError, CoreResult};
 use crate::models::{ConditionResult, StepResult};
@@ -32,161 +63,195 @@ impl ConditionEvaluator {
     }
     
     /// Evaluate a condition expression
+    ///
+    /// Expressions that cannot be parsed or evaluated give an unmet result
+    /// describing the error in `metadata`.
     pub fn evaluate_condition(&self, condition_expr: &str) -> CoreResult<ConditionResult> {
         log::debug!("Evaluating condition: {}", condition_expr);
         
-        let parsed_condition = self.parse_condition_expression(condition_expr)?;
+        let outcome = Parser::parse(condition_expr)
+            .and_then(|expr| self.evaluate_bool(&expr).map_err(ExpressionError::evaluation));
         
-        // Evaluate the parsed condition
-        let result = self.evaluate_parsed_condition(&parsed_condition)?;
+        let result = match outcome {
+            Ok(met) => ConditionResult::success(met),
+            Err(error) => {
+                log::debug!("Condition '{}' could not be evaluated: {}", condition_expr, error.message);
+                let mut result = ConditionResult::failure(error.message.clone());
+                result.metadata = serde_json::json!({
+                    "expression": condition_expr,
+                    "stage": error.stage,
+                    "position": error.position,
+                });
+                result
+            }
+        };
         
         log::debug!("Condition evaluation result: {}", result.met);
         Ok(result)
     }
     
-    /// Parse a condition expression into evaluable components
-    fn parse_condition_expression(&self, expr: &str) -> CoreResult<ParsedCondition> {
-        // Remove whitespace and normalize
+    /// Resolve a reference expression such as "ctx.payload.orders" or "ctx.steps.fetch.output.items" to its value
+    pub fn resolve_value(&self, expr: &str) -> CoreResult<Value> {
         let expr = expr.trim();
         
-        // Handle simple boolean expressions
-        if expr == "true" {
-            return Ok(ParsedCondition::Boolean(true));
+        if expr == "ctx.payload" {
+            return Ok(self.context.payload.clone());
         }
-        if expr == "false" {
-            return Ok(ParsedCondition::Boolean(false));
+        if let Some(field_path) = expr.strip_prefix("ctx.payload.") {
+            return self.get_payload_field(field_path);
         }
-        
-        // Handle context references (ctx.payload.field, ctx.last.field, etc.)
-        if expr.starts_with("ctx.") {
-            return self.parse_context_reference(expr);
+        if let Some(field_path) = expr.strip_prefix("ctx.last.") {
+            return self.get_last_step_field(field_path);
         }
-        
-        // Handle step result references (ctx.steps.step_id.output.field)
-        if expr.starts_with("ctx.steps.") {
-            return self.parse_step_reference(expr);
+        if let Some(reference) = expr.strip_prefix("ctx.steps.") {
+            let mut parts = reference.splitn(3, '.');
+            let step_id = parts.next().unwrap_or_default();
+            return match parts.next() {
+                Some("output") => self.get_step_output_field(step_id, parts.next().unwrap_or("")),
+                _ => Err(CoreError::Validation(format!("Unsupported step reference: {}", expr))),
+            };
         }
-        
-        // Handle comparison expressions (field > value, field == value, etc.)
-        if self.contains_comparison_operator(expr) {
-            return self.parse_comparison_expression(expr);
+        if expr.starts_with("ctx.") {
+            return Err(CoreError::Validation(format!("Unsupported context reference: {}", expr)));
         }
         
-        Ok(ParsedCondition::FieldReference(expr.to_string()))
+        self.get_field_reference(expr)
     }
     
-    /// Parse context reference expressions
-    fn parse_context_reference(&self, expr: &str) -> CoreResult<ParsedCondition> {
-        // Handle ctx.payload.field
-        if expr.starts_with("ctx.payload.") {
-            let field_path = &expr[13..]; // Remove "ctx.payload."
-            return Ok(ParsedCondition::PayloadField(field_path.to_string()));
+    /// Evaluate an expression in a boolean position
+    fn evaluate_bool(&self, expr: &Expr) -> Result<bool, String> {
+        match expr {
+            Expr::Reference(Reference::StepStatus(step_id)) => Ok(self.get_step_status(step_id) == "completed"),
+            Expr::Reference(Reference::StepError(step_id)) => Ok(self.get_step_error(step_id).is_some()),
+            Expr::Not(inner) => Ok(!self.evaluate_bool(inner)?),
+            Expr::And(left, right) => Ok(self.evaluate_bool(left)? && self.evaluate_bool(right)?),
+            Expr::Or(left, right) => Ok(self.evaluate_bool(left)? || self.evaluate_bool(right)?),
+            Expr::Compare(left, op, right) => self.evaluate_comparison(left, op, right),
+            _ => Ok(self.is_truthy(&self.evaluate_value(expr)?)),
         }
-        
-        // Handle ctx.last.field
-        if expr.starts_with("ctx.last.") {
-            let field_path = &expr[9..]; // Remove "ctx.last."
-            return Ok(ParsedCondition::LastStepField(field_path.to_string()));
-        }
-        
-        Err(CoreError::Validation(format!("Unsupported context reference: {}", expr)))
     }
     
-    /// Parse step result reference expressions
-    fn parse_step_reference(&self, expr: &str) -> CoreResult<ParsedCondition> {
-        // Handle ctx.steps.step_id.output.field
-        let parts: Vec<&str> = expr.split('.').collect();
-        if parts.len() >= 4 && parts[0] == "ctx" && parts[1] == "steps" {
-            let step_id = parts[2];
-            let field_type = parts[3];
-            
-            match field_type {
-                "output" => {
-                    if parts.len() >= 5 {
-                        let field_path = parts[4..].join(".");
-                        return Ok(ParsedCondition::StepOutput(step_id.to_string(), field_path));
-                    } else {
-                        return Ok(ParsedCondition::StepOutput(step_id.to_string(), "".to_string()));
-                    }
-                },
-                "error" => {
-                    return Ok(ParsedCondition::StepError(step_id.to_string()));
-                },
-                "status" => {
-                    return Ok(ParsedCondition::StepStatus(step_id.to_string()));
-                },
-                _ => {
-                    return Err(CoreError::Validation(format!("Unsupported step field: {}", field_type)));
-                }
+    /// Evaluate an expression to its value
+    fn evaluate_value(&self, expr: &Expr) -> Result<Value, String> {
+        match expr {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Reference(reference) => self.resolve_reference(reference),
+            Expr::Call(name, args) => {
+                let args = args.iter()
+                    .map(|arg| self.evaluate_value(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                self.call_function(name, &args)
             }
+            Expr::Not(_) | Expr::And(..) | Expr::Or(..) | Expr::Compare(..) => Ok(Value::Bool(self.evaluate_bool(expr)?)),
         }
-        
-        Err(CoreError::Validation(format!("Invalid step reference format: {}", expr)))
     }
     
-    /// Parse comparison expressions
-    fn parse_comparison_expression(&self, expr: &str) -> CoreResult<ParsedCondition> {
-        let operators = ["==", "!=", ">=", "<=", ">", "<"];
+    /// Resolve a reference to its value
+    fn resolve_reference(&self, reference: &Reference) -> Result<Value, String> {
+        let value = match reference {
+            Reference::Payload(field_path) if field_path.is_empty() => Ok(self.context.payload.clone()),
+            Reference::Payload(field_path) => self.get_payload_field(field_path),
+            Reference::LastStep(field_path) => self.get_last_step_field(field_path),
+            Reference::StepOutput(step_id, field_path) => self.get_step_output_field(step_id, field_path),
+            Reference::StepError(step_id) => Ok(self.get_step_error(step_id).map(Value::String).unwrap_or(Value::Null)),
+            Reference::StepStatus(step_id) => Ok(Value::String(self.get_step_status(step_id))),
+            Reference::Field(field_path) => match (self.get_field_reference(field_path), field_path.strip_prefix("payload.")) {
+                (Ok(Value::Null), Some(payload_path)) => self.get_payload_field(payload_path),
+                (value, _) => value,
+            },
+        };
+        value.map_err(|e| e.to_string())
+    }
+    
+    /// Evaluate a comparison of two operands
+    fn evaluate_comparison(&self, left: &Expr, op: &CompareOp, right: &Expr) -> Result<bool, String> {
+        let left_value = self.evaluate_operand(left)?;
+        let right_value = self.evaluate_operand(right)?;
         
-        for op in &operators {
-            if expr.contains(op) {
-                let parts: Vec<&str> = expr.split(op).collect();
-                if parts.len() == 2 {
-                    let left = parts[0].trim();
-                    let right = parts[1].trim();
-                    
-                    return Ok(ParsedCondition::Comparison(
-                        left.to_string(),
-                        op.to_string(),
-                        right.to_string()
-                    ));
-                }
+        match op {
+            CompareOp::Eq => Ok(Self::loose_eq(&left_value, &right_value)),
+            CompareOp::Ne => Ok(!Self::loose_eq(&left_value, &right_value)),
+            _ => {
+                let ordering = self.compare_values(&left_value, &right_value)?;
+                Ok(match op {
+                    CompareOp::Gt => ordering.is_gt(),
+                    CompareOp::Ge => ordering.is_ge(),
+                    CompareOp::Lt => ordering.is_lt(),
+                    _ => ordering.is_le(),
+                })
             }
         }
+    }
+    
+    /// Evaluate a comparison operand, taking unresolved bare words as string literals
+    fn evaluate_operand(&self, expr: &Expr) -> Result<Value, String> {
+        match (expr, self.evaluate_value(expr)?) {
+            (Expr::Reference(Reference::Field(word)), Value::Null) => Ok(Value::String(word.clone())),
+            (_, value) => Ok(value),
+        }
+    }
+    
+    /// Call a built-in function
+    fn call_function(&self, name: &str, args: &[Value]) -> Result<Value, String> {
+        let arity = match name {
+            "length" => 1,
+            "contains" | "startsWith" | "endsWith" | "matches" | "includes" => 2,
+            _ => return Err(format!("Unknown function '{}'", name)),
+        };
+        if args.len() != arity {
+            return Err(format!("{}() takes {} argument(s), got {}", name, arity, args.len()));
+        }
         
-        Err(CoreError::Validation(format!("Invalid comparison expression: {}", expr)))
+        let text = |index: usize| match &args[index] {
+            Value::String(s) => Ok(s.as_str()),
+            other => Err(format!("{}() expects a string as argument {}, got {}", name, index + 1, other)),
+        };
+        let result = match name {
+            "length" => match &args[0] {
+                Value::String(s) => s.chars().count(),
+                Value::Array(items) => items.len(),
+                Value::Object(map) => map.len(),
+                Value::Null => 0,
+                other => return Err(format!("length() expects a string, array or object, got {}", other)),
+            }.into(),
+            "contains" => match &args[0] {
+                Value::Array(items) => items.iter().any(|item| Self::loose_eq(item, &args[1])),
+                Value::String(s) => s.contains(text(1)?),
+                Value::Null => false,
+                other => return Err(format!("contains() expects a string or array, got {}", other)),
+            }.into(),
+            "includes" => match &args[0] {
+                Value::Array(items) => items.iter().any(|item| Self::loose_eq(item, &args[1])),
+                Value::Null => false,
+                other => return Err(format!("includes() expects an array, got {}", other)),
+            }.into(),
+            "startsWith" => text(0)?.starts_with(text(1)?).into(),
+            "endsWith" => text(0)?.ends_with(text(1)?).into(),
+            _ => {
+                let pattern = regex::Regex::new(text(1)?)
+                    .map_err(|e| format!("matches() has an invalid regex: {}", e))?;
+                pattern.is_match(text(0)?).into()
+            }
+        };
+        Ok(result)
     }
     
-    /// Check if expression contains comparison operators
-    fn contains_comparison_operator(&self, expr: &str) -> bool {
-        let operators = ["==", "!=", ">=", "<=", ">", "<"];
-        operators.iter().any(|op| expr.contains(op))
+    /// Compare two values for equality, comparing numbers by value
+    fn loose_eq(left: &Value, right: &Value) -> bool {
+        match (left, right) {
+            (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+            _ => left == right,
+        }
     }
     
-    /// Evaluate a parsed condition
-    fn evaluate_parsed_condition(&self, condition: &ParsedCondition) -> CoreResult<ConditionResult> {
-        match condition {
-            ParsedCondition::Boolean(value) => {
-                Ok(ConditionResult::success(*value))
-            },
-            ParsedCondition::PayloadField(field_path) => {
-                let value = self.get_payload_field(field_path)?;
-                Ok(ConditionResult::success(self.is_truthy(&value)))
-            },
-            ParsedCondition::LastStepField(field_path) => {
-                let value = self.get_last_step_field(field_path)?;
-                Ok(ConditionResult::success(self.is_truthy(&value)))
-            },
-            ParsedCondition::StepOutput(step_id, field_path) => {
-                let value = self.get_step_output_field(step_id, field_path)?;
-                Ok(ConditionResult::success(self.is_truthy(&value)))
-            },
-            ParsedCondition::StepError(step_id) => {
-                let has_error = self.get_step_error(step_id)?;
-                Ok(ConditionResult::success(has_error))
-            },
-            ParsedCondition::StepStatus(step_id) => {
-                let status = self.get_step_status(step_id)?;
-                Ok(ConditionResult::success(status == "completed"))
-            },
-            ParsedCondition::FieldReference(field_path) => {
-                let value = self.get_field_reference(field_path)?;
-                Ok(ConditionResult::success(self.is_truthy(&value)))
-            },
-            ParsedCondition::Comparison(left, op, right) => {
-                let result = self.evaluate_comparison(left, op, right)?;
-                Ok(ConditionResult::success(result))
-            },
+    /// Order two values, numerically when both convert to numbers and lexicographically for strings
+    fn compare_values(&self, left: &Value, right: &Value) -> Result<std::cmp::Ordering, String> {
+        if let (Ok(a), Ok(b)) = (self.value_to_number(left), self.value_to_number(right)) {
+            return a.partial_cmp(&b).ok_or_else(|| format!("Cannot order {} and {}", left, right));
+        }
+        match (left, right) {
+            (Value::String(a), Value::String(b)) => Ok(a.cmp(b)),
+            _ => Err(format!("Cannot order {} and {}", left, right)),
         }
     }
     
@@ -233,21 +298,16 @@ impl ConditionEvaluator {
         }
     }
     
-    /// Check if a step has an error
-    fn get_step_error(&self, step_id: &str) -> CoreResult<bool> {
-        if let Some(step_result) = self.completed_steps.get(step_id) {
-            Ok(step_result.error.is_some())
-        } else {
-            Ok(false)
-        }
+    /// Get a step's error, if it failed
+    fn get_step_error(&self, step_id: &str) -> Option<String> {
+        self.completed_steps.get(step_id).and_then(|step_result| step_result.error.clone())
     }
     
     /// Get a step's status
-    fn get_step_status(&self, step_id: &str) -> CoreResult<String> {
-        if let Some(step_result) = self.completed_steps.get(step_id) {
-            Ok(step_result.status.as_str().to_string())
-        } else {
-            Ok("pending".to_string())
+    fn get_step_status(&self, step_id: &str) -> String {
+        match self.completed_steps.get(step_id) {
+            Some(step_result) => step_result.status.as_str().to_string(),
+            None => "pending".to_string(),
         }
     }
     
@@ -270,62 +330,6 @@ impl ConditionEvaluator {
         Ok(Value::Null)
     }
     
-    /// Evaluate a comparison expression
-    fn evaluate_comparison(&self, left: &str, op: &str, right: &str) -> CoreResult<bool> {
-        let left_value = if left.starts_with("ctx.") {
-            let parsed = self.parse_context_reference(left)?;
-            match parsed {
-                ParsedCondition::PayloadField(field_path) => self.get_payload_field(&field_path)?,
-                ParsedCondition::LastStepField(field_path) => self.get_last_step_field(&field_path)?,
-                _ => return Err(CoreError::Validation("Unsupported left operand in comparison".to_string())),
-            }
-        } else {
-            // Try to parse as number or string
-            if let Ok(num) = left.parse::<f64>() {
-                Value::Number(serde_json::Number::from_f64(num).unwrap_or_else(|| serde_json::Number::from(0)))
-            } else {
-                Value::String(left.to_string())
-            }
-        };
-        
-        let right_value = if right.starts_with("ctx.") {
-            let parsed = self.parse_context_reference(right)?;
-            match parsed {
-                ParsedCondition::PayloadField(field_path) => self.get_payload_field(&field_path)?,
-                ParsedCondition::LastStepField(field_path) => self.get_last_step_field(&field_path)?,
-                _ => return Err(CoreError::Validation("Unsupported right operand in comparison".to_string())),
-            }
-        } else {
-            // Try to parse as number or string
-            if let Ok(num) = right.parse::<f64>() {
-                Value::Number(serde_json::Number::from_f64(num).unwrap_or_else(|| serde_json::Number::from(0)))
-            } else {
-                Value::String(right.to_string())
-            }
-        };
-        
-        // Perform comparison
-        match op {
-            "==" => Ok(left_value == right_value),
-            "!=" => Ok(left_value != right_value),
-            ">" => self.compare_values(&left_value, &right_value, |a, b| a > b),
-            "<" => self.compare_values(&left_value, &right_value, |a, b| a < b),
-            ">=" => self.compare_values(&left_value, &right_value, |a, b| a >= b),
-            "<=" => self.compare_values(&left_value, &right_value, |a, b| a <= b),
-            _ => Err(CoreError::Validation(format!("Unsupported comparison operator: {}", op))),
-        }
-    }
-    
-    /// Compare two values using a comparison function
-    fn compare_values<F>(&self, left: &Value, right: &Value, compare_fn: F) -> CoreResult<bool>
-    where
-        F: Fn(f64, f64) -> bool,
-    {
-        let left_num = self.value_to_number(left)?;
-        let right_num = self.value_to_number(right)?;
-        Ok(compare_fn(left_num, right_num))
-    }
-    
     /// Convert a JSON value to a number
     fn value_to_number(&self, value: &Value) -> CoreResult<f64> {
         match value {
@@ -339,23 +343,20 @@ impl ConditionEvaluator {
         }
     }
     
-    /// Get a nested field from a JSON value
+    /// Get a nested field from a JSON value, indexing arrays by position
     fn get_nested_field(&self, value: &Value, field_path: &str) -> CoreResult<Value> {
         let parts: Vec<&str> = field_path.split('.').collect();
         let mut current = value;
         
         for part in parts {
-            match current {
-                Value::Object(map) => {
-                    if let Some(field_value) = map.get(part) {
-                        current = field_value;
-                    } else {
-                        return Ok(Value::Null);
-                    }
-                },
-                _ => {
-                    return Ok(Value::Null);
-                }
+            let next = match current {
+                Value::Object(map) => map.get(part),
+                Value::Array(items) => part.parse::<usize>().ok().and_then(|index| items.get(index)),
+                _ => None,
+            };
+            match next {
+                Some(field_value) => current = field_value,
+                None => return Ok(Value::Null),
             }
         }
         
@@ -375,15 +376,355 @@ impl ConditionEvaluator {
     }
 }
 
-/// Parsed condition representation
+/// Why an expression could not be parsed or evaluated
+#[derive(Debug, Clone)]
+struct ExpressionError {
+    /// "parse" or "evaluate"
+    stage: &'static str,
+    message: String,
+    /// Byte offset in the expression where parsing failed
+    position: Option<usize>,
+}
+
+impl ExpressionError {
+    fn parse(message: String, position: usize) -> Self {
+        Self { stage: "parse", message, position: Some(position) }
+    }
+
+    fn evaluation(message: String) -> Self {
+        Self { stage: "evaluate", message, position: None }
+    }
+}
+
+/// Parsed condition expression
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Reference(Reference),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, CompareOp, Box<Expr>),
+    Call(String, Vec<Expr>),
+}
+
+/// Value an expression refers to
 #[derive(Debug, Clone)]
-enum ParsedCondition {
-    Boolean(bool),
-    PayloadField(String),
-    LastStepField(String),
+enum Reference {
+    Payload(String),
+    LastStep(String),
     StepOutput(String, String),
     StepError(String),
     StepStatus(String),
-    FieldReference(String),
-    Comparison(String, String, String),
-} 
\ No newline at end of file
+    Field(String),
+}
+
+impl Reference {
+    /// Parse a dotted path into a reference
+    fn from_path(path: &str) -> Result<Self, String> {
+        if path == "ctx.payload" {
+            return Ok(Reference::Payload(String::new()));
+        }
+        if let Some(field_path) = path.strip_prefix("ctx.payload.") {
+            return Ok(Reference::Payload(field_path.to_string()));
+        }
+        if let Some(field_path) = path.strip_prefix("ctx.last.") {
+            return Ok(Reference::LastStep(field_path.to_string()));
+        }
+        if let Some(reference) = path.strip_prefix("ctx.steps.") {
+            let mut parts = reference.splitn(3, '.');
+            let step_id = parts.next().unwrap_or_default().to_string();
+            return match (parts.next(), parts.next()) {
+                (Some("output"), field_path) => Ok(Reference::StepOutput(step_id, field_path.unwrap_or_default().to_string())),
+                (Some("error"), None) => Ok(Reference::StepError(step_id)),
+                (Some("status"), None) => Ok(Reference::StepStatus(step_id)),
+                _ => Err(format!("Unsupported step reference: {}", path)),
+            };
+        }
+        if path.starts_with("ctx.") || path == "ctx" {
+            return Err(format!("Unsupported context reference: {}", path));
+        }
+        Ok(Reference::Field(path.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Word(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+/// Recursive descent parser over the tokens of an expression
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    pos: usize,
+    end: usize,
+}
+
+impl Parser {
+    /// Parse a whole expression
+    fn parse(input: &str) -> Result<Expr, ExpressionError> {
+        let mut parser = Parser {
+            tokens: Self::tokenize(input)?,
+            pos: 0,
+            end: input.len(),
+        };
+        if parser.tokens.is_empty() {
+            return Err(ExpressionError::parse("Expression is empty".to_string(), 0));
+        }
+        let expr = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            Some((token, offset)) => Err(ExpressionError::parse(format!("Unexpected {:?} after expression", token), *offset)),
+            None => Ok(expr),
+        }
+    }
+
+    fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, ExpressionError> {
+        const OPERATORS: [&str; 9] = ["==", "!=", ">=", "<=", "&&", "||", ">", "<", "!"];
+        let is_word_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '$' | '-');
+        let mut tokens = Vec::new();
+        let mut chars = input.char_indices().peekable();
+
+        while let Some(&(offset, c)) = chars.peek() {
+            if c.is_whitespace() {
+                chars.next();
+                continue;
+            }
+            let rest = &input[offset..];
+            if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(*op)) {
+                tokens.push((Token::Op(op), offset));
+                for _ in 0..op.len() {
+                    chars.next();
+                }
+                continue;
+            }
+            match c {
+                '(' | ')' | ',' => {
+                    chars.next();
+                    let token = match c {
+                        '(' => Token::LParen,
+                        ')' => Token::RParen,
+                        _ => Token::Comma,
+                    };
+                    tokens.push((token, offset));
+                }
+                '\'' | '"' => {
+                    chars.next();
+                    let mut text = String::new();
+                    let mut closed = false;
+                    while let Some((_, ch)) = chars.next() {
+                        match ch {
+                            '\\' => {
+                                if let Some((_, escaped)) = chars.next() {
+                                    text.push(escaped);
+                                }
+                            }
+                            ch if ch == c => {
+                                closed = true;
+                                break;
+                            }
+                            ch => text.push(ch),
+                        }
+                    }
+                    if !closed {
+                        return Err(ExpressionError::parse("Unterminated string literal".to_string(), offset));
+                    }
+                    tokens.push((Token::Str(text), offset));
+                }
+                c if is_word_char(c) => {
+                    let mut word = String::new();
+                    while let Some(&(_, ch)) = chars.peek() {
+                        if !is_word_char(ch) {
+                            break;
+                        }
+                        word.push(ch);
+                        chars.next();
+                    }
+                    let token = match word.parse::<f64>() {
+                        Ok(number) if word.starts_with(|ch: char| ch.is_ascii_digit() || ch == '-') => Token::Number(number),
+                        _ => Token::Word(word),
+                    };
+                    tokens.push((token, offset));
+                }
+                _ => return Err(ExpressionError::parse(format!("Unexpected character '{}'", c), offset)),
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(token, _)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens.get(self.pos).map(|(_, offset)| *offset).unwrap_or(self.end)
+    }
+
+    fn eat(&mut self, expected: &Token) -> bool {
+        if self.peek() == Some(expected) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_and()?;
+        while self.eat(&Token::Op("||")) {
+            left = Expr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ExpressionError> {
+        let mut left = self.parse_unary()?;
+        while self.eat(&Token::Op("&&")) {
+            left = Expr::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ExpressionError> {
+        if self.eat(&Token::Op("!")) {
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, ExpressionError> {
+        let left = self.parse_operand()?;
+        let op = match self.peek() {
+            Some(Token::Op("==")) => CompareOp::Eq,
+            Some(Token::Op("!=")) => CompareOp::Ne,
+            Some(Token::Op(">=")) => CompareOp::Ge,
+            Some(Token::Op("<=")) => CompareOp::Le,
+            Some(Token::Op(">")) => CompareOp::Gt,
+            Some(Token::Op("<")) => CompareOp::Lt,
+            _ => return Ok(left),
+        };
+        self.pos += 1;
+        let right = self.parse_operand()?;
+        Ok(Expr::Compare(Box::new(left), op, Box::new(right)))
+    }
+
+    fn parse_operand(&mut self) -> Result<Expr, ExpressionError> {
+        let offset = self.offset();
+        let Some((token, _)) = self.tokens.get(self.pos).cloned() else {
+            return Err(ExpressionError::parse("Expected a value but the expression ended".to_string(), offset));
+        };
+        self.pos += 1;
+
+        match token {
+            Token::Number(number) => Ok(Expr::Literal(serde_json::json!(number))),
+            Token::Str(text) => Ok(Expr::Literal(Value::String(text))),
+            Token::LParen => {
+                let expr = self.parse_or()?;
+                if !self.eat(&Token::RParen) {
+                    return Err(ExpressionError::parse("Expected ')'".to_string(), self.offset()));
+                }
+                Ok(expr)
+            }
+            Token::Word(word) => match word.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                "null" => Ok(Expr::Literal(Value::Null)),
+                _ if self.eat(&Token::LParen) => {
+                    let mut args = Vec::new();
+                    if !self.eat(&Token::RParen) {
+                        loop {
+                            args.push(self.parse_or()?);
+                            if self.eat(&Token::RParen) {
+                                break;
+                            }
+                            if !self.eat(&Token::Comma) {
+                                return Err(ExpressionError::parse(format!("Expected ',' or ')' in call to {}()", word), self.offset()));
+                            }
+                        }
+                    }
+                    Ok(Expr::Call(word, args))
+                }
+                _ => Reference::from_path(&word)
+                    .map(Expr::Reference)
+                    .map_err(|message| ExpressionError::parse(message, offset)),
+            },
+            other => Err(ExpressionError::parse(format!("Expected a value, found {:?}", other), offset)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RunStatus, StepStatus, WorkflowRun};
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_expressions_combine_paths_functions_and_logic() {
+        let payload = serde_json::json!({
+            "user": {"age": 21, "email": "ada@example.com", "roles": ["admin", "ops"]},
+            "status": "active",
+            "items": [{"sku": "A-1"}],
+        });
+        let run = WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: "conditions".to_string(),
+            status: RunStatus::Running,
+            payload: payload.clone(),
+            started_at: Utc::now(),
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        let fetched = StepResult {
+            step_id: "fetch".to_string(),
+            status: StepStatus::Completed,
+            output: Some(serde_json::json!({"count": "3"})),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(5),
+            cache_hit: None,
+            resources: None,
+        };
+        let context = Context::new(run.id.to_string(), run.workflow_id.clone(), "check".to_string(), payload, run, vec![]).unwrap();
+        let evaluator = ConditionEvaluator::new(context, vec![fetched]);
+        let met = |expr: &str| {
+            let result = evaluator.evaluate_condition(expr).unwrap();
+            assert!(result.error.is_none(), "{} failed: {:?}", expr, result.error);
+            result.met
+        };
+
+        assert!(met("payload.user.age >= 18 && ctx.payload.status == active"));
+        assert!(met("!(ctx.payload.user.age < 18) || false"));
+        assert!(met("contains(ctx.payload.user.email, '@example') && startsWith(ctx.payload.items.0.sku, \"A-\")"));
+        assert!(met("matches(ctx.payload.user.email, '^[a-z]+@') && endsWith(ctx.payload.user.email, '.com')"));
+        assert!(met("length(ctx.payload.user.roles) == 2 && includes(ctx.payload.user.roles, 'ops')"));
+        assert!(met("ctx.steps.fetch.output.count > 2 && ctx.steps.fetch.status && !ctx.steps.fetch.error"));
+        assert!(!met("ctx.payload.missing || ctx.steps.send.status == 'completed'"));
+
+        let result = evaluator.evaluate_condition("ctx.payload.user.age >= && true").unwrap();
+        assert!(!result.met);
+        assert_eq!(result.metadata["stage"], "parse");
+        assert_eq!(result.metadata["position"], 24);
+        let result = evaluator.evaluate_condition("length(ctx.payload.user.age)").unwrap();
+        assert_eq!(result.metadata["stage"], "evaluate");
+        assert!(result.error.unwrap().contains("length() expects"));
+    }
+}
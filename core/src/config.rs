@@ -3,7 +3,10 @@
 //! This module provides centralized configuration for all core components,
 //! supporting both default values and environment variable overrides.
 
+use std::collections::HashMap;
 use std::env;
+use crate::logging::{LogFilter, LogFormat};
+use crate::models::{NonIdempotentRecovery, OrphanRunPolicy};
 
 #[derive(Debug, Clone)]
 pub struct CoreConfig {
@@ -12,6 +15,17 @@ pub struct CoreConfig {
     pub webhook: WebhookConfig,
     pub database: DatabaseConfig,
     pub payload: PayloadConfig,
+    pub retention: RetentionConfig,
+    pub registration: RegistrationConfig,
+    pub trigger_rate_limit: TriggerRateLimitConfig,
+    pub trigger_stats: TriggerStatsConfig,
+    pub feature_flags: FeatureFlags,
+    pub secrets: SecretsConfig,
+    pub distributed: DistributedConfig,
+    pub hooks: HooksConfig,
+    pub telemetry: TelemetryConfig,
+    pub chaos: ChaosConfig,
+    pub logging: LoggingConfig,
 }
 
 #[derive(Debug, Clone)]
@@ -19,7 +33,156 @@ pub struct WorkerPoolConfig {
     pub min_workers: usize,
     pub max_workers: usize,
     pub worker_timeout_ms: u64,
+    /// How long a step that sent a heartbeat may go without the next one before it is considered hung
+    pub heartbeat_timeout_ms: u64,
     pub queue_size: usize,
+    /// Build contexts of downstream jobs as soon as a dependency completes
+    pub prefetch_contexts: bool,
+    /// Maximum number of prefetched contexts kept in memory
+    pub prefetch_cache_size: usize,
+    /// Caps on the workers, queue slots and start rate of each namespace, keyed by namespace;
+    /// nothing is held back for a namespace while it is idle
+    pub namespace_quotas: HashMap<String, NamespaceQuota>,
+    /// Worker allocation of each dispatcher lane, keyed by lane
+    pub lanes: HashMap<String, LaneAllocation>,
+    /// Number of the pool's workers carrying each capability tag, keyed by tag
+    pub worker_tags: HashMap<String, usize>,
+}
+
+/// Execution capacity of a namespace on the shared worker pool
+#[derive(Debug, Clone, PartialEq)]
+pub struct NamespaceQuota {
+    /// Maximum number of the namespace's jobs running at once
+    pub max_workers: usize,
+    /// Maximum number of the namespace's jobs waiting in the queue
+    pub queue_capacity: usize,
+    /// Maximum number of the namespace's jobs started per second
+    pub rate_limit_per_sec: Option<u32>,
+}
+
+impl NamespaceQuota {
+    /// Parse quotas written as `namespace=workers:queue[:rate],...`
+    pub fn parse_list(spec: &str) -> Result<HashMap<String, NamespaceQuota>, String> {
+        let mut quotas = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (namespace, limits) = entry.split_once('=')
+                .ok_or_else(|| format!("Invalid namespace quota '{}': expected namespace=workers:queue[:rate]", entry))?;
+            let parts: Vec<&str> = limits.split(':').collect();
+            if parts.len() < 2 || parts.len() > 3 {
+                return Err(format!("Invalid namespace quota '{}': expected namespace=workers:queue[:rate]", entry));
+            }
+            let parse = |value: &str| value.trim().parse::<usize>()
+                .map_err(|_| format!("Invalid number '{}' in namespace quota '{}'", value, entry));
+            let quota = NamespaceQuota {
+                max_workers: parse(parts[0])?,
+                queue_capacity: parse(parts[1])?,
+                rate_limit_per_sec: parts.get(2).map(|rate| parse(rate).map(|rate| rate as u32)).transpose()?,
+            };
+            quotas.insert(namespace.trim().to_string(), quota);
+        }
+        Ok(quotas)
+    }
+
+    /// Validate the quota
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_workers == 0 {
+            return Err("Namespace max workers must be greater than 0".to_string());
+        }
+        if self.queue_capacity == 0 {
+            return Err("Namespace queue capacity must be greater than 0".to_string());
+        }
+        if self.rate_limit_per_sec == Some(0) {
+            return Err("Namespace rate limit must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Worker allocation of a dispatcher lane
+///
+/// Runs execute in the lane their trigger or workflow picks, "default"
+/// otherwise. Reserved workers are held back from other lanes, so
+/// latency-sensitive runs find a free worker while a batch lane is busy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LaneAllocation {
+    /// Maximum number of the lane's jobs running at once
+    pub max_workers: usize,
+    /// Workers only the lane's jobs may use
+    pub reserved_workers: usize,
+}
+
+impl LaneAllocation {
+    /// Lane of runs that pick none
+    pub const DEFAULT_LANE: &'static str = "default";
+
+    /// Parse allocations written as `lane=max[:reserved],...`
+    pub fn parse_list(spec: &str) -> Result<HashMap<String, LaneAllocation>, String> {
+        let mut lanes = HashMap::new();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (lane, workers) = entry.split_once('=')
+                .ok_or_else(|| format!("Invalid lane allocation '{}': expected lane=max[:reserved]", entry))?;
+            let parts: Vec<&str> = workers.split(':').collect();
+            if parts.len() > 2 {
+                return Err(format!("Invalid lane allocation '{}': expected lane=max[:reserved]", entry));
+            }
+            let parse = |value: &str| value.trim().parse::<usize>()
+                .map_err(|_| format!("Invalid number '{}' in lane allocation '{}'", value, entry));
+            let lane = lane.trim();
+            Self::validate_name(lane)?;
+            let allocation = LaneAllocation {
+                max_workers: parse(parts[0])?,
+                reserved_workers: parts.get(1).map(|reserved| parse(reserved)).transpose()?.unwrap_or(0),
+            };
+            lanes.insert(lane.to_string(), allocation);
+        }
+        Ok(lanes)
+    }
+
+    /// Check that a lane name is usable in allocations and definitions
+    pub fn validate_name(lane: &str) -> Result<(), String> {
+        if lane.is_empty() || !lane.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(format!("Invalid lane name {:?}: use letters, digits, '-' and '_'", lane));
+        }
+        Ok(())
+    }
+
+    /// Validate the allocation
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_workers == 0 {
+            return Err("Lane max workers must be greater than 0".to_string());
+        }
+        if self.reserved_workers > self.max_workers {
+            return Err("Lane reserved workers cannot exceed its max workers".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Parse worker capability tags written as `tag=workers,...`
+///
+/// A tag given to N workers is carried by the first N workers of the pool, so
+/// `gpu=1,high-memory=2` gives worker-0 both tags and worker-1 the second.
+/// Steps declaring worker tags only run on workers carrying all of them.
+pub fn parse_worker_tags(spec: &str) -> Result<HashMap<String, usize>, String> {
+    let mut tags = HashMap::new();
+    for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (tag, workers) = entry.split_once('=')
+            .ok_or_else(|| format!("Invalid worker tag '{}': expected tag=workers", entry))?;
+        let tag = tag.trim();
+        validate_worker_tag(tag)?;
+        let workers = workers.trim().parse::<usize>()
+            .map_err(|_| format!("Invalid number '{}' in worker tag '{}'", workers, entry))?;
+        tags.insert(tag.to_string(), workers);
+    }
+    Ok(tags)
+}
+
+/// Check that a worker tag is usable in the pool configuration and step definitions
+pub fn validate_worker_tag(tag: &str) -> Result<(), String> {
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!("Invalid worker tag {:?}: use letters, digits, '-' and '_'", tag));
+    }
+    Ok(())
 }
 
 #[derive(Debug, Clone)]
@@ -32,6 +195,64 @@ pub struct ExecutionConfig {
     pub max_backoff_ms: u64,
     pub retry_jitter: bool,
     pub max_retries: u32,
+    /// Protection against retry spikes hammering a recovering service
+    pub retry_storm: RetryStormConfig,
+    /// How queued jobs are prioritized
+    pub priority: PriorityConfig,
+    /// What recovery and replay do with interrupted steps that are not idempotent
+    pub non_idempotent_recovery: NonIdempotentRecovery,
+    /// What startup recovery does with runs a crashed engine left running
+    pub orphan_run_policy: OrphanRunPolicy,
+    /// Seconds without activity after which a running run in a shared store, or in distributed mode, counts as orphaned
+    pub orphan_run_lease_secs: u64,
+    /// How long results of ad hoc step executions are kept
+    pub adhoc_result_ttl_secs: u64,
+    /// How long a failed step waits for the registered retry classifier's decision
+    pub retry_classifier_timeout_ms: u64,
+}
+
+/// Retry storm detection and mitigation
+///
+/// When retries of the same step spike across runs, further retries of that
+/// step get extra random delay and only a few of them may run at once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryStormConfig {
+    /// Retries of one step within the window that count as a storm
+    pub threshold: usize,
+    pub window_ms: u64,
+    /// Maximum retries of one step running at once during a storm
+    pub max_concurrent_retries: usize,
+    /// Upper bound of the extra delay added to each retry during a storm
+    pub jitter_ms: u64,
+}
+
+impl RetryStormConfig {
+    /// Validate the storm settings
+    pub fn validate(&self) -> Result<(), String> {
+        if self.threshold == 0 {
+            return Err("Retry storm threshold must be greater than 0".to_string());
+        }
+        if self.window_ms == 0 {
+            return Err("Retry storm window must be greater than 0".to_string());
+        }
+        if self.max_concurrent_retries == 0 {
+            return Err("Retry storm concurrent retries must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Derivation of job priorities in the dispatcher queue
+///
+/// Jobs start with the priority declared on their step or workflow, steps many
+/// others wait on are boosted, and waiting jobs gain priority over time so low
+/// priority work is not starved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PriorityConfig {
+    /// Downstream steps from which a step runs one level higher; 0 disables the boost
+    pub critical_path_dependents: usize,
+    /// Seconds a queued job waits per priority level it gains; 0 disables aging
+    pub aging_secs: u64,
 }
 
 /// Webhook server configuration
@@ -47,6 +268,87 @@ pub struct DatabaseConfig {
     pub default_path: String,
     pub connection_timeout_ms: u64,
     pub max_connections: usize,
+    /// Shared state store URL (`postgres://...`); the local SQLite file is used when unset
+    pub url: Option<String>,
+    /// SQLite journal mode: DELETE, TRUNCATE, PERSIST, MEMORY, WAL or OFF
+    pub journal_mode: String,
+    /// SQLite synchronous level: OFF, NORMAL, FULL or EXTRA
+    pub synchronous: String,
+    /// How long a write waits for another connection's lock before failing with "database is locked"
+    pub busy_timeout_ms: u64,
+    /// SQLite page cache size; negative values are KiB, positive values pages
+    pub cache_size: i64,
+    /// Bytes of the database file memory-mapped for reads; 0 disables mmap
+    pub mmap_size: u64,
+    /// Separate SQLite files holding the runs of some workflows or namespaces
+    pub isolated_stores: StoreRoutes,
+}
+
+/// Routes of workflows' runs to separate SQLite files
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StoreRoutes {
+    /// Store file by workflow ID
+    pub workflows: HashMap<String, String>,
+    /// Store file by namespace
+    pub namespaces: HashMap<String, String>,
+}
+
+impl StoreRoutes {
+    /// Parse routes written as `workflow:<id>=<path>` or `namespace:<name>=<path>`, comma-separated
+    pub fn parse_list(spec: &str) -> Result<Self, String> {
+        let mut routes = StoreRoutes::default();
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let invalid = || format!("Invalid store route '{}': expected workflow:<id>=<path> or namespace:<name>=<path>", entry);
+            let (target, path) = entry.split_once('=').ok_or_else(invalid)?;
+            let (kind, name) = target.split_once(':').ok_or_else(invalid)?;
+            let routes_of_kind = match kind.trim() {
+                "workflow" => &mut routes.workflows,
+                "namespace" => &mut routes.namespaces,
+                _ => return Err(invalid()),
+            };
+            routes_of_kind.insert(name.trim().to_string(), path.trim().to_string());
+        }
+        Ok(routes)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.workflows.is_empty() && self.namespaces.is_empty()
+    }
+
+    /// Get the store file of a workflow's runs, a route of the workflow winning over one of its namespace
+    pub fn route(&self, workflow_id: &str, namespace: Option<&str>) -> Option<&str> {
+        self.workflows.get(workflow_id)
+            .or_else(|| namespace.and_then(|namespace| self.namespaces.get(namespace)))
+            .map(String::as_str)
+    }
+
+    /// Get every store file routed to
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.workflows.values().chain(self.namespaces.values()).map(String::as_str)
+    }
+}
+
+impl DatabaseConfig {
+    pub const JOURNAL_MODES: [&'static str; 6] = ["DELETE", "TRUNCATE", "PERSIST", "MEMORY", "WAL", "OFF"];
+    pub const SYNCHRONOUS_LEVELS: [&'static str; 4] = ["OFF", "NORMAL", "FULL", "EXTRA"];
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !Self::JOURNAL_MODES.contains(&self.journal_mode.to_uppercase().as_str()) {
+            return Err(format!("Unknown SQLite journal mode: {}", self.journal_mode));
+        }
+        if !Self::SYNCHRONOUS_LEVELS.contains(&self.synchronous.to_uppercase().as_str()) {
+            return Err(format!("Unknown SQLite synchronous level: {}", self.synchronous));
+        }
+        if !self.isolated_stores.is_empty() && self.url.is_some() {
+            return Err("Isolated stores cannot be used with a shared state store".to_string());
+        }
+        for path in self.isolated_stores.paths() {
+            if path.is_empty() || path == self.default_path {
+                return Err(format!("Isolated store path must be a separate file: {:?}", path));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -56,6 +358,270 @@ pub struct PayloadConfig {
     pub medium_payload_threshold: usize,
     pub max_step_count_large: usize,
     pub max_step_count_medium: usize,
+    /// Largest step output accepted, in serialized bytes
+    pub max_step_output_bytes: usize,
+    /// Step outputs larger than this are stored as separate blobs
+    pub step_output_offload_bytes: usize,
+}
+
+/// Run history retention and cold storage export configuration
+#[derive(Debug, Clone)]
+pub struct RetentionConfig {
+    pub cold_storage_dir: String,
+    pub cold_storage_after_days: u32,
+    pub cold_storage_row_group_size: usize,
+    /// Finished runs older than this are deleted; `None` keeps them forever
+    pub max_run_age_days: Option<u32>,
+    /// Finished runs kept per workflow, newest first; `None` keeps all
+    pub max_runs_per_workflow: Option<usize>,
+    /// Age limit for failed runs, overriding `max_run_age_days` so they can be kept longer
+    pub failed_run_age_days: Option<u32>,
+    /// Seconds between automatic cleanup passes
+    pub cleanup_interval_secs: u64,
+}
+
+impl RetentionConfig {
+    /// Whether any limit is set, i.e. whether cleanup can delete anything
+    pub fn prunes_runs(&self) -> bool {
+        self.max_run_age_days.is_some() || self.max_runs_per_workflow.is_some() || self.failed_run_age_days.is_some()
+    }
+}
+
+/// Token bucket rate: sustained triggers per second plus a burst allowance
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub per_sec: f64,
+    pub burst: u32,
+}
+
+impl RateLimit {
+    /// Parse a rate written as `per_sec[:burst]`, the burst defaulting to the rate
+    pub fn parse(spec: &str) -> Result<RateLimit, String> {
+        let (rate, burst) = match spec.split_once(':') {
+            Some((rate, burst)) => (rate, Some(burst)),
+            None => (spec, None),
+        };
+        let per_sec: f64 = rate.trim().parse()
+            .map_err(|_| format!("Invalid rate '{}' in rate limit '{}'", rate, spec))?;
+        let burst = match burst {
+            Some(burst) => burst.trim().parse()
+                .map_err(|_| format!("Invalid burst '{}' in rate limit '{}'", burst, spec))?,
+            None => per_sec.ceil().max(1.0) as u32,
+        };
+        Ok(RateLimit { per_sec, burst })
+    }
+
+    /// Validate the rate
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.per_sec > 0.0 && self.per_sec.is_finite()) {
+            return Err("Rate limit must be greater than 0 per second".to_string());
+        }
+        if self.burst == 0 {
+            return Err("Rate limit burst must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Trigger execution rate limiting, applied before runs are created
+#[derive(Debug, Clone)]
+pub struct TriggerRateLimitConfig {
+    /// Limit across all workflows
+    pub global: Option<RateLimit>,
+    /// Limit applied to each workflow separately
+    pub per_workflow: Option<RateLimit>,
+}
+
+/// Per-trigger statistics kept by the trigger executor and webhook server
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerStatsConfig {
+    /// Span of the recent firings latency percentiles are computed over
+    pub latency_window_secs: u64,
+    /// How often firing counts are added to the database
+    pub persist_interval_secs: u64,
+}
+
+/// Workflow registration configuration
+#[derive(Debug, Clone)]
+pub struct RegistrationConfig {
+    /// Reject workflow JSON containing fields the engine does not recognize
+    pub strict_workflows: bool,
+}
+
+/// Secrets store configuration
+#[derive(Debug, Clone)]
+pub struct SecretsConfig {
+    /// Hex-encoded 256-bit key secrets are encrypted with at rest; secrets cannot be stored without it
+    pub encryption_key: Option<String>,
+}
+
+/// Several engine processes sharing one database
+///
+/// Each process keeps its own queue, and a job runs on the process whose
+/// worker claims it first: claims are leases in the shared store that the
+/// worker renews while the job runs. Jobs submitted by other processes, and
+/// jobs whose worker stopped renewing its lease, are pulled from the store.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistributedConfig {
+    /// Claim jobs through leases and pull jobs submitted by other processes
+    pub enabled: bool,
+    /// Identifies this process in job leases
+    pub node_id: String,
+    /// How long a claim holds without being renewed
+    pub lease_ms: u64,
+    /// How often the store is checked for jobs to pull
+    pub poll_interval_ms: u64,
+}
+
+/// Delivery of onSuccess/onFailure/onCancel hooks to Node
+#[derive(Debug, Clone, PartialEq)]
+pub struct HooksConfig {
+    /// Attempts made at a hook, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry of a failed hook, doubled for each later retry
+    pub retry_delay_ms: u64,
+    /// How long Node has to report a hook's outcome before the attempt counts as failed
+    pub ack_timeout_ms: u64,
+}
+
+/// Export of run and step traces to an OpenTelemetry collector
+#[derive(Debug, Clone, PartialEq)]
+pub struct TelemetryConfig {
+    /// OTLP/HTTP endpoint traces are sent to; tracing is off when unset
+    pub otlp_endpoint: Option<String>,
+    /// Service name the spans are reported under
+    pub service_name: String,
+}
+
+/// Failure injection, for testing how workflows handle failing steps
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChaosConfig {
+    /// Allow faults to be injected through the bridge; never enable it in production
+    pub enabled: bool,
+    /// Seed of the fault decisions when the rules do not set one
+    pub seed: u64,
+}
+
+/// Engine log output
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggingConfig {
+    /// Default level and per-module overrides, written as `info,core::dispatcher=debug`
+    pub filter: String,
+    pub format: LogFormat,
+    /// File log lines are appended to instead of stderr
+    pub file: Option<String>,
+    /// Size at which the log file is rotated
+    pub max_file_bytes: u64,
+    /// Rotated log files kept next to the current one
+    pub max_files: usize,
+}
+
+/// Engine feature flags, so risky subsystems can ship dark and be enabled per deployment
+///
+/// Flags are set with `CRONFLOW_FEATURE_FLAGS`, written as `name=on|off,...`
+/// or `name=workflow-a|workflow-b` to enable a flag for some workflows only.
+/// Flags that are not set keep their default.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureFlags {
+    settings: HashMap<String, FlagSetting>,
+}
+
+/// Setting of a single feature flag
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlagSetting {
+    On,
+    Off,
+    /// Enabled for the listed workflows only
+    Workflows(Vec<String>),
+}
+
+/// Feature flag checked by the engine
+#[derive(Debug, Clone, Copy)]
+pub struct FeatureFlag {
+    pub name: &'static str,
+    pub default: bool,
+    pub description: &'static str,
+}
+
+impl FeatureFlags {
+    /// Record the state of every dispatched job in the state store
+    pub const JOB_RECORDING: &'static str = "job_recording";
+    /// Persist worker diagnostics after every job
+    pub const WORKER_DIAGNOSTICS: &'static str = "worker_diagnostics";
+
+    /// Flags the engine checks
+    pub const KNOWN: &'static [FeatureFlag] = &[
+        FeatureFlag {
+            name: Self::JOB_RECORDING,
+            default: true,
+            description: "Record the state of every dispatched job in the state store",
+        },
+        FeatureFlag {
+            name: Self::WORKER_DIAGNOSTICS,
+            default: true,
+            description: "Persist worker diagnostics after every job",
+        },
+    ];
+
+    /// Parse flags written as `name=on|off` or `name=workflow-a|workflow-b`, comma separated
+    pub fn parse(spec: &str) -> Result<FeatureFlags, String> {
+        let mut flags = FeatureFlags { settings: HashMap::new() };
+        for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+            let (name, value) = entry.split_once('=')
+                .ok_or_else(|| format!("Invalid feature flag '{}': expected name=on|off|workflows", entry))?;
+            let setting = match value.trim().to_ascii_lowercase().as_str() {
+                "on" | "true" | "1" => FlagSetting::On,
+                "off" | "false" | "0" => FlagSetting::Off,
+                _ => {
+                    let workflows: Vec<String> = value.split('|')
+                        .map(str::trim)
+                        .filter(|id| !id.is_empty())
+                        .map(str::to_string)
+                        .collect();
+                    if workflows.is_empty() {
+                        return Err(format!("Invalid feature flag '{}': no workflows given", entry));
+                    }
+                    FlagSetting::Workflows(workflows)
+                }
+            };
+            flags.set(name.trim(), setting);
+        }
+        Ok(flags)
+    }
+
+    /// Set a flag, replacing its previous setting
+    pub fn set(&mut self, name: &str, setting: FlagSetting) {
+        self.settings.insert(name.to_string(), setting);
+    }
+
+    /// Get the explicit setting of a flag, if any
+    pub fn setting(&self, name: &str) -> Option<&FlagSetting> {
+        self.settings.get(name)
+    }
+
+    /// Whether a flag is enabled, for a given workflow when one is known
+    ///
+    /// A flag scoped to workflows is disabled for checks made outside any workflow.
+    pub fn is_enabled(&self, name: &str, workflow_id: Option<&str>) -> bool {
+        match self.settings.get(name) {
+            Some(FlagSetting::On) => true,
+            Some(FlagSetting::Off) => false,
+            Some(FlagSetting::Workflows(workflows)) => {
+                workflow_id.is_some_and(|id| workflows.iter().any(|w| w == id))
+            }
+            None => Self::KNOWN.iter().any(|flag| flag.name == name && flag.default),
+        }
+    }
+
+    /// Validate that only known flags are set
+    pub fn validate(&self) -> Result<(), String> {
+        for name in self.settings.keys() {
+            if !Self::KNOWN.iter().any(|flag| flag.name == name) {
+                return Err(format!("Unknown feature flag '{}'", name));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for CoreConfig {
@@ -66,6 +632,17 @@ impl Default for CoreConfig {
             webhook: WebhookConfig::default(),
             database: DatabaseConfig::default(),
             payload: PayloadConfig::default(),
+            retention: RetentionConfig::default(),
+            registration: RegistrationConfig::default(),
+            trigger_rate_limit: TriggerRateLimitConfig::default(),
+            trigger_stats: TriggerStatsConfig::default(),
+            feature_flags: FeatureFlags::default(),
+            secrets: SecretsConfig::default(),
+            distributed: DistributedConfig::default(),
+            hooks: HooksConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            chaos: ChaosConfig::default(),
+            logging: LoggingConfig::default(),
         }
     }
 }
@@ -85,10 +662,34 @@ impl Default for WorkerPoolConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(30000), // 30 seconds
+            heartbeat_timeout_ms: env::var("CRONFLOW_HEARTBEAT_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30000),
             queue_size: env::var("CRONFLOW_QUEUE_SIZE")
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(1000),
+            prefetch_contexts: env::var("CRONFLOW_PREFETCH_CONTEXTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(true),
+            prefetch_cache_size: env::var("CRONFLOW_PREFETCH_CACHE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            namespace_quotas: env::var("CRONFLOW_NAMESPACE_QUOTAS")
+                .ok()
+                .and_then(|v| NamespaceQuota::parse_list(&v).ok())
+                .unwrap_or_default(),
+            lanes: env::var("CRONFLOW_WORKER_LANES")
+                .ok()
+                .and_then(|v| LaneAllocation::parse_list(&v).ok())
+                .unwrap_or_default(),
+            worker_tags: env::var("CRONFLOW_WORKER_TAGS")
+                .ok()
+                .and_then(|v| parse_worker_tags(&v).ok())
+                .unwrap_or_default(),
         }
     }
 }
@@ -129,6 +730,66 @@ impl Default for ExecutionConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(3),
+            retry_storm: RetryStormConfig::default(),
+            priority: PriorityConfig::default(),
+            non_idempotent_recovery: env::var("CRONFLOW_NON_IDEMPOTENT_RECOVERY")
+                .ok()
+                .and_then(|v| NonIdempotentRecovery::parse(&v))
+                .unwrap_or_default(),
+            orphan_run_policy: env::var("CRONFLOW_ORPHAN_RUN_POLICY")
+                .ok()
+                .and_then(|v| OrphanRunPolicy::parse(&v))
+                .unwrap_or_default(),
+            orphan_run_lease_secs: env::var("CRONFLOW_ORPHAN_RUN_LEASE_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300),
+            adhoc_result_ttl_secs: env::var("CRONFLOW_ADHOC_RESULT_TTL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3600), // 1 hour
+            retry_classifier_timeout_ms: env::var("CRONFLOW_RETRY_CLASSIFIER_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5000),
+        }
+    }
+}
+
+impl Default for RetryStormConfig {
+    fn default() -> Self {
+        Self {
+            threshold: env::var("CRONFLOW_RETRY_STORM_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(20),
+            window_ms: env::var("CRONFLOW_RETRY_STORM_WINDOW_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10_000),
+            max_concurrent_retries: env::var("CRONFLOW_RETRY_STORM_MAX_CONCURRENT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(2),
+            jitter_ms: env::var("CRONFLOW_RETRY_STORM_JITTER_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5_000),
+        }
+    }
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        Self {
+            critical_path_dependents: env::var("CRONFLOW_PRIORITY_CRITICAL_PATH_DEPENDENTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            aging_secs: env::var("CRONFLOW_PRIORITY_AGING_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30),
         }
     }
 }
@@ -163,6 +824,27 @@ impl Default for DatabaseConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(10),
+            url: env::var("CRONFLOW_DATABASE_URL").ok().filter(|v| !v.is_empty()),
+            journal_mode: env::var("CRONFLOW_DB_JOURNAL_MODE")
+                .unwrap_or_else(|_| "WAL".to_string()),
+            synchronous: env::var("CRONFLOW_DB_SYNCHRONOUS")
+                .unwrap_or_else(|_| "NORMAL".to_string()),
+            busy_timeout_ms: env::var("CRONFLOW_DB_BUSY_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5000),
+            cache_size: env::var("CRONFLOW_DB_CACHE_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(-16_000), // 16MB
+            mmap_size: env::var("CRONFLOW_DB_MMAP_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+            isolated_stores: env::var("CRONFLOW_DB_ISOLATED_STORES")
+                .ok()
+                .and_then(|v| StoreRoutes::parse_list(&v).ok())
+                .unwrap_or_default(),
         }
     }
 }
@@ -190,10 +872,266 @@ impl Default for PayloadConfig {
                 .ok()
                 .and_then(|v| v.parse().ok())
                 .unwrap_or(10),
+            max_step_output_bytes: env::var("CRONFLOW_MAX_STEP_OUTPUT_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(100_000_000), // 100MB
+            step_output_offload_bytes: env::var("CRONFLOW_STEP_OUTPUT_OFFLOAD_THRESHOLD")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(64 * 1024), // 64KB
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            cold_storage_dir: env::var("CRONFLOW_COLD_STORAGE_DIR")
+                .unwrap_or_else(|_| ".cronflow/cold-storage".to_string()),
+            cold_storage_after_days: env::var("CRONFLOW_COLD_STORAGE_AFTER_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(7),
+            cold_storage_row_group_size: env::var("CRONFLOW_COLD_STORAGE_ROW_GROUP_SIZE")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10_000),
+            max_run_age_days: env::var("CRONFLOW_RUN_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            max_runs_per_workflow: env::var("CRONFLOW_MAX_RUNS_PER_WORKFLOW")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            failed_run_age_days: env::var("CRONFLOW_FAILED_RUN_RETENTION_DAYS")
+                .ok()
+                .and_then(|v| v.parse().ok()),
+            cleanup_interval_secs: env::var("CRONFLOW_RETENTION_INTERVAL_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3600),
+        }
+    }
+}
+
+impl Default for TriggerStatsConfig {
+    fn default() -> Self {
+        Self {
+            latency_window_secs: env::var("CRONFLOW_TRIGGER_LATENCY_WINDOW_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(300),
+            persist_interval_secs: env::var("CRONFLOW_TRIGGER_STATS_PERSIST_SECS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60),
+        }
+    }
+}
+
+impl Default for RegistrationConfig {
+    fn default() -> Self {
+        Self {
+            strict_workflows: env::var("CRONFLOW_STRICT_WORKFLOWS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(false),
         }
     }
 }
 
+impl Default for SecretsConfig {
+    fn default() -> Self {
+        Self {
+            encryption_key: env::var("CRONFLOW_SECRETS_KEY").ok().filter(|v| !v.is_empty()),
+        }
+    }
+}
+
+impl Default for DistributedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: env::var("CRONFLOW_DISTRIBUTED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            node_id: env::var("CRONFLOW_NODE_ID")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| format!(
+                    "{}-{}",
+                    env::var("HOSTNAME").unwrap_or_else(|_| "node".to_string()),
+                    std::process::id()
+                )),
+            lease_ms: env::var("CRONFLOW_JOB_LEASE_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30_000),
+            poll_interval_ms: env::var("CRONFLOW_JOB_POLL_INTERVAL_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+        }
+    }
+}
+
+impl DistributedConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.node_id.contains('/') {
+            return Err("Node ID cannot contain '/'".to_string());
+        }
+        if self.lease_ms == 0 {
+            return Err("Job lease must be greater than 0".to_string());
+        }
+        if self.poll_interval_ms == 0 {
+            return Err("Job poll interval must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+
+    /// Owner recorded in the leases a worker of this process takes
+    pub fn lease_owner(&self, worker_id: &str) -> String {
+        format!("{}/{}", self.node_id, worker_id)
+    }
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: env::var("CRONFLOW_HOOK_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(3),
+            retry_delay_ms: env::var("CRONFLOW_HOOK_RETRY_DELAY_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(1000),
+            ack_timeout_ms: env::var("CRONFLOW_HOOK_ACK_TIMEOUT_MS")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(30_000),
+        }
+    }
+}
+
+impl HooksConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("Hook attempts must be greater than 0".to_string());
+        }
+        if self.ack_timeout_ms == 0 {
+            return Err("Hook acknowledgement timeout must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            otlp_endpoint: env::var("CRONFLOW_OTLP_ENDPOINT")
+                .or_else(|_| env::var("OTEL_EXPORTER_OTLP_ENDPOINT"))
+                .ok()
+                .filter(|v| !v.trim().is_empty()),
+            service_name: env::var("CRONFLOW_OTEL_SERVICE_NAME")
+                .or_else(|_| env::var("OTEL_SERVICE_NAME"))
+                .unwrap_or_else(|_| "cronflow".to_string()),
+        }
+    }
+}
+
+impl Default for ChaosConfig {
+    fn default() -> Self {
+        Self {
+            enabled: env::var("CRONFLOW_CHAOS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            seed: env::var("CRONFLOW_CHAOS_SEED")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0),
+        }
+    }
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            filter: env::var("CRONFLOW_LOG")
+                .or_else(|_| env::var("RUST_LOG"))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .unwrap_or_else(|| "info".to_string()),
+            format: env::var("CRONFLOW_LOG_FORMAT")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(LogFormat::Text),
+            file: env::var("CRONFLOW_LOG_FILE").ok().filter(|v| !v.trim().is_empty()),
+            max_file_bytes: env::var("CRONFLOW_LOG_FILE_MAX_BYTES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(10 * 1024 * 1024),
+            max_files: env::var("CRONFLOW_LOG_FILE_MAX_FILES")
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(5),
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        LogFilter::parse(&self.filter)?;
+        if self.file.is_some() && self.max_file_bytes == 0 {
+            return Err("Log file size limit must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl TelemetryConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(endpoint) = &self.otlp_endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                return Err(format!("OTLP endpoint must be an http(s) URL, got '{}'", endpoint));
+            }
+        }
+        if self.service_name.trim().is_empty() {
+            return Err("Telemetry service name cannot be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl SecretsConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.encryption_key {
+            Some(key) if hex::decode(key).map(|bytes| bytes.len()) != Ok(32) => {
+                Err("Secrets encryption key must be 64 hex characters".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for TriggerRateLimitConfig {
+    fn default() -> Self {
+        let read = |name: &str| env::var(name).ok().and_then(|v| RateLimit::parse(&v).ok());
+        Self {
+            global: read("CRONFLOW_TRIGGER_RATE_LIMIT"),
+            per_workflow: read("CRONFLOW_WORKFLOW_TRIGGER_RATE_LIMIT"),
+        }
+    }
+}
+
+impl Default for FeatureFlags {
+    fn default() -> Self {
+        env::var("CRONFLOW_FEATURE_FLAGS")
+            .ok()
+            .and_then(|v| FeatureFlags::parse(&v).ok())
+            .unwrap_or_else(|| FeatureFlags { settings: HashMap::new() })
+    }
+}
+
 impl CoreConfig {
     pub fn new() -> Self {
         Self::default()
@@ -216,6 +1154,32 @@ impl CoreConfig {
             return Err("Queue size must be greater than 0".to_string());
         }
 
+        for (namespace, quota) in &self.worker_pool.namespace_quotas {
+            quota.validate().map_err(|e| format!("{} ({})", e, namespace))?;
+            if quota.max_workers > self.worker_pool.max_workers {
+                return Err(format!("Namespace {} max workers cannot exceed the worker pool size", namespace));
+            }
+        }
+
+        for (lane, allocation) in &self.worker_pool.lanes {
+            allocation.validate().map_err(|e| format!("{} ({})", e, lane))?;
+            if allocation.max_workers > self.worker_pool.max_workers {
+                return Err(format!("Lane {} max workers cannot exceed the worker pool size", lane));
+            }
+        }
+        // Reservations are held out of the workers started with the pool
+        let reserved: usize = self.worker_pool.lanes.values().map(|allocation| allocation.reserved_workers).sum();
+        if reserved > self.worker_pool.min_workers {
+            return Err(format!("Lanes reserve {} workers, more than the {} the pool starts with", reserved, self.worker_pool.min_workers));
+        }
+        
+        // Tags are handed out to the workers started with the pool
+        for (tag, workers) in &self.worker_pool.worker_tags {
+            if *workers == 0 || *workers > self.worker_pool.min_workers {
+                return Err(format!("Worker tag {} must be carried by 1 to {} workers", tag, self.worker_pool.min_workers));
+            }
+        }
+
         if self.execution.max_concurrent_steps == 0 {
             return Err("Max concurrent steps must be greater than 0".to_string());
         }
@@ -224,10 +1188,54 @@ impl CoreConfig {
             return Err("Retry attempts must be greater than 0".to_string());
         }
 
+        self.database.validate()?;
+
         if self.payload.max_size_bytes == 0 {
             return Err("Max payload size must be greater than 0".to_string());
         }
 
+        if self.payload.max_step_output_bytes == 0 {
+            return Err("Max step output size must be greater than 0".to_string());
+        }
+
+        self.execution.retry_storm.validate()?;
+
+        if self.execution.retry_classifier_timeout_ms == 0 {
+            return Err("Retry classifier timeout must be greater than 0".to_string());
+        }
+
+        if self.retention.cold_storage_row_group_size == 0 {
+            return Err("Cold storage row group size must be greater than 0".to_string());
+        }
+
+        if self.retention.max_runs_per_workflow == Some(0) {
+            return Err("Max runs per workflow must be greater than 0".to_string());
+        }
+
+        if self.retention.cleanup_interval_secs == 0 {
+            return Err("Retention cleanup interval must be greater than 0".to_string());
+        }
+
+        for limit in [&self.trigger_rate_limit.global, &self.trigger_rate_limit.per_workflow].into_iter().flatten() {
+            limit.validate()?;
+        }
+
+        if self.trigger_stats.latency_window_secs == 0 {
+            return Err("Trigger latency window must be greater than 0".to_string());
+        }
+
+        if self.trigger_stats.persist_interval_secs == 0 {
+            return Err("Trigger stats persist interval must be greater than 0".to_string());
+        }
+
+        self.feature_flags.validate()?;
+
+        self.secrets.validate()?;
+        self.distributed.validate()?;
+        self.hooks.validate()?;
+        self.telemetry.validate()?;
+        self.logging.validate()?;
+
         Ok(())
     }
 }
@@ -250,15 +1258,33 @@ mod tests {
         assert_eq!(config.worker_pool.max_workers, 10);
         assert_eq!(config.worker_pool.worker_timeout_ms, 30000);
         assert_eq!(config.worker_pool.queue_size, 1000);
+        assert!(config.worker_pool.prefetch_contexts);
+        assert_eq!(config.worker_pool.prefetch_cache_size, 1000);
+        assert!(config.worker_pool.namespace_quotas.is_empty());
         
         assert_eq!(config.execution.max_concurrent_steps, 10);
         assert_eq!(config.execution.default_timeout_ms, Some(30000));
         assert_eq!(config.execution.fail_fast, true);
         assert_eq!(config.execution.retry_attempts, 3);
+        assert_eq!(config.execution.retry_storm.threshold, 20);
+        assert_eq!(config.execution.retry_storm.max_concurrent_retries, 2);
+        assert_eq!(config.execution.orphan_run_policy, OrphanRunPolicy::Requeue);
+        assert_eq!(config.execution.orphan_run_lease_secs, 300);
         
         assert_eq!(config.payload.max_size_bytes, 10_000_000);
         assert_eq!(config.payload.large_payload_threshold, 100_000);
         assert_eq!(config.payload.medium_payload_threshold, 10_000);
+        assert_eq!(config.payload.max_step_output_bytes, 100_000_000);
+        assert_eq!(config.payload.step_output_offload_bytes, 64 * 1024);
+
+        assert_eq!(config.retention.cold_storage_dir, ".cronflow/cold-storage");
+        assert_eq!(config.retention.cold_storage_after_days, 7);
+        assert!(!config.retention.prunes_runs());
+        assert_eq!(config.retention.cleanup_interval_secs, 3600);
+        assert!(!config.registration.strict_workflows);
+        assert!(config.trigger_rate_limit.global.is_none());
+        assert!(config.trigger_rate_limit.per_workflow.is_none());
+        assert!(config.secrets.encryption_key.is_none());
     }
 
     #[test]
@@ -276,4 +1302,52 @@ mod tests {
         config.worker_pool.max_workers = 10;
         assert!(config.validate().is_ok());
     }
+
+    #[test]
+    fn test_feature_flag_parsing_and_scoping() {
+        let flags = FeatureFlags::parse("job_recording=off, worker_diagnostics=orders|billing").unwrap();
+        assert_eq!(flags.setting(FeatureFlags::JOB_RECORDING), Some(&FlagSetting::Off));
+        assert!(!flags.is_enabled(FeatureFlags::JOB_RECORDING, Some("orders")));
+        assert!(flags.is_enabled(FeatureFlags::WORKER_DIAGNOSTICS, Some("billing")));
+        assert!(!flags.is_enabled(FeatureFlags::WORKER_DIAGNOSTICS, Some("reports")));
+        assert!(!flags.is_enabled(FeatureFlags::WORKER_DIAGNOSTICS, None));
+        assert!(flags.validate().is_ok());
+
+        let defaults = FeatureFlags::parse("").unwrap();
+        assert!(defaults.is_enabled(FeatureFlags::JOB_RECORDING, None));
+        assert!(!defaults.is_enabled("not_a_flag", None));
+
+        assert!(FeatureFlags::parse("job_recording").is_err());
+        assert!(FeatureFlags::parse("job_recording=|").is_err());
+        assert!(FeatureFlags::parse("new_scheduler=on").unwrap().validate().is_err());
+    }
+
+    #[test]
+    fn test_namespace_quota_parsing_and_validation() {
+        let quotas = NamespaceQuota::parse_list("tenant-a=4:100:50, tenant-b=2:20").unwrap();
+        assert_eq!(quotas["tenant-a"], NamespaceQuota { max_workers: 4, queue_capacity: 100, rate_limit_per_sec: Some(50) });
+        assert_eq!(quotas["tenant-b"].rate_limit_per_sec, None);
+        assert!(NamespaceQuota::parse_list("tenant-a=4").is_err());
+        assert!(NamespaceQuota::parse_list("tenant-a=x:1").is_err());
+
+        let mut config = CoreConfig::default();
+        config.worker_pool.namespace_quotas = quotas;
+        assert!(config.validate().is_ok());
+
+        config.worker_pool.namespace_quotas.get_mut("tenant-b").unwrap().max_workers = 50;
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_rate_limit_parsing_and_validation() {
+        assert_eq!(RateLimit::parse("2.5:10").unwrap(), RateLimit { per_sec: 2.5, burst: 10 });
+        assert_eq!(RateLimit::parse("5").unwrap().burst, 5);
+        assert!(RateLimit::parse("fast").is_err());
+
+        let mut config = CoreConfig::default();
+        config.trigger_rate_limit.global = Some(RateLimit { per_sec: 0.0, burst: 1 });
+        assert!(config.validate().is_err());
+        config.trigger_rate_limit.global = Some(RateLimit { per_sec: 100.0, burst: 200 });
+        assert!(config.validate().is_ok());
+    }
 }
@@ -1,8 +1,15 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::models::{WorkflowRun, StepResult};
+use chrono::{DateTime, Utc};
+use crate::models::{WorkflowRun, StepResult, RunEvent, RunTrigger, RunLabels};
 use crate::error::CoreError;
 
+/// Version of the serialized context layout shared with Bun.js
+pub const CONTEXT_SCHEMA_VERSION: u32 = 1;
+
+/// Version of the engine issuing contexts
+pub const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
 /// Context object passed to Bun.js for job execution
 /// Contains all necessary information for step execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,6 +28,21 @@ pub struct Context {
     pub run: WorkflowRun,
     /// Metadata about the execution
     pub metadata: ContextMetadata,
+    /// Context state shared across steps, as of when the context was built
+    #[serde(default)]
+    pub state: ContextState,
+    /// Secrets of the workflow, decrypted
+    #[serde(default)]
+    pub secrets: HashMap<String, String>,
+    /// Attempt, retry and trigger details of this execution
+    #[serde(default)]
+    pub execution: ExecutionInfo,
+    /// The step's input mapping, rendered against this context
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<serde_json::Value>,
+    /// Labels of the run, as of when the context was built
+    #[serde(default, skip_serializing_if = "RunLabels::is_empty")]
+    pub labels: RunLabels,
     /// Serialization metadata for performance tracking
     #[serde(skip_serializing_if = "Option::is_none")]
     pub serialization_info: Option<SerializationInfo>,
@@ -46,6 +68,95 @@ pub struct ContextMetadata {
     /// Checksum for data integrity
     #[serde(skip_serializing_if = "Option::is_none")]
     pub checksum: Option<String>,
+    /// Serialized context layout version
+    #[serde(default)]
+    pub schema_version: u32,
+}
+
+/// Snapshot of the key/value state shared across steps
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContextState {
+    /// State shared by the steps of this run
+    pub run: HashMap<String, serde_json::Value>,
+    /// State shared by every run of the workflow
+    pub workflow: HashMap<String, serde_json::Value>,
+}
+
+/// Details of the execution a context was issued for
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionInfo {
+    /// Attempt number, 1 for the first execution of the step
+    pub attempt: u32,
+    pub is_retry: bool,
+    /// Earlier failed attempts of the step, oldest first
+    pub retry_history: Vec<RetryAttempt>,
+    /// What started the run, None for runs created without a trigger
+    pub trigger: Option<RunTrigger>,
+    pub engine_version: String,
+    /// When the step was queued, when it went through the job queue
+    pub queued_at: Option<DateTime<Utc>>,
+    /// When the context was handed out for execution
+    pub dispatched_at: DateTime<Utc>,
+}
+
+/// A failed attempt of a step that was retried
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RetryAttempt {
+    pub attempt: u32,
+    pub error: Option<String>,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl ExecutionInfo {
+    /// Build the execution details of a step from its run's history
+    ///
+    /// Retries are read from the run's retry events, or from the step's failed
+    /// results when it was retried by the SDK rather than the engine.
+    pub fn for_step(step_id: &str, trigger: Option<RunTrigger>, events: &[RunEvent], results: &[StepResult], queued_at: Option<DateTime<Utc>>) -> Self {
+        let mut retry_history: Vec<RetryAttempt> = events.iter()
+            .filter(|event| event.event_type == RunEvent::STEP_RETRIED && event.step_id.as_deref() == Some(step_id))
+            .enumerate()
+            .map(|(index, event)| RetryAttempt {
+                attempt: event.detail.get("attempt").and_then(|attempt| attempt.as_u64()).map_or(index as u32 + 1, |attempt| attempt as u32),
+                error: event.detail.get("error").and_then(|error| error.as_str()).map(str::to_string),
+                failed_at: event.created_at,
+            })
+            .collect();
+        if retry_history.is_empty() {
+            retry_history = results.iter()
+                .filter(|result| result.step_id == step_id && result.status.is_failure())
+                .enumerate()
+                .map(|(index, result)| RetryAttempt {
+                    attempt: index as u32 + 1,
+                    error: result.error.clone(),
+                    failed_at: result.completed_at.unwrap_or(result.started_at),
+                })
+                .collect();
+        }
+
+        ExecutionInfo {
+            attempt: retry_history.len() as u32 + 1,
+            is_retry: !retry_history.is_empty(),
+            retry_history,
+            trigger,
+            queued_at,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ExecutionInfo {
+    fn default() -> Self {
+        Self {
+            attempt: 1,
+            is_retry: false,
+            retry_history: Vec::new(),
+            trigger: None,
+            engine_version: ENGINE_VERSION.to_string(),
+            queued_at: None,
+            dispatched_at: Utc::now(),
+        }
+    }
 }
 
 /// Information about context serialization for performance tracking
@@ -99,6 +210,7 @@ impl Context {
             max_retries: 3,
             version: "1.0.0".to_string(),
             checksum: None,
+            schema_version: CONTEXT_SCHEMA_VERSION,
         };
 
         Ok(Context {
@@ -109,10 +221,24 @@ impl Context {
             steps,
             run,
             metadata,
+            state: ContextState::default(),
+            secrets: HashMap::new(),
+            execution: ExecutionInfo::default(),
+            input: None,
+            labels: RunLabels::new(),
             serialization_info: None,
         })
     }
 
+    /// Render the step's input mapping into `input`, once the rest of the context is set
+    pub fn render_input(&mut self, step: &crate::models::StepDefinition) -> Result<(), CoreError> {
+        self.input = match &step.input {
+            Some(input) => Some(crate::step_input::render(input, self)?),
+            None => None,
+        };
+        Ok(())
+    }
+
     /// Get a completed step result
     pub fn get_step_result(&self, step_name: &str) -> Option<&StepResult> {
         self.steps.get(step_name)
@@ -202,6 +328,9 @@ impl Context {
     }
 
     /// Generate a checksum for the context
+    ///
+    /// The checksum covers the attempt and creation time, so a context issued
+    /// for a new attempt never shares its checksum with an older one.
     pub fn generate_checksum(&self) -> String {
         use std::collections::hash_map::DefaultHasher;
         use std::hash::{Hash, Hasher};
@@ -210,6 +339,9 @@ impl Context {
         self.run_id.hash(&mut hasher);
         self.workflow_id.hash(&mut hasher);
         self.step_name.hash(&mut hasher);
+        self.metadata.schema_version.hash(&mut hasher);
+        self.metadata.retry_count.hash(&mut hasher);
+        self.metadata.created_at.hash(&mut hasher);
         
         // Hash the payload as a string
         if let Ok(payload_str) = serde_json::to_string(&self.payload) {
@@ -318,6 +450,7 @@ impl Default for ContextMetadata {
             max_retries: 3,
             version: "1.0.0".to_string(),
             checksum: None,
+            schema_version: CONTEXT_SCHEMA_VERSION,
         }
     }
 }
@@ -339,6 +472,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let context = Context::new(
@@ -366,6 +500,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let completed_step = StepResult {
@@ -376,6 +511,8 @@ mod tests {
             started_at: Utc::now(),
             completed_at: Some(Utc::now()),
             duration_ms: Some(1000),
+            cache_hit: None,
+            resources: None,
         };
 
         let context = Context::new(
@@ -402,6 +539,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let context = Context::new(
@@ -435,6 +573,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let context = Context::new(
@@ -460,6 +599,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let valid_context = Context::new(
@@ -493,6 +633,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let context = Context::new(
@@ -534,6 +675,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         let mut context = Context::new(
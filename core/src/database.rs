@@ -1,11 +1,18 @@
 //! Database operations for the Node-Cronflow Core Engine
 
-use rusqlite::Connection;
+use rusqlite::backup::{self, Backup};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
 use std::path::Path;
 use std::fs;
-use std::sync::{Arc, Mutex};
+use std::ops::Deref;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
 use crate::error::{CoreResult, CoreError};
-use crate::models::{WorkflowDefinition, WorkflowRun, StepResult};
+use crate::models::{DatabaseSnapshot, SchemaVersion, TriggerCounterRecord, TriggerCounters, WorkflowDefinition, WorkflowRun, StepResult, CachedStepOutput, StepHistoryRecord, RunQuery, RunPage, RunListEntry, RunPin, RunPinAuditEntry, StepTraceEvent, RunEvent, StepContextChecksum, AdhocStepExecution, StateEntry, StateScope, WorkerDiagnostics, StoredTrigger, TriggerSchedule, TriggerPollState, EventSchema, PayloadBlob, WorkflowVersion, StepDelay, FinishedRun, RunParent, RunTrigger, RunLabels, DisabledWorkflow, SignalWait, RunSignal, StoredSecret, StepLogEntry, StepLogLevel, SqliteSettings, SuppressedTrigger};
+use crate::config::DatabaseConfig;
+use crate::job::Job;
+use crate::record_compat::{self, CompatibilityReport};
+use crate::migrations;
 
 /// Database connection wrapper
 pub struct Database {
@@ -17,7 +24,149 @@ pub struct Database {
 #[derive(Clone)]
 pub struct AsyncDatabase {
     db_path: String,
-    conn: Arc<Mutex<Connection>>,
+    pool: Arc<ConnectionPool>,
+}
+
+/// Pool of connections to one SQLite database
+///
+/// Connections run in WAL mode by default, so readers proceed while a writer
+/// holds the database; concurrent writers wait on SQLite's busy timeout.
+pub struct ConnectionPool {
+    idle: Mutex<Vec<Connection>>,
+    available: Condvar,
+    size: usize,
+    checkout_timeout: Duration,
+}
+
+/// Connection checked out of a pool, returned when dropped
+pub struct PooledConnection<'a> {
+    pool: &'a ConnectionPool,
+    conn: Option<Connection>,
+}
+
+impl ConnectionPool {
+    /// Open `config.max_connections` connections to the database at `path`
+    ///
+    /// In-memory databases are private to their connection, so they always get a single connection.
+    pub fn open(path: &str, config: &DatabaseConfig) -> CoreResult<Self> {
+        let size = if path == ":memory:" { 1 } else { config.max_connections.max(1) };
+        let checkout_timeout = Duration::from_millis(config.connection_timeout_ms);
+        let connections = (0..size)
+            .map(|_| open_connection(path, config))
+            .collect::<CoreResult<Vec<_>>>()?;
+        Ok(ConnectionPool {
+            idle: Mutex::new(connections),
+            available: Condvar::new(),
+            size,
+            checkout_timeout,
+        })
+    }
+
+    /// Check out a connection, waiting up to the checkout timeout for one to be returned
+    pub fn get(&self) -> CoreResult<PooledConnection<'_>> {
+        let deadline = Instant::now() + self.checkout_timeout;
+        let mut idle = self.idle.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire connection pool lock: {}", e)))?;
+        loop {
+            if let Some(conn) = idle.pop() {
+                return Ok(PooledConnection { pool: self, conn: Some(conn) });
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(CoreError::Internal(format!(
+                    "Timed out after {}ms waiting for one of {} database connections",
+                    self.checkout_timeout.as_millis(), self.size
+                )));
+            }
+            idle = self.available.wait_timeout(idle, deadline - now)
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire connection pool lock: {}", e)))?
+                .0;
+        }
+    }
+
+    /// Number of connections in the pool
+    pub fn size(&self) -> usize {
+        self.size
+    }
+}
+
+impl Deref for PooledConnection<'_> {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("pooled connection used after release")
+    }
+}
+
+impl Drop for PooledConnection<'_> {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            if let Ok(mut idle) = self.pool.idle.lock() {
+                idle.push(conn);
+                self.pool.available.notify_one();
+            }
+        }
+    }
+}
+
+/// Open a connection with the configured journal mode, busy timeout and tuning
+fn open_connection(path: &str, config: &DatabaseConfig) -> CoreResult<Connection> {
+    config.validate().map_err(CoreError::Configuration)?;
+    let conn = Connection::open(path)?;
+    conn.busy_timeout(Duration::from_millis(config.busy_timeout_ms))?;
+    // In-memory databases answer "memory" and keep their journal mode
+    let _journal_mode: String = conn.query_row(&format!("PRAGMA journal_mode = {}", config.journal_mode), [], |row| row.get(0))?;
+    // SQLite caps mmap_size at its compile-time maximum, which may be 0
+    conn.execute_batch(&format!(
+        "PRAGMA synchronous = {}; PRAGMA cache_size = {}; PRAGMA mmap_size = {};",
+        config.synchronous, config.cache_size, config.mmap_size
+    ))?;
+    Ok(conn)
+}
+
+/// Insert or rewrite a workflow definition, keeping whether it is disabled
+const SAVE_WORKFLOW: &str = "INSERT INTO workflows (id, name, description, definition, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?) \
+    ON CONFLICT (id) DO UPDATE SET name = excluded.name, description = excluded.description, \
+    definition = excluded.definition, created_at = excluded.created_at, updated_at = excluded.updated_at";
+
+/// Insert a run, or rewrite it and count the write in its version
+const SAVE_RUN: &str = "INSERT INTO workflow_runs (id, workflow_id, status, payload, started_at, completed_at, error) VALUES (?, ?, ?, ?, ?, ?, ?) \
+    ON CONFLICT (id) DO UPDATE SET workflow_id = excluded.workflow_id, status = excluded.status, payload = excluded.payload, \
+    started_at = excluded.started_at, completed_at = excluded.completed_at, error = excluded.error, version = workflow_runs.version + 1";
+
+/// Rewrite a run only if its stored version is still the one it was read with
+const UPDATE_RUN_IF_VERSION: &str = "UPDATE workflow_runs SET status = ?, payload = ?, completed_at = ?, error = ?, version = version + 1 \
+    WHERE id = ? AND version = ?";
+
+/// Copy every page of one database into another, returning the number of pages copied
+///
+/// All pages are copied in one backup step, which reads the source within a single transaction.
+fn copy_database(source: &Connection, target: &mut Connection) -> CoreResult<u32> {
+    let backup = Backup::new(source, target)?;
+    loop {
+        match backup.step(-1)? {
+            backup::StepResult::Done => return Ok(backup.progress().pagecount.max(0) as u32),
+            // Backups ignore the busy timeout, so wait out writers here
+            backup::StepResult::Busy | backup::StepResult::Locked => std::thread::sleep(Duration::from_millis(10)),
+            _ => {}
+        }
+    }
+}
+
+/// Read the SQLite settings in effect on a connection
+fn read_sqlite_settings(conn: &Connection) -> CoreResult<SqliteSettings> {
+    let synchronous: i64 = conn.query_row("PRAGMA synchronous", [], |row| row.get(0))?;
+    let busy_timeout_ms: i64 = conn.query_row("PRAGMA busy_timeout", [], |row| row.get(0))?;
+    let mmap_size: i64 = conn.query_row("PRAGMA mmap_size", [], |row| row.get(0)).optional()?.unwrap_or(0);
+    Ok(SqliteSettings {
+        journal_mode: conn.query_row("PRAGMA journal_mode", [], |row| row.get(0))?,
+        synchronous: DatabaseConfig::SYNCHRONOUS_LEVELS.get(synchronous as usize)
+            .map(|level| level.to_string())
+            .unwrap_or_else(|| synchronous.to_string()),
+        busy_timeout_ms: busy_timeout_ms.max(0) as u64,
+        cache_size: conn.query_row("PRAGMA cache_size", [], |row| row.get(0))?,
+        mmap_size: mmap_size.max(0) as u64,
+    })
 }
 
 impl Database {
@@ -30,7 +179,7 @@ impl Database {
             }
         }
         
-        let conn = Connection::open(path)?;
+        let conn = open_connection(path, &DatabaseConfig::default())?;
         let db = Database { conn };
         db.init_schema()?;
         Ok(db)
@@ -38,17 +187,103 @@ impl Database {
 
     /// Initialize database schema
     fn init_schema(&self) -> CoreResult<()> {
-        // Read and execute the schema file
-        let schema = include_str!("schema.sql");
-        self.conn.execute_batch(schema)?;
+        migrations::migrate(&self.conn)?;
+        Ok(())
+    }
+
+    /// Get the schema version of the database and the migrations applied to it
+    pub fn get_schema_version(&self) -> CoreResult<SchemaVersion> {
+        migrations::get_schema_version(&self.conn)
+    }
+
+    /// Get the SQLite settings in effect on the connection
+    pub fn sqlite_settings(&self) -> CoreResult<SqliteSettings> {
+        read_sqlite_settings(&self.conn)
+    }
+
+    /// Copy the whole database to a new file at `path`
+    ///
+    /// The copy runs in a single backup step, so it holds one read transaction and
+    /// reflects the database at one point in time while writers on other
+    /// connections keep going.
+    pub fn create_snapshot(&self, path: &str) -> CoreResult<DatabaseSnapshot> {
+        if Path::new(path).exists() {
+            return Err(CoreError::Validation(format!("Snapshot file {} already exists", path)));
+        }
+        if let Some(parent) = Path::new(path).parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                fs::create_dir_all(parent)?;
+            }
+        }
+        
+        let mut target = Connection::open(path)?;
+        let pages = copy_database(&self.conn, &mut target)?;
+        drop(target);
+        Ok(DatabaseSnapshot {
+            path: path.to_string(),
+            pages,
+            size_bytes: fs::metadata(path)?.len(),
+            taken_at: chrono::Utc::now(),
+        })
+    }
+
+    /// Replace the contents of the database with a snapshot taken by `create_snapshot`
+    ///
+    /// The snapshot is checked before anything is overwritten. Other connections
+    /// see the restored contents from their next transaction on.
+    pub fn restore_snapshot(&mut self, path: &str) -> CoreResult<DatabaseSnapshot> {
+        if !Path::new(path).is_file() {
+            return Err(CoreError::Validation(format!("Snapshot file {} does not exist", path)));
+        }
+        let source = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let integrity: String = source.query_row("PRAGMA integrity_check", [], |row| row.get(0))?;
+        if integrity != "ok" {
+            return Err(CoreError::Validation(format!("Snapshot {} is corrupt: {}", path, integrity)));
+        }
+        let has_workflows: bool = source.query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'workflows'",
+            [],
+            |row| row.get(0),
+        )?;
+        if !has_workflows {
+            return Err(CoreError::Validation(format!("{} is not a cronflow database snapshot", path)));
+        }
+        migrations::check_supported(migrations::schema_version(&source)?)?;
+        
+        let pages = copy_database(&source, &mut self.conn)?;
+        // Snapshots taken by older engines lack the migrations added since
+        self.init_schema()?;
+        Ok(DatabaseSnapshot {
+            path: path.to_string(),
+            pages,
+            size_bytes: fs::metadata(path)?.len(),
+            taken_at: fs::metadata(path)?.modified()?.into(),
+        })
+    }
+
+    /// Start a transaction that every following write joins until it is committed or rolled back
+    pub fn begin_transaction(&self) -> CoreResult<()> {
+        self.conn.execute_batch("BEGIN IMMEDIATE")?;
+        Ok(())
+    }
+
+    /// Commit the transaction started by `begin_transaction`
+    pub fn commit_transaction(&self) -> CoreResult<()> {
+        self.conn.execute_batch("COMMIT")?;
+        Ok(())
+    }
+
+    /// Roll back the transaction started by `begin_transaction`
+    pub fn rollback_transaction(&self) -> CoreResult<()> {
+        self.conn.execute_batch("ROLLBACK")?;
         Ok(())
     }
 
     /// Save a workflow definition
     pub fn save_workflow(&self, workflow: &WorkflowDefinition) -> CoreResult<()> {
-        let definition = serde_json::to_string(workflow)?;
+        let definition = serde_json::to_string(&record_compat::workflow_to_record(workflow)?)?;
         self.conn.execute(
-            "INSERT OR REPLACE INTO workflows (id, name, description, definition, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+            SAVE_WORKFLOW,
             (
                 &workflow.id,
                 &workflow.name,
@@ -70,221 +305,1883 @@ impl Database {
         let mut rows = stmt.query([id])?;
         if let Some(row) = rows.next()? {
             let definition: String = row.get(0)?;
-            let workflow: WorkflowDefinition = serde_json::from_str(&definition)?;
+            let workflow = record_compat::workflow_from_record(serde_json::from_str(&definition)?)?;
             Ok(Some(workflow))
         } else {
             Ok(None)
         }
     }
 
-    /// Get all workflows
-    pub fn get_all_workflows(&self) -> CoreResult<Vec<WorkflowDefinition>> {
+    /// Get all workflows
+    pub fn get_all_workflows(&self) -> CoreResult<Vec<WorkflowDefinition>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT definition FROM workflows ORDER BY created_at DESC"
+        )?;
+        
+        let mut workflows = Vec::new();
+        let mut rows = stmt.query([])?;
+        
+        while let Some(row) = rows.next()? {
+            let definition: String = row.get(0)?;
+            let workflow = record_compat::workflow_from_record(serde_json::from_str(&definition)?)?;
+            workflows.push(workflow);
+        }
+        
+        Ok(workflows)
+    }
+
+    /// Delete a workflow
+    pub fn delete_workflow(&self, id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM workflows WHERE id = ?", [id])?;
+        Ok(())
+    }
+
+    /// Disable a workflow, or enable it with None, returning false if it does not exist
+    pub fn set_workflow_disabled(&self, workflow_id: &str, disabled: Option<&DisabledWorkflow>) -> CoreResult<bool> {
+        let updated = self.conn.execute(
+            "UPDATE workflows SET disabled = ?, disabled_reason = ?, disabled_at = ?, disabled_until = ? WHERE id = ?",
+            (
+                disabled.is_some(),
+                disabled.and_then(|disabled| disabled.reason.as_deref()),
+                disabled.map(|disabled| disabled.disabled_at.to_rfc3339()),
+                disabled.and_then(|disabled| disabled.until).map(|until| until.to_rfc3339()),
+                workflow_id,
+            ),
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Get how a workflow was disabled, None if it is enabled
+    pub fn get_disabled_workflow(&self, workflow_id: &str) -> CoreResult<Option<DisabledWorkflow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT disabled_reason, disabled_at, disabled_until FROM workflows WHERE id = ? AND disabled = 1"
+        )?;
+        let mut rows = stmt.query([workflow_id])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+        let disabled_at: Option<String> = row.get(1)?;
+        let until: Option<String> = row.get(2)?;
+        Ok(Some(DisabledWorkflow {
+            workflow_id: workflow_id.to_string(),
+            reason: row.get(0)?,
+            disabled_at: match disabled_at {
+                Some(at) => chrono::DateTime::parse_from_rfc3339(&at)?.with_timezone(&chrono::Utc),
+                None => chrono::Utc::now(),
+            },
+            until: until.map(|until| chrono::DateTime::parse_from_rfc3339(&until)).transpose()?.map(|until| until.with_timezone(&chrono::Utc)),
+        }))
+    }
+
+    /// Save a workflow run, counting the write in its stored version
+    pub fn save_run(&self, run: &WorkflowRun) -> CoreResult<()> {
+        self.conn.execute(
+            SAVE_RUN,
+            (
+                &run.id.to_string(),
+                &run.workflow_id,
+                &format!("{:?}", run.status),
+                &serde_json::to_string(&run.payload)?,
+                &run.started_at.to_rfc3339(),
+                &run.completed_at.map(|dt| dt.to_rfc3339()),
+                &run.error,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Rewrite a run unless it was written since it was read, returning false on a conflict
+    pub fn save_run_if_version(&self, run: &WorkflowRun) -> CoreResult<bool> {
+        let updated = self.conn.execute(
+            UPDATE_RUN_IF_VERSION,
+            (
+                &format!("{:?}", run.status),
+                &serde_json::to_string(&run.payload)?,
+                &run.completed_at.map(|dt| dt.to_rfc3339()),
+                &run.error,
+                &run.id.to_string(),
+                run.version,
+            ),
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Get a workflow run by ID
+    pub fn get_run(&self, run_id: &str) -> CoreResult<Option<WorkflowRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT workflow_id, status, payload, started_at, completed_at, error, version FROM workflow_runs WHERE id = ?"
+        )?;
+        
+        let mut rows = stmt.query([run_id])?;
+        if let Some(row) = rows.next()? {
+            let workflow_id: String = row.get(0)?;
+            let status_str: String = row.get(1)?;
+            let payload_str: String = row.get(2)?;
+            let started_at_str: String = row.get(3)?;
+            let completed_at_str: Option<String> = row.get(4)?;
+            let error: Option<String> = row.get(5)?;
+            let version: u64 = row.get(6)?;
+            
+            let status = record_compat::read_run_status(&status_str);
+            
+            let started_at = record_compat::read_timestamp(&started_at_str)?;
+            let completed_at = completed_at_str
+                .as_deref()
+                .map(record_compat::read_timestamp)
+                .transpose()?;
+            
+            let payload = serde_json::from_str(&payload_str)?;
+            
+            let run = WorkflowRun {
+                id: uuid::Uuid::parse_str(run_id)?,
+                workflow_id,
+                status,
+                payload,
+                started_at,
+                completed_at,
+                error,
+                version,
+            };
+            
+            Ok(Some(run))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get runs for a workflow
+    pub fn get_runs_for_workflow(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, status, payload, started_at, completed_at, error, version FROM workflow_runs WHERE workflow_id = ? ORDER BY started_at DESC"
+        )?;
+        
+        let mut runs = Vec::new();
+        let mut rows = stmt.query([workflow_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let run_id_str: String = row.get(0)?;
+            let status_str: String = row.get(1)?;
+            let payload_str: String = row.get(2)?;
+            let started_at_str: String = row.get(3)?;
+            let completed_at_str: Option<String> = row.get(4)?;
+            let error: Option<String> = row.get(5)?;
+            let version: u64 = row.get(6)?;
+            
+            let status = record_compat::read_run_status(&status_str);
+            
+            let started_at = record_compat::read_timestamp(&started_at_str)?;
+            let completed_at = completed_at_str
+                .as_deref()
+                .map(record_compat::read_timestamp)
+                .transpose()?;
+            
+            let payload = serde_json::from_str(&payload_str)?;
+            
+            let run = WorkflowRun {
+                id: uuid::Uuid::parse_str(&run_id_str)?,
+                workflow_id: workflow_id.to_string(),
+                status,
+                payload,
+                started_at,
+                completed_at,
+                error,
+                version,
+            };
+            
+            runs.push(run);
+        }
+        
+        Ok(runs)
+    }
+
+    /// List runs matching a query, newest first
+    pub fn list_runs(&self, query: &RunQuery) -> CoreResult<RunPage> {
+        list_runs_on(&self.conn, query)
+    }
+
+    /// Replace the search index entries of a run's payload or of one of its step outputs
+    pub fn index_run_document(&self, run: &WorkflowRun, source: &str, document: &serde_json::Value) -> CoreResult<()> {
+        index_run_document_on(&self.conn, run, source, document)
+    }
+
+    /// Get the IDs of a workflow's runs with a value at an indexed path, newest first
+    pub fn find_run_ids(&self, workflow_id: &str, path: &str, value: &str, limit: usize) -> CoreResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id FROM run_search_index WHERE workflow_id = ? AND path = ? AND value = ? \
+             GROUP BY run_id ORDER BY MAX(run_started_at) DESC LIMIT ?"
+        )?;
+        let run_ids = stmt.query_map((workflow_id, path, value, limit as i64), |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(run_ids)
+    }
+
+    /// Stop enforcing foreign keys, for node-local records of runs held in another store
+    pub fn disable_foreign_keys(&self) -> CoreResult<()> {
+        self.conn.execute_batch("PRAGMA foreign_keys = OFF;")?;
+        Ok(())
+    }
+
+    /// Record the isolated store of a workflow's runs, keeping an existing assignment
+    ///
+    /// Returns the path the workflow's runs are stored in.
+    pub fn assign_workflow_store(&self, workflow_id: &str, store_path: &str) -> CoreResult<String> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO workflow_stores (workflow_id, store_path, assigned_at) VALUES (?, ?, ?)",
+            (workflow_id, store_path, &chrono::Utc::now().to_rfc3339()),
+        )?;
+        Ok(self.get_workflow_store(workflow_id)?.unwrap_or_else(|| store_path.to_string()))
+    }
+
+    /// Get the isolated store of a workflow's runs
+    pub fn get_workflow_store(&self, workflow_id: &str) -> CoreResult<Option<String>> {
+        Ok(self.conn.query_row(
+            "SELECT store_path FROM workflow_stores WHERE workflow_id = ?",
+            [workflow_id],
+            |row| row.get(0),
+        ).optional()?)
+    }
+
+    /// Get every isolated store workflows are assigned to
+    pub fn get_workflow_store_paths(&self) -> CoreResult<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT store_path FROM workflow_stores ORDER BY store_path")?;
+        let paths = stmt.query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(paths)
+    }
+
+    /// Save a step result
+    pub fn save_step_result(&self, result: &StepResult, run_id: &str) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO step_results (run_id, step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (
+                run_id,
+                &result.step_id,
+                &format!("{:?}", result.status),
+                &result.output.as_ref().map(|v| serde_json::to_string(v)).transpose()?,
+                &result.error,
+                &result.started_at.to_rfc3339(),
+                &result.completed_at.map(|dt| dt.to_rfc3339()),
+                &result.duration_ms,
+                &result.cache_hit.as_ref().map(serde_json::to_string).transpose()?,
+                &result.resources.as_ref().map(serde_json::to_string).transpose()?,
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get step results for a run
+    pub fn get_step_results(&self, run_id: &str) -> CoreResult<Vec<StepResult>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources FROM step_results WHERE run_id = ? ORDER BY started_at ASC"
+        )?;
+        
+        let mut results = Vec::new();
+        let mut rows = stmt.query([run_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let step_id: String = row.get(0)?;
+            let status_str: String = row.get(1)?;
+            let output_str: Option<String> = row.get(2)?;
+            let error: Option<String> = row.get(3)?;
+            let started_at_str: String = row.get(4)?;
+            let completed_at_str: Option<String> = row.get(5)?;
+            let duration_ms: Option<u64> = row.get(6)?;
+            let cache_hit: Option<String> = row.get(7)?;
+            let resources: Option<String> = row.get(8)?;
+            
+            let status = record_compat::read_step_status(&status_str);
+            
+            let started_at = record_compat::read_timestamp(&started_at_str)?;
+            let completed_at = completed_at_str
+                .as_deref()
+                .map(record_compat::read_timestamp)
+                .transpose()?;
+            
+            let output = output_str
+                .map(|s| serde_json::from_str(&s))
+                .transpose()?;
+            let cache_hit = cache_hit
+                .map(|s| serde_json::from_str(&s))
+                .transpose()?;
+            let resources = resources
+                .map(|s| serde_json::from_str(&s))
+                .transpose()?;
+            
+            let result = StepResult {
+                step_id,
+                status,
+                output,
+                error,
+                started_at,
+                completed_at,
+                duration_ms,
+                cache_hit,
+                resources,
+            };
+            
+            results.push(result);
+        }
+        
+        Ok(results)
+    }
+
+    /// Persist a batch of step results and the updated runs they affect in a single transaction
+    pub fn save_step_results_batch(&self, results: &[(String, StepResult)], runs: &[WorkflowRun]) -> CoreResult<()> {
+        self.save_step_results_batch_with_events(results, runs, &[])
+    }
+
+    /// Persist a batch of step results, the runs they affect and the run events they produce in a single transaction
+    pub fn save_step_results_batch_with_events(&self, results: &[(String, StepResult)], runs: &[WorkflowRun], events: &[RunEvent]) -> CoreResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut insert_step = tx.prepare(
+                "INSERT INTO step_results (run_id, step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"
+            )?;
+            for (run_id, result) in results {
+                insert_step.execute((
+                    run_id,
+                    &result.step_id,
+                    &format!("{:?}", result.status),
+                    &result.output.as_ref().map(serde_json::to_string).transpose()?,
+                    &result.error,
+                    &result.started_at.to_rfc3339(),
+                    &result.completed_at.map(|dt| dt.to_rfc3339()),
+                    &result.duration_ms,
+                    &result.cache_hit.as_ref().map(serde_json::to_string).transpose()?,
+                    &result.resources.as_ref().map(serde_json::to_string).transpose()?,
+                ))?;
+            }
+            
+            // Runs are only advanced from the version they were read with
+            let mut update_run = tx.prepare(
+                "UPDATE workflow_runs SET status = ?, completed_at = ?, error = ?, version = version + 1 WHERE id = ? AND version = ?"
+            )?;
+            for run in runs {
+                let updated = update_run.execute((
+                    &format!("{:?}", run.status),
+                    &run.completed_at.map(|dt| dt.to_rfc3339()),
+                    &run.error,
+                    &run.id.to_string(),
+                    run.version,
+                ))?;
+                if updated == 0 {
+                    return Err(CoreError::Conflict(format!("Run {} was written since it was read", run.id)));
+                }
+            }
+        }
+        for event in events {
+            save_run_event_on(&tx, event)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get every completed, failed or cancelled run, newest first
+    pub fn get_finished_runs(&self) -> CoreResult<Vec<FinishedRun>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, workflow_id, status, started_at, completed_at FROM workflow_runs \
+             WHERE status NOT IN ('Pending', 'Running') ORDER BY started_at DESC"
+        )?;
+        
+        let mut runs = Vec::new();
+        let mut rows = stmt.query([])?;
+        
+        while let Some(row) = rows.next()? {
+            let status_str: String = row.get(2)?;
+            let Some(status) = record_compat::run_status(&status_str).filter(|s| s.is_terminal()) else {
+                continue;
+            };
+            let started_at_str: String = row.get(3)?;
+            let completed_at_str: Option<String> = row.get(4)?;
+            let finished_at = match completed_at_str {
+                Some(completed_at) => record_compat::read_timestamp(&completed_at)?,
+                None => record_compat::read_timestamp(&started_at_str)?,
+            };
+            
+            runs.push(FinishedRun {
+                run_id: row.get(0)?,
+                workflow_id: row.get(1)?,
+                status,
+                finished_at,
+            });
+        }
+        
+        Ok(runs)
+    }
+
+    /// Delete runs with their step results and jobs, returning the number of runs deleted
+    ///
+    /// Payload blobs no longer referenced by any step result are deleted as well.
+    pub fn delete_runs(&self, run_ids: &[String]) -> CoreResult<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut deleted = 0;
+        {
+            let mut delete_steps = tx.prepare("DELETE FROM step_results WHERE run_id = ?")?;
+            let mut delete_leases = tx.prepare("DELETE FROM job_leases WHERE job_id IN (SELECT id FROM jobs WHERE run_id = ?)")?;
+            let mut delete_jobs = tx.prepare("DELETE FROM jobs WHERE run_id = ?")?;
+            let mut delete_run = tx.prepare("DELETE FROM workflow_runs WHERE id = ?")?;
+            for run_id in run_ids {
+                delete_steps.execute([run_id])?;
+                delete_leases.execute([run_id])?;
+                delete_jobs.execute([run_id])?;
+                deleted += delete_run.execute([run_id])?;
+            }
+        }
+        tx.execute(
+            "DELETE FROM payload_blobs WHERE id NOT IN ( \
+                 SELECT json_extract(output, '$._payload_blob') FROM step_results \
+                 WHERE json_extract(output, '$._payload_blob') IS NOT NULL)",
+            [],
+        )?;
+        tx.commit()?;
+        Ok(deleted)
+    }
+
+    /// Delete the node-local records kept for runs: events, traces, delays, correlation IDs and the like
+    pub fn delete_run_records(&self, run_ids: &[String]) -> CoreResult<()> {
+        const TABLES: [&str; 17] = [
+            "run_events",
+            "step_logs",
+            "step_trace_events",
+            "step_delays",
+            "signal_waits",
+            "run_signals",
+            "run_serialization_queue",
+            "trigger_dedup_keys",
+            "suppressed_triggers",
+            "step_context_checksums",
+            "run_correlation_ids",
+            "run_workflow_versions",
+            "run_parents",
+            "run_triggers",
+            "buffered_runs",
+            "run_search_index",
+            "run_labels",
+        ];
+        let tx = self.conn.unchecked_transaction()?;
+        for table in TABLES {
+            let mut stmt = tx.prepare(&format!("DELETE FROM {} WHERE run_id = ?", table))?;
+            for run_id in run_ids {
+                stmt.execute([run_id])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get step results of terminal, unpinned runs started within `[from, to)`, joined with their run
+    pub fn get_step_history(&self, from: &chrono::DateTime<chrono::Utc>, to: &chrono::DateTime<chrono::Utc>) -> CoreResult<Vec<StepHistoryRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.id, r.workflow_id, r.status, r.started_at, s.step_id, s.status, s.output, s.error, s.started_at, s.completed_at, s.duration_ms \
+             FROM step_results s JOIN workflow_runs r ON s.run_id = r.id \
+             WHERE r.started_at >= ? AND r.started_at < ? AND r.status IN ('Completed', 'Failed', 'Cancelled') \
+             AND r.id NOT IN (SELECT run_id FROM run_pins) \
+             ORDER BY r.started_at ASC, s.id ASC"
+        )?;
+        
+        let mut records = Vec::new();
+        let mut rows = stmt.query((from.to_rfc3339(), to.to_rfc3339()))?;
+        
+        while let Some(row) = rows.next()? {
+            let run_started_at_str: String = row.get(3)?;
+            let started_at_str: String = row.get(8)?;
+            let completed_at_str: Option<String> = row.get(9)?;
+            
+            let run_started_at = record_compat::read_timestamp(&run_started_at_str)?;
+            let started_at = record_compat::read_timestamp(&started_at_str)?;
+            let completed_at = completed_at_str
+                .as_deref()
+                .map(record_compat::read_timestamp)
+                .transpose()?;
+            
+            records.push(StepHistoryRecord {
+                run_id: row.get(0)?,
+                workflow_id: row.get(1)?,
+                run_status: row.get(2)?,
+                run_started_at,
+                step_id: row.get(4)?,
+                step_status: row.get(5)?,
+                output: row.get(6)?,
+                error: row.get(7)?,
+                started_at,
+                completed_at,
+                duration_ms: row.get(10)?,
+            });
+        }
+        
+        Ok(records)
+    }
+
+    /// Get the start time of the oldest run started before `before` that has not finished
+    pub fn get_oldest_unfinished_run_start(&self, before: &chrono::DateTime<chrono::Utc>) -> CoreResult<Option<chrono::DateTime<chrono::Utc>>> {
+        let started_at: Option<String> = self.conn.query_row(
+            "SELECT MIN(started_at) FROM workflow_runs \
+             WHERE started_at < ? AND status NOT IN ('Completed', 'Failed', 'Cancelled')",
+            [before.to_rfc3339()],
+            |row| row.get(0),
+        )?;
+        
+        started_at.as_deref().map(record_compat::read_timestamp).transpose()
+    }
+
+    /// Check that every stored workflow, run and step result can be read by this engine
+    pub fn verify_record_compatibility(&self) -> CoreResult<CompatibilityReport> {
+        let mut report = CompatibilityReport::default();
+
+        let mut stmt = self.conn.prepare("SELECT id, definition FROM workflows ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: String = row.get(0)?;
+            let definition: String = row.get(1)?;
+            report.check_workflow(&id, serde_json::from_str(&definition).map_err(CoreError::from));
+        }
+
+        let mut stmt = self.conn.prepare("SELECT id, status, payload, started_at, completed_at FROM workflow_runs ORDER BY started_at")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: String = row.get(0)?;
+            let status: String = row.get(1)?;
+            let payload: String = row.get(2)?;
+            let started_at: String = row.get(3)?;
+            let completed_at: Option<String> = row.get(4)?;
+            let timestamps: Vec<&str> = std::iter::once(started_at.as_str()).chain(completed_at.as_deref()).collect();
+            report.check_run(&id, &status, &timestamps);
+            if let Err(e) = serde_json::from_str::<serde_json::Value>(&payload) {
+                report.add_issue("run", &id, format!("Unreadable payload: {}", e));
+            }
+        }
+
+        let mut stmt = self.conn.prepare("SELECT run_id, step_id, status, output, started_at, completed_at FROM step_results ORDER BY id")?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let run_id: String = row.get(0)?;
+            let step_id: String = row.get(1)?;
+            let status: String = row.get(2)?;
+            let output: Option<String> = row.get(3)?;
+            let started_at: String = row.get(4)?;
+            let completed_at: Option<String> = row.get(5)?;
+            let timestamps: Vec<&str> = std::iter::once(started_at.as_str()).chain(completed_at.as_deref()).collect();
+            report.check_step_result(&run_id, &step_id, &status, &timestamps);
+            if let Some(Err(e)) = output.map(|o| serde_json::from_str::<serde_json::Value>(&o)) {
+                report.add_issue("step_result", &format!("{}/{}", run_id, step_id), format!("Unreadable output: {}", e));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Pin a run, replacing any existing pin, and record it in the audit trail
+    pub fn pin_run(&self, run_id: &str, reason: &str, pinned_by: Option<&str>) -> CoreResult<RunPin> {
+        let pin = RunPin {
+            run_id: run_id.to_string(),
+            reason: reason.to_string(),
+            pinned_by: pinned_by.map(|s| s.to_string()),
+            pinned_at: chrono::Utc::now(),
+        };
+        
+        let tx = self.conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT OR REPLACE INTO run_pins (run_id, reason, pinned_by, pinned_at) VALUES (?, ?, ?, ?)",
+            (&pin.run_id, &pin.reason, &pin.pinned_by, &pin.pinned_at.to_rfc3339()),
+        )?;
+        tx.execute(
+            "INSERT INTO run_pin_audit (run_id, action, reason, actor, created_at) VALUES (?, 'pin', ?, ?, ?)",
+            (&pin.run_id, &pin.reason, &pin.pinned_by, &pin.pinned_at.to_rfc3339()),
+        )?;
+        tx.commit()?;
+        Ok(pin)
+    }
+
+    /// Remove a run's pin, returning whether it was pinned
+    pub fn unpin_run(&self, run_id: &str, unpinned_by: Option<&str>) -> CoreResult<bool> {
+        let tx = self.conn.unchecked_transaction()?;
+        let removed = tx.execute("DELETE FROM run_pins WHERE run_id = ?", [run_id])?;
+        if removed > 0 {
+            tx.execute(
+                "INSERT INTO run_pin_audit (run_id, action, reason, actor, created_at) VALUES (?, 'unpin', NULL, ?, ?)",
+                (run_id, unpinned_by, &chrono::Utc::now().to_rfc3339()),
+            )?;
+        }
+        tx.commit()?;
+        Ok(removed > 0)
+    }
+
+    /// Set labels of a run, replacing the values of keys it already has
+    pub fn set_run_labels(&self, run_id: &str, labels: &RunLabels) -> CoreResult<()> {
+        let set_at = chrono::Utc::now().to_rfc3339();
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut stmt = tx.prepare("INSERT OR REPLACE INTO run_labels (run_id, key, value, set_at) VALUES (?, ?, ?, ?)")?;
+            for (key, value) in labels {
+                stmt.execute((run_id, key, value, &set_at))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove a label of a run, returning whether the run had it
+    pub fn remove_run_label(&self, run_id: &str, key: &str) -> CoreResult<bool> {
+        let removed = self.conn.execute("DELETE FROM run_labels WHERE run_id = ? AND key = ?", [run_id, key])?;
+        Ok(removed > 0)
+    }
+
+    /// Get the labels of a run
+    pub fn get_run_labels(&self, run_id: &str) -> CoreResult<RunLabels> {
+        get_run_labels_on(&self.conn, run_id)
+    }
+
+    /// Get the pin on a run, if any
+    pub fn get_run_pin(&self, run_id: &str) -> CoreResult<Option<RunPin>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, reason, pinned_by, pinned_at FROM run_pins WHERE run_id = ?"
+        )?;
+        let mut rows = stmt.query([run_id])?;
+        
+        if let Some(row) = rows.next()? {
+            let pinned_at_str: String = row.get(3)?;
+            Ok(Some(RunPin {
+                run_id: row.get(0)?,
+                reason: row.get(1)?,
+                pinned_by: row.get(2)?,
+                pinned_at: chrono::DateTime::parse_from_rfc3339(&pinned_at_str)?.with_timezone(&chrono::Utc),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Get the pin audit trail of a run, oldest first
+    pub fn get_run_pin_audit(&self, run_id: &str) -> CoreResult<Vec<RunPinAuditEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, action, reason, actor, created_at FROM run_pin_audit WHERE run_id = ? ORDER BY id ASC"
+        )?;
+        
+        let mut entries = Vec::new();
+        let mut rows = stmt.query([run_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let created_at_str: String = row.get(4)?;
+            entries.push(RunPinAuditEntry {
+                run_id: row.get(0)?,
+                action: row.get(1)?,
+                reason: row.get(2)?,
+                actor: row.get(3)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(entries)
+    }
+
+    /// Record a step trace event
+    pub fn save_step_trace_event(&self, event: &StepTraceEvent) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO step_trace_events (run_id, step_id, event, detail, created_at) VALUES (?, ?, ?, ?, ?)",
+            (
+                &event.run_id,
+                &event.step_id,
+                &event.event,
+                &serde_json::to_string(&event.detail)?,
+                &event.created_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the trace events of a run in the order they were recorded
+    pub fn get_step_trace_events(&self, run_id: &str) -> CoreResult<Vec<StepTraceEvent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, step_id, event, detail, created_at FROM step_trace_events WHERE run_id = ? ORDER BY id ASC"
+        )?;
+        
+        let mut events = Vec::new();
+        let mut rows = stmt.query([run_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let detail_str: String = row.get(3)?;
+            let created_at_str: String = row.get(4)?;
+            events.push(StepTraceEvent {
+                run_id: row.get(0)?,
+                step_id: row.get(1)?,
+                event: row.get(2)?,
+                detail: serde_json::from_str(&detail_str)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(events)
+    }
+
+    /// Append an event to a run's event log
+    pub fn save_run_event(&self, event: &RunEvent) -> CoreResult<()> {
+        save_run_event_on(&self.conn, event)
+    }
+
+    /// Get the events of a run in the order they were recorded
+    pub fn get_run_events(&self, run_id: &str) -> CoreResult<Vec<RunEvent>> {
+        get_run_events_on(&self.conn, run_id)
+    }
+
+    /// Append a step log line, returning its sequence number
+    pub fn save_step_log(&self, entry: &StepLogEntry) -> CoreResult<i64> {
+        self.conn.execute(
+            "INSERT INTO step_logs (run_id, step_id, level, message, fields, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                &entry.run_id,
+                &entry.step_id,
+                entry.level.as_str(),
+                &entry.message,
+                &serde_json::to_string(&entry.fields)?,
+                &entry.created_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Get the log lines of a step appended after a sequence number, oldest first
+    pub fn get_step_logs(&self, run_id: &str, step_id: &str, after_seq: i64, limit: usize) -> CoreResult<Vec<StepLogEntry>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT seq, run_id, step_id, level, message, fields, created_at FROM step_logs \
+             WHERE run_id = ? AND step_id = ? AND seq > ? ORDER BY seq ASC LIMIT ?"
+        )?;
+        
+        let mut entries = Vec::new();
+        let mut rows = stmt.query((run_id, step_id, after_seq, limit as i64))?;
+        
+        while let Some(row) = rows.next()? {
+            let level_str: String = row.get(3)?;
+            let fields_str: String = row.get(5)?;
+            let created_at_str: String = row.get(6)?;
+            entries.push(StepLogEntry {
+                seq: row.get(0)?,
+                run_id: row.get(1)?,
+                step_id: row.get(2)?,
+                level: StepLogLevel::parse(&level_str)
+                    .ok_or_else(|| CoreError::Internal(format!("Unknown step log level: {}", level_str)))?,
+                message: row.get(4)?,
+                fields: serde_json::from_str(&fields_str)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(entries)
+    }
+
+    /// Record the checksum of the latest context issued for a step
+    pub fn save_step_context_checksum(&self, issued: &StepContextChecksum) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO step_context_checksums (run_id, step_id, checksum, schema_version, attempt, issued_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                &issued.run_id,
+                &issued.step_id,
+                &issued.checksum,
+                issued.schema_version,
+                issued.attempt,
+                &issued.issued_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the checksum of the latest context issued for a step
+    pub fn get_step_context_checksum(&self, run_id: &str, step_id: &str) -> CoreResult<Option<StepContextChecksum>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, step_id, checksum, schema_version, attempt, issued_at FROM step_context_checksums WHERE run_id = ? AND step_id = ?"
+        )?;
+        let mut rows = stmt.query([run_id, step_id])?;
+        
+        if let Some(row) = rows.next()? {
+            let issued_at_str: String = row.get(5)?;
+            Ok(Some(StepContextChecksum {
+                run_id: row.get(0)?,
+                step_id: row.get(1)?,
+                checksum: row.get(2)?,
+                schema_version: row.get(3)?,
+                attempt: row.get(4)?,
+                issued_at: chrono::DateTime::parse_from_rfc3339(&issued_at_str)?.with_timezone(&chrono::Utc),
+            }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Save the diagnostics of a dispatcher worker
+    pub fn save_worker_diagnostics(&self, diagnostics: &WorkerDiagnostics) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO worker_diagnostics (worker_id, status, current_job_id, jobs_processed, error_count, total_processing_time_ms, recent_job_ids, last_panic, last_panic_at, last_activity) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            (
+                &diagnostics.worker_id,
+                &diagnostics.status,
+                &diagnostics.current_job_id,
+                diagnostics.jobs_processed as i64,
+                diagnostics.error_count as i64,
+                diagnostics.total_processing_time_ms as i64,
+                &serde_json::to_string(&diagnostics.recent_job_ids)?,
+                &diagnostics.last_panic,
+                &diagnostics.last_panic_at.map(|t| t.to_rfc3339()),
+                &diagnostics.last_activity.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the persisted diagnostics of every dispatcher worker, ordered by worker ID
+    pub fn get_worker_diagnostics(&self) -> CoreResult<Vec<WorkerDiagnostics>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT worker_id, status, current_job_id, jobs_processed, error_count, total_processing_time_ms, recent_job_ids, last_panic, last_panic_at, last_activity FROM worker_diagnostics ORDER BY worker_id ASC"
+        )?;
+        
+        let mut workers = Vec::new();
+        let mut rows = stmt.query([])?;
+        
+        while let Some(row) = rows.next()? {
+            let jobs_processed: i64 = row.get(3)?;
+            let error_count: i64 = row.get(4)?;
+            let total_processing_time_ms: i64 = row.get(5)?;
+            let recent_job_ids_str: String = row.get(6)?;
+            let last_panic_at_str: Option<String> = row.get(8)?;
+            let last_activity_str: String = row.get(9)?;
+            workers.push(WorkerDiagnostics {
+                worker_id: row.get(0)?,
+                status: row.get(1)?,
+                current_job_id: row.get(2)?,
+                jobs_processed: jobs_processed as u64,
+                error_count: error_count as u64,
+                total_processing_time_ms: total_processing_time_ms as u64,
+                average_duration_ms: if jobs_processed > 0 { (total_processing_time_ms / jobs_processed) as u64 } else { 0 },
+                recent_job_ids: serde_json::from_str(&recent_job_ids_str)?,
+                last_panic: row.get(7)?,
+                last_panic_at: last_panic_at_str
+                    .map(|t| chrono::DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&chrono::Utc)))
+                    .transpose()?,
+                last_activity: chrono::DateTime::parse_from_rfc3339(&last_activity_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(workers)
+    }
+
+    /// Save a context state entry
+    pub fn save_state_entry(&self, entry: &StateEntry) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO context_state (scope, scope_id, key, value, expires_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                entry.scope.as_str(),
+                &entry.scope_id,
+                &entry.key,
+                &serde_json::to_string(&entry.value)?,
+                &entry.expires_at.map(|t| t.to_rfc3339()),
+                &entry.updated_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Save an encrypted secret, replacing any with the same scope and key
+    pub fn save_secret(&self, secret: &StoredSecret) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO secrets (scope, key, nonce, ciphertext, updated_at) VALUES (?, ?, ?, ?, ?)",
+            (&secret.scope, &secret.key, &secret.nonce, &secret.ciphertext, &secret.updated_at.to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    /// Delete a secret, returning whether it existed
+    pub fn delete_secret(&self, scope: &str, key: &str) -> CoreResult<bool> {
+        let deleted = self.conn.execute("DELETE FROM secrets WHERE scope = ? AND key = ?", (scope, key))?;
+        Ok(deleted > 0)
+    }
+
+    /// Get the encrypted secrets of the given scopes, or of every scope
+    pub fn get_secrets(&self, scopes: Option<&[&str]>) -> CoreResult<Vec<StoredSecret>> {
+        query_secrets(&self.conn, scopes)
+    }
+
+    /// Get the unexpired context state entries of a scope, optionally only one key
+    pub fn get_state_entries(&self, scope: StateScope, scope_id: &str, key: Option<&str>, now: chrono::DateTime<chrono::Utc>) -> CoreResult<Vec<StateEntry>> {
+        query_state_entries(&self.conn, scope, scope_id, key, now)
+    }
+
+    /// Delete context state entries that expired before the given time
+    pub fn delete_expired_state_entries(&self, now: chrono::DateTime<chrono::Utc>) -> CoreResult<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM context_state WHERE expires_at IS NOT NULL AND expires_at <= ?",
+            [&now.to_rfc3339()],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Save the result of an ad hoc step execution to the scratch area
+    pub fn save_adhoc_step_execution(&self, execution: &AdhocStepExecution) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO adhoc_step_executions (id, workflow_id, step_id, payload, result, created_at, expires_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+            (
+                &execution.id,
+                &execution.workflow_id,
+                &execution.step_id,
+                &serde_json::to_string(&execution.payload)?,
+                &serde_json::to_string(&execution.result)?,
+                &execution.created_at.to_rfc3339(),
+                &execution.expires_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the unexpired ad hoc step executions of a workflow, newest first
+    pub fn get_adhoc_step_executions(&self, workflow_id: &str, now: chrono::DateTime<chrono::Utc>) -> CoreResult<Vec<AdhocStepExecution>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, workflow_id, step_id, payload, result, created_at, expires_at FROM adhoc_step_executions WHERE workflow_id = ? AND expires_at > ? ORDER BY created_at DESC"
+        )?;
+        
+        let mut executions = Vec::new();
+        let mut rows = stmt.query([workflow_id, &now.to_rfc3339()])?;
+        
+        while let Some(row) = rows.next()? {
+            let payload_str: String = row.get(3)?;
+            let result_str: String = row.get(4)?;
+            let created_at_str: String = row.get(5)?;
+            let expires_at_str: String = row.get(6)?;
+            executions.push(AdhocStepExecution {
+                id: row.get(0)?,
+                workflow_id: row.get(1)?,
+                step_id: row.get(2)?,
+                payload: serde_json::from_str(&payload_str)?,
+                result: serde_json::from_str(&result_str)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+                expires_at: chrono::DateTime::parse_from_rfc3339(&expires_at_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(executions)
+    }
+
+    /// Delete ad hoc step executions that expired before the given time
+    pub fn delete_expired_adhoc_step_executions(&self, now: chrono::DateTime<chrono::Utc>) -> CoreResult<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM adhoc_step_executions WHERE expires_at <= ?",
+            [&now.to_rfc3339()],
+        )?;
+        Ok(deleted)
+    }
+
+    /// Persist whether the engine is globally paused
+    pub fn set_engine_paused(&self, paused: bool) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO engine_state (key, value, updated_at) VALUES ('paused', ?, ?)",
+            (if paused { "true" } else { "false" }, &chrono::Utc::now().to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    /// Check whether the engine is globally paused
+    pub fn is_engine_paused(&self) -> CoreResult<bool> {
+        let mut stmt = self.conn.prepare("SELECT value FROM engine_state WHERE key = 'paused'")?;
+        let mut rows = stmt.query([])?;
+        
+        if let Some(row) = rows.next()? {
+            let value: String = row.get(0)?;
+            Ok(value == "true")
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Buffer a run created while the engine is paused
+    pub fn buffer_run(&self, run_id: &str, workflow_id: &str) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO buffered_runs (run_id, workflow_id, buffered_at) VALUES (?, ?, ?)",
+            (run_id, workflow_id, &chrono::Utc::now().to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    /// Get the IDs of a workflow's pending and running runs, oldest first
+    pub fn get_unfinished_runs(&self, workflow_id: &str) -> CoreResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM workflow_runs WHERE workflow_id = ? AND status IN ('Pending', 'Running') ORDER BY started_at ASC"
+        )?;
+        let mut rows = stmt.query([workflow_id])?;
+        
+        let mut run_ids = Vec::new();
+        while let Some(row) = rows.next()? {
+            run_ids.push(row.get(0)?);
+        }
+        
+        Ok(run_ids)
+    }
+
+    /// Get the IDs of buffered runs in the order they were buffered
+    pub fn get_buffered_runs(&self) -> CoreResult<Vec<String>> {
+        let mut stmt = self.conn.prepare("SELECT run_id FROM buffered_runs ORDER BY seq ASC")?;
+        let mut rows = stmt.query([])?;
+        
+        let mut run_ids = Vec::new();
+        while let Some(row) = rows.next()? {
+            run_ids.push(row.get(0)?);
+        }
+        
+        Ok(run_ids)
+    }
+
+    /// Remove a run from the buffer once it has been released
+    pub fn remove_buffered_run(&self, run_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM buffered_runs WHERE run_id = ?", [run_id])?;
+        Ok(())
+    }
+
+    /// Save a workflow trigger, replacing any trigger with the same ID
+    pub fn save_trigger(&self, trigger: &StoredTrigger) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO triggers (id, workflow_id, trigger_type, config, created_at) VALUES (?, ?, ?, ?, ?)",
+            (
+                &trigger.id,
+                &trigger.workflow_id,
+                trigger.trigger.get_type(),
+                &serde_json::to_string(&trigger.trigger)?,
+                &trigger.created_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the triggers of a workflow
+    pub fn get_triggers(&self, workflow_id: &str) -> CoreResult<Vec<StoredTrigger>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, config, created_at FROM triggers WHERE workflow_id = ? ORDER BY id ASC"
+        )?;
+        
+        let mut triggers = Vec::new();
+        let mut rows = stmt.query([workflow_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let config: String = row.get(1)?;
+            let created_at_str: String = row.get(2)?;
+            triggers.push(StoredTrigger {
+                id: row.get(0)?,
+                workflow_id: workflow_id.to_string(),
+                trigger: serde_json::from_str(&config)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(triggers)
+    }
+
+    /// Delete the triggers of a workflow, returning how many were removed
+    pub fn delete_triggers(&self, workflow_id: &str) -> CoreResult<usize> {
+        Ok(self.conn.execute("DELETE FROM triggers WHERE workflow_id = ?", [workflow_id])?)
+    }
+
+    /// Record the duration of a successful step, keeping only its `window` most recent durations
+    pub fn record_step_duration(&self, workflow_id: &str, step_id: &str, duration_ms: u64, window: usize) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO step_durations (workflow_id, step_id, duration_ms, recorded_at) VALUES (?, ?, ?, ?)",
+            (workflow_id, step_id, duration_ms as i64, &chrono::Utc::now().to_rfc3339()),
+        )?;
+        self.conn.execute(
+            "DELETE FROM step_durations WHERE workflow_id = ?1 AND step_id = ?2 AND id NOT IN \
+             (SELECT id FROM step_durations WHERE workflow_id = ?1 AND step_id = ?2 ORDER BY id DESC LIMIT ?3)",
+            (workflow_id, step_id, window as i64),
+        )?;
+        Ok(())
+    }
+
+    /// Get the recorded durations of a step, most recent first
+    pub fn get_step_durations(&self, workflow_id: &str, step_id: &str) -> CoreResult<Vec<u64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT duration_ms FROM step_durations WHERE workflow_id = ? AND step_id = ? ORDER BY id DESC"
+        )?;
+        let durations = stmt.query_map([workflow_id, step_id], |row| row.get::<_, i64>(0))?
+            .map(|duration| duration.map(|ms| ms as u64))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(durations)
+    }
+
+    /// Save a workflow definition as its next version, returning the version number
+    pub fn save_workflow_version(&self, workflow: &WorkflowDefinition) -> CoreResult<u32> {
+        let version: u32 = self.conn.query_row(
+            "SELECT COALESCE(MAX(version), 0) + 1 FROM workflow_versions WHERE workflow_id = ?",
+            [&workflow.id],
+            |row| row.get(0),
+        )?;
+        self.conn.execute(
+            "INSERT INTO workflow_versions (workflow_id, version, definition, created_at) VALUES (?, ?, ?, ?)",
+            (
+                &workflow.id,
+                version,
+                &serde_json::to_string(&record_compat::workflow_to_record(workflow)?)?,
+                &chrono::Utc::now().to_rfc3339(),
+            ),
+        )?;
+        Ok(version)
+    }
+
+    /// Get the versions of a workflow, oldest first
+    pub fn get_workflow_versions(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowVersion>> {
+        self.query_workflow_versions(
+            "SELECT workflow_id, version, definition, created_at FROM workflow_versions WHERE workflow_id = ? ORDER BY version ASC",
+            rusqlite::params![workflow_id],
+        )
+    }
+
+    /// Get one version of a workflow
+    pub fn get_workflow_version(&self, workflow_id: &str, version: u32) -> CoreResult<Option<WorkflowVersion>> {
+        Ok(self.query_workflow_versions(
+            "SELECT workflow_id, version, definition, created_at FROM workflow_versions WHERE workflow_id = ? AND version = ?",
+            rusqlite::params![workflow_id, version],
+        )?.pop())
+    }
+
+    /// Get the latest version of a workflow
+    pub fn get_latest_workflow_version(&self, workflow_id: &str) -> CoreResult<Option<WorkflowVersion>> {
+        Ok(self.query_workflow_versions(
+            "SELECT workflow_id, version, definition, created_at FROM workflow_versions WHERE workflow_id = ? ORDER BY version DESC LIMIT 1",
+            rusqlite::params![workflow_id],
+        )?.pop())
+    }
+
+    fn query_workflow_versions(&self, sql: &str, params: impl rusqlite::Params) -> CoreResult<Vec<WorkflowVersion>> {
+        let mut stmt = self.conn.prepare(sql)?;
+        let mut rows = stmt.query(params)?;
+        
+        let mut versions = Vec::new();
+        while let Some(row) = rows.next()? {
+            let definition: String = row.get(2)?;
+            let created_at: String = row.get(3)?;
+            versions.push(WorkflowVersion {
+                workflow_id: row.get(0)?,
+                version: row.get(1)?,
+                definition: record_compat::workflow_from_record(serde_json::from_str(&definition)?)?,
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(versions)
+    }
+
+    /// Pin a run to the workflow version it started on
+    pub fn save_run_workflow_version(&self, run_id: &str, workflow_id: &str, version: u32) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO run_workflow_versions (run_id, workflow_id, version) VALUES (?, ?, ?)",
+            (run_id, workflow_id, version),
+        )?;
+        Ok(())
+    }
+
+    /// Get the workflow version a run is pinned to
+    pub fn get_run_workflow_version(&self, run_id: &str) -> CoreResult<Option<u32>> {
+        let mut stmt = self.conn.prepare("SELECT version FROM run_workflow_versions WHERE run_id = ?")?;
+        let mut rows = stmt.query([run_id])?;
+        
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Link a child run to the parent run and step that started it
+    pub fn save_run_parent(&self, parent: &RunParent) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO run_parents (run_id, parent_run_id, parent_step_id, created_at) VALUES (?, ?, ?, ?)",
+            (&parent.run_id, &parent.parent_run_id, &parent.parent_step_id, parent.created_at.to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    /// Get the parent link of a child run
+    pub fn get_run_parent(&self, run_id: &str) -> CoreResult<Option<RunParent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, parent_run_id, parent_step_id, created_at FROM run_parents WHERE run_id = ?"
+        )?;
+        let mut rows = stmt.query([run_id])?;
+        
+        match rows.next()? {
+            Some(row) => Ok(Some(Self::run_parent_from_row(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the child runs of a run, oldest first
+    pub fn get_child_runs(&self, parent_run_id: &str) -> CoreResult<Vec<RunParent>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, parent_run_id, parent_step_id, created_at FROM run_parents \
+             WHERE parent_run_id = ? ORDER BY created_at ASC"
+        )?;
+        let mut rows = stmt.query([parent_run_id])?;
+        
+        let mut children = Vec::new();
+        while let Some(row) = rows.next()? {
+            children.push(Self::run_parent_from_row(row)?);
+        }
+        Ok(children)
+    }
+
+    fn run_parent_from_row(row: &rusqlite::Row) -> CoreResult<RunParent> {
+        let created_at: String = row.get(3)?;
+        Ok(RunParent {
+            run_id: row.get(0)?,
+            parent_run_id: row.get(1)?,
+            parent_step_id: row.get(2)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&chrono::Utc),
+        })
+    }
+
+    /// Record what started a run
+    pub fn save_run_trigger(&self, run_id: &str, trigger: &RunTrigger) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO run_triggers (run_id, trigger_type, source, fired_at, trace_parent, lane) VALUES (?, ?, ?, ?, ?, ?)",
+            (run_id, &trigger.trigger_type, &trigger.source, trigger.fired_at.to_rfc3339(), &trigger.trace_parent, &trigger.lane),
+        )?;
+        Ok(())
+    }
+
+    /// Get what started a run, None for runs created without a trigger
+    pub fn get_run_trigger(&self, run_id: &str) -> CoreResult<Option<RunTrigger>> {
+        get_run_trigger_on(&self.conn, run_id)
+    }
+
+    /// Attach a correlation ID to a run
+    ///
+    /// Returns false, leaving the existing mapping alone, when the workflow
+    /// already has a run with that correlation ID.
+    pub fn save_run_correlation_id(&self, workflow_id: &str, correlation_id: &str, run_id: &str) -> CoreResult<bool> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO run_correlation_ids (workflow_id, correlation_id, run_id, created_at) VALUES (?, ?, ?, ?)",
+            (workflow_id, correlation_id, run_id, &chrono::Utc::now().to_rfc3339()),
+        )?;
+        Ok(inserted > 0)
+    }
+
+    /// Remove the correlation ID of a run
+    pub fn delete_run_correlation_id(&self, run_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM run_correlation_ids WHERE run_id = ?", [run_id])?;
+        Ok(())
+    }
+
+    /// Get the ID of a workflow's run with a correlation ID
+    pub fn get_run_id_by_correlation_id(&self, workflow_id: &str, correlation_id: &str) -> CoreResult<Option<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id FROM run_correlation_ids WHERE workflow_id = ? AND correlation_id = ?"
+        )?;
+        let mut rows = stmt.query([workflow_id, correlation_id])?;
+        
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the correlation ID of a run
+    pub fn get_run_correlation_id(&self, run_id: &str) -> CoreResult<Option<String>> {
+        let mut stmt = self.conn.prepare("SELECT correlation_id FROM run_correlation_ids WHERE run_id = ?")?;
+        let mut rows = stmt.query([run_id])?;
+        
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Save the firing state of an interval or one-time trigger
+    pub fn save_trigger_schedule(&self, schedule: &TriggerSchedule) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO trigger_schedules (trigger_id, workflow_id, config, next_fire_at, last_fired_at) VALUES (?, ?, ?, ?, ?)",
+            (
+                &schedule.trigger_id,
+                &schedule.workflow_id,
+                &serde_json::to_string(&schedule.trigger)?,
+                schedule.next_fire_at.map(|at| at.to_rfc3339()),
+                schedule.last_fired_at.map(|at| at.to_rfc3339()),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get trigger schedules, of one workflow or of all workflows
+    pub fn get_trigger_schedules(&self, workflow_id: Option<&str>) -> CoreResult<Vec<TriggerSchedule>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT trigger_id, workflow_id, config, next_fire_at, last_fired_at FROM trigger_schedules
+             WHERE ?1 IS NULL OR workflow_id = ?1 ORDER BY trigger_id ASC"
+        )?;
+        
+        let mut schedules = Vec::new();
+        let mut rows = stmt.query([workflow_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let config: String = row.get(2)?;
+            let next_fire_at_str: Option<String> = row.get(3)?;
+            let last_fired_at_str: Option<String> = row.get(4)?;
+            schedules.push(TriggerSchedule {
+                trigger_id: row.get(0)?,
+                workflow_id: row.get(1)?,
+                trigger: serde_json::from_str(&config)?,
+                next_fire_at: next_fire_at_str
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
+                    .transpose()?
+                    .map(|dt| dt.with_timezone(&chrono::Utc)),
+                last_fired_at: last_fired_at_str
+                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
+                    .transpose()?
+                    .map(|dt| dt.with_timezone(&chrono::Utc)),
+            });
+        }
+        
+        Ok(schedules)
+    }
+
+    /// Delete a trigger schedule
+    pub fn delete_trigger_schedule(&self, trigger_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM trigger_schedules WHERE trigger_id = ?", [trigger_id])?;
+        Ok(())
+    }
+
+    /// Save the last response value seen by a polling trigger
+    pub fn save_trigger_poll_state(&self, state: &TriggerPollState) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO trigger_poll_states (trigger_id, workflow_id, fingerprint, value, checked_at, changed_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                &state.trigger_id,
+                &state.workflow_id,
+                &state.fingerprint,
+                &serde_json::to_string(&state.value)?,
+                &state.checked_at.to_rfc3339(),
+                state.changed_at.map(|at| at.to_rfc3339()),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Add firing counts to the persisted counts of their triggers
+    pub fn add_trigger_counters(&self, records: &[TriggerCounterRecord]) -> CoreResult<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        {
+            let mut add = tx.prepare(
+                "INSERT INTO trigger_stats (trigger_key, workflow_id, fired, succeeded, rejected, auth_failed, rate_limited, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?) \
+                 ON CONFLICT (trigger_key, workflow_id) DO UPDATE SET \
+                 fired = trigger_stats.fired + excluded.fired, succeeded = trigger_stats.succeeded + excluded.succeeded, \
+                 rejected = trigger_stats.rejected + excluded.rejected, auth_failed = trigger_stats.auth_failed + excluded.auth_failed, \
+                 rate_limited = trigger_stats.rate_limited + excluded.rate_limited, updated_at = excluded.updated_at"
+            )?;
+            let now = chrono::Utc::now().to_rfc3339();
+            for record in records {
+                let counters = &record.counters;
+                add.execute((
+                    &record.trigger,
+                    &record.workflow_id,
+                    counters.fired,
+                    counters.succeeded,
+                    counters.rejected,
+                    counters.auth_failed,
+                    counters.rate_limited,
+                    &now,
+                ))?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Get the persisted firing counts of every trigger
+    pub fn get_trigger_counters(&self) -> CoreResult<Vec<TriggerCounterRecord>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT trigger_key, workflow_id, fired, succeeded, rejected, auth_failed, rate_limited FROM trigger_stats ORDER BY trigger_key, workflow_id"
+        )?;
+        let records = stmt.query_map([], |row| {
+            Ok(TriggerCounterRecord {
+                trigger: row.get(0)?,
+                workflow_id: row.get(1)?,
+                counters: TriggerCounters {
+                    fired: row.get(2)?,
+                    succeeded: row.get(3)?,
+                    rejected: row.get(4)?,
+                    auth_failed: row.get(5)?,
+                    rate_limited: row.get(6)?,
+                },
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+        Ok(records)
+    }
+
+    /// Get the last response value seen by a polling trigger
+    pub fn get_trigger_poll_state(&self, trigger_id: &str) -> CoreResult<Option<TriggerPollState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT trigger_id, workflow_id, fingerprint, value, checked_at, changed_at FROM trigger_poll_states WHERE trigger_id = ?"
+        )?;
+        let mut rows = stmt.query([trigger_id])?;
+        
+        match rows.next()? {
+            Some(row) => {
+                let value: String = row.get(3)?;
+                let checked_at: String = row.get(4)?;
+                let changed_at: Option<String> = row.get(5)?;
+                Ok(Some(TriggerPollState {
+                    trigger_id: row.get(0)?,
+                    workflow_id: row.get(1)?,
+                    fingerprint: row.get(2)?,
+                    value: serde_json::from_str(&value)?,
+                    checked_at: chrono::DateTime::parse_from_rfc3339(&checked_at)?.with_timezone(&chrono::Utc),
+                    changed_at: changed_at
+                        .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
+                        .transpose()?
+                        .map(|dt| dt.with_timezone(&chrono::Utc)),
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Delete the last response value seen by a polling trigger
+    pub fn delete_trigger_poll_state(&self, trigger_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM trigger_poll_states WHERE trigger_id = ?", [trigger_id])?;
+        Ok(())
+    }
+
+    /// Save the wake-up of a run waiting on a delay step
+    pub fn save_step_delay(&self, delay: &StepDelay) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO step_delays (run_id, step_id, workflow_id, wake_at, created_at) VALUES (?, ?, ?, ?, ?)",
+            (
+                &delay.run_id,
+                &delay.step_id,
+                &delay.workflow_id,
+                &delay.wake_at.to_rfc3339(),
+                &delay.created_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get step delays, of one run or of all runs, earliest wake-up first
+    pub fn get_step_delays(&self, run_id: Option<&str>) -> CoreResult<Vec<StepDelay>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, step_id, workflow_id, wake_at, created_at FROM step_delays
+             WHERE ?1 IS NULL OR run_id = ?1 ORDER BY wake_at ASC, run_id ASC"
+        )?;
+        
+        let mut delays = Vec::new();
+        let mut rows = stmt.query([run_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let wake_at_str: String = row.get(3)?;
+            let created_at_str: String = row.get(4)?;
+            delays.push(StepDelay {
+                run_id: row.get(0)?,
+                step_id: row.get(1)?,
+                workflow_id: row.get(2)?,
+                wake_at: chrono::DateTime::parse_from_rfc3339(&wake_at_str)?.with_timezone(&chrono::Utc),
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(delays)
+    }
+
+    /// Store the output of a cached step, replacing the entry for the same key
+    pub fn save_cached_step_output(&self, entry: &CachedStepOutput) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO step_cache (workflow_id, step_id, cache_key, output, cached_at, expires_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                &entry.workflow_id,
+                &entry.step_id,
+                &entry.key,
+                &serde_json::to_string(&entry.output)?,
+                &entry.cached_at.to_rfc3339(),
+                &entry.expires_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the cached output of a step for a key, unless it expired
+    pub fn get_cached_step_output(&self, workflow_id: &str, step_id: &str, key: &str, now: chrono::DateTime<chrono::Utc>) -> CoreResult<Option<CachedStepOutput>> {
+        let entry = self.conn.query_row(
+            "SELECT output, cached_at, expires_at FROM step_cache WHERE workflow_id = ? AND step_id = ? AND cache_key = ? AND expires_at > ?",
+            (workflow_id, step_id, key, now.to_rfc3339()),
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)),
+        ).optional()?;
+        
+        let Some((output, cached_at, expires_at)) = entry else {
+            return Ok(None);
+        };
+        Ok(Some(CachedStepOutput {
+            workflow_id: workflow_id.to_string(),
+            step_id: step_id.to_string(),
+            key: key.to_string(),
+            output: serde_json::from_str(&output)?,
+            cached_at: chrono::DateTime::parse_from_rfc3339(&cached_at)?.with_timezone(&chrono::Utc),
+            expires_at: chrono::DateTime::parse_from_rfc3339(&expires_at)?.with_timezone(&chrono::Utc),
+        }))
+    }
+
+    /// Delete expired step cache entries
+    pub fn delete_expired_step_cache(&self, now: chrono::DateTime<chrono::Utc>) -> CoreResult<usize> {
+        Ok(self.conn.execute("DELETE FROM step_cache WHERE expires_at <= ?", [now.to_rfc3339()])?)
+    }
+
+    /// Delete the delay of a step once its run was woken
+    pub fn delete_step_delay(&self, run_id: &str, step_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM step_delays WHERE run_id = ? AND step_id = ?", [run_id, step_id])?;
+        Ok(())
+    }
+
+    /// Persist the wait of a run on a signal step, replacing an earlier one for the step
+    pub fn save_signal_wait(&self, wait: &SignalWait) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO signal_waits (run_id, workflow_id, step_id, signal_name, timeout_at, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                &wait.run_id,
+                &wait.workflow_id,
+                &wait.step_id,
+                &wait.signal_name,
+                wait.timeout_at.map(|at| at.to_rfc3339()),
+                &wait.created_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get signal waits, of one run or of all runs, oldest first
+    pub fn get_signal_waits(&self, run_id: Option<&str>) -> CoreResult<Vec<SignalWait>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id, workflow_id, step_id, signal_name, timeout_at, created_at FROM signal_waits
+             WHERE ?1 IS NULL OR run_id = ?1 ORDER BY created_at ASC, run_id ASC"
+        )?;
+        
+        let mut waits = Vec::new();
+        let mut rows = stmt.query([run_id])?;
+        
+        while let Some(row) = rows.next()? {
+            let timeout_at: Option<String> = row.get(4)?;
+            let created_at: String = row.get(5)?;
+            waits.push(SignalWait {
+                run_id: row.get(0)?,
+                workflow_id: row.get(1)?,
+                step_id: row.get(2)?,
+                signal_name: row.get(3)?,
+                timeout_at: timeout_at.map(|at| chrono::DateTime::parse_from_rfc3339(&at)).transpose()?.map(|at| at.with_timezone(&chrono::Utc)),
+                created_at: chrono::DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(waits)
+    }
+
+    /// Remove the signal wait of a step of a run
+    pub fn delete_signal_wait(&self, run_id: &str, step_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM signal_waits WHERE run_id = ? AND step_id = ?", [run_id, step_id])?;
+        Ok(())
+    }
+
+    /// Keep a signal sent to a run until a signal step consumes it
+    pub fn save_run_signal(&self, run_id: &str, signal_name: &str, payload: &serde_json::Value, received_at: &chrono::DateTime<chrono::Utc>) -> CoreResult<i64> {
+        self.conn.execute(
+            "INSERT INTO run_signals (run_id, signal_name, payload, received_at) VALUES (?, ?, ?, ?)",
+            (run_id, signal_name, &serde_json::to_string(payload)?, &received_at.to_rfc3339()),
+        )?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Remove and return the oldest unconsumed signal of a name sent to a run
+    pub fn take_run_signal(&self, run_id: &str, signal_name: &str) -> CoreResult<Option<RunSignal>> {
+        let tx = self.conn.unchecked_transaction()?;
+        let signal = {
+            let mut stmt = tx.prepare(
+                "SELECT id, run_id, signal_name, payload, received_at FROM run_signals \
+                 WHERE run_id = ? AND signal_name = ? ORDER BY id ASC LIMIT 1"
+            )?;
+            let mut rows = stmt.query([run_id, signal_name])?;
+            match rows.next()? {
+                Some(row) => {
+                    let payload: String = row.get(3)?;
+                    let received_at: String = row.get(4)?;
+                    Some(RunSignal {
+                        id: row.get(0)?,
+                        run_id: row.get(1)?,
+                        signal_name: row.get(2)?,
+                        payload: serde_json::from_str(&payload)?,
+                        received_at: chrono::DateTime::parse_from_rfc3339(&received_at)?.with_timezone(&chrono::Utc),
+                    })
+                }
+                None => None,
+            }
+        };
+        if let Some(signal) = &signal {
+            tx.execute("DELETE FROM run_signals WHERE id = ?", [signal.id])?;
+        }
+        tx.commit()?;
+        Ok(signal)
+    }
+
+    /// Queue a run behind the runs of its workflow sharing its serialization key, keeping its place if already queued
+    pub fn queue_serialized_run(&self, workflow_id: &str, key: &str, run_id: &str) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR IGNORE INTO run_serialization_queue (run_id, workflow_id, serialization_key, queued_at) VALUES (?, ?, ?, ?)",
+            (run_id, workflow_id, key, &chrono::Utc::now().to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    /// Get the IDs of the runs queued on a serialization key of a workflow, in queue order
+    pub fn get_serialized_runs(&self, workflow_id: &str, key: &str) -> CoreResult<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT run_id FROM run_serialization_queue WHERE workflow_id = ? AND serialization_key = ? ORDER BY id ASC"
+        )?;
+        let run_ids = stmt.query_map([workflow_id, key], |row| row.get(0))?
+            .collect::<Result<Vec<String>, _>>()?;
+        Ok(run_ids)
+    }
+
+    /// Remove a run from its serialization queue
+    pub fn dequeue_serialized_run(&self, run_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM run_serialization_queue WHERE run_id = ?", [run_id])?;
+        Ok(())
+    }
+
+    /// Record the dedup key of a run a deduplicating trigger started, replacing the previous run of the key
+    pub fn save_trigger_dedup_key(&self, workflow_id: &str, key: &str, run_id: &str, created_at: &chrono::DateTime<chrono::Utc>) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO trigger_dedup_keys (workflow_id, dedup_key, run_id, created_at) VALUES (?, ?, ?, ?)",
+            (workflow_id, key, run_id, created_at.to_rfc3339()),
+        )?;
+        Ok(())
+    }
+
+    /// Get the run of a workflow started with a dedup key since `since`, if any
+    pub fn get_trigger_dedup_run(&self, workflow_id: &str, key: &str, since: &chrono::DateTime<chrono::Utc>) -> CoreResult<Option<String>> {
+        let run_id = self.conn.query_row(
+            "SELECT run_id FROM trigger_dedup_keys WHERE workflow_id = ? AND dedup_key = ? AND created_at >= ?",
+            (workflow_id, key, since.to_rfc3339()),
+            |row| row.get(0),
+        ).optional()?;
+        Ok(run_id)
+    }
+
+    /// Record a trigger firing suppressed as a duplicate
+    pub fn save_suppressed_trigger(&self, suppressed: &SuppressedTrigger) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT INTO suppressed_triggers (workflow_id, trigger_type, source, dedup_key, run_id, suppressed_at) VALUES (?, ?, ?, ?, ?, ?)",
+            (
+                &suppressed.workflow_id,
+                &suppressed.trigger_type,
+                &suppressed.source,
+                &suppressed.dedup_key,
+                suppressed.run_id.to_string(),
+                suppressed.suppressed_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the latest suppressed trigger firings of a workflow, newest first
+    pub fn get_suppressed_triggers(&self, workflow_id: &str, limit: usize) -> CoreResult<Vec<SuppressedTrigger>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT trigger_type, source, dedup_key, run_id, suppressed_at FROM suppressed_triggers WHERE workflow_id = ? ORDER BY id DESC LIMIT ?"
+        )?;
+        let mut rows = stmt.query((workflow_id, limit as i64))?;
+        let mut suppressed = Vec::new();
+        while let Some(row) = rows.next()? {
+            let run_id: String = row.get(3)?;
+            let suppressed_at: String = row.get(4)?;
+            suppressed.push(SuppressedTrigger {
+                workflow_id: workflow_id.to_string(),
+                trigger_type: row.get(0)?,
+                source: row.get(1)?,
+                dedup_key: row.get(2)?,
+                run_id: uuid::Uuid::parse_str(&run_id)?,
+                suppressed_at: record_compat::read_timestamp(&suppressed_at)?,
+            });
+        }
+        Ok(suppressed)
+    }
+
+    /// Save the schema of an event version
+    pub fn save_event_schema(&self, schema: &EventSchema) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO event_schemas (event_name, version, schema, registered_at) VALUES (?, ?, ?, ?)",
+            (
+                &schema.event_name,
+                schema.version,
+                &serde_json::to_string(&schema.schema)?,
+                schema.registered_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get the registered schemas of an event, oldest version first
+    pub fn get_event_schemas(&self, event_name: &str) -> CoreResult<Vec<EventSchema>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT event_name, version, schema, registered_at FROM event_schemas WHERE event_name = ? ORDER BY version ASC"
+        )?;
+        
+        let mut schemas = Vec::new();
+        let mut rows = stmt.query([event_name])?;
+        
+        while let Some(row) = rows.next()? {
+            let schema: String = row.get(2)?;
+            let registered_at: String = row.get(3)?;
+            schemas.push(EventSchema {
+                event_name: row.get(0)?,
+                version: row.get(1)?,
+                schema: serde_json::from_str(&schema)?,
+                registered_at: chrono::DateTime::parse_from_rfc3339(&registered_at)?.with_timezone(&chrono::Utc),
+            });
+        }
+        
+        Ok(schemas)
+    }
+
+    /// Save the latest state of a job
+    pub fn save_job(&self, job: &Job) -> CoreResult<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO jobs (id, workflow_id, run_id, step_name, state, job, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            (
+                &job.id,
+                &job.workflow_id,
+                &job.run_id,
+                &job.step_name,
+                &format!("{:?}", job.state),
+                &serde_json::to_string(job)?,
+                &job.metadata.created_at.to_rfc3339(),
+                &job.metadata.updated_at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    /// Get a job by ID
+    pub fn get_job(&self, job_id: &str) -> CoreResult<Option<Job>> {
+        let mut stmt = self.conn.prepare("SELECT job FROM jobs WHERE id = ?")?;
+        let mut rows = stmt.query([job_id])?;
+        match rows.next()? {
+            Some(row) => {
+                let job: String = row.get(0)?;
+                Ok(Some(serde_json::from_str(&job)?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get the jobs of a run, oldest first
+    pub fn get_jobs_for_run(&self, run_id: &str) -> CoreResult<Vec<Job>> {
         let mut stmt = self.conn.prepare(
-            "SELECT definition FROM workflows ORDER BY created_at DESC"
+            "SELECT job FROM jobs WHERE run_id = ? ORDER BY created_at ASC, id ASC"
         )?;
         
-        let mut workflows = Vec::new();
-        let mut rows = stmt.query([])?;
+        let mut jobs = Vec::new();
+        let mut rows = stmt.query([run_id])?;
         
         while let Some(row) = rows.next()? {
-            let definition: String = row.get(0)?;
-            let workflow: WorkflowDefinition = serde_json::from_str(&definition)?;
-            workflows.push(workflow);
+            let job: String = row.get(0)?;
+            jobs.push(serde_json::from_str(&job)?);
         }
         
-        Ok(workflows)
+        Ok(jobs)
     }
 
-    /// Delete a workflow
-    pub fn delete_workflow(&self, id: &str) -> CoreResult<()> {
-        self.conn.execute("DELETE FROM workflows WHERE id = ?", [id])?;
+    /// Delete a job
+    pub fn delete_job(&self, job_id: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM job_leases WHERE job_id = ?", [job_id])?;
+        self.conn.execute("DELETE FROM jobs WHERE id = ?", [job_id])?;
         Ok(())
     }
 
-    /// Save a workflow run
-    pub fn save_run(&self, run: &WorkflowRun) -> CoreResult<()> {
-        self.conn.execute(
-            "INSERT OR REPLACE INTO workflow_runs (id, workflow_id, status, payload, started_at, completed_at, error) VALUES (?, ?, ?, ?, ?, ?, ?)",
-            (
-                &run.id.to_string(),
-                &run.workflow_id,
-                &format!("{:?}", run.status),
-                &serde_json::to_string(&run.payload)?,
-                &run.started_at.to_rfc3339(),
-                &run.completed_at.map(|dt| dt.to_rfc3339()),
-                &run.error,
-            ),
+    /// Lease a job to an owner, unless another owner holds a lease on it that has not expired
+    ///
+    /// Jobs recorded as finished cannot be claimed.
+    pub fn claim_job(&self, job_id: &str, owner: &str, expires_at: &chrono::DateTime<chrono::Utc>, now: &chrono::DateTime<chrono::Utc>) -> CoreResult<bool> {
+        let claimed = self.conn.execute(
+            "INSERT INTO job_leases (job_id, owner, expires_at) SELECT ?1, ?2, ?3 \
+             WHERE NOT EXISTS (SELECT 1 FROM jobs WHERE id = ?1 AND state NOT IN ('Pending', 'Running', 'Retrying')) \
+             ON CONFLICT (job_id) DO UPDATE SET owner = excluded.owner, expires_at = excluded.expires_at \
+             WHERE job_leases.owner = excluded.owner OR job_leases.expires_at <= ?4",
+            (job_id, owner, expires_at.timestamp_millis(), now.timestamp_millis()),
         )?;
-        Ok(())
+        Ok(claimed > 0)
     }
 
-    /// Get a workflow run by ID
-    pub fn get_run(&self, run_id: &str) -> CoreResult<Option<WorkflowRun>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT workflow_id, status, payload, started_at, completed_at, error FROM workflow_runs WHERE id = ?"
+    /// Extend a job lease, returning false if the owner no longer holds it
+    pub fn renew_job_lease(&self, job_id: &str, owner: &str, expires_at: &chrono::DateTime<chrono::Utc>) -> CoreResult<bool> {
+        let renewed = self.conn.execute(
+            "UPDATE job_leases SET expires_at = ? WHERE job_id = ? AND owner = ?",
+            (expires_at.timestamp_millis(), job_id, owner),
         )?;
-        
-        let mut rows = stmt.query([run_id])?;
-        if let Some(row) = rows.next()? {
-            let workflow_id: String = row.get(0)?;
-            let status_str: String = row.get(1)?;
-            let payload_str: String = row.get(2)?;
-            let started_at_str: String = row.get(3)?;
-            let completed_at_str: Option<String> = row.get(4)?;
-            let error: Option<String> = row.get(5)?;
-            
-            let status = match status_str.as_str() {
-                "Pending" => crate::models::RunStatus::Pending,
-                "Running" => crate::models::RunStatus::Running,
-                "Completed" => crate::models::RunStatus::Completed,
-                "Failed" => crate::models::RunStatus::Failed,
-                "Cancelled" => crate::models::RunStatus::Cancelled,
-                _ => crate::models::RunStatus::Failed,
-            };
-            
-            let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)?.with_timezone(&chrono::Utc);
-            let completed_at = completed_at_str
-                .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
-                .transpose()?
-                .map(|dt| dt.with_timezone(&chrono::Utc));
-            
-            let payload = serde_json::from_str(&payload_str)?;
-            
-            let run = WorkflowRun {
-                id: uuid::Uuid::parse_str(run_id)?,
-                workflow_id,
-                status,
-                payload,
-                started_at,
-                completed_at,
-                error,
-            };
-            
-            Ok(Some(run))
-        } else {
-            Ok(None)
-        }
+        Ok(renewed > 0)
     }
 
-    /// Get runs for a workflow
-    pub fn get_runs_for_workflow(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowRun>> {
+    /// Give up a job lease the owner holds
+    pub fn release_job(&self, job_id: &str, owner: &str) -> CoreResult<()> {
+        self.conn.execute("DELETE FROM job_leases WHERE job_id = ? AND owner = ?", (job_id, owner))?;
+        Ok(())
+    }
+
+    /// Get unfinished jobs of unfinished runs that no unexpired lease holds, oldest first
+    pub fn get_claimable_jobs(&self, now: &chrono::DateTime<chrono::Utc>, limit: usize) -> CoreResult<Vec<Job>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, status, payload, started_at, completed_at, error FROM workflow_runs WHERE workflow_id = ? ORDER BY started_at DESC"
+            "SELECT j.job FROM jobs j \
+             JOIN workflow_runs r ON r.id = j.run_id \
+             LEFT JOIN job_leases l ON l.job_id = j.id \
+             WHERE j.state IN ('Pending', 'Running', 'Retrying') AND r.status IN ('Pending', 'Running') \
+             AND (l.job_id IS NULL OR l.expires_at <= ?) \
+             ORDER BY j.created_at ASC, j.id ASC LIMIT ?"
         )?;
         
-        let mut runs = Vec::new();
-        let mut rows = stmt.query([workflow_id])?;
+        let mut jobs = Vec::new();
+        let mut rows = stmt.query((now.timestamp_millis(), limit as i64))?;
         
         while let Some(row) = rows.next()? {
-            let run_id_str: String = row.get(0)?;
-            let status_str: String = row.get(1)?;
-            let payload_str: String = row.get(2)?;
-            let started_at_str: String = row.get(3)?;
-            let completed_at_str: Option<String> = row.get(4)?;
-            let error: Option<String> = row.get(5)?;
-            
-            let status = match status_str.as_str() {
-                "Pending" => crate::models::RunStatus::Pending,
-                "Running" => crate::models::RunStatus::Running,
-                "Completed" => crate::models::RunStatus::Completed,
-                "Failed" => crate::models::RunStatus::Failed,
-                "Cancelled" => crate::models::RunStatus::Cancelled,
-                _ => crate::models::RunStatus::Failed,
-            };
-            
-            let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)?.with_timezone(&chrono::Utc);
-            let completed_at = completed_at_str
-                .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
-                .transpose()?
-                .map(|dt| dt.with_timezone(&chrono::Utc));
-            
-            let payload = serde_json::from_str(&payload_str)?;
-            
-            let run = WorkflowRun {
-                id: uuid::Uuid::parse_str(&run_id_str)?,
-                workflow_id: workflow_id.to_string(),
-                status,
-                payload,
-                started_at,
-                completed_at,
-                error,
-            };
-            
-            runs.push(run);
+            let job: String = row.get(0)?;
+            jobs.push(serde_json::from_str(&job)?);
         }
         
-        Ok(runs)
+        Ok(jobs)
     }
 
-    /// Save a step result
-    pub fn save_step_result(&self, result: &StepResult, run_id: &str) -> CoreResult<()> {
+    /// Save a payload blob, keeping the existing copy of identical data
+    pub fn save_payload_blob(&self, blob: &PayloadBlob) -> CoreResult<()> {
+        save_payload_blob_on(&self.conn, blob)
+    }
+
+    /// Get the data of a payload blob
+    pub fn get_payload_blob(&self, id: &str) -> CoreResult<Option<String>> {
+        get_payload_blob_on(&self.conn, id)
+    }
+
+    /// Record a completed cold storage export
+    pub fn record_cold_storage_export(
+        &self,
+        range_start: &chrono::DateTime<chrono::Utc>,
+        range_end: &chrono::DateTime<chrono::Utc>,
+        files_written: usize,
+        rows_written: usize,
+    ) -> CoreResult<()> {
         self.conn.execute(
-            "INSERT INTO step_results (run_id, step_id, status, output, error, started_at, completed_at, duration_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO cold_storage_exports (range_start, range_end, files_written, rows_written, exported_at) VALUES (?, ?, ?, ?, ?)",
             (
-                run_id,
-                &result.step_id,
-                &format!("{:?}", result.status),
-                &result.output.as_ref().map(|v| serde_json::to_string(v)).transpose()?,
-                &result.error,
-                &result.started_at.to_rfc3339(),
-                &result.completed_at.map(|dt| dt.to_rfc3339()),
-                &result.duration_ms,
+                &range_start.to_rfc3339(),
+                &range_end.to_rfc3339(),
+                files_written as i64,
+                rows_written as i64,
+                &chrono::Utc::now().to_rfc3339(),
             ),
         )?;
         Ok(())
     }
 
-    /// Get step results for a run
-    pub fn get_step_results(&self, run_id: &str) -> CoreResult<Vec<StepResult>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT step_id, status, output, error, started_at, completed_at, duration_ms FROM step_results WHERE run_id = ? ORDER BY started_at ASC"
+    /// Get the end of the most recent cold storage export range, if any
+    pub fn get_last_cold_storage_export_end(&self) -> CoreResult<Option<chrono::DateTime<chrono::Utc>>> {
+        let range_end: Option<String> = self.conn.query_row(
+            "SELECT MAX(range_end) FROM cold_storage_exports",
+            [],
+            |row| row.get(0),
         )?;
         
-        let mut results = Vec::new();
-        let mut rows = stmt.query([run_id])?;
-        
-        while let Some(row) = rows.next()? {
-            let step_id: String = row.get(0)?;
-            let status_str: String = row.get(1)?;
-            let output_str: Option<String> = row.get(2)?;
-            let error: Option<String> = row.get(3)?;
-            let started_at_str: String = row.get(4)?;
-            let completed_at_str: Option<String> = row.get(5)?;
-            let duration_ms: Option<u64> = row.get(6)?;
-            
-            let status = match status_str.as_str() {
-                "Pending" => crate::models::StepStatus::Pending,
-                "Running" => crate::models::StepStatus::Running,
-                "Completed" => crate::models::StepStatus::Completed,
-                "Failed" => crate::models::StepStatus::Failed,
-                "Skipped" => crate::models::StepStatus::Skipped,
-                _ => crate::models::StepStatus::Failed,
-            };
-            
-            let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)?.with_timezone(&chrono::Utc);
-            let completed_at = completed_at_str
-                .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
-                .transpose()?
-                .map(|dt| dt.with_timezone(&chrono::Utc));
-            
-            let output = output_str
-                .map(|s| serde_json::from_str(&s))
-                .transpose()?;
-            
-            let result = StepResult {
-                step_id,
-                status,
-                output,
-                error,
-                started_at,
-                completed_at,
-                duration_ms,
-            };
-            
-            results.push(result);
-        }
-        
-        Ok(results)
+        Ok(range_end
+            .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
+            .transpose()?
+            .map(|dt| dt.with_timezone(&chrono::Utc)))
     }
 
     /// Get database statistics
@@ -305,6 +2202,211 @@ impl Database {
     }
 }
 
+/// Append an event to a run's event log on a connection
+fn save_run_event_on(conn: &Connection, event: &RunEvent) -> CoreResult<()> {
+    conn.execute(
+        "INSERT INTO run_events (run_id, event_type, step_id, actor, detail, created_at) VALUES (?, ?, ?, ?, ?, ?)",
+        (
+            &event.run_id,
+            &event.event_type,
+            &event.step_id,
+            &event.actor,
+            &serde_json::to_string(&event.detail)?,
+            &event.created_at.to_rfc3339(),
+        ),
+    )?;
+    Ok(())
+}
+
+/// Get the events of a run in the order they were recorded on a connection
+fn get_run_events_on(conn: &Connection, run_id: &str) -> CoreResult<Vec<RunEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT run_id, event_type, step_id, actor, detail, created_at FROM run_events WHERE run_id = ? ORDER BY id ASC"
+    )?;
+    
+    let mut events = Vec::new();
+    let mut rows = stmt.query([run_id])?;
+    
+    while let Some(row) = rows.next()? {
+        let detail_str: String = row.get(4)?;
+        let created_at_str: String = row.get(5)?;
+        events.push(RunEvent {
+            run_id: row.get(0)?,
+            event_type: row.get(1)?,
+            step_id: row.get(2)?,
+            actor: row.get(3)?,
+            detail: serde_json::from_str(&detail_str)?,
+            created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)?.with_timezone(&chrono::Utc),
+        });
+    }
+    
+    Ok(events)
+}
+
+/// Get the labels of a run on a connection
+fn get_run_labels_on(conn: &Connection, run_id: &str) -> CoreResult<RunLabels> {
+    let mut stmt = conn.prepare("SELECT key, value FROM run_labels WHERE run_id = ?")?;
+    let labels = stmt.query_map([run_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<RunLabels, _>>()?;
+    Ok(labels)
+}
+
+/// Get what started a run on a connection
+fn get_run_trigger_on(conn: &Connection, run_id: &str) -> CoreResult<Option<RunTrigger>> {
+    let mut stmt = conn.prepare("SELECT trigger_type, source, fired_at, trace_parent, lane FROM run_triggers WHERE run_id = ?")?;
+    let mut rows = stmt.query([run_id])?;
+    match rows.next()? {
+        Some(row) => {
+            let fired_at: String = row.get(2)?;
+            Ok(Some(RunTrigger {
+                trigger_type: row.get(0)?,
+                source: row.get(1)?,
+                fired_at: chrono::DateTime::parse_from_rfc3339(&fired_at)?.with_timezone(&chrono::Utc),
+                trace_parent: row.get(3)?,
+                lane: row.get(4)?,
+                labels: RunLabels::new(),
+            }))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Replace the search index entries of a run's payload or step output on a connection
+fn index_run_document_on(conn: &Connection, run: &WorkflowRun, source: &str, document: &serde_json::Value) -> CoreResult<()> {
+    let run_id = run.id.to_string();
+    let started_at = run.started_at.to_rfc3339();
+    let tx = conn.unchecked_transaction()?;
+    tx.execute("DELETE FROM run_search_index WHERE run_id = ? AND source = ?", (&run_id, source))?;
+    {
+        let mut insert = tx.prepare(
+            "INSERT OR REPLACE INTO run_search_index (run_id, workflow_id, run_started_at, source, path, value) VALUES (?, ?, ?, ?, ?, ?)"
+        )?;
+        for (path, value) in crate::run_search::index_entries(document) {
+            insert.execute((&run_id, &run.workflow_id, &started_at, source, &path, &value))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Save a payload blob on a connection
+fn save_payload_blob_on(conn: &Connection, blob: &PayloadBlob) -> CoreResult<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO payload_blobs (id, data, size_bytes, created_at) VALUES (?, ?, ?, ?)",
+        (&blob.id, &blob.data, blob.size_bytes as i64, &blob.created_at.to_rfc3339()),
+    )?;
+    Ok(())
+}
+
+/// Get the data of a payload blob on a connection
+fn get_payload_blob_on(conn: &Connection, id: &str) -> CoreResult<Option<String>> {
+    let mut stmt = conn.prepare("SELECT data FROM payload_blobs WHERE id = ?")?;
+    let mut rows = stmt.query([id])?;
+    match rows.next()? {
+        Some(row) => Ok(Some(row.get(0)?)),
+        None => Ok(None),
+    }
+}
+
+/// List runs matching a query on a connection, newest first
+fn list_runs_on(conn: &Connection, query: &RunQuery) -> CoreResult<RunPage> {
+    let mut conditions = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    
+    if let Some(workflow_id) = &query.workflow_id {
+        conditions.push("r.workflow_id = ?");
+        params.push(workflow_id.clone());
+    }
+    if let Some(status) = &query.status {
+        conditions.push("r.status = ?");
+        params.push(format!("{:?}", status));
+    }
+    if let Some(since) = &query.since {
+        conditions.push("r.started_at >= ?");
+        params.push(since.to_rfc3339());
+    }
+    match query.pinned {
+        Some(true) => conditions.push("p.run_id IS NOT NULL"),
+        Some(false) => conditions.push("p.run_id IS NULL"),
+        None => {}
+    }
+    for (key, value) in &query.labels {
+        conditions.push("EXISTS (SELECT 1 FROM run_labels l WHERE l.run_id = r.id AND l.key = ? AND l.value = ?)");
+        params.push(key.clone());
+        params.push(value.clone());
+    }
+    
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!(" WHERE {}", conditions.join(" AND "))
+    };
+    let from_clause = "FROM workflow_runs r LEFT JOIN run_pins p ON p.run_id = r.id";
+    
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) {}{}", from_clause, where_clause),
+        rusqlite::params_from_iter(params.iter()),
+        |row| row.get(0),
+    )?;
+    
+    let limit = query.effective_limit();
+    let offset = query.offset.unwrap_or(0);
+    let mut stmt = conn.prepare(&format!(
+        "SELECT r.id, r.workflow_id, r.status, r.payload, r.started_at, r.completed_at, r.error, p.reason, p.pinned_by, p.pinned_at, r.version \
+         {}{} ORDER BY r.started_at DESC LIMIT {} OFFSET {}",
+        from_clause, where_clause, limit, offset
+    ))?;
+    
+    let mut runs = Vec::new();
+    let mut rows = stmt.query(rusqlite::params_from_iter(params.iter()))?;
+    
+    while let Some(row) = rows.next()? {
+        let run_id_str: String = row.get(0)?;
+        let status_str: String = row.get(2)?;
+        let payload_str: String = row.get(3)?;
+        let started_at_str: String = row.get(4)?;
+        let completed_at_str: Option<String> = row.get(5)?;
+        let pinned_at_str: Option<String> = row.get(9)?;
+        
+        let completed_at = completed_at_str
+            .as_deref()
+            .map(record_compat::read_timestamp)
+            .transpose()?;
+        
+        let pin = match pinned_at_str {
+            Some(pinned_at) => Some(RunPin {
+                run_id: run_id_str.clone(),
+                reason: row.get(7)?,
+                pinned_by: row.get(8)?,
+                pinned_at: chrono::DateTime::parse_from_rfc3339(&pinned_at)?.with_timezone(&chrono::Utc),
+            }),
+            None => None,
+        };
+        
+        runs.push(RunListEntry {
+            run: WorkflowRun {
+                id: uuid::Uuid::parse_str(&run_id_str)?,
+                workflow_id: row.get(1)?,
+                status: record_compat::read_run_status(&status_str),
+                payload: serde_json::from_str(&payload_str)?,
+                started_at: record_compat::read_timestamp(&started_at_str)?,
+                completed_at,
+                error: row.get(6)?,
+                version: row.get(10)?,
+            },
+            pin,
+            labels: get_run_labels_on(conn, &run_id_str)?,
+        });
+    }
+    
+    Ok(RunPage {
+        runs,
+        total: total as usize,
+        limit,
+        offset,
+    })
+}
+
 // ============================================================================
 // ASYNC DATABASE WRAPPER (Task 2.1.1)
 // ============================================================================
@@ -319,15 +2421,16 @@ impl AsyncDatabase {
             }
         }
         
-        let conn = Connection::open(path)?;
+        let pool = ConnectionPool::open(path, &DatabaseConfig::default())?;
         
         // Initialize schema
-        let schema = include_str!("schema.sql");
-        conn.execute_batch(schema)?;
+        let conn = pool.get()?;
+        migrations::migrate(&conn)?;
+        drop(conn);
         
         Ok(AsyncDatabase {
             db_path: path.to_string(),
-            conn: Arc::new(Mutex::new(conn)),
+            pool: Arc::new(pool),
         })
     }
 
@@ -337,11 +2440,10 @@ impl AsyncDatabase {
         F: FnOnce(&Connection) -> CoreResult<T> + Send + 'static,
         T: Send + 'static,
     {
-        let conn = self.conn.clone();
+        let pool = self.pool.clone();
         tokio::task::spawn_blocking(move || {
-            let connection = conn.lock()
-                .map_err(|e| CoreError::Internal(format!("Failed to acquire database lock: {}", e)))?;
-            operation(&*connection)
+            let connection = pool.get()?;
+            operation(&connection)
         })
         .await
         .map_err(|e| CoreError::Internal(format!("Task join error: {}", e)))?
@@ -351,9 +2453,9 @@ impl AsyncDatabase {
     pub async fn save_workflow(&self, workflow: &WorkflowDefinition) -> CoreResult<()> {
         let workflow = workflow.clone();
         self.execute_blocking(move |conn| {
-            let definition = serde_json::to_string(&workflow)?;
+            let definition = serde_json::to_string(&record_compat::workflow_to_record(&workflow)?)?;
             conn.execute(
-                "INSERT OR REPLACE INTO workflows (id, name, description, definition, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+                SAVE_WORKFLOW,
                 (
                     &workflow.id,
                     &workflow.name,
@@ -377,7 +2479,7 @@ impl AsyncDatabase {
             let mut rows = stmt.query([&id])?;
             if let Some(row) = rows.next()? {
                 let definition: String = row.get(0)?;
-                let workflow: WorkflowDefinition = serde_json::from_str(&definition)?;
+                let workflow = record_compat::workflow_from_record(serde_json::from_str(&definition)?)?;
                 Ok(Some(workflow))
             } else {
                 Ok(None)
@@ -397,7 +2499,7 @@ impl AsyncDatabase {
             
             while let Some(row) = rows.next()? {
                 let definition: String = row.get(0)?;
-                let workflow: WorkflowDefinition = serde_json::from_str(&definition)?;
+                let workflow = record_compat::workflow_from_record(serde_json::from_str(&definition)?)?;
                 workflows.push(workflow);
             }
             
@@ -418,7 +2520,7 @@ impl AsyncDatabase {
         let run = run.clone();
         self.execute_blocking(move |conn| {
             conn.execute(
-                "INSERT OR REPLACE INTO workflow_runs (id, workflow_id, status, payload, started_at, completed_at, error) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                SAVE_RUN,
                 (
                     &run.id.to_string(),
                     &run.workflow_id,
@@ -433,11 +2535,30 @@ impl AsyncDatabase {
         }).await
     }
 
+    /// Rewrite a run unless it was written since it was read, returning false on a conflict (async)
+    pub async fn save_run_if_version(&self, run: &WorkflowRun) -> CoreResult<bool> {
+        let run = run.clone();
+        self.execute_blocking(move |conn| {
+            let updated = conn.execute(
+                UPDATE_RUN_IF_VERSION,
+                (
+                    &format!("{:?}", run.status),
+                    &serde_json::to_string(&run.payload)?,
+                    &run.completed_at.map(|dt| dt.to_rfc3339()),
+                    &run.error,
+                    &run.id.to_string(),
+                    run.version,
+                ),
+            )?;
+            Ok(updated > 0)
+        }).await
+    }
+
     /// Get a workflow run by ID (async)
     pub async fn get_run(&self, run_id: String) -> CoreResult<Option<WorkflowRun>> {
         self.execute_blocking(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT workflow_id, status, payload, started_at, completed_at, error FROM workflow_runs WHERE id = ?"
+                "SELECT workflow_id, status, payload, started_at, completed_at, error, version FROM workflow_runs WHERE id = ?"
             )?;
             
             let mut rows = stmt.query([&run_id])?;
@@ -448,21 +2569,15 @@ impl AsyncDatabase {
                 let started_at_str: String = row.get(3)?;
                 let completed_at_str: Option<String> = row.get(4)?;
                 let error: Option<String> = row.get(5)?;
+                let version: u64 = row.get(6)?;
                 
-                let status = match status_str.as_str() {
-                    "Pending" => crate::models::RunStatus::Pending,
-                    "Running" => crate::models::RunStatus::Running,
-                    "Completed" => crate::models::RunStatus::Completed,
-                    "Failed" => crate::models::RunStatus::Failed,
-                    "Cancelled" => crate::models::RunStatus::Cancelled,
-                    _ => crate::models::RunStatus::Failed,
-                };
+                let status = record_compat::read_run_status(&status_str);
                 
-                let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)?.with_timezone(&chrono::Utc);
+                let started_at = record_compat::read_timestamp(&started_at_str)?;
                 let completed_at = completed_at_str
-                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
-                    .transpose()?
-                    .map(|dt| dt.with_timezone(&chrono::Utc));
+                    .as_deref()
+                    .map(record_compat::read_timestamp)
+                    .transpose()?;
                 
                 let payload = serde_json::from_str(&payload_str)?;
                 
@@ -474,6 +2589,7 @@ impl AsyncDatabase {
                     started_at,
                     completed_at,
                     error,
+                    version,
                 };
                 
                 Ok(Some(run))
@@ -487,7 +2603,7 @@ impl AsyncDatabase {
     pub async fn get_runs_for_workflow(&self, workflow_id: String) -> CoreResult<Vec<WorkflowRun>> {
         self.execute_blocking(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT id, status, payload, started_at, completed_at, error FROM workflow_runs WHERE workflow_id = ? ORDER BY started_at DESC"
+                "SELECT id, status, payload, started_at, completed_at, error, version FROM workflow_runs WHERE workflow_id = ? ORDER BY started_at DESC"
             )?;
             
             let mut runs = Vec::new();
@@ -500,21 +2616,15 @@ impl AsyncDatabase {
                 let started_at_str: String = row.get(3)?;
                 let completed_at_str: Option<String> = row.get(4)?;
                 let error: Option<String> = row.get(5)?;
+                let version: u64 = row.get(6)?;
                 
-                let status = match status_str.as_str() {
-                    "Pending" => crate::models::RunStatus::Pending,
-                    "Running" => crate::models::RunStatus::Running,
-                    "Completed" => crate::models::RunStatus::Completed,
-                    "Failed" => crate::models::RunStatus::Failed,
-                    "Cancelled" => crate::models::RunStatus::Cancelled,
-                    _ => crate::models::RunStatus::Failed,
-                };
+                let status = record_compat::read_run_status(&status_str);
                 
-                let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)?.with_timezone(&chrono::Utc);
+                let started_at = record_compat::read_timestamp(&started_at_str)?;
                 let completed_at = completed_at_str
-                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
-                    .transpose()?
-                    .map(|dt| dt.with_timezone(&chrono::Utc));
+                    .as_deref()
+                    .map(record_compat::read_timestamp)
+                    .transpose()?;
                 
                 let payload = serde_json::from_str(&payload_str)?;
                 
@@ -526,6 +2636,7 @@ impl AsyncDatabase {
                     started_at,
                     completed_at,
                     error,
+                    version,
                 };
                 
                 runs.push(run);
@@ -540,7 +2651,7 @@ impl AsyncDatabase {
         let result = result.clone();
         self.execute_blocking(move |conn| {
             conn.execute(
-                "INSERT INTO step_results (run_id, step_id, status, output, error, started_at, completed_at, duration_ms) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                "INSERT INTO step_results (run_id, step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
                 (
                     &run_id,
                     &result.step_id,
@@ -550,17 +2661,89 @@ impl AsyncDatabase {
                     &result.started_at.to_rfc3339(),
                     &result.completed_at.map(|dt| dt.to_rfc3339()),
                     &result.duration_ms,
+                    &result.cache_hit.as_ref().map(serde_json::to_string).transpose()?,
+                    &result.resources.as_ref().map(serde_json::to_string).transpose()?,
+                ),
+            )?;
+            Ok(())
+        }).await
+    }
+
+    /// Append an event to a run's event log (async)
+    pub async fn save_run_event(&self, event: &RunEvent) -> CoreResult<()> {
+        let event = event.clone();
+        self.execute_blocking(move |conn| save_run_event_on(conn, &event)).await
+    }
+
+    /// Get the events of a run in the order they were recorded (async)
+    pub async fn get_run_events(&self, run_id: String) -> CoreResult<Vec<RunEvent>> {
+        self.execute_blocking(move |conn| get_run_events_on(conn, &run_id)).await
+    }
+
+    /// Get what started a run (async)
+    pub async fn get_run_trigger(&self, run_id: String) -> CoreResult<Option<RunTrigger>> {
+        self.execute_blocking(move |conn| get_run_trigger_on(conn, &run_id)).await
+    }
+
+    /// Record the checksum of the latest context issued for a step (async)
+    pub async fn save_step_context_checksum(&self, issued: &StepContextChecksum) -> CoreResult<()> {
+        let issued = issued.clone();
+        self.execute_blocking(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO step_context_checksums (run_id, step_id, checksum, schema_version, attempt, issued_at) VALUES (?, ?, ?, ?, ?, ?)",
+                (
+                    &issued.run_id,
+                    &issued.step_id,
+                    &issued.checksum,
+                    issued.schema_version,
+                    issued.attempt,
+                    &issued.issued_at.to_rfc3339(),
                 ),
             )?;
             Ok(())
         }).await
     }
 
+    /// Get the unexpired context state entries of a scope (async)
+    pub async fn get_state_entries(&self, scope: StateScope, scope_id: String, now: chrono::DateTime<chrono::Utc>) -> CoreResult<Vec<StateEntry>> {
+        self.execute_blocking(move |conn| query_state_entries(conn, scope, &scope_id, None, now)).await
+    }
+
+    /// Get the labels of a run (async)
+    pub async fn get_run_labels(&self, run_id: String) -> CoreResult<RunLabels> {
+        self.execute_blocking(move |conn| get_run_labels_on(conn, &run_id)).await
+    }
+
+    /// Get the encrypted secrets of the given scopes (async)
+    pub async fn get_secrets(&self, scopes: Vec<String>) -> CoreResult<Vec<StoredSecret>> {
+        self.execute_blocking(move |conn| {
+            let scopes: Vec<&str> = scopes.iter().map(String::as_str).collect();
+            query_secrets(conn, Some(&scopes))
+        }).await
+    }
+
+    /// Replace the search index entries of a run's payload or of one of its step outputs (async)
+    pub async fn index_run_document(&self, run: &WorkflowRun, source: String, document: serde_json::Value) -> CoreResult<()> {
+        let run = run.clone();
+        self.execute_blocking(move |conn| index_run_document_on(conn, &run, &source, &document)).await
+    }
+
+    /// Save a payload blob (async)
+    pub async fn save_payload_blob(&self, blob: &PayloadBlob) -> CoreResult<()> {
+        let blob = blob.clone();
+        self.execute_blocking(move |conn| save_payload_blob_on(conn, &blob)).await
+    }
+
+    /// Get the data of a payload blob (async)
+    pub async fn get_payload_blob(&self, id: String) -> CoreResult<Option<String>> {
+        self.execute_blocking(move |conn| get_payload_blob_on(conn, &id)).await
+    }
+
     /// Get step results for a run (async)
     pub async fn get_step_results(&self, run_id: String) -> CoreResult<Vec<StepResult>> {
         self.execute_blocking(move |conn| {
             let mut stmt = conn.prepare(
-                "SELECT step_id, status, output, error, started_at, completed_at, duration_ms FROM step_results WHERE run_id = ? ORDER BY started_at ASC"
+                "SELECT step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources FROM step_results WHERE run_id = ? ORDER BY started_at ASC"
             )?;
             
             let mut results = Vec::new();
@@ -574,25 +2757,26 @@ impl AsyncDatabase {
                 let started_at_str: String = row.get(4)?;
                 let completed_at_str: Option<String> = row.get(5)?;
                 let duration_ms: Option<u64> = row.get(6)?;
+                let cache_hit: Option<String> = row.get(7)?;
+                let resources: Option<String> = row.get(8)?;
                 
-                let status = match status_str.as_str() {
-                    "Pending" => crate::models::StepStatus::Pending,
-                    "Running" => crate::models::StepStatus::Running,
-                    "Completed" => crate::models::StepStatus::Completed,
-                    "Failed" => crate::models::StepStatus::Failed,
-                    "Skipped" => crate::models::StepStatus::Skipped,
-                    _ => crate::models::StepStatus::Failed,
-                };
+                let status = record_compat::read_step_status(&status_str);
                 
-                let started_at = chrono::DateTime::parse_from_rfc3339(&started_at_str)?.with_timezone(&chrono::Utc);
+                let started_at = record_compat::read_timestamp(&started_at_str)?;
                 let completed_at = completed_at_str
-                    .map(|s| chrono::DateTime::parse_from_rfc3339(&s))
-                    .transpose()?
-                    .map(|dt| dt.with_timezone(&chrono::Utc));
+                    .as_deref()
+                    .map(record_compat::read_timestamp)
+                    .transpose()?;
                 
                 let output = output_str
                     .map(|s| serde_json::from_str(&s))
                     .transpose()?;
+                let cache_hit = cache_hit
+                    .map(|s| serde_json::from_str(&s))
+                    .transpose()?;
+                let resources = resources
+                    .map(|s| serde_json::from_str(&s))
+                    .transpose()?;
                 
                 let result = StepResult {
                     step_id,
@@ -602,6 +2786,8 @@ impl AsyncDatabase {
                     started_at,
                     completed_at,
                     duration_ms,
+                    cache_hit,
+                    resources,
                 };
                 
                 results.push(result);
@@ -611,6 +2797,11 @@ impl AsyncDatabase {
         }).await
     }
 
+    /// List runs matching a query, newest first (async)
+    pub async fn list_runs(&self, query: RunQuery) -> CoreResult<RunPage> {
+        self.execute_blocking(move |conn| list_runs_on(conn, &query)).await
+    }
+
     /// Get database statistics (async)
     pub async fn get_stats(&self) -> CoreResult<serde_json::Value> {
         self.execute_blocking(|conn| {
@@ -629,4 +2820,171 @@ impl AsyncDatabase {
             }))
         }).await
     }
-} 
\ No newline at end of file
+} 
+
+/// Query the encrypted secrets of the given scopes, or of every scope
+fn query_secrets(conn: &Connection, scopes: Option<&[&str]>) -> CoreResult<Vec<StoredSecret>> {
+    let mut stmt = conn.prepare(
+        "SELECT scope, key, nonce, ciphertext, updated_at FROM secrets ORDER BY scope ASC, key ASC"
+    )?;
+    
+    let mut secrets = Vec::new();
+    let mut rows = stmt.query([])?;
+    
+    while let Some(row) = rows.next()? {
+        let scope: String = row.get(0)?;
+        if scopes.is_some_and(|scopes| !scopes.contains(&scope.as_str())) {
+            continue;
+        }
+        let updated_at_str: String = row.get(4)?;
+        secrets.push(StoredSecret {
+            scope,
+            key: row.get(1)?,
+            nonce: row.get(2)?,
+            ciphertext: row.get(3)?,
+            updated_at: chrono::DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&chrono::Utc),
+        });
+    }
+    
+    Ok(secrets)
+}
+
+fn query_state_entries(
+    conn: &Connection,
+    scope: StateScope,
+    scope_id: &str,
+    key: Option<&str>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> CoreResult<Vec<StateEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT key, value, expires_at, updated_at FROM context_state WHERE scope = ?1 AND scope_id = ?2 AND (?3 IS NULL OR key = ?3) AND (expires_at IS NULL OR expires_at > ?4) ORDER BY key ASC"
+    )?;
+    
+    let mut entries = Vec::new();
+    let mut rows = stmt.query((scope.as_str(), scope_id, key, &now.to_rfc3339()))?;
+    
+    while let Some(row) = rows.next()? {
+        let value_str: String = row.get(1)?;
+        let expires_at_str: Option<String> = row.get(2)?;
+        let updated_at_str: String = row.get(3)?;
+        entries.push(StateEntry {
+            scope,
+            scope_id: scope_id.to_string(),
+            key: row.get(0)?,
+            value: serde_json::from_str(&value_str)?,
+            expires_at: expires_at_str
+                .map(|t| chrono::DateTime::parse_from_rfc3339(&t).map(|t| t.with_timezone(&chrono::Utc)))
+                .transpose()?,
+            updated_at: chrono::DateTime::parse_from_rfc3339(&updated_at_str)?.with_timezone(&chrono::Utc),
+        });
+    }
+    
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RunStatus, StepStatus};
+
+    #[test]
+    fn test_connection_pool_checkout_and_wal_reads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pool.db");
+        let config = DatabaseConfig {
+            max_connections: 2,
+            connection_timeout_ms: 50,
+            ..DatabaseConfig::default()
+        };
+        let pool = ConnectionPool::open(path.to_str().unwrap(), &config).unwrap();
+        assert_eq!(pool.size(), 2);
+        assert_eq!(ConnectionPool::open(":memory:", &DatabaseConfig { max_connections: 4, ..config.clone() }).unwrap().size(), 1);
+
+        let writer = pool.get().unwrap();
+        writer.execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY); INSERT INTO items VALUES (1);").unwrap();
+        let settings = read_sqlite_settings(&writer).unwrap();
+        assert_eq!(settings.journal_mode, "wal");
+        assert_eq!(settings.synchronous, config.synchronous.to_uppercase());
+        assert_eq!((settings.busy_timeout_ms, settings.cache_size), (config.busy_timeout_ms, config.cache_size));
+
+        // Readers are not blocked by an open write transaction
+        writer.execute_batch("BEGIN IMMEDIATE; INSERT INTO items VALUES (2);").unwrap();
+        let reader = pool.get().unwrap();
+        let count: i64 = reader.query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+
+        assert!(pool.get().is_err());
+        drop(reader);
+        assert!(pool.get().is_ok());
+        writer.execute_batch("COMMIT;").unwrap();
+    }
+
+    #[test]
+    fn test_step_results_batch_writes_events_in_its_transaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::new(dir.path().join("batch.db").to_str().unwrap()).unwrap();
+        db.save_workflow(&WorkflowDefinition {
+            id: "batch-workflow".to_string(),
+            name: "Batch Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let mut run = WorkflowRun {
+            id: uuid::Uuid::new_v4(),
+            workflow_id: "batch-workflow".to_string(),
+            status: RunStatus::Running,
+            payload: serde_json::json!({}),
+            started_at: chrono::Utc::now(),
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        db.save_run(&run).unwrap();
+        let run_id = run.id.to_string();
+
+        let result = StepResult {
+            step_id: "a".to_string(),
+            status: StepStatus::Completed,
+            output: None,
+            error: None,
+            started_at: chrono::Utc::now(),
+            completed_at: Some(chrono::Utc::now()),
+            duration_ms: Some(5),
+            cache_hit: None,
+            resources: None,
+        };
+        let event = RunEvent {
+            run_id: run_id.clone(),
+            event_type: RunEvent::for_step_status(&result.status).to_string(),
+            step_id: Some("a".to_string()),
+            actor: RunEvent::ACTOR_API.to_string(),
+            detail: RunEvent::step_result_detail(&result),
+            created_at: chrono::Utc::now(),
+        };
+        let results = vec![(run_id.clone(), result)];
+
+        // A conflicting run update rolls back the events with the step results
+        run.version = 1;
+        let conflict = db.save_step_results_batch_with_events(&results, std::slice::from_ref(&run), std::slice::from_ref(&event));
+        assert!(matches!(conflict, Err(CoreError::Conflict(_))));
+        assert!(db.get_run_events(&run_id).unwrap().is_empty());
+        assert!(db.get_step_results(&run_id).unwrap().is_empty());
+
+        run.version = 0;
+        db.save_step_results_batch_with_events(&results, &[run], &[event]).unwrap();
+        assert_eq!(db.get_run_events(&run_id).unwrap().len(), 1);
+    }
+}
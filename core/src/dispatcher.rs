@@ -1,15 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
-use tokio::sync::Mutex;
+use tokio::sync::{oneshot, Mutex};
 use tokio::task::JoinHandle;
 
 use crate::error::CoreError;
-use crate::job::{Job, JobQueue, JobState};
-use crate::models::{StepResult, StepStatus, WorkflowDefinition, WorkflowRun, RunStatus};
+use crate::job::{Job, JobPriority, JobQueue, JobState};
+use crate::models::{StepResult, StepStatus, WorkflowDefinition, WorkflowRun, RunStatus, RunEvent, WorkerDiagnostics, StepControl, ResourceQuota, ResourceUsage};
 use crate::state::StateManager;
+use crate::context::{Context, ExecutionInfo};
+use crate::chaos::{ChaosRules, ChaosStatus, Fault, FaultInjector};
+use crate::resource_usage::UsageSample;
+use crate::retry_classifier::{ClassifierHandler, RetryClassifier, RetryDecision};
+use crate::config::{ChaosConfig, DistributedConfig, FeatureFlags, LaneAllocation, NamespaceQuota, RetentionConfig, RetryStormConfig};
 use serde_json;
 use serde::Serialize;
 
@@ -19,7 +25,19 @@ pub struct WorkerPoolConfig {
     pub min_workers: usize,
     pub max_workers: usize,
     pub worker_timeout_ms: u64,
+    pub heartbeat_timeout_ms: u64,
     pub queue_size: usize,
+    pub prefetch_contexts: bool,
+    pub prefetch_cache_size: usize,
+    pub namespace_quotas: HashMap<String, NamespaceQuota>,
+    pub lanes: HashMap<String, LaneAllocation>,
+    pub worker_tags: HashMap<String, usize>,
+    pub retry_storm: RetryStormConfig,
+    pub retry_classifier_timeout_ms: u64,
+    pub feature_flags: FeatureFlags,
+    pub retention: RetentionConfig,
+    pub distributed: DistributedConfig,
+    pub chaos: ChaosConfig,
 }
 
 impl Default for WorkerPoolConfig {
@@ -30,7 +48,19 @@ impl Default for WorkerPoolConfig {
             min_workers: core_config.worker_pool.min_workers,
             max_workers: core_config.worker_pool.max_workers,
             worker_timeout_ms: core_config.worker_pool.worker_timeout_ms,
+            heartbeat_timeout_ms: core_config.worker_pool.heartbeat_timeout_ms,
             queue_size: core_config.worker_pool.queue_size,
+            prefetch_contexts: core_config.worker_pool.prefetch_contexts,
+            prefetch_cache_size: core_config.worker_pool.prefetch_cache_size,
+            namespace_quotas: core_config.worker_pool.namespace_quotas,
+            lanes: core_config.worker_pool.lanes,
+            worker_tags: core_config.worker_pool.worker_tags,
+            retry_storm: core_config.execution.retry_storm,
+            retry_classifier_timeout_ms: core_config.execution.retry_classifier_timeout_ms,
+            feature_flags: core_config.feature_flags,
+            retention: core_config.retention,
+            distributed: core_config.distributed,
+            chaos: core_config.chaos,
         }
     }
 }
@@ -44,6 +74,21 @@ pub enum WorkerStatus {
     Stopped,
 }
 
+impl WorkerStatus {
+    /// Get status as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkerStatus::Idle => "idle",
+            WorkerStatus::Busy { .. } => "busy",
+            WorkerStatus::Stopping => "stopping",
+            WorkerStatus::Stopped => "stopped",
+        }
+    }
+}
+
+/// Number of recent job IDs kept in worker diagnostics
+const WORKER_RECENT_JOBS: usize = 20;
+
 /// Worker information
 #[derive(Debug, Clone)]
 pub struct Worker {
@@ -52,6 +97,10 @@ pub struct Worker {
     pub jobs_processed: u64,
     pub total_processing_time_ms: u64,
     pub last_activity: DateTime<Utc>,
+    pub error_count: u64,
+    pub recent_job_ids: VecDeque<String>,
+    pub last_panic: Option<String>,
+    pub last_panic_at: Option<DateTime<Utc>>,
 }
 
 impl Worker {
@@ -62,6 +111,23 @@ impl Worker {
             jobs_processed: 0,
             total_processing_time_ms: 0,
             last_activity: Utc::now(),
+            error_count: 0,
+            recent_job_ids: VecDeque::new(),
+            last_panic: None,
+            last_panic_at: None,
+        }
+    }
+
+    /// Create a worker that continues the counters persisted by an earlier process
+    pub fn restore(diagnostics: &WorkerDiagnostics) -> Self {
+        Self {
+            jobs_processed: diagnostics.jobs_processed,
+            total_processing_time_ms: diagnostics.total_processing_time_ms,
+            error_count: diagnostics.error_count,
+            recent_job_ids: diagnostics.recent_job_ids.iter().cloned().collect(),
+            last_panic: diagnostics.last_panic.clone(),
+            last_panic_at: diagnostics.last_panic_at,
+            ..Self::new(diagnostics.worker_id.clone())
         }
     }
 
@@ -94,6 +160,40 @@ impl Worker {
             _ => None,
         }
     }
+
+    /// Remember a processed job and whether it failed
+    pub fn record_job(&mut self, job_id: String, failed: bool) {
+        if failed {
+            self.error_count += 1;
+        }
+        self.recent_job_ids.push_back(job_id);
+        while self.recent_job_ids.len() > WORKER_RECENT_JOBS {
+            self.recent_job_ids.pop_front();
+        }
+    }
+
+    /// Remember that a job panicked on this worker
+    pub fn record_panic(&mut self, message: String) {
+        self.last_panic = Some(message);
+        self.last_panic_at = Some(Utc::now());
+    }
+
+    /// Snapshot the diagnostics of this worker
+    pub fn diagnostics(&self) -> WorkerDiagnostics {
+        WorkerDiagnostics {
+            worker_id: self.id.clone(),
+            status: self.status.as_str().to_string(),
+            current_job_id: self.get_current_job_id(),
+            jobs_processed: self.jobs_processed,
+            error_count: self.error_count,
+            total_processing_time_ms: self.total_processing_time_ms,
+            average_duration_ms: self.total_processing_time_ms.checked_div(self.jobs_processed).unwrap_or(0),
+            recent_job_ids: self.recent_job_ids.iter().cloned().collect(),
+            last_panic: self.last_panic.clone(),
+            last_panic_at: self.last_panic_at,
+            last_activity: self.last_activity,
+        }
+    }
 }
 
 /// Job execution result
@@ -118,6 +218,932 @@ pub struct DispatcherStats {
     pub active_workers: usize,
     pub idle_workers: usize,
     pub queue_depth: usize,
+    /// Jobs that started with a prefetched context
+    pub prefetch_hits: u64,
+    /// Jobs whose context had to be built when they were dequeued
+    pub prefetch_misses: u64,
+    /// Retries delayed by retry storm protection
+    pub storm_delayed_retries: u64,
+}
+
+/// Outcome of draining the dispatcher at shutdown
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct ShutdownSummary {
+    /// Jobs that were running at shutdown and finished within the timeout
+    pub drained_jobs: usize,
+    /// Jobs still running at the timeout, told to stop and left for recovery
+    pub aborted_jobs: Vec<String>,
+    /// Queued jobs that never started, persisted for the next start
+    pub persisted_jobs: usize,
+}
+
+/// A queued or running job, as shown to operators inspecting the queue
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInspection {
+    /// The job without its serialized context
+    #[serde(flatten)]
+    pub job: Job,
+    /// Worker executing the job, while it runs
+    pub worker_id: Option<String>,
+    /// When a retry waiting for its backoff may start
+    pub next_retry_at: Option<DateTime<Utc>>,
+    /// Priority of a queued job including aging
+    pub effective_priority: Option<JobPriority>,
+    /// Last heartbeat of a running job's step, if it sent any
+    pub last_heartbeat_at: Option<DateTime<Utc>>,
+}
+
+/// A job a worker is executing
+#[derive(Debug, Clone)]
+struct RunningJob {
+    job: Job,
+    worker_id: String,
+    started_at: DateTime<Utc>,
+    last_heartbeat_at: Option<DateTime<Utc>>,
+}
+
+impl RunningJob {
+    fn new(job: Job, worker_id: String) -> Self {
+        Self { job, worker_id, started_at: Utc::now(), last_heartbeat_at: None }
+    }
+    
+    /// When the job times out
+    ///
+    /// A step that never sent a heartbeat gets its timeout from the moment it
+    /// started. Once it sends heartbeats, only their age counts, so a slow step
+    /// keeps running while it reports progress and a hung one is caught after
+    /// the heartbeat timeout.
+    fn deadline(&self, timeout_ms: u64, heartbeat_timeout_ms: u64) -> (DateTime<Utc>, JobTimeout) {
+        match self.last_heartbeat_at {
+            Some(heartbeat) => (heartbeat + chrono::Duration::milliseconds(heartbeat_timeout_ms as i64), JobTimeout::MissedHeartbeat(heartbeat_timeout_ms)),
+            None => (self.started_at + chrono::Duration::milliseconds(timeout_ms as i64), JobTimeout::Elapsed(timeout_ms)),
+        }
+    }
+}
+
+/// Why a running job timed out
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum JobTimeout {
+    /// The job ran longer than its timeout without sending a heartbeat, in milliseconds
+    Elapsed(u64),
+    /// The job's step stopped sending heartbeats for longer than the heartbeat timeout, in milliseconds
+    MissedHeartbeat(u64),
+}
+
+impl JobTimeout {
+    fn message(&self, step_id: &str) -> String {
+        match self {
+            JobTimeout::Elapsed(timeout_ms) => format!("Step {} timed out after {}ms", step_id, timeout_ms),
+            JobTimeout::MissedHeartbeat(timeout_ms) => format!("Step {} sent no heartbeat for {}ms and is considered hung", step_id, timeout_ms),
+        }
+    }
+}
+
+/// Per-workflow run concurrency tracking
+///
+/// A run occupies a slot of its workflow from the moment its first job is
+/// dequeued until none of its jobs are queued or running anymore.
+///
+/// Runs of a workflow sharing a serialization key also wait their turn: only
+/// jobs of the oldest queued run of a key may start, and the key passes to
+/// the next run once the holder releases its slot.
+#[derive(Debug, Default)]
+pub struct WorkflowConcurrency {
+    limits: HashMap<String, usize>,
+    active_runs: HashMap<String, HashMap<String, usize>>,
+    /// Queued run IDs by workflow ID and serialization key, the holder of the key first
+    serialized: HashMap<(String, String), Vec<String>>,
+    /// Serialization key of each queued run
+    run_keys: HashMap<String, (String, String)>,
+    /// Runs that released their key, kept out of persisted queues read before they finished
+    released: HashMap<String, (String, String)>,
+}
+
+impl WorkflowConcurrency {
+    /// Set or clear the concurrency limit of a workflow
+    pub fn set_limit(&mut self, workflow_id: &str, limit: Option<usize>) {
+        match limit {
+            Some(limit) => { self.limits.insert(workflow_id.to_string(), limit); }
+            None => { self.limits.remove(workflow_id); }
+        }
+    }
+
+    /// Get the concurrency limit of a workflow
+    pub fn get_limit(&self, workflow_id: &str) -> Option<usize> {
+        self.limits.get(workflow_id).copied()
+    }
+
+    /// Set the queue of a serialization key, oldest run first
+    pub fn set_serialized_runs(&mut self, workflow_id: &str, key: &str, run_ids: Vec<String>) {
+        let key = (workflow_id.to_string(), key.to_string());
+        self.released.retain(|run_id, released_key| *released_key != key || run_ids.contains(run_id));
+        let run_ids: Vec<String> = run_ids.into_iter()
+            .filter(|run_id| !self.released.contains_key(run_id))
+            .collect();
+        for run_id in &run_ids {
+            self.run_keys.insert(run_id.clone(), key.clone());
+        }
+        self.serialized.insert(key, run_ids);
+    }
+
+    /// Pass the serialization key held or awaited by a run on to the next run
+    fn release_serialized_run(&mut self, run_id: &str) {
+        let Some(key) = self.run_keys.remove(run_id) else {
+            return;
+        };
+        if let Some(runs) = self.serialized.get_mut(&key) {
+            runs.retain(|queued| queued != run_id);
+            if runs.is_empty() {
+                self.serialized.remove(&key);
+            }
+        }
+        self.released.insert(run_id.to_string(), key);
+    }
+
+    /// Check whether a job may start without exceeding its workflow's limit or jumping its key's queue
+    pub fn can_start(&self, job: &Job) -> bool {
+        if let Some(key) = self.run_keys.get(&job.run_id) {
+            if self.serialized.get(key).and_then(|runs| runs.first()) != Some(&job.run_id) {
+                return false;
+            }
+        }
+        let Some(limit) = self.limits.get(&job.workflow_id) else {
+            return true;
+        };
+        match self.active_runs.get(&job.workflow_id) {
+            Some(runs) => runs.contains_key(&job.run_id) || runs.len() < *limit,
+            None => true,
+        }
+    }
+
+    /// Record that a job of a run has started
+    pub fn job_started(&mut self, job: &Job) {
+        *self.active_runs
+            .entry(job.workflow_id.clone())
+            .or_default()
+            .entry(job.run_id.clone())
+            .or_insert(0) += 1;
+    }
+
+    /// Record that a job of a run has finished, releasing the run's slot when it has no queued jobs left
+    pub fn job_finished(&mut self, workflow_id: &str, run_id: &str, run_has_queued_jobs: bool) {
+        if let Some(runs) = self.active_runs.get_mut(workflow_id) {
+            if let Some(running) = runs.get_mut(run_id) {
+                *running = running.saturating_sub(1);
+                if *running == 0 && !run_has_queued_jobs {
+                    runs.remove(run_id);
+                }
+            }
+            if runs.is_empty() {
+                self.active_runs.remove(workflow_id);
+            }
+        }
+        if !run_has_queued_jobs && !self.active_runs(workflow_id).contains(run_id) {
+            self.release_serialized_run(run_id);
+        }
+    }
+
+    /// Release a run's slot and serialization key if none of its jobs are running
+    pub fn release_idle_run(&mut self, workflow_id: &str, run_id: &str) {
+        if let Some(runs) = self.active_runs.get_mut(workflow_id) {
+            if runs.get(run_id) == Some(&0) {
+                runs.remove(run_id);
+            }
+            if runs.is_empty() {
+                self.active_runs.remove(workflow_id);
+            }
+        }
+        if !self.active_runs(workflow_id).contains(run_id) {
+            self.release_serialized_run(run_id);
+        }
+    }
+
+    /// Get the number of runs of a workflow currently holding a slot
+    pub fn active_run_count(&self, workflow_id: &str) -> usize {
+        self.active_runs.get(workflow_id).map(|runs| runs.len()).unwrap_or(0)
+    }
+
+    /// Get the IDs of runs of a workflow currently holding a slot
+    pub fn active_runs(&self, workflow_id: &str) -> HashSet<String> {
+        self.active_runs
+            .get(workflow_id)
+            .map(|runs| runs.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Per-namespace worker quota tracking
+///
+/// Jobs inherit the namespace of their workflow. A namespace with a quota may
+/// only occupy its share of the shared worker pool and queue, so a burst from
+/// one tenant cannot starve the others. Namespaces without a quota are unlimited.
+#[derive(Debug, Default)]
+pub struct NamespaceQuotas {
+    quotas: HashMap<String, NamespaceQuota>,
+    workflow_namespaces: HashMap<String, String>,
+    running: HashMap<String, usize>,
+    /// Job ID to the namespace it was counted against when it started
+    running_jobs: HashMap<String, String>,
+    /// Start times within the last second, for rate limiting
+    recent_starts: HashMap<String, VecDeque<Instant>>,
+}
+
+impl NamespaceQuotas {
+    /// Create quota tracking from configured quotas
+    pub fn new(quotas: HashMap<String, NamespaceQuota>) -> Self {
+        Self {
+            quotas,
+            ..Default::default()
+        }
+    }
+
+    /// Set or clear the quota of a namespace
+    pub fn set_quota(&mut self, namespace: &str, quota: Option<NamespaceQuota>) {
+        match quota {
+            Some(quota) => { self.quotas.insert(namespace.to_string(), quota); }
+            None => { self.quotas.remove(namespace); }
+        }
+    }
+
+    /// Get the quota of a namespace
+    pub fn get_quota(&self, namespace: &str) -> Option<&NamespaceQuota> {
+        self.quotas.get(namespace)
+    }
+
+    /// Set or clear the namespace of a workflow
+    pub fn set_workflow_namespace(&mut self, workflow_id: &str, namespace: Option<&str>) {
+        match namespace {
+            Some(namespace) => { self.workflow_namespaces.insert(workflow_id.to_string(), namespace.to_string()); }
+            None => { self.workflow_namespaces.remove(workflow_id); }
+        }
+    }
+
+    /// Get the namespace and quota governing a workflow's jobs
+    fn quota_for(&self, workflow_id: &str) -> Option<(&String, &NamespaceQuota)> {
+        let namespace = self.workflow_namespaces.get(workflow_id)?;
+        self.quotas.get(namespace).map(|quota| (namespace, quota))
+    }
+
+    /// Check whether another job of a workflow fits in its namespace's queue capacity
+    pub fn can_enqueue(&self, workflow_id: &str, queue: &JobQueue) -> Result<(), CoreError> {
+        let Some((namespace, quota)) = self.quota_for(workflow_id) else {
+            return Ok(());
+        };
+        let queued = queue.get_jobs()
+            .iter()
+            .filter(|job| self.workflow_namespaces.get(&job.workflow_id) == Some(namespace))
+            .count();
+        if queued >= quota.queue_capacity {
+            return Err(CoreError::QuotaExceeded(format!(
+                "Queue capacity of namespace {} is full ({} jobs)", namespace, quota.queue_capacity
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check whether a job may start without exceeding its namespace's workers or rate
+    pub fn can_start(&self, job: &Job, now: Instant) -> bool {
+        let Some((namespace, quota)) = self.quota_for(&job.workflow_id) else {
+            return true;
+        };
+        if self.running.get(namespace).copied().unwrap_or(0) >= quota.max_workers {
+            return false;
+        }
+        match (quota.rate_limit_per_sec, self.recent_starts.get(namespace)) {
+            (Some(rate), Some(starts)) => {
+                let window_start = now.checked_sub(Duration::from_secs(1));
+                let started = starts.iter().filter(|at| window_start.is_none_or(|start| **at > start)).count();
+                started < rate as usize
+            }
+            _ => true,
+        }
+    }
+
+    /// Record that a job has started
+    pub fn job_started(&mut self, job: &Job, now: Instant) {
+        let Some((namespace, quota)) = self.quota_for(&job.workflow_id) else {
+            return;
+        };
+        let namespace = namespace.clone();
+        let rate_limited = quota.rate_limit_per_sec.is_some();
+        *self.running.entry(namespace.clone()).or_insert(0) += 1;
+        self.running_jobs.insert(job.id.clone(), namespace.clone());
+        if rate_limited {
+            let starts = self.recent_starts.entry(namespace).or_default();
+            while starts.front().is_some_and(|at| now.duration_since(*at) >= Duration::from_secs(1)) {
+                starts.pop_front();
+            }
+            starts.push_back(now);
+        }
+    }
+
+    /// Record that a job has finished, releasing the namespace it started in
+    ///
+    /// The namespace recorded at start is released even if the workflow has
+    /// since moved to another namespace or its quota was removed.
+    pub fn job_finished(&mut self, job_id: &str) {
+        if let Some(namespace) = self.running_jobs.remove(job_id) {
+            if let Some(running) = self.running.get_mut(&namespace) {
+                *running = running.saturating_sub(1);
+                if *running == 0 {
+                    self.running.remove(&namespace);
+                }
+            }
+        }
+    }
+
+    /// Get the number of a namespace's jobs currently running
+    pub fn running_count(&self, namespace: &str) -> usize {
+        self.running.get(namespace).copied().unwrap_or(0)
+    }
+}
+
+/// Tag holding the dispatcher lane of a job
+pub const LANE_TAG: &str = "lane";
+
+/// Worker allocation of dispatcher lanes
+///
+/// Jobs run in the lane tagged on them when they were submitted, the default
+/// lane otherwise. A lane may not run more jobs than its allocation allows,
+/// and no lane may take a worker that another lane reserved and is not using.
+/// Lanes without an allocation share the workers nobody reserved.
+#[derive(Debug, Default)]
+pub struct RunLanes {
+    allocations: HashMap<String, LaneAllocation>,
+    running: HashMap<String, usize>,
+    /// Workers in the pool
+    pool_size: usize,
+}
+
+/// Allocation and load of a dispatcher lane
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct LaneStatus {
+    pub lane: String,
+    pub max_workers: Option<usize>,
+    pub reserved_workers: usize,
+    pub running: usize,
+    pub queued: usize,
+}
+
+impl RunLanes {
+    /// Create lane tracking from configured allocations
+    pub fn new(allocations: HashMap<String, LaneAllocation>) -> Self {
+        Self {
+            allocations,
+            ..Default::default()
+        }
+    }
+
+    /// Set the number of workers in the pool
+    pub fn set_pool_size(&mut self, pool_size: usize) {
+        self.pool_size = pool_size;
+    }
+
+    /// Get the lane a job runs in
+    pub fn lane_of(job: &Job) -> &str {
+        job.metadata.tags.get(LANE_TAG).map(String::as_str).unwrap_or(LaneAllocation::DEFAULT_LANE)
+    }
+
+    /// Check whether a job may start within its lane's allocation and the other lanes' reservations
+    pub fn can_start(&self, job: &Job) -> bool {
+        if self.allocations.is_empty() {
+            return true;
+        }
+        let lane = Self::lane_of(job);
+        let running = self.running_count(lane);
+        if let Some(allocation) = self.allocations.get(lane) {
+            if running >= allocation.max_workers {
+                return false;
+            }
+            if running < allocation.reserved_workers {
+                return true;
+            }
+        }
+        let busy: usize = self.running.values().sum();
+        let held: usize = self.allocations.iter()
+            .filter(|(other, _)| other.as_str() != lane)
+            .map(|(other, allocation)| allocation.reserved_workers.saturating_sub(self.running_count(other)))
+            .sum();
+        busy + held < self.pool_size
+    }
+
+    /// Record that a job has started
+    pub fn job_started(&mut self, job: &Job) {
+        *self.running.entry(Self::lane_of(job).to_string()).or_insert(0) += 1;
+    }
+
+    /// Record that a job of a lane has finished
+    pub fn job_finished(&mut self, lane: &str) {
+        if let Some(running) = self.running.get_mut(lane) {
+            *running = running.saturating_sub(1);
+            if *running == 0 {
+                self.running.remove(lane);
+            }
+        }
+    }
+
+    /// Get the number of a lane's jobs currently running
+    pub fn running_count(&self, lane: &str) -> usize {
+        self.running.get(lane).copied().unwrap_or(0)
+    }
+
+    /// Get the allocation and load of the configured lanes and those with jobs
+    pub fn status(&self, queue: &JobQueue) -> Vec<LaneStatus> {
+        let mut queued: HashMap<&str, usize> = HashMap::new();
+        for job in queue.get_jobs() {
+            *queued.entry(Self::lane_of(job)).or_insert(0) += 1;
+        }
+        let mut lanes: Vec<&str> = self.allocations.keys()
+            .chain(self.running.keys())
+            .map(String::as_str)
+            .chain(queued.keys().copied())
+            .collect();
+        lanes.sort_unstable();
+        lanes.dedup();
+        lanes.into_iter()
+            .map(|lane| {
+                let allocation = self.allocations.get(lane);
+                LaneStatus {
+                    lane: lane.to_string(),
+                    max_workers: allocation.map(|allocation| allocation.max_workers),
+                    reserved_workers: allocation.map(|allocation| allocation.reserved_workers).unwrap_or(0),
+                    running: self.running_count(lane),
+                    queued: queued.get(lane).copied().unwrap_or(0),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Tag holding the comma-separated worker capability tags a job requires
+pub const WORKER_TAGS_TAG: &str = "worker_tags";
+
+/// Routing of jobs to the workers carrying the capability tags they require
+///
+/// Workers get their tags from the pool configuration when they start, or
+/// register them later. A job whose step declares worker tags is only
+/// dequeued by a worker carrying all of them, and the time it waited in the
+/// queue is recorded under each of its tags.
+#[derive(Debug, Default)]
+pub struct WorkerTagRouting {
+    workers: HashMap<String, HashSet<String>>,
+    waits: HashMap<String, TagQueueTime>,
+}
+
+/// Queue time of the jobs requiring a tag
+#[derive(Debug, Clone, Default)]
+struct TagQueueTime {
+    dispatched: u64,
+    total_wait_ms: u64,
+    max_wait_ms: u64,
+}
+
+/// Workers and queue time of a worker capability tag
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct WorkerTagStatus {
+    pub tag: String,
+    /// Workers carrying the tag
+    pub workers: Vec<String>,
+    /// Queued jobs requiring the tag
+    pub queued: usize,
+    /// Jobs requiring the tag dispatched so far
+    pub dispatched: u64,
+    pub avg_queue_time_ms: Option<u64>,
+    pub max_queue_time_ms: u64,
+}
+
+impl WorkerTagRouting {
+    /// Get the capability tags a job requires
+    pub fn required_tags(job: &Job) -> impl Iterator<Item = &str> {
+        job.get_tag(WORKER_TAGS_TAG)
+            .into_iter()
+            .flat_map(|tags| tags.split(','))
+            .filter(|tag| !tag.is_empty())
+    }
+
+    /// Set the capability tags of a worker, replacing those it had
+    pub fn set_worker_tags(&mut self, worker_id: &str, tags: impl IntoIterator<Item = String>) {
+        let tags: HashSet<String> = tags.into_iter().collect();
+        if tags.is_empty() {
+            self.workers.remove(worker_id);
+        } else {
+            self.workers.insert(worker_id.to_string(), tags);
+        }
+    }
+
+    /// Get the capability tags of a worker, sorted
+    pub fn worker_tags(&self, worker_id: &str) -> Vec<String> {
+        let mut tags: Vec<String> = self.workers.get(worker_id).into_iter().flatten().cloned().collect();
+        tags.sort_unstable();
+        tags
+    }
+
+    /// Check whether a worker carries every tag a job requires
+    pub fn can_run(&self, worker_id: &str, job: &Job) -> bool {
+        let carried = self.workers.get(worker_id);
+        Self::required_tags(job).all(|tag| carried.is_some_and(|carried| carried.contains(tag)))
+    }
+
+    /// Check whether any worker carries every tag a job requires
+    pub fn has_worker_for(&self, job: &Job) -> bool {
+        Self::required_tags(job).next().is_none()
+            || self.workers.keys().any(|worker_id| self.can_run(worker_id, job))
+    }
+
+    /// Record the time a dispatched job waited in the queue under each tag it requires
+    pub fn job_dispatched(&mut self, job: &Job, now: DateTime<Utc>) {
+        let wait_ms = (now - job.metadata.created_at).num_milliseconds().max(0) as u64;
+        for tag in Self::required_tags(job) {
+            let waits = self.waits.entry(tag.to_string()).or_default();
+            waits.dispatched += 1;
+            waits.total_wait_ms += wait_ms;
+            waits.max_wait_ms = waits.max_wait_ms.max(wait_ms);
+        }
+    }
+
+    /// Get the workers and queue time of the tags workers carry and jobs require
+    pub fn status(&self, queue: &JobQueue) -> Vec<WorkerTagStatus> {
+        let mut queued: HashMap<&str, usize> = HashMap::new();
+        for job in queue.get_jobs() {
+            for tag in Self::required_tags(job) {
+                *queued.entry(tag).or_insert(0) += 1;
+            }
+        }
+        let mut tags: Vec<&str> = self.workers.values()
+            .flatten()
+            .chain(self.waits.keys())
+            .map(String::as_str)
+            .chain(queued.keys().copied())
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags.into_iter()
+            .map(|tag| {
+                let mut workers: Vec<String> = self.workers.iter()
+                    .filter(|(_, carried)| carried.contains(tag))
+                    .map(|(worker_id, _)| worker_id.clone())
+                    .collect();
+                workers.sort_unstable();
+                let waits = self.waits.get(tag).cloned().unwrap_or_default();
+                WorkerTagStatus {
+                    tag: tag.to_string(),
+                    workers,
+                    queued: queued.get(tag).copied().unwrap_or(0),
+                    dispatched: waits.dispatched,
+                    avg_queue_time_ms: (waits.dispatched > 0).then(|| waits.total_wait_ms / waits.dispatched),
+                    max_queue_time_ms: waits.max_wait_ms,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Per-workflow resource quota tracking
+///
+/// The usage of a workflow's finished attempts is kept for the length of its
+/// quota window. While the usage within the window is over the quota, the
+/// workflow's jobs stay queued, until enough of it has aged out of the window.
+/// Running jobs are not interrupted, and attempts without measured usage do
+/// not count.
+#[derive(Debug, Default)]
+pub struct ResourceQuotas {
+    quotas: HashMap<String, ResourceQuota>,
+    /// Usage of finished attempts within the window, oldest first
+    usage: HashMap<String, VecDeque<(Instant, ResourceUsage)>>,
+}
+
+/// Resource usage of a workflow within its quota window
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WorkflowResourceUsage {
+    pub workflow_id: String,
+    pub quota: Option<ResourceQuota>,
+    /// Attempts finished within the window
+    pub attempts: usize,
+    pub cpu_time_ms: u64,
+    /// Highest peak memory of an attempt within the window
+    pub peak_memory_bytes: u64,
+    /// Whether dispatching of the workflow's jobs is paused
+    pub exceeded: bool,
+}
+
+impl ResourceQuotas {
+    /// Set or clear the resource quota of a workflow
+    pub fn set_quota(&mut self, workflow_id: &str, quota: Option<ResourceQuota>) {
+        match quota {
+            Some(quota) => { self.quotas.insert(workflow_id.to_string(), quota); }
+            None => {
+                self.quotas.remove(workflow_id);
+                self.usage.remove(workflow_id);
+            }
+        }
+    }
+
+    /// Record the usage of a finished attempt, returning whether it put the workflow over its quota
+    pub fn record(&mut self, workflow_id: &str, usage: ResourceUsage, now: Instant) -> bool {
+        if !self.quotas.contains_key(workflow_id) {
+            return false;
+        }
+        let exceeded = self.usage(workflow_id, now).exceeded;
+        self.usage.entry(workflow_id.to_string()).or_default().push_back((now, usage));
+        self.prune(workflow_id, now);
+        !exceeded && self.usage(workflow_id, now).exceeded
+    }
+
+    /// Check whether a job may start without its workflow being over its quota
+    pub fn can_start(&self, job: &Job, now: Instant) -> bool {
+        !self.usage(&job.workflow_id, now).exceeded
+    }
+
+    /// Get a workflow's usage within its quota window
+    pub fn usage(&self, workflow_id: &str, now: Instant) -> WorkflowResourceUsage {
+        let mut usage = WorkflowResourceUsage {
+            workflow_id: workflow_id.to_string(),
+            quota: self.quotas.get(workflow_id).cloned(),
+            ..Default::default()
+        };
+        let Some(quota) = &usage.quota else {
+            return usage;
+        };
+        let window = Duration::from_secs(quota.window_secs);
+        let attempts = self.usage.get(workflow_id).into_iter().flatten()
+            .filter(|(at, _)| now.saturating_duration_since(*at) < window);
+        for (_, attempt) in attempts {
+            usage.attempts += 1;
+            usage.cpu_time_ms += attempt.cpu_time_ms;
+            usage.peak_memory_bytes = usage.peak_memory_bytes.max(attempt.peak_memory_bytes);
+        }
+        usage.exceeded = quota.cpu_time_ms.is_some_and(|budget| usage.cpu_time_ms > budget)
+            || quota.peak_memory_bytes.is_some_and(|limit| usage.peak_memory_bytes > limit);
+        usage
+    }
+
+    /// Drop the usage that has aged out of a workflow's window
+    fn prune(&mut self, workflow_id: &str, now: Instant) {
+        let Some(window) = self.quotas.get(workflow_id).map(|quota| Duration::from_secs(quota.window_secs)) else {
+            return;
+        };
+        if let Some(attempts) = self.usage.get_mut(workflow_id) {
+            while attempts.front().is_some_and(|(at, _)| now.saturating_duration_since(*at) >= window) {
+                attempts.pop_front();
+            }
+        }
+    }
+}
+
+/// Engine-wide retry storm detection
+///
+/// Retries are keyed by workflow and step, the unit that calls one downstream
+/// action. When retries of a key spike past the configured threshold, further
+/// retries of that key are spread out with extra random delay and only a few
+/// of them may run at once, so a recovering service is not hammered.
+#[derive(Debug)]
+pub struct RetryStormGuard {
+    config: RetryStormConfig,
+    /// Retry times within the detection window, per key
+    recent_retries: HashMap<String, VecDeque<Instant>>,
+    /// Keys of the retries currently running, per job
+    running_retries: HashMap<String, String>,
+    /// Earliest start of delayed retries, per job
+    not_before: HashMap<String, Instant>,
+}
+
+impl RetryStormGuard {
+    /// Create storm detection from its configuration
+    pub fn new(config: RetryStormConfig) -> Self {
+        Self {
+            config,
+            recent_retries: HashMap::new(),
+            running_retries: HashMap::new(),
+            not_before: HashMap::new(),
+        }
+    }
+
+    /// Key grouping the retries of a job
+    pub fn retry_key(job: &Job) -> String {
+        format!("{}:{}", job.workflow_id, job.step_name)
+    }
+
+    /// Check whether a job is a retry of an earlier attempt
+    fn is_retry(job: &Job) -> bool {
+        job.metadata.attempt_count > 0
+    }
+
+    /// Number of retries of a key within the detection window
+    fn retries_in_window(&self, key: &str, now: Instant) -> usize {
+        let window = Duration::from_millis(self.config.window_ms);
+        self.recent_retries.get(key)
+            .map(|retries| retries.iter().filter(|at| now.duration_since(**at) < window).count())
+            .unwrap_or(0)
+    }
+
+    /// Check whether retries of a key are currently storming
+    pub fn is_storming(&self, key: &str, now: Instant) -> bool {
+        self.retries_in_window(key, now) >= self.config.threshold
+    }
+
+    /// Record a retry of a job, returning the extra delay applied if its key is storming
+    pub fn record_retry(&mut self, job: &Job, now: Instant) -> Option<Duration> {
+        let key = Self::retry_key(job);
+        let window = Duration::from_millis(self.config.window_ms);
+        let retries = self.recent_retries.entry(key.clone()).or_default();
+        while retries.front().is_some_and(|at| now.duration_since(*at) >= window) {
+            retries.pop_front();
+        }
+        retries.push_back(now);
+        
+        if !self.is_storming(&key, now) || self.config.jitter_ms == 0 {
+            return None;
+        }
+        // Spread delays further the more the storm exceeds the threshold
+        let intensity = (self.retries_in_window(&key, now) / self.config.threshold) as u64;
+        let spread_ms = self.config.jitter_ms.saturating_mul(intensity.max(1));
+        let delay = Duration::from_millis((Uuid::new_v4().as_u128() % (spread_ms as u128 + 1)) as u64);
+        self.not_before.insert(job.id.clone(), now + delay);
+        Some(delay)
+    }
+
+    /// Check whether a job may start given its retry delay and the storm's concurrency cap
+    pub fn can_start(&self, job: &Job, now: Instant) -> bool {
+        if !Self::is_retry(job) {
+            return true;
+        }
+        if self.not_before.get(&job.id).is_some_and(|at| now < *at) {
+            return false;
+        }
+        let key = Self::retry_key(job);
+        !self.is_storming(&key, now) || self.running_count(&key) < self.config.max_concurrent_retries
+    }
+
+    /// Record that a job has started
+    pub fn job_started(&mut self, job: &Job) {
+        self.not_before.remove(&job.id);
+        if Self::is_retry(job) {
+            self.running_retries.insert(job.id.clone(), Self::retry_key(job));
+        }
+    }
+
+    /// Record that a job has finished
+    pub fn job_finished(&mut self, job_id: &str) {
+        self.running_retries.remove(job_id);
+    }
+
+    /// Get the number of retries of a key currently running
+    pub fn running_count(&self, key: &str) -> usize {
+        self.running_retries.values().filter(|running| *running == key).count()
+    }
+}
+
+/// Serialized step contexts built ahead of time for jobs about to run
+///
+/// A job's context is rebuilt whenever one of its dependencies completes, so
+/// the entry built after the last dependency includes all of their results.
+#[derive(Debug, Default)]
+pub struct ContextPrefetchCache {
+    capacity: usize,
+    /// Job ID to (number of completed steps the context was built from, context JSON)
+    contexts: HashMap<String, (usize, String)>,
+    order: VecDeque<String>,
+    hits: u64,
+    misses: u64,
+}
+
+impl ContextPrefetchCache {
+    /// Create a cache holding at most `capacity` contexts
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            ..Default::default()
+        }
+    }
+
+    /// Store a prefetched context unless a newer one is already cached
+    pub fn insert(&mut self, job_id: String, completed_steps: usize, context_json: String) {
+        match self.contexts.get_mut(&job_id) {
+            Some(existing) => {
+                if completed_steps >= existing.0 {
+                    *existing = (completed_steps, context_json);
+                }
+            }
+            None => {
+                self.contexts.insert(job_id.clone(), (completed_steps, context_json));
+                self.order.push_back(job_id);
+            }
+        }
+        
+        while self.contexts.len() > self.capacity {
+            match self.order.pop_front() {
+                Some(oldest) => {
+                    self.contexts.remove(&oldest);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Take the prefetched context of a job, recording a hit or miss
+    ///
+    /// A context built from a different number of completed steps than the
+    /// run has now is stale; it is dropped and counts as a miss.
+    pub fn take(&mut self, job_id: &str, completed_steps: usize) -> Option<String> {
+        let cached = self.contexts.remove(job_id);
+        if cached.is_some() {
+            self.order.retain(|id| id != job_id);
+        }
+        
+        match cached {
+            Some((built_from, context_json)) if built_from == completed_steps => {
+                self.hits += 1;
+                Some(context_json)
+            }
+            _ => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Get the number of cached contexts
+    pub fn len(&self) -> usize {
+        self.contexts.len()
+    }
+
+    /// Check if the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.contexts.is_empty()
+    }
+}
+
+/// Cooperative cancellation signal shared with the jobs of a run
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Signal cancellation
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether cancellation was signalled
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// Cancellation tokens of the runs with jobs in the dispatcher, and of the jobs being executed
+///
+/// Tokens of cancelled runs are kept so jobs submitted for them later are
+/// dropped instead of executed.
+#[derive(Debug, Default)]
+pub struct RunCancellations {
+    tokens: HashMap<String, CancellationToken>,
+    running_jobs: HashMap<String, CancellationToken>,
+}
+
+impl RunCancellations {
+    /// Get the token of a run, creating it if needed
+    pub fn token(&mut self, run_id: &str) -> CancellationToken {
+        self.tokens.entry(run_id.to_string()).or_default().clone()
+    }
+
+    /// Signal cancellation to every job of a run
+    pub fn cancel(&mut self, run_id: &str) {
+        self.token(run_id).cancel();
+    }
+
+    /// Check whether a run was cancelled
+    pub fn is_cancelled(&self, run_id: &str) -> bool {
+        self.tokens.get(run_id).map(|token| token.is_cancelled()).unwrap_or(false)
+    }
+
+    /// Drop the token of a run that has no jobs left, unless it was cancelled
+    pub fn release(&mut self, run_id: &str) {
+        if !self.is_cancelled(run_id) {
+            self.tokens.remove(run_id);
+        }
+    }
+
+    /// Register a job a worker starts executing, returning the token that stops it
+    pub fn start_job(&mut self, job_id: &str) -> CancellationToken {
+        let token = CancellationToken::default();
+        self.running_jobs.insert(job_id.to_string(), token.clone());
+        token
+    }
+
+    /// Signal a running job to stop, returning whether it is running
+    pub fn cancel_job(&mut self, job_id: &str) -> bool {
+        match self.running_jobs.get(job_id) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget a job once its attempt finished
+    pub fn finish_job(&mut self, job_id: &str) {
+        self.running_jobs.remove(job_id);
+    }
 }
 
 /// Job dispatcher for managing workflow job execution
@@ -127,28 +1153,72 @@ pub struct Dispatcher {
     config: WorkerPoolConfig,
     stats: Arc<Mutex<DispatcherStats>>,
     completed_jobs: Arc<Mutex<Vec<String>>>,
-    running_jobs: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+    running_jobs: Arc<Mutex<HashMap<String, RunningJob>>>,
     shutdown_flag: Arc<Mutex<bool>>,
     state_manager: Arc<Mutex<StateManager>>, // Added for workflow state updates
     worker_handles: Arc<Mutex<Vec<JoinHandle<()>>>>, // Track tokio task handles
+    concurrency: Arc<Mutex<WorkflowConcurrency>>,
+    namespaces: Arc<Mutex<NamespaceQuotas>>,
+    lanes: Arc<Mutex<RunLanes>>,
+    worker_tags: Arc<Mutex<WorkerTagRouting>>,
+    resource_quotas: Arc<Mutex<ResourceQuotas>>,
+    retry_storms: Arc<Mutex<RetryStormGuard>>,
+    retry_classifier: Arc<Mutex<RetryClassifier>>,
+    result_waiters: Arc<Mutex<HashMap<String, oneshot::Sender<JobExecutionResult>>>>,
+    context_cache: Arc<Mutex<ContextPrefetchCache>>,
+    paused: Arc<Mutex<bool>>,
+    cancellations: Arc<Mutex<RunCancellations>>,
+    chaos: Arc<Mutex<FaultInjector>>,
 }
 
 impl Dispatcher {
+    /// Jobs a queue listing returns when no limit is given
+    pub const DEFAULT_JOB_LIST_LIMIT: usize = 100;
+
     /// Create a new job dispatcher
     pub fn new(config: WorkerPoolConfig, state_manager: Arc<Mutex<StateManager>>) -> Self {
         Self {
             job_queue: Arc::new(Mutex::new(JobQueue::new())),
             workers: Arc::new(Mutex::new(HashMap::new())),
-            config,
             stats: Arc::new(Mutex::new(DispatcherStats::default())),
             completed_jobs: Arc::new(Mutex::new(Vec::new())),
             running_jobs: Arc::new(Mutex::new(HashMap::new())),
             shutdown_flag: Arc::new(Mutex::new(false)),
             state_manager,
             worker_handles: Arc::new(Mutex::new(Vec::new())),
+            concurrency: Arc::new(Mutex::new(WorkflowConcurrency::default())),
+            namespaces: Arc::new(Mutex::new(NamespaceQuotas::new(config.namespace_quotas.clone()))),
+            lanes: Arc::new(Mutex::new(RunLanes::new(config.lanes.clone()))),
+            worker_tags: Arc::new(Mutex::new(WorkerTagRouting::default())),
+            resource_quotas: Arc::new(Mutex::new(ResourceQuotas::default())),
+            retry_storms: Arc::new(Mutex::new(RetryStormGuard::new(config.retry_storm.clone()))),
+            retry_classifier: Arc::new(Mutex::new(RetryClassifier::new(config.retry_classifier_timeout_ms))),
+            result_waiters: Arc::new(Mutex::new(HashMap::new())),
+            context_cache: Arc::new(Mutex::new(ContextPrefetchCache::new(config.prefetch_cache_size))),
+            paused: Arc::new(Mutex::new(false)),
+            cancellations: Arc::new(Mutex::new(RunCancellations::default())),
+            chaos: Arc::new(Mutex::new(FaultInjector::new(&config.chaos))),
+            config,
         }
     }
 
+    /// Start with dequeuing paused, e.g. when the engine was paused before a restart
+    pub fn with_paused(mut self, paused: bool) -> Self {
+        self.paused = Arc::new(Mutex::new(paused));
+        self
+    }
+
+    /// Stop or restart dequeuing jobs; running jobs are not interrupted
+    pub async fn set_paused(&self, paused: bool) {
+        *self.paused.lock().await = paused;
+        log::info!("Dispatcher dequeuing {}", if paused { "paused" } else { "resumed" });
+    }
+
+    /// Check if dequeuing is paused
+    pub async fn is_paused(&self) -> bool {
+        *self.paused.lock().await
+    }
+
     /// Start the dispatcher with worker pool
     pub async fn start(&mut self) -> Result<(), CoreError> {
         log::info!("Starting job dispatcher with {} workers", self.config.min_workers);
@@ -156,6 +1226,11 @@ impl Dispatcher {
         // Start worker pool
         for i in 0..self.config.min_workers {
             let worker_id = format!("worker-{}", i);
+            // Configured tags go to the first workers of the pool
+            let tags = self.config.worker_tags.iter()
+                .filter(|(_, workers)| i < **workers)
+                .map(|(tag, _)| tag.clone());
+            self.worker_tags.lock().await.set_worker_tags(&worker_id, tags);
             let shutdown_flag = Arc::clone(&self.shutdown_flag);
             self.start_worker(worker_id, shutdown_flag).await?;
         }
@@ -164,6 +1239,22 @@ impl Dispatcher {
         let shutdown_flag = Arc::clone(&self.shutdown_flag);
         self.start_timeout_monitor(shutdown_flag).await?;
         
+        // Start run timeout monitor
+        let shutdown_flag = Arc::clone(&self.shutdown_flag);
+        self.start_run_timeout_monitor(shutdown_flag).await;
+        
+        // Pull jobs from the shared store when several processes share it
+        if self.config.distributed.enabled {
+            let shutdown_flag = Arc::clone(&self.shutdown_flag);
+            self.start_job_puller(shutdown_flag).await;
+        }
+        
+        // Start retention cleanup, if the policy can delete anything
+        if self.config.retention.prunes_runs() {
+            let shutdown_flag = Arc::clone(&self.shutdown_flag);
+            self.start_retention_cleanup(shutdown_flag).await;
+        }
+        
         log::info!("Job dispatcher started successfully");
         Ok(())
     }
@@ -198,20 +1289,122 @@ impl Dispatcher {
             }
         }
         
-        log::info!("Job dispatcher stopped");
-        Ok(())
+        log::info!("Job dispatcher stopped");
+        Ok(())
+    }
+
+    /// Shut down gracefully: stop dequeuing, let running jobs finish and stop the workers
+    ///
+    /// Jobs still running after `timeout` are told to stop as if their run was
+    /// cancelled, and a step interrupted event is recorded on their run so it can
+    /// be recovered. Queued jobs are persisted regardless of job recording.
+    pub async fn shutdown(&mut self, timeout: Duration) -> Result<ShutdownSummary, CoreError> {
+        log::info!("Shutting down job dispatcher, draining for up to {}ms", timeout.as_millis());
+        self.set_paused(true).await;
+        
+        let in_flight = self.running_jobs.lock().await.len();
+        let deadline = Instant::now() + timeout;
+        while !self.running_jobs.lock().await.is_empty() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        
+        let mut aborted_jobs: Vec<String> = self.running_jobs.lock().await.keys().cloned().collect();
+        aborted_jobs.sort();
+        for job_id in &aborted_jobs {
+            let Ok((_, run_id, step_id)) = Job::parse_job_id(job_id) else {
+                continue;
+            };
+            log::warn!("Job {} did not finish before shutdown, aborting it", job_id);
+            self.cancellations.lock().await.cancel(&run_id);
+            if let Ok(run_uuid) = Uuid::parse_str(&run_id) {
+                let detail = serde_json::json!({"job_id": job_id, "reason": "engine shutdown"});
+                if let Err(e) = self.state_manager.lock().await.record_run_event(&run_uuid, RunEvent::STEP_INTERRUPTED, Some(&step_id), RunEvent::ACTOR_ENGINE, detail) {
+                    log::warn!("Failed to record interruption of job {}: {}", job_id, e);
+                }
+            }
+        }
+        
+        let queued: Vec<Job> = self.job_queue.lock().await.get_jobs().to_vec();
+        {
+            let state_manager = self.state_manager.lock().await;
+            for job in &queued {
+                if let Err(e) = state_manager.save_job(job) {
+                    log::warn!("Failed to persist queued job {}: {}", job.id, e);
+                }
+            }
+        }
+        
+        self.stop().await?;
+        
+        let summary = ShutdownSummary {
+            drained_jobs: in_flight.saturating_sub(aborted_jobs.len()),
+            aborted_jobs,
+            persisted_jobs: queued.len(),
+        };
+        log::info!("Job dispatcher shut down: {} drained, {} aborted, {} queued jobs persisted",
+            summary.drained_jobs, summary.aborted_jobs.len(), summary.persisted_jobs);
+        Ok(summary)
     }
 
     /// Submit a job for execution
-    pub async fn submit_job(&self, job: Job) -> Result<(), CoreError> {
+    pub async fn submit_job(&self, mut job: Job) -> Result<(), CoreError> {
         let job_id = job.id.clone();
         log::info!("Submitting job {} for execution", job_id);
         
-        let queue_depth = {
+        // Steps with an adaptive timeout run with the one learned from their recent durations
+        match self.state_manager.lock().await.step_timeout_ms(&job.workflow_id, &job.step_name) {
+            Ok(Some(timeout_ms)) => job.timeout_ms = Some(timeout_ms),
+            Ok(None) => {}
+            Err(e) => log::warn!("Failed to resolve the timeout of job {}: {}", job_id, e),
+        }
+        
+        // Jobs run in the lane their run's trigger or workflow picked
+        if job.get_tag(LANE_TAG).is_none() {
+            match self.state_manager.lock().await.run_lane(&job.workflow_id, &job.run_id) {
+                Ok(Some(lane)) => job.add_tag(LANE_TAG.to_string(), lane),
+                Ok(None) => {}
+                Err(e) => log::warn!("Failed to resolve the lane of job {}: {}", job_id, e),
+            }
+        }
+        
+        // Jobs of steps declaring worker tags only run on workers carrying them
+        if job.get_tag(WORKER_TAGS_TAG).is_none() {
+            match self.state_manager.lock().await.step_worker_tags(&job.workflow_id, &job.step_name) {
+                Ok(tags) if !tags.is_empty() => job.add_tag(WORKER_TAGS_TAG.to_string(), tags.join(",")),
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to resolve the worker tags of job {}: {}", job_id, e),
+            }
+        }
+        if !self.worker_tags.lock().await.has_worker_for(&job) {
+            log::warn!("No worker carries the tags job {} requires ({}), it waits until one registers them",
+                job_id, job.get_tag(WORKER_TAGS_TAG).map(String::as_str).unwrap_or_default());
+        }
+        
+        // Record the job before a worker can pick it up and record its progress
+        // Other processes sharing the store pull jobs from their records, so those are always kept
+        let record_job = self.config.distributed.enabled
+            || self.config.feature_flags.is_enabled(FeatureFlags::JOB_RECORDING, Some(&job.workflow_id));
+        if record_job {
+            if let Err(e) = self.state_manager.lock().await.save_job(&job) {
+                log::warn!("Failed to record job {}: {}", job_id, e);
+            }
+        }
+        
+        let queued = {
             let mut queue = self.job_queue.lock().await;
-            queue.enqueue(job)?;
-            queue.get_jobs().len()
+            self.namespaces.lock().await.can_enqueue(&job.workflow_id, &queue)
+                .and_then(|_| queue.enqueue(job))
+                .map(|_| queue.get_jobs().len())
         }; // Release lock here
+        let queue_depth = match queued {
+            Ok(queue_depth) => queue_depth,
+            Err(e) => {
+                if record_job {
+                    let _ = self.state_manager.lock().await.forget_job(&job_id);
+                }
+                return Err(e);
+            }
+        };
         
         // Update stats without holding queue lock
         {
@@ -223,6 +1416,101 @@ impl Dispatcher {
         Ok(())
     }
 
+    /// Submit a job and get a receiver for its execution result
+    ///
+    /// The receiver resolves once a worker has processed the job. It errors if
+    /// the job is cancelled before a worker picks it up.
+    pub async fn submit_job_with_result(&self, job: Job) -> Result<oneshot::Receiver<JobExecutionResult>, CoreError> {
+        let job_id = job.id.clone();
+        let (sender, receiver) = oneshot::channel();
+        {
+            let mut waiters = self.result_waiters.lock().await;
+            waiters.insert(job_id.clone(), sender);
+        }
+        
+        if let Err(e) = self.submit_job(job).await {
+            let mut waiters = self.result_waiters.lock().await;
+            waiters.remove(&job_id);
+            return Err(e);
+        }
+        
+        Ok(receiver)
+    }
+
+    /// Set the maximum number of concurrently executing runs of a workflow
+    pub async fn set_workflow_concurrency(&self, workflow_id: &str, limit: Option<usize>) {
+        let mut concurrency = self.concurrency.lock().await;
+        concurrency.set_limit(workflow_id, limit);
+        log::debug!("Set concurrency limit for workflow {} to {:?}", workflow_id, limit);
+    }
+
+    /// Queue a run behind the unfinished runs of its workflow sharing its serialization key
+    ///
+    /// The queue is persisted, so runs keep their order across restarts.
+    pub async fn serialize_run(&self, workflow_id: &str, run_id: &Uuid, key: &str) -> Result<(), CoreError> {
+        let run_ids = self.state_manager.lock().await.serialize_run(workflow_id, key, run_id)?;
+        log::debug!("Run {} is queued on key {} of workflow {} behind {} runs", run_id, key, workflow_id, run_ids.len().saturating_sub(1));
+        self.concurrency.lock().await.set_serialized_runs(workflow_id, key, run_ids);
+        Ok(())
+    }
+
+    /// Set or clear the worker quota of a namespace
+    pub async fn set_namespace_quota(&self, namespace: &str, quota: Option<NamespaceQuota>) {
+        let mut namespaces = self.namespaces.lock().await;
+        namespaces.set_quota(namespace, quota);
+        log::debug!("Set worker quota for namespace {} to {:?}", namespace, namespaces.get_quota(namespace));
+    }
+
+    /// Assign a workflow's jobs to a namespace quota
+    pub async fn set_workflow_namespace(&self, workflow_id: &str, namespace: Option<&str>) {
+        self.namespaces.lock().await.set_workflow_namespace(workflow_id, namespace);
+    }
+
+    /// Set or clear the resource quota of a workflow's jobs
+    pub async fn set_workflow_resource_quota(&self, workflow_id: &str, quota: Option<ResourceQuota>) {
+        self.resource_quotas.lock().await.set_quota(workflow_id, quota);
+    }
+
+    /// Get a workflow's resource usage within its quota window
+    pub async fn get_workflow_resource_usage(&self, workflow_id: &str) -> WorkflowResourceUsage {
+        self.resource_quotas.lock().await.usage(workflow_id, Instant::now())
+    }
+
+    /// Get the allocation and load of the dispatcher lanes
+    pub async fn get_lane_status(&self) -> Vec<LaneStatus> {
+        let queue = self.job_queue.lock().await;
+        self.lanes.lock().await.status(&queue)
+    }
+
+    /// Register the capability tags of a worker, replacing those it had
+    pub async fn set_worker_tags(&self, worker_id: &str, tags: Vec<String>) -> Result<(), CoreError> {
+        for tag in &tags {
+            crate::config::validate_worker_tag(tag).map_err(CoreError::Validation)?;
+        }
+        if !self.workers.lock().await.contains_key(worker_id) {
+            return Err(CoreError::Validation(format!("Unknown worker {}", worker_id)));
+        }
+        self.worker_tags.lock().await.set_worker_tags(worker_id, tags);
+        Ok(())
+    }
+
+    /// Get the workers and queue time of each worker capability tag
+    pub async fn get_worker_tag_status(&self) -> Vec<WorkerTagStatus> {
+        let queue = self.job_queue.lock().await;
+        self.worker_tags.lock().await.status(&queue)
+    }
+
+    /// Get the number of a namespace's jobs currently running
+    pub async fn get_namespace_running_count(&self, namespace: &str) -> usize {
+        self.namespaces.lock().await.running_count(namespace)
+    }
+
+    /// Get the number of runs of a workflow currently executing
+    pub async fn get_active_run_count(&self, workflow_id: &str) -> usize {
+        let concurrency = self.concurrency.lock().await;
+        concurrency.active_run_count(workflow_id)
+    }
+
     /// Get dispatcher statistics
     pub async fn get_stats(&self) -> Result<DispatcherStats, CoreError> {
         // Gather data from each lock scope separately to minimize lock duration
@@ -243,8 +1531,15 @@ impl Dispatcher {
             (active, idle)
         };
         
+        let (prefetch_hits, prefetch_misses) = {
+            let cache = self.context_cache.lock().await;
+            (cache.hits, cache.misses)
+        };
+        
         let mut result = stats_clone;
         result.queue_depth = queue_depth;
+        result.prefetch_hits = prefetch_hits;
+        result.prefetch_misses = prefetch_misses;
         result.active_workers = active_workers;
         result.idle_workers = idle_workers;
         
@@ -270,20 +1565,198 @@ impl Dispatcher {
         }
     }
 
+    /// List the queued and running jobs, optionally of one state and workflow
+    ///
+    /// Running jobs come first, longest running first, followed by queued jobs
+    /// in the order they would be dequeued, ignoring dependencies.
+    pub async fn list_jobs(&self, state: Option<&JobState>, workflow_id: Option<&str>, limit: usize) -> Vec<JobInspection> {
+        let matches = |job: &Job| state.is_none_or(|state| job.state == *state) && workflow_id.is_none_or(|id| job.workflow_id == id);
+        
+        let mut running: Vec<RunningJob> = self.running_jobs.lock().await.values()
+            .filter(|running| matches(&running.job))
+            .cloned()
+            .collect();
+        running.sort_by_key(|running| running.started_at);
+        let mut jobs: Vec<JobInspection> = running.into_iter().map(Self::inspect_running_job).collect();
+        
+        let queue = self.job_queue.lock().await;
+        let now = Utc::now();
+        let mut queued: Vec<&Job> = queue.get_jobs().iter().filter(|job| matches(job)).collect();
+        queued.sort_by(|a, b| queue.effective_priority(b, now).cmp(&queue.effective_priority(a, now))
+            .then(a.metadata.created_at.cmp(&b.metadata.created_at)));
+        jobs.extend(queued.into_iter().map(|job| Self::inspect_queued_job(&queue, job, now)));
+        
+        jobs.truncate(limit);
+        jobs
+    }
+    
+    /// Record a heartbeat of a running step, pushing back the moment it is considered hung
+    pub async fn report_step_heartbeat(&self, run_id: &str, step_id: &str) -> Result<(), CoreError> {
+        let mut running = self.running_jobs.lock().await;
+        let running_job = running.values_mut()
+            .find(|running| running.job.run_id == run_id && running.job.step_name == step_id)
+            .ok_or_else(|| CoreError::JobNotFound(format!("no running job for step {} of run {}", step_id, run_id)))?;
+        running_job.last_heartbeat_at = Some(Utc::now());
+        Ok(())
+    }
+    
+    /// Start injecting the faults of `rules` into the jobs they match, when failure injection is enabled
+    pub async fn set_chaos_rules(&self, rules: ChaosRules) -> Result<(), CoreError> {
+        self.chaos.lock().await.set_rules(rules)
+    }
+    
+    /// Stop injecting faults
+    pub async fn clear_chaos_rules(&self) {
+        self.chaos.lock().await.clear_rules();
+    }
+    
+    /// Get the failure injection rules and the faults injected so far
+    pub async fn get_chaos_status(&self) -> ChaosStatus {
+        self.chaos.lock().await.status()
+    }
+    
+    /// Ask `handler` what to do with failed steps instead of their retry rules, or stop asking with None
+    pub async fn set_retry_classifier(&self, handler: Option<ClassifierHandler>) {
+        self.retry_classifier.lock().await.set_handler(handler);
+    }
+    
+    /// Hand the classifier's decision to the failed step waiting for it
+    pub async fn report_retry_decision(&self, request_id: &str, decision: RetryDecision) -> Result<(), CoreError> {
+        self.retry_classifier.lock().await.report(request_id, decision)
+    }
+    
+    /// Get a queued or running job
+    pub async fn get_job(&self, job_id: &str) -> Option<JobInspection> {
+        if let Some(running) = self.running_jobs.lock().await.get(job_id) {
+            return Some(Self::inspect_running_job(running.clone()));
+        }
+        let queue = self.job_queue.lock().await;
+        queue.get_job(job_id).map(|job| Self::inspect_queued_job(&queue, job, Utc::now()))
+    }
+    
+    fn inspect_running_job(running: RunningJob) -> JobInspection {
+        JobInspection {
+            job: running.job,
+            worker_id: Some(running.worker_id),
+            next_retry_at: None,
+            effective_priority: None,
+            last_heartbeat_at: running.last_heartbeat_at,
+        }
+    }
+    
+    fn inspect_queued_job(queue: &JobQueue, job: &Job, now: DateTime<Utc>) -> JobInspection {
+        let mut job = job.clone();
+        job.context.remove("serialized_context");
+        JobInspection {
+            next_retry_at: queue.retry_not_before(&job.id),
+            effective_priority: Some(queue.effective_priority(&job, now)),
+            worker_id: None,
+            last_heartbeat_at: None,
+            job,
+        }
+    }
+    
     /// Cancel a job
+    ///
+    /// A queued job is cancelled before a worker picks it up. A running job is
+    /// told to stop through its cancellation token; its attempt is neither
+    /// recorded nor retried.
     pub async fn cancel_job(&self, job_id: &str) -> Result<bool, CoreError> {
         log::info!("Cancelling job {}", job_id);
         
         let mut queue = self.job_queue.lock().await;
         
-        if let Some(job) = queue.get_job_mut(job_id) {
+        let cancelled = if let Some(job) = queue.get_job_mut(job_id) {
             job.cancel()?;
+            true
+        } else {
+            self.cancellations.lock().await.cancel_job(job_id)
+        };
+        drop(queue);
+        
+        if cancelled {
+            // Dropping the sender tells anyone awaiting the result that it will never arrive
+            self.result_waiters.lock().await.remove(job_id);
             log::info!("Job {} cancelled successfully", job_id);
-            Ok(true)
         } else {
             log::warn!("Job {} not found for cancellation", job_id);
-            Ok(false)
         }
+        Ok(cancelled)
+    }
+
+    /// Remove the queued jobs of a run; jobs that are already running finish normally
+    ///
+    /// Returns the number of removed jobs.
+    pub async fn cancel_run_jobs(&self, workflow_id: &str, run_id: &str) -> usize {
+        Self::remove_run_jobs(&self.job_queue, &self.concurrency, &self.result_waiters, workflow_id, run_id).await
+    }
+
+    /// Remove the queued jobs of a run, releasing its concurrency slot and result waiters
+    async fn remove_run_jobs(
+        job_queue: &Mutex<JobQueue>,
+        concurrency: &Mutex<WorkflowConcurrency>,
+        result_waiters: &Mutex<HashMap<String, oneshot::Sender<JobExecutionResult>>>,
+        workflow_id: &str,
+        run_id: &str,
+    ) -> usize {
+        let removed: Vec<String> = {
+            let mut queue = job_queue.lock().await;
+            let job_ids: Vec<String> = queue.get_jobs()
+                .iter()
+                .filter(|job| job.run_id == run_id)
+                .map(|job| job.id.clone())
+                .collect();
+            for job_id in &job_ids {
+                queue.remove_job(job_id);
+            }
+            concurrency.lock().await.release_idle_run(workflow_id, run_id);
+            job_ids
+        };
+        
+        let mut waiters = result_waiters.lock().await;
+        for job_id in &removed {
+            waiters.remove(job_id);
+        }
+        
+        log::info!("Removed {} queued jobs of run {}", removed.len(), run_id);
+        removed.len()
+    }
+
+    /// Cancel a run: remove its queued jobs and signal its running jobs to stop
+    ///
+    /// Running jobs observe the signal cooperatively; results of steps that
+    /// already completed are kept. Returns the number of removed queued jobs.
+    pub async fn cancel_run(&self, workflow_id: &str, run_id: &str) -> usize {
+        self.cancellations.lock().await.cancel(run_id);
+        self.cancel_run_jobs(workflow_id, run_id).await
+    }
+
+    /// Get the diagnostics of a worker, falling back to persisted diagnostics
+    pub async fn get_worker_details(&self, worker_id: &str) -> Result<Option<WorkerDiagnostics>, CoreError> {
+        if let Some(worker) = self.workers.lock().await.get(worker_id) {
+            return Ok(Some(worker.diagnostics()));
+        }
+        let persisted = self.state_manager.lock().await.get_worker_diagnostics()?;
+        Ok(persisted.into_iter().find(|diagnostics| diagnostics.worker_id == worker_id))
+    }
+
+    /// List the diagnostics of every known worker, live workers taking precedence
+    pub async fn list_workers(&self) -> Result<Vec<WorkerDiagnostics>, CoreError> {
+        let mut workers: Vec<WorkerDiagnostics> = self.state_manager.lock().await.get_worker_diagnostics()?;
+        for worker in self.workers.lock().await.values() {
+            let live = worker.diagnostics();
+            match workers.iter_mut().find(|diagnostics| diagnostics.worker_id == live.worker_id) {
+                Some(diagnostics) => *diagnostics = live,
+                None => workers.push(live),
+            }
+        }
+        workers.sort_by(|a, b| a.worker_id.cmp(&b.worker_id));
+        Ok(workers)
+    }
+
+    /// Check whether a run was cancelled in the dispatcher
+    pub async fn is_run_cancelled(&self, run_id: &str) -> bool {
+        self.cancellations.lock().await.is_cancelled(run_id)
     }
 
     /// Start a worker task (async)
@@ -295,11 +1768,39 @@ impl Dispatcher {
         let running_jobs = Arc::clone(&self.running_jobs);
         let state_manager = Arc::clone(&self.state_manager);
         let worker_handles = Arc::clone(&self.worker_handles);
+        let concurrency = Arc::clone(&self.concurrency);
+        let namespaces = Arc::clone(&self.namespaces);
+        let lanes = Arc::clone(&self.lanes);
+        let worker_tags = Arc::clone(&self.worker_tags);
+        let resource_quotas = Arc::clone(&self.resource_quotas);
+        let retry_storms = Arc::clone(&self.retry_storms);
+        let retry_classifier = Arc::clone(&self.retry_classifier);
+        let result_waiters = Arc::clone(&self.result_waiters);
+        let context_cache = Arc::clone(&self.context_cache);
+        let prefetch_contexts = self.config.prefetch_contexts;
+        let feature_flags = self.config.feature_flags.clone();
+        let paused = Arc::clone(&self.paused);
+        let cancellations = Arc::clone(&self.cancellations);
+        let chaos = Arc::clone(&self.chaos);
+        let worker_timeout_ms = self.config.worker_timeout_ms;
+        let heartbeat_timeout_ms = self.config.heartbeat_timeout_ms;
+        let lease_owner = self.config.distributed.enabled.then(|| self.config.distributed.lease_owner(&worker_id));
+        let lease = chrono::Duration::milliseconds(self.config.distributed.lease_ms as i64);
+        let distributed = self.config.distributed.enabled;
         
-        // Initialize worker in the workers map
+        // Initialize worker in the workers map, continuing its persisted diagnostics
         {
+            let persisted = state_manager.lock().await.get_worker_diagnostics()
+                .unwrap_or_else(|e| {
+                    log::warn!("Failed to load diagnostics of worker {}: {}", worker_id, e);
+                    Vec::new()
+                })
+                .into_iter()
+                .find(|diagnostics| diagnostics.worker_id == worker_id);
+            let worker = persisted.as_ref().map(Worker::restore).unwrap_or_else(|| Worker::new(worker_id.clone()));
             let mut workers_guard = workers.lock().await;
-            workers_guard.insert(worker_id.clone(), Worker::new(worker_id.clone()));
+            workers_guard.insert(worker_id.clone(), worker);
+            lanes.lock().await.set_pool_size(workers_guard.len());
         }
         
         // Spawn async worker task
@@ -316,14 +1817,111 @@ impl Dispatcher {
                     }
                 } // Lock released here
                 
+                // Leave queued jobs alone while the engine is paused
+                if *paused.lock().await {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                }
+                
                 // Try to get a job (minimize lock duration)
                 let job = {
                     let mut queue = job_queue.lock().await;
                     let completed = completed_jobs.lock().await;
-                    queue.dequeue(&completed)
+                    let mut concurrency_guard = concurrency.lock().await;
+                    let mut namespace_guard = namespaces.lock().await;
+                    let mut lane_guard = lanes.lock().await;
+                    let mut tag_guard = worker_tags.lock().await;
+                    let quota_guard = resource_quotas.lock().await;
+                    let mut storm_guard = retry_storms.lock().await;
+                    let now = Instant::now();
+                    let job = queue.dequeue_where(&completed, |job| {
+                        tag_guard.can_run(&worker_id, job)
+                            && concurrency_guard.can_start(job)
+                            && namespace_guard.can_start(job, now)
+                            && lane_guard.can_start(job)
+                            && quota_guard.can_start(job, now)
+                            && storm_guard.can_start(job, now)
+                    });
+                    if let Some(job) = &job {
+                        concurrency_guard.job_started(job);
+                        namespace_guard.job_started(job, now);
+                        lane_guard.job_started(job);
+                        tag_guard.job_dispatched(job, Utc::now());
+                        storm_guard.job_started(job);
+                    }
+                    job
                 }; // Locks released here
                 
                 if let Some(mut job) = job {
+                    // Drop jobs of cancelled runs instead of starting them, and jobs another process claimed
+                    let cancel_token = cancellations.lock().await.token(&job.run_id);
+                    let claimed = match &lease_owner {
+                        _ if cancel_token.is_cancelled() => false,
+                        Some(owner) => match state_manager.lock().await.claim_job(&job.id, owner, lease) {
+                            Ok(claimed) => claimed,
+                            Err(e) => {
+                                log::warn!("Worker {} failed to claim job {}, leaving it for a later pull: {}", worker_id, job.id, e);
+                                false
+                            }
+                        },
+                        None => true,
+                    };
+                    let injection = if claimed { chaos.lock().await.injection_for(&job) } else { Default::default() };
+                    if !injection.is_empty() {
+                        if let Ok(run_uuid) = uuid::Uuid::parse_str(&job.run_id) {
+                            let fault = injection.fault.map(|fault| fault.as_str());
+                            let detail = serde_json::json!({"job_id": job.id, "fault": fault, "delay_ms": injection.delay_ms});
+                            if let Err(e) = state_manager.lock().await.record_run_event(&run_uuid, RunEvent::FAULT_INJECTED, Some(&job.step_name), &worker_id, detail) {
+                                log::warn!("Failed to record injected fault of job {}: {}", job.id, e);
+                            }
+                        }
+                    }
+                    if !claimed || injection.fault == Some(Fault::Drop) {
+                        if cancel_token.is_cancelled() {
+                            log::info!("Worker {} dropping job {} of cancelled run {}", worker_id, job.id, job.run_id);
+                            let _ = job.cancel();
+                        } else if claimed {
+                            // Keep the lease, so the job is only reclaimed once it expires, as after a crash
+                            log::warn!("Worker {} dropping job {}: injected fault", worker_id, job.id);
+                        } else {
+                            log::info!("Worker {} dropping job {} held by another process", worker_id, job.id);
+                        }
+                        result_waiters.lock().await.remove(&job.id);
+                        let queue = job_queue.lock().await;
+                        concurrency.lock().await.job_finished(&job.workflow_id, &job.run_id, queue.has_jobs_for_run(&job.run_id));
+                        namespaces.lock().await.job_finished(&job.id);
+                        lanes.lock().await.job_finished(RunLanes::lane_of(&job));
+                        retry_storms.lock().await.job_finished(&job.id);
+                        continue;
+                    }
+                    
+                    // Attach the serialized context, built ahead of time if a dependency completion
+                    // prefetched it and no step of the run completed since
+                    let completed_steps = match Uuid::parse_str(&job.run_id) {
+                        Ok(run_uuid) => state_manager.lock().await.get_completed_steps(&run_uuid).map(|steps| steps.len()).ok(),
+                        Err(_) => None,
+                    };
+                    let prefetched = match completed_steps {
+                        Some(completed_steps) => context_cache.lock().await.take(&job.id, completed_steps),
+                        None => None,
+                    };
+                    let context_json = match prefetched {
+                        Some(context_json) => Some(context_json),
+                        None => {
+                            let state_manager_guard = state_manager.lock().await;
+                            match Self::build_job_context(&state_manager_guard, &job) {
+                                Ok((_, context_json)) => Some(context_json),
+                                Err(e) => {
+                                    log::debug!("Could not build context for job {}: {}", job.id, e);
+                                    None
+                                }
+                            }
+                        }
+                    };
+                    if let Some(context_json) = context_json {
+                        job.add_context("serialized_context".to_string(), serde_json::Value::String(context_json));
+                    }
+                    
                     // Update worker status
                     {
                         let mut workers_guard = workers.lock().await;
@@ -332,24 +1930,121 @@ impl Dispatcher {
                         }
                     }
                     
+                    if let Ok(run_uuid) = uuid::Uuid::parse_str(&job.run_id) {
+                        let state_manager_guard = state_manager.lock().await;
+                        let detail = serde_json::json!({"job_id": job.id});
+                        if let Err(e) = state_manager_guard.record_run_event(&run_uuid, RunEvent::STEP_STARTED, Some(&job.step_name), &worker_id, detail) {
+                            log::warn!("Failed to record start event for job {}: {}", job.id, e);
+                        }
+                    }
+                    
+                    if let Err(e) = job.start() {
+                        log::warn!("Job {} started from unexpected state: {}", job.id, e);
+                    }
+                    
                     // Track running job
                     {
+                        let mut snapshot = job.clone();
+                        snapshot.context.remove("serialized_context");
                         let mut running = running_jobs.lock().await;
-                        running.insert(job.id.clone(), Utc::now());
+                        running.insert(job.id.clone(), RunningJob::new(snapshot, worker_id.clone()));
                     }
                     
                     let job_id_clone = job.id.clone();
+                    let job_workflow_id = job.workflow_id.clone();
+                    let job_run_id = job.run_id.clone();
+                    let job_lane = RunLanes::lane_of(&job).to_string();
+                    let job_step_name = job.step_name.clone();
                     log::info!("Worker {} processing job {}", worker_id, job_id_clone);
                     
                     // Process the job (use spawn_blocking for potentially CPU-intensive work)
                     let start_time = Instant::now();
                     let state_manager_clone = Arc::clone(&state_manager);
+                    let retry_classifier_clone = Arc::clone(&retry_classifier);
                     
-                    let (result, mut job_back) = tokio::task::spawn_blocking(move || {
-                        let result = Self::process_job(&mut job);
-                        (result, job)
-                    }).await.unwrap_or_else(|e| {
+                    // Blocking steps cannot be killed, so a timed out attempt is told to stop through its abort token
+                    let timeout_ms = job.timeout_ms.unwrap_or(worker_timeout_ms);
+                    let abort_token = CancellationToken::default();
+                    let attempt_job = job.clone();
+                    let job_cancel_token = cancel_token.clone();
+                    let job_abort_token = abort_token.clone();
+                    let stop_token = cancellations.lock().await.start_job(&job.id);
+                    let job_stop_token = stop_token.clone();
+                    let mut worker_panic = None;
+                    let mut timed_out = false;
+                    
+                    // Renew the job's lease while it runs, so other processes do not reclaim it
+                    let heartbeat = lease_owner.clone().map(|owner| {
+                        let state_manager = Arc::clone(&state_manager);
+                        let job_id = job.id.clone();
+                        tokio::spawn(async move {
+                            let mut interval = tokio::time::interval(Duration::from_millis((lease.num_milliseconds() as u64 / 3).max(1)));
+                            interval.tick().await;
+                            loop {
+                                interval.tick().await;
+                                match state_manager.lock().await.renew_job_lease(&job_id, &owner, Utc::now() + lease) {
+                                    Ok(true) => {}
+                                    Ok(false) => {
+                                        log::warn!("Lease of job {} was taken over by another process", job_id);
+                                        break;
+                                    }
+                                    Err(e) => log::warn!("Failed to renew the lease of job {}: {}", job_id, e),
+                                }
+                            }
+                        })
+                    });
+                    
+                    let started_at = Utc::now();
+                    if injection.delay_ms > 0 {
+                        log::warn!("Delaying job {} by {}ms: injected fault", job.id, injection.delay_ms);
+                        tokio::time::sleep(Duration::from_millis(injection.delay_ms)).await;
+                    }
+                    let injected_fault = injection.fault;
+                    let mut task = tokio::task::spawn_blocking(move || {
+                        let sample = UsageSample::take();
+                        let mut result = match injected_fault {
+                            Some(fault) => {
+                                log::warn!("Job {} not run, injected fault: {}", job.id, fault.as_str());
+                                Err(CoreError::StepExecution(format!("Injected {} fault in step {}", fault.as_str(), job.step_name)))
+                            }
+                            None => Self::process_job(&mut job, &job_cancel_token, &job_stop_token, &job_abort_token),
+                        };
+                        let usage = sample.and_then(|sample| sample.usage_since());
+                        if let Ok(step_result) = &mut result {
+                            step_result.resources = usage;
+                        }
+                        (result, job, usage)
+                    });
+                    // Wait for the job until its deadline, which the step's heartbeats move.
+                    // The first heartbeat can bring the deadline closer, so it is checked at
+                    // least twice per heartbeat timeout.
+                    let recheck = Duration::from_millis((heartbeat_timeout_ms / 2).max(1));
+                    let mut deadline = (started_at + chrono::Duration::milliseconds(timeout_ms as i64), JobTimeout::Elapsed(timeout_ms));
+                    let execution = loop {
+                        if let Some(running) = running_jobs.lock().await.get(&job_id_clone) {
+                            deadline = running.deadline(timeout_ms, heartbeat_timeout_ms);
+                        }
+                        let remaining = (deadline.0 - Utc::now()).to_std().unwrap_or_default();
+                        if remaining.is_zero() || injected_fault == Some(Fault::Timeout) {
+                            break Err(deadline.1);
+                        }
+                        tokio::select! {
+                            joined = &mut task => break Ok(joined),
+                            _ = tokio::time::sleep(remaining.min(recheck)) => {}
+                        }
+                    };
+                    let (result, mut job_back, usage) = match execution {
+                        Ok(joined) => joined,
+                        Err(timeout) => {
+                            let message = timeout.message(&job_step_name);
+                            log::warn!("Job {} timed out, aborting it: {}", job_id_clone, message);
+                            abort_token.cancel();
+                            timed_out = true;
+                            Ok((Err(CoreError::StepExecution(message)), attempt_job, None))
+                        }
+                    }.unwrap_or_else(|e| {
                         log::error!("Worker task panicked: {:?}", e);
+                        worker_panic = Some(Self::panic_message(e));
                         // Create a dummy job for error case
                         let dummy_job = Job {
                             id: job_id_clone.clone(),
@@ -366,19 +2061,44 @@ impl Dispatcher {
                             timeout_ms: None,
                             context: std::collections::HashMap::new(),
                         };
-                        (Err(CoreError::Internal("Worker task panicked".to_string())), dummy_job)
+                        (Err(CoreError::Internal("Worker task panicked".to_string())), dummy_job, None)
                     });
                     
+                    if let Some(heartbeat) = heartbeat {
+                        heartbeat.abort();
+                    }
+                    cancellations.lock().await.finish_job(&job_id_clone);
+                    
                     let processing_time = start_time.elapsed().as_millis() as u64;
                     let success = result.is_ok();
+                    let completes_run = result.as_ref().ok().and_then(|r| r.control()) == Some(StepControl::CompleteRun);
+                    let execution_result = JobExecutionResult {
+                        job_id: job_back.id.clone(),
+                        success,
+                        result: result.as_ref().ok().cloned(),
+                        error: result.as_ref().err().map(|e| e.to_string()),
+                        processing_time_ms: processing_time,
+                        worker_id: worker_id.clone(),
+                    };
                     
                     // Clone job_id for logging
                     let job_id_for_logging = job_back.id.clone();
                     let job_id_final = job_back.id.clone();
                     
                     // Process result or handle failure in spawn_blocking to avoid blocking async runtime
-                    tokio::task::spawn_blocking(move || {
-                        if let Ok(step_result) = result {
+                    let run_cancelled = cancel_token.is_cancelled();
+                    let job_cancelled = stop_token.is_cancelled();
+                    let (job_back, failover) = tokio::task::spawn_blocking(move || {
+                        let mut failover = None;
+                        if job_cancelled {
+                            // Whoever cancelled the job settles its step; drop the attempt, even if it finished
+                            log::info!("Job {} stopped because it was cancelled", job_id_final);
+                            let _ = job_back.cancel();
+                        } else if run_cancelled && result.is_err() {
+                            // The run was cancelled; neither record the aborted attempt nor retry it
+                            log::info!("Job {} stopped because its run was cancelled", job_id_final);
+                            let _ = job_back.cancel();
+                        } else if let Ok(step_result) = result {
                             let _ = job_back.complete(step_result.clone());
                             // Process the job result
                             if let Err(e) = Self::process_job_result_internal(&state_manager_clone, &job_back, &step_result) {
@@ -388,27 +2108,112 @@ impl Dispatcher {
                             let error = result.err().unwrap().to_string();
                             let _ = job_back.fail(error.clone());
                             // Handle job failure
-                            if let Err(e) = Self::handle_job_failure_internal(&state_manager_clone, &mut job_back, &error) {
-                                log::error!("Failed to handle job failure for {}: {}", job_id_final, e);
+                            let timeout = timed_out.then_some(timeout_ms);
+                            match Self::handle_job_failure_internal(&state_manager_clone, &retry_classifier_clone, &mut job_back, &error, timeout, usage) {
+                                Ok(step_id) => failover = step_id,
+                                Err(e) => log::error!("Failed to handle job failure for {}: {}", job_id_final, e),
                             }
                         }
-                    }).await.unwrap_or_else(|e| {
+                        (job_back, failover)
+                    }).await.map_err(|e| {
                         log::error!("Failed to process job result/failure: {:?}", e);
+                    }).ok().unzip();
+                    
+                    // Record the job's state after this attempt
+                    let recorded_job = job_back.as_ref().filter(|job| {
+                        worker_panic.is_none()
+                            && (distributed || feature_flags.is_enabled(FeatureFlags::JOB_RECORDING, Some(&job.workflow_id)))
                     });
+                    if let Some(job) = recorded_job {
+                        if let Err(e) = state_manager.lock().await.save_job(job) {
+                            log::warn!("Failed to record job {}: {}", job.id, e);
+                        }
+                    }
                     
-                    // Update worker status
-                    {
+                    // Run the step the classifier failed the job over to
+                    if let (Some(job), Some(step_id)) = (job_back.as_ref(), failover.flatten()) {
+                        match Self::queue_failover_step(&state_manager, &job_queue, &running_jobs, job, &step_id).await {
+                            Ok(true) => log::info!("Job {} failed over to step {}", job.id, step_id),
+                            Ok(false) => log::debug!("Failover step {} of job {} is already queued, running or finished", step_id, job.id),
+                            Err(e) => log::error!("Failed to queue failover step {} of job {}: {}", step_id, job.id, e),
+                        }
+                    }
+                    
+                    // Re-queue retries after their backoff, spread out further while their step is in a retry storm
+                    let retrying = match job_back {
+                        Some(job) if job.state == JobState::Retrying => {
+                            let mut delay = Duration::from_millis(job.next_retry_delay_with_jitter());
+                            if let Some(storm_delay) = retry_storms.lock().await.record_retry(&job, Instant::now()) {
+                                log::warn!("Retry storm on {}, delaying retry of job {} by {}ms",
+                                    RetryStormGuard::retry_key(&job), job.id, storm_delay.as_millis());
+                                stats.lock().await.storm_delayed_retries += 1;
+                                delay += storm_delay;
+                            }
+                            log::debug!("Retry of job {} scheduled in {}ms", job.id, delay.as_millis());
+                            let not_before = Utc::now() + chrono::Duration::milliseconds(delay.as_millis() as i64);
+                            let job_id = job.id.clone();
+                            // Keep the job through its backoff; it is reclaimed if this process stops meanwhile
+                            if let Some(owner) = &lease_owner {
+                                if let Err(e) = state_manager.lock().await.renew_job_lease(&job_id, owner, not_before + lease) {
+                                    log::warn!("Failed to extend the lease of job {} over its backoff: {}", job_id, e);
+                                }
+                            }
+                            match job_queue.lock().await.enqueue_retry(job, not_before) {
+                                Ok(()) => true,
+                                Err(e) => {
+                                    log::error!("Failed to re-queue job {} for retry: {}", job_id, e);
+                                    false
+                                }
+                            }
+                        }
+                        _ => false,
+                    };
+                    
+                    // Update worker status and persist its diagnostics
+                    let diagnostics = {
                         let mut workers_guard = workers.lock().await;
-                        if let Some(worker) = workers_guard.get_mut(&worker_id) {
+                        workers_guard.get_mut(&worker_id).map(|worker| {
                             worker.finish_job(processing_time);
+                            worker.record_job(job_id_for_logging.clone(), !success);
+                            if let Some(message) = worker_panic.take() {
+                                worker.record_panic(message);
+                            }
+                            worker.diagnostics()
+                        })
+                    };
+                    if let Some(diagnostics) = diagnostics.filter(|_| feature_flags.is_enabled(FeatureFlags::WORKER_DIAGNOSTICS, None)) {
+                        if let Err(e) = state_manager.lock().await.save_worker_diagnostics(&diagnostics) {
+                            log::warn!("Failed to persist diagnostics of worker {}: {}", worker_id, e);
+                        }
+                    }
+                    
+                    // Drop the queued jobs of a run a step completed early
+                    if completes_run {
+                        let dropped = job_queue.lock().await.remove_jobs_for_run(&job_run_id);
+                        if !dropped.is_empty() {
+                            log::info!("Dropped {} queued jobs of run {} after it completed early", dropped.len(), job_run_id);
+                        }
+                        for mut job in dropped {
+                            result_waiters.lock().await.remove(&job.id);
+                            let _ = job.cancel();
+                            if feature_flags.is_enabled(FeatureFlags::JOB_RECORDING, Some(&job.workflow_id)) {
+                                if let Err(e) = state_manager.lock().await.save_job(&job) {
+                                    log::warn!("Failed to record job {}: {}", job.id, e);
+                                }
+                            }
                         }
                     }
                     
                     // Mark job as completed
-                    {
+                    if !retrying {
                         let mut completed = completed_jobs.lock().await;
                         completed.push(job_id_for_logging.clone());
                     }
+                    if let Some(owner) = lease_owner.as_ref().filter(|_| !retrying) {
+                        if let Err(e) = state_manager.lock().await.release_job(&job_id_for_logging, owner) {
+                            log::warn!("Failed to release the lease of job {}: {}", job_id_for_logging, e);
+                        }
+                    }
                     
                     // Remove from running jobs
                     {
@@ -416,6 +2221,43 @@ impl Dispatcher {
                         running.remove(&job_id_for_logging);
                     }
                     
+                    // Prefetch contexts of the queued jobs that depend on this step
+                    if prefetch_contexts {
+                        let step_job_id = Job::get_job_id(&job_workflow_id, &job_run_id, &job_step_name);
+                        let downstream: Vec<Job> = {
+                            let queue = job_queue.lock().await;
+                            queue.get_jobs()
+                                .iter()
+                                .filter(|queued| queued.dependencies.contains(&step_job_id))
+                                .cloned()
+                                .collect()
+                        };
+                        if !downstream.is_empty() {
+                            tokio::spawn(Self::prefetch_contexts(Arc::clone(&state_manager), Arc::clone(&context_cache), downstream));
+                        }
+                    }
+                    
+                    // Count the attempt against its workflow's resource quota
+                    if let Some(usage) = usage {
+                        if resource_quotas.lock().await.record(&job_workflow_id, usage, Instant::now()) {
+                            log::warn!("Workflow {} went over its resource quota, pausing dispatch of its jobs", job_workflow_id);
+                        }
+                    }
+                    
+                    // Release the workflow concurrency slot once the run has no jobs left
+                    {
+                        let queue = job_queue.lock().await;
+                        let mut concurrency_guard = concurrency.lock().await;
+                        let run_has_jobs = queue.has_jobs_for_run(&job_run_id);
+                        concurrency_guard.job_finished(&job_workflow_id, &job_run_id, run_has_jobs);
+                        namespaces.lock().await.job_finished(&job_id_for_logging);
+                        lanes.lock().await.job_finished(&job_lane);
+                        retry_storms.lock().await.job_finished(&job_id_for_logging);
+                        if !run_has_jobs {
+                            cancellations.lock().await.release(&job_run_id);
+                        }
+                    }
+                    
                     // Update statistics
                     {
                         let mut stats_guard = stats.lock().await;
@@ -425,11 +2267,22 @@ impl Dispatcher {
                         } else {
                             stats_guard.failed_jobs += 1;
                         }
+                        if timed_out {
+                            stats_guard.timed_out_jobs += 1;
+                        }
                         
                         let total_time = stats_guard.average_processing_time_ms * (stats_guard.total_jobs_processed - 1) + processing_time;
                         stats_guard.average_processing_time_ms = total_time / stats_guard.total_jobs_processed;
                     }
                     
+                    // Hand the result to whoever submitted the job and is waiting for it
+                    {
+                        let mut waiters = result_waiters.lock().await;
+                        if let Some(sender) = waiters.remove(&job_id_for_logging) {
+                            let _ = sender.send(execution_result);
+                        }
+                    }
+                    
                     log::info!("Worker {} completed job {} in {}ms", worker_id, job_id_for_logging, processing_time);
                 } else {
                     // No job available, yield and sleep briefly
@@ -486,12 +2339,10 @@ impl Dispatcher {
                     queue.get_jobs()
                         .iter()
                         .filter(|job| {
-                            if let Some(started_at) = running.get(&job.id) {
-                                let elapsed = now.signed_duration_since(*started_at);
-                                elapsed.num_milliseconds() as u64 > job.timeout_ms.unwrap_or(config.worker_timeout_ms)
-                            } else {
-                                false
-                            }
+                            running.get(&job.id).is_some_and(|running_job| {
+                                let (deadline, _) = running_job.deadline(job.timeout_ms.unwrap_or(config.worker_timeout_ms), config.heartbeat_timeout_ms);
+                                now > deadline
+                            })
                         })
                         .map(|job| job.id.clone())
                         .collect::<Vec<_>>()
@@ -514,29 +2365,297 @@ impl Dispatcher {
                         let mut stats_guard = stats.lock().await;
                         stats_guard.timed_out_jobs += 1;
                     }
-                    
-                    // Remove from running jobs
-                    {
-                        let mut running = running_jobs.lock().await;
-                        running.remove(&job_id);
+                    
+                    // Remove from running jobs
+                    {
+                        let mut running = running_jobs.lock().await;
+                        running.remove(&job_id);
+                    }
+                }
+            }
+            
+            log::info!("Timeout monitor stopped");
+        });
+        
+        // Store the task handle
+        {
+            let mut handles = worker_handles.lock().await;
+            handles.push(handle);
+        }
+        
+        Ok(())
+    }
+
+    /// Start the task ending runs that exceed their workflow's run timeout (async)
+    ///
+    /// A timed out run is marked failed or cancelled, its queued jobs are
+    /// removed and its running jobs are signalled to stop. Its onFailure or
+    /// onCancel hook fires when the run's final status is recorded.
+    async fn start_run_timeout_monitor(&self, shutdown_flag: Arc<Mutex<bool>>) {
+        let state_manager = Arc::clone(&self.state_manager);
+        let job_queue = Arc::clone(&self.job_queue);
+        let concurrency = Arc::clone(&self.concurrency);
+        let result_waiters = Arc::clone(&self.result_waiters);
+        let cancellations = Arc::clone(&self.cancellations);
+        let worker_handles = Arc::clone(&self.worker_handles);
+        
+        let handle = tokio::spawn(async move {
+            log::info!("Run timeout monitor started");
+            
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            
+            loop {
+                interval.tick().await;
+                if *shutdown_flag.lock().await {
+                    break;
+                }
+                
+                let timed_out = {
+                    let mut state_manager = state_manager.lock().await;
+                    let now = Utc::now();
+                    let runs = state_manager.timed_out_runs(now).unwrap_or_else(|e| {
+                        log::error!("Failed to find timed out runs: {}", e);
+                        Vec::new()
+                    });
+                    runs.iter()
+                        .filter_map(|(run, workflow)| match state_manager.time_out_run(run, workflow, now) {
+                            Ok(context) => context,
+                            Err(e) => {
+                                log::error!("Failed to time out run {}: {}", run.id, e);
+                                None
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                }; // Lock released here
+                
+                for context in timed_out {
+                    log::warn!("Run {} of workflow {} timed out: {}", context.run_id, context.workflow_id, context.error.as_deref().unwrap_or_default());
+                    cancellations.lock().await.cancel(&context.run_id);
+                    Self::remove_run_jobs(&job_queue, &concurrency, &result_waiters, &context.workflow_id, &context.run_id).await;
+                }
+            }
+            
+            log::info!("Run timeout monitor stopped");
+        });
+        
+        worker_handles.lock().await.push(handle);
+    }
+
+    /// Start the task pulling jobs other processes recorded into the local queue (async)
+    ///
+    /// Pulled jobs include jobs submitted elsewhere and jobs whose worker let its
+    /// lease expire. Dependencies that finished on another process are marked
+    /// completed, and a worker only runs a pulled job once it claims it.
+    async fn start_job_puller(&self, shutdown_flag: Arc<Mutex<bool>>) {
+        let state_manager = Arc::clone(&self.state_manager);
+        let job_queue = Arc::clone(&self.job_queue);
+        let completed_jobs = Arc::clone(&self.completed_jobs);
+        let running_jobs = Arc::clone(&self.running_jobs);
+        let poll_interval = Duration::from_millis(self.config.distributed.poll_interval_ms);
+        let pull_limit = self.config.queue_size;
+        let worker_handles = Arc::clone(&self.worker_handles);
+        
+        let handle = tokio::spawn(async move {
+            log::info!("Job puller started, polling every {}ms", poll_interval.as_millis());
+            
+            let mut interval = tokio::time::interval(poll_interval);
+            
+            loop {
+                interval.tick().await;
+                if *shutdown_flag.lock().await {
+                    break;
+                }
+                
+                // Queued jobs may be waiting on dependencies that finished on another process
+                let waiting: Vec<Job> = {
+                    let queue = job_queue.lock().await;
+                    let completed = completed_jobs.lock().await;
+                    queue.get_jobs().iter()
+                        .filter(|job| !job.dependencies.iter().all(|dependency| completed.contains(dependency)))
+                        .cloned()
+                        .collect()
+                }; // Locks released here
+                
+                // The store is read on a blocking thread without holding the queue locks
+                let reads = tokio::task::spawn_blocking({
+                    let state_manager = Arc::clone(&state_manager);
+                    move || {
+                        let state_manager = state_manager.blocking_lock();
+                        let claimable = state_manager.claimable_jobs(pull_limit)?;
+                        let mut finished = Vec::new();
+                        for job in waiting.iter().chain(&claimable) {
+                            match state_manager.finished_dependencies(job) {
+                                Ok(dependencies) => finished.extend(dependencies),
+                                Err(e) => log::debug!("Failed to check the dependencies of job {}: {}", job.id, e),
+                            }
+                        }
+                        Ok::<_, CoreError>((claimable, finished))
+                    }
+                }).await
+                    .map_err(|e| CoreError::Internal(format!("Job pull task failed: {}", e)))
+                    .and_then(|reads| reads);
+                let (claimable, finished) = match reads {
+                    Ok(reads) => reads,
+                    Err(e) => {
+                        log::warn!("Failed to pull jobs from the shared store: {}", e);
+                        continue;
+                    }
+                };
+                
+                let mut queue = job_queue.lock().await;
+                let running = running_jobs.lock().await;
+                for mut job in claimable {
+                    if queue.get_job(&job.id).is_some() || running.contains_key(&job.id) {
+                        continue;
+                    }
+                    if job.state == JobState::Running {
+                        log::warn!("Reclaiming job {} from an expired lease", job.id);
+                        job.state = JobState::Pending;
+                    }
+                    let job_id = job.id.clone();
+                    match queue.enqueue(job) {
+                        Ok(()) => log::debug!("Pulled job {} from the shared store", job_id),
+                        Err(e) => log::warn!("Failed to queue pulled job {}: {}", job_id, e),
+                    }
+                }
+                drop(running);
+                drop(queue);
+                
+                let mut completed = completed_jobs.lock().await;
+                for dependency in finished {
+                    if !completed.contains(&dependency) {
+                        completed.push(dependency);
                     }
                 }
             }
             
-            log::info!("Timeout monitor stopped");
+            log::info!("Job puller stopped");
         });
         
-        // Store the task handle
-        {
-            let mut handles = worker_handles.lock().await;
-            handles.push(handle);
+        worker_handles.lock().await.push(handle);
+    }
+
+    /// Start the task deleting runs the retention policy expires (async)
+    ///
+    /// A pass runs at startup and then every cleanup interval. The task wakes every
+    /// second so shutdown is not held up by a long interval.
+    async fn start_retention_cleanup(&self, shutdown_flag: Arc<Mutex<bool>>) {
+        let state_manager = Arc::clone(&self.state_manager);
+        let retention = self.config.retention.clone();
+        let worker_handles = Arc::clone(&self.worker_handles);
+        
+        let handle = tokio::spawn(async move {
+            log::info!("Retention cleanup started, running every {}s", retention.cleanup_interval_secs);
+            
+            let cleanup_interval = Duration::from_secs(retention.cleanup_interval_secs);
+            let mut next_cleanup = Instant::now();
+            let mut interval = tokio::time::interval(Duration::from_secs(1));
+            
+            loop {
+                interval.tick().await;
+                if *shutdown_flag.lock().await {
+                    break;
+                }
+                if Instant::now() < next_cleanup {
+                    continue;
+                }
+                next_cleanup = Instant::now() + cleanup_interval;
+                
+                let result = state_manager.lock().await.apply_retention(&retention, Utc::now());
+                if let Err(e) = result {
+                    log::error!("Retention cleanup failed: {}", e);
+                }
+            }
+            
+            log::info!("Retention cleanup stopped");
+        });
+        
+        worker_handles.lock().await.push(handle);
+    }
+
+    /// Build and cache the serialized contexts of jobs whose dependencies just progressed
+    async fn prefetch_contexts(
+        state_manager: Arc<Mutex<StateManager>>,
+        context_cache: Arc<Mutex<ContextPrefetchCache>>,
+        jobs: Vec<Job>,
+    ) {
+        for job in jobs {
+            let built = {
+                let state_manager_guard = state_manager.lock().await;
+                Self::build_job_context(&state_manager_guard, &job)
+            };
+            
+            match built {
+                Ok((completed_steps, context_json)) => {
+                    log::debug!("Prefetched context for job {} ({} completed steps)", job.id, completed_steps);
+                    context_cache.lock().await.insert(job.id.clone(), completed_steps, context_json);
+                }
+                Err(e) => log::debug!("Skipping context prefetch for job {}: {}", job.id, e),
+            }
         }
+    }
+
+    /// Build the serialized step context of a job, returning the number of completed steps it includes
+    fn build_job_context(state_manager: &StateManager, job: &Job) -> Result<(usize, String), CoreError> {
+        let run_uuid = Uuid::parse_str(&job.run_id)?;
+        let run = state_manager.get_run(&run_uuid)?
+            .ok_or_else(|| CoreError::RunNotFound(job.run_id.clone()))?;
+        let workflow = state_manager.get_run_workflow(&job.workflow_id, &run_uuid)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(job.workflow_id.clone()))?;
+        let completed_steps = state_manager.get_completed_steps_with_outputs(&run_uuid)?;
+        let completed_count = completed_steps.len();
+        let execution = state_manager.execution_info(&run_uuid, &job.step_name, &completed_steps, Some(job.metadata.created_at))?;
         
-        Ok(())
+        let mut context = Context::new(
+            job.run_id.clone(),
+            job.workflow_id.clone(),
+            job.step_name.clone(),
+            job.payload.clone(),
+            run,
+            completed_steps,
+        )?;
+        
+        if let Some(step_index) = workflow.steps.iter().position(|step| step.id == job.step_name) {
+            context.update_step_metadata(step_index, workflow.steps.len());
+            if let Some(timeout) = job.timeout_ms.or(workflow.steps[step_index].timeout) {
+                context.set_timeout(timeout);
+            }
+        }
+        context.metadata.retry_count = job.metadata.attempt_count;
+        if let Some(dispatched_at) = job.metadata.started_at {
+            state_manager.trace_queue_wait(&run_uuid, &job.step_name, job.metadata.created_at, dispatched_at);
+        }
+        context.execution = ExecutionInfo {
+            dispatched_at: job.metadata.started_at.unwrap_or(execution.dispatched_at),
+            ..execution
+        };
+        context.state = state_manager.context_state(&job.run_id, &job.workflow_id)?;
+        context.secrets = state_manager.workflow_secrets(&job.workflow_id)?;
+        context.labels = state_manager.get_run_labels(&run_uuid)?;
+        if let Some(step) = workflow.get_step(&job.step_name) {
+            context.render_input(step)?;
+        }
+        state_manager.record_issued_context(&context)?;
+        
+        Ok((completed_count, context.to_json()?))
+    }
+
+    /// Describe why a job task panicked
+    fn panic_message(error: tokio::task::JoinError) -> String {
+        if !error.is_panic() {
+            return error.to_string();
+        }
+        let payload = error.into_panic();
+        payload.downcast_ref::<&str>().map(|message| message.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "Job task panicked".to_string())
     }
 
     /// Process a job (simplified version without bridge dependency)
-    fn process_job(job: &mut Job) -> Result<StepResult, CoreError> {
+    ///
+    /// `stop_token` is signalled when the job itself is cancelled, and
+    /// `abort_token` when the attempt runs past its timeout.
+    fn process_job(job: &mut Job, cancel_token: &CancellationToken, stop_token: &CancellationToken, abort_token: &CancellationToken) -> Result<StepResult, CoreError> {
         log::info!("Processing job: {}", job.id);
         
         // Simulate job processing
@@ -549,8 +2668,29 @@ impl Dispatcher {
             }
         }
         
-        // Simulate some processing time
-        std::thread::sleep(std::time::Duration::from_millis(100));
+        // Simulate some processing time; steps check whether to stop between units of work
+        let mut remaining_ms = job.payload.get("simulated_duration_ms").and_then(|v| v.as_u64()).unwrap_or(100);
+        loop {
+            let unit_ms = remaining_ms.min(100);
+            std::thread::sleep(std::time::Duration::from_millis(unit_ms));
+            remaining_ms -= unit_ms;
+            
+            if cancel_token.is_cancelled() {
+                log::info!("Job {} aborted because its run was cancelled", job.id);
+                return Err(CoreError::Cancelled(format!("Run {} was cancelled", job.run_id)));
+            }
+            if stop_token.is_cancelled() {
+                log::info!("Job {} aborted because it was cancelled", job.id);
+                return Err(CoreError::Cancelled(format!("Job {} was cancelled", job.id)));
+            }
+            if abort_token.is_cancelled() {
+                log::info!("Job {} aborted because it timed out", job.id);
+                return Err(CoreError::StepExecution(format!("Step {} was aborted", job.step_name)));
+            }
+            if remaining_ms == 0 {
+                break;
+            }
+        }
         
         let processing_time = start_time.elapsed();
         
@@ -570,6 +2710,8 @@ impl Dispatcher {
             started_at: chrono::Utc::now(),
             completed_at: Some(chrono::Utc::now()),
             duration_ms: Some(processing_time.as_millis() as u64),
+            cache_hit: None,
+            resources: None,
         };
         
         log::info!("Job {} processed successfully in {}ms", job.id, processing_time.as_millis());
@@ -629,6 +2771,9 @@ impl Dispatcher {
             
             // Save the step result
             state_manager.save_step_result(run_id, step_result.clone())?;
+            if step_result.control() == Some(StepControl::CompleteRun) {
+                state_manager.complete_run_early(run_id, &step_result.step_id)?;
+            }
             
             if let Some(run) = state_manager.get_run(run_id)? {
                 if run.status == RunStatus::Pending {
@@ -649,7 +2794,7 @@ impl Dispatcher {
         rt.block_on(async {
             let mut state_manager = self.state_manager.lock().await;
             
-            let workflow = state_manager.get_workflow(workflow_id)?
+            let workflow = state_manager.get_run_workflow(workflow_id, run_id)?
                 .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?;
             
             let completed_steps = state_manager.get_completed_steps(run_id)?;
@@ -660,7 +2805,7 @@ impl Dispatcher {
             
             if all_steps_completed {
                 let has_failures = completed_steps.iter().any(|result| {
-                    matches!(result.status, StepStatus::Failed)
+                    result.status.is_failure()
                 });
                 
                 let final_status = if has_failures {
@@ -671,7 +2816,7 @@ impl Dispatcher {
                 
                 let error_message = if has_failures {
                     let failed_steps: Vec<_> = completed_steps.iter()
-                        .filter(|result| matches!(result.status, StepStatus::Failed))
+                        .filter(|result| result.status.is_failure())
                         .map(|result| format!("{}: {}", result.step_id, result.error.as_deref().unwrap_or("Unknown error")))
                         .collect();
                     Some(format!("Workflow failed: {}", failed_steps.join(", ")))
@@ -693,7 +2838,7 @@ impl Dispatcher {
                     run.payload.clone(),
                 );
                 
-                // Execute hooks (for now, just log - will be implemented in Phase 3)
+                // Hooks fire when the final status is recorded
                 log::info!("Workflow {} completed with status: {:?}", workflow_id, final_status);
                 log::info!("Completion context: {:?}", completion_context);
                 
@@ -713,7 +2858,7 @@ impl Dispatcher {
         rt.block_on(async {
             let state_manager = self.state_manager.lock().await;
             
-            let workflow = state_manager.get_workflow(workflow_id)?
+            let workflow = state_manager.get_run_workflow(workflow_id, run_id)?
                 .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?;
             
             let completed_steps = state_manager.get_completed_steps(run_id)?;
@@ -751,7 +2896,7 @@ impl Dispatcher {
         rt.block_on(async {
             log::warn!("Handling failure for job: {} - {}", job.id, error);
             
-            if job.can_retry() {
+            if job.should_retry(error) {
                 log::info!("Retrying job: {} (attempt {}/{})", 
                     job.id, job.metadata.attempt_count + 1, job.retry_config.max_attempts);
                 
@@ -764,8 +2909,16 @@ impl Dispatcher {
                 let mut completed = self.completed_jobs.lock().await;
                 completed.retain(|id| id != &job.id);
                 
+                // Wait out the backoff, spread out further if its step is in a retry storm
+                let mut delay = Duration::from_millis(job.next_retry_delay_with_jitter());
+                if let Some(storm_delay) = self.retry_storms.lock().await.record_retry(job, Instant::now()) {
+                    self.stats.lock().await.storm_delayed_retries += 1;
+                    delay += storm_delay;
+                }
+                
                 // Re-enqueue the job
-                queue.enqueue(job.clone())?;
+                let not_before = Utc::now() + chrono::Duration::milliseconds(delay.as_millis() as i64);
+                queue.enqueue_retry(job.clone(), not_before)?;
                 
                 log::info!("Job {} re-queued for retry", job.id);
             } else {
@@ -784,6 +2937,8 @@ impl Dispatcher {
                             (completed - started).num_milliseconds() as u64
                         })
                     }),
+                    cache_hit: None,
+                    resources: None,
                 };
                 
                 self.process_job_result(job, &step_result)?;
@@ -836,6 +2991,9 @@ impl Dispatcher {
             
             // Save the step result
             state_manager_guard.save_step_result(&run_uuid, step_result.clone())?;
+            if step_result.control() == Some(StepControl::CompleteRun) {
+                state_manager_guard.complete_run_early(&run_uuid, &step_result.step_id)?;
+            }
             
             if let Some(run) = state_manager_guard.get_run(&run_uuid)? {
                 if run.status == RunStatus::Pending {
@@ -851,30 +3009,113 @@ impl Dispatcher {
     }
 
     /// Internal method to handle job failure (sync wrapper for spawn_blocking)
+    ///
+    /// `timeout_ms` is set when the attempt was aborted for running past it,
+    /// and `usage` when the resources it used were measured. A registered
+    /// retry classifier decides instead of the job's retry rules; returns the
+    /// step it failed the job over to, if any.
     fn handle_job_failure_internal(
         state_manager: &Arc<tokio::sync::Mutex<StateManager>>, 
+        retry_classifier: &Mutex<RetryClassifier>,
         job: &mut Job, 
-        error: &str
-    ) -> Result<(), CoreError> {
+        error: &str,
+        timeout_ms: Option<u64>,
+        usage: Option<ResourceUsage>
+    ) -> Result<Option<String>, CoreError> {
         let rt = tokio::runtime::Handle::try_current()
             .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
         
         rt.block_on(async {
             log::warn!("Handling failure for job: {} - {}", job.id, error);
             
-            if job.can_retry() {
+            let decision = match crate::retry_classifier::classify(retry_classifier, job, error).await {
+                Some((request, decision)) => {
+                    let run_uuid = uuid::Uuid::parse_str(&job.run_id)
+                        .map_err(|e| CoreError::Validation(format!("Invalid run ID: {}", e)))?;
+                    let state_manager_guard = state_manager.lock().await;
+                    let checked = state_manager_guard.get_run_workflow(&job.workflow_id, &run_uuid)?
+                        .ok_or_else(|| CoreError::WorkflowNotFound(job.workflow_id.clone()))
+                        .and_then(|workflow| decision.validate(&workflow, &job.step_name));
+                    let mut detail = serde_json::json!({
+                        "request_id": request.id,
+                        "attempt": request.attempt,
+                        "error": error,
+                        "decision": decision,
+                    });
+                    let decision = match checked {
+                        Ok(()) => decision,
+                        Err(e) => {
+                            log::warn!("Ignoring retry decision for job {}: {}", job.id, e);
+                            detail["rejected"] = serde_json::json!(e.to_string());
+                            RetryDecision::Default
+                        }
+                    };
+                    if let Err(e) = state_manager_guard.record_run_event(&run_uuid, RunEvent::RETRY_CLASSIFIED, Some(&job.step_name), RunEvent::ACTOR_ENGINE, detail) {
+                        log::warn!("Failed to record retry decision for job {}: {}", job.id, e);
+                    }
+                    decision
+                }
+                None => RetryDecision::Default,
+            };
+            job.metadata.retry_delay_ms = None;
+            let retry = match &decision {
+                RetryDecision::Retry { delay_ms } => {
+                    job.metadata.retry_delay_ms = *delay_ms;
+                    job.can_retry()
+                }
+                RetryDecision::Default => job.should_retry(error),
+                RetryDecision::Fail | RetryDecision::Failover { .. } => false,
+            };
+            let failover = match decision {
+                RetryDecision::Failover { step_id } => Some(step_id),
+                _ => None,
+            };
+            
+            if retry {
                 log::info!("Retrying job: {} (attempt {}/{})", 
                     job.id, job.metadata.attempt_count + 1, job.retry_config.max_attempts);
                 
                 job.retry()?;
+                
+                if let Ok(run_uuid) = uuid::Uuid::parse_str(&job.run_id) {
+                    if let Some(timeout_ms) = timeout_ms {
+                        let detail = serde_json::json!({
+                            "attempt": job.metadata.attempt_count,
+                            "timeout_ms": timeout_ms,
+                        });
+                        if let Err(e) = state_manager.lock().await.record_run_event(&run_uuid, RunEvent::STEP_TIMED_OUT, Some(&job.step_name), RunEvent::ACTOR_ENGINE, detail) {
+                            log::warn!("Failed to record timeout event for job {}: {}", job.id, e);
+                        }
+                    }
+                    let detail = serde_json::json!({
+                        "attempt": job.metadata.attempt_count,
+                        "max_attempts": job.retry_config.max_attempts,
+                        "error": error,
+                    });
+                    let state_manager_guard = state_manager.lock().await;
+                    if let Err(e) = state_manager_guard.record_step_trace_event(&run_uuid, &job.step_name, crate::explainer::EVENT_RETRY_SCHEDULED, detail.clone()) {
+                        log::warn!("Failed to record retry trace event for job {}: {}", job.id, e);
+                    }
+                    if let Err(e) = state_manager_guard.record_run_event(&run_uuid, RunEvent::STEP_RETRIED, Some(&job.step_name), RunEvent::ACTOR_ENGINE, detail) {
+                        log::warn!("Failed to record retry event for job {}: {}", job.id, e);
+                    }
+                }
             } else {
                 log::error!("Job {} failed permanently after {} attempts", 
                     job.id, job.metadata.attempt_count);
                 
+                // A step that failed over is skipped, so the run can still succeed
+                let status = if failover.is_some() {
+                    StepStatus::Skipped
+                } else if timeout_ms.is_some() {
+                    StepStatus::TimedOut
+                } else {
+                    StepStatus::Failed
+                };
                 let step_result = StepResult {
                     step_id: job.step_name.clone(),
-                    status: StepStatus::Failed,
-                    output: None,
+                    status,
+                    output: failover.as_ref().map(|step_id| serde_json::json!({"failover_to": step_id})),
                     error: Some(error.to_string()),
                     started_at: job.metadata.started_at.unwrap_or_else(Utc::now),
                     completed_at: Some(Utc::now()),
@@ -883,6 +3124,8 @@ impl Dispatcher {
                             (completed - started).num_milliseconds() as u64
                         })
                     }),
+                    cache_hit: None,
+                    resources: usage,
                 };
                 
                 let mut state_manager_guard = state_manager.lock().await;
@@ -897,9 +3140,45 @@ impl Dispatcher {
                 Self::check_workflow_completion_internal(&mut state_manager_guard, &workflow_id, &run_uuid)?;
             }
             
-            Ok::<(), CoreError>(())
+            Ok::<Option<String>, CoreError>(failover)
         })
     }
+    
+    /// Queue the step a failed job fails over to, unless it is queued, running or finished already
+    ///
+    /// The step runs without waiting for its dependencies. Returns whether it was queued.
+    async fn queue_failover_step(
+        state_manager: &Mutex<StateManager>,
+        job_queue: &Mutex<JobQueue>,
+        running_jobs: &Mutex<HashMap<String, RunningJob>>,
+        failed: &Job,
+        step_id: &str,
+    ) -> Result<bool, CoreError> {
+        let job_id = Job::get_job_id(&failed.workflow_id, &failed.run_id, step_id);
+        if job_queue.lock().await.get_job(&job_id).is_some() || running_jobs.lock().await.contains_key(&job_id) {
+            return Ok(false);
+        }
+        
+        let run_uuid = Uuid::parse_str(&failed.run_id)?;
+        let mut job = {
+            let state_manager = state_manager.lock().await;
+            if state_manager.get_completed_steps(&run_uuid)?.iter().any(|result| result.step_id == step_id) {
+                return Ok(false);
+            }
+            let run = state_manager.get_run(&run_uuid)?
+                .ok_or_else(|| CoreError::RunNotFound(failed.run_id.clone()))?;
+            if run.status.is_terminal() {
+                return Ok(false);
+            }
+            let workflow = state_manager.get_run_workflow(&failed.workflow_id, &run_uuid)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(failed.workflow_id.clone()))?;
+            Job::from_workflow_step(&workflow, &run, step_id, run.payload.clone())?
+        }; // Lock released here
+        job.id = job_id;
+        job.dependencies.clear();
+        job_queue.lock().await.enqueue(job)?;
+        Ok(true)
+    }
 
     /// Internal method to check workflow completion (for worker threads)
     fn check_workflow_completion_internal(
@@ -907,7 +3186,14 @@ impl Dispatcher {
         workflow_id: &str, 
         run_id: &Uuid
     ) -> Result<(), CoreError> {
-        let workflow = state_manager.get_workflow(workflow_id)?
+        // Cancelled runs keep their status; late results are only recorded
+        if let Some(run) = state_manager.get_run(run_id)? {
+            if run.status.is_terminal() {
+                return Ok(());
+            }
+        }
+        
+        let workflow = state_manager.get_run_workflow(workflow_id, run_id)?
             .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?;
         
         let completed_steps = state_manager.get_completed_steps(run_id)?;
@@ -918,7 +3204,7 @@ impl Dispatcher {
         
         if all_steps_completed {
             let has_failures = completed_steps.iter().any(|result| {
-                matches!(result.status, StepStatus::Failed)
+                result.status.is_failure()
             });
             
             let final_status = if has_failures {
@@ -929,7 +3215,7 @@ impl Dispatcher {
             
             let error_message = if has_failures {
                 let failed_steps: Vec<_> = completed_steps.iter()
-                    .filter(|result| matches!(result.status, StepStatus::Failed))
+                    .filter(|result| result.status.is_failure())
                     .map(|result| format!("{}: {}", result.step_id, result.error.as_deref().unwrap_or("Unknown error")))
                     .collect();
                 Some(format!("Workflow failed: {}", failed_steps.join(", ")))
@@ -1138,4 +3424,947 @@ mod tests {
         
         println!("✅ Job error handling flow test completed successfully");
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_workflow_concurrency_limits_runs() {
+        let mut concurrency = WorkflowConcurrency::default();
+        concurrency.set_limit("limited", Some(1));
+
+        let first = Job::new("limited".to_string(), "run-1".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+        let second_step = Job::new("limited".to_string(), "run-1".to_string(), "step-2".to_string(), json!({}), JobPriority::Normal);
+        let other_run = Job::new("limited".to_string(), "run-2".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+        let unlimited = Job::new("unlimited".to_string(), "run-3".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+
+        assert!(concurrency.can_start(&first));
+        concurrency.job_started(&first);
+
+        // Jobs of an admitted run keep running, other runs wait for a slot
+        assert!(concurrency.can_start(&second_step));
+        assert!(!concurrency.can_start(&other_run));
+        assert!(concurrency.can_start(&unlimited));
+
+        // The slot is held while the run still has queued jobs
+        concurrency.job_finished("limited", "run-1", true);
+        assert!(!concurrency.can_start(&other_run));
+
+        concurrency.job_started(&second_step);
+        concurrency.job_finished("limited", "run-1", false);
+        assert_eq!(concurrency.active_run_count("limited"), 0);
+        assert!(concurrency.can_start(&other_run));
+    }
+
+    #[test]
+    fn test_runs_sharing_a_serialization_key_run_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("serialized.db").to_str().unwrap()).unwrap();
+        let workflow = WorkflowDefinition {
+            id: "keyed".to_string(),
+            name: "Keyed".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: Some("payload.customer_id".to_string()),
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        state_manager.register_workflow(workflow.clone()).unwrap();
+        let mut queue_run = |concurrency: &mut WorkflowConcurrency, payload: serde_json::Value| {
+            let run_id = state_manager.create_run("keyed", payload.clone()).unwrap();
+            let key = workflow.serialization_key(&payload).unwrap();
+            concurrency.set_serialized_runs("keyed", &key, state_manager.serialize_run("keyed", &key, &run_id).unwrap());
+            run_id
+        };
+        let job = |run_id: &Uuid| Job::new("keyed".to_string(), run_id.to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+
+        let mut concurrency = WorkflowConcurrency::default();
+        let first = queue_run(&mut concurrency, json!({"customer_id": 7}));
+        let second = queue_run(&mut concurrency, json!({"customer_id": 7}));
+        let other = queue_run(&mut concurrency, json!({"customer_id": "acme"}));
+        assert!(concurrency.can_start(&job(&first)));
+        assert!(!concurrency.can_start(&job(&second)));
+        assert!(concurrency.can_start(&job(&other)));
+
+        // After a restart the persisted queue keeps the second run behind the first
+        let mut restarted = WorkflowConcurrency::default();
+        restarted.set_serialized_runs("keyed", "7", state_manager.serialize_run("keyed", "7", &second).unwrap());
+        assert!(!restarted.can_start(&job(&second)));
+
+        // The key passes on once the first run has no jobs left
+        concurrency.job_started(&job(&first));
+        concurrency.job_finished("keyed", &first.to_string(), false);
+        assert!(concurrency.can_start(&job(&second)));
+        state_manager.cancel_run(&first, "done").unwrap();
+        assert_eq!(state_manager.serialize_run("keyed", "7", &second).unwrap(), vec![second.to_string()]);
+        assert_eq!(workflow.serialization_key(&json!({"order": 1})), None);
+    }
+
+    #[test]
+    fn test_namespace_quotas_isolate_tenants() {
+        let mut quotas = HashMap::new();
+        quotas.insert("tenant-a".to_string(), NamespaceQuota { max_workers: 1, queue_capacity: 2, rate_limit_per_sec: Some(2) });
+        let mut namespaces = NamespaceQuotas::new(quotas);
+        namespaces.set_workflow_namespace("noisy", Some("tenant-a"));
+        namespaces.set_workflow_namespace("quiet", Some("tenant-b"));
+
+        let noisy = |run: &str| Job::new("noisy".to_string(), run.to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+        let quiet = Job::new("quiet".to_string(), "run-q".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+
+        // Queue capacity only counts the namespace's own jobs
+        let mut queue = JobQueue::new();
+        queue.enqueue(noisy("run-1")).unwrap();
+        queue.enqueue(quiet.clone()).unwrap();
+        assert!(namespaces.can_enqueue("noisy", &queue).is_ok());
+        queue.enqueue(noisy("run-2")).unwrap();
+        assert!(matches!(namespaces.can_enqueue("noisy", &queue), Err(CoreError::QuotaExceeded(_))));
+        assert!(namespaces.can_enqueue("quiet", &queue).is_ok());
+
+        // A busy namespace waits for its own workers while others keep starting
+        let now = Instant::now();
+        let (first, second, third) = (noisy("run-1"), noisy("run-2"), noisy("run-3"));
+        assert!(namespaces.can_start(&first, now));
+        namespaces.job_started(&first, now);
+        assert!(!namespaces.can_start(&second, now));
+        assert!(namespaces.can_start(&quiet, now));
+        assert_eq!(namespaces.running_count("tenant-a"), 1);
+
+        // The rate limit holds within the second even when workers free up
+        namespaces.job_finished(&first.id);
+        namespaces.job_started(&second, now);
+        namespaces.job_finished(&second.id);
+        assert!(!namespaces.can_start(&third, now));
+        assert!(namespaces.can_start(&third, now + Duration::from_secs(1)));
+
+        // A job releases the namespace it started in after its workflow moves
+        namespaces.job_started(&third, now + Duration::from_secs(1));
+        namespaces.set_workflow_namespace("noisy", Some("tenant-b"));
+        namespaces.job_finished(&third.id);
+        assert_eq!(namespaces.running_count("tenant-a"), 0);
+    }
+
+    #[test]
+    fn test_lanes_keep_reserved_workers_for_interactive_runs() {
+        let mut lanes = RunLanes::new(LaneAllocation::parse_list("interactive=2:1, batch=3").unwrap());
+        lanes.set_pool_size(4);
+        let job = |lane: Option<&str>| {
+            let mut job = Job::new("wf".to_string(), "run-1".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+            if let Some(lane) = lane {
+                job.add_tag(LANE_TAG.to_string(), lane.to_string());
+            }
+            job
+        };
+
+        // The batch lane stops at its maximum, leaving the reserved worker free
+        for _ in 0..3 {
+            assert!(lanes.can_start(&job(Some("batch"))));
+            lanes.job_started(&job(Some("batch")));
+        }
+        assert!(!lanes.can_start(&job(Some("batch"))));
+        assert!(!lanes.can_start(&job(None)));
+        assert!(lanes.can_start(&job(Some("interactive"))));
+        lanes.job_started(&job(Some("interactive")));
+
+        // Beyond its reservation a lane competes for the shared workers
+        assert!(!lanes.can_start(&job(Some("interactive"))));
+        lanes.job_finished("batch");
+        assert!(lanes.can_start(&job(Some("interactive"))));
+
+        let mut queue = JobQueue::new();
+        queue.enqueue(job(None)).unwrap();
+        let status = lanes.status(&queue);
+        assert_eq!(status.iter().map(|lane| lane.lane.as_str()).collect::<Vec<_>>(), ["batch", "default", "interactive"]);
+        assert_eq!(status[0], LaneStatus { lane: "batch".to_string(), max_workers: Some(3), reserved_workers: 0, running: 2, queued: 0 });
+        assert_eq!((status[1].max_workers, status[1].queued), (None, 1));
+
+        assert!(LaneAllocation::parse_list("batch=1:2:3").is_err());
+        assert!(LaneAllocation::parse_list("bad lane=1").is_err());
+        assert!(LaneAllocation::parse_list("interactive=1:2").unwrap()["interactive"].validate().is_err());
+    }
+
+    #[test]
+    fn test_worker_tags_route_jobs_to_capable_workers() {
+        let tags = crate::config::parse_worker_tags("gpu=1, high-memory=2").unwrap();
+        assert_eq!(tags.get("high-memory"), Some(&2));
+        assert!(crate::config::parse_worker_tags("gpu").is_err());
+        assert!(crate::config::parse_worker_tags("g p u=1").is_err());
+
+        let mut routing = WorkerTagRouting::default();
+        routing.set_worker_tags("worker-0", ["gpu".to_string(), "high-memory".to_string()]);
+        routing.set_worker_tags("worker-1", ["high-memory".to_string()]);
+        let job = |tags: Option<&str>| {
+            let mut job = Job::new("wf".to_string(), "run-1".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+            if let Some(tags) = tags {
+                job.add_tag(WORKER_TAGS_TAG.to_string(), tags.to_string());
+            }
+            job
+        };
+
+        // Untagged jobs run anywhere, tagged ones only where every tag is carried
+        assert!(routing.can_run("worker-2", &job(None)));
+        assert!(routing.can_run("worker-0", &job(Some("gpu,high-memory"))));
+        assert!(!routing.can_run("worker-1", &job(Some("gpu,high-memory"))));
+        assert!(routing.can_run("worker-1", &job(Some("high-memory"))));
+        assert!(!routing.has_worker_for(&job(Some("tpu"))));
+
+        let mut dispatched = job(Some("gpu"));
+        dispatched.metadata.created_at = Utc::now() - chrono::Duration::milliseconds(500);
+        routing.job_dispatched(&dispatched, Utc::now());
+        let mut queue = JobQueue::new();
+        queue.enqueue(job(Some("tpu"))).unwrap();
+        let status = routing.status(&queue);
+        assert_eq!(status.iter().map(|tag| tag.tag.as_str()).collect::<Vec<_>>(), ["gpu", "high-memory", "tpu"]);
+        assert_eq!(status[0].workers, ["worker-0"]);
+        assert_eq!(status[0].dispatched, 1);
+        assert!(status[0].avg_queue_time_ms.unwrap() >= 500);
+        assert_eq!(status[1].workers, ["worker-0", "worker-1"]);
+        assert_eq!((status[2].queued, status[2].avg_queue_time_ms), (1, None));
+
+        // Workers can register their tags later on
+        routing.set_worker_tags("worker-2", ["tpu".to_string()]);
+        assert!(routing.can_run("worker-2", &job(Some("tpu"))));
+        assert_eq!(routing.worker_tags("worker-0"), ["gpu", "high-memory"]);
+    }
+
+    #[test]
+    fn test_resource_quotas_pause_workflows_over_budget() {
+        let mut quotas = ResourceQuotas::default();
+        quotas.set_quota("heavy", Some(ResourceQuota { cpu_time_ms: Some(1000), peak_memory_bytes: Some(1 << 30), window_secs: 60 }));
+        let heavy = Job::new("heavy".to_string(), "run-1".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+        let light = Job::new("light".to_string(), "run-2".to_string(), "step-1".to_string(), json!({}), JobPriority::Normal);
+        let usage = |cpu_time_ms: u64, peak_memory_bytes: u64| ResourceUsage { cpu_time_ms, peak_memory_bytes };
+
+        let now = Instant::now();
+        assert!(!quotas.record("heavy", usage(600, 1 << 20), now));
+        assert!(quotas.can_start(&heavy, now));
+        // The attempt spending the rest of the budget pauses the workflow, once
+        assert!(quotas.record("heavy", usage(600, 1 << 20), now));
+        assert!(!quotas.record("heavy", usage(10, 1 << 20), now));
+        assert!(!quotas.can_start(&heavy, now));
+        assert!(!quotas.record("light", usage(5000, 1 << 20), now));
+        assert!(quotas.can_start(&light, now));
+
+        let spent = quotas.usage("heavy", now);
+        assert_eq!((spent.attempts, spent.cpu_time_ms, spent.exceeded), (3, 1210, true));
+
+        // Dispatching resumes once the usage ages out of the window
+        let later = now + Duration::from_secs(60);
+        assert!(quotas.can_start(&heavy, later));
+        assert!(quotas.record("heavy", usage(1, 2 << 30), later));
+        assert!(!quotas.can_start(&heavy, later));
+
+        // Attempts measure their own thread's CPU time
+        if let Some(sample) = UsageSample::take() {
+            let started = Instant::now();
+            let mut spins = 0u64;
+            while started.elapsed() < Duration::from_millis(50) {
+                spins = std::hint::black_box(spins + 1);
+            }
+            let measured = sample.usage_since().unwrap();
+            assert!(measured.cpu_time_ms >= 20, "measured {:?}", measured);
+            assert!(measured.peak_memory_bytes > 0);
+        }
+    }
+
+    #[test]
+    fn test_retry_storm_spreads_and_caps_retries() {
+        let mut guard = RetryStormGuard::new(RetryStormConfig {
+            threshold: 3,
+            window_ms: 1000,
+            max_concurrent_retries: 1,
+            jitter_ms: 500,
+        });
+        let retry = |run: &str, step: &str| {
+            let mut job = Job::new("flaky".to_string(), run.to_string(), step.to_string(), json!({}), JobPriority::Normal);
+            job.metadata.attempt_count = 1;
+            job
+        };
+        let now = Instant::now();
+
+        // Retries below the threshold are not delayed
+        assert!(guard.record_retry(&retry("run-1", "charge"), now).is_none());
+        assert!(guard.record_retry(&retry("run-2", "charge"), now).is_none());
+        assert!(!guard.is_storming("flaky:charge", now));
+
+        let delayed = retry("run-3", "charge");
+        let delay = guard.record_retry(&delayed, now).unwrap();
+        assert!(delay <= Duration::from_millis(500));
+        assert!(guard.is_storming("flaky:charge", now));
+        assert!(guard.can_start(&delayed, now + Duration::from_millis(500)));
+
+        // During the storm only one retry of the step runs at once
+        let first = retry("run-1", "charge");
+        assert!(guard.can_start(&first, now));
+        guard.job_started(&first);
+        assert!(!guard.can_start(&retry("run-2", "charge"), now));
+
+        // First attempts and other steps are unaffected
+        assert!(guard.can_start(&Job::new("flaky".to_string(), "run-4".to_string(), "charge".to_string(), json!({}), JobPriority::Normal), now));
+        assert!(guard.can_start(&retry("run-1", "notify"), now));
+
+        guard.job_finished(&first.id);
+        assert!(guard.can_start(&retry("run-2", "charge"), now));
+
+        // The storm ends once the window passes
+        assert!(!guard.is_storming("flaky:charge", now + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn test_context_prefetch_cache_keeps_newest_and_evicts_oldest() {
+        let mut cache = ContextPrefetchCache::new(2);
+        cache.insert("job-1".to_string(), 2, "newer".to_string());
+        cache.insert("job-1".to_string(), 1, "stale".to_string());
+        cache.insert("job-2".to_string(), 0, "second".to_string());
+        cache.insert("job-3".to_string(), 0, "third".to_string());
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.take("job-1", 2), None);
+        assert_eq!(cache.take("job-3", 0).as_deref(), Some("third"));
+
+        // A step completed after the context was built, so it is refetched
+        assert_eq!(cache.take("job-2", 1), None);
+        assert!(cache.is_empty());
+        assert_eq!((cache.hits, cache.misses), (1, 2));
+    }
+
+    #[test]
+    fn test_run_cancellations_signal_running_jobs() {
+        let mut cancellations = RunCancellations::default();
+        let running = cancellations.token("run-1");
+        assert!(!running.is_cancelled());
+
+        cancellations.cancel("run-1");
+        assert!(running.is_cancelled());
+        assert!(cancellations.token("run-1").is_cancelled());
+
+        // Cancelled runs keep their token so late jobs are dropped
+        cancellations.release("run-1");
+        assert!(cancellations.is_cancelled("run-1"));
+
+        cancellations.token("run-2");
+        cancellations.release("run-2");
+        assert!(!cancellations.is_cancelled("run-2"));
+        assert_eq!(cancellations.tokens.len(), 1);
+    }
+
+    #[test]
+    fn test_worker_diagnostics_persist_and_restore() {
+        let mut worker = Worker::new("worker-0".to_string());
+        for i in 0..(WORKER_RECENT_JOBS + 2) {
+            worker.start_job(format!("job-{}", i));
+            worker.finish_job(10);
+            worker.record_job(format!("job-{}", i), i % 2 == 0);
+        }
+        worker.record_panic("index out of bounds".to_string());
+
+        let diagnostics = worker.diagnostics();
+        assert_eq!(diagnostics.status, "idle");
+        assert_eq!(diagnostics.jobs_processed, WORKER_RECENT_JOBS as u64 + 2);
+        assert_eq!(diagnostics.error_count, (WORKER_RECENT_JOBS as u64 + 2) / 2);
+        assert_eq!(diagnostics.average_duration_ms, 10);
+        assert_eq!(diagnostics.recent_job_ids.len(), WORKER_RECENT_JOBS);
+        assert_eq!(diagnostics.recent_job_ids.first().map(String::as_str), Some("job-2"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let state_manager = StateManager::new(dir.path().join("workers.db").to_str().unwrap()).unwrap();
+        state_manager.save_worker_diagnostics(&diagnostics).unwrap();
+        let persisted = state_manager.get_worker_diagnostics().unwrap();
+        assert_eq!(persisted.len(), 1);
+        assert_eq!(persisted[0].last_panic.as_deref(), Some("index out of bounds"));
+
+        let restored = Worker::restore(&persisted[0]);
+        assert!(restored.is_idle());
+        assert_eq!(restored.error_count, diagnostics.error_count);
+        assert_eq!(restored.diagnostics().recent_job_ids, diagnostics.recent_job_ids);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_dependency_completion_prefetches_downstream_context() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("prefetch.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "prefetch-workflow".to_string(),
+            name: "Prefetch Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("prefetch-workflow", json!({})).unwrap().to_string();
+
+        let mut dispatcher = Dispatcher::new(WorkerPoolConfig::default(), Arc::new(Mutex::new(state_manager)));
+        dispatcher.start().await.unwrap();
+
+        let mut upstream = Job::new("prefetch-workflow".to_string(), run_id.clone(), "a".to_string(), json!({}), JobPriority::Normal);
+        upstream.id = Job::get_job_id("prefetch-workflow", &run_id, "a");
+        // The second dependency never completes, so the downstream job stays queued
+        let mut downstream = Job::new("prefetch-workflow".to_string(), run_id.clone(), "b".to_string(), json!({}), JobPriority::Normal);
+        downstream.dependencies = vec![upstream.id.clone(), Job::get_job_id("prefetch-workflow", &run_id, "missing")];
+        let downstream_id = downstream.id.clone();
+
+        dispatcher.submit_job(downstream).await.unwrap();
+        dispatcher.submit_job_with_result(upstream).await.unwrap().await.unwrap();
+
+        let mut context_json = None;
+        for _ in 0..50 {
+            context_json = dispatcher.context_cache.lock().await.take(&downstream_id, 1);
+            if context_json.is_some() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let context = Context::from_json(&context_json.expect("context was not prefetched")).unwrap();
+        assert_eq!(context.step_name, "b");
+        assert!(context.get_step_result("a").is_some());
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_timed_out_jobs_are_aborted_and_retried() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("timeouts.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "timeout-workflow".to_string(),
+            name: "Timeout Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_uuid = state_manager.create_run("timeout-workflow", json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+
+        let mut dispatcher = Dispatcher::new(WorkerPoolConfig::default(), Arc::clone(&state_manager));
+        dispatcher.start().await.unwrap();
+
+        let mut job = Job::new(
+            "timeout-workflow".to_string(),
+            run_uuid.to_string(),
+            "slow".to_string(),
+            json!({"simulated_duration_ms": 10_000}),
+            JobPriority::Normal,
+        );
+        job.id = Job::get_job_id("timeout-workflow", &run_uuid.to_string(), "slow");
+        job.timeout_ms = Some(100);
+        job.retry_config = crate::job::RetryConfig { max_attempts: 2, backoff_ms: 10, max_backoff_ms: 10, jitter: false, ..Default::default() };
+        let started = Instant::now();
+        let first_attempt = dispatcher.submit_job_with_result(job).await.unwrap().await.unwrap();
+        assert!(!first_attempt.success);
+        assert!(first_attempt.error.unwrap().contains("timed out after 100ms"));
+
+        let mut results = Vec::new();
+        let mut timed_out_jobs = 0;
+        for _ in 0..100 {
+            results = state_manager.lock().await.get_completed_steps(&run_uuid).unwrap();
+            timed_out_jobs = dispatcher.get_stats().await.unwrap().timed_out_jobs;
+            if !results.is_empty() && timed_out_jobs == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(started.elapsed() < Duration::from_secs(5), "timed out steps were not aborted");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].status, StepStatus::TimedOut);
+        assert_eq!(timed_out_jobs, 2);
+
+        let events = state_manager.lock().await.get_run_timeline(&run_uuid).unwrap();
+        let event_types: Vec<&str> = events.iter()
+            .filter(|e| e.step_id.as_deref() == Some("slow"))
+            .map(|e| e.event_type.as_str())
+            .collect();
+        assert_eq!(event_types, vec![
+            RunEvent::STEP_STARTED, RunEvent::STEP_TIMED_OUT, RunEvent::STEP_RETRIED,
+            RunEvent::STEP_STARTED, RunEvent::STEP_TIMED_OUT,
+        ]);
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_injected_faults_fail_steps_deterministically() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("chaos.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "chaos-workflow".to_string(),
+            name: "Chaos Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_uuid = state_manager.create_run("chaos-workflow", json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+
+        // Rules are rejected unless failure injection is enabled
+        let disabled = Dispatcher::new(WorkerPoolConfig { chaos: ChaosConfig { enabled: false, seed: 0 }, ..WorkerPoolConfig::default() }, Arc::clone(&state_manager));
+        assert!(disabled.set_chaos_rules(ChaosRules { failure_rate: 1.0, ..Default::default() }).await.is_err());
+
+        // The same seed injects the same faults
+        let job = |step: &str| Job::new("chaos-workflow".to_string(), run_uuid.to_string(), step.to_string(), json!({}), JobPriority::Normal);
+        let rules = ChaosRules { seed: Some(42), failure_rate: 0.5, ..Default::default() };
+        let injections = || {
+            let mut injector = FaultInjector::new(&ChaosConfig { enabled: true, seed: 0 });
+            injector.set_rules(rules.clone()).unwrap();
+            (0..20).map(|i| injector.injection_for(&job(&format!("step-{}", i)))).collect::<Vec<_>>()
+        };
+        let first = injections();
+        assert_eq!(first, injections());
+        assert!(first.iter().any(|injection| injection.fault == Some(Fault::Fail)));
+        assert!(first.iter().any(|injection| injection.is_empty()));
+        assert!(ChaosRules { drop_rate: 1.5, ..Default::default() }.validate().is_err());
+
+        let config = WorkerPoolConfig { chaos: ChaosConfig { enabled: true, seed: 0 }, ..WorkerPoolConfig::default() };
+        let mut dispatcher = Dispatcher::new(config, Arc::clone(&state_manager));
+        dispatcher.start().await.unwrap();
+        dispatcher.set_chaos_rules(ChaosRules { step_id: Some("flaky".to_string()), failure_rate: 1.0, ..Default::default() }).await.unwrap();
+
+        let flaky = dispatcher.submit_job_with_result(job("flaky")).await.unwrap().await.unwrap();
+        assert!(!flaky.success);
+        assert!(flaky.error.unwrap().contains("Injected fail fault"));
+        let steady = dispatcher.submit_job_with_result(job("steady")).await.unwrap().await.unwrap();
+        assert!(steady.success);
+        assert_eq!(dispatcher.get_chaos_status().await.stats.failures, 1);
+
+        let events = state_manager.lock().await.get_run_timeline(&run_uuid).unwrap();
+        assert!(events.iter().any(|e| e.event_type == RunEvent::FAULT_INJECTED && e.step_id.as_deref() == Some("flaky")));
+        dispatcher.clear_chaos_rules().await;
+        assert!(dispatcher.get_chaos_status().await.rules.is_none());
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_retry_classifier_decisions_override_retry_config_and_can_fail_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("classifier.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: Vec<String>| crate::models::StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on,
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "classifier-workflow".to_string(),
+            name: "Classifier Workflow".to_string(),
+            description: None,
+            steps: vec![step("charge", vec![]), step("fallback", vec!["charge".to_string()])],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_uuid = state_manager.create_run("classifier-workflow", json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+
+        let config = WorkerPoolConfig { chaos: ChaosConfig { enabled: true, seed: 0 }, ..WorkerPoolConfig::default() };
+        let mut dispatcher = Dispatcher::new(config, Arc::clone(&state_manager));
+        dispatcher.start().await.unwrap();
+        dispatcher.set_chaos_rules(ChaosRules { step_id: Some("charge".to_string()), failure_rate: 1.0, ..Default::default() }).await.unwrap();
+        let (sender, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        dispatcher.set_retry_classifier(Some(Box::new(move |request| {
+            let _ = sender.send(request.clone());
+        }))).await;
+
+        let mut job = Job::new("classifier-workflow".to_string(), run_uuid.to_string(), "charge".to_string(), json!({}), JobPriority::Normal);
+        job.id = Job::get_job_id("classifier-workflow", &run_uuid.to_string(), "charge");
+        job.retry_config = crate::job::RetryConfig { max_attempts: 3, backoff_ms: 60_000, max_backoff_ms: 60_000, jitter: false, ..Default::default() };
+        dispatcher.submit_job(job).await.unwrap();
+
+        // The classifier's delay replaces the minute-long backoff
+        let first = tokio::time::timeout(Duration::from_secs(5), requests.recv()).await.unwrap().unwrap();
+        assert_eq!((first.step_id.as_str(), first.attempt, first.default_retry), ("charge", 1, true));
+        dispatcher.report_retry_decision(&first.id, RetryDecision::Retry { delay_ms: Some(10) }).await.unwrap();
+        let second = tokio::time::timeout(Duration::from_secs(5), requests.recv()).await.unwrap().unwrap();
+        assert_eq!(second.attempt, 2);
+        assert!(dispatcher.report_retry_decision(&first.id, RetryDecision::Fail).await.is_err());
+        dispatcher.report_retry_decision(&second.id, RetryDecision::Failover { step_id: "fallback".to_string() }).await.unwrap();
+
+        let mut run = None;
+        for _ in 0..100 {
+            run = state_manager.lock().await.get_run(&run_uuid).unwrap();
+            if run.as_ref().is_some_and(|run| run.status.is_terminal()) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(run.unwrap().status, RunStatus::Completed);
+        let results = state_manager.lock().await.get_completed_steps(&run_uuid).unwrap();
+        let charge = results.iter().find(|result| result.step_id == "charge").unwrap();
+        assert_eq!(charge.status, StepStatus::Skipped);
+        assert_eq!(charge.output, Some(json!({"failover_to": "fallback"})));
+        assert!(results.iter().any(|result| result.step_id == "fallback" && result.status == StepStatus::Completed));
+
+        let decisions: Vec<String> = state_manager.lock().await.get_run_timeline(&run_uuid).unwrap().iter()
+            .filter(|e| e.event_type == RunEvent::RETRY_CLASSIFIED)
+            .map(|e| e.detail["decision"]["action"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(decisions, vec!["retry", "failover"]);
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_shutdown_drains_running_jobs_and_persists_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("shutdown.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "shutdown-workflow".to_string(),
+            name: "Shutdown Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_uuid = state_manager.create_run("shutdown-workflow", json!({})).unwrap();
+        let run_id = run_uuid.to_string();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+
+        let mut dispatcher = Dispatcher::new(WorkerPoolConfig::default(), Arc::clone(&state_manager));
+        dispatcher.start().await.unwrap();
+
+        let job = |step: &str, duration_ms: u64| {
+            let mut job = Job::new("shutdown-workflow".to_string(), run_id.clone(), step.to_string(), json!({"simulated_duration_ms": duration_ms}), JobPriority::Normal);
+            job.id = Job::get_job_id("shutdown-workflow", &run_id, step);
+            job
+        };
+        let slow_id = Job::get_job_id("shutdown-workflow", &run_id, "slow");
+        let mut waiting = job("waiting", 100);
+        waiting.dependencies = vec![Job::get_job_id("shutdown-workflow", &run_id, "missing")];
+        dispatcher.submit_job(waiting).await.unwrap();
+        dispatcher.submit_job(job("quick", 300)).await.unwrap();
+        dispatcher.submit_job(job("slow", 10_000)).await.unwrap();
+        for _ in 0..100 {
+            if dispatcher.running_jobs.lock().await.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let started = Instant::now();
+        let summary = dispatcher.shutdown(Duration::from_millis(1000)).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5), "shutdown waited for the aborted job");
+        assert_eq!(summary, ShutdownSummary { drained_jobs: 1, aborted_jobs: vec![slow_id], persisted_jobs: 1 });
+
+        let state_manager = state_manager.lock().await;
+        let jobs = state_manager.get_jobs_for_run(&run_id).unwrap();
+        assert!(jobs.iter().any(|job| job.step_name == "waiting"));
+        let interrupted: Vec<Option<String>> = state_manager.get_run_timeline(&run_uuid).unwrap().into_iter()
+            .filter(|e| e.event_type == RunEvent::STEP_INTERRUPTED)
+            .map(|e| e.step_id)
+            .collect();
+        assert_eq!(interrupted, vec![Some("slow".to_string())]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_cancelling_a_running_job_stops_it_without_recording_the_attempt() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("cancel_job.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "cancel-workflow".to_string(),
+            name: "Cancel Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("cancel-workflow", json!({})).unwrap();
+
+        let mut dispatcher = Dispatcher::new(WorkerPoolConfig::default(), Arc::new(Mutex::new(state_manager)));
+        dispatcher.start().await.unwrap();
+        let job = Job::new("cancel-workflow".to_string(), run_id.to_string(), "slow".to_string(), json!({"simulated_duration_ms": 10_000}), JobPriority::Normal);
+        let job_id = job.id.clone();
+        let receiver = dispatcher.submit_job_with_result(job).await.unwrap();
+        for _ in 0..50 {
+            if dispatcher.running_jobs.lock().await.contains_key(&job_id) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let started = Instant::now();
+        assert!(dispatcher.cancel_job(&job_id).await.unwrap());
+        assert!(receiver.await.is_err());
+        for _ in 0..50 {
+            if dispatcher.get_stats().await.unwrap().total_jobs_processed == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(dispatcher.get_stats().await.unwrap().total_jobs_processed, 1);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(dispatcher.state_manager.lock().await.get_completed_steps(&run_id).unwrap().is_empty());
+        assert!(!dispatcher.cancel_job(&job_id).await.unwrap());
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_queue_inspection_lists_running_and_queued_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_manager = StateManager::new(dir.path().join("inspect.db").to_str().unwrap()).unwrap();
+        let mut dispatcher = Dispatcher::new(WorkerPoolConfig::default(), Arc::new(Mutex::new(state_manager)));
+        dispatcher.start().await.unwrap();
+        
+        let job = |workflow_id: &str, step: &str, payload: serde_json::Value, priority: JobPriority| {
+            Job::new(workflow_id.to_string(), "inspect-run".to_string(), step.to_string(), payload, priority)
+        };
+        let slow = job("inspect", "slow", json!({"simulated_duration_ms": 2_000}), JobPriority::Normal);
+        let slow_id = slow.id.clone();
+        dispatcher.submit_job(slow).await.unwrap();
+        for _ in 0..50 {
+            if dispatcher.running_jobs.lock().await.contains_key(&slow_id) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        dispatcher.set_paused(true).await;
+        
+        let low = job("inspect", "low", json!({}), JobPriority::Low);
+        let high = job("inspect", "high", json!({}), JobPriority::High);
+        let other = job("other", "other", json!({}), JobPriority::Critical);
+        let (low_id, high_id) = (low.id.clone(), high.id.clone());
+        for job in [low, high, other] {
+            dispatcher.submit_job(job).await.unwrap();
+        }
+        let mut retry = job("inspect", "retry", json!({}), JobPriority::Normal);
+        retry.state = JobState::Retrying;
+        let not_before = Utc::now() + chrono::Duration::seconds(60);
+        dispatcher.job_queue.lock().await.enqueue_retry(retry, not_before).unwrap();
+        
+        let jobs = dispatcher.list_jobs(None, Some("inspect"), 10).await;
+        let steps: Vec<&str> = jobs.iter().map(|j| j.job.step_name.as_str()).collect();
+        assert_eq!(steps, vec!["slow", "high", "retry", "low"]);
+        assert!(jobs[0].worker_id.is_some());
+        assert_eq!(jobs[0].job.state, JobState::Running);
+        assert_eq!(jobs[2].next_retry_at, Some(not_before));
+        assert_eq!(dispatcher.list_jobs(Some(&JobState::Pending), None, 10).await.len(), 3);
+        assert_eq!(dispatcher.list_jobs(None, None, 2).await.len(), 2);
+        
+        let running = dispatcher.get_job(&slow_id).await.unwrap();
+        let running_json = serde_json::to_value(&running).unwrap();
+        assert_eq!(running_json["state"], "Running");
+        assert!(running_json["worker_id"].is_string());
+        assert!(running_json["context"].get("serialized_context").is_none());
+        assert_eq!(dispatcher.get_job(&high_id).await.unwrap().effective_priority, Some(JobPriority::High));
+        assert!(dispatcher.get_job(&low_id).await.unwrap().worker_id.is_none());
+        assert!(dispatcher.get_job("missing").await.is_none());
+        assert_eq!(JobState::parse("retrying"), Some(JobState::Retrying));
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_heartbeats_keep_slow_steps_running_and_expose_hung_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("heartbeats.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "heartbeat-workflow".to_string(),
+            name: "Heartbeat Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_uuid = state_manager.create_run("heartbeat-workflow", json!({})).unwrap();
+        let run_id = run_uuid.to_string();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+
+        let config = WorkerPoolConfig { heartbeat_timeout_ms: 200, ..WorkerPoolConfig::default() };
+        let mut dispatcher = Dispatcher::new(config, Arc::clone(&state_manager));
+        dispatcher.start().await.unwrap();
+        assert!(dispatcher.report_step_heartbeat(&run_id, "slow").await.is_err());
+
+        let job = |step: &str, duration_ms: u64, timeout_ms: u64| {
+            let mut job = Job::new("heartbeat-workflow".to_string(), run_id.clone(), step.to_string(), json!({"simulated_duration_ms": duration_ms}), JobPriority::Normal);
+            job.id = Job::get_job_id("heartbeat-workflow", &run_id, step);
+            job.timeout_ms = Some(timeout_ms);
+            job.retry_config = crate::job::RetryConfig { max_attempts: 1, backoff_ms: 10, max_backoff_ms: 10, jitter: false, ..Default::default() };
+            job
+        };
+
+        // Runs well past its timeout, but keeps sending heartbeats
+        let mut slow = dispatcher.submit_job_with_result(job("slow", 600, 150)).await.unwrap();
+        let result = loop {
+            match tokio::time::timeout(Duration::from_millis(50), &mut slow).await {
+                Ok(result) => break result.unwrap(),
+                Err(_) => {
+                    let _ = dispatcher.report_step_heartbeat(&run_id, "slow").await;
+                }
+            }
+        };
+        assert!(result.success, "{:?}", result.error);
+
+        // Sends one heartbeat, then hangs long before its own timeout is reached
+        let started = Instant::now();
+        let hung = dispatcher.submit_job_with_result(job("hung", 10_000, 10_000)).await.unwrap();
+        while dispatcher.report_step_heartbeat(&run_id, "hung").await.is_err() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(dispatcher.get_job(&Job::get_job_id("heartbeat-workflow", &run_id, "hung")).await.unwrap().last_heartbeat_at.is_some());
+        let result = hung.await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("sent no heartbeat for 200ms"));
+        assert!(started.elapsed() < Duration::from_secs(5), "hung step was not detected by its heartbeat");
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[test]
+    fn test_job_context_carries_attempts_and_trigger_of_the_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("execution.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "execution-workflow".to_string(),
+            name: "Execution Workflow".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_uuid = state_manager.create_run("execution-workflow", json!({})).unwrap();
+        let run_id = run_uuid.to_string();
+        let mut job = Job::new("execution-workflow".to_string(), run_id.clone(), "charge".to_string(), json!({}), JobPriority::Normal);
+
+        // A run created without a trigger, on its first attempt
+        let (_, context_json) = Dispatcher::build_job_context(&state_manager, &job).unwrap();
+        let execution = Context::from_json(&context_json).unwrap().execution;
+        assert_eq!(execution.attempt, 1);
+        assert!(!execution.is_retry && execution.trigger.is_none());
+        assert_eq!(execution.queued_at, Some(job.metadata.created_at));
+        assert_eq!(execution.engine_version, env!("CARGO_PKG_VERSION"));
+
+        let trigger = crate::models::RunTrigger::new("webhook", Some("/orders"));
+        state_manager.record_run_trigger(&run_uuid, &trigger).unwrap();
+        let detail = json!({"attempt": 1, "max_attempts": 3, "error": "card declined"});
+        state_manager.record_run_event(&run_uuid, RunEvent::STEP_RETRIED, Some("charge"), RunEvent::ACTOR_ENGINE, detail).unwrap();
+        state_manager.record_run_event(&run_uuid, RunEvent::STEP_RETRIED, Some("other"), RunEvent::ACTOR_ENGINE, json!({"attempt": 1})).unwrap();
+        job.start().unwrap();
+
+        let (_, context_json) = Dispatcher::build_job_context(&state_manager, &job).unwrap();
+        let execution = Context::from_json(&context_json).unwrap().execution;
+        assert_eq!(execution.attempt, 2);
+        assert!(execution.is_retry);
+        assert_eq!(execution.retry_history.len(), 1);
+        assert_eq!(execution.retry_history[0].error.as_deref(), Some("card declined"));
+        assert_eq!(execution.trigger, Some(trigger));
+        assert_eq!(Some(execution.dispatched_at), job.metadata.started_at);
+    }
+}
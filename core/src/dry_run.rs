@@ -0,0 +1,310 @@
+//! Dry runs of workflows
+//!
+//! A dry run walks a workflow's steps for a payload without dispatching any
+//! job: control flow conditions and `skip_if` expressions are evaluated
+//! against an ephemeral run that is never stored, and the report lists the
+//! steps that would execute, in order, and why the others would be skipped.
+//!
+//! Conditions are evaluated against the payload alone. Step outputs are not
+//! known without executing the steps, so conditions reading them evaluate as
+//! unmet and are reported with their error. Steps execute in stages: a step
+//! runs in the stage after its last dependency, a step without dependencies
+//! after every step defined before it, and the steps of a parallel or race
+//! group together.
+
+use std::collections::{HashMap, HashSet};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use crate::condition_evaluator::ConditionEvaluator;
+use crate::context::Context;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{ConditionType, RunStatus, StepDefinition, WorkflowDefinition, WorkflowRun};
+use crate::workflow_analysis::{self, WorkflowDiagnostic};
+
+/// Step a dry run found would execute
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DryRunStep {
+    pub step_id: String,
+    /// Stage the step would run in, from 0; steps of a stage run together
+    pub stage: usize,
+    /// Parallel or race group the step runs in
+    pub group_id: Option<String>,
+}
+
+/// Step a dry run found would be skipped
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DryRunSkip {
+    pub step_id: String,
+    pub reason: String,
+}
+
+/// Condition evaluated during a dry run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DryRunCondition {
+    pub step_id: String,
+    pub expression: String,
+    pub met: bool,
+    /// Why the expression could not be evaluated, e.g. because it reads a step output
+    pub error: Option<String>,
+}
+
+/// Result of a dry run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DryRunReport {
+    pub workflow_id: String,
+    /// ID of the ephemeral run, which is not stored
+    pub run_id: String,
+    /// Steps that would execute, in execution order
+    pub steps: Vec<DryRunStep>,
+    /// Steps that would be skipped, in definition order
+    pub skipped: Vec<DryRunSkip>,
+    /// Conditions in the order they were evaluated
+    pub conditions: Vec<DryRunCondition>,
+    /// Warnings of the workflow analysis
+    pub diagnostics: Vec<WorkflowDiagnostic>,
+}
+
+/// If block open at a step, in definition order
+struct OpenBlock {
+    block_id: String,
+    /// Whether a branch of the block was taken
+    taken: bool,
+    /// Whether the current branch is taken
+    active: bool,
+    /// Why the current branch is not taken
+    reason: String,
+}
+
+/// Walk a workflow for a payload without executing it
+pub fn dry_run(workflow: &WorkflowDefinition, payload: serde_json::Value) -> CoreResult<DryRunReport> {
+    workflow.validate().map_err(CoreError::InvalidWorkflow)?;
+    let diagnostics = workflow_analysis::analyze(workflow);
+    if let Some(error) = diagnostics.iter().find(|diagnostic| diagnostic.is_error()) {
+        return Err(CoreError::InvalidWorkflow(error.message.clone()));
+    }
+
+    let run = WorkflowRun {
+        id: Uuid::new_v4(),
+        workflow_id: workflow.id.clone(),
+        status: RunStatus::Running,
+        payload: payload.clone(),
+        started_at: Utc::now(),
+        completed_at: None,
+        error: None,
+        version: 0,
+    };
+    let context = Context::new(run.id.to_string(), workflow.id.clone(), "dry-run".to_string(), payload, run.clone(), Vec::new())?;
+    let evaluator = ConditionEvaluator::new(context, Vec::new());
+    let mut conditions = Vec::new();
+    let mut evaluate = |step: &StepDefinition, expression: &str| -> CoreResult<bool> {
+        let result = evaluator.evaluate_condition(expression)?;
+        conditions.push(DryRunCondition {
+            step_id: step.id.clone(),
+            expression: expression.to_string(),
+            met: result.met,
+            error: result.error,
+        });
+        Ok(result.met)
+    };
+
+    // Control flow places steps in branches by their position in the definition
+    let mut skip_reasons: HashMap<&str, String> = HashMap::new();
+    let mut open: Vec<OpenBlock> = Vec::new();
+    for step in &workflow.steps {
+        let marker = step.condition_type.as_ref().filter(|_| step.is_control_flow_step());
+        // Markers other than if are evaluated in the scope enclosing their block
+        let scope = match marker {
+            Some(ConditionType::If) | None => open.len(),
+            Some(_) => open.len().saturating_sub(1),
+        };
+        if let Some(block) = open[..scope].iter().find(|block| !block.active) {
+            skip_reasons.insert(&step.id, block.reason.clone());
+            if marker == Some(&ConditionType::If) {
+                open.push(OpenBlock { block_id: String::new(), taken: true, active: false, reason: block.reason.clone() });
+            } else if marker == Some(&ConditionType::EndIf) {
+                open.pop();
+            }
+            continue;
+        }
+
+        match marker {
+            None => {}
+            Some(ConditionType::If) => {
+                let block_id = step.control_flow_block.clone().unwrap_or_else(|| step.id.clone());
+                let met = evaluate(step, step.condition_expression.as_deref().unwrap_or_default())?;
+                let reason = format!("the condition of step '{}' in block '{}' was not met", step.id, block_id);
+                open.push(OpenBlock { block_id, taken: met, active: met, reason });
+            }
+            Some(ConditionType::EndIf) => {
+                open.pop();
+            }
+            Some(condition_type) => {
+                let Some(block) = open.last_mut() else { continue };
+                if block.taken {
+                    block.active = false;
+                    block.reason = format!("an earlier branch of block '{}' was taken", block.block_id);
+                    skip_reasons.insert(&step.id, block.reason.clone());
+                    continue;
+                }
+                let met = match condition_type {
+                    ConditionType::Else => true,
+                    _ => evaluate(step, step.condition_expression.as_deref().unwrap_or_default())?,
+                };
+                block.taken = met;
+                block.active = met;
+                block.reason = format!("the condition of step '{}' in block '{}' was not met", step.id, block.block_id);
+            }
+        }
+    }
+
+    // Dependencies and skip_if expressions are applied once dependencies are placed
+    let index: HashMap<&str, usize> = workflow.steps.iter().enumerate().map(|(i, step)| (step.id.as_str(), i)).collect();
+    let mut stages: HashMap<&str, usize> = HashMap::new();
+    let mut group_stages: HashMap<&str, usize> = HashMap::new();
+    let mut satisfied: HashSet<&str> = HashSet::new();
+    let mut steps = Vec::new();
+    for step in dependency_order(workflow, &index) {
+        if let Some(skipped) = step.depends_on.iter().find(|dependency| !satisfied.contains(dependency.as_str())) {
+            skip_reasons.entry(&step.id).or_insert_with(|| format!("it depends on skipped step '{}'", skipped));
+        }
+        if skip_reasons.contains_key(step.id.as_str()) {
+            continue;
+        }
+        if let Some(expression) = &step.skip_if {
+            if evaluate(step, expression)? {
+                skip_reasons.insert(&step.id, format!("its skip_if expression '{}' was true", expression));
+                if step.satisfies_dependents_when_skipped() {
+                    satisfied.insert(&step.id);
+                }
+                continue;
+            }
+        }
+
+        let group_id = step.get_parallel_group_id().filter(|_| step.is_parallel() || step.is_race());
+        let stage = match group_id.and_then(|group_id| group_stages.get(group_id.as_str())) {
+            Some(stage) => *stage,
+            None => {
+                let upstream: Vec<usize> = if step.depends_on.is_empty() {
+                    stages.iter().filter(|(id, _)| index[*id] < index[step.id.as_str()]).map(|(_, stage)| stage + 1).collect()
+                } else {
+                    step.depends_on.iter().filter_map(|dependency| stages.get(dependency.as_str())).map(|stage| stage + 1).collect()
+                };
+                upstream.into_iter().max().unwrap_or(0)
+            }
+        };
+        if let Some(group_id) = group_id {
+            group_stages.entry(group_id).or_insert(stage);
+        }
+        stages.insert(&step.id, stage);
+        satisfied.insert(&step.id);
+        steps.push(DryRunStep { step_id: step.id.clone(), stage, group_id: group_id.cloned() });
+    }
+    steps.sort_by_key(|step| (step.stage, index[step.step_id.as_str()]));
+
+    Ok(DryRunReport {
+        workflow_id: workflow.id.clone(),
+        run_id: run.id.to_string(),
+        steps,
+        skipped: workflow.steps.iter()
+            .filter_map(|step| skip_reasons.get(step.id.as_str()).map(|reason| DryRunSkip { step_id: step.id.clone(), reason: reason.clone() }))
+            .collect(),
+        conditions,
+        diagnostics,
+    })
+}
+
+/// Steps ordered so each follows its dependencies, otherwise in definition order
+fn dependency_order<'a>(workflow: &'a WorkflowDefinition, index: &HashMap<&str, usize>) -> Vec<&'a StepDefinition> {
+    let mut placed: HashSet<&str> = HashSet::new();
+    let mut ordered = Vec::with_capacity(workflow.steps.len());
+    while ordered.len() < workflow.steps.len() {
+        // Analysis rejected cycles and unknown dependencies, so a step is always ready
+        let Some(step) = workflow.steps.iter()
+            .filter(|step| !placed.contains(step.id.as_str()))
+            .filter(|step| step.depends_on.iter().all(|dependency| placed.contains(dependency.as_str()) || !index.contains_key(dependency.as_str())))
+            .min_by_key(|step| index[step.id.as_str()])
+        else {
+            break;
+        };
+        placed.insert(&step.id);
+        ordered.push(step);
+    }
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(id: &str, depends_on: &[&str]) -> serde_json::Value {
+        serde_json::json!({"id": id, "name": id, "action": id, "depends_on": depends_on, "is_control_flow": false})
+    }
+
+    fn marker(id: &str, condition_type: &str, expression: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "id": id, "name": id, "action": id, "depends_on": [], "is_control_flow": true,
+            "condition_type": condition_type, "condition_expression": expression, "control_flow_block": "size",
+        })
+    }
+
+    #[test]
+    fn test_dry_runs_report_executed_and_skipped_steps_in_order() {
+        let mut notify_email = step("notify_email", &["fetch"]);
+        notify_email["parallel"] = true.into();
+        notify_email["parallel_group_id"] = "notify".into();
+        notify_email["parallel_step_count"] = 2.into();
+        let mut notify_chat = step("notify_chat", &["fetch"]);
+        notify_chat["parallel"] = true.into();
+        notify_chat["parallel_group_id"] = "notify".into();
+        notify_chat["parallel_step_count"] = 2.into();
+        let mut audit = step("audit", &["fetch"]);
+        audit["skip_if"] = "ctx.payload.test == true".into();
+        audit["skip_dependents"] = true.into();
+        let json = serde_json::json!({
+            "id": "orders",
+            "name": "Orders",
+            "steps": [
+                step("fetch", &[]),
+                marker("if_large", "If", Some("ctx.payload.amount > 100")),
+                step("review", &[]),
+                marker("elseif_medium", "ElseIf", Some("ctx.payload.amount > 10")),
+                step("approve", &[]),
+                marker("else_small", "Else", None),
+                step("ignore", &[]),
+                marker("endif_size", "EndIf", None),
+                notify_email,
+                notify_chat,
+                audit,
+                step("archive", &["audit"]),
+            ],
+            "triggers": ["Manual"],
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+        }).to_string();
+        let (workflow, _) = WorkflowDefinition::from_json(&json, true).unwrap();
+
+        let report = dry_run(&workflow, serde_json::json!({"amount": 50, "test": true})).unwrap();
+        let executed: Vec<(&str, usize)> = report.steps.iter().map(|step| (step.step_id.as_str(), step.stage)).collect();
+        assert_eq!(executed, vec![
+            ("fetch", 0), ("if_large", 1), ("notify_email", 1), ("notify_chat", 1),
+            ("elseif_medium", 2), ("approve", 3), ("endif_size", 4),
+        ]);
+        assert_eq!(report.steps[2].group_id.as_deref(), Some("notify"));
+
+        let skipped: Vec<&str> = report.skipped.iter().map(|skip| skip.step_id.as_str()).collect();
+        assert_eq!(skipped, vec!["review", "else_small", "ignore", "audit", "archive"]);
+        assert!(report.skipped[0].reason.contains("if_large"));
+        assert!(report.skipped[1].reason.contains("earlier branch"));
+        assert!(report.skipped[4].reason.contains("'audit'"));
+        let conditions: Vec<(&str, bool)> = report.conditions.iter().map(|c| (c.step_id.as_str(), c.met)).collect();
+        assert_eq!(conditions, vec![("if_large", false), ("elseif_medium", true), ("audit", true)]);
+
+        // A different payload takes another branch; nothing was stored for either run
+        let report = dry_run(&workflow, serde_json::json!({"amount": 500})).unwrap();
+        assert!(report.steps.iter().any(|step| step.step_id == "review"));
+        assert!(report.steps.iter().any(|step| step.step_id == "archive"));
+        assert!(report.skipped.iter().all(|skip| skip.step_id != "audit"));
+        assert!(report.skipped.iter().any(|skip| skip.step_id == "elseif_medium"));
+    }
+}
@@ -26,6 +26,12 @@ pub enum CoreError {
     #[error("Step not found: {0}")]
     StepNotFound(String),
 
+    #[error("Worker not found: {0}")]
+    WorkerNotFound(String),
+
+    #[error("Job not found: {0}")]
+    JobNotFound(String),
+
     #[error("Step execution failed: {0}")]
     StepExecution(String),
 
@@ -50,6 +56,36 @@ pub enum CoreError {
     #[error("Trigger not found: {0}")]
     TriggerNotFound(String),
 
+    #[error("Webhook signature verification failed: {0}")]
+    InvalidSignature(String),
+
+    #[error("Webhook authentication failed: {0}")]
+    Unauthorized(String),
+
+    #[error("Quota exceeded: {0}")]
+    QuotaExceeded(String),
+
+    #[error("Rate limit exceeded: {message}")]
+    RateLimited { message: String, retry_after_ms: u64 },
+
+    #[error("Duplicate correlation ID: {0}")]
+    DuplicateCorrelationId(String),
+
+    #[error("Incompatible event schema: {0}")]
+    IncompatibleEventSchema(String),
+
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
+
+    #[error("Context mismatch: {0}")]
+    ContextMismatch(String),
+
+    #[error("Conflicting update: {0}")]
+    Conflict(String),
+
+    #[error("Workflow disabled: {0}")]
+    WorkflowDisabled(String),
+
     #[error("Date parsing error: {0}")]
     DateParse(#[from] chrono::ParseError),
 
@@ -59,9 +95,129 @@ pub enum CoreError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Export error: {0}")]
+    Export(#[from] parquet::errors::ParquetError),
+
+    #[cfg(feature = "postgres")]
+    #[error("Postgres error: {0}")]
+    Postgres(#[from] postgres::Error),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+impl CoreError {
+    /// Machine-readable code of the error, e.g. WORKFLOW_NOT_FOUND, for clients to branch on
+    pub fn code(&self) -> &'static str {
+        match self {
+            CoreError::Database(e) => match e.sqlite_error_code() {
+                Some(rusqlite::ErrorCode::DatabaseBusy | rusqlite::ErrorCode::DatabaseLocked) => "DB_LOCKED",
+                _ => "DB_ERROR",
+            },
+            CoreError::Serialization(_) => "SERIALIZATION_FAILED",
+            CoreError::Http(_) => "HTTP_FAILED",
+            CoreError::InvalidWorkflow(_) => "INVALID_WORKFLOW",
+            CoreError::WorkflowNotFound(_) => "WORKFLOW_NOT_FOUND",
+            CoreError::RunNotFound(_) => "RUN_NOT_FOUND",
+            CoreError::StepNotFound(_) => "STEP_NOT_FOUND",
+            CoreError::WorkerNotFound(_) => "WORKER_NOT_FOUND",
+            CoreError::JobNotFound(_) => "JOB_NOT_FOUND",
+            CoreError::StepExecution(_) => "STEP_EXECUTION_FAILED",
+            CoreError::State(_) => "STATE_ERROR",
+            CoreError::InvalidState(_) | CoreError::InvalidStateTransition(_) => "INVALID_STATE_TRANSITION",
+            CoreError::Configuration(_) => "INVALID_CONFIGURATION",
+            CoreError::Validation(_) => "VALIDATION_FAILED",
+            CoreError::InvalidTrigger(_) => "INVALID_TRIGGER",
+            CoreError::TriggerNotFound(_) => "TRIGGER_NOT_FOUND",
+            CoreError::InvalidSignature(_) => "INVALID_SIGNATURE",
+            CoreError::Unauthorized(_) => "UNAUTHORIZED",
+            CoreError::QuotaExceeded(_) => "QUOTA_EXCEEDED",
+            CoreError::RateLimited { .. } => "RATE_LIMITED",
+            CoreError::DuplicateCorrelationId(_) => "DUPLICATE_CORRELATION_ID",
+            CoreError::IncompatibleEventSchema(_) => "INCOMPATIBLE_EVENT_SCHEMA",
+            CoreError::Cancelled(_) => "CANCELLED",
+            CoreError::ContextMismatch(_) => "CONTEXT_MISMATCH",
+            CoreError::Conflict(_) => "RUN_CONFLICT",
+            CoreError::WorkflowDisabled(_) => "WORKFLOW_DISABLED",
+            CoreError::DateParse(_) => "INVALID_DATE",
+            CoreError::UuidParse(_) => "INVALID_ID",
+            CoreError::Io(_) => "IO_ERROR",
+            CoreError::Export(_) => "EXPORT_FAILED",
+            #[cfg(feature = "postgres")]
+            CoreError::Postgres(e) => match e.code() {
+                Some(state) if *state == postgres::error::SqlState::LOCK_NOT_AVAILABLE
+                    || *state == postgres::error::SqlState::T_R_DEADLOCK_DETECTED => "DB_LOCKED",
+                _ => "DB_ERROR",
+            },
+            CoreError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+
+    /// Details of the error as JSON: its code, the context it was raised with and any variant fields
+    pub fn details(&self) -> serde_json::Value {
+        let context = match self {
+            CoreError::InvalidWorkflow(context)
+            | CoreError::WorkflowNotFound(context)
+            | CoreError::RunNotFound(context)
+            | CoreError::StepNotFound(context)
+            | CoreError::WorkerNotFound(context)
+            | CoreError::JobNotFound(context)
+            | CoreError::StepExecution(context)
+            | CoreError::State(context)
+            | CoreError::InvalidState(context)
+            | CoreError::InvalidStateTransition(context)
+            | CoreError::Configuration(context)
+            | CoreError::Validation(context)
+            | CoreError::InvalidTrigger(context)
+            | CoreError::TriggerNotFound(context)
+            | CoreError::InvalidSignature(context)
+            | CoreError::Unauthorized(context)
+            | CoreError::QuotaExceeded(context)
+            | CoreError::DuplicateCorrelationId(context)
+            | CoreError::IncompatibleEventSchema(context)
+            | CoreError::Cancelled(context)
+            | CoreError::ContextMismatch(context)
+            | CoreError::Conflict(context)
+            | CoreError::WorkflowDisabled(context)
+            | CoreError::Internal(context) => context.clone(),
+            CoreError::RateLimited { message, .. } => message.clone(),
+            CoreError::Database(e) => e.to_string(),
+            CoreError::Serialization(e) => e.to_string(),
+            CoreError::Http(e) => e.to_string(),
+            CoreError::DateParse(e) => e.to_string(),
+            CoreError::UuidParse(e) => e.to_string(),
+            CoreError::Io(e) => e.to_string(),
+            CoreError::Export(e) => e.to_string(),
+            #[cfg(feature = "postgres")]
+            CoreError::Postgres(e) => e.to_string(),
+        };
+        let mut details = serde_json::json!({"code": self.code(), "context": context});
+        match self {
+            CoreError::RateLimited { retry_after_ms, .. } => {
+                details["retry_after_ms"] = serde_json::json!(retry_after_ms);
+            }
+            CoreError::Database(e) => {
+                if let Some(sqlite) = e.sqlite_error() {
+                    details["sqlite_code"] = serde_json::json!(sqlite.extended_code);
+                }
+            }
+            CoreError::Serialization(e) => {
+                details["line"] = serde_json::json!(e.line());
+                details["column"] = serde_json::json!(e.column());
+            }
+            CoreError::Http(e) => {
+                details["status"] = serde_json::json!(e.status().map(|status| status.as_u16()));
+                details["url"] = serde_json::json!(e.url().map(|url| url.to_string()));
+            }
+            #[cfg(feature = "postgres")]
+            CoreError::Postgres(e) => {
+                details["sql_state"] = serde_json::json!(e.code().map(|state| state.code()));
+            }
+            _ => {}
+        }
+        details
+    }
+}
+
 /// Result type for core operations
 pub type CoreResult<T> = Result<T, CoreError>; 
\ No newline at end of file
@@ -0,0 +1,153 @@
+//! Event schema registry
+//!
+//! Event names can register a JSON Schema per version. Published payloads are
+//! checked against the schema of their version, and workflows subscribing to
+//! an event declare the versions they accept, so mismatches are reported when
+//! publishing or subscribing rather than deep inside a run.
+
+use chrono::{DateTime, Utc};
+use jsonschema::JSONSchema;
+use crate::error::{CoreError, CoreResult};
+use crate::models::EventSchema;
+
+/// Compile the JSON Schema of an event version
+pub fn compile(schema: &EventSchema) -> CoreResult<JSONSchema> {
+    JSONSchema::compile(&schema.schema).map_err(|e| CoreError::Validation(format!(
+        "Invalid JSON Schema for event '{}' version {}: {}", schema.event_name, schema.version, e
+    )))
+}
+
+/// Check a published payload against the schema of its event version
+pub fn validate_payload(schema: &EventSchema, payload: &serde_json::Value) -> CoreResult<()> {
+    let compiled = compile(schema)?;
+    let result = compiled.validate(payload);
+    if let Err(errors) = result {
+        let problems: Vec<String> = errors
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                format!("{}: {}", if path.is_empty() { "/" } else { path.as_str() }, e)
+            })
+            .collect();
+        return Err(CoreError::Validation(format!(
+            "Payload of event '{}' does not match schema version {}: {}",
+            schema.event_name, schema.version, problems.join("; ")
+        )));
+    }
+    Ok(())
+}
+
+/// Check that a subscription only accepts registered versions of its event
+///
+/// Events without any registered schema are untyped and accept any subscription.
+pub fn check_subscription(event_name: &str, versions: &[u32], registered: &[EventSchema]) -> CoreResult<()> {
+    if registered.is_empty() {
+        return Ok(());
+    }
+    let unknown: Vec<String> = versions.iter()
+        .filter(|v| !registered.iter().any(|s| s.version == **v))
+        .map(|v| v.to_string())
+        .collect();
+    if !unknown.is_empty() {
+        let known: Vec<String> = registered.iter().map(|s| s.version.to_string()).collect();
+        return Err(CoreError::IncompatibleEventSchema(format!(
+            "Event '{}' has no schema version {}, registered versions are {}",
+            event_name, unknown.join(", "), known.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Check that every subscriber of an event accepts the published version
+///
+/// `subscribers` pairs workflow IDs with the versions they accept.
+pub fn check_publication(event_name: &str, version: u32, subscribers: &[(String, Vec<u32>)]) -> CoreResult<()> {
+    let rejecting: Vec<&str> = subscribers.iter()
+        .filter(|(_, versions)| !versions.is_empty() && !versions.contains(&version))
+        .map(|(workflow_id, _)| workflow_id.as_str())
+        .collect();
+    if !rejecting.is_empty() {
+        return Err(CoreError::IncompatibleEventSchema(format!(
+            "Version {} of event '{}' is not accepted by subscribed workflows: {}",
+            version, event_name, rejecting.join(", ")
+        )));
+    }
+    Ok(())
+}
+
+/// Build the payload of a run triggered by a published event
+///
+/// Fields of an object payload are also copied to the top level, as the SDK does.
+pub fn run_payload(event_name: &str, version: Option<u32>, payload: &serde_json::Value, published_at: DateTime<Utc>) -> serde_json::Value {
+    let mut run_payload = serde_json::json!({
+        "event": {
+            "name": event_name,
+            "version": version,
+            "payload": payload,
+            "timestamp": published_at.timestamp_millis(),
+        },
+    });
+    if let (Some(fields), Some(target)) = (payload.as_object(), run_payload.as_object_mut()) {
+        for (key, value) in fields {
+            if key != "event" {
+                target.insert(key.clone(), value.clone());
+            }
+        }
+    }
+    run_payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(version: u32, schema: serde_json::Value) -> EventSchema {
+        EventSchema {
+            event_name: "order.created".to_string(),
+            version,
+            schema,
+            registered_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_event_schemas_catch_mismatches_before_runs() {
+        let v1 = schema(1, serde_json::json!({
+            "type": "object",
+            "required": ["order_id"],
+            "properties": {"order_id": {"type": "string"}, "total": {"type": "number"}},
+        }));
+        let v2 = schema(2, serde_json::json!({"type": "object", "required": ["order_id", "currency"]}));
+
+        assert!(validate_payload(&v1, &serde_json::json!({"order_id": "o-1", "total": 12.5})).is_ok());
+        let Err(CoreError::Validation(message)) = validate_payload(&v1, &serde_json::json!({"total": "12"})) else {
+            panic!("expected the payload to be rejected");
+        };
+        assert!(message.contains("/total") && message.contains("order_id"), "{}", message);
+        assert!(compile(&schema(3, serde_json::json!({"type": 12}))).is_err());
+
+        let registered = vec![v1, v2];
+        assert!(check_subscription("order.created", &[1, 2], &registered).is_ok());
+        assert!(check_subscription("order.created", &[], &registered).is_ok());
+        assert!(matches!(
+            check_subscription("order.created", &[3], &registered),
+            Err(CoreError::IncompatibleEventSchema(_))
+        ));
+        assert!(check_subscription("untyped", &[7], &[]).is_ok());
+
+        let subscribers = vec![
+            ("billing".to_string(), vec![1]),
+            ("audit".to_string(), vec![]),
+        ];
+        assert!(check_publication("order.created", 1, &subscribers).is_ok());
+        let Err(CoreError::IncompatibleEventSchema(message)) = check_publication("order.created", 2, &subscribers) else {
+            panic!("expected the version to be rejected");
+        };
+        assert!(message.contains("billing") && !message.contains("audit"));
+
+        let published_at = Utc::now();
+        let payload = run_payload("order.created", Some(1), &serde_json::json!({"order_id": "o-1"}), published_at);
+        assert_eq!(payload["order_id"], "o-1");
+        assert_eq!(payload["event"]["version"], 1);
+        assert_eq!(payload["event"]["timestamp"], published_at.timestamp_millis());
+    }
+}
@@ -0,0 +1,360 @@
+//! Run explainer
+//!
+//! This module turns the persisted state of a run (step results plus the
+//! trace events recorded by the state machine and dispatcher) into a
+//! structured and human-readable account of why each step ran, was skipped,
+//! retried or waited.
+
+use chrono::{DateTime, Utc};
+use crate::models::{
+    RunExplanation, StepDefinition, StepExplanation, StepResult, StepStatus, StepTraceEvent,
+    WorkflowDefinition, WorkflowRun,
+};
+
+/// Trace event recorded when a step condition is evaluated
+pub const EVENT_CONDITION_EVALUATED: &str = "condition_evaluated";
+/// Trace event recorded when control flow skips a step
+pub const EVENT_SKIPPED: &str = "skipped";
+/// Trace event recorded when a failed step is scheduled for another attempt
+pub const EVENT_RETRY_SCHEDULED: &str = "retry_scheduled";
+
+/// Explain a run from its workflow definition, step results and trace events
+pub fn explain_run(
+    workflow: &WorkflowDefinition,
+    run: &WorkflowRun,
+    results: &[StepResult],
+    events: &[StepTraceEvent],
+) -> RunExplanation {
+    let steps: Vec<StepExplanation> = workflow.steps.iter()
+        .map(|step| explain_step(step, run, results, events))
+        .collect();
+
+    let mut narrative = vec![describe_run(run, &steps)];
+    narrative.extend(steps.iter().map(|step| step.explanation.clone()));
+
+    RunExplanation {
+        run_id: run.id.to_string(),
+        workflow_id: run.workflow_id.clone(),
+        status: run.status.clone(),
+        duration_ms: run.get_duration_ms(),
+        steps,
+        narrative,
+    }
+}
+
+/// Build the explanation of a single step
+fn explain_step(
+    step: &StepDefinition,
+    run: &WorkflowRun,
+    results: &[StepResult],
+    events: &[StepTraceEvent],
+) -> StepExplanation {
+    let step_results: Vec<&StepResult> = results.iter().filter(|r| r.step_id == step.id).collect();
+    let step_events: Vec<StepTraceEvent> = events.iter().filter(|e| e.step_id == step.id).cloned().collect();
+    let latest = step_results.last().copied();
+
+    let blocked_by: Vec<String> = step.depends_on.iter()
+        .filter(|dep| !dependency_completed(dep, results))
+        .cloned()
+        .collect();
+
+    let skip_event = step_events.iter().rev().find(|e| e.event == EVENT_SKIPPED).cloned();
+    let outcome = match latest.map(|r| &r.status) {
+        Some(StepStatus::Completed) => "completed",
+        Some(StepStatus::Failed) => "failed",
+        Some(StepStatus::Skipped) => "skipped",
+        Some(StepStatus::TimedOut) => "timed_out",
+        Some(StepStatus::Running) => "running",
+        Some(StepStatus::Pending) => "pending",
+        None if skip_event.is_some() => "skipped",
+        None if !blocked_by.is_empty() => "blocked",
+        None if run.is_completed() => "not_reached",
+        None => "pending",
+    };
+
+    let retries = step_events.iter().filter(|e| e.event == EVENT_RETRY_SCHEDULED).count();
+    let finished_attempts = step_results.iter()
+        .filter(|r| matches!(r.status, StepStatus::Completed | StepStatus::Failed | StepStatus::TimedOut))
+        .count();
+    let attempts = finished_attempts.max(retries + usize::from(finished_attempts > 0));
+
+    let waited_ms = latest.and_then(|result| {
+        let ready_at = step.depends_on.iter()
+            .filter_map(|dep| dependency_finished_at(dep, results))
+            .max()?;
+        let waited = (result.started_at - ready_at).num_milliseconds();
+        Some(waited.max(0) as u64)
+    });
+
+    let mut explanation = StepExplanation {
+        step_id: step.id.clone(),
+        name: step.name.clone(),
+        outcome: outcome.to_string(),
+        depends_on: step.depends_on.clone(),
+        blocked_by,
+        waited_ms,
+        duration_ms: latest.and_then(|r| r.get_duration_ms()),
+        attempts,
+        error: latest.and_then(|r| r.error.clone()),
+        events: step_events,
+        explanation: String::new(),
+    };
+    explanation.explanation = describe_step(&explanation, skip_event.as_ref());
+    explanation
+}
+
+/// Whether a dependency finished successfully
+fn dependency_completed(step_id: &str, results: &[StepResult]) -> bool {
+    results.iter()
+        .rev()
+        .find(|r| r.step_id == step_id)
+        .map(|r| r.status == StepStatus::Completed)
+        .unwrap_or(false)
+}
+
+/// When a dependency last finished
+fn dependency_finished_at(step_id: &str, results: &[StepResult]) -> Option<DateTime<Utc>> {
+    results.iter()
+        .rev()
+        .find(|r| r.step_id == step_id)
+        .and_then(|r| r.completed_at)
+}
+
+/// Summarize the run in one sentence
+fn describe_run(run: &WorkflowRun, steps: &[StepExplanation]) -> String {
+    let count = |outcome: &str| steps.iter().filter(|s| s.outcome == outcome).count();
+    let mut line = format!(
+        "Run {} of workflow '{}' is {}: {} completed, {} failed, {} skipped out of {} steps",
+        run.id,
+        run.workflow_id,
+        run.status.as_str(),
+        count("completed"),
+        count("failed") + count("timed_out"),
+        count("skipped"),
+        steps.len(),
+    );
+    if let Some(duration_ms) = run.get_duration_ms() {
+        line.push_str(&format!(" in {}ms", duration_ms));
+    }
+    if let Some(error) = &run.error {
+        line.push_str(&format!(" (error: {})", error));
+    }
+    line
+}
+
+/// Describe a step in one sentence
+fn describe_step(step: &StepExplanation, skip_event: Option<&StepTraceEvent>) -> String {
+    let mut parts = Vec::new();
+
+    let headline = match step.outcome.as_str() {
+        "completed" | "failed" | "timed_out" => {
+            let mut headline = format!("Step '{}' {}", step.step_id, step.outcome.replace('_', " "));
+            if let Some(duration_ms) = step.duration_ms {
+                headline.push_str(&format!(" in {}ms", duration_ms));
+            }
+            if step.attempts > 1 {
+                headline.push_str(&format!(" after {} attempts", step.attempts));
+            }
+            headline
+        }
+        "skipped" => {
+            let reason = skip_event
+                .and_then(|e| e.detail.get("reason"))
+                .and_then(|r| r.as_str())
+                .unwrap_or("control flow did not select it");
+            format!("Step '{}' was skipped because {}", step.step_id, reason)
+        }
+        "blocked" => format!(
+            "Step '{}' did not run because its dependencies {} never completed",
+            step.step_id,
+            step.blocked_by.join(", ")
+        ),
+        "not_reached" => format!("Step '{}' was not reached before the run finished", step.step_id),
+        other => format!("Step '{}' is {}", step.step_id, other),
+    };
+    parts.push(headline);
+
+    if let Some(waited_ms) = step.waited_ms {
+        parts.push(format!(
+            "it started {}ms after its dependencies ({}) finished",
+            waited_ms,
+            step.depends_on.join(", ")
+        ));
+    } else if step.depends_on.is_empty() && matches!(step.outcome.as_str(), "completed" | "failed" | "timed_out" | "running") {
+        parts.push("it had no dependencies".to_string());
+    }
+
+    for event in &step.events {
+        if event.event == EVENT_CONDITION_EVALUATED {
+            let expression = event.detail.get("expression").and_then(|v| v.as_str()).unwrap_or("?");
+            match event.detail.get("error").and_then(|v| v.as_str()) {
+                Some(error) => parts.push(format!("condition `{}` failed to evaluate: {}", expression, error)),
+                None => {
+                    let met = event.detail.get("met").and_then(|v| v.as_bool()).unwrap_or(false);
+                    parts.push(format!("condition `{}` evaluated to {}", expression, met));
+                }
+            }
+        }
+    }
+
+    let retry_errors: Vec<&str> = step.events.iter()
+        .filter(|e| e.event == EVENT_RETRY_SCHEDULED)
+        .filter_map(|e| e.detail.get("error").and_then(|v| v.as_str()))
+        .collect();
+    if !retry_errors.is_empty() {
+        parts.push(format!("it was retried {} time(s) after: {}", retry_errors.len(), retry_errors.join("; ")));
+    }
+
+    if step.outcome == "failed" {
+        if let Some(error) = &step.error {
+            parts.push(format!("final error: {}", error));
+        }
+    }
+
+    parts.join("; ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RunStatus;
+    use chrono::Duration;
+    use uuid::Uuid;
+
+    fn step(id: &str, depends_on: &[&str]) -> StepDefinition {
+        StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        }
+    }
+
+    fn result(step_id: &str, status: StepStatus, started_at: DateTime<Utc>, duration_ms: i64) -> StepResult {
+        StepResult {
+            step_id: step_id.to_string(),
+            error: (status == StepStatus::Failed).then(|| "boom".to_string()),
+            status,
+            output: None,
+            started_at,
+            completed_at: Some(started_at + Duration::milliseconds(duration_ms)),
+            duration_ms: Some(duration_ms as u64),
+            cache_hit: None,
+            resources: None,
+        }
+    }
+
+    fn event(run_id: &str, step_id: &str, event: &str, detail: serde_json::Value) -> StepTraceEvent {
+        StepTraceEvent {
+            run_id: run_id.to_string(),
+            step_id: step_id.to_string(),
+            event: event.to_string(),
+            detail,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_explain_run_covers_waits_conditions_retries_and_blocks() {
+        let start = Utc::now();
+        let workflow = WorkflowDefinition {
+            id: "explained".to_string(),
+            name: "Explained".to_string(),
+            description: None,
+            steps: vec![
+                step("fetch", &[]),
+                step("notify", &["fetch"]),
+                step("audit", &["fetch"]),
+                step("charge", &["fetch"]),
+                step("receipt", &["charge"]),
+            ],
+            triggers: vec![],
+            created_at: start,
+            updated_at: start,
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let run = WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: workflow.id.clone(),
+            status: RunStatus::Failed,
+            payload: serde_json::json!({}),
+            started_at: start,
+            completed_at: Some(start + Duration::milliseconds(500)),
+            error: Some("charge failed".to_string()),
+            version: 0,
+        };
+        let run_id = run.id.to_string();
+        let results = vec![
+            result("fetch", StepStatus::Completed, start, 100),
+            result("notify", StepStatus::Completed, start + Duration::milliseconds(140), 20),
+            result("charge", StepStatus::Failed, start + Duration::milliseconds(300), 50),
+        ];
+        let events = vec![
+            event(&run_id, "notify", EVENT_CONDITION_EVALUATED, serde_json::json!({"expression": "payload.notify", "met": true})),
+            event(&run_id, "audit", EVENT_SKIPPED, serde_json::json!({"reason": "condition on step 'audit' was not met"})),
+            event(&run_id, "charge", EVENT_RETRY_SCHEDULED, serde_json::json!({"attempt": 1, "error": "timeout"})),
+        ];
+
+        let explanation = explain_run(&workflow, &run, &results, &events);
+        assert_eq!(explanation.steps.len(), 5);
+        assert_eq!(explanation.narrative.len(), 6);
+
+        let notify = &explanation.steps[1];
+        assert_eq!(notify.outcome, "completed");
+        assert_eq!(notify.waited_ms, Some(40));
+        assert!(notify.explanation.contains("condition `payload.notify` evaluated to true"));
+
+        let audit = &explanation.steps[2];
+        assert_eq!(audit.outcome, "skipped");
+        assert!(audit.explanation.contains("was not met"));
+
+        let charge = &explanation.steps[3];
+        assert_eq!(charge.outcome, "failed");
+        assert_eq!(charge.attempts, 2);
+        assert!(charge.explanation.contains("retried 1 time(s) after: timeout"));
+
+        let receipt = &explanation.steps[4];
+        assert_eq!(receipt.outcome, "blocked");
+        assert_eq!(receipt.blocked_by, vec!["charge".to_string()]);
+    }
+}
@@ -0,0 +1,275 @@
+//! Export of a workflow's run history as JSON Lines or CSV
+//!
+//! Where cold storage archives aged history into Parquet partitions, an
+//! export covers a workflow's runs since a given time, unfinished ones
+//! included, for loading into a data warehouse. Runs are read a page at a
+//! time, oldest page last, and each page is rendered into a chunk handed to
+//! the caller before the next is read, so the state store is only locked per
+//! page and large histories are never held in memory. Runs started after the
+//! export began are left for the next export.
+//!
+//! JSON Lines have one run per line with its step results nested. CSV has one
+//! row per step result with the run's columns repeated; runs without results
+//! get a single row with empty step columns. Outputs offloaded to the blob
+//! store are exported as their blob references.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{RunQuery, StepResult, WorkflowRun};
+use crate::state::StateManager;
+
+/// Columns of CSV exports
+const CSV_COLUMNS: [&str; 13] = [
+    "run_id", "workflow_id", "run_status", "run_started_at", "run_completed_at", "run_error",
+    "step_id", "step_status", "step_started_at", "step_completed_at", "duration_ms", "step_error", "output",
+];
+
+/// Format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = CoreError;
+
+    fn from_str(format: &str) -> CoreResult<Self> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "jsonl" | "ndjson" => Ok(ExportFormat::Jsonl),
+            "csv" => Ok(ExportFormat::Csv),
+            other => Err(CoreError::Validation(format!("Unknown export format {:?}, expected jsonl or csv", other))),
+        }
+    }
+}
+
+/// Summary of a finished export
+#[derive(Debug, Clone, Serialize)]
+pub struct RunExportSummary {
+    pub workflow_id: String,
+    pub since: Option<DateTime<Utc>>,
+    pub format: ExportFormat,
+    pub runs: usize,
+    pub steps: usize,
+    /// Bytes of the chunks handed out
+    pub bytes: usize,
+    /// File written, for exports to a path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// Run as written to JSON Lines
+#[derive(Serialize)]
+struct ExportedRun<'a> {
+    #[serde(flatten)]
+    run: &'a WorkflowRun,
+    steps: &'a [StepResult],
+}
+
+/// Renders a workflow's run history page by page
+pub struct RunExporter {
+    query: RunQuery,
+    format: ExportFormat,
+    /// Runs started later are left out
+    began_at: DateTime<Utc>,
+    /// Runs already written, as new runs shift the pages
+    exported: HashSet<Uuid>,
+    done: bool,
+    summary: RunExportSummary,
+}
+
+impl RunExporter {
+    /// Runs read per page
+    pub const PAGE_SIZE: usize = 200;
+
+    pub fn new(workflow_id: &str, since: Option<DateTime<Utc>>, format: ExportFormat) -> Self {
+        RunExporter {
+            query: RunQuery {
+                workflow_id: Some(workflow_id.to_string()),
+                since,
+                limit: Some(Self::PAGE_SIZE),
+                offset: Some(0),
+                ..Default::default()
+            },
+            format,
+            began_at: Utc::now(),
+            exported: HashSet::new(),
+            done: false,
+            summary: RunExportSummary {
+                workflow_id: workflow_id.to_string(),
+                since,
+                format,
+                runs: 0,
+                steps: 0,
+                bytes: 0,
+                path: None,
+            },
+        }
+    }
+
+    /// Read and render the next page of runs, None once every run was exported
+    ///
+    /// The first chunk of a CSV export starts with its header row.
+    pub fn next_chunk(&mut self, state_manager: &StateManager) -> CoreResult<Option<String>> {
+        if self.done {
+            return Ok(None);
+        }
+        let page = state_manager.list_runs(&self.query)?;
+        let mut chunk = String::new();
+        if self.format == ExportFormat::Csv && self.query.offset == Some(0) {
+            push_csv_row(&mut chunk, CSV_COLUMNS.iter().map(|column| column.to_string()));
+        }
+        self.query.offset = Some(page.offset + page.runs.len());
+        self.done = page.runs.len() < page.limit;
+
+        for entry in page.runs {
+            let run = entry.run;
+            if run.started_at > self.began_at || !self.exported.insert(run.id) {
+                continue;
+            }
+            let steps = state_manager.get_completed_steps(&run.id)?;
+            match self.format {
+                ExportFormat::Jsonl => {
+                    chunk.push_str(&serde_json::to_string(&ExportedRun { run: &run, steps: &steps })?);
+                    chunk.push('\n');
+                }
+                ExportFormat::Csv => push_csv_rows(&mut chunk, &run, &steps)?,
+            }
+            self.summary.runs += 1;
+            self.summary.steps += steps.len();
+        }
+        self.summary.bytes += chunk.len();
+        Ok(Some(chunk))
+    }
+
+    /// Summary of what was exported so far
+    pub fn summary(&self) -> &RunExportSummary {
+        &self.summary
+    }
+
+    /// Export every page through `sink`, returning the summary
+    pub fn export_all(mut self, state_manager: &StateManager, mut sink: impl FnMut(&str) -> CoreResult<()>) -> CoreResult<RunExportSummary> {
+        while let Some(chunk) = self.next_chunk(state_manager)? {
+            if !chunk.is_empty() {
+                sink(&chunk)?;
+            }
+        }
+        Ok(self.summary)
+    }
+}
+
+/// Append the CSV rows of a run
+fn push_csv_rows(chunk: &mut String, run: &WorkflowRun, steps: &[StepResult]) -> CoreResult<()> {
+    let run_columns = [
+        run.id.to_string(),
+        run.workflow_id.clone(),
+        run.status.as_str().to_string(),
+        run.started_at.to_rfc3339(),
+        run.completed_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+        run.error.clone().unwrap_or_default(),
+    ];
+    if steps.is_empty() {
+        push_csv_row(chunk, run_columns.into_iter().chain(std::iter::repeat_n(String::new(), 7)));
+        return Ok(());
+    }
+    for step in steps {
+        let step_columns = [
+            step.step_id.clone(),
+            step.status.as_str().to_string(),
+            step.started_at.to_rfc3339(),
+            step.completed_at.map(|at| at.to_rfc3339()).unwrap_or_default(),
+            step.duration_ms.map(|ms| ms.to_string()).unwrap_or_default(),
+            step.error.clone().unwrap_or_default(),
+            step.output.as_ref().map(serde_json::to_string).transpose()?.unwrap_or_default(),
+        ];
+        push_csv_row(chunk, run_columns.iter().cloned().chain(step_columns));
+    }
+    Ok(())
+}
+
+/// Append a CSV row, quoting fields that need it
+fn push_csv_row(chunk: &mut String, fields: impl Iterator<Item = String>) {
+    for (index, field) in fields.enumerate() {
+        if index > 0 {
+            chunk.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            chunk.push('"');
+            chunk.push_str(&field.replace('"', "\"\""));
+            chunk.push('"');
+        } else {
+            chunk.push_str(&field);
+        }
+    }
+    chunk.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{RunStatus, StepStatus};
+
+    #[test]
+    fn test_run_history_exports_as_json_lines_and_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("export.db").to_str().unwrap()).unwrap();
+        let json = serde_json::json!({
+            "id": "billing",
+            "name": "Billing",
+            "steps": [{"id": "charge", "name": "charge", "action": "charge", "depends_on": [], "is_control_flow": false}],
+            "triggers": ["Manual"],
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+        }).to_string();
+        let (workflow, _) = crate::models::WorkflowDefinition::from_json(&json, true).unwrap();
+        state_manager.register_workflow(workflow).unwrap();
+
+        let finished = state_manager.create_run("billing", serde_json::json!({"order": 1})).unwrap();
+        state_manager.save_step_result(&finished, StepResult {
+            step_id: "charge".to_string(),
+            status: StepStatus::Completed,
+            output: Some(serde_json::json!({"note": "paid, \"in full\""})),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(12),
+            cache_hit: None,
+            resources: None,
+        }).unwrap();
+        state_manager.update_run_status(&finished, RunStatus::Completed).unwrap();
+        let pending = state_manager.create_run("billing", serde_json::json!({"order": 2})).unwrap();
+
+        let mut lines = String::new();
+        let summary = RunExporter::new("billing", None, ExportFormat::Jsonl)
+            .export_all(&state_manager, |chunk| {
+                lines.push_str(chunk);
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!((summary.runs, summary.steps, summary.bytes), (2, 1, lines.len()));
+        let runs: Vec<serde_json::Value> = lines.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let exported = runs.iter().find(|run| run["id"] == finished.to_string()).unwrap();
+        assert_eq!(exported["steps"][0]["step_id"], "charge");
+        assert!(runs.iter().any(|run| run["id"] == pending.to_string() && run["steps"] == serde_json::json!([])));
+
+        // CSV quotes fields with separators and gives runs without steps one row
+        let mut csv = String::new();
+        let mut exporter = RunExporter::new("billing", None, ExportFormat::Csv);
+        while let Some(chunk) = exporter.next_chunk(&state_manager).unwrap() {
+            csv.push_str(&chunk);
+        }
+        let rows: Vec<&str> = csv.split("\r\n").filter(|row| !row.is_empty()).collect();
+        assert_eq!(rows.len(), 3);
+        assert!(rows[0].starts_with("run_id,workflow_id,run_status"));
+        assert!(csv.contains(r#""{""note"":""paid, \""in full\""""}""#));
+        assert!(rows.iter().any(|row| row.starts_with(&pending.to_string()) && row.ends_with(",,,,,,")));
+
+        assert!("parquet".parse::<ExportFormat>().is_err());
+        assert_eq!(" CSV ".parse::<ExportFormat>().unwrap(), ExportFormat::Csv);
+    }
+}
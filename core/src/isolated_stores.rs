@@ -0,0 +1,308 @@
+//! Per-workflow isolated run stores
+//!
+//! Workflows, or whole namespaces, can keep their runs, step results and
+//! offloaded outputs in a SQLite file of their own, so a runaway workflow
+//! cannot bloat or lock the database every other workflow runs on. A workflow
+//! is assigned its store when it is first registered with a matching route,
+//! and the assignment is recorded in the main database, which acts as the
+//! registry; later route changes do not move runs already stored. Workflows,
+//! triggers, jobs and node-local records stay in the main database, with a
+//! copy of each isolated workflow's definition in its store.
+//!
+//! Isolation only applies to the local SQLite store, not to a shared one.
+
+use std::collections::HashMap;
+use chrono::{DateTime, Utc};
+use crate::config::StoreRoutes;
+use crate::database::Database;
+use crate::error::{CoreError, CoreResult};
+use crate::job::Job;
+use crate::models::{DisabledWorkflow, FinishedRun, PayloadBlob, RunListEntry, RunPage, RunQuery, StepResult, StoredTrigger, WorkflowDefinition, WorkflowRun};
+use crate::record_compat::CompatibilityReport;
+use crate::storage::StorageBackend;
+
+/// State store routing runs of isolated workflows to their own SQLite files
+pub struct IsolatedStores {
+    /// Main database, holding everything but the runs of isolated workflows
+    main: Database,
+    routes: StoreRoutes,
+    /// Isolated stores by file path
+    stores: HashMap<String, Database>,
+}
+
+impl IsolatedStores {
+    /// Open the main database at `db_path` with the isolated stores of the routes and of past assignments
+    ///
+    /// Returns None when there are neither routes nor assigned workflows.
+    pub fn open(db_path: &str, routes: &StoreRoutes) -> CoreResult<Option<Self>> {
+        let main = Database::new(db_path)?;
+        let mut paths = main.get_workflow_store_paths()?;
+        paths.extend(routes.paths().map(str::to_string));
+        if paths.is_empty() {
+            return Ok(None);
+        }
+
+        let mut stores = HashMap::new();
+        for path in paths {
+            if !stores.contains_key(&path) {
+                log::info!("Opening isolated run store {}", path);
+                stores.insert(path.clone(), Database::new(&path)?);
+            }
+        }
+        Ok(Some(IsolatedStores { main, routes: routes.clone(), stores }))
+    }
+
+    /// Get the store holding a workflow's runs
+    fn for_workflow(&self, workflow_id: &str) -> CoreResult<&Database> {
+        let Some(path) = self.main.get_workflow_store(workflow_id)? else {
+            return Ok(&self.main);
+        };
+        self.stores.get(&path)
+            .ok_or_else(|| CoreError::State(format!("Isolated store {} of workflow {} is not open", path, workflow_id)))
+    }
+
+    /// Get the store holding a run, the main database when the run is not found
+    fn for_run(&self, run_id: &str) -> CoreResult<&Database> {
+        for store in self.stores.values() {
+            if store.get_run(run_id)?.is_some() {
+                return Ok(store);
+            }
+        }
+        Ok(&self.main)
+    }
+
+    /// Every store runs can be in, the main database first
+    fn all(&self) -> impl Iterator<Item = &Database> {
+        std::iter::once(&self.main).chain(self.stores.values())
+    }
+}
+
+impl StorageBackend for IsolatedStores {
+    fn name(&self) -> &'static str {
+        "sqlite (isolated)"
+    }
+
+    fn save_workflow(&self, workflow: &WorkflowDefinition) -> CoreResult<()> {
+        self.main.save_workflow(workflow)?;
+        let Some(path) = self.routes.route(&workflow.id, workflow.namespace.as_deref()) else {
+            return Ok(());
+        };
+        let assigned = self.main.assign_workflow_store(&workflow.id, path)?;
+        if assigned != path {
+            log::warn!("Workflow {} keeps its runs in {} rather than the newly routed {}", workflow.id, assigned, path);
+        }
+        // The isolated store keeps a copy of the definition its runs refer to
+        self.for_workflow(&workflow.id)?.save_workflow(workflow)
+    }
+
+    fn get_workflow(&self, id: &str) -> CoreResult<Option<WorkflowDefinition>> {
+        self.main.get_workflow(id)
+    }
+
+    fn get_all_workflows(&self) -> CoreResult<Vec<WorkflowDefinition>> {
+        self.main.get_all_workflows()
+    }
+
+    fn delete_workflow(&self, id: &str) -> CoreResult<()> {
+        self.for_workflow(id)?.delete_workflow(id)?;
+        self.main.delete_workflow(id)
+    }
+
+    fn set_workflow_disabled(&self, workflow_id: &str, disabled: Option<&DisabledWorkflow>) -> CoreResult<bool> {
+        self.main.set_workflow_disabled(workflow_id, disabled)
+    }
+
+    fn get_disabled_workflow(&self, workflow_id: &str) -> CoreResult<Option<DisabledWorkflow>> {
+        self.main.get_disabled_workflow(workflow_id)
+    }
+
+    fn save_run(&self, run: &WorkflowRun) -> CoreResult<()> {
+        self.for_workflow(&run.workflow_id)?.save_run(run)
+    }
+
+    fn save_run_if_version(&self, run: &WorkflowRun) -> CoreResult<bool> {
+        self.for_workflow(&run.workflow_id)?.save_run_if_version(run)
+    }
+
+    fn get_run(&self, run_id: &str) -> CoreResult<Option<WorkflowRun>> {
+        for store in self.all() {
+            if let Some(run) = store.get_run(run_id)? {
+                return Ok(Some(run));
+            }
+        }
+        Ok(None)
+    }
+
+    fn get_runs_for_workflow(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowRun>> {
+        self.for_workflow(workflow_id)?.get_runs_for_workflow(workflow_id)
+    }
+
+    /// List runs of one store, or merge the newest runs of every store
+    ///
+    /// Pins and labels are kept in the main database, so they are looked up
+    /// there for runs of isolated stores, which cannot be filtered by them.
+    fn list_runs(&self, query: &RunQuery) -> CoreResult<RunPage> {
+        let stores: Vec<&Database> = match &query.workflow_id {
+            Some(workflow_id) => vec![self.for_workflow(workflow_id)?],
+            None => self.all().collect(),
+        };
+        let isolated = stores.iter().any(|store| !std::ptr::eq(*store, &self.main));
+        if isolated && query.pinned.is_some() {
+            return Err(CoreError::Validation("Runs of isolated workflows cannot be filtered by pin".to_string()));
+        }
+        if isolated && !query.labels.is_empty() {
+            return Err(CoreError::Validation("Runs of isolated workflows cannot be filtered by label".to_string()));
+        }
+
+        let limit = query.effective_limit();
+        let offset = query.offset.unwrap_or(0);
+        let store_query = RunQuery {
+            limit: Some(offset + limit),
+            offset: Some(0),
+            ..query.clone()
+        };
+        let mut total = 0;
+        let mut runs: Vec<RunListEntry> = Vec::new();
+        for store in stores {
+            let page = store.list_runs(&store_query)?;
+            total += page.total;
+            for mut entry in page.runs {
+                if !std::ptr::eq(store, &self.main) {
+                    entry.pin = self.main.get_run_pin(&entry.run.id.to_string())?;
+                    entry.labels = self.main.get_run_labels(&entry.run.id.to_string())?;
+                }
+                runs.push(entry);
+            }
+        }
+        runs.sort_by_key(|entry| std::cmp::Reverse(entry.run.started_at));
+
+        Ok(RunPage {
+            runs: runs.into_iter().skip(offset).take(limit).collect(),
+            total,
+            limit,
+            offset,
+        })
+    }
+
+    /// Runs of each workflow stay newest first: a workflow's runs in the main
+    /// database predate its assignment, so isolated stores are listed first.
+    fn get_finished_runs(&self) -> CoreResult<Vec<FinishedRun>> {
+        let mut runs = Vec::new();
+        for store in self.stores.values().chain(std::iter::once(&self.main)) {
+            runs.extend(store.get_finished_runs()?);
+        }
+        Ok(runs)
+    }
+
+    fn delete_runs(&self, run_ids: &[String]) -> CoreResult<usize> {
+        let mut deleted = 0;
+        for store in self.all() {
+            deleted += store.delete_runs(run_ids)?;
+        }
+        Ok(deleted)
+    }
+
+    fn save_step_result(&self, result: &StepResult, run_id: &str) -> CoreResult<()> {
+        self.for_run(run_id)?.save_step_result(result, run_id)
+    }
+
+    fn get_step_results(&self, run_id: &str) -> CoreResult<Vec<StepResult>> {
+        self.for_run(run_id)?.get_step_results(run_id)
+    }
+
+    /// Persist the batch atomically within each store it spans
+    fn save_step_results_batch(&self, results: &[(String, StepResult)], runs: &[WorkflowRun]) -> CoreResult<()> {
+        type Batch<'a> = (&'a Database, Vec<(String, StepResult)>, Vec<WorkflowRun>);
+        fn batch_of<'a, 'b>(batches: &'b mut Vec<Batch<'a>>, store: &'a Database) -> &'b mut Batch<'a> {
+            let index = match batches.iter().position(|(batch_store, _, _)| std::ptr::eq(*batch_store, store)) {
+                Some(index) => index,
+                None => {
+                    batches.push((store, Vec::new(), Vec::new()));
+                    batches.len() - 1
+                }
+            };
+            &mut batches[index]
+        }
+
+        let mut batches = Vec::new();
+        let mut run_stores = HashMap::new();
+        for run in runs {
+            let store = self.for_workflow(&run.workflow_id)?;
+            run_stores.insert(run.id.to_string(), store);
+            batch_of(&mut batches, store).2.push(run.clone());
+        }
+        for (run_id, result) in results {
+            let store = match run_stores.get(run_id) {
+                Some(store) => *store,
+                None => self.for_run(run_id)?,
+            };
+            batch_of(&mut batches, store).1.push((run_id.clone(), result.clone()));
+        }
+
+        for (store, results, runs) in batches {
+            store.save_step_results_batch(&results, &runs)?;
+        }
+        Ok(())
+    }
+
+    fn save_payload_blob(&self, run_id: &str, blob: &PayloadBlob) -> CoreResult<()> {
+        self.for_run(run_id)?.save_payload_blob(blob)
+    }
+
+    fn get_payload_blob(&self, id: &str) -> CoreResult<Option<String>> {
+        for store in self.all() {
+            if let Some(data) = store.get_payload_blob(id)? {
+                return Ok(Some(data));
+            }
+        }
+        Ok(None)
+    }
+
+    fn save_trigger(&self, trigger: &StoredTrigger) -> CoreResult<()> {
+        self.main.save_trigger(trigger)
+    }
+
+    fn get_triggers(&self, workflow_id: &str) -> CoreResult<Vec<StoredTrigger>> {
+        self.main.get_triggers(workflow_id)
+    }
+
+    fn delete_triggers(&self, workflow_id: &str) -> CoreResult<usize> {
+        self.main.delete_triggers(workflow_id)
+    }
+
+    fn save_job(&self, job: &Job) -> CoreResult<()> {
+        self.main.save_job(job)
+    }
+
+    fn get_job(&self, job_id: &str) -> CoreResult<Option<Job>> {
+        self.main.get_job(job_id)
+    }
+
+    fn get_jobs_for_run(&self, run_id: &str) -> CoreResult<Vec<Job>> {
+        self.main.get_jobs_for_run(run_id)
+    }
+
+    fn delete_job(&self, job_id: &str) -> CoreResult<()> {
+        self.main.delete_job(job_id)
+    }
+
+    fn claim_job(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>, now: &DateTime<Utc>) -> CoreResult<bool> {
+        self.main.claim_job(job_id, owner, expires_at, now)
+    }
+
+    fn renew_job_lease(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>) -> CoreResult<bool> {
+        self.main.renew_job_lease(job_id, owner, expires_at)
+    }
+
+    fn release_job(&self, job_id: &str, owner: &str) -> CoreResult<()> {
+        self.main.release_job(job_id, owner)
+    }
+
+    fn get_claimable_jobs(&self, now: &DateTime<Utc>, limit: usize) -> CoreResult<Vec<Job>> {
+        self.main.get_claimable_jobs(now, limit)
+    }
+
+    fn verify_record_compatibility(&self) -> CoreResult<CompatibilityReport> {
+        self.main.verify_record_compatibility()
+    }
+}
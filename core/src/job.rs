@@ -1,10 +1,10 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
 use crate::error::CoreError;
-use crate::models::{WorkflowDefinition, WorkflowRun, StepResult, StepDefinition};
+use crate::models::{WorkflowDefinition, WorkflowRun, StepResult, StepDefinition, BackoffStrategy, RetryRules};
 
 /// Job states for tracking execution progress
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -17,15 +17,46 @@ pub enum JobState {
     Retrying,
 }
 
+impl JobState {
+    /// Parse a state name case-insensitively (e.g. "retrying" or "Retrying")
+    pub fn parse(value: &str) -> Option<JobState> {
+        match value.to_ascii_lowercase().as_str() {
+            "pending" => Some(JobState::Pending),
+            "running" => Some(JobState::Running),
+            "completed" => Some(JobState::Completed),
+            "failed" => Some(JobState::Failed),
+            "cancelled" => Some(JobState::Cancelled),
+            "retrying" => Some(JobState::Retrying),
+            _ => None,
+        }
+    }
+}
+
 /// Job priority levels
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, PartialOrd, Ord, Eq)]
 pub enum JobPriority {
+    #[serde(alias = "low")]
     Low = 1,
+    #[serde(alias = "normal")]
     Normal = 2,
+    #[serde(alias = "high")]
     High = 3,
+    #[serde(alias = "critical")]
     Critical = 4,
 }
 
+impl JobPriority {
+    /// The priority raised by a number of levels, capped at Critical
+    pub fn boosted(self, levels: u64) -> Self {
+        match (self as u64).saturating_add(levels) {
+            1 => JobPriority::Low,
+            2 => JobPriority::Normal,
+            3 => JobPriority::High,
+            _ => JobPriority::Critical,
+        }
+    }
+}
+
 /// Retry configuration for jobs
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetryConfig {
@@ -33,6 +64,10 @@ pub struct RetryConfig {
     pub backoff_ms: u64,
     pub max_backoff_ms: u64,
     pub jitter: bool,
+    #[serde(default)]
+    pub strategy: BackoffStrategy,
+    #[serde(default)]
+    pub rules: RetryRules,
 }
 
 impl Default for RetryConfig {
@@ -44,6 +79,8 @@ impl Default for RetryConfig {
             backoff_ms: core_config.execution.retry_backoff_ms,
             max_backoff_ms: core_config.execution.max_backoff_ms,
             jitter: core_config.execution.retry_jitter,
+            strategy: BackoffStrategy::default(),
+            rules: RetryRules::default(),
         }
     }
 }
@@ -58,6 +95,12 @@ pub struct JobMetadata {
     pub attempt_count: u32,
     pub last_error: Option<String>,
     pub tags: HashMap<String, String>,
+    /// Priority the job had when it was dequeued, including aging
+    #[serde(default)]
+    pub effective_priority: Option<JobPriority>,
+    /// Delay before the next retry chosen by the retry classifier, replacing the backoff
+    #[serde(default)]
+    pub retry_delay_ms: Option<u64>,
 }
 
 impl Default for JobMetadata {
@@ -70,6 +113,8 @@ impl Default for JobMetadata {
             attempt_count: 0,
             last_error: None,
             tags: HashMap::new(),
+            effective_priority: None,
+            retry_delay_ms: None,
         }
     }
 }
@@ -125,10 +170,8 @@ impl Job {
         step_name: &str,
         payload: serde_json::Value,
     ) -> Result<Self, CoreError> {
-        let step = workflow
-            .steps
-            .iter()
-            .find(|s| s.id == step_name)
+        let step = &workflow
+            .step_with_defaults(step_name)
             .ok_or_else(|| {
                 CoreError::InvalidWorkflow(format!("Step '{}' not found in workflow", step_name))
             })?;
@@ -136,13 +179,20 @@ impl Job {
         step.validate()
             .map_err(|e| CoreError::InvalidWorkflow(e))?;
 
+        let priority_config = crate::config::CoreConfig::default().execution.priority;
+        let base_priority = step.priority.or(workflow.priority).unwrap_or(JobPriority::Normal);
+        let priority = Self::determine_priority(step, workflow, &priority_config);
+
         let mut job = Self::new(
             workflow.id.clone(),
             run.id.to_string(),
             step_name.to_string(),
             payload,
-            Self::determine_priority(step, workflow),
+            priority,
         );
+        if priority > base_priority {
+            job.add_tag("priority_boost".to_string(), "critical_path".to_string());
+        }
 
         // Apply step-specific configuration
         Self::apply_step_configuration(&mut job, step, workflow)?;
@@ -250,12 +300,47 @@ impl Job {
     }
 
     /// Determine job priority based on step and workflow configuration
-    fn determine_priority(step: &StepDefinition, workflow: &WorkflowDefinition) -> JobPriority {
-        // In the future, this could be based on:
-        // - Step type (critical steps get higher priority)
-        // - Workflow configuration
-        // - Step tags or metadata
-        JobPriority::Normal
+    ///
+    /// The step's priority wins over the workflow's, and steps with at least
+    /// `critical_path_dependents` steps downstream of them run one level higher.
+    fn determine_priority(step: &StepDefinition, workflow: &WorkflowDefinition, config: &crate::config::PriorityConfig) -> JobPriority {
+        let priority = step.priority.or(workflow.priority).unwrap_or(JobPriority::Normal);
+        if config.critical_path_dependents > 0
+            && Self::downstream_step_count(step, workflow) >= config.critical_path_dependents
+        {
+            return priority.boosted(1);
+        }
+        priority
+    }
+
+    /// Count the steps that wait, directly or transitively, on a step
+    ///
+    /// Uses the same dependency rules as `setup_dependencies`: a step without
+    /// explicit dependencies waits on the step before it.
+    fn downstream_step_count(step: &StepDefinition, workflow: &WorkflowDefinition) -> usize {
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (index, candidate) in workflow.steps.iter().enumerate() {
+            if candidate.depends_on.is_empty() {
+                if index > 0 {
+                    dependents.entry(workflow.steps[index - 1].id.as_str()).or_default().push(&candidate.id);
+                }
+            } else {
+                for dependency in &candidate.depends_on {
+                    dependents.entry(dependency.as_str()).or_default().push(&candidate.id);
+                }
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut pending = vec![step.id.as_str()];
+        while let Some(current) = pending.pop() {
+            for dependent in dependents.get(current).into_iter().flatten() {
+                if *dependent != step.id && seen.insert(*dependent) {
+                    pending.push(dependent);
+                }
+            }
+        }
+        seen.len()
     }
 
     /// Apply step configuration to job
@@ -269,11 +354,18 @@ impl Job {
         }
 
         if let Some(retry) = &step.retry {
+            // Exponential backoff is capped at its own maximum or 10x the base backoff
+            let max_backoff_ms = match &retry.strategy {
+                BackoffStrategy::Exponential { max_ms, .. } => max_ms.unwrap_or(retry.backoff_ms * 10),
+                _ => u64::MAX,
+            };
             job.retry_config = RetryConfig {
                 max_attempts: retry.max_attempts,
                 backoff_ms: retry.backoff_ms,
-                max_backoff_ms: retry.backoff_ms * 10, // Use 10x backoff as max
+                max_backoff_ms,
                 jitter: true,
+                strategy: retry.strategy.clone(),
+                rules: retry.rules.clone(),
             };
         }
 
@@ -447,16 +539,36 @@ impl Job {
             && self.metadata.attempt_count < self.retry_config.max_attempts
     }
 
-    /// Calculate next retry delay with exponential backoff
+    /// Check if the job can be retried after failing with this error
+    pub fn should_retry(&self, error: &str) -> bool {
+        self.can_retry() && self.retry_config.rules.allows(error)
+    }
+
+    /// Calculate next retry delay with the configured backoff strategy
     pub fn next_retry_delay(&self) -> u64 {
-        let base_delay = self.retry_config.backoff_ms;
-        let attempt = self.metadata.attempt_count.saturating_sub(1);
-        let delay = base_delay * 2_u64.pow(attempt);
+        let delay = self.retry_config.strategy.delay_ms(self.retry_config.backoff_ms, self.metadata.attempt_count);
         
         // Cap at max backoff
         delay.min(self.retry_config.max_backoff_ms)
     }
 
+    /// Calculate next retry delay, randomized when jitter is enabled
+    ///
+    /// With jitter the delay is drawn between half and all of the backoff, so
+    /// retries of jobs that failed together do not all fire at once. A delay
+    /// chosen by the retry classifier is used as is.
+    pub fn next_retry_delay_with_jitter(&self) -> u64 {
+        if let Some(delay) = self.metadata.retry_delay_ms {
+            return delay;
+        }
+        let delay = self.next_retry_delay();
+        if !self.retry_config.jitter || delay < 2 {
+            return delay;
+        }
+        let half = delay / 2;
+        half + (Uuid::new_v4().as_u128() % (u128::from(delay - half) + 1)) as u64
+    }
+
     /// Validate job configuration
     pub fn validate(&self) -> Result<(), CoreError> {
         if self.id.is_empty() {
@@ -509,6 +621,10 @@ impl Job {
 #[derive(Debug, Clone)]
 pub struct JobQueue {
     pub jobs: Vec<Job>,
+    /// Retries waiting for their backoff, keyed by the time they may start
+    scheduled_retries: BTreeSet<(DateTime<Utc>, String)>,
+    /// Seconds of waiting per priority level a job gains; 0 disables aging
+    aging_secs: u64,
 }
 
 impl JobQueue {
@@ -516,7 +632,24 @@ impl JobQueue {
     pub fn new() -> Self {
         Self {
             jobs: Vec::new(),
+            scheduled_retries: BTreeSet::new(),
+            aging_secs: crate::config::CoreConfig::default().execution.priority.aging_secs,
+        }
+    }
+
+    /// Set the seconds of waiting per priority level a queued job gains
+    pub fn with_aging_secs(mut self, aging_secs: u64) -> Self {
+        self.aging_secs = aging_secs;
+        self
+    }
+
+    /// Priority of a queued job, raised one level per aging interval it has waited
+    pub fn effective_priority(&self, job: &Job, now: DateTime<Utc>) -> JobPriority {
+        if self.aging_secs == 0 {
+            return job.priority;
         }
+        let waited_secs = (now - job.metadata.created_at).num_seconds().max(0) as u64;
+        job.priority.boosted(waited_secs / self.aging_secs)
     }
 
     /// Add a job to the queue
@@ -526,28 +659,78 @@ impl JobQueue {
         Ok(())
     }
 
+    /// Add a retry to the queue that may not start before the given time
+    pub fn enqueue_retry(&mut self, job: Job, not_before: DateTime<Utc>) -> Result<(), CoreError> {
+        let job_id = job.id.clone();
+        self.enqueue(job)?;
+        self.scheduled_retries.insert((not_before, job_id));
+        Ok(())
+    }
+
+    /// Get the time the next scheduled retry may start
+    pub fn next_retry_at(&self) -> Option<DateTime<Utc>> {
+        self.scheduled_retries.first().map(|(at, _)| *at)
+    }
+
+    /// Get the time a job's scheduled retry may start, if it waits for its backoff
+    pub fn retry_not_before(&self, job_id: &str) -> Option<DateTime<Utc>> {
+        self.scheduled_retries.iter()
+            .find(|(_, scheduled)| scheduled == job_id)
+            .map(|(at, _)| *at)
+    }
+
+    /// Release scheduled retries whose time has come, returning the IDs still waiting
+    fn waiting_retries(&mut self, now: DateTime<Utc>) -> HashSet<String> {
+        while self.scheduled_retries.first().is_some_and(|(at, _)| *at <= now) {
+            self.scheduled_retries.pop_first();
+        }
+        self.scheduled_retries.iter().map(|(_, job_id)| job_id.clone()).collect()
+    }
+
+    /// Forget the scheduled retry of a job
+    fn unschedule(&mut self, job_id: &str) {
+        self.scheduled_retries.retain(|(_, scheduled)| scheduled != job_id);
+    }
+
     /// Get the next job to execute (highest priority, oldest first)
     pub fn dequeue(&mut self, completed_jobs: &[String]) -> Option<Job> {
+        self.dequeue_where(completed_jobs, |_| true)
+    }
+
+    /// Get the next ready job accepted by `can_start` (highest priority, oldest first)
+    ///
+    /// Jobs rejected by `can_start` and retries still waiting for their backoff
+    /// stay in the queue.
+    pub fn dequeue_where<F>(&mut self, completed_jobs: &[String], can_start: F) -> Option<Job>
+    where
+        F: Fn(&Job) -> bool,
+    {
+        let now = Utc::now();
+        let waiting = self.waiting_retries(now);
         let ready_jobs: Vec<_> = self.jobs
             .iter()
             .enumerate()
-            .filter(|(_, job)| job.is_ready(completed_jobs))
+            .filter(|(_, job)| !waiting.contains(&job.id) && job.is_ready(completed_jobs) && can_start(job))
             .collect();
 
         if ready_jobs.is_empty() {
             return None;
         }
 
-        // Sort by priority (highest first), then by creation time (oldest first)
-        let next_job_index = ready_jobs
+        // Sort by effective priority (highest first), then by creation time (oldest first)
+        let (next_job_index, priority) = ready_jobs
             .iter()
-            .max_by(|(_, a), (_, b)| {
-                a.priority.cmp(&b.priority)
-                    .then(a.metadata.created_at.cmp(&b.metadata.created_at))
+            .map(|(index, job)| (*index, self.effective_priority(job, now), job.metadata.created_at))
+            .max_by(|(_, a_priority, a_created), (_, b_priority, b_created)| {
+                a_priority.cmp(b_priority)
+                    .then(b_created.cmp(a_created))
             })
-            .map(|(index, _)| *index)?;
+            .map(|(index, priority, _)| (index, priority))?;
 
-        Some(self.jobs.remove(next_job_index))
+        let mut job = self.jobs.remove(next_job_index);
+        job.metadata.effective_priority = Some(priority);
+        self.unschedule(&job.id);
+        Some(job)
     }
 
     /// Get all jobs in the queue
@@ -555,6 +738,21 @@ impl JobQueue {
         &self.jobs
     }
 
+    /// Check whether any queued job belongs to a run
+    pub fn has_jobs_for_run(&self, run_id: &str) -> bool {
+        self.jobs.iter().any(|job| job.run_id == run_id)
+    }
+
+    /// Remove every queued job of a run
+    pub fn remove_jobs_for_run(&mut self, run_id: &str) -> Vec<Job> {
+        let (removed, kept): (Vec<Job>, Vec<Job>) = self.jobs.drain(..).partition(|job| job.run_id == run_id);
+        self.jobs = kept;
+        for job in &removed {
+            self.unschedule(&job.id);
+        }
+        removed
+    }
+
     /// Get job by ID
     pub fn get_job(&self, job_id: &str) -> Option<&Job> {
         self.jobs.iter().find(|job| job.id == job_id)
@@ -568,6 +766,7 @@ impl JobQueue {
     /// Remove job by ID
     pub fn remove_job(&mut self, job_id: &str) -> Option<Job> {
         let index = self.jobs.iter().position(|job| job.id == job_id)?;
+        self.unschedule(job_id);
         Some(self.jobs.remove(index))
     }
 
@@ -618,7 +817,7 @@ impl JobQueueStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{WorkflowDefinition, StepDefinition, TriggerDefinition, RunStatus, StepStatus, RetryConfig as ModelsRetryConfig};
+    use crate::models::{WorkflowDefinition, StepDefinition, TriggerDefinition, RunStatus, StepStatus, RetryConfig as ModelsRetryConfig, StepDefaults};
     use chrono::Utc;
     use uuid::Uuid;
 
@@ -636,8 +835,27 @@ mod tests {
                     retry: Some(ModelsRetryConfig {
                         max_attempts: 3,
                         backoff_ms: 1000,
+                        strategy: Default::default(),
+                        rules: Default::default(),
                     }),
                     depends_on: vec![],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 },
                 StepDefinition {
                     id: "step-2".to_string(),
@@ -646,6 +864,23 @@ mod tests {
                     timeout: Some(10000),
                     retry: None,
                     depends_on: vec!["step-1".to_string()],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 },
                 StepDefinition {
                     id: "step-3".to_string(),
@@ -655,13 +890,42 @@ mod tests {
                     retry: Some(ModelsRetryConfig {
                         max_attempts: 2,
                         backoff_ms: 2000,
+                        strategy: Default::default(),
+                        rules: Default::default(),
                     }),
                     depends_on: vec!["step-1".to_string(), "step-2".to_string()],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 },
             ],
             triggers: vec![],
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
         }
     }
 
@@ -674,6 +938,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         }
     }
 
@@ -718,6 +983,8 @@ mod tests {
             started_at: Utc::now(),
             completed_at: Some(Utc::now()),
             duration_ms: Some(100),
+            cache_hit: None,
+            resources: None,
         };
 
         assert!(job.complete(result).is_ok());
@@ -767,6 +1034,40 @@ mod tests {
         assert_eq!(queue.get_jobs().len(), 0);
     }
 
+    #[test]
+    fn test_scheduled_retries_wait_for_their_backoff() {
+        let mut queue = JobQueue::new();
+        let mut job = Job::new(
+            "workflow-1".to_string(),
+            "run-1".to_string(),
+            "step-1".to_string(),
+            serde_json::json!({}),
+            JobPriority::Normal,
+        );
+        job.retry_config.backoff_ms = 1000;
+        job.retry_config.max_backoff_ms = 4000;
+        job.retry_config.jitter = true;
+        job.metadata.attempt_count = 2;
+        for _ in 0..50 {
+            assert!((1000..=2000).contains(&job.next_retry_delay_with_jitter()));
+        }
+        job.retry_config.jitter = false;
+        job.metadata.attempt_count = 5;
+        assert_eq!(job.next_retry_delay_with_jitter(), 4000);
+
+        let job_id = job.id.clone();
+        queue.enqueue_retry(job, Utc::now() + chrono::Duration::seconds(60)).unwrap();
+        assert!(queue.next_retry_at().is_some());
+        assert!(queue.dequeue(&[]).is_none());
+
+        let mut due = queue.remove_job(&job_id).unwrap();
+        assert!(queue.next_retry_at().is_none());
+        due.id = "due".to_string();
+        queue.enqueue_retry(due, Utc::now() - chrono::Duration::seconds(1)).unwrap();
+        assert_eq!(queue.dequeue(&[]).map(|job| job.id), Some("due".to_string()));
+        assert!(queue.next_retry_at().is_none());
+    }
+
     #[test]
     fn test_job_retry() {
         let mut job = Job::new(
@@ -923,4 +1224,73 @@ mod tests {
         assert_eq!(job.get_tag("step_name").unwrap(), "Step 1");
         assert_eq!(job.get_tag("step_action").unwrap(), "test_action_1");
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_priorities_inherit_boost_the_critical_path_and_age() {
+        let workflow: WorkflowDefinition = serde_json::from_value(serde_json::json!({
+            "id": "test-workflow", "name": "Test Workflow", "priority": "low",
+            "steps": [
+                {"id": "step-1", "name": "Step 1", "action": "test_action_1", "depends_on": [], "is_control_flow": false},
+                {"id": "step-2", "name": "Step 2", "action": "test_action_2", "depends_on": [], "is_control_flow": false, "priority": "high"},
+                {"id": "step-3", "name": "Step 3", "action": "test_action_3", "depends_on": ["step-1", "step-2"], "is_control_flow": false}
+            ],
+            "triggers": [], "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"
+        })).unwrap();
+        let config = crate::config::PriorityConfig { critical_path_dependents: 2, aging_secs: 30 };
+
+        // step-1 has step-2 and step-3 downstream, step-2 only step-3
+        assert_eq!(Job::determine_priority(&workflow.steps[0], &workflow, &config), JobPriority::Normal);
+        assert_eq!(Job::determine_priority(&workflow.steps[1], &workflow, &config), JobPriority::High);
+        assert_eq!(Job::determine_priority(&workflow.steps[2], &workflow, &config), JobPriority::Low);
+        let disabled = crate::config::PriorityConfig { critical_path_dependents: 0, aging_secs: 0 };
+        assert_eq!(Job::determine_priority(&workflow.steps[0], &workflow, &disabled), JobPriority::Low);
+
+        let job = |priority, waited_secs| {
+            let mut job = Job::new("workflow-1".to_string(), "run-1".to_string(), "step-1".to_string(), serde_json::json!({}), priority);
+            job.metadata.created_at = Utc::now() - chrono::Duration::seconds(waited_secs);
+            job
+        };
+        let mut queue = JobQueue::new().with_aging_secs(30);
+        queue.enqueue(job(JobPriority::Normal, 0)).unwrap();
+        queue.enqueue(job(JobPriority::Low, 65)).unwrap();
+        queue.enqueue(job(JobPriority::High, 5)).unwrap();
+
+        // The low priority job waited two aging intervals and now ranks as High, ahead of the newer High job
+        let first = queue.dequeue(&[]).unwrap();
+        assert_eq!((first.priority, first.metadata.effective_priority), (JobPriority::Low, Some(JobPriority::High)));
+        assert_eq!(queue.dequeue(&[]).unwrap().priority, JobPriority::High);
+        assert_eq!(queue.dequeue(&[]).unwrap().metadata.effective_priority, Some(JobPriority::Normal));
+    }
+
+    #[test]
+    fn test_steps_inherit_workflow_defaults_unless_they_override_them() {
+        let json = serde_json::json!({
+            "id": "test-workflow", "name": "Test Workflow",
+            "defaults": {"timeout": 30000, "retry": {"max_attempts": 4, "backoff_ms": 500}, "priority": "high", "lane": "batch"},
+            "steps": [
+                {"id": "step-1", "name": "Step 1", "action": "test_action_1", "depends_on": [], "is_control_flow": false},
+                {"id": "step-2", "name": "Step 2", "action": "test_action_2", "depends_on": ["step-1"], "is_control_flow": false,
+                 "timeout": 1000, "retry": {"max_attempts": 1, "backoff_ms": 100}, "priority": "low"},
+                {"id": "wait", "name": "Wait", "action": "delay", "depends_on": ["step-2"], "is_control_flow": false, "delay_ms": 1000}
+            ],
+            "triggers": [], "created_at": "2024-01-01T00:00:00Z", "updated_at": "2024-01-01T00:00:00Z"
+        });
+        let workflow: WorkflowDefinition = serde_json::from_value(json.clone()).unwrap();
+        assert!(workflow.validate().is_ok());
+        let run = create_test_run();
+
+        let inherited = Job::from_workflow_step(&workflow, &run, "step-1", serde_json::json!({})).unwrap();
+        assert_eq!((inherited.timeout_ms, inherited.retry_config.max_attempts, inherited.priority), (Some(30000), 4, JobPriority::High));
+        let overridden = Job::from_workflow_step(&workflow, &run, "step-2", serde_json::json!({})).unwrap();
+        assert_eq!((overridden.timeout_ms, overridden.retry_config.max_attempts, overridden.priority), (Some(1000), 1, JobPriority::Low));
+        assert_eq!(workflow.default_lane(), Some("batch"));
+
+        // Registered definitions carry the defaults in their steps, except steps no worker runs
+        let (registered, _) = WorkflowDefinition::from_json(&json.to_string(), true).unwrap();
+        assert_eq!(registered.steps[0].timeout, Some(30000));
+        assert_eq!((registered.steps[2].timeout, registered.steps[2].retry.is_none()), (None, true));
+
+        let invalid = WorkflowDefinition { defaults: Some(StepDefaults { timeout: Some(0), ..Default::default() }), ..workflow };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -18,13 +18,44 @@ pub mod step_orchestrator;
 pub mod workflow_state_machine;
 pub mod condition_evaluator;
 pub mod config;
+pub mod cold_storage;
+pub mod retention;
+pub mod explainer;
+pub mod recovery;
+pub mod replay;
+pub mod payload_sampling;
+pub mod payload_offload;
+pub mod event_schemas;
+pub mod record_compat;
+pub mod workflow_bundle;
+pub mod storage;
+pub mod isolated_stores;
+pub mod secrets;
+pub mod run_stream;
+pub mod completion_hooks;
+pub mod workflow_analysis;
+pub mod polling;
+pub mod run_search;
+pub mod telemetry;
+pub mod chaos;
+pub mod resource_usage;
+pub mod history_export;
+pub mod output_schemas;
+pub mod logging;
+pub mod dry_run;
+pub mod retry_classifier;
+pub mod migrations;
+pub mod step_input;
+pub mod backfill;
 
 /// Core engine version
 pub const VERSION: &str = "0.1.0";
 
 /// Initialize the core engine
+///
+/// Safe to call more than once; the logger is only installed the first time.
 pub fn init() -> Result<(), Box<dyn std::error::Error>> {
-    env_logger::init();
+    logging::init(&config::CoreConfig::default().logging)?;
     
     log::info!("Node-Cronflow Core Engine v{} initialized", VERSION);
     Ok(())
@@ -67,8 +98,27 @@ mod tests {
                     retry: Some(RetryConfig {
                         max_attempts: 3,
                         backoff_ms: 1000,
+                        strategy: Default::default(),
+                        rules: Default::default(),
                     }),
                     depends_on: vec![],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 }
             ],
             triggers: vec![
@@ -76,6 +126,16 @@ mod tests {
             ],
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
         };
 
         assert!(workflow.validate().is_ok(), "Workflow should be valid");
@@ -109,6 +169,7 @@ mod tests {
             started_at: now,
             completed_at: None,
             error: None,
+            version: 0,
         };
         
         assert!(run.validate().is_ok(), "Workflow run should be valid");
@@ -141,6 +202,8 @@ mod tests {
             started_at: now,
             completed_at: Some(now),
             duration_ms: Some(1000),
+            cache_hit: None,
+            resources: None,
         };
         
         assert!(step_result.validate().is_ok(), "Step result should be valid");
@@ -170,6 +233,16 @@ mod tests {
             triggers: vec![],
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
         };
         
         let validation_result = invalid_workflow.validate();
@@ -182,6 +255,23 @@ mod tests {
             timeout: None,
             retry: None,
             depends_on: vec![],
+            for_each_items: None,
+            for_each_concurrency: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
         };
         
         let step_validation_result = invalid_step.validate();
@@ -213,18 +303,55 @@ mod tests {
                     retry: Some(RetryConfig {
                         max_attempts: 3,
                         backoff_ms: 1000,
+                        strategy: Default::default(),
+                        rules: Default::default(),
                     }),
                     depends_on: vec![],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 }
             ],
             triggers: vec![
                 TriggerDefinition::Webhook {
                     path: "/webhook/test".to_string(),
                     method: "POST".to_string(),
+                    validation: None,
+                    respond_with: None,
+                    response: None,
+                    payload_mapping: None,
+                    dedup: None,
+                    auth: None,
+                    lane: None,
+                    request_fields: None,
                 }
             ],
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
         };
         
         let register_result = state_manager.register_workflow(workflow.clone());
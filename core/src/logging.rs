@@ -0,0 +1,354 @@
+//! Engine logging
+//!
+//! The engine installs its own logger once per process, whichever of `init`
+//! and the bridge comes first; later installs leave it alone, so embedding the
+//! engine twice no longer fails. The level can be set per module, e.g.
+//! `info,core::dispatcher=debug`, and changed at runtime through the bridge.
+//! Lines are written as text or as JSON objects, to stderr or to a file that
+//! is rotated once it grows past its size limit. Secret values are masked in
+//! every line.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, RwLock};
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use crate::config::LoggingConfig;
+use crate::error::{CoreError, CoreResult};
+use crate::secrets::MaskingLogger;
+
+static LOGGER: OnceLock<MaskingLogger<EngineLogger>> = OnceLock::new();
+
+/// How log lines are written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(format: &str) -> Result<Self, String> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(format!("Unknown log format {:?}, expected text or json", other)),
+        }
+    }
+}
+
+/// Default level and per-module level overrides
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// Module path prefixes and their levels
+    modules: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Parse a filter written as `level,module=level,...`, where every part is optional
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut filter = LogFilter { default: LevelFilter::Info, modules: Vec::new() };
+        for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((module, level)) => filter.set(Some(module.trim()), parse_level(level)?),
+                None => match parse_level(directive) {
+                    Ok(level) => filter.default = level,
+                    // A bare module name logs everything from it, as with RUST_LOG
+                    Err(_) => filter.set(Some(directive), LevelFilter::Trace),
+                },
+            }
+        }
+        Ok(filter)
+    }
+
+    /// Set the level of a module, or the default level when no module is given
+    pub fn set(&mut self, module: Option<&str>, level: LevelFilter) {
+        let Some(module) = module.map(str::trim).filter(|module| !module.is_empty() && *module != "*") else {
+            self.default = level;
+            return;
+        };
+        match self.modules.iter_mut().find(|(prefix, _)| prefix == module) {
+            Some(entry) => entry.1 = level,
+            None => self.modules.push((module.to_string(), level)),
+        }
+    }
+
+    /// Level of a log target, from the most specific module matching it
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.modules.iter()
+            .filter(|(module, _)| target == module || target.strip_prefix(module.as_str()).is_some_and(|rest| rest.starts_with("::")))
+            .max_by_key(|(module, _)| module.len())
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    /// Most verbose level any target is logged at
+    pub fn max_level(&self) -> LevelFilter {
+        self.modules.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+fn parse_level(level: &str) -> Result<LevelFilter, String> {
+    level.trim().parse().map_err(|_| format!("Unknown log level {:?}", level.trim()))
+}
+
+/// Logging settings reported through the bridge
+#[derive(Debug, Clone, Serialize)]
+pub struct LoggingStatus {
+    pub level: String,
+    pub modules: Vec<ModuleLevel>,
+    pub format: LogFormat,
+    pub file: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModuleLevel {
+    pub module: String,
+    pub level: String,
+}
+
+/// Log file rotated once it grows past its size limit
+///
+/// The current file keeps its path; rotated ones get `.1` (the newest) up to
+/// `.<max_files>` appended, and older ones are deleted.
+struct LogFile {
+    path: String,
+    file: File,
+    size: u64,
+    max_bytes: u64,
+    max_files: usize,
+}
+
+impl LogFile {
+    fn open(path: &str, max_bytes: u64, max_files: usize) -> CoreResult<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|e| CoreError::Configuration(format!("Failed to open log file {}: {}", path, e)))?;
+        let size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+        Ok(LogFile { path: path.to_string(), file, size, max_bytes, max_files })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.max_bytes {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.max_files == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            let _ = std::fs::remove_file(format!("{}.{}", self.path, self.max_files));
+            for index in (1..self.max_files).rev() {
+                let rotated = format!("{}.{}", self.path, index);
+                if std::path::Path::new(&rotated).exists() {
+                    std::fs::rename(&rotated, format!("{}.{}", self.path, index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, format!("{}.1", self.path))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+enum Sink {
+    Stderr,
+    File(LogFile),
+}
+
+/// Logger of the engine, reconfigurable while installed
+pub struct EngineLogger {
+    filter: RwLock<LogFilter>,
+    format: RwLock<LogFormat>,
+    sink: Mutex<Sink>,
+    file: RwLock<Option<String>>,
+}
+
+impl EngineLogger {
+    fn new(config: &LoggingConfig) -> CoreResult<Self> {
+        let logger = EngineLogger {
+            filter: RwLock::new(LogFilter::parse(&config.filter).map_err(CoreError::Configuration)?),
+            format: RwLock::new(config.format),
+            sink: Mutex::new(Sink::Stderr),
+            file: RwLock::new(None),
+        };
+        logger.configure(config)?;
+        Ok(logger)
+    }
+
+    fn configure(&self, config: &LoggingConfig) -> CoreResult<()> {
+        let filter = LogFilter::parse(&config.filter).map_err(CoreError::Configuration)?;
+        let sink = match &config.file {
+            Some(path) => Sink::File(LogFile::open(path, config.max_file_bytes, config.max_files)?),
+            None => Sink::Stderr,
+        };
+        log::set_max_level(filter.max_level());
+        *self.filter.write().map_err(|_| lock_error())? = filter;
+        *self.format.write().map_err(|_| lock_error())? = config.format;
+        *self.sink.lock().map_err(|_| lock_error())? = sink;
+        *self.file.write().map_err(|_| lock_error())? = config.file.clone();
+        Ok(())
+    }
+
+    fn set_level(&self, module: Option<&str>, level: LevelFilter) -> CoreResult<()> {
+        let mut filter = self.filter.write().map_err(|_| lock_error())?;
+        filter.set(module, level);
+        log::set_max_level(filter.max_level());
+        Ok(())
+    }
+
+    fn status(&self) -> CoreResult<LoggingStatus> {
+        let filter = self.filter.read().map_err(|_| lock_error())?;
+        Ok(LoggingStatus {
+            level: filter.default.to_string().to_lowercase(),
+            modules: filter.modules.iter()
+                .map(|(module, level)| ModuleLevel { module: module.clone(), level: level.to_string().to_lowercase() })
+                .collect(),
+            format: *self.format.read().map_err(|_| lock_error())?,
+            file: self.file.read().map_err(|_| lock_error())?.clone(),
+        })
+    }
+
+    fn render(&self, record: &Record) -> String {
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        let format = self.format.read().map(|format| *format).unwrap_or(LogFormat::Text);
+        match format {
+            LogFormat::Text => format!("[{} {:<5} {}] {}", timestamp, record.level(), record.target(), record.args()),
+            LogFormat::Json => serde_json::json!({
+                "timestamp": timestamp,
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+                "file": record.file(),
+                "line": record.line(),
+            }).to_string(),
+        }
+    }
+}
+
+impl Log for EngineLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.read()
+            .map(|filter| metadata.level() <= filter.level_for(metadata.target()))
+            .unwrap_or(false)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = self.render(record);
+        // Lines that cannot be written have nowhere else to go
+        if let Ok(mut sink) = self.sink.lock() {
+            match &mut *sink {
+                Sink::Stderr => {
+                    let _ = writeln!(std::io::stderr(), "{}", line);
+                }
+                Sink::File(file) => {
+                    let _ = file.write_line(&line);
+                }
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut sink) = self.sink.lock() {
+            match &mut *sink {
+                Sink::Stderr => {
+                    let _ = std::io::stderr().flush();
+                }
+                Sink::File(file) => {
+                    let _ = file.file.flush();
+                }
+            }
+        }
+    }
+}
+
+fn lock_error() -> CoreError {
+    CoreError::Internal("Failed to acquire logger lock".to_string())
+}
+
+/// Install the engine logger, unless it is installed already
+pub fn init(config: &LoggingConfig) -> CoreResult<()> {
+    if LOGGER.get().is_some() {
+        return Ok(());
+    }
+    install(config)
+}
+
+/// Install the engine logger, or apply a new configuration to the installed one
+pub fn configure(config: &LoggingConfig) -> CoreResult<()> {
+    match LOGGER.get() {
+        Some(logger) => logger.inner().configure(config),
+        None => install(config),
+    }
+}
+
+fn install(config: &LoggingConfig) -> CoreResult<()> {
+    let logger = MaskingLogger::new(EngineLogger::new(config)?);
+    if LOGGER.set(logger).is_err() {
+        // Another thread installed it first
+        return Ok(());
+    }
+    let logger = LOGGER.get().ok_or_else(|| CoreError::Internal("Logger missing after install".to_string()))?;
+    log::set_logger(logger)
+        .map_err(|_| CoreError::Configuration("Another logger is already installed in this process".to_string()))?;
+    log::set_max_level(logger.inner().filter.read().map_err(|_| lock_error())?.max_level());
+    Ok(())
+}
+
+/// Set the level of a module at runtime, or the default level when no module is given
+pub fn set_log_level(module: Option<&str>, level: &str) -> CoreResult<LoggingStatus> {
+    let level = parse_level(level).map_err(CoreError::Validation)?;
+    let logger = LOGGER.get()
+        .ok_or_else(|| CoreError::InvalidState("The engine logger is not installed".to_string()))?;
+    logger.inner().set_level(module, level)?;
+    logger.inner().status()
+}
+
+/// Current logging settings, None while the engine logger is not installed
+pub fn status() -> CoreResult<Option<LoggingStatus>> {
+    LOGGER.get().map(|logger| logger.inner().status()).transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_module_levels_and_log_file_rotation() {
+        let mut filter = LogFilter::parse("warn,core::dispatcher=debug,core::dispatcher::leases=off").unwrap();
+        assert_eq!(filter.level_for("core::state"), LevelFilter::Warn);
+        assert_eq!(filter.level_for("core::dispatcher"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("core::dispatcher::leases"), LevelFilter::Off);
+        assert_eq!(filter.level_for("core::dispatcher_metrics"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Debug);
+        filter.set(Some("core::state"), LevelFilter::Trace);
+        filter.set(None, LevelFilter::Error);
+        assert_eq!((filter.level_for("core::state::cache"), filter.level_for("actix")), (LevelFilter::Trace, LevelFilter::Error));
+        assert!(LogFilter::parse("core=loud").is_err());
+        assert_eq!(" JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("engine.log").to_str().unwrap().to_string();
+        let mut file = LogFile::open(&path, 20, 2).unwrap();
+        for line in ["first line", "second line", "third line", "fourth line"] {
+            file.write_line(line).unwrap();
+        }
+        let read = |suffix: &str| std::fs::read_to_string(format!("{}{}", path, suffix)).unwrap();
+        assert_eq!(read(""), "fourth line\n");
+        assert_eq!(read(".1"), "third line\n");
+        assert_eq!(read(".2"), "second line\n");
+        assert!(!std::path::Path::new(&format!("{}.3", path)).exists());
+    }
+}
@@ -0,0 +1,195 @@
+//! Versioned schema migrations of the SQLite database
+//!
+//! Migrations run in order whenever a database is opened, each in its own
+//! transaction, and are recorded in `schema_migrations`. They only go forward:
+//! a database migrated by a newer engine is refused rather than read with a
+//! schema this engine does not understand. Schema changes are made by
+//! appending a migration to `MIGRATIONS`, never by editing released ones or
+//! `schema.sql`, which is the initial migration.
+
+use rusqlite::{Connection, Transaction, TransactionBehavior};
+use crate::error::{CoreError, CoreResult};
+use crate::models::{AppliedMigration, SchemaVersion};
+
+/// A schema change, identified by its version
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    apply: fn(&Connection) -> CoreResult<()>,
+}
+
+/// Every migration, in the order they are applied
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "initial_schema", apply: initial_schema },
+    Migration { version: 2, name: "add_missing_columns", apply: add_missing_columns },
+    Migration { version: 3, name: "add_run_labels", apply: add_run_labels },
+];
+
+/// Latest schema version this engine knows
+pub const SCHEMA_VERSION: u32 = MIGRATIONS[MIGRATIONS.len() - 1].version;
+
+const CREATE_MIGRATIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS schema_migrations (\
+    version INTEGER PRIMARY KEY, \
+    name TEXT NOT NULL, \
+    applied_at TEXT NOT NULL)";
+
+/// Columns added to tables after they were first released, as (table, column, type)
+///
+/// Databases created before migrations existed may lack them.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("step_results", "cache_hit", "TEXT"),
+    ("step_results", "resources", "TEXT"),
+    ("run_triggers", "trace_parent", "TEXT"),
+    ("run_triggers", "lane", "TEXT"),
+    ("workflow_runs", "version", "INTEGER NOT NULL DEFAULT 0"),
+    ("workflows", "disabled", "INTEGER NOT NULL DEFAULT 0"),
+    ("workflows", "disabled_reason", "TEXT"),
+    ("workflows", "disabled_at", "TEXT"),
+    ("workflows", "disabled_until", "TEXT"),
+];
+
+fn initial_schema(conn: &Connection) -> CoreResult<()> {
+    conn.execute_batch(include_str!("schema.sql"))?;
+    Ok(())
+}
+
+fn add_missing_columns(conn: &Connection) -> CoreResult<()> {
+    for (table, column, column_type) in ADDED_COLUMNS {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+            (table, column),
+            |row| row.get(0),
+        )?;
+        if !exists {
+            conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, column_type))?;
+        }
+    }
+    Ok(())
+}
+
+fn add_run_labels(conn: &Connection) -> CoreResult<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS run_labels (
+            run_id TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL,
+            set_at TEXT NOT NULL,
+            PRIMARY KEY (run_id, key),
+            FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+        );
+        CREATE INDEX IF NOT EXISTS idx_run_labels_key_value ON run_labels (key, value);"
+    )?;
+    Ok(())
+}
+
+/// Get the latest migration applied to a database, 0 for one never migrated
+pub fn schema_version(conn: &Connection) -> CoreResult<u32> {
+    let has_table: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = 'schema_migrations'",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_table {
+        return Ok(0);
+    }
+    let version: Option<u32> = conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Fail if a database was migrated past what this engine supports
+pub fn check_supported(version: u32) -> CoreResult<()> {
+    if version > SCHEMA_VERSION {
+        return Err(CoreError::Configuration(format!(
+            "Database schema version {} is newer than version {} supported by this engine; upgrade the engine",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(())
+}
+
+/// Apply the migrations a database lacks, returning the versions applied
+///
+/// Each migration commits on its own, so a failed one leaves the database at
+/// the previous version.
+pub fn migrate(conn: &Connection) -> CoreResult<Vec<u32>> {
+    let version = schema_version(conn)?;
+    check_supported(version)?;
+    if version == SCHEMA_VERSION {
+        return Ok(Vec::new());
+    }
+    
+    conn.execute_batch(CREATE_MIGRATIONS_TABLE)?;
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS {
+        // Taking the write lock first keeps two processes from applying the same migration
+        let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+        let current = schema_version(&tx)?;
+        check_supported(current)?;
+        if migration.version <= current {
+            continue;
+        }
+        (migration.apply)(&tx)?;
+        tx.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, ?, ?)",
+            (migration.version, migration.name, chrono::Utc::now().to_rfc3339()),
+        )?;
+        tx.commit()?;
+        log::info!("Applied database migration {} ({})", migration.version, migration.name);
+        applied.push(migration.version);
+    }
+    Ok(applied)
+}
+
+/// Get the schema version of a database with the migrations applied to it
+pub fn get_schema_version(conn: &Connection) -> CoreResult<SchemaVersion> {
+    let mut migrations = Vec::new();
+    if schema_version(conn)? > 0 {
+        let mut stmt = conn.prepare("SELECT version, name, applied_at FROM schema_migrations ORDER BY version")?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))?;
+        for row in rows {
+            let (version, name, applied_at) = row?;
+            migrations.push(AppliedMigration {
+                version,
+                name,
+                applied_at: chrono::DateTime::parse_from_rfc3339(&applied_at)?.with_timezone(&chrono::Utc),
+            });
+        }
+    }
+    Ok(SchemaVersion {
+        version: migrations.last().map(|migration| migration.version).unwrap_or(0),
+        supported_version: SCHEMA_VERSION,
+        migrations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_upgrade_old_databases_once_and_refuse_newer_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let conn = Connection::open(dir.path().join("legacy.db")).unwrap();
+        // A database created before migrations, without columns added since
+        conn.execute_batch(include_str!("schema.sql")).unwrap();
+        conn.execute_batch("ALTER TABLE step_results DROP COLUMN cache_hit").unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+
+        assert_eq!(migrate(&conn).unwrap(), vec![1, 2, 3]);
+        let has_cache_hit: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('step_results') WHERE name = 'cache_hit'", [], |row| row.get(0),
+        ).unwrap();
+        assert!(has_cache_hit);
+        assert!(migrate(&conn).unwrap().is_empty());
+        let version = get_schema_version(&conn).unwrap();
+        assert_eq!((version.version, version.supported_version), (SCHEMA_VERSION, SCHEMA_VERSION));
+        assert_eq!(version.migrations.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), vec!["initial_schema", "add_missing_columns", "add_run_labels"]);
+
+        conn.execute(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES (?, 'from_the_future', ?)",
+            (SCHEMA_VERSION + 1, chrono::Utc::now().to_rfc3339()),
+        ).unwrap();
+        let err = migrate(&conn).unwrap_err();
+        assert!(err.to_string().contains("newer than version"));
+    }
+}
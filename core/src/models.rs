@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
+use crate::error::{CoreError, CoreResult};
+use crate::workflow_analysis::WorkflowDiagnostic;
 
 /// Control flow condition types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -138,6 +140,100 @@ pub struct WorkflowDefinition {
     pub triggers: Vec<TriggerDefinition>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Maximum number of runs of this workflow executing concurrently
+    pub concurrency: Option<usize>,
+    /// Namespace whose worker quota this workflow's jobs run under
+    pub namespace: Option<String>,
+    /// Percentage of successful runs whose full payload is stored; the rest keep only a hash
+    pub payload_sample_rate: Option<f64>,
+    /// Priority of the workflow's jobs, unless their step sets one
+    #[serde(default)]
+    pub priority: Option<crate::job::JobPriority>,
+    /// Longest a run may stay unfinished after it started before it is timed out
+    #[serde(default)]
+    pub run_timeout_ms: Option<u64>,
+    /// Status a timed out run ends with
+    #[serde(default)]
+    pub run_timeout_outcome: RunTimeoutOutcome,
+    /// Payload path such as "payload.customer_id" whose value serializes runs:
+    /// runs sharing a value execute one at a time, in the order they were created
+    #[serde(default)]
+    pub serialize_on: Option<String>,
+    /// Budget of the resources the workflow's jobs may use; dispatching of
+    /// its jobs pauses once a window's budget is spent
+    #[serde(default)]
+    pub resource_quota: Option<ResourceQuota>,
+    /// Dispatcher lane the workflow's runs execute in, unless their trigger picks one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lane: Option<String>,
+    /// Settings the workflow's steps inherit unless they set their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<StepDefaults>,
+}
+
+/// Settings a workflow's steps inherit unless they set their own
+///
+/// The timeout and retry apply to steps executed by workers, not to control
+/// flow, delay, pause, signal or sub-workflow steps. The lane applies to the
+/// workflow's runs when neither their trigger nor the workflow picks one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StepDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<crate::job::JobPriority>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lane: Option<String>,
+}
+
+impl StepDefaults {
+    /// Validate the defaults
+    pub fn validate(&self) -> Result<(), String> {
+        if self.timeout == Some(0) {
+            return Err("Default step timeout must be greater than 0".to_string());
+        }
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        if let Some(lane) = &self.lane {
+            crate::config::LaneAllocation::validate_name(lane)?;
+        }
+        Ok(())
+    }
+
+    /// Fill the settings a step leaves unset
+    pub fn apply_to(&self, step: &mut StepDefinition) {
+        step.priority = step.priority.or(self.priority);
+        let runs_on_worker = !(step.is_control_flow || step.is_delay_step() || step.is_pause_step()
+            || step.is_subworkflow_step() || step.wait_for_signal.is_some());
+        if runs_on_worker {
+            step.timeout = step.timeout.or(self.timeout);
+            if step.retry.is_none() {
+                step.retry = self.retry.clone();
+            }
+        }
+    }
+}
+
+/// How a run that exceeds its workflow's run timeout ends
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunTimeoutOutcome {
+    #[default]
+    Failed,
+    Cancelled,
+}
+
+impl RunTimeoutOutcome {
+    /// Run status of the outcome
+    pub fn status(self) -> RunStatus {
+        match self {
+            RunTimeoutOutcome::Failed => RunStatus::Failed,
+            RunTimeoutOutcome::Cancelled => RunStatus::Cancelled,
+        }
+    }
 }
 
 impl WorkflowDefinition {
@@ -155,6 +251,40 @@ impl WorkflowDefinition {
             return Err("Workflow must have at least one step".to_string());
         }
         
+        if self.concurrency == Some(0) {
+            return Err("Workflow concurrency must be greater than 0".to_string());
+        }
+        
+        if self.namespace.as_deref().is_some_and(|namespace| namespace.trim().is_empty()) {
+            return Err("Workflow namespace cannot be empty".to_string());
+        }
+        
+        if let Some(lane) = &self.lane {
+            crate::config::LaneAllocation::validate_name(lane)?;
+        }
+        
+        if let Some(serialize_on) = &self.serialize_on {
+            if payload_path(serialize_on).is_none() {
+                return Err(format!("Invalid serialize_on expression: {:?}", serialize_on));
+            }
+        }
+        
+        if self.payload_sample_rate.is_some_and(|rate| !(0.0..=100.0).contains(&rate)) {
+            return Err("Workflow payload sample rate must be between 0 and 100".to_string());
+        }
+        
+        if self.run_timeout_ms == Some(0) {
+            return Err("Workflow run timeout must be greater than 0".to_string());
+        }
+        
+        if let Some(quota) = &self.resource_quota {
+            quota.validate()?;
+        }
+        
+        if let Some(defaults) = &self.defaults {
+            defaults.validate()?;
+        }
+        
         let step_ids: Vec<&String> = self.steps.iter().map(|s| &s.id).collect();
         let unique_ids: Vec<&String> = step_ids.iter().map(|&&ref id| id).collect();
         if step_ids.len() != unique_ids.len() {
@@ -165,21 +295,312 @@ impl WorkflowDefinition {
             step.validate()?;
         }
         
+        if self.steps.iter().any(|step| step.subworkflow_id.as_deref() == Some(self.id.as_str())) {
+            return Err("Workflow cannot run itself as a sub-workflow".to_string());
+        }
+        
+        let errors: Vec<String> = crate::workflow_analysis::analyze(self).into_iter()
+            .filter(WorkflowDiagnostic::is_error)
+            .map(|diagnostic| diagnostic.message)
+            .collect();
+        if !errors.is_empty() {
+            return Err(format!("Invalid step graph: {}", errors.join("; ")));
+        }
+        
         Ok(())
     }
     
+    /// Get the final output of a run: the output of its last completed step in definition order
+    pub fn final_output(&self, results: &[StepResult]) -> Option<serde_json::Value> {
+        self.steps.iter().rev().find_map(|step| {
+            results.iter()
+                .find(|result| result.step_id == step.id && result.status == StepStatus::Completed)
+                .map(|result| result.output.clone().unwrap_or(serde_json::Value::Null))
+        })
+    }
+    
     /// Get a step by ID
     pub fn get_step(&self, step_id: &str) -> Option<&StepDefinition> {
         self.steps.iter().find(|s| s.id == step_id)
     }
     
+    /// Get a step with the settings it inherits from the workflow's defaults filled in
+    pub fn step_with_defaults(&self, step_id: &str) -> Option<StepDefinition> {
+        let mut step = self.get_step(step_id)?.clone();
+        if let Some(defaults) = &self.defaults {
+            defaults.apply_to(&mut step);
+        }
+        Some(step)
+    }
+    
+    /// Fill the settings every step inherits from the workflow's defaults
+    pub fn apply_step_defaults(&mut self) {
+        if let Some(defaults) = &self.defaults {
+            for step in &mut self.steps {
+                defaults.apply_to(step);
+            }
+        }
+    }
+    
+    /// Lane the workflow's runs execute in unless their trigger picks one
+    pub fn default_lane(&self) -> Option<&str> {
+        self.lane.as_deref().or(self.defaults.as_ref().and_then(|defaults| defaults.lane.as_deref()))
+    }
+    
     /// Check if workflow has a specific trigger type
     pub fn has_trigger_type(&self, trigger_type: &str) -> bool {
         self.triggers.iter().any(|t| match t {
             TriggerDefinition::Webhook { .. } => trigger_type == "webhook",
             TriggerDefinition::Manual => trigger_type == "manual",
+            TriggerDefinition::Schedule { .. } => trigger_type == "schedule",
+            TriggerDefinition::Interval { .. } => trigger_type == "interval",
+            TriggerDefinition::At { .. } => trigger_type == "at",
+            TriggerDefinition::Event { .. } => trigger_type == "event",
+            TriggerDefinition::Polling { .. } => trigger_type == "polling",
+        })
+    }
+    
+    /// Get the overlap policy of the workflow's schedule or interval trigger
+    pub fn overlap_policy(&self) -> OverlapPolicy {
+        self.triggers.iter()
+            .find_map(|t| match t {
+                TriggerDefinition::Schedule { overlap_policy, .. }
+                | TriggerDefinition::Interval { overlap_policy, .. } => Some(*overlap_policy),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+    
+    /// Get the dedup settings of the workflow's trigger on an event
+    pub fn event_dedup(&self, event_name: &str) -> Option<&TriggerDedup> {
+        self.triggers.iter().find_map(|t| match t {
+            TriggerDefinition::Event { event_name: name, dedup, .. } if name == event_name => dedup.as_ref(),
+            _ => None,
         })
     }
+    
+    /// Get the serialization key of a run with the given payload
+    ///
+    /// Returns None when the workflow does not serialize runs or the payload
+    /// has no value at its path; such runs are not serialized.
+    pub fn serialization_key(&self, payload: &serde_json::Value) -> Option<String> {
+        payload_key(payload, self.serialize_on.as_deref()?)
+    }
+    
+    /// Parse a workflow from JSON, reporting fields that do not map onto the definition
+    ///
+    /// Such fields (e.g. `dependsOn` instead of `depends_on`) are silently dropped by
+    /// deserialization. In strict mode they reject the workflow instead. The report
+    /// also carries the diagnostics of the workflow's step graph.
+    pub fn from_json(json: &str, strict: bool) -> CoreResult<(WorkflowDefinition, WorkflowCompatibilityReport)> {
+        let input: serde_json::Value = serde_json::from_str(json)?;
+        let ignored = std::cell::RefCell::new(Vec::new());
+        let mut workflow = WorkflowDefinition::deserialize(FieldRecorder { value: &input, path: String::new(), ignored: &ignored })?;
+        
+        let mut report = WorkflowCompatibilityReport { ignored_fields: ignored.into_inner(), ..Default::default() };
+        // Registered steps carry what they inherit, so every reader of a step sees it
+        workflow.apply_step_defaults();
+        report.diagnostics = crate::workflow_analysis::analyze(&workflow);
+        
+        if strict && !report.ignored_fields.is_empty() {
+            return Err(CoreError::InvalidWorkflow(format!(
+                "Unknown fields in strict mode: {}", report.ignored_fields.join(", ")
+            )));
+        }
+        
+        Ok((workflow, report))
+    }
+}
+
+/// Fields of a workflow JSON document that deserialization ignored, and problems in its step graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkflowCompatibilityReport {
+    /// JSON paths such as `steps[0].dependsOn`
+    pub ignored_fields: Vec<String>,
+    /// Errors reject the workflow when it is validated, warnings are informational
+    #[serde(default)]
+    pub diagnostics: Vec<WorkflowDiagnostic>,
+}
+
+impl WorkflowCompatibilityReport {
+    /// Check if every field was recognized
+    pub fn is_clean(&self) -> bool {
+        self.ignored_fields.is_empty()
+    }
+    
+    /// Messages of the warning diagnostics
+    pub fn warnings(&self) -> Vec<&str> {
+        self.diagnostics.iter()
+            .filter(|diagnostic| !diagnostic.is_error())
+            .map(|diagnostic| diagnostic.message.as_str())
+            .collect()
+    }
+}
+
+/// Deserializer over a JSON value recording keys that no struct field claims
+///
+/// Keys are checked against the field lists serde derives for each struct, so
+/// fields left out of serialization (e.g. empty `worker_tags`) are not mistaken
+/// for unknown ones. Null values are not reported since they cannot change behavior.
+struct FieldRecorder<'de> {
+    value: &'de serde_json::Value,
+    path: String,
+    ignored: &'de std::cell::RefCell<Vec<String>>,
+}
+
+impl<'de> FieldRecorder<'de> {
+    fn child(&self, value: &'de serde_json::Value, path: String) -> Self {
+        FieldRecorder { value, path, ignored: self.ignored }
+    }
+    
+    fn key_path(&self, key: &str) -> String {
+        if self.path.is_empty() { key.to_string() } else { format!("{}.{}", self.path, key) }
+    }
+    
+    fn visit_object<V: serde::de::Visitor<'de>>(
+        self,
+        object: &'de serde_json::Map<String, serde_json::Value>,
+        fields: Option<&'static [&'static str]>,
+        visitor: V,
+    ) -> Result<V::Value, serde_json::Error> {
+        visitor.visit_map(RecordedMap { recorder: self, entries: object.iter(), fields, value: None })
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for FieldRecorder<'de> {
+    type Error = serde_json::Error;
+    
+    fn deserialize_any<V: serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.value {
+            serde_json::Value::Array(items) => visitor.visit_seq(RecordedSeq { recorder: self, items: items.iter().enumerate() }),
+            serde_json::Value::Object(object) => self.visit_object(object, None, visitor),
+            value => value.deserialize_any(visitor),
+        }
+    }
+    
+    fn deserialize_option<V: serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        if self.value.is_null() { visitor.visit_none() } else { visitor.visit_some(self) }
+    }
+    
+    fn deserialize_newtype_struct<V: serde::de::Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+    
+    fn deserialize_struct<V: serde::de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            serde_json::Value::Object(object) => self.visit_object(object, Some(fields), visitor),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+    
+    fn deserialize_enum<V: serde::de::Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.value {
+            serde_json::Value::Object(object) if object.len() == 1 => visitor.visit_enum(self),
+            value => value.deserialize_enum(name, variants, visitor),
+        }
+    }
+    
+    fn deserialize_ignored_any<V: serde::de::Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+    
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map identifier
+    }
+}
+
+impl<'de> serde::de::EnumAccess<'de> for FieldRecorder<'de> {
+    type Error = serde_json::Error;
+    type Variant = FieldRecorder<'de>;
+    
+    fn variant_seed<S: serde::de::DeserializeSeed<'de>>(self, seed: S) -> Result<(S::Value, Self::Variant), Self::Error> {
+        let (key, value) = self.value.as_object()
+            .and_then(|object| object.iter().next())
+            .expect("enum objects have exactly one key");
+        let variant = seed.deserialize(serde::de::value::BorrowedStrDeserializer::<serde_json::Error>::new(key))?;
+        let path = self.key_path(key);
+        Ok((variant, self.child(value, path)))
+    }
+}
+
+impl<'de> serde::de::VariantAccess<'de> for FieldRecorder<'de> {
+    type Error = serde_json::Error;
+    
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    
+    fn newtype_variant_seed<S: serde::de::DeserializeSeed<'de>>(self, seed: S) -> Result<S::Value, Self::Error> {
+        seed.deserialize(self)
+    }
+    
+    fn tuple_variant<V: serde::de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        serde::Deserializer::deserialize_seq(self, visitor)
+    }
+    
+    fn struct_variant<V: serde::de::Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        serde::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+struct RecordedSeq<'de> {
+    recorder: FieldRecorder<'de>,
+    items: std::iter::Enumerate<std::slice::Iter<'de, serde_json::Value>>,
+}
+
+impl<'de> serde::de::SeqAccess<'de> for RecordedSeq<'de> {
+    type Error = serde_json::Error;
+    
+    fn next_element_seed<S: serde::de::DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, Self::Error> {
+        match self.items.next() {
+            Some((index, value)) => {
+                let path = format!("{}[{}]", self.recorder.path, index);
+                seed.deserialize(self.recorder.child(value, path)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+struct RecordedMap<'de> {
+    recorder: FieldRecorder<'de>,
+    entries: serde_json::map::Iter<'de>,
+    /// Keys a struct accepts; None for maps, whose keys are all accepted
+    fields: Option<&'static [&'static str]>,
+    value: Option<(&'de serde_json::Value, String)>,
+}
+
+impl<'de> serde::de::MapAccess<'de> for RecordedMap<'de> {
+    type Error = serde_json::Error;
+    
+    fn next_key_seed<S: serde::de::DeserializeSeed<'de>>(&mut self, seed: S) -> Result<Option<S::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        
+        let path = self.recorder.key_path(key);
+        if self.fields.is_some_and(|fields| !fields.contains(&key.as_str())) && !value.is_null() {
+            self.recorder.ignored.borrow_mut().push(path.clone());
+        }
+        self.value = Some((value, path));
+        seed.deserialize(serde::de::value::BorrowedStrDeserializer::<serde_json::Error>::new(key)).map(Some)
+    }
+    
+    fn next_value_seed<S: serde::de::DeserializeSeed<'de>>(&mut self, seed: S) -> Result<S::Value, Self::Error> {
+        let (value, path) = self.value.take().expect("next_value_seed is called after next_key_seed");
+        seed.deserialize(self.recorder.child(value, path))
+    }
 }
 
 /// Step definition structure
@@ -211,8 +632,59 @@ pub struct StepDefinition {
     pub race: Option<bool>,
     /// Whether this is a forEach loop step
     pub for_each: Option<bool>,
+    /// Expression resolving to the items a forEach step fans out over, e.g. "ctx.payload.orders"
+    #[serde(default)]
+    pub for_each_items: Option<String>,
+    /// Maximum number of forEach items in flight at once
+    #[serde(default)]
+    pub for_each_concurrency: Option<usize>,
     /// Whether this step should pause workflow execution
     pub pause: Option<bool>,
+    /// Whether the step is side-effect-free and safe to re-execute automatically
+    #[serde(default)]
+    pub idempotent: bool,
+    /// Timeout learned from the step's recent durations, used in place of `timeout` once enough are known
+    #[serde(default)]
+    pub adaptive_timeout: Option<AdaptiveTimeout>,
+    /// Time to wait once the step is reached, without holding a worker
+    #[serde(default)]
+    pub delay_ms: Option<u64>,
+    /// Expression resolving to the time the run may continue, e.g. "ctx.payload.send_at"
+    #[serde(default)]
+    pub delay_until: Option<String>,
+    /// Registered workflow the step runs as a child run, completing with the child's final output
+    #[serde(default)]
+    pub subworkflow_id: Option<String>,
+    /// Child payload fields mapped to expressions such as "ctx.steps.fetch.output.id"; the parent payload is passed when unset
+    #[serde(default)]
+    pub subworkflow_input: Option<std::collections::HashMap<String, String>>,
+    /// Priority of the step's jobs, overriding the workflow's
+    #[serde(default)]
+    pub priority: Option<crate::job::JobPriority>,
+    /// Reuse the output of an earlier execution with the same cache key instead of executing the step
+    #[serde(default)]
+    pub cache: Option<StepCache>,
+    /// Signal the step waits for, without holding a worker, completing with the signal's payload
+    #[serde(default)]
+    pub wait_for_signal: Option<String>,
+    /// How long a signal step waits before failing; it waits indefinitely when unset
+    #[serde(default)]
+    pub signal_timeout_ms: Option<u64>,
+    /// Expression skipping the step when true, evaluated right before the step is dispatched
+    #[serde(default)]
+    pub skip_if: Option<String>,
+    /// Skip the steps depending on a step skipped by `skip_if`, rather than treating it as satisfied
+    #[serde(default)]
+    pub skip_dependents: bool,
+    /// JSON Schema the step's output must match, failing the step otherwise
+    #[serde(default)]
+    pub output_schema: Option<serde_json::Value>,
+    /// Capability tags, e.g. "gpu", a worker must carry to run the step's jobs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub worker_tags: Vec<String>,
+    /// Input rendered from the context before the step executes, e.g. {"orderId": "{{ payload.order.id }}"}
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input: Option<serde_json::Value>,
 }
 
 impl StepDefinition {
@@ -234,10 +706,70 @@ impl StepDefinition {
             retry.validate()?;
         }
         
+        if let Some(adaptive_timeout) = &self.adaptive_timeout {
+            adaptive_timeout.validate()?;
+        }
+        
         self.validate_control_flow()?;
         
         self.validate_parallel_execution()?;
         
+        self.validate_for_each()?;
+        
+        if self.delay_ms.is_some() && self.delay_until.is_some() {
+            return Err("Delay step cannot have both delay_ms and delay_until".to_string());
+        }
+        
+        if self.subworkflow_input.is_some() && self.subworkflow_id.is_none() {
+            return Err("Sub-workflow input requires a sub-workflow ID".to_string());
+        }
+        if self.is_subworkflow_step() && (self.is_delay_step() || self.is_pause_step() || self.is_for_each()) {
+            return Err("Sub-workflow step cannot also be a delay, pause or forEach step".to_string());
+        }
+        
+        if let Some(cache) = &self.cache {
+            cache.validate()?;
+            if self.is_subworkflow_step() || self.is_delay_step() || self.is_pause_step() || self.is_for_each() {
+                return Err("Cached step cannot be a sub-workflow, delay, pause or forEach step".to_string());
+            }
+        }
+        
+        if self.signal_timeout_ms.is_some() && self.wait_for_signal.is_none() {
+            return Err("Signal timeout requires a signal to wait for".to_string());
+        }
+        if let Some(signal) = &self.wait_for_signal {
+            if signal.trim().is_empty() {
+                return Err("Signal name cannot be empty".to_string());
+            }
+            if self.is_subworkflow_step() || self.is_delay_step() || self.is_pause_step() || self.is_for_each() || self.cache.is_some() {
+                return Err("Signal step cannot also be a sub-workflow, delay, pause, forEach or cached step".to_string());
+            }
+        }
+        
+        for tag in &self.worker_tags {
+            crate::config::validate_worker_tag(tag)?;
+        }
+        
+        if self.skip_dependents && self.skip_if.is_none() {
+            return Err("Skipping dependents requires a skip_if expression".to_string());
+        }
+        if let Some(skip_if) = &self.skip_if {
+            if skip_if.trim().is_empty() {
+                return Err("skip_if expression cannot be empty".to_string());
+            }
+            if self.is_control_flow || self.parallel.is_some() || self.race == Some(true) {
+                return Err("skip_if is not supported on control flow, parallel or race steps".to_string());
+            }
+        }
+        
+        if let Some(schema) = &self.output_schema {
+            crate::output_schemas::compile(schema).map_err(|e| format!("Invalid output schema: {}", e))?;
+        }
+        
+        if let Some(input) = &self.input {
+            crate::step_input::validate(input).map_err(|e| format!("Invalid input mapping: {}", e))?;
+        }
+        
         Ok(())
     }
     
@@ -285,6 +817,17 @@ impl StepDefinition {
         Ok(())
     }
     
+    /// Validate forEach configuration
+    fn validate_for_each(&self) -> Result<(), String> {
+        if self.is_for_each() && self.for_each_items.is_none() {
+            return Err("forEach step must have an items expression".to_string());
+        }
+        if self.for_each_concurrency == Some(0) {
+            return Err("forEach concurrency must be greater than 0".to_string());
+        }
+        Ok(())
+    }
+    
     /// Check if step has dependencies
     pub fn has_dependencies(&self) -> bool {
         !self.depends_on.is_empty()
@@ -295,6 +838,15 @@ impl StepDefinition {
         self.timeout
     }
     
+    /// Get the timeout to apply given the step's recent durations
+    ///
+    /// Falls back to the static timeout until the adaptive timeout has enough samples.
+    pub fn effective_timeout_ms(&self, durations: &[u64]) -> Option<u64> {
+        self.adaptive_timeout.as_ref()
+            .and_then(|adaptive| adaptive.timeout_ms(durations))
+            .or(self.timeout)
+    }
+    
     /// Check if step can be retried (placeholder - will be implemented with retry count tracking)
     pub fn can_retry(&self) -> bool {
         // In the future, this will check against the actual retry count
@@ -368,85 +920,665 @@ impl StepDefinition {
     pub fn is_pause_step(&self) -> bool {
         self.pause.unwrap_or(false)
     }
+    
+    /// Check if this step waits before the run continues
+    pub fn is_delay_step(&self) -> bool {
+        self.delay_ms.is_some() || self.delay_until.is_some()
+    }
+    
+    /// Check if this step runs another workflow as a child run
+    pub fn is_subworkflow_step(&self) -> bool {
+        self.subworkflow_id.is_some()
+    }
+    
+    /// Check if this step waits for a signal sent to the run
+    pub fn is_signal_step(&self) -> bool {
+        self.wait_for_signal.is_some()
+    }
+    
+    /// Check if steps depending on this one may run once `skip_if` skipped it
+    pub fn satisfies_dependents_when_skipped(&self) -> bool {
+        self.skip_if.is_some() && !self.skip_dependents
+    }
 }
 
-/// Retry configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RetryConfig {
-    pub max_attempts: u32,
-    pub backoff_ms: u64,
+/// Output caching of a step, keyed by a value resolved from the run context
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepCache {
+    /// Expression resolving to the cache key, e.g. "ctx.payload.customer_id" or "ctx.payload"
+    pub key_expression: String,
+    /// How long a cached output is reused
+    pub ttl_ms: u64,
 }
 
-impl RetryConfig {
-    /// Validate retry configuration
+impl StepCache {
+    /// Validate the cache configuration
     pub fn validate(&self) -> Result<(), String> {
-        if self.max_attempts == 0 {
-            return Err("Max attempts must be greater than 0".to_string());
+        if self.key_expression.trim().is_empty() {
+            return Err("Step cache key expression cannot be empty".to_string());
         }
-        
-        if self.backoff_ms == 0 {
-            return Err("Backoff must be greater than 0".to_string());
+        if self.ttl_ms == 0 {
+            return Err("Step cache TTL must be greater than 0".to_string());
         }
-        
         Ok(())
     }
-    
-    /// Get total retry time in milliseconds
-    pub fn get_total_retry_time_ms(&self) -> u64 {
-        self.max_attempts as u64 * self.backoff_ms
+
+    /// Cache key for the value the key expression resolved to
+    ///
+    /// Object fields are hashed in sorted order, so values differing only in
+    /// field order share an entry.
+    pub fn key_for(value: &serde_json::Value) -> String {
+        use sha2::{Digest, Sha256};
+        
+        fn sorted(value: &serde_json::Value) -> serde_json::Value {
+            match value {
+                serde_json::Value::Object(fields) => {
+                    let mut names: Vec<&String> = fields.keys().collect();
+                    names.sort();
+                    serde_json::Value::Object(names.into_iter().map(|name| (name.clone(), sorted(&fields[name]))).collect())
+                }
+                serde_json::Value::Array(items) => serde_json::Value::Array(items.iter().map(sorted).collect()),
+                other => other.clone(),
+            }
+        }
+        hex::encode(Sha256::digest(sorted(value).to_string().as_bytes()))
     }
 }
 
-/// Trigger definition structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum TriggerDefinition {
-    Webhook {
-        path: String,
-        method: String,
-    },
-    Manual,
+/// Resources a step attempt used while it ran on a worker
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResourceUsage {
+    /// CPU time of the worker thread running the attempt
+    pub cpu_time_ms: u64,
+    /// Highest resident memory of the engine process sampled during the attempt
+    pub peak_memory_bytes: u64,
 }
 
-impl TriggerDefinition {
-    /// Validate the trigger definition
+/// Resources a workflow's jobs may use within a sliding window
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceQuota {
+    /// CPU time the workflow's jobs may use together within the window
+    #[serde(default)]
+    pub cpu_time_ms: Option<u64>,
+    /// Peak memory a single job may reach; a job going over it spends the window's budget
+    #[serde(default)]
+    pub peak_memory_bytes: Option<u64>,
+    pub window_secs: u64,
+}
+
+impl ResourceQuota {
     pub fn validate(&self) -> Result<(), String> {
-        match self {
-            TriggerDefinition::Webhook { path, method } => {
-                if path.is_empty() {
-                    return Err("Webhook path cannot be empty".to_string());
-                }
-                if method.is_empty() {
-                    return Err("Webhook method cannot be empty".to_string());
-                }
-                let valid_methods = ["GET", "POST", "PUT", "DELETE", "PATCH"];
-                if !valid_methods.contains(&method.to_uppercase().as_str()) {
-                    return Err(format!("Invalid HTTP method: {}", method));
-                }
-                Ok(())
-            }
-            TriggerDefinition::Manual => Ok(()),
+        if self.window_secs == 0 {
+            return Err("Resource quota window must be greater than 0".to_string());
         }
-    }
-    
-    /// Get trigger type as string
-    pub fn get_type(&self) -> &'static str {
-        match self {
-            TriggerDefinition::Webhook { .. } => "webhook",
-            TriggerDefinition::Manual => "manual",
+        if self.cpu_time_ms.is_none() && self.peak_memory_bytes.is_none() {
+            return Err("Resource quota must limit CPU time or peak memory".to_string());
         }
+        if self.cpu_time_ms == Some(0) || self.peak_memory_bytes == Some(0) {
+            return Err("Resource quota limits must be greater than 0".to_string());
+        }
+        Ok(())
     }
 }
 
-/// Workflow run state
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WorkflowRun {
-    pub id: Uuid,
+/// Marks a step result whose output was reused from the step cache
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepCacheHit {
+    /// Hash of the resolved cache key
+    pub key: String,
+    /// When the reused output was produced
+    pub cached_at: DateTime<Utc>,
+}
+
+/// Step output stored in the step cache
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CachedStepOutput {
     pub workflow_id: String,
-    pub status: RunStatus,
-    pub payload: serde_json::Value,
-    pub started_at: DateTime<Utc>,
-    pub completed_at: Option<DateTime<Utc>>,
-    pub error: Option<String>,
+    pub step_id: String,
+    pub key: String,
+    pub output: serde_json::Value,
+    pub cached_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Timeout derived from a percentile of a step's recent durations
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AdaptiveTimeout {
+    /// Percentile of recent durations the timeout is based on
+    #[serde(default = "AdaptiveTimeout::default_percentile")]
+    pub percentile: f64,
+    /// Multiplier applied to the percentile
+    #[serde(default = "AdaptiveTimeout::default_factor")]
+    pub factor: f64,
+    pub min_ms: u64,
+    pub max_ms: u64,
+    /// Durations needed before the learned timeout replaces the static one
+    #[serde(default = "AdaptiveTimeout::default_min_samples")]
+    pub min_samples: usize,
+}
+
+impl AdaptiveTimeout {
+    /// Number of recent durations kept per step
+    pub const SAMPLE_WINDOW: usize = 500;
+
+    fn default_percentile() -> f64 {
+        99.0
+    }
+
+    fn default_factor() -> f64 {
+        2.0
+    }
+
+    fn default_min_samples() -> usize {
+        20
+    }
+
+    /// Validate the adaptive timeout configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.percentile > 0.0 && self.percentile <= 100.0) {
+            return Err("Adaptive timeout percentile must be in (0, 100]".to_string());
+        }
+        if self.factor < 1.0 {
+            return Err("Adaptive timeout factor must be at least 1".to_string());
+        }
+        if self.min_ms == 0 || self.min_ms > self.max_ms {
+            return Err("Adaptive timeout bounds must satisfy 0 < min_ms <= max_ms".to_string());
+        }
+        if self.min_samples == 0 || self.min_samples > Self::SAMPLE_WINDOW {
+            return Err(format!("Adaptive timeout min_samples must be between 1 and {}", Self::SAMPLE_WINDOW));
+        }
+        Ok(())
+    }
+
+    /// Get the learned timeout, or None while there are too few durations
+    pub fn timeout_ms(&self, durations: &[u64]) -> Option<u64> {
+        if durations.len() < self.min_samples {
+            return None;
+        }
+        let learned = (percentile_ms(durations, self.percentile)? as f64 * self.factor).ceil() as u64;
+        Some(learned.clamp(self.min_ms, self.max_ms))
+    }
+}
+
+/// Get a percentile of durations by nearest rank
+pub fn percentile_ms(durations: &[u64], percentile: f64) -> Option<u64> {
+    if durations.is_empty() {
+        return None;
+    }
+    let mut sorted = durations.to_vec();
+    sorted.sort_unstable();
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Distribution of a step's recent successful durations
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepDurationStats {
+    pub workflow_id: String,
+    pub step_id: String,
+    pub samples: usize,
+    pub p50_ms: Option<u64>,
+    pub p90_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+    /// Timeout the step currently runs with
+    pub timeout_ms: Option<u64>,
+}
+
+/// Retry configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub backoff_ms: u64,
+    /// How the delay grows from `backoff_ms` with every retry
+    #[serde(default)]
+    pub strategy: BackoffStrategy,
+    /// Which failures are retried
+    #[serde(default, flatten)]
+    pub rules: RetryRules,
+}
+
+/// How the delay before a retry grows with the number of retries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BackoffStrategy {
+    /// The base backoff before every retry
+    Fixed,
+    /// The base backoff times the retry number
+    Linear,
+    /// The base backoff multiplied by `factor` with every retry, capped at `max_ms`
+    Exponential {
+        #[serde(default = "BackoffStrategy::default_factor")]
+        factor: f64,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        max_ms: Option<u64>,
+    },
+    /// The base backoff times the Fibonacci number of the retry: 1, 1, 2, 3, 5, ...
+    Fibonacci,
+}
+
+impl Default for BackoffStrategy {
+    fn default() -> Self {
+        BackoffStrategy::Exponential { factor: Self::default_factor(), max_ms: None }
+    }
+}
+
+impl BackoffStrategy {
+    fn default_factor() -> f64 {
+        2.0
+    }
+    
+    /// Delay before a retry, where `retry` is 1 for the first retry
+    pub fn delay_ms(&self, backoff_ms: u64, retry: u32) -> u64 {
+        let retry = retry.max(1);
+        match self {
+            BackoffStrategy::Fixed => backoff_ms,
+            BackoffStrategy::Linear => backoff_ms.saturating_mul(u64::from(retry)),
+            BackoffStrategy::Exponential { factor, max_ms } => {
+                let delay = backoff_ms as f64 * factor.powi(retry as i32 - 1);
+                let delay = if delay >= u64::MAX as f64 { u64::MAX } else { delay as u64 };
+                max_ms.map_or(delay, |max_ms| delay.min(max_ms))
+            }
+            BackoffStrategy::Fibonacci => {
+                let (mut current, mut next) = (1u64, 1u64);
+                for _ in 1..retry {
+                    (current, next) = (next, current.saturating_add(next));
+                }
+                backoff_ms.saturating_mul(current)
+            }
+        }
+    }
+    
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            BackoffStrategy::Exponential { factor, .. } if !factor.is_finite() || *factor < 1.0 => {
+                Err("Exponential backoff factor must be at least 1".to_string())
+            }
+            BackoffStrategy::Exponential { max_ms: Some(0), .. } => Err("Exponential backoff maximum must be greater than 0".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Kind of failure, as far as retrying it is concerned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryErrorClass {
+    /// The step ran out of time or stopped sending heartbeats
+    Timeout,
+    /// The step's input or configuration was rejected, so a retry fails the same way
+    Validation,
+    /// An HTTP request failed
+    Network,
+    /// Any other step failure
+    Execution,
+}
+
+impl RetryErrorClass {
+    /// Classify a failure by its error message
+    pub fn classify(error: &str) -> Self {
+        let error = error.to_lowercase();
+        if error.contains("timed out") || error.contains("no heartbeat") {
+            RetryErrorClass::Timeout
+        } else if error.contains("validation error") || error.contains("invalid workflow definition") {
+            RetryErrorClass::Validation
+        } else if error.contains("http request error") {
+            RetryErrorClass::Network
+        } else {
+            RetryErrorClass::Execution
+        }
+    }
+}
+
+/// Which classes of failures are retried
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RetryRules {
+    /// Only these classes are retried; every class when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_on: Option<Vec<RetryErrorClass>>,
+    /// These classes are never retried, taking precedence over `retry_on`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_retry_on: Vec<RetryErrorClass>,
+}
+
+impl RetryRules {
+    /// Check if a failure with this error message may be retried
+    pub fn allows(&self, error: &str) -> bool {
+        let class = RetryErrorClass::classify(error);
+        !self.no_retry_on.contains(&class)
+            && self.retry_on.as_ref().is_none_or(|classes| classes.contains(&class))
+    }
+}
+
+impl RetryConfig {
+    /// Validate retry configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_attempts == 0 {
+            return Err("Max attempts must be greater than 0".to_string());
+        }
+        
+        if self.backoff_ms == 0 {
+            return Err("Backoff must be greater than 0".to_string());
+        }
+        
+        self.strategy.validate()
+    }
+    
+    /// Get total retry time in milliseconds
+    pub fn get_total_retry_time_ms(&self) -> u64 {
+        (1..self.max_attempts).map(|retry| self.strategy.delay_ms(self.backoff_ms, retry)).fold(0, u64::saturating_add)
+    }
+}
+
+/// Trigger definition structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TriggerDefinition {
+    Webhook {
+        path: String,
+        method: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        validation: Option<crate::triggers::WebhookValidation>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        respond_with: Option<crate::triggers::WebhookReply>,
+        /// Boxed to keep the variant small
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        response: Option<Box<crate::triggers::WebhookResponseOptions>>,
+        /// Builds the run payload from the request instead of the whole HTTP envelope
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        payload_mapping: Option<Box<crate::triggers::WebhookPayloadMapping>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dedup: Option<TriggerDedup>,
+        /// Boxed to keep the variant small
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auth: Option<Box<crate::triggers::WebhookAuth>>,
+        /// Dispatcher lane of the runs the webhook starts, overriding the workflow's
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        lane: Option<String>,
+        /// Adds the request's parts to the run payload as structured `request` fields
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        request_fields: Option<Box<crate::triggers::WebhookRequestFields>>,
+    },
+    Manual,
+    /// Recurring trigger fired by the SDK scheduler
+    Schedule {
+        cron_expression: String,
+        #[serde(default)]
+        overlap_policy: OverlapPolicy,
+    },
+    /// Recurring trigger fired every fixed number of seconds by the engine
+    Interval {
+        every_seconds: u64,
+        #[serde(default)]
+        overlap_policy: OverlapPolicy,
+        #[serde(default)]
+        catch_up: CatchUpPolicy,
+    },
+    /// One-time trigger fired by the engine at a timestamp
+    At {
+        run_at: DateTime<Utc>,
+    },
+    /// Subscription to a published event
+    Event {
+        event_name: String,
+        /// Schema versions of the event the workflow accepts, any version when empty
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        versions: Vec<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        dedup: Option<TriggerDedup>,
+    },
+    /// URL fetched by the engine every fixed number of seconds, firing when its response changes
+    Polling {
+        url: String,
+        every_seconds: u64,
+        #[serde(default, skip_serializing_if = "std::collections::BTreeMap::is_empty")]
+        headers: std::collections::BTreeMap<String, String>,
+        /// Key of a secret of the workflow sent as a bearer token
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        auth_secret: Option<String>,
+        /// JSONPath of the response value compared between polls, the whole response when unset
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        compare_path: Option<String>,
+    },
+}
+
+/// Deduplication of the runs a webhook or event trigger starts
+///
+/// A firing whose key matches that of a run of the workflow created within
+/// the window is recorded as suppressed instead of starting another run.
+/// Firings without a value at the key path always start a run.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriggerDedup {
+    /// Path of the key in the run payload, written as `payload.x`, `ctx.payload.x` or `$.x`
+    pub key: String,
+    pub window_secs: u64,
+}
+
+impl TriggerDedup {
+    pub fn validate(&self) -> Result<(), String> {
+        if payload_path(&self.key).is_none() {
+            return Err(format!("Invalid dedup key expression: {:?}", self.key));
+        }
+        if self.window_secs == 0 {
+            return Err("Dedup window must be at least one second".to_string());
+        }
+        Ok(())
+    }
+    
+    /// Get the dedup key of a firing with the given run payload
+    pub fn key_for(&self, payload: &serde_json::Value) -> Option<String> {
+        payload_key(payload, &self.key)
+    }
+}
+
+/// Trigger firing that did not start a run because it duplicated a recent one
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SuppressedTrigger {
+    pub workflow_id: String,
+    pub trigger_type: String,
+    pub source: Option<String>,
+    pub dedup_key: String,
+    /// Run the firing duplicated
+    pub run_id: Uuid,
+    pub suppressed_at: DateTime<Utc>,
+}
+
+/// Payload path segments of an expression written as `payload.x`, `ctx.payload.x` or `$.x`
+fn payload_path(expression: &str) -> Option<Vec<String>> {
+    let expression = expression.trim();
+    let path = expression.strip_prefix("ctx.payload.")
+        .or_else(|| expression.strip_prefix("payload."))
+        .unwrap_or(expression);
+    crate::triggers::split_json_path(path).filter(|segments| !segments.is_empty())
+}
+
+/// Value of a payload at a path expression as a key, None when absent or null
+fn payload_key(payload: &serde_json::Value, expression: &str) -> Option<String> {
+    match crate::triggers::lookup_json_path(payload, &payload_path(expression)?) {
+        serde_json::Value::Null => None,
+        serde_json::Value::String(key) => Some(key),
+        other => Some(other.to_string()),
+    }
+}
+
+/// What an interval trigger does about the firings it missed while the engine was down
+///
+/// A firing counts as missed once the next one is also due, so a scheduler
+/// tick running late by less than the interval never catches up.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum CatchUpPolicy {
+    /// Drop the missed firings and wait for the next one
+    None,
+    /// Start one run standing in for all the missed firings
+    #[default]
+    FireOnce,
+    /// Start a run for each missed firing, for the most recent `max` of them
+    FireAll { max: u32 },
+}
+
+/// What to do when a schedule fires while an earlier run of the workflow is still unfinished
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlapPolicy {
+    /// Start another run alongside the unfinished ones
+    #[default]
+    Allow,
+    /// Drop this firing
+    Skip,
+    /// Start the run, but only execute it once the earlier runs have finished
+    Queue,
+    /// Cancel the unfinished runs, then start a new one
+    CancelPrevious,
+}
+
+impl TriggerDefinition {
+    /// Validate the trigger definition
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            TriggerDefinition::Webhook { path, method, validation, respond_with, response, payload_mapping, dedup, auth, lane, request_fields } => {
+                if path.is_empty() {
+                    return Err("Webhook path cannot be empty".to_string());
+                }
+                if method.is_empty() {
+                    return Err("Webhook method cannot be empty".to_string());
+                }
+                let valid_methods = ["GET", "POST", "PUT", "DELETE", "PATCH"];
+                if !valid_methods.contains(&method.to_uppercase().as_str()) {
+                    return Err(format!("Invalid HTTP method: {}", method));
+                }
+                if let Some(validation) = validation {
+                    validation.validate().map_err(|e| e.to_string())?;
+                }
+                if let Some(respond_with) = respond_with {
+                    respond_with.validate().map_err(|e| e.to_string())?;
+                }
+                if let Some(response) = response {
+                    response.validate().map_err(|e| e.to_string())?;
+                }
+                if let Some(payload_mapping) = payload_mapping {
+                    payload_mapping.validate().map_err(|e| e.to_string())?;
+                }
+                if let Some(dedup) = dedup {
+                    dedup.validate()?;
+                }
+                if let Some(auth) = auth {
+                    auth.validate().map_err(|e| e.to_string())?;
+                }
+                if let Some(lane) = lane {
+                    crate::config::LaneAllocation::validate_name(lane)?;
+                }
+                if let Some(request_fields) = request_fields {
+                    request_fields.validate().map_err(|e| e.to_string())?;
+                    if payload_mapping.iter().flat_map(|mapping| mapping.fields.keys())
+                        .any(|field| field.split('.').next() == Some(crate::triggers::WebhookRequestFields::PAYLOAD_FIELD))
+                    {
+                        return Err("Webhook payload mapping conflicts with the request fields".to_string());
+                    }
+                }
+                Ok(())
+            }
+            TriggerDefinition::Manual => Ok(()),
+            TriggerDefinition::Schedule { cron_expression, .. } => {
+                if cron_expression.trim().is_empty() {
+                    return Err("Schedule cron expression cannot be empty".to_string());
+                }
+                Ok(())
+            }
+            TriggerDefinition::Interval { every_seconds, catch_up, .. } => {
+                if *every_seconds == 0 {
+                    return Err("Interval must be at least one second".to_string());
+                }
+                if *catch_up == (CatchUpPolicy::FireAll { max: 0 }) {
+                    return Err("Catch-up must fire at least one missed firing".to_string());
+                }
+                Ok(())
+            }
+            TriggerDefinition::At { .. } => Ok(()),
+            TriggerDefinition::Event { event_name, dedup, .. } => {
+                if event_name.trim().is_empty() {
+                    return Err("Event name cannot be empty".to_string());
+                }
+                if let Some(dedup) = dedup {
+                    dedup.validate()?;
+                }
+                Ok(())
+            }
+            TriggerDefinition::Polling { url, every_seconds, auth_secret, compare_path, .. } => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(format!("Polling URL must be an http or https URL: {}", url));
+                }
+                if *every_seconds == 0 {
+                    return Err("Polling interval must be at least one second".to_string());
+                }
+                if auth_secret.as_deref().is_some_and(|secret| secret.trim().is_empty()) {
+                    return Err("Polling auth secret cannot be empty".to_string());
+                }
+                if let Some(path) = compare_path {
+                    crate::triggers::split_json_path(path)
+                        .ok_or_else(|| format!("Invalid polling compare path: {:?}", path))?;
+                }
+                Ok(())
+            }
+        }
+    }
+    
+    /// Get when an engine-fired trigger fires first, once registered at `now`
+    ///
+    /// Returns None for triggers fired from outside the engine.
+    pub fn first_fire_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            TriggerDefinition::Interval { every_seconds, .. } | TriggerDefinition::Polling { every_seconds, .. } => {
+                Some(now + chrono::Duration::seconds(*every_seconds as i64))
+            }
+            TriggerDefinition::At { run_at } => Some(*run_at),
+            _ => None,
+        }
+    }
+    
+    /// Get when an engine-fired trigger fires next, after firing for `scheduled_at`
+    ///
+    /// Interval firings and polls missed while the engine was down are all
+    /// handled by the firing that just happened, as its catch-up policy says.
+    /// One-time triggers never fire again.
+    pub fn next_fire_after(&self, scheduled_at: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            TriggerDefinition::Interval { every_seconds, .. } | TriggerDefinition::Polling { every_seconds, .. } => {
+                let every = chrono::Duration::seconds(*every_seconds as i64);
+                let missed = (now - scheduled_at).num_seconds().max(0) / *every_seconds as i64;
+                Some(scheduled_at + every * (missed as i32 + 1))
+            }
+            _ => None,
+        }
+    }
+    
+    /// Get trigger type as string
+    pub fn get_type(&self) -> &'static str {
+        match self {
+            TriggerDefinition::Webhook { .. } => "webhook",
+            TriggerDefinition::Manual => "manual",
+            TriggerDefinition::Schedule { .. } => "schedule",
+            TriggerDefinition::Interval { .. } => "interval",
+            TriggerDefinition::At { .. } => "at",
+            TriggerDefinition::Event { .. } => "event",
+            TriggerDefinition::Polling { .. } => "polling",
+        }
+    }
+}
+
+/// Workflow run state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRun {
+    pub id: Uuid,
+    pub workflow_id: String,
+    pub status: RunStatus,
+    pub payload: serde_json::Value,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    /// Number of times the stored run was rewritten, checked by conditional updates
+    #[serde(default)]
+    pub version: u64,
 }
 
 impl WorkflowRun {
@@ -512,6 +1644,18 @@ impl RunStatus {
             RunStatus::Cancelled => "cancelled",
         }
     }
+    
+    /// Parse a status name case-insensitively (e.g. "completed" or "Completed")
+    pub fn parse(value: &str) -> Option<RunStatus> {
+        match value.to_ascii_lowercase().as_str() {
+            "pending" => Some(RunStatus::Pending),
+            "running" => Some(RunStatus::Running),
+            "completed" => Some(RunStatus::Completed),
+            "failed" => Some(RunStatus::Failed),
+            "cancelled" => Some(RunStatus::Cancelled),
+            _ => None,
+        }
+    }
 }
 
 /// Step execution result
@@ -524,6 +1668,12 @@ pub struct StepResult {
     pub started_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
     pub duration_ms: Option<u64>,
+    /// Set when the output was reused from the step cache
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_hit: Option<StepCacheHit>,
+    /// Resources used by the attempt that produced the result, where measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
 }
 
 impl StepResult {
@@ -533,11 +1683,11 @@ impl StepResult {
             return Err("Step ID cannot be empty".to_string());
         }
         
-        if matches!(self.status, StepStatus::Completed | StepStatus::Failed) && self.completed_at.is_none() {
+        if matches!(self.status, StepStatus::Completed | StepStatus::Failed | StepStatus::TimedOut) && self.completed_at.is_none() {
             return Err("Completed steps must have a completed_at timestamp".to_string());
         }
         
-        if matches!(self.status, StepStatus::Failed) && self.error.is_none() {
+        if self.status.is_failure() && self.error.is_none() {
             return Err("Failed steps must have an error message".to_string());
         }
         
@@ -551,7 +1701,7 @@ impl StepResult {
     
     /// Check if step is completed (success or failure)
     pub fn is_completed(&self) -> bool {
-        matches!(self.status, StepStatus::Completed | StepStatus::Failed)
+        matches!(self.status, StepStatus::Completed | StepStatus::Failed | StepStatus::TimedOut)
     }
     
     /// Get step duration in milliseconds
@@ -562,6 +1712,60 @@ impl StepResult {
             })
         })
     }
+    
+    /// Create the result of a step that was skipped
+    pub fn skipped(step_id: &str) -> Self {
+        let now = Utc::now();
+        Self {
+            step_id: step_id.to_string(),
+            status: StepStatus::Skipped,
+            output: None,
+            error: None,
+            started_at: now,
+            completed_at: Some(now),
+            duration_ms: None,
+            cache_hit: None,
+            resources: None,
+        }
+    }
+    
+    /// Get the control directive returned by a successful step, if any
+    pub fn control(&self) -> Option<StepControl> {
+        if self.status != StepStatus::Completed {
+            return None;
+        }
+        self.output.as_ref().and_then(StepControl::from_output)
+    }
+}
+
+/// Directive a step returns in its output to steer the rest of its run
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StepControl {
+    /// Skip the remaining steps and complete the run successfully
+    CompleteRun,
+}
+
+impl StepControl {
+    /// Output key holding the directive
+    pub const KEY: &'static str = "__control";
+
+    /// Read the directive from a step output, e.g. `{"__control": "complete_run"}`
+    pub fn from_output(output: &serde_json::Value) -> Option<Self> {
+        output.get(Self::KEY).and_then(|v| serde_json::from_value(v.clone()).ok())
+    }
+
+    /// Steps of a workflow a directive skips, given the results recorded so far
+    ///
+    /// Steps that already have a result keep it; every other step is skipped.
+    pub fn skipped_steps(&self, workflow: &WorkflowDefinition, results: &[StepResult]) -> Vec<StepResult> {
+        match self {
+            StepControl::CompleteRun => workflow.steps.iter()
+                .filter(|step| !results.iter().any(|r| r.step_id == step.id && r.status.is_terminal()))
+                .map(|step| StepResult::skipped(&step.id))
+                .collect(),
+        }
+    }
 }
 
 /// Step status enumeration
@@ -573,12 +1777,19 @@ pub enum StepStatus {
     Completed,
     Failed,
     Skipped,
+    /// Aborted after running past its timeout
+    TimedOut,
 }
 
 impl StepStatus {
     /// Check if status is terminal (no further transitions possible)
     pub fn is_terminal(&self) -> bool {
-        matches!(self, StepStatus::Completed | StepStatus::Failed | StepStatus::Skipped)
+        matches!(self, StepStatus::Completed | StepStatus::Failed | StepStatus::Skipped | StepStatus::TimedOut)
+    }
+    
+    /// Check if status is a failure, including timeouts
+    pub fn is_failure(&self) -> bool {
+        matches!(self, StepStatus::Failed | StepStatus::TimedOut)
     }
     
     /// Get status as string
@@ -589,33 +1800,940 @@ impl StepStatus {
             StepStatus::Completed => "completed",
             StepStatus::Failed => "failed",
             StepStatus::Skipped => "skipped",
+            StepStatus::TimedOut => "timed_out",
+        }
+    }
+    
+    /// Parse a status name case-insensitively (e.g. "skipped" or "Skipped")
+    pub fn parse(value: &str) -> Option<StepStatus> {
+        match value.to_ascii_lowercase().as_str() {
+            "pending" => Some(StepStatus::Pending),
+            "running" => Some(StepStatus::Running),
+            "completed" => Some(StepStatus::Completed),
+            "failed" => Some(StepStatus::Failed),
+            "skipped" => Some(StepStatus::Skipped),
+            "timed_out" | "timedout" => Some(StepStatus::TimedOut),
+            _ => None,
         }
     }
 } 
 
-/// Workflow completion context for hook execution
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WorkflowCompletionContext {
-    /// Run ID
-    pub run_id: String,
-    /// Workflow ID
-    pub workflow_id: String,
-    /// Final run status
-    pub status: RunStatus,
-    /// All completed step results
-    pub completed_steps: Vec<StepResult>,
-    /// Error message if workflow failed
-    pub error: Option<String>,
-    /// Total execution duration in milliseconds
-    pub duration_ms: Option<u64>,
-    /// Workflow start time
-    pub started_at: chrono::DateTime<Utc>,
-    /// Workflow completion time
-    pub completed_at: chrono::DateTime<Utc>,
-    /// Original payload that triggered the workflow
-    pub payload: serde_json::Value,
-    /// Final workflow output (last step result)
-    pub final_output: Option<serde_json::Value>,
+/// Filters and pagination for listing workflow runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunQuery {
+    pub workflow_id: Option<String>,
+    pub status: Option<RunStatus>,
+    /// Only include runs started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only include pinned (`true`) or unpinned (`false`) runs
+    pub pinned: Option<bool>,
+    /// Only include runs with all of these labels
+    #[serde(default)]
+    pub labels: RunLabels,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl RunQuery {
+    /// Default page size when no limit is given
+    pub const DEFAULT_LIMIT: usize = 50;
+    /// Largest page size a single query may request
+    pub const MAX_LIMIT: usize = 1000;
+    
+    /// Get the effective page size
+    pub fn effective_limit(&self) -> usize {
+        self.limit.unwrap_or(Self::DEFAULT_LIMIT).clamp(1, Self::MAX_LIMIT)
+    }
+}
+
+/// Summary of an operator's bulk cancel or retry of a workflow's runs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BulkRunSummary {
+    pub workflow_id: String,
+    /// Number of runs the operation changed
+    pub count: usize,
+    /// Runs the operation changed, oldest first
+    pub run_ids: Vec<String>,
+}
+
+/// A page of workflow runs matching a query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPage {
+    pub runs: Vec<RunListEntry>,
+    /// Total number of runs matching the filters, ignoring pagination
+    pub total: usize,
+    pub limit: usize,
+    pub offset: usize,
+}
+
+/// Workflow run as returned by run listings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunListEntry {
+    #[serde(flatten)]
+    pub run: WorkflowRun,
+    /// Present when the run is pinned
+    pub pin: Option<RunPin>,
+    #[serde(default, skip_serializing_if = "RunLabels::is_empty")]
+    pub labels: RunLabels,
+}
+
+/// Key/value labels of a run, e.g. a customer ID, environment or release version
+pub type RunLabels = std::collections::BTreeMap<String, String>;
+
+/// Most labels a single run may have
+pub const MAX_RUN_LABELS: usize = 32;
+
+/// Check label keys and values are within bounds
+///
+/// Keys are up to 64 letters, digits, `.`, `_`, `-` or `/`; values up to 256 characters.
+pub fn validate_run_labels(labels: &RunLabels) -> Result<(), String> {
+    if labels.len() > MAX_RUN_LABELS {
+        return Err(format!("A run can have at most {} labels, got {}", MAX_RUN_LABELS, labels.len()));
+    }
+    for (key, value) in labels {
+        if key.is_empty() || key.len() > 64 {
+            return Err(format!("Label key {:?} must be 1 to 64 characters", key));
+        }
+        if !key.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '/')) {
+            return Err(format!("Label key {:?} may only contain letters, digits, '.', '_', '-' and '/'", key));
+        }
+        if value.chars().count() > 256 {
+            return Err(format!("Value of label {} is longer than 256 characters", key));
+        }
+    }
+    Ok(())
+}
+
+/// Pin that exempts a run from retention and archival cleanup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPin {
+    pub run_id: String,
+    pub reason: String,
+    pub pinned_by: Option<String>,
+    pub pinned_at: DateTime<Utc>,
+}
+
+/// Audit trail entry for pinning and unpinning a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPinAuditEntry {
+    pub run_id: String,
+    /// Either "pin" or "unpin"
+    pub action: String,
+    pub reason: Option<String>,
+    pub actor: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Workflow taken out of service, whose triggers are rejected until it is enabled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisabledWorkflow {
+    pub workflow_id: String,
+    pub reason: Option<String>,
+    pub disabled_at: DateTime<Utc>,
+    /// End of a maintenance window, after which the workflow is enabled again
+    pub until: Option<DateTime<Utc>>,
+}
+
+impl DisabledWorkflow {
+    /// Whether the workflow is still disabled at `now`
+    pub fn is_active(&self, now: DateTime<Utc>) -> bool {
+        self.until.is_none_or(|until| now < until)
+    }
+}
+
+/// Execution trace event recorded for a step, used to explain runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepTraceEvent {
+    pub run_id: String,
+    pub step_id: String,
+    /// Event kind, e.g. "condition_evaluated", "skipped" or "retry_scheduled"
+    pub event: String,
+    pub detail: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Entry of a run's append-only event log, recording one state transition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunEvent {
+    pub run_id: String,
+    /// Event kind, e.g. "run_created", "step_started" or "hook_fired"
+    pub event_type: String,
+    pub step_id: Option<String>,
+    /// Who caused the transition: "engine", "api", "trigger" or a worker ID
+    pub actor: String,
+    pub detail: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RunEvent {
+    pub const RUN_CREATED: &'static str = "run_created";
+    pub const RUN_PAUSED: &'static str = "run_paused";
+    pub const RUN_RESUMED: &'static str = "run_resumed";
+    pub const RUN_RETRIED: &'static str = "run_retried";
+    pub const STEP_STARTED: &'static str = "step_started";
+    pub const STEP_RETRIED: &'static str = "step_retried";
+    pub const STEP_TIMED_OUT: &'static str = "step_timed_out";
+    pub const STEP_DELAYED: &'static str = "step_delayed";
+    pub const SIGNAL_AWAITED: &'static str = "signal_awaited";
+    pub const SIGNAL_RECEIVED: &'static str = "signal_received";
+    pub const SUBWORKFLOW_STARTED: &'static str = "subworkflow_started";
+    pub const STEP_INTERRUPTED: &'static str = "step_interrupted";
+    pub const STEP_CANCELLED: &'static str = "step_cancelled";
+    pub const HOOK_FIRED: &'static str = "hook_fired";
+    pub const HOOK_SUCCEEDED: &'static str = "hook_succeeded";
+    pub const HOOK_FAILED: &'static str = "hook_failed";
+    pub const RECOVERY_CONFIRMED: &'static str = "recovery_confirmed";
+    pub const RUN_RECOVERED: &'static str = "run_recovered";
+    pub const RUN_TIMED_OUT: &'static str = "run_timed_out";
+    pub const FAULT_INJECTED: &'static str = "fault_injected";
+    pub const RETRY_CLASSIFIED: &'static str = "retry_classified";
+
+    pub const ACTOR_ENGINE: &'static str = "engine";
+    pub const ACTOR_API: &'static str = "api";
+    pub const ACTOR_TRIGGER: &'static str = "trigger";
+
+    /// Event type recorded when a run moves to a status
+    pub fn for_run_status(status: &RunStatus) -> &'static str {
+        match status {
+            RunStatus::Pending => "run_pending",
+            RunStatus::Running => "run_started",
+            RunStatus::Completed => "run_completed",
+            RunStatus::Failed => "run_failed",
+            RunStatus::Cancelled => "run_cancelled",
+        }
+    }
+
+    /// Event type recorded when a step result is saved with a status
+    pub fn for_step_status(status: &StepStatus) -> &'static str {
+        match status {
+            StepStatus::Pending => "step_pending",
+            StepStatus::Running => Self::STEP_STARTED,
+            StepStatus::Completed => "step_completed",
+            StepStatus::Failed => "step_failed",
+            StepStatus::Skipped => "step_skipped",
+            StepStatus::TimedOut => Self::STEP_TIMED_OUT,
+        }
+    }
+
+    /// Detail recorded with a step result event
+    pub fn step_result_detail(result: &StepResult) -> serde_json::Value {
+        serde_json::json!({
+            "duration_ms": result.duration_ms,
+            "error": result.error,
+        })
+    }
+}
+
+/// How recovery treats interrupted steps that are not idempotent
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NonIdempotentRecovery {
+    /// Wait for an operator to confirm re-execution
+    #[default]
+    Confirm,
+    /// Skip the step
+    Skip,
+}
+
+impl NonIdempotentRecovery {
+    /// Parse a policy name ("confirm" or "skip")
+    pub fn parse(value: &str) -> Option<NonIdempotentRecovery> {
+        match value.to_ascii_lowercase().as_str() {
+            "confirm" => Some(NonIdempotentRecovery::Confirm),
+            "skip" => Some(NonIdempotentRecovery::Skip),
+            _ => None,
+        }
+    }
+}
+
+/// What startup recovery does with runs left running by a crashed engine
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanRunPolicy {
+    /// Re-queue the run's incomplete steps, following the recovery plan
+    #[default]
+    Requeue,
+    /// Mark the run failed
+    Fail,
+}
+
+impl OrphanRunPolicy {
+    /// Parse a policy name ("requeue" or "fail")
+    pub fn parse(value: &str) -> Option<OrphanRunPolicy> {
+        match value.to_ascii_lowercase().as_str() {
+            "requeue" => Some(OrphanRunPolicy::Requeue),
+            "fail" => Some(OrphanRunPolicy::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// What startup recovery did with an orphaned run
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum OrphanRunOutcome {
+    /// Incomplete steps are run again
+    Requeued,
+    /// Left running until an operator confirms re-executing its non-idempotent steps
+    AwaitingConfirmation,
+    Failed,
+}
+
+/// Run found orphaned by startup recovery
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanedRun {
+    pub run_id: String,
+    pub workflow_id: String,
+    /// Latest step or event recorded for the run
+    pub last_activity_at: DateTime<Utc>,
+    pub outcome: OrphanRunOutcome,
+    /// Recovery plan of the run's incomplete steps, absent if its workflow no longer exists
+    pub plan: Option<RunRecoveryPlan>,
+}
+
+/// Result of the recovery pass run when the engine starts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanRecoveryReport {
+    pub recovered_at: DateTime<Utc>,
+    pub policy: OrphanRunPolicy,
+    /// Running runs with activity more recent than this were left alone
+    pub lease_secs: u64,
+    pub runs: Vec<OrphanedRun>,
+}
+
+/// What recovery does with a step
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    ReExecute,
+    AwaitConfirmation,
+    Skip,
+}
+
+/// Recovery decision for one step of a run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepRecovery {
+    pub step_id: String,
+    pub idempotent: bool,
+    pub action: RecoveryAction,
+    pub reason: String,
+}
+
+/// Which steps of a run may be re-executed after a crash or during replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRecoveryPlan {
+    pub run_id: String,
+    pub replay: bool,
+    pub steps: Vec<StepRecovery>,
+}
+
+impl RunRecoveryPlan {
+    /// Check whether recovery can proceed without operator confirmation
+    pub fn is_ready(&self) -> bool {
+        self.steps.iter().all(|step| step.action != RecoveryAction::AwaitConfirmation)
+    }
+}
+
+/// Why a single step ended up in its current state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepExplanation {
+    pub step_id: String,
+    pub name: String,
+    /// One of completed, failed, skipped, running, pending, blocked or not_reached
+    pub outcome: String,
+    pub depends_on: Vec<String>,
+    /// Dependencies that never completed, when the step did not run
+    pub blocked_by: Vec<String>,
+    /// Time between the last dependency finishing and this step starting
+    pub waited_ms: Option<u64>,
+    pub duration_ms: Option<u64>,
+    pub attempts: usize,
+    pub error: Option<String>,
+    pub events: Vec<StepTraceEvent>,
+    /// Human-readable summary of the above
+    pub explanation: String,
+}
+
+/// Structured and narrative explanation of a workflow run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunExplanation {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub status: RunStatus,
+    pub duration_ms: Option<u64>,
+    pub steps: Vec<StepExplanation>,
+    /// One line for the run followed by one line per step
+    pub narrative: Vec<String>,
+}
+
+/// Step result reported by the Node.js side for a specific run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepCompletion {
+    pub run_id: String,
+    #[serde(flatten)]
+    pub result: StepResult,
+    /// Checksum of the context the step was executed with
+    #[serde(default)]
+    pub context_checksum: Option<String>,
+    /// Schema version of the context the step was executed with
+    #[serde(default)]
+    pub context_schema_version: Option<u32>,
+}
+
+/// Run to create as part of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunRequest {
+    pub workflow_id: String,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    #[serde(default)]
+    pub correlation_id: Option<String>,
+}
+
+/// Checksum of the latest context issued to Bun.js for a step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepContextChecksum {
+    pub run_id: String,
+    pub step_id: String,
+    pub checksum: String,
+    pub schema_version: u32,
+    pub attempt: u32,
+    pub issued_at: DateTime<Utc>,
+}
+
+/// SQLite settings in effect on the engine's database connection
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SqliteSettings {
+    /// In-memory databases always report "memory"
+    pub journal_mode: String,
+    pub synchronous: String,
+    pub busy_timeout_ms: u64,
+    pub cache_size: i64,
+    pub mmap_size: u64,
+}
+
+/// Backup of the engine's database taken with SQLite's online backup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct DatabaseSnapshot {
+    pub path: String,
+    pub pages: u32,
+    pub size_bytes: u64,
+    pub taken_at: DateTime<Utc>,
+}
+
+/// Migration recorded as applied to a database
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    pub applied_at: DateTime<Utc>,
+}
+
+/// Schema version of a database and of the engine reading it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SchemaVersion {
+    /// Latest migration applied to the database
+    pub version: u32,
+    /// Latest migration this engine knows
+    pub supported_version: u32,
+    pub migrations: Vec<AppliedMigration>,
+}
+
+/// Diagnostics of a single dispatcher worker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerDiagnostics {
+    pub worker_id: String,
+    pub status: String,
+    pub current_job_id: Option<String>,
+    pub jobs_processed: u64,
+    pub error_count: u64,
+    pub total_processing_time_ms: u64,
+    pub average_duration_ms: u64,
+    /// Most recent job IDs, newest last
+    pub recent_job_ids: Vec<String>,
+    pub last_panic: Option<String>,
+    pub last_panic_at: Option<DateTime<Utc>>,
+    pub last_activity: DateTime<Utc>,
+}
+
+/// Scope of a context state entry
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StateScope {
+    /// Shared by the steps of one run
+    Run,
+    /// Shared by every run of a workflow
+    Workflow,
+}
+
+impl StateScope {
+    /// Parse a scope name
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "run" => Some(StateScope::Run),
+            "workflow" => Some(StateScope::Workflow),
+            _ => None,
+        }
+    }
+    
+    /// Get scope as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StateScope::Run => "run",
+            StateScope::Workflow => "workflow",
+        }
+    }
+}
+
+/// Value stored in the context state of a run or workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub scope: StateScope,
+    /// Run ID or workflow ID, depending on the scope
+    pub scope_id: String,
+    pub key: String,
+    pub value: serde_json::Value,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Severity of a step log line
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StepLogLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl StepLogLevel {
+    /// Parse a level name ("debug", "info", "warn" or "error")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "debug" => Some(StepLogLevel::Debug),
+            "info" => Some(StepLogLevel::Info),
+            "warn" | "warning" => Some(StepLogLevel::Warn),
+            "error" => Some(StepLogLevel::Error),
+            _ => None,
+        }
+    }
+    
+    /// Get level as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StepLogLevel::Debug => "debug",
+            StepLogLevel::Info => "info",
+            StepLogLevel::Warn => "warn",
+            StepLogLevel::Error => "error",
+        }
+    }
+}
+
+/// Structured log line a step appended while it ran
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepLogEntry {
+    /// Position in the log, increasing across all runs; pass the last one seen to read on
+    pub seq: i64,
+    pub run_id: String,
+    pub step_id: String,
+    pub level: StepLogLevel,
+    pub message: String,
+    /// Structured fields attached to the line
+    pub fields: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StepLogEntry {
+    /// Most log lines returned by one read
+    pub const PAGE_SIZE: usize = 500;
+}
+
+/// Secret encrypted at rest, injected into the contexts of the steps allowed to see it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredSecret {
+    pub key: String,
+    /// Workflow ID whose steps receive the secret, or "global" for every workflow
+    pub scope: String,
+    /// Hex-encoded AES-256-GCM nonce
+    pub nonce: String,
+    /// Hex-encoded ciphertext including the authentication tag
+    pub ciphertext: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Result of a step executed ad hoc, outside any run
+///
+/// Ad hoc executions live in a scratch area and expire, so they never show up
+/// in run history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdhocStepExecution {
+    pub id: String,
+    pub workflow_id: String,
+    pub step_id: String,
+    pub payload: serde_json::Value,
+    pub result: StepResult,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Trigger of a registered workflow as kept in the state store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredTrigger {
+    pub id: String,
+    pub workflow_id: String,
+    pub trigger: TriggerDefinition,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StoredTrigger {
+    /// Get the stored form of each trigger of a workflow, keyed by its position
+    pub fn for_workflow(workflow: &WorkflowDefinition) -> Vec<StoredTrigger> {
+        workflow.triggers.iter()
+            .enumerate()
+            .map(|(index, trigger)| StoredTrigger {
+                id: format!("{}:{}", workflow.id, index),
+                workflow_id: workflow.id.clone(),
+                trigger: trigger.clone(),
+                created_at: workflow.updated_at,
+            })
+            .collect()
+    }
+}
+
+/// Persisted firing state of an interval, polling or one-time trigger
+///
+/// Kept apart from the trigger definitions so that re-registering an
+/// unchanged workflow on startup does not reset its schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TriggerSchedule {
+    /// ID of the stored trigger this schedule fires
+    pub trigger_id: String,
+    pub workflow_id: String,
+    pub trigger: TriggerDefinition,
+    /// None once a one-time trigger has fired
+    pub next_fire_at: Option<DateTime<Utc>>,
+    pub last_fired_at: Option<DateTime<Utc>>,
+}
+
+impl TriggerSchedule {
+    /// Whether the schedule should fire at `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_fire_at.is_some_and(|at| at <= now)
+    }
+    
+    /// Get the scheduled times a due schedule starts runs for at `now`, oldest first,
+    /// and how many firings were missed since it last fired
+    ///
+    /// No firing is missed while the scheduler keeps up; the times are then
+    /// just the schedule's next firing.
+    pub fn due_firings(&self, now: DateTime<Utc>) -> (Vec<DateTime<Utc>>, usize) {
+        let Some(next_fire_at) = self.next_fire_at.filter(|at| *at <= now) else {
+            return (Vec::new(), 0);
+        };
+        let TriggerDefinition::Interval { every_seconds, catch_up, .. } = &self.trigger else {
+            return (vec![next_fire_at], 0);
+        };
+        let every = chrono::Duration::seconds(*every_seconds as i64);
+        let slots: Vec<DateTime<Utc>> = std::iter::successors(Some(next_fire_at), |at| Some(*at + every))
+            .take_while(|at| *at <= now)
+            .collect();
+        if slots.len() == 1 {
+            return (slots, 0);
+        }
+        let missed = slots.len();
+        let fired = match catch_up {
+            CatchUpPolicy::None => Vec::new(),
+            CatchUpPolicy::FireOnce => slots[missed - 1..].to_vec(),
+            CatchUpPolicy::FireAll { max } => slots[missed.saturating_sub(*max as usize)..].to_vec(),
+        };
+        (fired, missed)
+    }
+}
+
+/// Last response value seen by a polling trigger
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriggerPollState {
+    pub trigger_id: String,
+    pub workflow_id: String,
+    /// SHA-256 of the compared value
+    pub fingerprint: String,
+    /// Value at the trigger's compare path, or the whole response
+    pub value: serde_json::Value,
+    pub checked_at: DateTime<Utc>,
+    /// When the value last changed, None while it is the one first seen
+    pub changed_at: Option<DateTime<Utc>>,
+}
+
+/// What became of a trigger's firings
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TriggerCounters {
+    /// Every firing, whatever became of it
+    pub fired: u64,
+    /// Firings that started, buffered, skipped or deduplicated a run
+    pub succeeded: u64,
+    /// Firings refused for any other reason, e.g. an invalid request or a disabled workflow
+    pub rejected: u64,
+    pub auth_failed: u64,
+    pub rate_limited: u64,
+}
+
+impl TriggerCounters {
+    /// Add another trigger's or period's counts to these
+    pub fn add(&mut self, other: &TriggerCounters) {
+        self.fired += other.fired;
+        self.succeeded += other.succeeded;
+        self.rejected += other.rejected;
+        self.auth_failed += other.auth_failed;
+        self.rate_limited += other.rate_limited;
+    }
+}
+
+/// Firing counts of one trigger
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TriggerCounterRecord {
+    /// Trigger type and source, e.g. `webhook:/webhook/orders` or `event:order.created`
+    pub trigger: String,
+    pub workflow_id: String,
+    #[serde(flatten)]
+    pub counters: TriggerCounters,
+}
+
+/// Persisted wake-up of a run waiting on a delay step
+///
+/// The scheduler resumes the run once `wake_at` has passed, so delays
+/// survive restarts without holding a worker.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StepDelay {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub step_id: String,
+    pub wake_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl StepDelay {
+    /// Whether the run should be woken at `now`
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.wake_at <= now
+    }
+}
+
+/// Persisted wait of a run on a signal step
+///
+/// The run stays paused until the signal is sent to it, or until the
+/// scheduler wakes it at `timeout_at` to fail the step.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SignalWait {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub step_id: String,
+    pub signal_name: String,
+    pub timeout_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SignalWait {
+    /// Whether the wait has timed out at `now`
+    pub fn is_timed_out(&self, now: DateTime<Utc>) -> bool {
+        self.timeout_at.is_some_and(|at| at <= now)
+    }
+}
+
+/// Signal sent to a run, kept until a signal step of the run consumes it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunSignal {
+    pub id: i64,
+    pub run_id: String,
+    pub signal_name: String,
+    pub payload: serde_json::Value,
+    pub received_at: DateTime<Utc>,
+}
+
+/// Link from a child run to the sub-workflow step of the parent run that started it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunParent {
+    pub run_id: String,
+    pub parent_run_id: String,
+    pub parent_step_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RunParent {
+    /// Most runs a chain of sub-workflows may nest, counting the outermost run
+    pub const MAX_DEPTH: usize = 16;
+}
+
+/// What started a run, recorded when the run is created
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunTrigger {
+    /// Trigger type, e.g. "webhook", "manual", "interval", "event" or "subworkflow"
+    pub trigger_type: String,
+    /// Webhook path, trigger ID, event name or parent run the run was started by
+    pub source: Option<String>,
+    pub fired_at: DateTime<Utc>,
+    /// W3C `traceparent` of the caller, so the run joins the caller's trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_parent: Option<String>,
+    /// Dispatcher lane the trigger picked for the run, None to use the workflow's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lane: Option<String>,
+    /// Labels the trigger gave the run, kept with the run's labels rather than the trigger
+    #[serde(default, skip_serializing_if = "RunLabels::is_empty")]
+    pub labels: RunLabels,
+}
+
+impl RunTrigger {
+    pub const SUBWORKFLOW: &'static str = "subworkflow";
+
+    pub fn new(trigger_type: &str, source: Option<&str>) -> Self {
+        RunTrigger {
+            trigger_type: trigger_type.to_string(),
+            source: source.map(str::to_string),
+            fired_at: Utc::now(),
+            trace_parent: None,
+            lane: None,
+            labels: RunLabels::new(),
+        }
+    }
+
+    /// Run the started run in a dispatcher lane
+    pub fn with_lane(mut self, lane: Option<&str>) -> Self {
+        self.lane = lane.map(str::to_string);
+        self
+    }
+
+    /// Label the started run
+    pub fn with_labels(mut self, labels: RunLabels) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Attach the trace context the run was started in, ignoring malformed headers
+    pub fn with_trace_parent(mut self, trace_parent: Option<&str>) -> Self {
+        self.trace_parent = trace_parent
+            .filter(|header| crate::telemetry::parse_traceparent(header).is_some())
+            .map(|header| header.trim().to_string());
+        self
+    }
+}
+
+/// Registered JSON Schema of one version of an event
+///
+/// Versions are immutable once registered; a changed payload shape gets a new version.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EventSchema {
+    pub event_name: String,
+    pub version: u32,
+    pub schema: serde_json::Value,
+    pub registered_at: DateTime<Utc>,
+}
+
+/// Registered revision of a workflow definition
+///
+/// Registering a changed definition adds a version; runs keep executing with
+/// the version they started on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowVersion {
+    pub workflow_id: String,
+    pub version: u32,
+    pub definition: WorkflowDefinition,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Large step output stored apart from its step result
+///
+/// Blobs are keyed by the SHA-256 of their data, so identical outputs share one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PayloadBlob {
+    pub id: String,
+    /// Serialized JSON output
+    pub data: String,
+    pub size_bytes: usize,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Outcome of persisting a batch of step completions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepBatchCompletionSummary {
+    pub persisted_steps: usize,
+    /// Run ID to run status after the batch was applied
+    pub runs: std::collections::HashMap<String, RunStatus>,
+}
+
+/// Step result flattened with its parent run, used for history exports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepHistoryRecord {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub run_status: String,
+    pub run_started_at: DateTime<Utc>,
+    pub step_id: String,
+    pub step_status: String,
+    /// Step output as stored (JSON text)
+    pub output: Option<String>,
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub duration_ms: Option<u64>,
+}
+
+/// Completed, failed or cancelled run, as considered by retention cleanup
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FinishedRun {
+    pub run_id: String,
+    pub workflow_id: String,
+    pub status: RunStatus,
+    /// When the run completed, or started if no completion time was recorded
+    pub finished_at: DateTime<Utc>,
+}
+
+/// Workflow completion context for hook execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowCompletionContext {
+    /// Run ID
+    pub run_id: String,
+    /// Workflow ID
+    pub workflow_id: String,
+    /// Final run status
+    pub status: RunStatus,
+    /// All completed step results
+    pub completed_steps: Vec<StepResult>,
+    /// Error message if workflow failed
+    pub error: Option<String>,
+    /// Total execution duration in milliseconds
+    pub duration_ms: Option<u64>,
+    /// Workflow start time
+    pub started_at: chrono::DateTime<Utc>,
+    /// Workflow completion time
+    pub completed_at: chrono::DateTime<Utc>,
+    /// Original payload that triggered the workflow
+    pub payload: serde_json::Value,
+    /// Final workflow output (last step result)
+    pub final_output: Option<serde_json::Value>,
+    /// How the run was cancelled, for cancelled runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cancellation: Option<RunCancellation>,
+}
+
+/// Why a run was cancelled
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelReason {
+    /// Cancelled through the API, by a schedule overlap policy or with its parent run
+    Manual,
+    /// Exceeded its workflow's run timeout
+    Timeout,
+}
+
+/// How a cancelled run ended, passed to its onCancel hook
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunCancellation {
+    pub reason: CancelReason,
+    /// Message the run was cancelled with
+    pub message: Option<String>,
+    /// Steps that had started but not finished, whose side effects may need undoing
+    pub interrupted_steps: Vec<String>,
 }
 
 impl WorkflowCompletionContext {
@@ -644,9 +2762,16 @@ impl WorkflowCompletionContext {
             completed_at,
             payload,
             final_output,
+            cancellation: None,
         }
     }
     
+    /// Attach how a cancelled run was cancelled
+    pub fn with_cancellation(mut self, cancellation: RunCancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+    
     /// Check if workflow completed successfully
     pub fn is_success(&self) -> bool {
         matches!(self.status, RunStatus::Completed)
@@ -657,6 +2782,20 @@ impl WorkflowCompletionContext {
         matches!(self.status, RunStatus::Failed)
     }
     
+    /// Check if workflow was cancelled
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.status, RunStatus::Cancelled)
+    }
+    
+    /// Hook run for the final status: onSuccess, onCancel for cancelled runs, or onFailure
+    pub fn hook_type(&self) -> &'static str {
+        match self.status {
+            RunStatus::Completed => "onSuccess",
+            RunStatus::Cancelled => "onCancel",
+            _ => "onFailure",
+        }
+    }
+    
     /// Get number of completed steps
     pub fn completed_step_count(&self) -> usize {
         self.completed_steps.len()
@@ -665,7 +2804,7 @@ impl WorkflowCompletionContext {
     /// Get number of failed steps
     pub fn failed_step_count(&self) -> usize {
         self.completed_steps.iter()
-            .filter(|step| matches!(step.status, StepStatus::Failed))
+            .filter(|step| step.status.is_failure())
             .count()
     }
 } 
@@ -691,6 +2830,9 @@ pub struct ParallelStepGroup {
     pub fail_fast: bool,
     /// Maximum timeout for the entire parallel group
     pub timeout_ms: Option<u64>,
+    /// Step that won the race, for race groups
+    #[serde(default)]
+    pub winner: Option<String>,
 }
 
 impl ParallelStepGroup {
@@ -706,6 +2848,7 @@ impl ParallelStepGroup {
             error: None,
             fail_fast: true, // Default to fail fast
             timeout_ms: None,
+            winner: None,
         }
     }
     
@@ -752,7 +2895,7 @@ impl ParallelStepGroup {
     pub fn has_failures(&self) -> bool {
         self.step_ids.iter().any(|step_id| {
             self.results.contains_key(step_id.as_str()) && 
-            matches!(self.results[step_id.as_str()].status, StepStatus::Failed)
+            self.results[step_id.as_str()].status.is_failure()
         })
     }
     
@@ -768,7 +2911,7 @@ impl ParallelStepGroup {
     pub fn failed_count(&self) -> usize {
         self.step_ids.iter().filter(|step_id| {
             self.results.contains_key(step_id.as_str()) && 
-            matches!(self.results[step_id.as_str()].status, StepStatus::Failed)
+            self.results[step_id.as_str()].status.is_failure()
         }).count()
     }
     
@@ -0,0 +1,74 @@
+//! Output schemas of steps
+//!
+//! A step can declare a JSON Schema its output must match. Outputs are checked
+//! as completed results are recorded: a mismatch fails the step, without
+//! retrying it, since running the same code again returns the same shape. The
+//! declared properties are also the step's contract with the steps reading its
+//! output, which workflow analysis checks when the workflow is registered.
+
+use jsonschema::JSONSchema;
+use regex::Regex;
+use std::sync::OnceLock;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{StepDefinition, StepResult, StepStatus};
+
+/// Compile the output schema of a step
+pub fn compile(schema: &serde_json::Value) -> CoreResult<JSONSchema> {
+    JSONSchema::compile(schema).map_err(|e| CoreError::Validation(e.to_string()))
+}
+
+/// Check an output against the schema a step declares, describing every mismatch
+pub fn check_output(step: &StepDefinition, output: Option<&serde_json::Value>) -> Result<(), String> {
+    let Some(schema) = &step.output_schema else {
+        return Ok(());
+    };
+    let compiled = compile(schema).map_err(|e| format!("Invalid output schema of step {}: {}", step.id, e))?;
+    let output = output.unwrap_or(&serde_json::Value::Null);
+    if let Err(errors) = compiled.validate(output) {
+        let problems: Vec<String> = errors
+            .map(|e| {
+                let path = e.instance_path.to_string();
+                format!("{}: {}", if path.is_empty() { "/" } else { path.as_str() }, e)
+            })
+            .collect();
+        return Err(format!("Output of step {} does not match its schema: {}", step.id, problems.join("; ")));
+    }
+    Ok(())
+}
+
+/// Fail a completed result whose output does not match the step's schema
+pub fn enforce(step: &StepDefinition, mut result: StepResult) -> StepResult {
+    if result.status != StepStatus::Completed {
+        return result;
+    }
+    if let Err(error) = check_output(step, result.output.as_ref()) {
+        log::warn!("{}", error);
+        result.status = StepStatus::Failed;
+        result.error = Some(error);
+    }
+    result
+}
+
+/// Whether a step's schema declares a top-level output field
+///
+/// Schemas without `properties`, such as those of arrays or free-form objects,
+/// declare no contract and are taken to allow any field.
+pub fn declares_field(schema: &serde_json::Value, field: &str) -> bool {
+    match schema.get("properties").and_then(|properties| properties.as_object()) {
+        Some(properties) => properties.contains_key(field),
+        None => true,
+    }
+}
+
+/// References to step output fields in an expression, as `(step ID, top-level field)`
+///
+/// A reference to a whole output, such as `ctx.steps.fetch.output`, has no field.
+pub fn output_references(expression: &str) -> Vec<(String, Option<String>)> {
+    static REFERENCE: OnceLock<Regex> = OnceLock::new();
+    let reference = REFERENCE.get_or_init(|| {
+        Regex::new(r"\bsteps\.([A-Za-z0-9_-]+)\.output(?:\.([A-Za-z0-9_$-]+))?").expect("valid output reference pattern")
+    });
+    reference.captures_iter(expression)
+        .map(|captures| (captures[1].to_string(), captures.get(2).map(|field| field.as_str().to_string())))
+        .collect()
+}
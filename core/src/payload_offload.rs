@@ -0,0 +1,151 @@
+//! Step output size limits and offloading
+//!
+//! Step outputs above a size threshold are moved out of the step results into
+//! a content-addressed blob store, leaving a small reference behind. Reading
+//! step results stays cheap, and large outputs are only loaded when a step
+//! context needs them. Outputs above the hard limit are rejected.
+
+use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use crate::config::PayloadConfig;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{PayloadBlob, StepResult};
+
+/// Key marking an output that was moved to the blob store
+pub const BLOB_REF_KEY: &str = "_payload_blob";
+
+/// Size limits applied to step outputs before they are persisted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutputLimits {
+    /// Largest output accepted, in serialized bytes
+    pub max_bytes: usize,
+    /// Outputs larger than this are offloaded to the blob store
+    pub offload_bytes: usize,
+}
+
+impl OutputLimits {
+    /// Get the output limits of a payload configuration
+    pub fn from_config(config: &PayloadConfig) -> Self {
+        OutputLimits {
+            max_bytes: config.max_step_output_bytes,
+            offload_bytes: config.step_output_offload_bytes,
+        }
+    }
+}
+
+/// Get the ID of the blob an output points to, if it was offloaded
+pub fn blob_id(output: &serde_json::Value) -> Option<&str> {
+    output.get(BLOB_REF_KEY).and_then(|v| v.as_str())
+}
+
+/// Check a step result against the output limits, offloading a large output
+///
+/// Returns the result to persist and the blob to store alongside it, if any.
+pub fn offload(mut result: StepResult, limits: OutputLimits) -> CoreResult<(StepResult, Option<PayloadBlob>)> {
+    let Some(output) = &result.output else {
+        return Ok((result, None));
+    };
+    if blob_id(output).is_some() {
+        return Ok((result, None));
+    }
+
+    let data = serde_json::to_string(output)?;
+    if data.len() > limits.max_bytes {
+        return Err(CoreError::Validation(format!(
+            "Output of step '{}' is {} bytes, over the {} byte limit",
+            result.step_id, data.len(), limits.max_bytes
+        )));
+    }
+    if data.len() <= limits.offload_bytes {
+        return Ok((result, None));
+    }
+
+    let blob = PayloadBlob {
+        id: hex::encode(Sha256::digest(data.as_bytes())),
+        size_bytes: data.len(),
+        data,
+        created_at: chrono::Utc::now(),
+    };
+    result.output = Some(serde_json::json!({
+        BLOB_REF_KEY: blob.id,
+        "size_bytes": blob.size_bytes,
+    }));
+    Ok((result, Some(blob)))
+}
+
+/// Get the IDs of the blobs referenced by step outputs
+pub fn blob_ids(results: &[StepResult]) -> Vec<String> {
+    let mut ids: Vec<String> = results.iter()
+        .filter_map(|r| r.output.as_ref().and_then(blob_id))
+        .map(str::to_string)
+        .collect();
+    ids.sort();
+    ids.dedup();
+    ids
+}
+
+/// Replace blob references in step outputs by the outputs they point to
+///
+/// `blobs` maps blob IDs to their serialized data.
+pub fn resolve(results: &mut [StepResult], blobs: &HashMap<String, String>) -> CoreResult<()> {
+    for result in results.iter_mut() {
+        let Some(id) = result.output.as_ref().and_then(blob_id) else {
+            continue;
+        };
+        let data = blobs.get(id).ok_or_else(|| CoreError::State(format!(
+            "Output blob {} of step '{}' is missing", id, result.step_id
+        )))?;
+        result.output = Some(serde_json::from_str(data)?);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StepStatus;
+    use chrono::Utc;
+
+    fn result(step_id: &str, output: serde_json::Value) -> StepResult {
+        StepResult {
+            step_id: step_id.to_string(),
+            status: StepStatus::Completed,
+            output: Some(output),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(5),
+            cache_hit: None,
+            resources: None,
+        }
+    }
+
+    #[test]
+    fn test_large_outputs_are_offloaded_and_resolved() {
+        let limits = OutputLimits { max_bytes: 1_000, offload_bytes: 100 };
+
+        let (small, blob) = offload(result("small", serde_json::json!({"ok": true})), limits).unwrap();
+        assert!(blob.is_none());
+        assert_eq!(small.output, Some(serde_json::json!({"ok": true})));
+
+        let large_output = serde_json::json!({"body": "x".repeat(500)});
+        let (large, blob) = offload(result("large", large_output.clone()), limits).unwrap();
+        let blob = blob.unwrap();
+        assert_eq!(blob_id(large.output.as_ref().unwrap()), Some(blob.id.as_str()));
+        assert_eq!(large.output.as_ref().unwrap()["size_bytes"], blob.size_bytes);
+        assert!(offload(large.clone(), limits).unwrap().1.is_none());
+
+        let (copy, same_blob) = offload(result("copy", large_output.clone()), limits).unwrap();
+        assert_eq!(same_blob.unwrap().id, blob.id);
+
+        let mut results = vec![small, large, copy];
+        assert_eq!(blob_ids(&results), vec![blob.id.clone()]);
+        assert!(resolve(&mut results, &HashMap::new()).is_err());
+        resolve(&mut results, &HashMap::from([(blob.id.clone(), blob.data.clone())])).unwrap();
+        assert_eq!(results[1].output, Some(large_output.clone()));
+        assert_eq!(results[2].output, Some(large_output));
+
+        let too_large = result("too_large", serde_json::json!({"body": "x".repeat(2_000)}));
+        assert!(matches!(offload(too_large, limits), Err(CoreError::Validation(_))));
+    }
+}
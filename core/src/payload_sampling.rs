@@ -0,0 +1,117 @@
+//! Run payload sampling
+//!
+//! High-volume workflows can store the full payload for only a sample of
+//! their runs. Runs outside the sample keep a hash of their payload once they
+//! complete successfully; failed runs always keep the full payload so they
+//! stay debuggable.
+
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+use crate::models::{RunStatus, WorkflowDefinition, WorkflowRun};
+
+/// Key marking a payload that was replaced by its hash
+pub const SAMPLED_OUT_KEY: &str = "_payload_sampled_out";
+
+/// Whether a run falls into the sample that keeps its full payload
+///
+/// The decision only depends on the run ID, so it is stable across retries
+/// and engine restarts.
+pub fn is_sampled(run_id: &Uuid, sample_rate: f64) -> bool {
+    if sample_rate >= 100.0 {
+        return true;
+    }
+    let digest = Sha256::digest(run_id.as_bytes());
+    let bucket = u16::from_be_bytes([digest[0], digest[1]]) % 10_000;
+    f64::from(bucket) < sample_rate * 100.0
+}
+
+/// Replace a payload by its hash and size
+pub fn payload_digest(payload: &serde_json::Value) -> serde_json::Value {
+    let serialized = serde_json::to_string(payload).unwrap_or_default();
+    serde_json::json!({
+        SAMPLED_OUT_KEY: true,
+        "sha256": hex::encode(Sha256::digest(serialized.as_bytes())),
+        "size_bytes": serialized.len(),
+    })
+}
+
+/// Whether a stored payload was replaced by its hash
+pub fn is_sampled_out(payload: &serde_json::Value) -> bool {
+    payload.get(SAMPLED_OUT_KEY).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+/// Get the copy of a run to persist, with its payload hashed if sampled out
+///
+/// Returns None when the run is stored as is.
+pub fn sampled_run(workflow: &WorkflowDefinition, run: &WorkflowRun) -> Option<WorkflowRun> {
+    let sample_rate = workflow.payload_sample_rate?;
+    if run.status != RunStatus::Completed || is_sampled(&run.id, sample_rate) || is_sampled_out(&run.payload) {
+        return None;
+    }
+    Some(WorkflowRun {
+        payload: payload_digest(&run.payload),
+        ..run.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn workflow(sample_rate: Option<f64>) -> WorkflowDefinition {
+        WorkflowDefinition {
+            id: "sampled".to_string(),
+            name: "Sampled".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: sample_rate,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }
+    }
+
+    fn run(status: RunStatus) -> WorkflowRun {
+        WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: "sampled".to_string(),
+            status,
+            payload: serde_json::json!({"order": 42}),
+            started_at: Utc::now(),
+            completed_at: None,
+            error: None,
+            version: 0,
+        }
+    }
+
+    #[test]
+    fn test_payload_sampling_keeps_failures_and_hashes_the_rest() {
+        let ids: Vec<Uuid> = (0..2000).map(|_| Uuid::new_v4()).collect();
+        let sampled = ids.iter().filter(|id| is_sampled(id, 10.0)).count();
+        assert!((100..300).contains(&sampled), "sampled {} of 2000", sampled);
+        assert!(ids.iter().all(|id| is_sampled(id, 100.0)));
+        assert!(!ids.iter().any(|id| is_sampled(id, 0.0)));
+
+        let none_sampled = workflow(Some(0.0));
+        let completed = run(RunStatus::Completed);
+        let stored = sampled_run(&none_sampled, &completed).unwrap();
+        assert!(is_sampled_out(&stored.payload));
+        assert_eq!(stored.payload["size_bytes"], 12);
+        assert_eq!(stored.payload, payload_digest(&completed.payload));
+        assert!(sampled_run(&none_sampled, &stored).is_none());
+
+        assert!(sampled_run(&none_sampled, &run(RunStatus::Failed)).is_none());
+        assert!(sampled_run(&none_sampled, &run(RunStatus::Running)).is_none());
+        assert!(sampled_run(&workflow(None), &completed).is_none());
+    }
+}
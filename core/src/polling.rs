@@ -0,0 +1,116 @@
+//! HTTP polling triggers
+//!
+//! A polling trigger is scheduled like an interval trigger. Each time it is
+//! due, its URL is fetched and the value at its compare path, or the whole
+//! response, is compared to the last value seen, which is persisted so a
+//! restart does not refire on unchanged data. The first poll only records the
+//! value it saw; a run starts once a later poll sees a different one.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use crate::error::{CoreError, CoreResult};
+use crate::models::{StepCache, TriggerPollState};
+
+/// How long a poll may take before it counts as failed
+const POLL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// What a poll found compared to the previous one
+#[derive(Debug, Clone, PartialEq)]
+pub enum PollOutcome {
+    /// First poll of the trigger, the value becomes the baseline
+    Baseline,
+    Unchanged,
+    Changed { previous: serde_json::Value },
+}
+
+/// Fetch the response of a polling trigger's URL
+///
+/// JSON responses are parsed, anything else is kept as a string. The auth
+/// token, when given, is sent as a bearer token.
+pub async fn fetch(url: &str, headers: &BTreeMap<String, String>, auth_token: Option<&str>) -> CoreResult<serde_json::Value> {
+    let client = reqwest::Client::builder().timeout(POLL_TIMEOUT).build()?;
+    let mut request = client.get(url);
+    for (name, value) in headers {
+        request = request.header(name, value);
+    }
+    if let Some(token) = auth_token {
+        request = request.bearer_auth(token);
+    }
+
+    let response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(CoreError::InvalidTrigger(format!("Polling {} returned {}", url, status)));
+    }
+    let body = response.text().await?;
+    Ok(serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body)))
+}
+
+/// Value of a response compared between polls
+pub fn compared_value(response: &serde_json::Value, compare_path: Option<&str>) -> CoreResult<serde_json::Value> {
+    let Some(path) = compare_path else {
+        return Ok(response.clone());
+    };
+    let segments = crate::triggers::split_json_path(path)
+        .ok_or_else(|| CoreError::InvalidTrigger(format!("Invalid polling compare path: {:?}", path)))?;
+    Ok(crate::triggers::lookup_json_path(response, &segments))
+}
+
+/// Compare a polled value to the last one seen, returning the state to persist
+///
+/// Values are fingerprinted like step cache keys, so objects differing only
+/// in field order count as unchanged.
+pub fn observe(trigger_id: &str, workflow_id: &str, previous: Option<&TriggerPollState>, value: serde_json::Value, now: DateTime<Utc>) -> (TriggerPollState, PollOutcome) {
+    let fingerprint = StepCache::key_for(&value);
+    let (outcome, changed_at) = match previous {
+        None => (PollOutcome::Baseline, None),
+        Some(previous) if previous.fingerprint == fingerprint => (PollOutcome::Unchanged, previous.changed_at),
+        Some(previous) => (PollOutcome::Changed { previous: previous.value.clone() }, Some(now)),
+    };
+
+    let state = TriggerPollState {
+        trigger_id: trigger_id.to_string(),
+        workflow_id: workflow_id.to_string(),
+        fingerprint,
+        value,
+        checked_at: now,
+        changed_at,
+    };
+    (state, outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_polls_fire_only_when_the_compared_value_changes() {
+        let now = Utc::now();
+        let response = serde_json::json!({"build": {"id": 7, "status": "queued"}, "fetched": 1});
+        let value = compared_value(&response, Some("$.build.status")).unwrap();
+        assert_eq!(value, serde_json::json!("queued"));
+
+        let (baseline, outcome) = observe("poll-1", "wf", None, value, now);
+        assert_eq!(outcome, PollOutcome::Baseline);
+        assert_eq!(baseline.changed_at, None);
+
+        // Fields outside the compare path do not count as changes
+        let response = serde_json::json!({"build": {"id": 7, "status": "queued"}, "fetched": 2});
+        let value = compared_value(&response, Some("$.build.status")).unwrap();
+        let (unchanged, outcome) = observe("poll-1", "wf", Some(&baseline), value, now);
+        assert_eq!(outcome, PollOutcome::Unchanged);
+
+        let response = serde_json::json!({"build": {"id": 7, "status": "passed"}, "fetched": 3});
+        let value = compared_value(&response, Some("$.build.status")).unwrap();
+        let (changed, outcome) = observe("poll-1", "wf", Some(&unchanged), value, now);
+        assert_eq!(outcome, PollOutcome::Changed { previous: serde_json::json!("queued") });
+        assert_eq!(changed.changed_at, Some(now));
+
+        // Whole responses are compared regardless of field order
+        let (whole, _) = observe("poll-2", "wf", None, serde_json::json!({"a": 1, "b": 2}), now);
+        let (_, outcome) = observe("poll-2", "wf", Some(&whole), serde_json::json!({"b": 2, "a": 1}), now);
+        assert_eq!(outcome, PollOutcome::Unchanged);
+        assert!(compared_value(&response, Some("$..")).is_err());
+    }
+}
@@ -0,0 +1,291 @@
+//! Roll-forward compatibility for persisted records
+//!
+//! Records written by older engines are read through migration shims instead
+//! of straight deserialization. Workflow definitions carry a record version,
+//! and definitions from before a version change are upgraded one version at a
+//! time. Run and step rows accept status names and timestamp formats used by
+//! earlier releases. Whatever the shims cannot read is listed by a
+//! `CompatibilityReport` rather than surfacing as a failed run later.
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use crate::error::{CoreError, CoreResult};
+use crate::models::{RunStatus, StepStatus, WorkflowDefinition};
+
+/// Record version of workflow definitions written by this engine
+pub const WORKFLOW_RECORD_VERSION: u32 = 2;
+
+/// Key holding the record version in a stored workflow definition
+pub const RECORD_VERSION_KEY: &str = "record_version";
+
+/// Serialize a workflow definition for storage, tagged with its record version
+pub fn workflow_to_record(workflow: &WorkflowDefinition) -> CoreResult<serde_json::Value> {
+    let mut record = serde_json::to_value(workflow)?;
+    if let Some(fields) = record.as_object_mut() {
+        fields.insert(RECORD_VERSION_KEY.to_string(), WORKFLOW_RECORD_VERSION.into());
+    }
+    Ok(record)
+}
+
+/// Read a stored workflow definition, upgrading it from older record versions
+///
+/// Definitions without a version predate versioning and are read as version 1.
+pub fn workflow_from_record(mut record: serde_json::Value) -> CoreResult<WorkflowDefinition> {
+    let version = record.get(RECORD_VERSION_KEY).and_then(|v| v.as_u64()).unwrap_or(1) as u32;
+    if version > WORKFLOW_RECORD_VERSION {
+        return Err(CoreError::Validation(format!(
+            "Workflow record version {} was written by a newer engine, this engine reads up to version {}",
+            version, WORKFLOW_RECORD_VERSION
+        )));
+    }
+    if version < 2 {
+        upgrade_workflow_v1(&mut record);
+    }
+    if let Some(fields) = record.as_object_mut() {
+        fields.remove(RECORD_VERSION_KEY);
+    }
+    Ok(serde_json::from_value(record)?)
+}
+
+/// Fill in the fields version 1 definitions could leave out
+fn upgrade_workflow_v1(record: &mut serde_json::Value) {
+    let Some(fields) = record.as_object_mut() else {
+        return;
+    };
+    fields.entry("triggers").or_insert_with(|| serde_json::json!([]));
+    if let Some(created_at) = fields.get("created_at").cloned() {
+        fields.entry("updated_at").or_insert(created_at);
+    }
+    if let Some(steps) = fields.get_mut("steps").and_then(|s| s.as_array_mut()) {
+        for step in steps.iter_mut().filter_map(|s| s.as_object_mut()) {
+            step.entry("depends_on").or_insert_with(|| serde_json::json!([]));
+            step.entry("is_control_flow").or_insert(serde_json::Value::Bool(false));
+            if let Some(name) = step.get("name").cloned() {
+                step.entry("action").or_insert(name);
+            }
+        }
+    }
+}
+
+/// Parse a stored run status, including names used by earlier releases
+pub fn run_status(raw: &str) -> Option<RunStatus> {
+    RunStatus::parse(raw).or_else(|| match raw.to_ascii_lowercase().as_str() {
+        "queued" => Some(RunStatus::Pending),
+        "started" => Some(RunStatus::Running),
+        "success" | "succeeded" => Some(RunStatus::Completed),
+        "error" | "errored" => Some(RunStatus::Failed),
+        "canceled" => Some(RunStatus::Cancelled),
+        _ => None,
+    })
+}
+
+/// Parse a stored step status, including names used by earlier releases
+pub fn step_status(raw: &str) -> Option<StepStatus> {
+    StepStatus::parse(raw).or_else(|| match raw.to_ascii_lowercase().as_str() {
+        "queued" => Some(StepStatus::Pending),
+        "started" => Some(StepStatus::Running),
+        "success" | "succeeded" => Some(StepStatus::Completed),
+        "error" | "errored" => Some(StepStatus::Failed),
+        "timeout" => Some(StepStatus::TimedOut),
+        _ => None,
+    })
+}
+
+/// Read a stored run status, treating unknown statuses as failed
+pub fn read_run_status(raw: &str) -> RunStatus {
+    run_status(raw).unwrap_or_else(|| {
+        log::warn!("Unknown run status '{}' read as failed", raw);
+        RunStatus::Failed
+    })
+}
+
+/// Read a stored step status, treating unknown statuses as failed
+pub fn read_step_status(raw: &str) -> StepStatus {
+    step_status(raw).unwrap_or_else(|| {
+        log::warn!("Unknown step status '{}' read as failed", raw);
+        StepStatus::Failed
+    })
+}
+
+/// Read a stored timestamp
+///
+/// Besides RFC 3339, accepts SQLite's `YYYY-MM-DD HH:MM:SS` in UTC and Unix
+/// timestamps in milliseconds.
+pub fn read_timestamp(raw: &str) -> CoreResult<DateTime<Utc>> {
+    let rfc3339 = match DateTime::parse_from_rfc3339(raw) {
+        Ok(timestamp) => return Ok(timestamp.with_timezone(&Utc)),
+        Err(e) => e,
+    };
+    if let Ok(timestamp) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f") {
+        return Ok(Utc.from_utc_datetime(&timestamp));
+    }
+    if let Some(timestamp) = raw.parse::<i64>().ok().and_then(|ms| Utc.timestamp_millis_opt(ms).single()) {
+        return Ok(timestamp);
+    }
+    Err(rfc3339.into())
+}
+
+/// Record the current engine cannot read
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompatibilityIssue {
+    /// "workflow", "run" or "step_result"
+    pub record_type: String,
+    /// Workflow or run ID, or "<run ID>/<step ID>" for step results
+    pub record_id: String,
+    pub problem: String,
+}
+
+/// Result of checking every stored record against the current engine
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CompatibilityReport {
+    pub workflow_record_version: u32,
+    pub workflows_checked: usize,
+    pub runs_checked: usize,
+    pub step_results_checked: usize,
+    pub issues: Vec<CompatibilityIssue>,
+}
+
+impl Default for CompatibilityReport {
+    fn default() -> Self {
+        CompatibilityReport {
+            workflow_record_version: WORKFLOW_RECORD_VERSION,
+            workflows_checked: 0,
+            runs_checked: 0,
+            step_results_checked: 0,
+            issues: Vec::new(),
+        }
+    }
+}
+
+impl CompatibilityReport {
+    /// Whether every checked record can be read
+    pub fn is_compatible(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Record a problem with a stored record
+    pub fn add_issue(&mut self, record_type: &str, record_id: &str, problem: impl ToString) {
+        self.issues.push(CompatibilityIssue {
+            record_type: record_type.to_string(),
+            record_id: record_id.to_string(),
+            problem: problem.to_string(),
+        });
+    }
+
+    /// Check a stored workflow definition
+    pub fn check_workflow(&mut self, id: &str, definition: CoreResult<serde_json::Value>) {
+        self.workflows_checked += 1;
+        if let Err(e) = definition.and_then(workflow_from_record) {
+            self.add_issue("workflow", id, e);
+        }
+    }
+
+    /// Check the status and timestamps of a stored run
+    pub fn check_run(&mut self, id: &str, status: &str, timestamps: &[&str]) {
+        self.runs_checked += 1;
+        if run_status(status).is_none() {
+            self.add_issue("run", id, format!("Unknown run status '{}'", status));
+        }
+        self.check_timestamps("run", id, timestamps);
+    }
+
+    /// Check the status and timestamps of a stored step result
+    pub fn check_step_result(&mut self, run_id: &str, step_id: &str, status: &str, timestamps: &[&str]) {
+        self.step_results_checked += 1;
+        let id = format!("{}/{}", run_id, step_id);
+        if step_status(status).is_none() {
+            self.add_issue("step_result", &id, format!("Unknown step status '{}'", status));
+        }
+        self.check_timestamps("step_result", &id, timestamps);
+    }
+
+    fn check_timestamps(&mut self, record_type: &str, id: &str, timestamps: &[&str]) {
+        for timestamp in timestamps {
+            if let Err(e) = read_timestamp(timestamp) {
+                self.add_issue(record_type, id, format!("Unreadable timestamp '{}': {}", timestamp, e));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_old_records_are_upgraded_or_reported() {
+        let v1 = serde_json::json!({
+            "id": "legacy",
+            "name": "Legacy",
+            "description": null,
+            "steps": [{"id": "fetch", "name": "fetch"}],
+            "created_at": "2024-03-01T10:00:00Z",
+        });
+        let workflow = workflow_from_record(v1).unwrap();
+        assert!(workflow.triggers.is_empty());
+        assert_eq!(workflow.updated_at, workflow.created_at);
+        assert_eq!(workflow.steps[0].action, "fetch");
+        assert!(workflow.steps[0].depends_on.is_empty() && !workflow.steps[0].is_control_flow);
+
+        let record = workflow_to_record(&workflow).unwrap();
+        assert_eq!(record[RECORD_VERSION_KEY], WORKFLOW_RECORD_VERSION);
+        assert_eq!(workflow_from_record(record.clone()).unwrap().steps.len(), 1);
+        let mut newer = record;
+        newer[RECORD_VERSION_KEY] = (WORKFLOW_RECORD_VERSION + 1).into();
+        assert!(workflow_from_record(newer.clone()).is_err());
+
+        assert_eq!(run_status("Succeeded"), Some(RunStatus::Completed));
+        assert_eq!(run_status("canceled"), Some(RunStatus::Cancelled));
+        assert_eq!(step_status("Timeout"), Some(StepStatus::TimedOut));
+        assert_eq!(read_step_status("Exploded"), StepStatus::Failed);
+        assert_eq!(read_timestamp("2024-03-01 10:00:00").unwrap(), workflow.created_at);
+        assert_eq!(read_timestamp("1709287200000").unwrap(), workflow.created_at);
+
+        let mut report = CompatibilityReport::default();
+        report.check_workflow("legacy", Ok(workflow_to_record(&workflow).unwrap()));
+        report.check_workflow("future", Ok(newer));
+        report.check_run("run-1", "Success", &["2024-03-01 10:00:00"]);
+        report.check_step_result("run-1", "fetch", "Exploded", &["yesterday"]);
+        assert_eq!((report.workflows_checked, report.runs_checked, report.step_results_checked), (2, 1, 1));
+        let problems: Vec<(&str, &str)> = report.issues.iter()
+            .map(|i| (i.record_type.as_str(), i.record_id.as_str()))
+            .collect();
+        assert_eq!(problems, vec![("workflow", "future"), ("step_result", "run-1/fetch"), ("step_result", "run-1/fetch")]);
+        assert!(!report.is_compatible());
+    }
+
+    #[test]
+    fn test_sqlite_store_reads_legacy_rows_and_reports_the_rest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        let db = crate::database::Database::new(path.to_str().unwrap()).unwrap();
+
+        let legacy = rusqlite::Connection::open(&path).unwrap();
+        legacy.execute(
+            "INSERT INTO workflows (id, name, definition, created_at, updated_at) VALUES ('old', 'Old', ?, '2024-03-01 10:00:00', '2024-03-01 10:00:00')",
+            [r#"{"id": "old", "name": "Old", "steps": [], "created_at": "2024-03-01T10:00:00Z"}"#],
+        ).unwrap();
+        let run_id = uuid::Uuid::new_v4().to_string();
+        legacy.execute(
+            "INSERT INTO workflow_runs (id, workflow_id, status, payload, started_at, completed_at) VALUES (?, 'old', 'Succeeded', '{}', '2024-03-01 10:00:00', '1709287260000')",
+            [&run_id],
+        ).unwrap();
+        legacy.execute(
+            "INSERT INTO step_results (run_id, step_id, status, started_at) VALUES (?, 'fetch', 'Exploded', '2024-03-01 10:00:00')",
+            [&run_id],
+        ).unwrap();
+
+        assert!(db.get_workflow("old").unwrap().is_some());
+        let run = db.get_run(&run_id).unwrap().unwrap();
+        assert_eq!(run.status, RunStatus::Completed);
+        assert_eq!(run.get_duration_ms(), Some(60_000));
+
+        let report = db.verify_record_compatibility().unwrap();
+        assert_eq!((report.workflows_checked, report.runs_checked, report.step_results_checked), (1, 1, 1));
+        assert_eq!(report.issues, vec![CompatibilityIssue {
+            record_type: "step_result".to_string(),
+            record_id: format!("{}/fetch", run_id),
+            problem: "Unknown step status 'Exploded'".to_string(),
+        }]);
+    }
+}
@@ -0,0 +1,250 @@
+//! Run recovery planning
+//!
+//! This module decides which steps of a run may be re-executed after a crash
+//! or during a replay. Idempotent steps are re-executed automatically, while
+//! steps with side effects either wait for an operator to confirm them or are
+//! skipped, depending on the configured policy.
+//!
+//! Runs left running by a crashed engine are found by their last activity:
+//! once nothing was recorded for a run within the lease, no engine is driving
+//! it anymore and startup recovery takes it over.
+
+use chrono::{DateTime, Utc};
+use crate::models::{
+    NonIdempotentRecovery, OrphanRunOutcome, OrphanRunPolicy, RecoveryAction, RunEvent, RunRecoveryPlan,
+    StepDefinition, StepRecovery, StepResult, StepStatus, WorkflowDefinition, WorkflowRun,
+};
+
+/// Plan the recovery of a run from its workflow definition, step results and event log
+///
+/// Crash recovery covers steps that started but never finished. Replay covers
+/// every step that started, including the ones that already finished.
+pub fn plan_recovery(
+    workflow: &WorkflowDefinition,
+    run: &WorkflowRun,
+    results: &[StepResult],
+    events: &[RunEvent],
+    policy: NonIdempotentRecovery,
+    replay: bool,
+) -> RunRecoveryPlan {
+    let steps = workflow.steps.iter()
+        .filter(|step| needs_recovery(step, results, events, replay))
+        .map(|step| plan_step(step, events, policy))
+        .collect();
+
+    RunRecoveryPlan {
+        run_id: run.id.to_string(),
+        replay,
+        steps,
+    }
+}
+
+/// Get when anything was last recorded for a run: its start, a step result or an event
+pub fn last_activity(run: &WorkflowRun, results: &[StepResult], events: &[RunEvent]) -> DateTime<Utc> {
+    let step_times = results.iter().flat_map(|r| std::iter::once(r.started_at).chain(r.completed_at));
+    let event_times = events.iter().map(|e| e.created_at);
+    step_times.chain(event_times).fold(run.started_at, DateTime::max)
+}
+
+/// Decide what startup recovery does with an orphaned run, given its recovery plan
+pub fn orphan_outcome(plan: &RunRecoveryPlan, policy: OrphanRunPolicy) -> OrphanRunOutcome {
+    match policy {
+        OrphanRunPolicy::Fail => OrphanRunOutcome::Failed,
+        OrphanRunPolicy::Requeue if plan.is_ready() => OrphanRunOutcome::Requeued,
+        OrphanRunPolicy::Requeue => OrphanRunOutcome::AwaitingConfirmation,
+    }
+}
+
+/// Whether a step has to be considered by recovery
+fn needs_recovery(step: &StepDefinition, results: &[StepResult], events: &[RunEvent], replay: bool) -> bool {
+    let latest = results.iter().rev().find(|r| r.step_id == step.id);
+    let started = events.iter()
+        .any(|e| e.event_type == RunEvent::STEP_STARTED && e.step_id.as_deref() == Some(step.id.as_str()));
+
+    match latest.map(|r| &r.status) {
+        Some(StepStatus::Running) | Some(StepStatus::Pending) => true,
+        Some(StepStatus::Completed) | Some(StepStatus::Failed) | Some(StepStatus::TimedOut) => replay,
+        Some(StepStatus::Skipped) => false,
+        None => started,
+    }
+}
+
+/// Decide what recovery does with a single step
+fn plan_step(step: &StepDefinition, events: &[RunEvent], policy: NonIdempotentRecovery) -> StepRecovery {
+    let (action, reason) = if step.idempotent {
+        (RecoveryAction::ReExecute, "step is idempotent".to_string())
+    } else if is_confirmed(&step.id, events) {
+        (RecoveryAction::ReExecute, "re-execution was confirmed by an operator".to_string())
+    } else {
+        match policy {
+            NonIdempotentRecovery::Confirm => (
+                RecoveryAction::AwaitConfirmation,
+                "step is not idempotent and needs operator confirmation".to_string(),
+            ),
+            NonIdempotentRecovery::Skip => (
+                RecoveryAction::Skip,
+                "step is not idempotent and the recovery policy skips it".to_string(),
+            ),
+        }
+    };
+
+    StepRecovery {
+        step_id: step.id.clone(),
+        idempotent: step.idempotent,
+        action,
+        reason,
+    }
+}
+
+/// Whether an operator confirmed re-execution after the step last started
+fn is_confirmed(step_id: &str, events: &[RunEvent]) -> bool {
+    events.iter()
+        .rev()
+        .filter(|e| e.step_id.as_deref() == Some(step_id))
+        .find(|e| e.event_type == RunEvent::RECOVERY_CONFIRMED || e.event_type == RunEvent::STEP_STARTED)
+        .map(|e| e.event_type == RunEvent::RECOVERY_CONFIRMED)
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RunStatus;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn step(id: &str, idempotent: bool) -> StepDefinition {
+        StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: vec![],
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        }
+    }
+
+    fn result(step_id: &str, status: StepStatus) -> StepResult {
+        StepResult {
+            step_id: step_id.to_string(),
+            status,
+            output: None,
+            error: None,
+            started_at: Utc::now(),
+            completed_at: None,
+            duration_ms: None,
+            cache_hit: None,
+            resources: None,
+        }
+    }
+
+    fn event(run_id: &str, step_id: &str, event_type: &str) -> RunEvent {
+        RunEvent {
+            run_id: run_id.to_string(),
+            event_type: event_type.to_string(),
+            step_id: Some(step_id.to_string()),
+            actor: RunEvent::ACTOR_ENGINE.to_string(),
+            detail: serde_json::json!({}),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_plan_recovery_gates_non_idempotent_steps() {
+        let now = Utc::now();
+        let workflow = WorkflowDefinition {
+            id: "recoverable".to_string(),
+            name: "Recoverable".to_string(),
+            description: None,
+            steps: vec![
+                step("fetch", true),
+                step("charge", false),
+                step("email", false),
+                step("archive", true),
+            ],
+            triggers: vec![],
+            created_at: now,
+            updated_at: now,
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let run = WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: workflow.id.clone(),
+            status: RunStatus::Running,
+            payload: serde_json::json!({}),
+            started_at: now,
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        let run_id = run.id.to_string();
+        let results = vec![
+            result("fetch", StepStatus::Completed),
+            result("charge", StepStatus::Running),
+        ];
+        let mut events = vec![
+            event(&run_id, "fetch", RunEvent::STEP_STARTED),
+            event(&run_id, "charge", RunEvent::STEP_STARTED),
+            event(&run_id, "email", RunEvent::STEP_STARTED),
+        ];
+
+        let plan = plan_recovery(&workflow, &run, &results, &events, NonIdempotentRecovery::Confirm, false);
+        let ids: Vec<&str> = plan.steps.iter().map(|s| s.step_id.as_str()).collect();
+        assert_eq!(ids, vec!["charge", "email"]);
+        assert!(plan.steps.iter().all(|s| s.action == RecoveryAction::AwaitConfirmation));
+        assert!(!plan.is_ready());
+
+        let skipped = plan_recovery(&workflow, &run, &results, &events, NonIdempotentRecovery::Skip, false);
+        assert!(skipped.steps.iter().all(|s| s.action == RecoveryAction::Skip));
+        assert!(skipped.is_ready());
+
+        events.push(event(&run_id, "charge", RunEvent::RECOVERY_CONFIRMED));
+        let plan = plan_recovery(&workflow, &run, &results, &events, NonIdempotentRecovery::Confirm, false);
+        assert_eq!(plan.steps[0].action, RecoveryAction::ReExecute);
+        assert_eq!(plan.steps[1].action, RecoveryAction::AwaitConfirmation);
+
+        let replay = plan_recovery(&workflow, &run, &results, &events, NonIdempotentRecovery::Confirm, true);
+        assert_eq!(replay.steps.len(), 3);
+        assert_eq!(replay.steps[0].step_id, "fetch");
+        assert_eq!(replay.steps[0].action, RecoveryAction::ReExecute);
+        assert!(replay.steps[0].idempotent);
+    }
+}
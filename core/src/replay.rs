@@ -0,0 +1,337 @@
+//! Deterministic run replay
+//!
+//! A replay feeds a recorded sequence of step and run events into a fresh
+//! workflow state machine and records every transition it computes. Times
+//! come from the events, and nothing is executed or persisted, so replaying
+//! the same events always gives the same report. This lets an event log
+//! attached to a bug report be replayed exactly.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::error::{CoreError, CoreResult};
+use crate::models::{RunEvent, RunStatus, StepStatus, WorkflowDefinition, WorkflowRun};
+use crate::state::StateManager;
+use crate::workflow_state_machine::{WorkflowExecutionState, WorkflowStateMachine};
+
+/// Recorded event fed into a replay
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayEvent {
+    /// Event kind, one of the run event types handled by replays
+    pub event_type: String,
+    pub step_id: Option<String>,
+    pub at: DateTime<Utc>,
+    #[serde(default)]
+    pub output: Option<serde_json::Value>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+impl ReplayEvent {
+    /// Event of a step starting
+    pub fn started(step_id: &str, at: DateTime<Utc>) -> Self {
+        Self::step(RunEvent::STEP_STARTED, step_id, at)
+    }
+
+    /// Event of a step completing with an output
+    pub fn completed(step_id: &str, at: DateTime<Utc>, output: serde_json::Value) -> Self {
+        ReplayEvent {
+            output: Some(output),
+            ..Self::step(RunEvent::for_step_status(&StepStatus::Completed), step_id, at)
+        }
+    }
+
+    /// Event of a step failing with an error
+    pub fn failed(step_id: &str, at: DateTime<Utc>, error: &str) -> Self {
+        ReplayEvent {
+            error: Some(error.to_string()),
+            ..Self::step(RunEvent::for_step_status(&StepStatus::Failed), step_id, at)
+        }
+    }
+
+    fn step(event_type: &str, step_id: &str, at: DateTime<Utc>) -> Self {
+        ReplayEvent {
+            event_type: event_type.to_string(),
+            step_id: Some(step_id.to_string()),
+            at,
+            output: None,
+            error: None,
+        }
+    }
+}
+
+/// Self-contained replay input, as attached to a bug report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayRequest {
+    pub workflow: WorkflowDefinition,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+    pub events: Vec<ReplayEvent>,
+}
+
+/// State of the run after one replayed event
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayTransition {
+    /// Position of the event in the replayed sequence
+    pub index: usize,
+    pub event: ReplayEvent,
+    pub state: WorkflowExecutionState,
+    /// Steps ready to run after the event, sorted by ID
+    pub ready_steps: Vec<String>,
+    /// Why the event could not be applied, if it disagrees with the state machine
+    pub divergence: Option<String>,
+}
+
+/// Result of replaying a run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayReport {
+    pub workflow_id: String,
+    pub run_id: String,
+    pub transitions: Vec<ReplayTransition>,
+    pub final_state: WorkflowExecutionState,
+    pub step_statuses: BTreeMap<String, StepStatus>,
+}
+
+impl ReplayReport {
+    /// Whether every event was accepted by the state machine
+    pub fn is_consistent(&self) -> bool {
+        self.transitions.iter().all(|t| t.divergence.is_none())
+    }
+}
+
+/// Convert a run's recorded event log into replay events
+///
+/// Only step starts, completions, failures and timeouts and run pauses, resumes and
+/// cancellations drive the state machine; other events are left out.
+pub fn events_from_run_log(events: &[RunEvent]) -> Vec<ReplayEvent> {
+    events.iter()
+        .filter(|e| is_replayable(&e.event_type))
+        .map(|e| ReplayEvent {
+            event_type: e.event_type.clone(),
+            step_id: e.step_id.clone(),
+            at: e.created_at,
+            output: e.detail.get("output").cloned(),
+            error: e.detail.get("error").and_then(|v| v.as_str()).map(str::to_string),
+        })
+        .collect()
+}
+
+/// Whether an event type drives the state machine during a replay
+fn is_replayable(event_type: &str) -> bool {
+    event_type == RunEvent::STEP_STARTED
+        || event_type == RunEvent::for_step_status(&StepStatus::Completed)
+        || event_type == RunEvent::for_step_status(&StepStatus::Failed)
+        || event_type == RunEvent::for_step_status(&StepStatus::TimedOut)
+        || event_type == RunEvent::RUN_PAUSED
+        || event_type == RunEvent::RUN_RESUMED
+        || event_type == RunEvent::for_run_status(&RunStatus::Cancelled)
+}
+
+/// Replay recorded events against a fresh state machine for a run
+///
+/// The state manager is only handed to the state machine; the replay neither
+/// reads nor writes through it.
+pub fn replay(
+    state_manager: Arc<Mutex<StateManager>>,
+    workflow: &WorkflowDefinition,
+    run: &WorkflowRun,
+    events: &[ReplayEvent],
+) -> CoreResult<ReplayReport> {
+    let mut machine = WorkflowStateMachine::new(state_manager, workflow.id.clone(), run.id);
+    machine.initialize_from(workflow.clone(), run.clone())?;
+
+    let mut started_at: HashMap<String, DateTime<Utc>> = HashMap::new();
+    let mut transitions = Vec::with_capacity(events.len());
+    for (index, event) in events.iter().enumerate() {
+        let divergence = apply(&mut machine, event, &mut started_at).err().map(|e| e.to_string());
+        let mut ready_steps = machine.get_ready_steps();
+        ready_steps.sort();
+        transitions.push(ReplayTransition {
+            index,
+            event: event.clone(),
+            state: machine.get_execution_state().clone(),
+            ready_steps,
+            divergence,
+        });
+    }
+
+    Ok(ReplayReport {
+        workflow_id: workflow.id.clone(),
+        run_id: run.id.to_string(),
+        transitions,
+        final_state: machine.get_execution_state().clone(),
+        step_statuses: machine.get_step_states().iter()
+            .map(|(step_id, state)| (step_id.clone(), state.status.clone()))
+            .collect(),
+    })
+}
+
+/// Apply one event to the state machine
+fn apply(
+    machine: &mut WorkflowStateMachine,
+    event: &ReplayEvent,
+    started_at: &mut HashMap<String, DateTime<Utc>>,
+) -> CoreResult<()> {
+    let event_type = event.event_type.as_str();
+    if event_type == RunEvent::RUN_PAUSED {
+        return machine.pause();
+    }
+    if event_type == RunEvent::RUN_RESUMED {
+        return machine.resume();
+    }
+    if event_type == RunEvent::for_run_status(&RunStatus::Cancelled) {
+        return machine.cancel(event.error.clone());
+    }
+
+    let step_id = event.step_id.as_deref()
+        .ok_or_else(|| CoreError::Validation(format!("Event {} has no step ID", event_type)))?;
+    if event_type == RunEvent::STEP_STARTED {
+        if !machine.get_ready_steps().iter().any(|id| id == step_id) {
+            return Err(CoreError::State(format!("Step {} started before it was ready", step_id)));
+        }
+        machine.mark_step_running(step_id)?;
+        started_at.insert(step_id.to_string(), event.at);
+        return Ok(());
+    }
+
+    let started = started_at.remove(step_id);
+    if event_type == RunEvent::for_step_status(&StepStatus::Completed) {
+        let output = event.output.clone().unwrap_or(serde_json::Value::Null);
+        machine.mark_step_completed_at(step_id, output, started, event.at)?;
+    } else if event_type == RunEvent::for_step_status(&StepStatus::Failed)
+        || event_type == RunEvent::for_step_status(&StepStatus::TimedOut)
+    {
+        let error = event.error.clone().unwrap_or_else(|| "Unknown error".to_string());
+        machine.mark_step_failed_at(step_id, error, started, event.at)?;
+    } else {
+        return Err(CoreError::Validation(format!("Event type {} cannot be replayed", event_type)));
+    }
+    machine.check_workflow_completion()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StepDefinition;
+    use chrono::TimeZone;
+    use uuid::Uuid;
+
+    fn step(id: &str, depends_on: &[&str]) -> StepDefinition {
+        StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        }
+    }
+
+    #[test]
+    fn test_replay_is_deterministic_and_reports_divergences() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("replay.db");
+        let state_manager = Arc::new(Mutex::new(StateManager::new(db_path.to_str().unwrap()).unwrap()));
+
+        let t0 = Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap();
+        let at = |secs: i64| t0 + chrono::Duration::seconds(secs);
+        let workflow = WorkflowDefinition {
+            id: "replayed".to_string(),
+            name: "Replayed".to_string(),
+            description: None,
+            steps: vec![step("fetch", &[]), step("transform", &["fetch"]), step("store", &["transform"])],
+            triggers: vec![],
+            created_at: t0,
+            updated_at: t0,
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let run = WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: workflow.id.clone(),
+            status: RunStatus::Running,
+            payload: serde_json::json!({}),
+            started_at: t0,
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        let events = vec![
+            ReplayEvent::started("fetch", at(1)),
+            ReplayEvent::completed("fetch", at(2), serde_json::json!({"rows": 3})),
+            ReplayEvent::started("transform", at(3)),
+            ReplayEvent::completed("transform", at(4), serde_json::json!({})),
+            ReplayEvent::started("store", at(5)),
+            ReplayEvent::failed("store", at(7), "disk full"),
+        ];
+
+        let report = replay(state_manager.clone(), &workflow, &run, &events).unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.transitions[0].ready_steps, Vec::<String>::new());
+        assert_eq!(report.transitions[1].ready_steps, vec!["transform".to_string()]);
+        assert_eq!(report.final_state, WorkflowExecutionState::Failed);
+        assert_eq!(report.step_statuses["store"], StepStatus::Failed);
+        assert_eq!(report, replay(state_manager.clone(), &workflow, &run, &events).unwrap());
+
+        let out_of_order = vec![ReplayEvent::started("store", at(1))];
+        let report = replay(state_manager, &workflow, &run, &out_of_order).unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(report.step_statuses["store"], StepStatus::Pending);
+
+        let log = vec![RunEvent {
+            run_id: run.id.to_string(),
+            event_type: RunEvent::HOOK_FIRED.to_string(),
+            step_id: None,
+            actor: RunEvent::ACTOR_ENGINE.to_string(),
+            detail: serde_json::json!({}),
+            created_at: t0,
+        }, RunEvent {
+            run_id: run.id.to_string(),
+            event_type: RunEvent::for_step_status(&StepStatus::Failed).to_string(),
+            step_id: Some("fetch".to_string()),
+            actor: RunEvent::ACTOR_ENGINE.to_string(),
+            detail: serde_json::json!({"duration_ms": 5, "error": "timeout"}),
+            created_at: at(1),
+        }];
+        assert_eq!(events_from_run_log(&log), vec![ReplayEvent::failed("fetch", at(1), "timeout")]);
+    }
+}
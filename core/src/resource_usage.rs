@@ -0,0 +1,68 @@
+//! Resource accounting of step attempts
+//!
+//! Workers run each attempt on a blocking thread of its own, so the CPU time
+//! of an attempt is the CPU time that thread spent between the attempt's start
+//! and end, read from `/proc/thread-self/schedstat`. Memory cannot be
+//! attributed to a thread: the peak recorded is the higher of the engine
+//! process's resident memory at the start and end of the attempt, which also
+//! counts what attempts running alongside it held. Usage is only measured on
+//! Linux; elsewhere attempts record none.
+
+use crate::models::ResourceUsage;
+
+/// CPU time and resident memory sampled when an attempt starts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UsageSample {
+    cpu_time_ns: u64,
+    resident_bytes: u64,
+}
+
+impl UsageSample {
+    /// Sample the calling thread, None where usage is not measured
+    pub fn take() -> Option<Self> {
+        Some(UsageSample {
+            cpu_time_ns: thread_cpu_time_ns()?,
+            resident_bytes: resident_bytes()?,
+        })
+    }
+
+    /// Usage of the calling thread since this sample, which must have been taken on it
+    pub fn usage_since(&self) -> Option<ResourceUsage> {
+        let now = Self::take()?;
+        Some(ResourceUsage {
+            cpu_time_ms: now.cpu_time_ns.saturating_sub(self.cpu_time_ns) / 1_000_000,
+            peak_memory_bytes: now.resident_bytes.max(self.resident_bytes),
+        })
+    }
+}
+
+/// CPU time the calling thread has spent running, in nanoseconds
+#[cfg(target_os = "linux")]
+fn thread_cpu_time_ns() -> Option<u64> {
+    let schedstat = std::fs::read_to_string("/proc/thread-self/schedstat").ok()?;
+    schedstat.split_whitespace().next()?.parse().ok()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thread_cpu_time_ns() -> Option<u64> {
+    None
+}
+
+/// Resident memory of the engine process, in bytes
+#[cfg(target_os = "linux")]
+fn resident_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kilobytes = status.lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_bytes() -> Option<u64> {
+    None
+}
@@ -0,0 +1,108 @@
+//! Run retention
+//!
+//! Finished runs are deleted once they fall outside the retention policy: older
+//! than the maximum age, or beyond the newest runs kept per workflow. Failed
+//! runs can be given their own, usually longer, age limit; with one set they
+//! are left out of the per-workflow count, so a stream of successful runs does
+//! not push them out. Pinned and unfinished runs are never deleted.
+
+use std::collections::{HashMap, HashSet};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use crate::config::RetentionConfig;
+use crate::models::{FinishedRun, RunStatus};
+
+/// Result of a cleanup pass or manual purge
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunPurgeSummary {
+    pub runs_deleted: usize,
+    /// Runs that were due for deletion but are pinned
+    pub pinned_kept: usize,
+}
+
+/// Select the runs the retention policy expires
+///
+/// `runs` must be sorted newest first, as returned by the store. Runs in
+/// `pinned` do not count toward the per-workflow limit, but are still selected
+/// once past their age limit so the caller can report them as kept.
+pub fn expired_runs<'a>(runs: &'a [FinishedRun], pinned: &HashSet<&str>, config: &RetentionConfig, now: DateTime<Utc>) -> Vec<&'a FinishedRun> {
+    let cutoff = |days: Option<u32>| days.map(|days| now - Duration::days(days as i64));
+    let max_age_cutoff = cutoff(config.max_run_age_days);
+    let failed_cutoff = cutoff(config.failed_run_age_days);
+
+    let mut kept_per_workflow: HashMap<&str, usize> = HashMap::new();
+    runs.iter()
+        .filter(|run| {
+            let failed_kept_longer = run.status == RunStatus::Failed && failed_cutoff.is_some();
+            let age_cutoff = if failed_kept_longer { failed_cutoff } else { max_age_cutoff };
+            if age_cutoff.is_some_and(|cutoff| run.finished_at < cutoff) {
+                return true;
+            }
+            if failed_kept_longer || pinned.contains(run.run_id.as_str()) {
+                return false;
+            }
+            let Some(max_runs) = config.max_runs_per_workflow else {
+                return false;
+            };
+            let kept = kept_per_workflow.entry(run.workflow_id.as_str()).or_insert(0);
+            *kept += 1;
+            *kept > max_runs
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn run(id: &str, workflow_id: &str, status: RunStatus, finished_at: DateTime<Utc>) -> FinishedRun {
+        FinishedRun {
+            run_id: id.to_string(),
+            workflow_id: workflow_id.to_string(),
+            status,
+            finished_at,
+        }
+    }
+
+    #[test]
+    fn test_runs_expire_by_age_and_count_with_failures_kept_longer() {
+        let now = Utc.with_ymd_and_hms(2026, 3, 1, 0, 0, 0).unwrap();
+        let days_ago = |days: i64| now - Duration::days(days);
+        let runs = vec![
+            run("a1", "a", RunStatus::Completed, days_ago(1)),
+            run("a2", "a", RunStatus::Failed, days_ago(2)),
+            run("a3", "a", RunStatus::Completed, days_ago(3)),
+            run("a4", "a", RunStatus::Cancelled, days_ago(4)),
+            run("b1", "b", RunStatus::Completed, days_ago(5)),
+            run("a5", "a", RunStatus::Failed, days_ago(20)),
+            run("b2", "b", RunStatus::Completed, days_ago(40)),
+            run("a6", "a", RunStatus::Failed, days_ago(100)),
+        ];
+        let ids = |config: &RetentionConfig| -> Vec<String> {
+            expired_runs(&runs, &HashSet::new(), config, now).iter().map(|r| r.run_id.clone()).collect()
+        };
+
+        let mut config = RetentionConfig {
+            max_run_age_days: None,
+            max_runs_per_workflow: None,
+            failed_run_age_days: None,
+            ..RetentionConfig::default()
+        };
+        assert!(ids(&config).is_empty());
+
+        config.max_run_age_days = Some(30);
+        assert_eq!(ids(&config), vec!["b2", "a6"]);
+
+        config.max_runs_per_workflow = Some(2);
+        assert_eq!(ids(&config), vec!["a3", "a4", "a5", "b2", "a6"]);
+
+        config.failed_run_age_days = Some(90);
+        assert_eq!(ids(&config), vec!["a4", "b2", "a6"]);
+
+        // Pinned runs leave room under the limit, and are still selected once too old
+        let pinned = HashSet::from(["a1", "a6"]);
+        let expired: Vec<&str> = expired_runs(&runs, &pinned, &config, now).iter().map(|r| r.run_id.as_str()).collect();
+        assert_eq!(expired, vec!["b2", "a6"]);
+    }
+}
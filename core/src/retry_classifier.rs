@@ -0,0 +1,204 @@
+//! Retry decisions for failed steps made by user code
+//!
+//! A classifier registered through the N-API bridge is asked what to do with
+//! every failed step attempt: retry it, optionally after a delay of its own,
+//! fail it at once, or fail over to another step of the workflow. Its
+//! decision overrides the step's retry rules and backoff, but not its attempt
+//! budget: a step is never retried past `max_attempts`. Node reports the
+//! decision back by request ID; when no decision arrives within the timeout,
+//! or the decision is "default", the step's static retry config applies.
+//! Every decision is recorded in the run's event log by the dispatcher.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{oneshot, Mutex};
+use uuid::Uuid;
+use crate::error::{CoreError, CoreResult};
+use crate::job::Job;
+use crate::models::WorkflowDefinition;
+
+/// Callback delivering a classification request to the classifier
+pub type ClassifierHandler = Box<dyn Fn(&RetryClassification) + Send + Sync>;
+
+/// A failed step attempt awaiting a retry decision
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryClassification {
+    /// Identifies the request when its decision is reported
+    pub id: String,
+    pub workflow_id: String,
+    pub run_id: String,
+    pub step_id: String,
+    /// Attempt that failed, starting at 1
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub error: String,
+    /// What the step's static retry config would do with the failure
+    pub default_retry: bool,
+}
+
+/// What to do with a failed step attempt
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum RetryDecision {
+    /// Retry the step, after `delay_ms` instead of the backoff when given
+    Retry {
+        #[serde(default)]
+        delay_ms: Option<u64>,
+    },
+    /// Fail the step without retrying it
+    Fail,
+    /// Give up on the step and run another step of the workflow instead
+    Failover { step_id: String },
+    /// Leave the failure to the step's retry config
+    Default,
+}
+
+impl RetryDecision {
+    /// Check the decision can be applied to a failed step of the workflow
+    pub fn validate(&self, workflow: &WorkflowDefinition, failed_step: &str) -> CoreResult<()> {
+        if let RetryDecision::Failover { step_id } = self {
+            if step_id == failed_step {
+                return Err(CoreError::Validation(format!("Step '{}' cannot fail over to itself", step_id)));
+            }
+            if workflow.get_step(step_id).is_none() {
+                return Err(CoreError::Validation(format!(
+                    "Failover step '{}' not found in workflow '{}'", step_id, workflow.id
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Short name of the decision, as used in the event log
+    pub fn action(&self) -> &'static str {
+        match self {
+            RetryDecision::Retry { .. } => "retry",
+            RetryDecision::Fail => "fail",
+            RetryDecision::Failover { .. } => "failover",
+            RetryDecision::Default => "default",
+        }
+    }
+}
+
+/// The registered classifier and the requests waiting for its decision
+pub struct RetryClassifier {
+    timeout: Duration,
+    handler: Option<ClassifierHandler>,
+    pending: HashMap<String, oneshot::Sender<RetryDecision>>,
+}
+
+impl RetryClassifier {
+    pub fn new(timeout_ms: u64) -> Self {
+        Self {
+            timeout: Duration::from_millis(timeout_ms),
+            handler: None,
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Set the handler failures are delivered to, or remove it with None
+    pub fn set_handler(&mut self, handler: Option<ClassifierHandler>) {
+        self.handler = handler;
+        if self.handler.is_none() {
+            // Dropping the senders lets waiting failures fall back to their retry config
+            self.pending.clear();
+        }
+    }
+
+    /// Check if a classifier is registered
+    pub fn is_registered(&self) -> bool {
+        self.handler.is_some()
+    }
+
+    /// Deliver a failed attempt to the handler
+    ///
+    /// Returns None when no handler is registered.
+    pub fn request(&mut self, job: &Job, error: &str) -> Option<(RetryClassification, oneshot::Receiver<RetryDecision>)> {
+        let handler = self.handler.as_ref()?;
+        let request = RetryClassification {
+            id: Uuid::new_v4().to_string(),
+            workflow_id: job.workflow_id.clone(),
+            run_id: job.run_id.clone(),
+            step_id: job.step_name.clone(),
+            attempt: job.metadata.attempt_count,
+            max_attempts: job.retry_config.max_attempts,
+            error: error.to_string(),
+            default_retry: job.should_retry(error),
+        };
+        let (sender, receiver) = oneshot::channel();
+        self.pending.insert(request.id.clone(), sender);
+        handler(&request);
+        Some((request, receiver))
+    }
+
+    /// Hand the reported decision to the failure waiting for it
+    pub fn report(&mut self, request_id: &str, decision: RetryDecision) -> CoreResult<()> {
+        let sender = self.pending.remove(request_id)
+            .ok_or_else(|| CoreError::Validation(format!("No retry classification {} is awaiting a decision", request_id)))?;
+        sender.send(decision)
+            .map_err(|_| CoreError::Validation(format!("Retry classification {} is no longer awaiting a decision", request_id)))
+    }
+
+    /// Number of requests awaiting a decision
+    pub fn in_flight(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+/// Ask the registered classifier what to do with a failed attempt
+///
+/// Returns None when no classifier is registered or it did not decide in time.
+pub async fn classify(classifier: &Mutex<RetryClassifier>, job: &Job, error: &str) -> Option<(RetryClassification, RetryDecision)> {
+    let (request, receiver, timeout) = {
+        let mut classifier = classifier.lock().await;
+        let timeout = classifier.timeout;
+        let (request, receiver) = classifier.request(job, error)?;
+        (request, receiver, timeout)
+    }; // Lock released here
+    match tokio::time::timeout(timeout, receiver).await {
+        Ok(Ok(decision)) => Some((request, decision)),
+        _ => {
+            classifier.lock().await.pending.remove(&request.id);
+            log::warn!("No retry decision for job {} within {}ms, using its retry config", job.id, timeout.as_millis());
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use serde_json::json;
+    use crate::job::JobPriority;
+
+    #[tokio::test]
+    async fn test_failures_wait_for_the_reported_decision_and_fall_back_on_timeout() {
+        let classifier = Arc::new(Mutex::new(RetryClassifier::new(100)));
+        let mut job = Job::new("billing".to_string(), Uuid::new_v4().to_string(), "charge".to_string(), json!({}), JobPriority::Normal);
+        job.metadata.attempt_count = 1;
+        assert!(classify(&classifier, &job, "card declined").await.is_none());
+
+        let (sender, mut requests) = tokio::sync::mpsc::unbounded_channel();
+        classifier.lock().await.set_handler(Some(Box::new(move |request| {
+            let _ = sender.send(request.clone());
+        })));
+        let reporter = Arc::clone(&classifier);
+        tokio::spawn(async move {
+            let request: RetryClassification = requests.recv().await.unwrap();
+            assert_eq!((request.step_id.as_str(), request.attempt, request.error.as_str()), ("charge", 1, "card declined"));
+            let decision = serde_json::from_value(json!({"action": "retry", "delay_ms": 250})).unwrap();
+            reporter.lock().await.report(&request.id, decision).unwrap();
+            // Unanswered requests time out
+            let _ = requests.recv().await;
+        });
+
+        let (request, decision) = classify(&classifier, &job, "card declined").await.unwrap();
+        assert_eq!(decision, RetryDecision::Retry { delay_ms: Some(250) });
+        assert!(classifier.lock().await.report(&request.id, RetryDecision::Fail).is_err());
+
+        assert!(classify(&classifier, &job, "card declined").await.is_none());
+        assert_eq!(classifier.lock().await.in_flight(), 0);
+    }
+}
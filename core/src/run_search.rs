@@ -0,0 +1,74 @@
+//! Run search by payload and step output content
+//!
+//! When a run is created and when its steps finish, the scalar values inside
+//! its payload and step outputs are written to a search index keyed by their
+//! JSON path, so a run can be found by a value such as an order ID without
+//! scanning every payload. Values are indexed as text, so a search for "12345"
+//! finds both the string and the number. Long strings, offloaded outputs and
+//! fields past the per-document limit are not indexed. Index entries outlive
+//! payload sampling, so sampled-out runs stay findable.
+
+use crate::error::{CoreError, CoreResult};
+use crate::payload_offload::BLOB_REF_KEY;
+
+/// Source of the entries indexed from a run's payload
+pub const PAYLOAD_SOURCE: &str = "payload";
+
+/// Longest string value indexed, in bytes
+const MAX_VALUE_BYTES: usize = 256;
+
+/// Most fields indexed from a single payload or output
+const MAX_ENTRIES: usize = 500;
+
+/// Source of the entries indexed from a step's output
+pub fn step_source(step_id: &str) -> String {
+    format!("step:{}", step_id)
+}
+
+/// Normalize a JSON path such as `$.order.items[0].sku` to its indexed form
+pub fn normalize_path(path: &str) -> CoreResult<String> {
+    crate::triggers::split_json_path(path)
+        .filter(|segments| !segments.is_empty())
+        .map(|segments| segments.join("."))
+        .ok_or_else(|| CoreError::Validation(format!("Invalid run search path: {:?}", path)))
+}
+
+/// Get the (path, value) entries indexed for a payload or step output
+pub fn index_entries(document: &serde_json::Value) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    if document.get(BLOB_REF_KEY).is_none() {
+        collect(document, &mut Vec::new(), &mut entries);
+    }
+    entries
+}
+
+fn collect(value: &serde_json::Value, path: &mut Vec<String>, entries: &mut Vec<(String, String)>) {
+    if entries.len() >= MAX_ENTRIES {
+        return;
+    }
+    let children: Box<dyn Iterator<Item = (String, &serde_json::Value)>> = match value {
+        serde_json::Value::Object(fields) => Box::new(fields.iter().map(|(name, child)| (name.clone(), child))),
+        serde_json::Value::Array(items) => Box::new(items.iter().enumerate().map(|(index, child)| (index.to_string(), child))),
+        scalar => {
+            if let Some(text) = indexed_text(scalar).filter(|_| !path.is_empty()) {
+                entries.push((path.join("."), crate::secrets::mask(&text).into_owned()));
+            }
+            return;
+        }
+    };
+    for (segment, child) in children {
+        path.push(segment);
+        collect(child, path, entries);
+        path.pop();
+    }
+}
+
+/// Text a scalar value is indexed and searched by
+fn indexed_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(text) if text.len() <= MAX_VALUE_BYTES => Some(text.clone()),
+        serde_json::Value::Number(number) => Some(number.to_string()),
+        serde_json::Value::Bool(flag) => Some(flag.to_string()),
+        _ => None,
+    }
+}
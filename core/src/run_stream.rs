@@ -0,0 +1,177 @@
+//! Live run event streaming
+//!
+//! Every run event the engine records is also pushed to the subscribers
+//! registered here, so the SDK can follow runs as they progress instead of
+//! polling their status. Subscriptions are process-wide: a subscriber receives
+//! the events of every state manager in the process that pass its filter.
+//! Listeners are called on the thread that recorded the event and must not
+//! block; the N-API subscription hands events to a threadsafe function.
+//!
+//! Callers waiting for a run to finish use a `RunCompletion`, which resolves
+//! on the run's terminal event rather than polling the run's status.
+
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use crate::models::{RunEvent, RunStatus};
+
+/// Callback receiving the events a subscription matches
+pub type RunEventListener = Box<dyn Fn(&RunEvent) + Send + Sync>;
+
+/// Which events a subscription receives
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunEventFilter {
+    /// Only events of this run
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// Only events of these types, e.g. "step_completed" or "run_failed"
+    #[serde(default)]
+    pub event_types: Option<Vec<String>>,
+}
+
+impl RunEventFilter {
+    /// Whether an event passes the filter
+    pub fn matches(&self, event: &RunEvent) -> bool {
+        self.run_id.as_ref().is_none_or(|run_id| *run_id == event.run_id)
+            && self.event_types.as_ref().is_none_or(|types| types.contains(&event.event_type))
+    }
+}
+
+struct Subscription {
+    id: u32,
+    filter: RunEventFilter,
+    listener: RunEventListener,
+}
+
+static SUBSCRIPTIONS: RwLock<Vec<Subscription>> = RwLock::new(Vec::new());
+static NEXT_SUBSCRIPTION_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Register a listener for the run events matching a filter, returning the subscription ID
+pub fn subscribe(filter: RunEventFilter, listener: RunEventListener) -> u32 {
+    let id = NEXT_SUBSCRIPTION_ID.fetch_add(1, Ordering::Relaxed);
+    match SUBSCRIPTIONS.write() {
+        Ok(mut subscriptions) => subscriptions.push(Subscription { id, filter, listener }),
+        Err(_) => log::error!("Failed to acquire run event subscriptions lock"),
+    }
+    id
+}
+
+/// Remove a subscription, returning whether it existed
+pub fn unsubscribe(id: u32) -> bool {
+    let Ok(mut subscriptions) = SUBSCRIPTIONS.write() else {
+        return false;
+    };
+    let before = subscriptions.len();
+    subscriptions.retain(|subscription| subscription.id != id);
+    subscriptions.len() < before
+}
+
+/// Push a recorded event to the matching subscribers
+pub fn publish(event: &RunEvent) {
+    let Ok(subscriptions) = SUBSCRIPTIONS.read() else {
+        return;
+    };
+    for subscription in subscriptions.iter().filter(|subscription| subscription.filter.matches(event)) {
+        (subscription.listener)(event);
+    }
+}
+
+/// Wait for a run to reach a terminal state
+///
+/// The subscription is registered as soon as the wait is created, so a run
+/// finishing between creating it and reading the run's stored status is not
+/// missed. Dropping the wait removes the subscription.
+pub struct RunCompletion {
+    subscription_id: u32,
+    finished: oneshot::Receiver<RunEvent>,
+}
+
+impl RunCompletion {
+    /// Start listening for the terminal event of a run
+    pub fn watch(run_id: &str) -> Self {
+        let (sender, finished) = oneshot::channel();
+        let sender = Mutex::new(Some(sender));
+        let filter = RunEventFilter {
+            run_id: Some(run_id.to_string()),
+            event_types: Some([RunStatus::Completed, RunStatus::Failed, RunStatus::Cancelled].iter()
+                .map(|status| RunEvent::for_run_status(status).to_string())
+                .collect()),
+        };
+        let subscription_id = subscribe(filter, Box::new(move |event| {
+            if let Some(sender) = sender.lock().ok().and_then(|mut sender| sender.take()) {
+                let _ = sender.send(event.clone());
+            }
+        }));
+        Self { subscription_id, finished }
+    }
+
+    /// Wait for the run's terminal event, None if the timeout expires first
+    pub async fn wait(mut self, timeout: Duration) -> Option<RunEvent> {
+        tokio::time::timeout(timeout, &mut self.finished).await.ok().and_then(Result::ok)
+    }
+}
+
+impl Drop for RunCompletion {
+    fn drop(&mut self) {
+        unsubscribe(self.subscription_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn event(run_id: &str, event_type: &str) -> RunEvent {
+        RunEvent {
+            run_id: run_id.to_string(),
+            event_type: event_type.to_string(),
+            step_id: None,
+            actor: RunEvent::ACTOR_ENGINE.to_string(),
+            detail: serde_json::json!({}),
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_subscribers_receive_matching_events_until_unsubscribed() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&received);
+        let filter = RunEventFilter {
+            run_id: Some("stream-run".to_string()),
+            event_types: Some(vec!["step_completed".to_string(), "run_completed".to_string()]),
+        };
+        let id = subscribe(filter, Box::new(move |event| sink.lock().unwrap().push(event.event_type.clone())));
+
+        publish(&event("stream-run", "step_started"));
+        publish(&event("stream-run", "step_completed"));
+        publish(&event("other-run", "run_completed"));
+        publish(&event("stream-run", "run_completed"));
+        assert_eq!(*received.lock().unwrap(), vec!["step_completed", "run_completed"]);
+
+        assert!(unsubscribe(id));
+        assert!(!unsubscribe(id));
+        publish(&event("stream-run", "step_completed"));
+        assert_eq!(received.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_completions_resolve_on_terminal_events_or_time_out() {
+        let completion = RunCompletion::watch("waited-run");
+        publish(&event("waited-run", "step_completed"));
+        publish(&event("other-run", "run_completed"));
+        assert!(RunCompletion::watch("waited-run").wait(Duration::from_millis(20)).await.is_none());
+
+        publish(&event("waited-run", "run_failed"));
+        let finished = completion.wait(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(finished.event_type, "run_failed");
+
+        // Finished waits leave no subscription behind
+        let subscriptions = |run_id: &str| SUBSCRIPTIONS.read().unwrap().iter()
+            .filter(|subscription| subscription.filter.run_id.as_deref() == Some(run_id))
+            .count();
+        assert_eq!(subscriptions("waited-run"), 0);
+    }
+}
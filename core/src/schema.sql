@@ -3,6 +3,9 @@
 -- This file contains the SQLite schema for the Node-Cronflow core engine.
 -- The schema is designed to store workflow definitions, execution runs,
 -- and step results with proper indexing for performance.
+--
+-- This is the initial migration (see migrations.rs). Databases already past it
+-- never run it again, so schema changes go into new migrations instead.
 
 -- Workflow definitions table
 -- Stores the workflow definitions that users create
@@ -12,7 +15,11 @@ CREATE TABLE IF NOT EXISTS workflows (
     description TEXT,
     definition TEXT NOT NULL,
     created_at TEXT NOT NULL,
-    updated_at TEXT NOT NULL
+    updated_at TEXT NOT NULL,
+    disabled INTEGER NOT NULL DEFAULT 0,
+    disabled_reason TEXT,
+    disabled_at TEXT,
+    disabled_until TEXT
 );
 
 -- Workflow runs table
@@ -25,9 +32,32 @@ CREATE TABLE IF NOT EXISTS workflow_runs (
     started_at TEXT NOT NULL,
     completed_at TEXT,
     error TEXT,
+    version INTEGER NOT NULL DEFAULT 0,
     FOREIGN KEY (workflow_id) REFERENCES workflows (id)
 );
 
+-- Run search index table
+-- Scalar values inside run payloads and step outputs by JSON path, so runs can be found by their content
+CREATE TABLE IF NOT EXISTS run_search_index (
+    run_id TEXT NOT NULL,
+    workflow_id TEXT NOT NULL,
+    run_started_at TEXT NOT NULL,
+    source TEXT NOT NULL,
+    path TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (run_id, source, path)
+);
+
+CREATE INDEX IF NOT EXISTS idx_run_search_index_value ON run_search_index (workflow_id, path, value);
+
+-- Workflow stores table
+-- Separate SQLite file holding the runs of each isolated workflow, kept once assigned
+CREATE TABLE IF NOT EXISTS workflow_stores (
+    workflow_id TEXT PRIMARY KEY,
+    store_path TEXT NOT NULL,
+    assigned_at TEXT NOT NULL
+);
+
 -- Step results table
 -- Stores the results of individual steps within a workflow run
 CREATE TABLE IF NOT EXISTS step_results (
@@ -40,6 +70,8 @@ CREATE TABLE IF NOT EXISTS step_results (
     started_at TEXT NOT NULL,
     completed_at TEXT,
     duration_ms INTEGER,
+    cache_hit TEXT,
+    resources TEXT,
     FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
 );
 
@@ -54,15 +86,396 @@ CREATE TABLE IF NOT EXISTS triggers (
     FOREIGN KEY (workflow_id) REFERENCES workflows (id)
 );
 
+-- Cold storage exports table
+-- Records each history export so repeated exports resume where the last one ended
+CREATE TABLE IF NOT EXISTS cold_storage_exports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    range_start TEXT NOT NULL,
+    range_end TEXT NOT NULL,
+    files_written INTEGER NOT NULL,
+    rows_written INTEGER NOT NULL,
+    exported_at TEXT NOT NULL
+);
+
+-- Run pins table
+-- Pinned runs are exempt from retention and archival cleanup
+CREATE TABLE IF NOT EXISTS run_pins (
+    run_id TEXT PRIMARY KEY,
+    reason TEXT NOT NULL,
+    pinned_by TEXT,
+    pinned_at TEXT NOT NULL,
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Run pin audit table
+-- Append-only history of who pinned and unpinned runs
+CREATE TABLE IF NOT EXISTS run_pin_audit (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    reason TEXT,
+    actor TEXT,
+    created_at TEXT NOT NULL
+);
+
+-- Engine state table
+-- Key/value flags that must survive restarts, such as the global pause
+CREATE TABLE IF NOT EXISTS engine_state (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Buffered runs table
+-- Runs created by triggers while the engine is paused, released in order on resume
+CREATE TABLE IF NOT EXISTS buffered_runs (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL UNIQUE,
+    workflow_id TEXT NOT NULL,
+    buffered_at TEXT NOT NULL,
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Run correlation IDs table
+-- External IDs callers attach to runs, unique within a workflow
+CREATE TABLE IF NOT EXISTS run_correlation_ids (
+    workflow_id TEXT NOT NULL,
+    correlation_id TEXT NOT NULL,
+    run_id TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, correlation_id)
+);
+
+-- Workflow versions table
+-- Every registered revision of a workflow definition
+CREATE TABLE IF NOT EXISTS workflow_versions (
+    workflow_id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    definition TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, version)
+);
+
+-- Run workflow versions table
+-- Workflow version each run started on and keeps executing with
+CREATE TABLE IF NOT EXISTS run_workflow_versions (
+    run_id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    version INTEGER NOT NULL
+);
+
+-- Signal waits table
+-- Runs paused on a signal step, with when the wait times out
+CREATE TABLE IF NOT EXISTS signal_waits (
+    run_id TEXT NOT NULL,
+    workflow_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    signal_name TEXT NOT NULL,
+    timeout_at TEXT,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (run_id, step_id)
+);
+
+-- Run signals table
+-- Signals sent to runs, kept until a signal step consumes them
+CREATE TABLE IF NOT EXISTS run_signals (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    signal_name TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    received_at TEXT NOT NULL
+);
+
+-- Run serialization queue table
+-- Unfinished runs of workflows serializing on a key, in the order they were queued; the oldest run of a key holds it
+CREATE TABLE IF NOT EXISTS run_serialization_queue (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL UNIQUE,
+    workflow_id TEXT NOT NULL,
+    serialization_key TEXT NOT NULL,
+    queued_at TEXT NOT NULL
+);
+
+-- Trigger dedup keys table
+-- Dedup key of the latest run a deduplicating trigger started, per workflow
+CREATE TABLE IF NOT EXISTS trigger_dedup_keys (
+    workflow_id TEXT NOT NULL,
+    dedup_key TEXT NOT NULL,
+    run_id TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, dedup_key)
+);
+
+-- Suppressed triggers table
+-- Trigger firings that did not start a run because they duplicated a recent one
+CREATE TABLE IF NOT EXISTS suppressed_triggers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workflow_id TEXT NOT NULL,
+    trigger_type TEXT NOT NULL,
+    source TEXT,
+    dedup_key TEXT NOT NULL,
+    run_id TEXT NOT NULL,
+    suppressed_at TEXT NOT NULL
+);
+
+-- Run parents table
+-- Links child runs started by sub-workflow steps to the parent run and step waiting on them
+CREATE TABLE IF NOT EXISTS run_parents (
+    run_id TEXT PRIMARY KEY,
+    parent_run_id TEXT NOT NULL,
+    parent_step_id TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+-- Run triggers table
+-- Trigger type and source that started each triggered run
+CREATE TABLE IF NOT EXISTS run_triggers (
+    run_id TEXT PRIMARY KEY,
+    trigger_type TEXT NOT NULL,
+    source TEXT,
+    fired_at TEXT NOT NULL,
+    trace_parent TEXT,
+    lane TEXT
+);
+
+-- Step durations table
+-- Recent durations of successful steps, the basis of adaptive timeouts
+CREATE TABLE IF NOT EXISTS step_durations (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    workflow_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    recorded_at TEXT NOT NULL
+);
+
+-- Trigger schedules table
+-- Next firing of interval and one-time triggers, kept so schedules survive restarts
+CREATE TABLE IF NOT EXISTS trigger_schedules (
+    trigger_id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    config TEXT NOT NULL,
+    next_fire_at TEXT,
+    last_fired_at TEXT,
+    FOREIGN KEY (workflow_id) REFERENCES workflows (id)
+);
+
+-- Trigger poll states table
+-- Last response value seen by each polling trigger, compared against on the next poll
+CREATE TABLE IF NOT EXISTS trigger_poll_states (
+    trigger_id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    fingerprint TEXT NOT NULL,
+    value TEXT NOT NULL,
+    checked_at TEXT NOT NULL,
+    changed_at TEXT,
+    FOREIGN KEY (workflow_id) REFERENCES workflows (id)
+);
+
+-- Trigger stats table
+-- Firing counts of each trigger of a workflow, added to periodically by every engine process
+CREATE TABLE IF NOT EXISTS trigger_stats (
+    trigger_key TEXT NOT NULL,
+    workflow_id TEXT NOT NULL,
+    fired INTEGER NOT NULL DEFAULT 0,
+    succeeded INTEGER NOT NULL DEFAULT 0,
+    rejected INTEGER NOT NULL DEFAULT 0,
+    auth_failed INTEGER NOT NULL DEFAULT 0,
+    rate_limited INTEGER NOT NULL DEFAULT 0,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (trigger_key, workflow_id)
+);
+
+-- Step delays table
+-- Wake-up times of runs waiting on delay steps, kept so delays survive restarts
+CREATE TABLE IF NOT EXISTS step_delays (
+    run_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    workflow_id TEXT NOT NULL,
+    wake_at TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    PRIMARY KEY (run_id, step_id),
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Event schemas table
+-- JSON Schema of each registered version of a published event
+CREATE TABLE IF NOT EXISTS event_schemas (
+    event_name TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    schema TEXT NOT NULL,
+    registered_at TEXT NOT NULL,
+    PRIMARY KEY (event_name, version)
+);
+
+-- Step trace events table
+-- Condition evaluations, skips and retries recorded so runs can be explained afterwards
+CREATE TABLE IF NOT EXISTS step_trace_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    event TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Run events table
+-- Append-only log of every state transition of a run, used for timelines and auditing
+CREATE TABLE IF NOT EXISTS run_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    step_id TEXT,
+    actor TEXT NOT NULL,
+    detail TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Step logs table
+-- Structured log lines appended by steps, read back in seq order
+CREATE TABLE IF NOT EXISTS step_logs (
+    seq INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    level TEXT NOT NULL,
+    message TEXT NOT NULL,
+    fields TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Step context checksums table
+-- Checksum of the latest context issued for each step; result submissions must echo it
+CREATE TABLE IF NOT EXISTS step_context_checksums (
+    run_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    checksum TEXT NOT NULL,
+    schema_version INTEGER NOT NULL,
+    attempt INTEGER NOT NULL,
+    issued_at TEXT NOT NULL,
+    PRIMARY KEY (run_id, step_id),
+    FOREIGN KEY (run_id) REFERENCES workflow_runs (id)
+);
+
+-- Ad hoc step executions table
+-- Scratch area for steps executed outside any run; entries expire
+CREATE TABLE IF NOT EXISTS adhoc_step_executions (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    result TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    FOREIGN KEY (workflow_id) REFERENCES workflows (id)
+);
+
+-- Step cache table
+-- Outputs of cached steps, reused by later executions with the same cache key until they expire
+CREATE TABLE IF NOT EXISTS step_cache (
+    workflow_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    cache_key TEXT NOT NULL,
+    output TEXT NOT NULL,
+    cached_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL,
+    PRIMARY KEY (workflow_id, step_id, cache_key)
+);
+
+-- Context state table
+-- Key/value store shared across the steps of a run or of every run of a workflow
+CREATE TABLE IF NOT EXISTS context_state (
+    scope TEXT NOT NULL,
+    scope_id TEXT NOT NULL,
+    key TEXT NOT NULL,
+    value TEXT NOT NULL,
+    expires_at TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (scope, scope_id, key)
+);
+
+-- Secrets table
+-- Secrets encrypted at rest, scoped to one workflow or global
+CREATE TABLE IF NOT EXISTS secrets (
+    scope TEXT NOT NULL,
+    key TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    ciphertext TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (scope, key)
+);
+
+-- Worker diagnostics table
+-- Latest counters of each dispatcher worker, kept across restarts
+CREATE TABLE IF NOT EXISTS worker_diagnostics (
+    worker_id TEXT PRIMARY KEY,
+    status TEXT NOT NULL,
+    current_job_id TEXT,
+    jobs_processed INTEGER NOT NULL,
+    error_count INTEGER NOT NULL,
+    total_processing_time_ms INTEGER NOT NULL,
+    recent_job_ids TEXT NOT NULL,
+    last_panic TEXT,
+    last_panic_at TEXT,
+    last_activity TEXT NOT NULL
+);
+
+-- Jobs table
+-- Latest state of each dispatched job
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    run_id TEXT NOT NULL,
+    step_name TEXT NOT NULL,
+    state TEXT NOT NULL,
+    job TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+
+-- Job leases table
+-- Worker holding each claimed job while several engine processes share the database
+CREATE TABLE IF NOT EXISTS job_leases (
+    job_id TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    expires_at INTEGER NOT NULL -- Unix time in milliseconds
+);
+
+-- Payload blobs table
+-- Large step outputs, referenced from step results by content hash
+CREATE TABLE IF NOT EXISTS payload_blobs (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    size_bytes INTEGER NOT NULL,
+    created_at TEXT NOT NULL
+);
+
 -- Indexes for performance
 CREATE INDEX IF NOT EXISTS idx_workflow_runs_workflow_id ON workflow_runs (workflow_id);
 CREATE INDEX IF NOT EXISTS idx_workflow_runs_status ON workflow_runs (status);
 CREATE INDEX IF NOT EXISTS idx_workflow_runs_started_at ON workflow_runs (started_at);
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_workflow_started ON workflow_runs (workflow_id, started_at);
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_status_started ON workflow_runs (status, started_at);
 CREATE INDEX IF NOT EXISTS idx_step_results_run_id ON step_results (run_id);
 CREATE INDEX IF NOT EXISTS idx_step_results_step_id ON step_results (step_id);
 CREATE INDEX IF NOT EXISTS idx_step_results_status ON step_results (status);
+CREATE INDEX IF NOT EXISTS idx_run_pin_audit_run_id ON run_pin_audit (run_id);
+CREATE INDEX IF NOT EXISTS idx_step_trace_events_run_id ON step_trace_events (run_id);
+CREATE INDEX IF NOT EXISTS idx_run_events_run_id ON run_events (run_id);
+CREATE INDEX IF NOT EXISTS idx_step_logs_step ON step_logs (run_id, step_id, seq);
+CREATE INDEX IF NOT EXISTS idx_adhoc_step_executions_workflow_id ON adhoc_step_executions (workflow_id);
 CREATE INDEX IF NOT EXISTS idx_triggers_workflow_id ON triggers (workflow_id);
 CREATE INDEX IF NOT EXISTS idx_triggers_type ON triggers (trigger_type);
+CREATE INDEX IF NOT EXISTS idx_jobs_run_id ON jobs (run_id);
+CREATE INDEX IF NOT EXISTS idx_step_durations_step ON step_durations (workflow_id, step_id, id);
+CREATE INDEX IF NOT EXISTS idx_step_delays_wake_at ON step_delays (wake_at);
+CREATE INDEX IF NOT EXISTS idx_signal_waits_timeout_at ON signal_waits (timeout_at);
+CREATE INDEX IF NOT EXISTS idx_run_signals_run ON run_signals (run_id, signal_name, id);
+CREATE INDEX IF NOT EXISTS idx_run_serialization_queue_key ON run_serialization_queue (workflow_id, serialization_key, id);
+CREATE INDEX IF NOT EXISTS idx_run_parents_parent ON run_parents (parent_run_id, parent_step_id);
+CREATE INDEX IF NOT EXISTS idx_trigger_dedup_keys_run ON trigger_dedup_keys (run_id);
+CREATE INDEX IF NOT EXISTS idx_suppressed_triggers_workflow ON suppressed_triggers (workflow_id, id);
 
 -- Views for common queries
 CREATE VIEW IF NOT EXISTS v_active_runs AS
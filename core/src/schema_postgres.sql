@@ -0,0 +1,97 @@
+-- Node-Cronflow Postgres Schema
+--
+-- Shared state store for multi-instance deployments. It holds the tables that
+-- every engine instance has to see: workflow definitions, runs, step results,
+-- offloaded step outputs, triggers, jobs and their leases. Node-local data stays in the
+-- SQLite file.
+
+-- Workflow definitions table
+CREATE TABLE IF NOT EXISTS workflows (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    definition JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL,
+    disabled BOOLEAN NOT NULL DEFAULT FALSE,
+    disabled_reason TEXT,
+    disabled_at TIMESTAMPTZ,
+    disabled_until TIMESTAMPTZ
+);
+ALTER TABLE workflows ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE workflows ADD COLUMN IF NOT EXISTS disabled_reason TEXT;
+ALTER TABLE workflows ADD COLUMN IF NOT EXISTS disabled_at TIMESTAMPTZ;
+ALTER TABLE workflows ADD COLUMN IF NOT EXISTS disabled_until TIMESTAMPTZ;
+
+-- Workflow runs table
+CREATE TABLE IF NOT EXISTS workflow_runs (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    payload JSONB NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ,
+    error TEXT,
+    version BIGINT NOT NULL DEFAULT 0
+);
+ALTER TABLE workflow_runs ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 0;
+
+-- Step results table
+CREATE TABLE IF NOT EXISTS step_results (
+    id BIGSERIAL PRIMARY KEY,
+    run_id TEXT NOT NULL,
+    step_id TEXT NOT NULL,
+    status TEXT NOT NULL,
+    output JSONB,
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ,
+    duration_ms BIGINT,
+    cache_hit JSONB,
+    resources JSONB
+);
+ALTER TABLE step_results ADD COLUMN IF NOT EXISTS cache_hit JSONB;
+ALTER TABLE step_results ADD COLUMN IF NOT EXISTS resources JSONB;
+
+-- Triggers table
+CREATE TABLE IF NOT EXISTS triggers (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    trigger_type TEXT NOT NULL,
+    config JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- Jobs table
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    workflow_id TEXT NOT NULL,
+    run_id TEXT NOT NULL,
+    step_name TEXT NOT NULL,
+    state TEXT NOT NULL,
+    job JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL
+);
+
+-- Job leases table
+CREATE TABLE IF NOT EXISTS job_leases (
+    job_id TEXT PRIMARY KEY,
+    owner TEXT NOT NULL,
+    expires_at BIGINT NOT NULL
+);
+
+-- Payload blobs table
+CREATE TABLE IF NOT EXISTS payload_blobs (
+    id TEXT PRIMARY KEY,
+    data TEXT NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+-- Indexes for performance
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_workflow_started ON workflow_runs (workflow_id, started_at);
+CREATE INDEX IF NOT EXISTS idx_workflow_runs_status_started ON workflow_runs (status, started_at);
+CREATE INDEX IF NOT EXISTS idx_step_results_run_id ON step_results (run_id);
+CREATE INDEX IF NOT EXISTS idx_triggers_workflow_id ON triggers (workflow_id);
+CREATE INDEX IF NOT EXISTS idx_jobs_run_id ON jobs (run_id);
@@ -0,0 +1,211 @@
+//! Secrets store
+//!
+//! Secrets are encrypted with AES-256-GCM under the key set by
+//! `CRONFLOW_SECRETS_KEY` before they are written to the database; the nonce
+//! is stored next to the ciphertext and the scope and key are bound in as
+//! associated data, so a ciphertext cannot be moved to another row. A secret is
+//! scoped to one workflow or is global, and steps receive the secrets of their
+//! workflow in their context, workflow secrets taking precedence.
+//!
+//! Every secret value the engine has seen is masked in log lines and in run
+//! event records.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use crate::config::SecretsConfig;
+use crate::error::{CoreError, CoreResult};
+use crate::models::StoredSecret;
+
+/// Scope of secrets available to every workflow
+pub const GLOBAL_SCOPE: &str = "global";
+
+/// Text secret values are replaced with
+pub const MASK: &str = "********";
+
+/// Secret values masked in logs and run events, longest first
+static MASKED_VALUES: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Encrypts and decrypts secret values
+pub struct SecretCipher {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretCipher {
+    /// Create the cipher from the configured key, if one is set
+    pub fn from_config(config: &SecretsConfig) -> CoreResult<Option<Self>> {
+        config.validate().map_err(CoreError::Configuration)?;
+        config.encryption_key.as_deref().map(Self::from_hex).transpose()
+    }
+
+    /// Create the cipher from a hex-encoded 256-bit key
+    pub fn from_hex(hex_key: &str) -> CoreResult<Self> {
+        let bytes = hex::decode(hex_key)
+            .map_err(|e| CoreError::Configuration(format!("Invalid secrets encryption key: {}", e)))?;
+        let key = UnboundKey::new(&AES_256_GCM, &bytes)
+            .map_err(|_| CoreError::Configuration("Secrets encryption key must be 256 bits".to_string()))?;
+        Ok(Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        })
+    }
+
+    /// Encrypt a secret value for storage
+    pub fn encrypt(&self, scope: &str, key: &str, value: &str) -> CoreResult<StoredSecret> {
+        let mut nonce = [0u8; NONCE_LEN];
+        self.rng.fill(&mut nonce)
+            .map_err(|_| CoreError::Internal("Failed to generate a secret nonce".to_string()))?;
+        let mut data = value.as_bytes().to_vec();
+        self.key.seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Self::aad(scope, key), &mut data)
+            .map_err(|_| CoreError::Internal(format!("Failed to encrypt secret {}", key)))?;
+
+        Ok(StoredSecret {
+            key: key.to_string(),
+            scope: scope.to_string(),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(data),
+            updated_at: chrono::Utc::now(),
+        })
+    }
+
+    /// Decrypt a stored secret
+    pub fn decrypt(&self, secret: &StoredSecret) -> CoreResult<String> {
+        let undecryptable = || CoreError::Validation(format!("Secret {} of scope {} cannot be decrypted with the configured key", secret.key, secret.scope));
+        let nonce: [u8; NONCE_LEN] = hex::decode(&secret.nonce).ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(undecryptable)?;
+        let mut data = hex::decode(&secret.ciphertext).map_err(|_| undecryptable())?;
+        let plaintext = self.key.open_in_place(Nonce::assume_unique_for_key(nonce), Self::aad(&secret.scope, &secret.key), &mut data)
+            .map_err(|_| undecryptable())?;
+        String::from_utf8(plaintext.to_vec()).map_err(|_| undecryptable())
+    }
+
+    fn aad(scope: &str, key: &str) -> Aad<Vec<u8>> {
+        Aad::from(format!("{}/{}", scope, key).into_bytes())
+    }
+}
+
+/// Decrypt the secrets a workflow's steps receive, workflow secrets overriding global ones
+pub fn decrypt_for_workflow(cipher: &SecretCipher, secrets: &[StoredSecret], workflow_id: &str) -> CoreResult<HashMap<String, String>> {
+    let mut values = HashMap::new();
+    for scope in [GLOBAL_SCOPE, workflow_id] {
+        for secret in secrets.iter().filter(|secret| secret.scope == scope) {
+            let value = cipher.decrypt(secret)?;
+            register_masked_value(&value);
+            values.insert(secret.key.clone(), value);
+        }
+    }
+    Ok(values)
+}
+
+/// Mask a secret value from now on
+pub fn register_masked_value(value: &str) {
+    if value.is_empty() {
+        return;
+    }
+    let Ok(mut values) = MASKED_VALUES.write() else {
+        return;
+    };
+    if !values.iter().any(|known| known == value) {
+        values.push(value.to_string());
+        values.sort_by_key(|known| std::cmp::Reverse(known.len()));
+    }
+}
+
+/// Replace known secret values in a text
+pub fn mask(text: &str) -> Cow<'_, str> {
+    let Ok(values) = MASKED_VALUES.read() else {
+        return Cow::Borrowed(text);
+    };
+    let mut masked = Cow::Borrowed(text);
+    for value in values.iter() {
+        if masked.contains(value.as_str()) {
+            masked = Cow::Owned(masked.replace(value.as_str(), MASK));
+        }
+    }
+    masked
+}
+
+/// Replace known secret values in every string of a JSON value
+pub fn mask_value(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => {
+            if let Cow::Owned(masked) = mask(text) {
+                *text = masked;
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(mask_value),
+        serde_json::Value::Object(map) => map.values_mut().for_each(mask_value),
+        _ => {}
+    }
+}
+
+/// Logger masking secret values before handing records to the wrapped logger
+pub struct MaskingLogger<L> {
+    inner: L,
+}
+
+impl<L: log::Log> MaskingLogger<L> {
+    pub fn new(inner: L) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &L {
+        &self.inner
+    }
+}
+
+impl<L: log::Log> log::Log for MaskingLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        self.inner.log(&log::Record::builder()
+            .args(format_args!("{}", mask(&message)))
+            .metadata(record.metadata().clone())
+            .module_path(record.module_path())
+            .file(record.file())
+            .line(record.line())
+            .build());
+    }
+
+    fn flush(&self) {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_round_trip_bound_to_their_row_and_are_masked() {
+        let cipher = SecretCipher::from_hex(&"2a".repeat(32)).unwrap();
+        let global = cipher.encrypt(GLOBAL_SCOPE, "API_TOKEN", "tok-global-4242").unwrap();
+        let scoped = cipher.encrypt("billing", "API_TOKEN", "tok-billing-9191").unwrap();
+        assert!(!global.ciphertext.contains(&hex::encode("tok-global-4242")));
+        assert_eq!(cipher.decrypt(&global).unwrap(), "tok-global-4242");
+
+        let moved = StoredSecret { scope: "billing".to_string(), ..global.clone() };
+        assert!(cipher.decrypt(&moved).is_err());
+        let other_key = SecretCipher::from_hex(&"3b".repeat(32)).unwrap();
+        assert!(other_key.decrypt(&global).is_err());
+
+        let secrets = vec![scoped, global];
+        assert_eq!(decrypt_for_workflow(&cipher, &secrets, "billing").unwrap()["API_TOKEN"], "tok-billing-9191");
+        assert_eq!(decrypt_for_workflow(&cipher, &secrets, "reports").unwrap()["API_TOKEN"], "tok-global-4242");
+
+        let mut detail = serde_json::json!({"error": "401 for tok-billing-9191", "attempts": [{"header": "Bearer tok-global-4242"}]});
+        mask_value(&mut detail);
+        assert_eq!(detail, serde_json::json!({"error": "401 for ********", "attempts": [{"header": "Bearer ********"}]}));
+        assert_eq!(mask("nothing secret here"), "nothing secret here");
+    }
+}
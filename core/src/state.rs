@@ -1,49 +1,645 @@
 //! State management for the Node-Cronflow Core Engine
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use crate::error::{CoreError, CoreResult};
-use crate::models::{WorkflowDefinition, WorkflowRun, StepResult, RunStatus};
+use crate::models::{WorkflowDefinition, WorkflowRun, StepResult, CachedStepOutput, RunStatus, StepCompletion, StepBatchCompletionSummary, RunRequest, RunQuery, RunPage, BulkRunSummary, RunPin, RunPinAuditEntry, StepTraceEvent, RunExplanation, RunEvent, RunRecoveryPlan, NonIdempotentRecovery, StepContextChecksum, AdhocStepExecution, StateEntry, StateScope, StoredSecret, StepLogEntry, StepLogLevel, WorkerDiagnostics, StoredTrigger, TriggerSchedule, TriggerPollState, TriggerDefinition, StepDelay, SignalWait, RunSignal, RunParent, RunTrigger, RunLabels, validate_run_labels, DisabledWorkflow, SuppressedTrigger, TriggerDedup, EventSchema, WorkflowVersion, StepControl, StepStatus, AdaptiveTimeout, StepDurationStats, FinishedRun, OrphanRunPolicy, OrphanRunOutcome, OrphanedRun, OrphanRecoveryReport, RecoveryAction, percentile_ms};
+use crate::context::{Context, ContextState, ExecutionInfo};
+use crate::payload_sampling;
+use crate::output_schemas;
+use crate::event_schemas;
+use crate::payload_offload::{self, OutputLimits};
 use crate::database::{Database, AsyncDatabase};
+use crate::job::Job;
+use crate::storage::{self, StorageBackend};
+use crate::isolated_stores::IsolatedStores;
+use crate::record_compat::CompatibilityReport;
+use crate::cold_storage::{ColdStorageExporter, ColdStorageExportSummary};
+use crate::config::RetentionConfig;
+use crate::retention::{self, RunPurgeSummary};
+use crate::run_stream;
+use crate::run_search;
+use crate::secrets::{self, SecretCipher};
+use crate::telemetry::{self, RunTrace};
+use crate::workflow_bundle::{WorkflowBundle, WorkflowImport};
+
+/// Attempts at a run update that keeps losing to concurrent writers before it fails with a conflict
+pub const RUN_WRITE_ATTEMPTS: usize = 5;
+
+/// Source of the IDs given to new runs
+pub trait RunIdGenerator: Send + Sync {
+    /// Get the ID of a new run of a workflow
+    fn next_run_id(&self, workflow_id: &str) -> Uuid;
+}
+
+/// Default run ID generator, giving random v4 UUIDs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomRunIds;
+
+impl RunIdGenerator for RandomRunIds {
+    fn next_run_id(&self, _workflow_id: &str) -> Uuid {
+        Uuid::new_v4()
+    }
+}
 
 /// State manager for workflow orchestration (synchronous version - kept for backward compatibility)
 pub struct StateManager {
     db: Database,
+    /// Shared state store, when one is configured in place of the local database
+    storage: Option<Box<dyn StorageBackend>>,
     active_runs: HashMap<Uuid, WorkflowRun>,
+    output_limits: OutputLimits,
+    run_ids: Box<dyn RunIdGenerator>,
+    /// Cipher of the secrets store, when an encryption key is configured
+    secret_cipher: Option<SecretCipher>,
 }
 
 impl StateManager {
     /// Create a new state manager
+    ///
+    /// Workflows, runs, steps, triggers and jobs go to the shared store set by
+    /// `CRONFLOW_DATABASE_URL` if there is one, and to `db_path` otherwise,
+    /// apart from the runs of workflows routed to isolated stores.
     pub fn new(db_path: &str) -> CoreResult<Self> {
+        let config = crate::config::DatabaseConfig::default();
+        let storage = match config.url {
+            Some(url) => Some(storage::open_backend(&url)?),
+            None => IsolatedStores::open(db_path, &config.isolated_stores)?
+                .map(|stores| Box::new(stores) as Box<dyn StorageBackend>),
+        };
+        Self::with_storage(db_path, storage)
+    }
+
+    /// Create a new state manager persisting shared state to the given backend
+    pub fn with_storage(db_path: &str, storage: Option<Box<dyn StorageBackend>>) -> CoreResult<Self> {
         let db = Database::new(db_path)?;
+        if let Some(storage) = &storage {
+            log::info!("Using the {} state store", storage.name());
+            // Events, pins and other node-local records refer to runs the local database may not hold
+            db.disable_foreign_keys()?;
+        }
+        let secret_cipher = SecretCipher::from_config(&crate::config::SecretsConfig::default())?;
+        if let Some(cipher) = &secret_cipher {
+            // Mask stored secrets in logs from the start, not only once a step receives them
+            for secret in db.get_secrets(None)? {
+                match cipher.decrypt(&secret) {
+                    Ok(value) => secrets::register_masked_value(&value),
+                    Err(e) => log::warn!("{}", e),
+                }
+            }
+        }
         Ok(StateManager {
             db,
+            storage,
             active_runs: HashMap::new(),
+            output_limits: OutputLimits::from_config(&crate::config::PayloadConfig::default()),
+            run_ids: Box::new(RandomRunIds),
+            secret_cipher,
         })
     }
 
+    /// Replace the cipher of the secrets store
+    pub fn set_secret_cipher(&mut self, cipher: Option<SecretCipher>) {
+        self.secret_cipher = cipher;
+    }
+
+    /// Replace the generator of run IDs
+    pub fn set_run_id_generator(&mut self, run_ids: Box<dyn RunIdGenerator>) {
+        self.run_ids = run_ids;
+    }
+
+    /// Get the store holding workflows, runs, steps, triggers and jobs
+    fn store(&self) -> &dyn StorageBackend {
+        Self::store_of(&self.db, &self.storage)
+    }
+
+    /// Same as `store`, borrowing only the fields it needs
+    fn store_of<'a>(db: &'a Database, storage: &'a Option<Box<dyn StorageBackend>>) -> &'a dyn StorageBackend {
+        storage.as_deref().unwrap_or(db)
+    }
+
     /// Register a new workflow
     pub fn register_workflow(&self, workflow: WorkflowDefinition) -> CoreResult<()> {
         log::info!("Registering workflow: {}", workflow.id);
-        self.db.save_workflow(&workflow)
+        for trigger in &workflow.triggers {
+            if let TriggerDefinition::Event { event_name, versions, .. } = trigger {
+                event_schemas::check_subscription(event_name, versions, &self.db.get_event_schemas(event_name)?)?;
+            }
+        }
+        let store = self.store();
+        store.save_workflow(&workflow)?;
+        // Re-registering an unchanged definition, e.g. on every startup, keeps its version
+        let latest = self.db.get_latest_workflow_version(&workflow.id)?;
+        if !latest.is_some_and(|latest| same_definition(&latest.definition, &workflow)) {
+            let version = self.db.save_workflow_version(&workflow)?;
+            log::info!("Registered version {} of workflow {}", version, workflow.id);
+        }
+        store.delete_triggers(&workflow.id)?;
+        let triggers = StoredTrigger::for_workflow(&workflow);
+        for trigger in &triggers {
+            store.save_trigger(trigger)?;
+        }
+        self.sync_trigger_schedules(&workflow.id, &triggers, Utc::now())
+    }
+
+    /// Register several workflows in one transaction of the local database
+    ///
+    /// Either every workflow is registered or, on the first error, none is.
+    pub fn register_workflows(&mut self, workflows: Vec<WorkflowDefinition>) -> CoreResult<usize> {
+        let count = workflows.len();
+        self.in_transaction(|state_manager| {
+            for workflow in workflows {
+                state_manager.register_workflow(workflow)?;
+            }
+            Ok(count)
+        })?;
+        log::info!("Registered batch of {} workflows", count);
+        Ok(count)
+    }
+
+    /// Run `f` in one transaction of the local database, rolling it back if `f` fails
+    ///
+    /// Writes to a configured shared store are not part of the transaction.
+    fn in_transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> CoreResult<T>) -> CoreResult<T> {
+        self.db.begin_transaction()?;
+        match f(self) {
+            Ok(value) => {
+                self.db.commit_transaction()?;
+                Ok(value)
+            }
+            Err(e) => {
+                if let Err(rollback_error) = self.db.rollback_transaction() {
+                    log::warn!("Failed to roll back transaction: {}", rollback_error);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Bring the schedules of a workflow's interval, polling and one-time triggers in line with its triggers
+    ///
+    /// Unchanged triggers keep their persisted schedule, so re-registering a
+    /// workflow on startup neither resets intervals nor refires one-time triggers.
+    fn sync_trigger_schedules(&self, workflow_id: &str, triggers: &[StoredTrigger], now: chrono::DateTime<Utc>) -> CoreResult<()> {
+        let existing = self.db.get_trigger_schedules(Some(workflow_id))?;
+        for schedule in &existing {
+            let unchanged = triggers.iter().any(|t| t.id == schedule.trigger_id && same_trigger(&t.trigger, &schedule.trigger));
+            if !unchanged {
+                self.db.delete_trigger_schedule(&schedule.trigger_id)?;
+                self.db.delete_trigger_poll_state(&schedule.trigger_id)?;
+            }
+        }
+        
+        for trigger in triggers {
+            let Some(next_fire_at) = trigger.trigger.first_fire_at(now) else {
+                continue;
+            };
+            let kept = existing.iter().any(|s| s.trigger_id == trigger.id && same_trigger(&trigger.trigger, &s.trigger));
+            if !kept {
+                self.db.save_trigger_schedule(&TriggerSchedule {
+                    trigger_id: trigger.id.clone(),
+                    workflow_id: workflow_id.to_string(),
+                    trigger: trigger.trigger.clone(),
+                    next_fire_at: Some(next_fire_at),
+                    last_fired_at: None,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get the trigger schedules of a workflow
+    pub fn get_trigger_schedules(&self, workflow_id: &str) -> CoreResult<Vec<TriggerSchedule>> {
+        self.db.get_trigger_schedules(Some(workflow_id))
+    }
+
+    /// Get the trigger schedules due at `now`, earliest first
+    pub fn get_due_trigger_schedules(&self, now: chrono::DateTime<Utc>) -> CoreResult<Vec<TriggerSchedule>> {
+        let mut due: Vec<TriggerSchedule> = self.db.get_trigger_schedules(None)?
+            .into_iter()
+            .filter(|s| s.is_due(now))
+            .collect();
+        due.sort_by_key(|s| s.next_fire_at);
+        Ok(due)
+    }
+
+    /// Get when the next trigger schedule is due
+    pub fn get_next_trigger_fire_at(&self) -> CoreResult<Option<chrono::DateTime<Utc>>> {
+        Ok(self.db.get_trigger_schedules(None)?
+            .iter()
+            .filter_map(|s| s.next_fire_at)
+            .min())
+    }
+
+    /// Record that a schedule fired at `now` and move it to its next firing
+    pub fn advance_trigger_schedule(&self, schedule: &TriggerSchedule, now: chrono::DateTime<Utc>) -> CoreResult<TriggerSchedule> {
+        let advanced = TriggerSchedule {
+            next_fire_at: schedule.next_fire_at.and_then(|at| schedule.trigger.next_fire_after(at, now)),
+            last_fired_at: Some(now),
+            ..schedule.clone()
+        };
+        self.db.save_trigger_schedule(&advanced)?;
+        Ok(advanced)
+    }
+
+    /// Get the last response value seen by a polling trigger
+    pub fn get_trigger_poll_state(&self, trigger_id: &str) -> CoreResult<Option<TriggerPollState>> {
+        self.db.get_trigger_poll_state(trigger_id)
+    }
+
+    /// Record the response value a polling trigger just saw
+    pub fn save_trigger_poll_state(&self, state: &TriggerPollState) -> CoreResult<()> {
+        self.db.save_trigger_poll_state(state)
+    }
+
+    /// Persist the wake-up of a run reaching a delay step
+    pub fn delay_step(&self, run_id: &Uuid, workflow_id: &str, step_id: &str, wake_at: chrono::DateTime<Utc>) -> CoreResult<StepDelay> {
+        let delay = StepDelay {
+            run_id: run_id.to_string(),
+            workflow_id: workflow_id.to_string(),
+            step_id: step_id.to_string(),
+            wake_at,
+            created_at: Utc::now(),
+        };
+        self.db.save_step_delay(&delay)?;
+        self.record_run_event(run_id, RunEvent::STEP_DELAYED, Some(step_id), RunEvent::ACTOR_ENGINE, serde_json::json!({"wake_at": wake_at}))?;
+        Ok(delay)
+    }
+
+    /// Get the pending delay of a step of a run
+    pub fn get_step_delay(&self, run_id: &Uuid, step_id: &str) -> CoreResult<Option<StepDelay>> {
+        Ok(self.db.get_step_delays(Some(&run_id.to_string()))?
+            .into_iter()
+            .find(|d| d.step_id == step_id))
+    }
+
+    /// Get the delays whose runs should be woken at `now`, earliest first
+    pub fn get_due_step_delays(&self, now: chrono::DateTime<Utc>) -> CoreResult<Vec<StepDelay>> {
+        Ok(self.db.get_step_delays(None)?
+            .into_iter()
+            .filter(|d| d.is_due(now))
+            .collect())
+    }
+
+    /// Get when the next delayed run should be woken
+    pub fn get_next_delay_wake_at(&self) -> CoreResult<Option<chrono::DateTime<Utc>>> {
+        Ok(self.db.get_step_delays(None)?.first().map(|d| d.wake_at))
+    }
+
+    /// Remove the delay of a step once its run moved past it
+    pub fn clear_step_delay(&self, run_id: &Uuid, step_id: &str) -> CoreResult<()> {
+        self.db.delete_step_delay(&run_id.to_string(), step_id)
+    }
+
+    /// Persist the wait of a run reaching a signal step
+    pub fn wait_for_signal(&self, run_id: &Uuid, workflow_id: &str, step_id: &str, signal_name: &str, timeout_at: Option<chrono::DateTime<Utc>>) -> CoreResult<SignalWait> {
+        let wait = SignalWait {
+            run_id: run_id.to_string(),
+            workflow_id: workflow_id.to_string(),
+            step_id: step_id.to_string(),
+            signal_name: signal_name.to_string(),
+            timeout_at,
+            created_at: Utc::now(),
+        };
+        self.db.save_signal_wait(&wait)?;
+        self.record_run_event(run_id, RunEvent::SIGNAL_AWAITED, Some(step_id), RunEvent::ACTOR_ENGINE, serde_json::json!({
+            "signal": signal_name,
+            "timeout_at": timeout_at,
+        }))?;
+        Ok(wait)
+    }
+
+    /// Get the pending signal wait of a step of a run
+    pub fn get_signal_wait(&self, run_id: &Uuid, step_id: &str) -> CoreResult<Option<SignalWait>> {
+        Ok(self.db.get_signal_waits(Some(&run_id.to_string()))?
+            .into_iter()
+            .find(|w| w.step_id == step_id))
+    }
+
+    /// Get the signal waits that have timed out at `now`, whose runs should be woken
+    pub fn get_timed_out_signal_waits(&self, now: chrono::DateTime<Utc>) -> CoreResult<Vec<SignalWait>> {
+        Ok(self.db.get_signal_waits(None)?
+            .into_iter()
+            .filter(|w| w.is_timed_out(now))
+            .collect())
+    }
+
+    /// Get when the next signal wait times out
+    pub fn get_next_signal_timeout_at(&self) -> CoreResult<Option<chrono::DateTime<Utc>>> {
+        Ok(self.db.get_signal_waits(None)?.iter().filter_map(|w| w.timeout_at).min())
+    }
+
+    /// Remove the signal wait of a step once its run moved past it
+    pub fn clear_signal_wait(&self, run_id: &Uuid, step_id: &str) -> CoreResult<()> {
+        self.db.delete_signal_wait(&run_id.to_string(), step_id)
+    }
+
+    /// Consume the oldest signal of a name sent to a run
+    pub fn take_signal(&self, run_id: &Uuid, signal_name: &str) -> CoreResult<Option<RunSignal>> {
+        self.db.take_run_signal(&run_id.to_string(), signal_name)
+    }
+
+    /// Send a signal to a run, returning the wait of the step it resumes
+    ///
+    /// The signal is kept until a signal step of the run consumes it, so one
+    /// sent before the run reaches its step is not lost. Finished runs cannot
+    /// receive signals.
+    pub fn send_signal(&self, run_id: &Uuid, signal_name: &str, payload: serde_json::Value) -> CoreResult<Option<SignalWait>> {
+        if signal_name.trim().is_empty() {
+            return Err(CoreError::Validation("Signal name cannot be empty".to_string()));
+        }
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        if run.status.is_terminal() {
+            return Err(CoreError::State(format!("Run {} is {:?} and cannot receive signals", run_id, run.status)));
+        }
+        
+        self.db.save_run_signal(&run_id.to_string(), signal_name, &payload, &Utc::now())?;
+        let wait = self.db.get_signal_waits(Some(&run_id.to_string()))?
+            .into_iter()
+            .find(|w| w.signal_name == signal_name);
+        self.record_run_event(run_id, RunEvent::SIGNAL_RECEIVED, wait.as_ref().map(|w| w.step_id.as_str()), RunEvent::ACTOR_API, serde_json::json!({
+            "signal": signal_name,
+        }))?;
+        Ok(wait)
+    }
+
+    /// Register the JSON Schema of an event version
+    ///
+    /// Registering a version again with the same schema is a no-op; a version
+    /// cannot be given a different schema.
+    pub fn register_event_schema(&self, event_name: &str, version: u32, schema: serde_json::Value) -> CoreResult<EventSchema> {
+        if event_name.trim().is_empty() {
+            return Err(CoreError::Validation("Event name cannot be empty".to_string()));
+        }
+        let event_schema = EventSchema {
+            event_name: event_name.to_string(),
+            version,
+            schema,
+            registered_at: Utc::now(),
+        };
+        event_schemas::compile(&event_schema)?;
+        
+        if let Some(existing) = self.db.get_event_schemas(event_name)?.into_iter().find(|s| s.version == version) {
+            if existing.schema != event_schema.schema {
+                return Err(CoreError::IncompatibleEventSchema(format!(
+                    "Version {} of event '{}' is already registered with a different schema", version, event_name
+                )));
+            }
+            return Ok(existing);
+        }
+        self.db.save_event_schema(&event_schema)?;
+        log::info!("Registered schema version {} of event: {}", version, event_name);
+        Ok(event_schema)
+    }
+
+    /// Get the registered schemas of an event, oldest version first
+    pub fn get_event_schemas(&self, event_name: &str) -> CoreResult<Vec<EventSchema>> {
+        self.db.get_event_schemas(event_name)
+    }
+
+    /// Get the schema of an event version, or of its latest version
+    ///
+    /// Returns None for events without registered schemas; asking for a
+    /// version such an event does not have is an error.
+    pub fn get_event_schema(&self, event_name: &str, version: Option<u32>) -> CoreResult<Option<EventSchema>> {
+        let schemas = self.db.get_event_schemas(event_name)?;
+        match version {
+            Some(version) => match schemas.into_iter().find(|s| s.version == version) {
+                Some(schema) => Ok(Some(schema)),
+                None => Err(CoreError::IncompatibleEventSchema(format!(
+                    "Event '{}' has no schema version {}", event_name, version
+                ))),
+            },
+            None => Ok(schemas.into_iter().last()),
+        }
+    }
+
+    /// Report the stored workflows, runs and step results this engine cannot read
+    pub fn verify_store_compatibility(&self) -> CoreResult<CompatibilityReport> {
+        self.store().verify_record_compatibility()
+    }
+
+    /// Get one registered version of a workflow
+    pub fn get_workflow_version(&self, workflow_id: &str, version: u32) -> CoreResult<Option<WorkflowVersion>> {
+        self.db.get_workflow_version(workflow_id, version)
+    }
+
+    /// List the registered versions of a workflow, oldest first
+    pub fn list_workflow_versions(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowVersion>> {
+        self.db.get_workflow_versions(workflow_id)
+    }
+
+    /// Export a workflow as a portable bundle
+    pub fn export_workflow(&self, workflow_id: &str) -> CoreResult<WorkflowBundle> {
+        let workflow = self.get_workflow(workflow_id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?;
+        let version = self.db.get_latest_workflow_version(workflow_id)?;
+        WorkflowBundle::export(&workflow, version.as_ref())
+    }
+
+    /// Register the workflow of a bundle, as a new version if it changed
+    pub fn import_workflow(&self, bundle: &WorkflowBundle, bundle_schema_version: u32) -> CoreResult<WorkflowImport> {
+        let workflow = bundle.definition()?;
+        let workflow_id = workflow.id.clone();
+        let trigger_count = workflow.triggers.len();
+        self.register_workflow(workflow)?;
+        
+        let version = self.db.get_latest_workflow_version(&workflow_id)?;
+        log::info!("Imported workflow {} from a schema version {} bundle", workflow_id, bundle_schema_version);
+        Ok(WorkflowImport {
+            workflow_id,
+            bundle_schema_version,
+            source_version: bundle.source_version.as_ref().map(|source| source.version),
+            version: version.map(|version| version.version),
+            trigger_count,
+        })
     }
 
     /// Get a workflow by ID
     pub fn get_workflow(&self, id: &str) -> CoreResult<Option<WorkflowDefinition>> {
-        self.db.get_workflow(id)
+        self.store().get_workflow(id)
+    }
+
+    /// Disable a workflow so its triggers are rejected, until `until` if given
+    ///
+    /// Its definition can still be updated and runs already started carry on.
+    pub fn disable_workflow(&self, workflow_id: &str, reason: Option<&str>, until: Option<DateTime<Utc>>) -> CoreResult<DisabledWorkflow> {
+        let now = Utc::now();
+        if until.is_some_and(|until| until <= now) {
+            return Err(CoreError::Validation(format!("Workflow {} cannot be disabled until a time already past", workflow_id)));
+        }
+        let disabled = DisabledWorkflow {
+            workflow_id: workflow_id.to_string(),
+            reason: reason.map(str::trim).filter(|reason| !reason.is_empty()).map(str::to_string),
+            disabled_at: now,
+            until,
+        };
+        if !self.store().set_workflow_disabled(workflow_id, Some(&disabled))? {
+            return Err(CoreError::WorkflowNotFound(workflow_id.to_string()));
+        }
+        log::info!("Disabled workflow {} until {:?}: {:?}", workflow_id, until, disabled.reason);
+        Ok(disabled)
+    }
+
+    /// Enable a workflow, returning whether it was disabled
+    pub fn enable_workflow(&self, workflow_id: &str) -> CoreResult<bool> {
+        let was_disabled = self.get_disabled_workflow(workflow_id)?.is_some();
+        if !self.store().set_workflow_disabled(workflow_id, None)? {
+            return Err(CoreError::WorkflowNotFound(workflow_id.to_string()));
+        }
+        if was_disabled {
+            log::info!("Enabled workflow {}", workflow_id);
+        }
+        Ok(was_disabled)
+    }
+
+    /// Get how a workflow was disabled, None if it is enabled
+    ///
+    /// A workflow whose maintenance window has ended is enabled again here.
+    pub fn get_disabled_workflow(&self, workflow_id: &str) -> CoreResult<Option<DisabledWorkflow>> {
+        let Some(disabled) = self.store().get_disabled_workflow(workflow_id)? else {
+            return Ok(None);
+        };
+        if disabled.is_active(Utc::now()) {
+            return Ok(Some(disabled));
+        }
+        self.store().set_workflow_disabled(workflow_id, None)?;
+        log::info!("Maintenance window of workflow {} ended, enabled it again", workflow_id);
+        Ok(None)
+    }
+
+    /// Fail with `CoreError::WorkflowDisabled` if a workflow is disabled
+    pub fn check_workflow_enabled(&self, workflow_id: &str) -> CoreResult<()> {
+        let Some(disabled) = self.get_disabled_workflow(workflow_id)? else {
+            return Ok(());
+        };
+        let mut message = format!("{} is disabled", workflow_id);
+        if let Some(reason) = &disabled.reason {
+            message.push_str(&format!(" ({})", reason));
+        }
+        if let Some(until) = disabled.until {
+            message.push_str(&format!(" until {}", until.to_rfc3339()));
+        }
+        Err(CoreError::WorkflowDisabled(message))
+    }
+
+    /// Get the stored triggers of a workflow
+    pub fn get_triggers(&self, workflow_id: &str) -> CoreResult<Vec<StoredTrigger>> {
+        self.store().get_triggers(workflow_id)
+    }
+
+    /// Record the latest state of a job
+    pub fn save_job(&self, job: &Job) -> CoreResult<()> {
+        self.store().save_job(job)
+    }
+
+    /// Remove the record of a job that was never queued
+    pub fn forget_job(&self, job_id: &str) -> CoreResult<()> {
+        self.store().delete_job(job_id)
+    }
+
+    /// Get the recorded jobs of a run
+    pub fn get_jobs_for_run(&self, run_id: &str) -> CoreResult<Vec<Job>> {
+        self.store().get_jobs_for_run(run_id)
+    }
+
+    /// Lease a recorded job to a worker for a while, unless another worker holds it
+    pub fn claim_job(&self, job_id: &str, owner: &str, lease: chrono::Duration) -> CoreResult<bool> {
+        let now = Utc::now();
+        self.store().claim_job(job_id, owner, &(now + lease), &now)
+    }
+
+    /// Extend a job lease until the given time, returning false if the worker lost it
+    pub fn renew_job_lease(&self, job_id: &str, owner: &str, until: chrono::DateTime<Utc>) -> CoreResult<bool> {
+        self.store().renew_job_lease(job_id, owner, &until)
+    }
+
+    /// Give up a job lease
+    pub fn release_job(&self, job_id: &str, owner: &str) -> CoreResult<()> {
+        self.store().release_job(job_id, owner)
+    }
+
+    /// Get recorded jobs no live lease holds, including jobs whose worker stopped renewing its lease
+    pub fn claimable_jobs(&self, limit: usize) -> CoreResult<Vec<Job>> {
+        self.store().get_claimable_jobs(&Utc::now(), limit)
+    }
+
+    /// Get the dependencies of a job whose steps have finished, possibly on another engine process
+    pub fn finished_dependencies(&self, job: &Job) -> CoreResult<Vec<String>> {
+        if job.dependencies.is_empty() {
+            return Ok(Vec::new());
+        }
+        let results = self.store().get_step_results(&job.run_id)?;
+        Ok(job.dependencies.iter()
+            .filter(|dependency| Job::parse_job_id(dependency).is_ok_and(|(_, _, step_id)| {
+                results.iter().any(|result| result.step_id == step_id && result.status.is_terminal())
+            }))
+            .cloned()
+            .collect())
     }
 
     /// Create a new workflow run
     pub fn create_run(&mut self, workflow_id: &str, payload: serde_json::Value) -> CoreResult<Uuid> {
+        self.create_run_by(workflow_id, payload, RunEvent::ACTOR_ENGINE)
+    }
+
+    /// Create a new workflow run, recording who created it
+    pub fn create_run_by(&mut self, workflow_id: &str, payload: serde_json::Value, actor: &str) -> CoreResult<Uuid> {
+        self.create_correlated_run(workflow_id, payload, actor, None)
+    }
+
+    /// Create several runs in one transaction of the local database, returning their IDs in order
+    ///
+    /// Either every run is created or, on the first error, none is.
+    pub fn create_runs_batch(&mut self, requests: Vec<RunRequest>, actor: &str) -> CoreResult<Vec<Uuid>> {
+        let mut run_ids = Vec::with_capacity(requests.len());
+        let created = self.in_transaction(|state_manager| {
+            for request in requests {
+                let run_id = state_manager.create_correlated_run(&request.workflow_id, request.payload, actor, request.correlation_id.as_deref())?;
+                run_ids.push(run_id);
+            }
+            Ok(())
+        });
+        if let Err(e) = created {
+            for run_id in &run_ids {
+                self.active_runs.remove(run_id);
+            }
+            return Err(e);
+        }
+        log::info!("Created batch of {} runs", run_ids.len());
+        Ok(run_ids)
+    }
+
+    /// Create a new workflow run, optionally addressable by an external correlation ID
+    ///
+    /// Correlation IDs are unique within a workflow; reusing one is rejected
+    /// with `CoreError::DuplicateCorrelationId`.
+    pub fn create_correlated_run(
+        &mut self,
+        workflow_id: &str,
+        payload: serde_json::Value,
+        actor: &str,
+        correlation_id: Option<&str>,
+    ) -> CoreResult<Uuid> {
         let _workflow = self.get_workflow(workflow_id)?
             .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?;
 
-        let run_id = Uuid::new_v4();
+        let run_id = self.run_ids.next_run_id(workflow_id);
         let now = Utc::now();
 
+        if let Some(correlation_id) = correlation_id {
+            if !self.db.save_run_correlation_id(workflow_id, correlation_id, &run_id.to_string())? {
+                let existing = self.db.get_run_id_by_correlation_id(workflow_id, correlation_id)?.unwrap_or_default();
+                return Err(CoreError::DuplicateCorrelationId(format!(
+                    "'{}' is already used by run {} of workflow {}", correlation_id, existing, workflow_id
+                )));
+            }
+        }
+
         let run = WorkflowRun {
             id: run_id,
             workflow_id: workflow_id.to_string(),
@@ -52,15 +648,94 @@ impl StateManager {
             started_at: now,
             completed_at: None,
             error: None,
+            version: 0,
         };
 
-        self.db.save_run(&run)?;
+        if let Err(e) = self.store().save_run(&run) {
+            if correlation_id.is_some() {
+                self.db.delete_run_correlation_id(&run_id.to_string())?;
+            }
+            return Err(e);
+        }
+        let version = self.db.get_latest_workflow_version(workflow_id)?.map(|v| v.version);
+        if let Some(version) = version {
+            self.db.save_run_workflow_version(&run_id.to_string(), workflow_id, version)?;
+        }
+        self.index_run_document(&run, run_search::PAYLOAD_SOURCE, &run.payload);
         self.active_runs.insert(run_id, run);
+        let mut detail = serde_json::json!({"workflow_id": workflow_id, "workflow_version": version});
+        if let Some(correlation_id) = correlation_id {
+            detail["correlation_id"] = serde_json::json!(correlation_id);
+        }
+        self.record_run_event(&run_id, RunEvent::RUN_CREATED, None, actor, detail)?;
 
         log::info!("Created workflow run: {} for workflow: {}", run_id, workflow_id);
         Ok(run_id)
     }
 
+    /// Create a child run for a sub-workflow step of a parent run
+    ///
+    /// Fails once the chain of parent runs reaches `RunParent::MAX_DEPTH`, which
+    /// stops workflows that indirectly start themselves.
+    pub fn create_child_run(&mut self, parent_run_id: &Uuid, parent_step_id: &str, workflow_id: &str, payload: serde_json::Value) -> CoreResult<Uuid> {
+        let mut depth = 1;
+        let mut ancestor = self.db.get_run_parent(&parent_run_id.to_string())?;
+        while let Some(parent) = ancestor {
+            depth += 1;
+            ancestor = self.db.get_run_parent(&parent.parent_run_id)?;
+        }
+        if depth >= RunParent::MAX_DEPTH {
+            return Err(CoreError::Validation(format!(
+                "Sub-workflow {} of step {} would nest runs more than {} deep", workflow_id, parent_step_id, RunParent::MAX_DEPTH
+            )));
+        }
+        
+        let run_id = self.create_correlated_run(workflow_id, payload, RunEvent::ACTOR_ENGINE, None)?;
+        self.db.save_run_parent(&RunParent {
+            run_id: run_id.to_string(),
+            parent_run_id: parent_run_id.to_string(),
+            parent_step_id: parent_step_id.to_string(),
+            created_at: Utc::now(),
+        })?;
+        self.record_run_trigger(&run_id, &RunTrigger::new(RunTrigger::SUBWORKFLOW, Some(&parent_run_id.to_string())))?;
+        self.record_run_event(
+            parent_run_id,
+            RunEvent::SUBWORKFLOW_STARTED,
+            Some(parent_step_id),
+            RunEvent::ACTOR_ENGINE,
+            serde_json::json!({"child_run_id": run_id.to_string(), "workflow_id": workflow_id}),
+        )?;
+        Ok(run_id)
+    }
+
+    /// Get the child run a sub-workflow step of a run started, if any
+    pub fn get_child_run(&self, parent_run_id: &Uuid, parent_step_id: &str) -> CoreResult<Option<Uuid>> {
+        self.db.get_child_runs(&parent_run_id.to_string())?
+            .into_iter()
+            .rfind(|child| child.parent_step_id == parent_step_id)
+            .map(|child| Uuid::parse_str(&child.run_id).map_err(CoreError::from))
+            .transpose()
+    }
+
+    /// Get the child runs started by a run's sub-workflow steps, oldest first
+    pub fn get_child_runs(&self, parent_run_id: &Uuid) -> CoreResult<Vec<RunParent>> {
+        self.db.get_child_runs(&parent_run_id.to_string())
+    }
+
+    /// Get the parent run and step of a child run
+    pub fn get_run_parent(&self, run_id: &Uuid) -> CoreResult<Option<RunParent>> {
+        self.db.get_run_parent(&run_id.to_string())
+    }
+
+    /// Get the final output of a run, the output of its last completed step
+    pub fn get_run_output(&self, run_id: &Uuid) -> CoreResult<Option<serde_json::Value>> {
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let workflow = self.get_run_workflow(&run.workflow_id, run_id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+        Ok(workflow.final_output(&self.get_completed_steps_with_outputs(run_id)?))
+    }
+
     /// Get a workflow run by ID
     pub fn get_run(&self, run_id: &Uuid) -> CoreResult<Option<WorkflowRun>> {
         // First check active runs
@@ -69,98 +744,1586 @@ impl StateManager {
         }
 
         // Load from database
-        self.db.get_run(&run_id.to_string())
+        self.store().get_run(&run_id.to_string())
     }
 
-    /// Update run status
-    pub fn update_run_status(&mut self, run_id: &Uuid, status: RunStatus) -> CoreResult<()> {
-        if let Some(run) = self.active_runs.get_mut(run_id) {
-            run.status = status.clone();
-            
-            if matches!(status, RunStatus::Completed | RunStatus::Failed) {
-                run.completed_at = Some(Utc::now());
-            }
+    /// Get a workflow's run by the correlation ID it was created with
+    pub fn get_run_by_correlation_id(&self, workflow_id: &str, correlation_id: &str) -> CoreResult<Option<WorkflowRun>> {
+        match self.db.get_run_id_by_correlation_id(workflow_id, correlation_id)? {
+            Some(run_id) => self.get_run(&Uuid::parse_str(&run_id)?),
+            None => Ok(None),
+        }
+    }
 
-            self.db.save_run(run)?;
-            log::info!("Updated run {} status to {:?}", run_id, status);
+    /// Get the workflow version a run started on
+    pub fn get_run_workflow_version(&self, run_id: &Uuid) -> CoreResult<Option<u32>> {
+        self.db.get_run_workflow_version(&run_id.to_string())
+    }
+
+    /// Get the definition a run executes with
+    ///
+    /// This is the workflow version the run started on, or the current
+    /// definition for runs started before the workflow was versioned.
+    pub fn get_run_workflow(&self, workflow_id: &str, run_id: &Uuid) -> CoreResult<Option<WorkflowDefinition>> {
+        match self.db.get_run_workflow_version(&run_id.to_string())? {
+            Some(version) => Ok(self.db.get_workflow_version(workflow_id, version)?.map(|v| v.definition)),
+            None => self.get_workflow(workflow_id),
         }
+    }
 
-        Ok(())
+    /// Get the correlation ID a run was created with
+    pub fn get_run_correlation_id(&self, run_id: &Uuid) -> CoreResult<Option<String>> {
+        self.db.get_run_correlation_id(&run_id.to_string())
     }
 
-    /// Save step result
-    pub fn save_step_result(&self, run_id: &Uuid, result: StepResult) -> CoreResult<()> {
-        self.db.save_step_result(&result, &run_id.to_string())
+    /// List persisted runs matching a query
+    pub fn list_runs(&self, query: &RunQuery) -> CoreResult<RunPage> {
+        self.store().list_runs(query)
     }
 
-    /// Get all active runs
-    pub fn get_active_runs(&self) -> Vec<WorkflowRun> {
-        self.active_runs.values().cloned().collect()
+    /// Pin a run so it is exempt from retention and archival cleanup
+    pub fn pin_run(&self, run_id: &Uuid, reason: &str, pinned_by: Option<&str>) -> CoreResult<RunPin> {
+        if reason.trim().is_empty() {
+            return Err(CoreError::Validation("Pin reason cannot be empty".to_string()));
+        }
+        if self.get_run(run_id)?.is_none() {
+            return Err(CoreError::RunNotFound(run_id.to_string()));
+        }
+        
+        let pin = self.db.pin_run(&run_id.to_string(), reason, pinned_by)?;
+        log::info!("Pinned run {} by {:?}: {}", run_id, pinned_by, reason);
+        Ok(pin)
     }
 
-    /// Clean up completed runs
-    pub fn cleanup_completed_runs(&mut self) -> CoreResult<()> {
-        let completed_runs: Vec<Uuid> = self.active_runs
-            .iter()
-            .filter(|(_, run)| matches!(run.status, RunStatus::Completed | RunStatus::Failed))
-            .map(|(id, _)| *id)
-            .collect();
+    /// Unpin a run, returning whether it was pinned
+    pub fn unpin_run(&self, run_id: &Uuid, unpinned_by: Option<&str>) -> CoreResult<bool> {
+        let removed = self.db.unpin_run(&run_id.to_string(), unpinned_by)?;
+        if removed {
+            log::info!("Unpinned run {} by {:?}", run_id, unpinned_by);
+        }
+        Ok(removed)
+    }
 
-        let count = completed_runs.len();
-        for run_id in completed_runs {
-            self.active_runs.remove(&run_id);
+    /// Get the pin audit trail of a run
+    pub fn get_run_pin_audit(&self, run_id: &Uuid) -> CoreResult<Vec<RunPinAuditEntry>> {
+        self.db.get_run_pin_audit(&run_id.to_string())
+    }
+
+    /// Label a run, replacing the values of labels it already has, and return all its labels
+    pub fn set_run_labels(&self, run_id: &Uuid, labels: &RunLabels) -> CoreResult<RunLabels> {
+        if self.get_run(run_id)?.is_none() {
+            return Err(CoreError::RunNotFound(run_id.to_string()));
         }
+        let mut merged = self.db.get_run_labels(&run_id.to_string())?;
+        merged.extend(labels.clone());
+        validate_run_labels(&merged).map_err(CoreError::Validation)?;
+        
+        self.db.set_run_labels(&run_id.to_string(), labels)?;
+        log::info!("Labelled run {} with {:?}", run_id, labels.keys().collect::<Vec<_>>());
+        Ok(merged)
+    }
 
-        log::info!("Cleaned up {} completed runs", count);
+    /// Remove a label of a run, returning whether the run had it
+    pub fn remove_run_label(&self, run_id: &Uuid, key: &str) -> CoreResult<bool> {
+        self.db.remove_run_label(&run_id.to_string(), key)
+    }
+
+    /// Get the labels of a run
+    pub fn get_run_labels(&self, run_id: &Uuid) -> CoreResult<RunLabels> {
+        self.db.get_run_labels(&run_id.to_string())
+    }
+
+    /// List runs with a label set to a value, newest first
+    pub fn list_runs_by_label(&self, key: &str, value: &str, limit: Option<usize>, offset: Option<usize>) -> CoreResult<RunPage> {
+        self.list_runs(&RunQuery {
+            labels: RunLabels::from([(key.to_string(), value.to_string())]),
+            limit,
+            offset,
+            ..RunQuery::default()
+        })
+    }
+
+    /// Record a trace event for a step of a run
+    pub fn record_step_trace_event(&self, run_id: &Uuid, step_id: &str, event: &str, detail: serde_json::Value) -> CoreResult<()> {
+        self.db.save_step_trace_event(&StepTraceEvent {
+            run_id: run_id.to_string(),
+            step_id: step_id.to_string(),
+            event: event.to_string(),
+            detail,
+            created_at: Utc::now(),
+        })
+    }
+
+    /// Explain why each step of a run ran, was skipped, retried or waited
+    pub fn explain_run(&self, run_id: &Uuid) -> CoreResult<RunExplanation> {
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+        let results = self.get_completed_steps(run_id)?;
+        let events = self.db.get_step_trace_events(&run_id.to_string())?;
+        
+        Ok(crate::explainer::explain_run(&workflow, &run, &results, &events))
+    }
+
+    /// Append an event to a run's event log
+    ///
+    /// Secret values in the detail are masked.
+    pub fn record_run_event(&self, run_id: &Uuid, event_type: &str, step_id: Option<&str>, actor: &str, mut detail: serde_json::Value) -> CoreResult<()> {
+        secrets::mask_value(&mut detail);
+        let event = RunEvent {
+            run_id: run_id.to_string(),
+            event_type: event_type.to_string(),
+            step_id: step_id.map(str::to_string),
+            actor: actor.to_string(),
+            detail,
+            created_at: Utc::now(),
+        };
+        self.db.save_run_event(&event)?;
+        run_stream::publish(&event);
         Ok(())
     }
 
-    /// Get completed steps for a run
-    pub fn get_completed_steps(&self, run_id: &Uuid) -> CoreResult<Vec<StepResult>> {
-        self.db.get_step_results(&run_id.to_string())
+    /// Get the ordered event log of a run
+    pub fn get_run_timeline(&self, run_id: &Uuid) -> CoreResult<Vec<RunEvent>> {
+        if self.get_run(run_id)?.is_none() {
+            return Err(CoreError::RunNotFound(run_id.to_string()));
+        }
+        self.db.get_run_events(&run_id.to_string())
+    }
+
+    /// Append a structured log line for a step of a run
+    ///
+    /// Secret values in the message and fields are masked.
+    pub fn append_step_log(&self, run_id: &Uuid, step_id: &str, level: StepLogLevel, message: &str, mut fields: serde_json::Value) -> CoreResult<StepLogEntry> {
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+        if workflow.get_step(step_id).is_none() {
+            return Err(CoreError::StepNotFound(step_id.to_string()));
+        }
+        secrets::mask_value(&mut fields);
+        
+        let mut entry = StepLogEntry {
+            seq: 0,
+            run_id: run_id.to_string(),
+            step_id: step_id.to_string(),
+            level,
+            message: secrets::mask(message).into_owned(),
+            fields,
+            created_at: Utc::now(),
+        };
+        entry.seq = self.db.save_step_log(&entry)?;
+        Ok(entry)
+    }
+
+    /// Get the log lines of a step appended after a sequence number, a page at a time
+    ///
+    /// Pass the `seq` of the last line read to follow the log while the step runs.
+    pub fn get_step_logs(&self, run_id: &Uuid, step_id: &str, after_seq: i64) -> CoreResult<Vec<StepLogEntry>> {
+        if self.get_run(run_id)?.is_none() {
+            return Err(CoreError::RunNotFound(run_id.to_string()));
+        }
+        self.db.get_step_logs(&run_id.to_string(), step_id, after_seq, StepLogEntry::PAGE_SIZE)
+    }
+
+    /// Get the SQLite settings in effect on the node's database
+    pub fn database_settings(&self) -> CoreResult<crate::models::SqliteSettings> {
+        self.db.sqlite_settings()
+    }
+
+    /// Add firing counts to the persisted counts of their triggers
+    pub fn add_trigger_counters(&self, records: &[crate::models::TriggerCounterRecord]) -> CoreResult<()> {
+        self.db.add_trigger_counters(records)
+    }
+
+    /// Get the persisted firing counts of every trigger
+    pub fn get_trigger_counters(&self) -> CoreResult<Vec<crate::models::TriggerCounterRecord>> {
+        self.db.get_trigger_counters()
+    }
+
+    /// Back up workflows, triggers, runs, jobs and state entries to a new SQLite file
+    ///
+    /// Only the local database can be snapshotted; with a shared or isolated
+    /// store part of the state lives elsewhere and is refused.
+    pub fn create_snapshot(&self, path: &str) -> CoreResult<crate::models::DatabaseSnapshot> {
+        if self.storage.is_some() {
+            return Err(CoreError::Configuration(
+                "Snapshots cover the local database only, not shared or isolated stores".to_string(),
+            ));
+        }
+        let snapshot = self.db.create_snapshot(path)?;
+        log::info!("Wrote database snapshot {} ({} pages)", path, snapshot.pages);
+        Ok(snapshot)
+    }
+
+    /// Replace the local database with a snapshot taken by `create_snapshot`
+    ///
+    /// The engine must be paused, and stays paused afterwards whatever the
+    /// snapshot recorded, so runs resume only once the caller resumes the engine.
+    pub fn restore_snapshot(&mut self, path: &str) -> CoreResult<crate::models::DatabaseSnapshot> {
+        if self.storage.is_some() {
+            return Err(CoreError::Configuration(
+                "Snapshots cover the local database only, not shared or isolated stores".to_string(),
+            ));
+        }
+        if !self.db.is_engine_paused()? {
+            return Err(CoreError::InvalidState("Pause the engine before restoring a snapshot".to_string()));
+        }
+        let snapshot = self.db.restore_snapshot(path)?;
+        self.db.set_engine_paused(true)?;
+        self.active_runs.clear();
+        log::warn!("Restored database from snapshot {} ({} pages)", path, snapshot.pages);
+        Ok(snapshot)
+    }
+
+    /// Get the schema version of the local database and the migrations applied to it
+    pub fn get_schema_version(&self) -> CoreResult<crate::models::SchemaVersion> {
+        self.db.get_schema_version()
+    }
+
+    /// Plan which steps of a run may be re-executed after a crash or during replay
+    pub fn plan_run_recovery(&self, run_id: &Uuid, replay: bool, policy: NonIdempotentRecovery) -> CoreResult<RunRecoveryPlan> {
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+        let results = self.get_completed_steps(run_id)?;
+        let events = self.db.get_run_events(&run_id.to_string())?;
+
+        Ok(crate::recovery::plan_recovery(&workflow, &run, &results, &events, policy, replay))
+    }
+
+    /// Confirm that a non-idempotent step of a run may be re-executed by recovery
+    pub fn confirm_step_recovery(&self, run_id: &Uuid, step_id: &str, actor: &str) -> CoreResult<()> {
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+        if workflow.get_step(step_id).is_none() {
+            return Err(CoreError::StepNotFound(step_id.to_string()));
+        }
+        self.record_run_event(run_id, RunEvent::RECOVERY_CONFIRMED, Some(step_id), actor, serde_json::json!({}))
+    }
+
+    /// Whether workflows, runs and steps live in a store shared with other engine instances
+    pub fn has_shared_store(&self) -> bool {
+        self.storage.is_some()
+    }
+
+    /// Take over runs left running by an engine that stopped without finishing them
+    ///
+    /// A running run is orphaned once nothing was recorded for it within `lease`.
+    /// Under the requeue policy the run's interrupted steps are planned like crash
+    /// recovery: steps the plan skips get a skipped result, and the run is reported
+    /// as requeued for the caller to resume, or as awaiting confirmation. Under the
+    /// fail policy its interrupted steps and the run itself are marked failed.
+    pub fn recover_orphaned_runs(
+        &mut self,
+        policy: OrphanRunPolicy,
+        non_idempotent: NonIdempotentRecovery,
+        lease: chrono::Duration,
+        now: chrono::DateTime<Utc>,
+    ) -> CoreResult<OrphanRecoveryReport> {
+        let mut running = Vec::new();
+        let mut query = RunQuery {
+            workflow_id: None,
+            status: Some(RunStatus::Running),
+            since: None,
+            pinned: None,
+            labels: RunLabels::new(),
+            limit: Some(RunQuery::MAX_LIMIT),
+            offset: Some(0),
+        };
+        loop {
+            let page = self.store().list_runs(&query)?;
+            let fetched = page.runs.len();
+            running.extend(page.runs.into_iter().map(|entry| entry.run));
+            if fetched == 0 || running.len() >= page.total {
+                break;
+            }
+            query.offset = Some(running.len());
+        }
+        
+        let mut report = OrphanRecoveryReport {
+            recovered_at: now,
+            policy,
+            lease_secs: lease.num_seconds().max(0) as u64,
+            runs: Vec::new(),
+        };
+        for run in running {
+            let run_key = run.id.to_string();
+            let results = self.store().get_step_results(&run_key)?;
+            let events = self.db.get_run_events(&run_key)?;
+            let last_activity_at = crate::recovery::last_activity(&run, &results, &events);
+            if now - last_activity_at < lease {
+                continue;
+            }
+            
+            let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?;
+            let plan = workflow.as_ref()
+                .map(|workflow| crate::recovery::plan_recovery(workflow, &run, &results, &events, non_idempotent, false));
+            let outcome = match &plan {
+                Some(plan) => crate::recovery::orphan_outcome(plan, policy),
+                None => OrphanRunOutcome::Failed,
+            };
+            
+            let interrupted_steps = plan.iter().flat_map(|plan| plan.steps.iter());
+            for step in interrupted_steps {
+                let (status, error) = match outcome {
+                    OrphanRunOutcome::Failed => (StepStatus::Failed, "Interrupted when the engine stopped".to_string()),
+                    _ if step.action == RecoveryAction::Skip => (StepStatus::Skipped, format!("Skipped by recovery: {}", step.reason)),
+                    _ => continue,
+                };
+                self.store().save_step_result(&StepResult {
+                    step_id: step.step_id.clone(),
+                    status,
+                    output: None,
+                    error: Some(error),
+                    started_at: now,
+                    completed_at: Some(now),
+                    duration_ms: Some(0),
+                    cache_hit: None,
+                    resources: None,
+                }, &run_key)?;
+            }
+            
+            let detail = serde_json::json!({"outcome": outcome, "last_activity_at": last_activity_at});
+            self.record_run_event(&run.id, RunEvent::RUN_RECOVERED, None, RunEvent::ACTOR_ENGINE, detail)?;
+            if outcome == OrphanRunOutcome::Failed {
+                let error = match &workflow {
+                    Some(_) => "Run was interrupted when the engine stopped".to_string(),
+                    None => format!("Run was interrupted and workflow {} no longer exists", run.workflow_id),
+                };
+                let failed = self.write_run(&run.id, |run| {
+                    run.status = RunStatus::Failed;
+                    run.completed_at = Some(now);
+                    run.error = Some(error.clone());
+                    true
+                })?;
+                self.active_runs.remove(&run.id);
+                if failed.is_some() {
+                    self.record_run_event(&run.id, RunEvent::for_run_status(&RunStatus::Failed), None, RunEvent::ACTOR_ENGINE, serde_json::json!({"error": error}))?;
+                }
+            }
+            
+            log::warn!("Recovered orphaned run {} of workflow {}: {:?}", run.id, run.workflow_id, outcome);
+            report.runs.push(OrphanedRun {
+                run_id: run_key,
+                workflow_id: run.workflow_id.clone(),
+                last_activity_at,
+                outcome,
+                plan,
+            });
+        }
+        
+        Ok(report)
+    }
+
+    /// Persist the diagnostics of a dispatcher worker
+    pub fn save_worker_diagnostics(&self, diagnostics: &WorkerDiagnostics) -> CoreResult<()> {
+        self.db.save_worker_diagnostics(diagnostics)
+    }
+
+    /// Get the persisted diagnostics of every dispatcher worker
+    pub fn get_worker_diagnostics(&self) -> CoreResult<Vec<WorkerDiagnostics>> {
+        self.db.get_worker_diagnostics()
+    }
+
+    /// Get a value from the context state of a run or workflow
+    pub fn state_get(&self, scope: StateScope, scope_id: &str, key: &str) -> CoreResult<Option<serde_json::Value>> {
+        Ok(self.db.get_state_entries(scope, scope_id, Some(key), Utc::now())?
+            .into_iter()
+            .next()
+            .map(|entry| entry.value))
+    }
+
+    /// Set a value in the context state of a run or workflow, optionally expiring after `ttl_secs`
+    pub fn state_set(&self, scope: StateScope, scope_id: &str, key: &str, value: serde_json::Value, ttl_secs: Option<u64>) -> CoreResult<()> {
+        let now = Utc::now();
+        self.db.delete_expired_state_entries(now)?;
+        self.db.save_state_entry(&StateEntry {
+            scope,
+            scope_id: scope_id.to_string(),
+            key: key.to_string(),
+            value,
+            expires_at: ttl_secs.map(|ttl| now + chrono::Duration::seconds(ttl as i64)),
+            updated_at: now,
+        })
+    }
+
+    /// Increment an integer in the context state and return the new value
+    ///
+    /// Missing keys start at zero. Without `ttl_secs` an existing expiry is kept.
+    pub fn state_incr(&self, scope: StateScope, scope_id: &str, key: &str, by: i64, ttl_secs: Option<u64>) -> CoreResult<i64> {
+        let now = Utc::now();
+        let current = self.db.get_state_entries(scope, scope_id, Some(key), now)?.into_iter().next();
+        let current_value = match &current {
+            Some(entry) => entry.value.as_i64().ok_or_else(|| CoreError::Validation(format!(
+                "Cannot increment non-integer state value for key '{}'", key
+            )))?,
+            None => 0,
+        };
+        let value = current_value.checked_add(by)
+            .ok_or_else(|| CoreError::Validation(format!("Incrementing state key '{}' overflows", key)))?;
+        
+        self.db.save_state_entry(&StateEntry {
+            scope,
+            scope_id: scope_id.to_string(),
+            key: key.to_string(),
+            value: serde_json::json!(value),
+            expires_at: match ttl_secs {
+                Some(ttl) => Some(now + chrono::Duration::seconds(ttl as i64)),
+                None => current.and_then(|entry| entry.expires_at),
+            },
+            updated_at: now,
+        })?;
+        Ok(value)
+    }
+
+    /// Snapshot the context state visible to the steps of a run
+    pub fn context_state(&self, run_id: &str, workflow_id: &str) -> CoreResult<ContextState> {
+        let now = Utc::now();
+        let values = |entries: Vec<StateEntry>| entries.into_iter().map(|entry| (entry.key, entry.value)).collect();
+        Ok(ContextState {
+            run: values(self.db.get_state_entries(StateScope::Run, run_id, None, now)?),
+            workflow: values(self.db.get_state_entries(StateScope::Workflow, workflow_id, None, now)?),
+        })
+    }
+
+    /// Store a secret, encrypted, for one workflow or globally
+    pub fn set_secret(&self, key: &str, value: &str, scope: Option<&str>) -> CoreResult<StoredSecret> {
+        let cipher = self.secret_cipher.as_ref()
+            .ok_or_else(|| CoreError::Configuration("Set CRONFLOW_SECRETS_KEY to store secrets".to_string()))?;
+        if key.trim().is_empty() {
+            return Err(CoreError::Validation("Secret key cannot be empty".to_string()));
+        }
+        let scope = scope.unwrap_or(secrets::GLOBAL_SCOPE);
+        
+        let secret = cipher.encrypt(scope, key, value)?;
+        self.db.save_secret(&secret)?;
+        secrets::register_masked_value(value);
+        log::info!("Stored secret {} for scope {}", key, scope);
+        Ok(secret)
+    }
+
+    /// Delete a secret, returning whether it existed
+    pub fn delete_secret(&self, key: &str, scope: Option<&str>) -> CoreResult<bool> {
+        self.db.delete_secret(scope.unwrap_or(secrets::GLOBAL_SCOPE), key)
+    }
+
+    /// Decrypt the secrets the steps of a workflow receive in their context
+    pub fn workflow_secrets(&self, workflow_id: &str) -> CoreResult<HashMap<String, String>> {
+        let Some(cipher) = &self.secret_cipher else {
+            return Ok(HashMap::new());
+        };
+        let stored = self.db.get_secrets(Some(&[secrets::GLOBAL_SCOPE, workflow_id]))?;
+        secrets::decrypt_for_workflow(cipher, &stored, workflow_id)
+    }
+
+    /// Store the result of an ad hoc step execution, dropping expired ones
+    pub fn save_adhoc_step_execution(&self, execution: &AdhocStepExecution) -> CoreResult<()> {
+        let expired = self.db.delete_expired_adhoc_step_executions(Utc::now())?;
+        if expired > 0 {
+            log::debug!("Removed {} expired ad hoc step executions", expired);
+        }
+        self.db.save_adhoc_step_execution(execution)
+    }
+
+    /// Get the unexpired ad hoc step executions of a workflow, newest first
+    pub fn get_adhoc_step_executions(&self, workflow_id: &str) -> CoreResult<Vec<AdhocStepExecution>> {
+        self.db.get_adhoc_step_executions(workflow_id, Utc::now())
+    }
+
+    /// Get the unexpired cached output of a step for a cache key
+    pub fn get_cached_step_output(&self, workflow_id: &str, step_id: &str, key: &str) -> CoreResult<Option<CachedStepOutput>> {
+        self.db.get_cached_step_output(workflow_id, step_id, key, Utc::now())
+    }
+
+    /// Cache the output of a step, dropping expired entries
+    pub fn cache_step_output(&self, entry: &CachedStepOutput) -> CoreResult<()> {
+        let expired = self.db.delete_expired_step_cache(Utc::now())?;
+        if expired > 0 {
+            log::debug!("Removed {} expired step cache entries", expired);
+        }
+        self.db.save_cached_step_output(entry)
+    }
+
+    /// Record what started a run
+    pub fn record_run_trigger(&self, run_id: &Uuid, trigger: &RunTrigger) -> CoreResult<()> {
+        self.db.save_run_trigger(&run_id.to_string(), trigger)
+    }
+
+    /// Find the run a trigger firing duplicates, recording the firing as suppressed
+    ///
+    /// A firing duplicates the run of its workflow last started with the same
+    /// dedup key, if that run was created within the dedup window.
+    pub fn find_duplicate_run(&self, workflow_id: &str, dedup: &TriggerDedup, payload: &serde_json::Value, trigger: &RunTrigger) -> CoreResult<Option<SuppressedTrigger>> {
+        let Some(key) = dedup.key_for(payload) else {
+            return Ok(None);
+        };
+        let since = trigger.fired_at - chrono::Duration::seconds(dedup.window_secs as i64);
+        let Some(run_id) = self.db.get_trigger_dedup_run(workflow_id, &key, &since)? else {
+            return Ok(None);
+        };
+        let run_id = Uuid::parse_str(&run_id)?;
+        if self.get_run(&run_id)?.is_none() {
+            return Ok(None);
+        }
+        
+        let suppressed = SuppressedTrigger {
+            workflow_id: workflow_id.to_string(),
+            trigger_type: trigger.trigger_type.clone(),
+            source: trigger.source.clone(),
+            dedup_key: key,
+            run_id,
+            suppressed_at: trigger.fired_at,
+        };
+        self.db.save_suppressed_trigger(&suppressed)?;
+        log::info!("Suppressed {} trigger of workflow {}: duplicates run {} (dedup key {})", suppressed.trigger_type, workflow_id, run_id, suppressed.dedup_key);
+        Ok(Some(suppressed))
+    }
+
+    /// Record the dedup key of a run started by a deduplicating trigger, opening its dedup window
+    pub fn record_dedup_key(&self, workflow_id: &str, dedup: &TriggerDedup, payload: &serde_json::Value, run_id: &Uuid, trigger: &RunTrigger) -> CoreResult<()> {
+        match dedup.key_for(payload) {
+            Some(key) => self.db.save_trigger_dedup_key(workflow_id, &key, &run_id.to_string(), &trigger.fired_at),
+            None => Ok(()),
+        }
+    }
+
+    /// Get the latest trigger firings of a workflow suppressed as duplicates, newest first
+    pub fn get_suppressed_triggers(&self, workflow_id: &str, limit: usize) -> CoreResult<Vec<SuppressedTrigger>> {
+        self.db.get_suppressed_triggers(workflow_id, limit)
+    }
+
+    /// Queue a run behind the unfinished runs of its workflow sharing its serialization key
+    ///
+    /// Returns the key's queue, oldest run first, after dropping the runs that
+    /// have finished since they were queued.
+    pub fn serialize_run(&self, workflow_id: &str, key: &str, run_id: &Uuid) -> CoreResult<Vec<String>> {
+        self.db.queue_serialized_run(workflow_id, key, &run_id.to_string())?;
+        let mut queue = Vec::new();
+        for queued in self.db.get_serialized_runs(workflow_id, key)? {
+            let unfinished = match Uuid::parse_str(&queued) {
+                Ok(queued_id) => self.get_run(&queued_id)?.is_some_and(|run| !run.status.is_terminal()),
+                Err(_) => false,
+            };
+            if unfinished {
+                queue.push(queued);
+            } else {
+                self.db.dequeue_serialized_run(&queued)?;
+            }
+        }
+        Ok(queue)
+    }
+
+    /// Get the trace a run's spans are exported in, None while tracing is off
+    pub fn run_trace(&self, run_id: &Uuid) -> Option<RunTrace> {
+        if !telemetry::is_enabled() {
+            return None;
+        }
+        let trigger = self.db.get_run_trigger(&run_id.to_string()).unwrap_or_else(|e| {
+            log::warn!("Failed to get the trace context of run {}: {}", run_id, e);
+            None
+        });
+        Some(RunTrace::new(*run_id, trigger.as_ref().and_then(|trigger| trigger.trace_parent.as_deref())))
+    }
+
+    /// Export the span of a run that just finished
+    fn trace_run_end(&self, run: &WorkflowRun) {
+        if let Some(trace) = self.run_trace(&run.id) {
+            telemetry::export_run(&trace, run);
+        }
+    }
+
+    /// Export the span of the time a step's job waited in the queue for a worker
+    pub fn trace_queue_wait(&self, run_id: &Uuid, step_id: &str, queued_at: DateTime<Utc>, dispatched_at: DateTime<Utc>) {
+        if let Some(trace) = self.run_trace(run_id) {
+            telemetry::export_queue_wait(&trace, step_id, queued_at, dispatched_at);
+        }
+    }
+
+    /// Build the execution details of a step's context from its run's trigger and history
+    pub fn execution_info(&self, run_id: &Uuid, step_id: &str, results: &[StepResult], queued_at: Option<DateTime<Utc>>) -> CoreResult<ExecutionInfo> {
+        let run_id = run_id.to_string();
+        let trigger = self.db.get_run_trigger(&run_id)?;
+        let events = self.db.get_run_events(&run_id)?;
+        Ok(ExecutionInfo::for_step(step_id, trigger, &events, results, queued_at))
+    }
+
+    /// Remember the checksum of a context issued to Bun.js so the step result can be matched to it
+    pub fn record_issued_context(&self, context: &Context) -> CoreResult<String> {
+        let checksum = context.generate_checksum();
+        self.db.save_step_context_checksum(&StepContextChecksum {
+            run_id: context.run_id.clone(),
+            step_id: context.step_name.clone(),
+            checksum: checksum.clone(),
+            schema_version: context.metadata.schema_version,
+            attempt: context.metadata.retry_count,
+            issued_at: Utc::now(),
+        })?;
+        Ok(checksum)
+    }
+
+    /// Check that a step result was produced from the latest context issued for the step
+    ///
+    /// Results for steps that never had a context issued are accepted as before.
+    pub fn verify_step_context(&self, completion: &StepCompletion) -> CoreResult<()> {
+        let step_id = &completion.result.step_id;
+        let Some(issued) = self.db.get_step_context_checksum(&completion.run_id, step_id)? else {
+            return Ok(());
+        };
+        
+        let checksum = completion.context_checksum.as_deref().ok_or_else(|| CoreError::ContextMismatch(format!(
+            "Result for step {} of run {} does not echo its context checksum", step_id, completion.run_id
+        )))?;
+        if let Some(schema_version) = completion.context_schema_version {
+            if schema_version != issued.schema_version {
+                return Err(CoreError::ContextMismatch(format!(
+                    "Result for step {} uses context schema version {}, expected {}", step_id, schema_version, issued.schema_version
+                )));
+            }
+        }
+        if checksum != issued.checksum {
+            return Err(CoreError::ContextMismatch(format!(
+                "Result for step {} of run {} was produced from a stale context; attempt {} is current",
+                step_id, completion.run_id, issued.attempt
+            )));
+        }
+        Ok(())
+    }
+
+    /// Persist whether the engine is globally paused
+    pub fn set_engine_paused(&self, paused: bool) -> CoreResult<()> {
+        self.db.set_engine_paused(paused)
+    }
+
+    /// Check whether the engine is globally paused
+    pub fn is_engine_paused(&self) -> CoreResult<bool> {
+        self.db.is_engine_paused()
+    }
+
+    /// Buffer a run created while the engine is paused
+    pub fn buffer_run(&self, run_id: &Uuid, workflow_id: &str) -> CoreResult<()> {
+        self.db.buffer_run(&run_id.to_string(), workflow_id)?;
+        self.record_run_event(run_id, RunEvent::RUN_PAUSED, None, RunEvent::ACTOR_ENGINE, serde_json::json!({"reason": "engine paused"}))
+    }
+
+    /// Get buffered runs in the order they were buffered
+    pub fn get_buffered_runs(&self) -> CoreResult<Vec<Uuid>> {
+        self.db.get_buffered_runs()?
+            .iter()
+            .map(|run_id| Uuid::parse_str(run_id).map_err(CoreError::from))
+            .collect()
+    }
+
+    /// Remove a released run from the buffer
+    pub fn remove_buffered_run(&self, run_id: &Uuid) -> CoreResult<()> {
+        self.db.remove_buffered_run(&run_id.to_string())?;
+        self.record_run_event(run_id, RunEvent::RUN_RESUMED, None, RunEvent::ACTOR_ENGINE, serde_json::json!({}))
+    }
+
+    /// Get the IDs of a workflow's pending and running runs, oldest first
+    pub fn get_unfinished_runs(&self, workflow_id: &str) -> CoreResult<Vec<Uuid>> {
+        self.db.get_unfinished_runs(workflow_id)?
+            .iter()
+            .map(|run_id| Uuid::parse_str(run_id).map_err(CoreError::from))
+            .collect()
+    }
+
+    /// Mark an unfinished run as cancelled
+    ///
+    /// Unfinished child runs of its sub-workflow steps are cancelled with it.
+    /// Returns false if the run had already finished.
+    pub fn cancel_run(&mut self, run_id: &Uuid, reason: &str) -> CoreResult<bool> {
+        self.end_unfinished_run(run_id, RunStatus::Cancelled, reason)
+    }
+
+    /// End an unfinished run with a terminal status, cancelling its unfinished child runs
+    fn end_unfinished_run(&mut self, run_id: &Uuid, status: RunStatus, reason: &str) -> CoreResult<bool> {
+        if self.get_run(run_id)?.is_none() {
+            return Err(CoreError::RunNotFound(run_id.to_string()));
+        }
+        
+        let cancelled = status == RunStatus::Cancelled;
+        let ended = self.write_run(run_id, |run| {
+            if run.status.is_terminal() {
+                return false;
+            }
+            run.status = status.clone();
+            run.completed_at = Some(Utc::now());
+            run.error = Some(reason.to_string());
+            true
+        })?;
+        let Some(run) = ended else {
+            return Ok(false);
+        };
+        self.db.remove_buffered_run(&run_id.to_string())?;
+        self.active_runs.remove(run_id);
+        self.trace_run_end(&run);
+        let detail = if cancelled { serde_json::json!({"reason": reason}) } else { serde_json::json!({"error": reason}) };
+        self.record_run_event(run_id, RunEvent::for_run_status(&status), None, RunEvent::ACTOR_ENGINE, detail)?;
+        
+        for child in self.db.get_child_runs(&run_id.to_string())? {
+            let child_id = Uuid::parse_str(&child.run_id)?;
+            let parent_outcome = if cancelled { "cancelled" } else { "failed" };
+            self.cancel_run(&child_id, &format!("Parent run {} was {}", run_id, parent_outcome))?;
+        }
+        
+        log::info!("Ended run {} as {:?}: {}", run_id, status, reason);
+        Ok(true)
+    }
+
+    /// Find unfinished runs that have been going for longer than their workflow's run timeout
+    ///
+    /// Only workflows whose current definition sets a run timeout are checked;
+    /// each run is held to the definition version it started on.
+    pub fn timed_out_runs(&self, now: chrono::DateTime<Utc>) -> CoreResult<Vec<(WorkflowRun, WorkflowDefinition)>> {
+        let mut timed_out = Vec::new();
+        for workflow in self.store().get_all_workflows()? {
+            if workflow.run_timeout_ms.is_none() {
+                continue;
+            }
+            for status in [RunStatus::Pending, RunStatus::Running] {
+                for run in self.all_runs_with_status(&workflow.id, status, None)? {
+                    let Some(definition) = self.get_run_workflow(&run.workflow_id, &run.id)? else {
+                        continue;
+                    };
+                    let exceeded = definition.run_timeout_ms
+                        .is_some_and(|timeout_ms| (now - run.started_at).num_milliseconds() > timeout_ms as i64);
+                    if exceeded {
+                        timed_out.push((run, definition));
+                    }
+                }
+            }
+        }
+        Ok(timed_out)
+    }
+
+    /// Get every run of a workflow with a status, started at or after `since`, newest first
+    fn all_runs_with_status(&self, workflow_id: &str, status: RunStatus, since: Option<chrono::DateTime<Utc>>) -> CoreResult<Vec<WorkflowRun>> {
+        let mut query = RunQuery {
+            workflow_id: Some(workflow_id.to_string()),
+            status: Some(status),
+            since,
+            pinned: None,
+            labels: RunLabels::new(),
+            limit: Some(RunQuery::MAX_LIMIT),
+            offset: Some(0),
+        };
+        let mut runs = Vec::new();
+        loop {
+            let page = self.store().list_runs(&query)?;
+            let fetched = page.runs.len();
+            runs.extend(page.runs.into_iter().map(|entry| entry.run));
+            if fetched == 0 || runs.len() >= page.total {
+                break;
+            }
+            query.offset = Some(runs.len());
+        }
+        Ok(runs)
+    }
+
+    /// Cancel the unfinished runs of a workflow in one transaction
+    ///
+    /// Only runs with `status`, when given, and started before `before`, when
+    /// given, are cancelled. Their unfinished child runs are cancelled with them.
+    pub fn cancel_runs(&mut self, workflow_id: &str, status: Option<RunStatus>, before: Option<chrono::DateTime<Utc>>, reason: &str) -> CoreResult<BulkRunSummary> {
+        let statuses = match status {
+            Some(status) if status.is_terminal() => {
+                return Err(CoreError::Validation(format!("Runs that are {} cannot be cancelled", status.as_str())));
+            }
+            Some(status) => vec![status],
+            None => vec![RunStatus::Pending, RunStatus::Running],
+        };
+        let mut runs = Vec::new();
+        for status in statuses {
+            runs.extend(self.all_runs_with_status(workflow_id, status, None)?
+                .into_iter()
+                .filter(|run| before.is_none_or(|before| run.started_at < before)));
+        }
+        runs.sort_by_key(|run| run.started_at);
+        
+        let run_ids = self.in_transaction(|state_manager| {
+            let mut cancelled = Vec::new();
+            for run in &runs {
+                if state_manager.cancel_run(&run.id, reason)? {
+                    cancelled.push(run.id.to_string());
+                }
+            }
+            Ok(cancelled)
+        })?;
+        log::info!("Cancelled {} runs of workflow {}", run_ids.len(), workflow_id);
+        Ok(BulkRunSummary { workflow_id: workflow_id.to_string(), count: run_ids.len(), run_ids })
+    }
+
+    /// Reopen the failed runs of a workflow in one transaction so their failed steps run again
+    ///
+    /// Only runs started at or after `since`, when given, are reopened. Completed
+    /// steps keep their results, while each failed or timed-out step gets a
+    /// pending result so the next execution runs it again; a sub-workflow step
+    /// still sees its finished child run. The runs are left pending for the
+    /// caller to execute.
+    pub fn retry_failed_runs(&mut self, workflow_id: &str, since: Option<chrono::DateTime<Utc>>) -> CoreResult<BulkRunSummary> {
+        let mut runs = self.all_runs_with_status(workflow_id, RunStatus::Failed, since)?;
+        runs.sort_by_key(|run| run.started_at);
+        
+        let run_ids = self.in_transaction(|state_manager| {
+            let mut reopened = Vec::with_capacity(runs.len());
+            for run in &runs {
+                let mut latest: HashMap<String, StepResult> = HashMap::new();
+                for result in state_manager.get_completed_steps(&run.id)? {
+                    latest.insert(result.step_id.clone(), result);
+                }
+                let mut failed_steps: Vec<String> = latest.into_values()
+                    .filter(|result| matches!(result.status, StepStatus::Failed | StepStatus::TimedOut))
+                    .map(|result| result.step_id)
+                    .collect();
+                failed_steps.sort();
+                
+                let now = Utc::now();
+                for step_id in &failed_steps {
+                    state_manager.save_step_result(&run.id, StepResult {
+                        step_id: step_id.clone(),
+                        status: StepStatus::Pending,
+                        output: None,
+                        error: None,
+                        started_at: now,
+                        completed_at: None,
+                        duration_ms: None,
+                        cache_hit: None,
+                        resources: None,
+                    })?;
+                }
+                let mut reopened_run = run.clone();
+                reopened_run.status = RunStatus::Pending;
+                reopened_run.completed_at = None;
+                reopened_run.error = None;
+                if !state_manager.store().save_run_if_version(&reopened_run)? {
+                    return Err(CoreError::Conflict(format!("Run {} was written while it was being reopened", run.id)));
+                }
+                reopened_run.version += 1;
+                state_manager.active_runs.insert(run.id, reopened_run);
+                state_manager.record_run_event(&run.id, RunEvent::RUN_RETRIED, None, RunEvent::ACTOR_API, serde_json::json!({
+                    "failed_steps": failed_steps,
+                }))?;
+                reopened.push(run.id.to_string());
+            }
+            Ok(reopened)
+        })?;
+        log::info!("Reopened {} failed runs of workflow {}", run_ids.len(), workflow_id);
+        Ok(BulkRunSummary { workflow_id: workflow_id.to_string(), count: run_ids.len(), run_ids })
+    }
+
+    /// End a run that exceeded its run timeout with its workflow's timeout outcome
+    ///
+    /// Returns the context the run's failure hooks receive, or None if the run
+    /// had already finished.
+    pub fn time_out_run(&mut self, run: &WorkflowRun, workflow: &WorkflowDefinition, now: chrono::DateTime<Utc>) -> CoreResult<Option<crate::models::WorkflowCompletionContext>> {
+        if self.get_run(&run.id)?.is_none_or(|current| current.status.is_terminal()) {
+            return Ok(None);
+        }
+        let timeout_ms = workflow.run_timeout_ms.unwrap_or_default();
+        let error = format!("Run exceeded its timeout of {}ms", timeout_ms);
+        let status = workflow.run_timeout_outcome.status();
+        let detail = serde_json::json!({"timeout_ms": timeout_ms, "outcome": workflow.run_timeout_outcome});
+        self.record_run_event(&run.id, RunEvent::RUN_TIMED_OUT, None, RunEvent::ACTOR_ENGINE, detail)?;
+        self.end_unfinished_run(&run.id, status.clone(), &error)?;
+        
+        let completed_steps = self.get_completed_steps(&run.id)?;
+        let cancellation = match status {
+            RunStatus::Cancelled => Some(self.run_cancellation(&run.id, Some(error.clone()), &completed_steps)?),
+            _ => None,
+        };
+        let context = crate::models::WorkflowCompletionContext::new(
+            run.id.to_string(),
+            run.workflow_id.clone(),
+            status,
+            completed_steps,
+            Some(error),
+            run.started_at,
+            now,
+            run.payload.clone(),
+        );
+        Ok(Some(match cancellation {
+            Some(cancellation) => context.with_cancellation(cancellation),
+            None => context,
+        }))
+    }
+
+    /// Describe how a cancelled run was cancelled and which of its steps it interrupted
+    fn run_cancellation(&self, run_id: &Uuid, message: Option<String>, completed_steps: &[StepResult]) -> CoreResult<crate::models::RunCancellation> {
+        let events = self.db.get_run_events(&run_id.to_string())?;
+        let reason = if events.iter().any(|event| event.event_type == RunEvent::RUN_TIMED_OUT) {
+            crate::models::CancelReason::Timeout
+        } else {
+            crate::models::CancelReason::Manual
+        };
+        let mut interrupted_steps: Vec<String> = Vec::new();
+        for event in events.iter().filter(|event| event.event_type == RunEvent::STEP_STARTED) {
+            let Some(step_id) = &event.step_id else {
+                continue;
+            };
+            if !interrupted_steps.contains(step_id) && !completed_steps.iter().any(|result| &result.step_id == step_id) {
+                interrupted_steps.push(step_id.clone());
+            }
+        }
+        Ok(crate::models::RunCancellation { reason, message, interrupted_steps })
+    }
+
+    /// Build the context the completion hooks of a finished run receive
+    ///
+    /// Returns None if the run does not exist or has not finished. The run is
+    /// read from the store, as another component may have finished it.
+    /// Cancelled runs also get how they were cancelled and the steps they
+    /// interrupted, for their onCancel hook.
+    pub fn completion_context(&self, run_id: &Uuid) -> CoreResult<Option<crate::models::WorkflowCompletionContext>> {
+        let Some(run) = self.store().get_run(&run_id.to_string())?.filter(|run| run.status.is_terminal()) else {
+            return Ok(None);
+        };
+        let completed_steps = self.get_completed_steps(run_id)?;
+        let cancellation = match run.status {
+            RunStatus::Cancelled => Some(self.run_cancellation(run_id, run.error.clone(), &completed_steps)?),
+            _ => None,
+        };
+        let context = crate::models::WorkflowCompletionContext::new(
+            run.id.to_string(),
+            run.workflow_id.clone(),
+            run.status.clone(),
+            completed_steps,
+            run.error.clone(),
+            run.started_at,
+            run.completed_at.unwrap_or_else(Utc::now),
+            run.payload,
+        );
+        Ok(Some(match cancellation {
+            Some(cancellation) => context.with_cancellation(cancellation),
+            None => context,
+        }))
+    }
+
+    /// Update run status
+    pub fn update_run_status(&mut self, run_id: &Uuid, status: RunStatus) -> CoreResult<()> {
+        if self.active_runs.contains_key(run_id) {
+            let mut changed = false;
+            let run = self.write_run(run_id, |run| {
+                changed = run.status != status;
+                run.status = status.clone();
+                if matches!(status, RunStatus::Completed | RunStatus::Failed) {
+                    run.completed_at = Some(Utc::now());
+                }
+                true
+            })?;
+            let finished = run.filter(|_| changed && status.is_terminal());
+            if changed {
+                self.record_run_event(run_id, RunEvent::for_run_status(&status), None, RunEvent::ACTOR_ENGINE, serde_json::json!({}))?;
+            }
+            if let Some(run) = finished {
+                self.trace_run_end(&run);
+            }
+            log::info!("Updated run {} status to {:?}", run_id, status);
+        }
+
+        Ok(())
+    }
+
+    /// Save step result
+    pub fn save_step_result(&self, run_id: &Uuid, result: StepResult) -> CoreResult<()> {
+        let result = self.check_step_output(run_id, result)?;
+        let result = self.offload_output(&run_id.to_string(), result)?;
+        let saving_since = std::time::SystemTime::now();
+        self.store().save_step_result(&result, &run_id.to_string())?;
+        if let Some(run) = self.get_run(run_id)? {
+            if let Some(trace) = self.run_trace(run_id) {
+                telemetry::export_db(&trace, self.store().name(), "save_step_result", saving_since);
+                telemetry::export_step(&trace, &run.workflow_id, &result);
+            }
+            self.record_step_duration(&run.workflow_id, &result);
+            if let Some(output) = &result.output {
+                self.index_run_document(&run, &run_search::step_source(&result.step_id), output);
+            }
+        }
+        self.record_run_event(
+            run_id,
+            RunEvent::for_step_status(&result.status),
+            Some(&result.step_id),
+            RunEvent::ACTOR_ENGINE,
+            RunEvent::step_result_detail(&result),
+        )
+    }
+
+    /// Index the content of a run's payload or step output for search, logging rather than failing on errors
+    fn index_run_document(&self, run: &WorkflowRun, source: &str, document: &serde_json::Value) {
+        if let Err(e) = self.db.index_run_document(run, source, document) {
+            log::warn!("Failed to index {} of run {} for search: {}", source, run.id, e);
+        }
+    }
+
+    /// Find a workflow's runs whose payload or a step output holds a value at a JSON path, newest first
+    ///
+    /// Values are compared as text, so `"12345"` matches both the string and the number.
+    pub fn find_runs(&self, workflow_id: &str, path: &str, value: &str, limit: Option<usize>) -> CoreResult<Vec<WorkflowRun>> {
+        let path = run_search::normalize_path(path)?;
+        let limit = limit.unwrap_or(RunQuery::DEFAULT_LIMIT).clamp(1, RunQuery::MAX_LIMIT);
+        let mut runs = Vec::new();
+        for run_id in self.db.find_run_ids(workflow_id, &path, value, limit)? {
+            // Runs deleted from a shared store may still be indexed on this node
+            if let Some(run) = self.store().get_run(&run_id)? {
+                runs.push(run);
+            }
+        }
+        Ok(runs)
+    }
+
+    /// Record the duration of a successful step, logging rather than failing on errors
+    fn record_step_duration(&self, workflow_id: &str, result: &StepResult) {
+        let (StepStatus::Completed, Some(duration_ms)) = (&result.status, result.duration_ms) else {
+            return;
+        };
+        if let Err(e) = self.db.record_step_duration(workflow_id, &result.step_id, duration_ms, AdaptiveTimeout::SAMPLE_WINDOW) {
+            log::warn!("Failed to record duration of step {}: {}", result.step_id, e);
+        }
+    }
+
+    /// Get the distribution of a step's recent successful durations
+    pub fn get_step_duration_stats(&self, workflow_id: &str, step_id: &str) -> CoreResult<StepDurationStats> {
+        let workflow = self.get_workflow(workflow_id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?;
+        let step = workflow.get_step(step_id)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step '{}' not found in workflow '{}'", step_id, workflow_id)))?;
+        let durations = self.db.get_step_durations(workflow_id, step_id)?;
+        
+        Ok(StepDurationStats {
+            workflow_id: workflow_id.to_string(),
+            step_id: step_id.to_string(),
+            samples: durations.len(),
+            p50_ms: percentile_ms(&durations, 50.0),
+            p90_ms: percentile_ms(&durations, 90.0),
+            p99_ms: percentile_ms(&durations, 99.0),
+            max_ms: durations.iter().max().copied(),
+            timeout_ms: step.effective_timeout_ms(&durations),
+        })
+    }
+
+    /// Get the timeout a step runs with, learned from its recent durations when it has an adaptive timeout
+    pub fn step_timeout_ms(&self, workflow_id: &str, step_id: &str) -> CoreResult<Option<u64>> {
+        let Some(step) = self.get_workflow(workflow_id)?.and_then(|w| w.get_step(step_id).cloned()) else {
+            return Ok(None);
+        };
+        if step.adaptive_timeout.is_none() {
+            return Ok(step.timeout);
+        }
+        Ok(step.effective_timeout_ms(&self.db.get_step_durations(workflow_id, step_id)?))
+    }
+
+    /// Get the worker capability tags a step's jobs require
+    pub fn step_worker_tags(&self, workflow_id: &str, step_id: &str) -> CoreResult<Vec<String>> {
+        Ok(self.get_workflow(workflow_id)?
+            .and_then(|workflow| workflow.get_step(step_id).map(|step| step.worker_tags.clone()))
+            .unwrap_or_default())
+    }
+
+    /// Get the dispatcher lane of a run: the one its trigger picked, else its workflow's
+    pub fn run_lane(&self, workflow_id: &str, run_id: &str) -> CoreResult<Option<String>> {
+        if let Some(lane) = self.db.get_run_trigger(run_id)?.and_then(|trigger| trigger.lane) {
+            return Ok(Some(lane));
+        }
+        Ok(self.get_workflow(workflow_id)?.and_then(|workflow| workflow.default_lane().map(str::to_string)))
+    }
+
+    /// Skip the steps of a run that have no result yet, after a step completed the run early
+    pub fn complete_run_early(&self, run_id: &Uuid, by_step: &str) -> CoreResult<usize> {
+        let run = self.get_run(run_id)?
+            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+        let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?
+            .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+        let results = self.get_completed_steps(run_id)?;
+        
+        let skipped = StepControl::CompleteRun.skipped_steps(&workflow, &results);
+        for result in &skipped {
+            self.save_step_result(run_id, result.clone())?;
+            self.record_early_completion_skip(run_id, &result.step_id, by_step);
+        }
+        log::info!("Step {} completed run {} early, skipping {} steps", by_step, run_id, skipped.len());
+        Ok(skipped.len())
+    }
+
+    /// Record why a step was skipped by an early run completion, logging rather than failing on errors
+    fn record_early_completion_skip(&self, run_id: &Uuid, step_id: &str, by_step: &str) {
+        let detail = serde_json::json!({ "reason": format!("step '{}' completed the run early", by_step) });
+        if let Err(e) = self.record_step_trace_event(run_id, step_id, crate::explainer::EVENT_SKIPPED, detail) {
+            log::warn!("Failed to record skip trace event for step {}: {}", step_id, e);
+        }
+    }
+
+    /// Get all active runs
+    pub fn get_active_runs(&self) -> Vec<WorkflowRun> {
+        self.active_runs.values().cloned().collect()
+    }
+
+    /// Clean up completed runs
+    pub fn cleanup_completed_runs(&mut self) -> CoreResult<()> {
+        let completed_runs: Vec<Uuid> = self.active_runs
+            .iter()
+            .filter(|(_, run)| matches!(run.status, RunStatus::Completed | RunStatus::Failed))
+            .map(|(id, _)| *id)
+            .collect();
+
+        let count = completed_runs.len();
+        for run_id in completed_runs {
+            self.active_runs.remove(&run_id);
+        }
+
+        log::info!("Cleaned up {} completed runs", count);
+        Ok(())
+    }
+
+    /// Get completed steps for a run
+    ///
+    /// Offloaded outputs are left as blob references; use
+    /// `get_completed_steps_with_outputs` where the outputs themselves are needed.
+    pub fn get_completed_steps(&self, run_id: &Uuid) -> CoreResult<Vec<StepResult>> {
+        self.store().get_step_results(&run_id.to_string())
+    }
+
+    /// Get the stored state of a run and its step results with outputs, bypassing the active run cache
+    ///
+    /// Used to follow runs that another engine component is executing.
+    pub fn get_run_progress(&self, run_id: &Uuid) -> CoreResult<(WorkflowRun, Vec<StepResult>)> {
+        let run = match self.store().get_run(&run_id.to_string())? {
+            Some(run) => run,
+            None => self.get_run(run_id)?.ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?,
+        };
+        Ok((run, self.get_completed_steps_with_outputs(run_id)?))
+    }
+
+    /// Get completed steps for a run with offloaded outputs loaded back
+    pub fn get_completed_steps_with_outputs(&self, run_id: &Uuid) -> CoreResult<Vec<StepResult>> {
+        let mut results = self.get_completed_steps(run_id)?;
+        let mut blobs = HashMap::new();
+        for id in payload_offload::blob_ids(&results) {
+            if let Some(data) = self.store().get_payload_blob(&id)? {
+                blobs.insert(id, data);
+            }
+        }
+        payload_offload::resolve(&mut results, &blobs)?;
+        Ok(results)
+    }
+
+    /// Fail a completed step whose output does not match the output schema of its step
+    fn check_step_output(&self, run_id: &Uuid, result: StepResult) -> CoreResult<StepResult> {
+        if result.status != StepStatus::Completed {
+            return Ok(result);
+        }
+        let Some(run) = self.get_run(run_id)? else {
+            return Ok(result);
+        };
+        let workflow = self.get_run_workflow(&run.workflow_id, run_id)?;
+        match workflow.as_ref().and_then(|workflow| workflow.get_step(&result.step_id)) {
+            Some(step) => Ok(output_schemas::enforce(step, result)),
+            None => Ok(result),
+        }
+    }
+
+    /// Check a step output against the size limits, storing it as a blob of the run when large
+    fn offload_output(&self, run_id: &str, result: StepResult) -> CoreResult<StepResult> {
+        let (result, blob) = payload_offload::offload(result, self.output_limits)?;
+        if let Some(blob) = blob {
+            log::debug!("Offloading {} byte output of step {}", blob.size_bytes, result.step_id);
+            self.store().save_payload_blob(run_id, &blob)?;
+        }
+        Ok(result)
+    }
+
+    /// Update run with step results
+    pub fn update_run_with_steps(&mut self, run_id: &Uuid, completed_steps: &[StepResult]) -> CoreResult<()> {
+        // Save each step result
+        for step_result in completed_steps {
+            self.save_step_result(run_id, step_result.clone())?;
+        }
+        
+        if self.active_runs.contains_key(run_id) {
+            let started = self.write_run(run_id, |run| {
+                let pending = run.status == RunStatus::Pending;
+                if pending {
+                    run.status = RunStatus::Running;
+                }
+                pending
+            })?;
+            if started.is_some() {
+                self.record_run_event(run_id, RunEvent::for_run_status(&RunStatus::Running), None, RunEvent::ACTOR_ENGINE, serde_json::json!({}))?;
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Rewrite a run unless it changed since it was read, hashing its payload if the workflow samples it out
+    fn persist_run(db: &dyn StorageBackend, run: &WorkflowRun) -> CoreResult<bool> {
+        if run.status == RunStatus::Completed {
+            if let Some(workflow) = db.get_workflow(&run.workflow_id)? {
+                if let Some(sampled) = payload_sampling::sampled_run(&workflow, run) {
+                    log::debug!("Storing only the payload hash of run {}", run.id);
+                    return db.save_run_if_version(&sampled);
+                }
+            }
+        }
+        db.save_run_if_version(run)
+    }
+
+    /// Apply a change to a run and write it, unless another writer got there first
+    ///
+    /// The change is made to the cached run, or the stored one. When the stored
+    /// run was written in between, the change is made again to a fresh read of
+    /// it, up to `RUN_WRITE_ATTEMPTS` times before failing with a conflict.
+    /// `change` returns false to leave the run alone. Returns the run written.
+    fn write_run(&mut self, run_id: &Uuid, mut change: impl FnMut(&mut WorkflowRun) -> bool) -> CoreResult<Option<WorkflowRun>> {
+        let mut read = self.get_run(run_id)?;
+        for _ in 0..RUN_WRITE_ATTEMPTS {
+            let Some(mut run) = read else {
+                return Ok(None);
+            };
+            if !change(&mut run) {
+                return Ok(None);
+            }
+            if Self::persist_run(self.store(), &run)? {
+                run.version += 1;
+                if let Some(cached) = self.active_runs.get_mut(run_id) {
+                    *cached = run.clone();
+                }
+                return Ok(Some(run));
+            }
+            log::debug!("Run {} was written concurrently, updating it again", run_id);
+            read = self.store().get_run(&run_id.to_string())?;
+        }
+        Err(CoreError::Conflict(format!(
+            "Run {} kept being written concurrently, gave up after {} attempts", run_id, RUN_WRITE_ATTEMPTS
+        )))
+    }
+
+    /// Replace the cached copy of a run with the stored one
+    fn refresh_cached_run(&mut self, run_id: &Uuid) -> CoreResult<()> {
+        if self.active_runs.contains_key(run_id) {
+            if let Some(run) = self.store().get_run(&run_id.to_string())? {
+                self.active_runs.insert(*run_id, run);
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete a run with final status
+    pub fn complete_run(&mut self, run_id: &Uuid, status: RunStatus, error: Option<String>) -> CoreResult<()> {
+        if !self.active_runs.contains_key(run_id) {
+            return Ok(());
+        }
+        let written = self.write_run(run_id, |run| {
+            run.status = status.clone();
+            run.completed_at = Some(Utc::now());
+            run.error = error.clone();
+            true
+        })?;
+        if let Some(finished) = written {
+            let detail = serde_json::json!({"error": finished.error});
+            self.record_run_event(run_id, RunEvent::for_run_status(&status), None, RunEvent::ACTOR_ENGINE, detail)?;
+            self.trace_run_end(&finished);
+            log::info!("Completed run {} with status {:?}", run_id, status);
+        }
+        
+        Ok(())
+    }
+
+    /// Validate and persist a batch of step completions, updating the status of every affected run
+    ///
+    /// The whole batch is rejected if any entry is invalid; otherwise all step results
+    /// and run status changes are written in one transaction. A batch that loses
+    /// to a concurrent write of one of its runs is computed again from the stored runs.
+    /// Runs are continued by `StepOrchestrator::complete_steps_batch`.
+    pub fn complete_steps_batch(&mut self, completions: Vec<StepCompletion>) -> CoreResult<StepBatchCompletionSummary> {
+        let mut attempt = 1;
+        loop {
+            match self.write_steps_batch(completions.clone()) {
+                Err(CoreError::Conflict(reason)) if attempt < RUN_WRITE_ATTEMPTS => {
+                    log::debug!("Writing the step batch again: {}", reason);
+                    for completion in &completions {
+                        self.refresh_cached_run(&Uuid::parse_str(&completion.run_id)?)?;
+                    }
+                    attempt += 1;
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+
+    /// Write a batch of step completions once, failing with a conflict if one of its runs was written meanwhile
+    fn write_steps_batch(&mut self, completions: Vec<StepCompletion>) -> CoreResult<StepBatchCompletionSummary> {
+        let mut runs: HashMap<Uuid, (WorkflowRun, WorkflowDefinition, Vec<StepResult>)> = HashMap::new();
+        let mut results = Vec::with_capacity(completions.len());
+        let mut early_skips = Vec::new();
+        
+        for completion in completions {
+            completion.result.validate()
+                .map_err(|e| CoreError::Validation(format!("Invalid result for step {}: {}", completion.result.step_id, e)))?;
+            self.verify_step_context(&completion)?;
+            
+            let run_id = Uuid::parse_str(&completion.run_id)?;
+            if let std::collections::hash_map::Entry::Vacant(entry) = runs.entry(run_id) {
+                let run = self.get_run(&run_id)?
+                    .ok_or_else(|| CoreError::RunNotFound(completion.run_id.clone()))?;
+                if run.status.is_terminal() {
+                    return Err(CoreError::InvalidState(format!("Run {} is already {}", run_id, run.status.as_str())));
+                }
+                let workflow = self.get_run_workflow(&run.workflow_id, &run.id)?
+                    .ok_or_else(|| CoreError::WorkflowNotFound(run.workflow_id.clone()))?;
+                let completed_steps = self.store().get_step_results(&completion.run_id)?;
+                entry.insert((run, workflow, completed_steps));
+            }
+            
+            let (_, workflow, completed_steps) = runs.get_mut(&run_id)
+                .ok_or_else(|| CoreError::Internal(format!("Run {} missing from batch", run_id)))?;
+            let Some(step) = workflow.get_step(&completion.result.step_id) else {
+                return Err(CoreError::StepNotFound(format!(
+                    "Step '{}' not found in workflow '{}'", completion.result.step_id, workflow.id
+                )));
+            };
+            let result = output_schemas::enforce(step, completion.result);
+            let control = result.control();
+            let result = self.offload_output(&completion.run_id, result)?;
+            let by_step = result.step_id.clone();
+            completed_steps.push(result.clone());
+            results.push((completion.run_id.clone(), result));
+            
+            if let Some(control) = control {
+                for skipped in control.skipped_steps(workflow, completed_steps) {
+                    early_skips.push((run_id, skipped.step_id.clone(), by_step.clone()));
+                    completed_steps.push(skipped.clone());
+                    results.push((completion.run_id.clone(), skipped));
+                }
+            }
+        }
+        
+        let mut updated_runs = Vec::with_capacity(runs.len());
+        let mut previous_statuses = HashMap::with_capacity(runs.len());
+        for (run, workflow, completed_steps) in runs.values() {
+            previous_statuses.insert(run.id, run.status.clone());
+            let mut run = run.clone();
+            let all_finished = workflow.steps.iter().all(|step| {
+                completed_steps.iter().any(|r| r.step_id == step.id && r.status.is_terminal())
+            });
+            
+            if all_finished {
+                let failed = completed_steps.iter().find(|r| r.status.is_failure());
+                run.status = if failed.is_some() { RunStatus::Failed } else { RunStatus::Completed };
+                run.error = failed.and_then(|r| r.error.clone());
+                run.completed_at = Some(Utc::now());
+            } else {
+                run.status = RunStatus::Running;
+            }
+            updated_runs.push(payload_sampling::sampled_run(workflow, &run).unwrap_or(run));
+        }
+        
+        let mut events: Vec<RunEvent> = results.iter()
+            .map(|(run_id, result)| RunEvent {
+                run_id: run_id.clone(),
+                event_type: RunEvent::for_step_status(&result.status).to_string(),
+                step_id: Some(result.step_id.clone()),
+                actor: RunEvent::ACTOR_API.to_string(),
+                detail: RunEvent::step_result_detail(result),
+                created_at: Utc::now(),
+            })
+            .collect();
+        for run in &updated_runs {
+            if previous_statuses.get(&run.id) != Some(&run.status) {
+                let mut detail = serde_json::json!({"error": run.error});
+                secrets::mask_value(&mut detail);
+                events.push(RunEvent {
+                    run_id: run.id.to_string(),
+                    event_type: RunEvent::for_run_status(&run.status).to_string(),
+                    step_id: None,
+                    actor: RunEvent::ACTOR_ENGINE.to_string(),
+                    detail,
+                    created_at: Utc::now(),
+                });
+            }
+        }
+        
+        // Run events are node-local, so they share the batch transaction only when this node's database is the store
+        let saving_since = std::time::SystemTime::now();
+        match &self.storage {
+            None => self.db.save_step_results_batch_with_events(&results, &updated_runs, &events)?,
+            Some(storage) => {
+                storage.save_step_results_batch(&results, &updated_runs)?;
+                for event in &events {
+                    self.db.save_run_event(event)?;
+                }
+            }
+        }
+        for event in &events {
+            run_stream::publish(event);
+        }
+        for run in &updated_runs {
+            if let Some(trace) = self.run_trace(&run.id) {
+                telemetry::export_db(&trace, self.store().name(), "save_step_results_batch", saving_since);
+            }
+        }
+        
+        for (run_id, result) in &results {
+            if let Some((run, _, _)) = Uuid::parse_str(run_id).ok().and_then(|id| runs.get(&id)) {
+                if let Some(trace) = self.run_trace(&run.id) {
+                    telemetry::export_step(&trace, &run.workflow_id, result);
+                }
+                self.record_step_duration(&run.workflow_id, result);
+                if let Some(output) = &result.output {
+                    self.index_run_document(run, &run_search::step_source(&result.step_id), output);
+                }
+            }
+        }
+        for (run_id, step_id, by_step) in &early_skips {
+            self.record_early_completion_skip(run_id, step_id, by_step);
+        }
+        for run in &updated_runs {
+            if previous_statuses.get(&run.id) != Some(&run.status) && run.status.is_terminal() {
+                self.trace_run_end(run);
+            }
+        }
+        
+        let mut summary = StepBatchCompletionSummary {
+            persisted_steps: results.len(),
+            runs: HashMap::new(),
+        };
+        for mut run in updated_runs {
+            run.version += 1;
+            summary.runs.insert(run.id.to_string(), run.status.clone());
+            if run.status.is_terminal() {
+                self.active_runs.remove(&run.id);
+            } else {
+                self.active_runs.insert(run.id, run);
+            }
+        }
+        
+        log::info!("Persisted batch of {} step completions across {} runs", summary.persisted_steps, summary.runs.len());
+        Ok(summary)
+    }
+
+    /// Delete finished runs that completed before a time, with their steps and events
+    pub fn purge_runs(&mut self, before: chrono::DateTime<Utc>) -> CoreResult<RunPurgeSummary> {
+        let runs = self.store().get_finished_runs()?;
+        let expired: Vec<&FinishedRun> = runs.iter().filter(|run| run.finished_at < before).collect();
+        let summary = self.delete_finished_runs(&expired)?;
+        log::info!("Purged {} runs finished before {}", summary.runs_deleted, before);
+        Ok(summary)
+    }
+
+    /// Delete the finished runs the retention policy expires
+    pub fn apply_retention(&mut self, config: &RetentionConfig, now: chrono::DateTime<Utc>) -> CoreResult<RunPurgeSummary> {
+        if !config.prunes_runs() {
+            return Ok(RunPurgeSummary::default());
+        }
+        let runs = self.store().get_finished_runs()?;
+        // Pinned runs do not count toward the per-workflow limit; deleting counts those that expired as kept
+        let mut pinned = HashSet::new();
+        for run in &runs {
+            if self.db.get_run_pin(&run.run_id)?.is_some() {
+                pinned.insert(run.run_id.as_str());
+            }
+        }
+        let expired = retention::expired_runs(&runs, &pinned, config, now);
+        let summary = self.delete_finished_runs(&expired)?;
+        if summary.runs_deleted > 0 {
+            log::info!("Retention cleanup deleted {} runs", summary.runs_deleted);
+        }
+        Ok(summary)
     }
 
-    /// Update run with step results
-    pub fn update_run_with_steps(&mut self, run_id: &Uuid, completed_steps: &[StepResult]) -> CoreResult<()> {
-        // Save each step result
-        for step_result in completed_steps {
-            self.save_step_result(run_id, step_result.clone())?;
+    /// Delete finished runs from the store and their node-local records, skipping pinned runs
+    fn delete_finished_runs(&mut self, runs: &[&FinishedRun]) -> CoreResult<RunPurgeSummary> {
+        let mut summary = RunPurgeSummary::default();
+        let mut run_ids = Vec::with_capacity(runs.len());
+        for run in runs {
+            if self.db.get_run_pin(&run.run_id)?.is_some() {
+                summary.pinned_kept += 1;
+            } else {
+                run_ids.push(run.run_id.clone());
+            }
+        }
+        if run_ids.is_empty() {
+            return Ok(summary);
         }
         
-        if let Some(run) = self.active_runs.get_mut(run_id) {
-            if run.status == crate::models::RunStatus::Pending {
-                run.status = crate::models::RunStatus::Running;
-                self.db.save_run(run)?;
+        // Node-local records go first, so a failed store delete leaves runs to retry rather than orphaned records
+        self.db.delete_run_records(&run_ids)?;
+        summary.runs_deleted = self.store().delete_runs(&run_ids)?;
+        for run_id in &run_ids {
+            if let Ok(run_uuid) = Uuid::parse_str(run_id) {
+                self.active_runs.remove(&run_uuid);
             }
         }
-        
-        Ok(())
+        Ok(summary)
     }
 
-    /// Complete a run with final status
-    pub fn complete_run(&mut self, run_id: &Uuid, status: RunStatus, error: Option<String>) -> CoreResult<()> {
-        if let Some(run) = self.active_runs.get_mut(run_id) {
-            run.status = status.clone();
-            run.completed_at = Some(Utc::now());
-            run.error = error;
-            
-            self.db.save_run(run)?;
-            log::info!("Completed run {} with status {:?}", run_id, status);
+    /// Export aged run history to cold storage
+    pub fn export_cold_storage(&self, exporter: &ColdStorageExporter) -> CoreResult<ColdStorageExportSummary> {
+        self.check_cold_storage_source()?;
+        exporter.export_due(&self.db)
+    }
+
+    /// Export history of runs started within a time range to cold storage
+    pub fn export_cold_storage_range(
+        &self,
+        exporter: &ColdStorageExporter,
+        from: chrono::DateTime<Utc>,
+        to: chrono::DateTime<Utc>,
+    ) -> CoreResult<ColdStorageExportSummary> {
+        self.check_cold_storage_source()?;
+        exporter.export_range(&self.db, from, to)
+    }
+
+    /// Refuse cold storage exports when run history lives outside the local database
+    ///
+    /// The exporter reads history and records its watermark in the local database,
+    /// which holds none of the runs written to a shared or isolated store.
+    fn check_cold_storage_source(&self) -> CoreResult<()> {
+        if self.storage.is_some() {
+            return Err(CoreError::Configuration(
+                "Cold storage exports cover the local database only, not shared or isolated stores".to_string(),
+            ));
         }
-        
         Ok(())
     }
 }
 
+/// Whether two workflow definitions are the same apart from their timestamps
+fn same_definition(a: &WorkflowDefinition, b: &WorkflowDefinition) -> bool {
+    let comparable = |workflow: &WorkflowDefinition| {
+        serde_json::to_value(WorkflowDefinition {
+            created_at: chrono::DateTime::<Utc>::UNIX_EPOCH,
+            updated_at: chrono::DateTime::<Utc>::UNIX_EPOCH,
+            ..workflow.clone()
+        }).ok()
+    };
+    comparable(a) == comparable(b)
+}
+
+/// Whether two trigger definitions are the same, e.g. across re-registrations
+fn same_trigger(a: &crate::models::TriggerDefinition, b: &crate::models::TriggerDefinition) -> bool {
+    serde_json::to_value(a).ok() == serde_json::to_value(b).ok()
+}
+
 // ============================================================================
 // ASYNC STATE MANAGER (Task 2.1.2)
 // ============================================================================
 
 /// Async state manager for workflow orchestration
 /// Uses AsyncDatabase and tokio for non-blocking operations
+///
+/// It only reads and writes the local SQLite database, never a shared or
+/// isolated store, so the async bridge refuses to start when one is configured.
 pub struct AsyncStateManager {
     db: AsyncDatabase,
     active_runs: Arc<Mutex<HashMap<Uuid, WorkflowRun>>>,
+    output_limits: OutputLimits,
+    secret_cipher: Option<Arc<SecretCipher>>,
 }
 
 impl AsyncStateManager {
@@ -170,6 +2333,8 @@ impl AsyncStateManager {
         Ok(AsyncStateManager {
             db,
             active_runs: Arc::new(Mutex::new(HashMap::new())),
+            output_limits: OutputLimits::from_config(&crate::config::PayloadConfig::default()),
+            secret_cipher: SecretCipher::from_config(&crate::config::SecretsConfig::default())?.map(Arc::new),
         })
     }
 
@@ -200,13 +2365,18 @@ impl AsyncStateManager {
             started_at: now,
             completed_at: None,
             error: None,
+            version: 0,
         };
 
         self.db.save_run(&run).await?;
+        if let Err(e) = self.db.index_run_document(&run, run_search::PAYLOAD_SOURCE.to_string(), run.payload.clone()).await {
+            log::warn!("Failed to index payload of run {} for search: {}", run.id, e);
+        }
         
         let mut active_runs = self.active_runs.lock().await;
         active_runs.insert(run_id, run);
         drop(active_runs);
+        self.record_run_event(&run_id, RunEvent::RUN_CREATED, None, RunEvent::ACTOR_ENGINE, serde_json::json!({"workflow_id": workflow_id})).await?;
 
         log::info!("Created workflow run: {} for workflow: {}", run_id, workflow_id);
         Ok(run_id)
@@ -225,17 +2395,88 @@ impl AsyncStateManager {
         self.db.get_run(run_id.to_string()).await
     }
 
+    /// List persisted runs matching a query (async)
+    pub async fn list_runs(&self, query: RunQuery) -> CoreResult<RunPage> {
+        self.db.list_runs(query).await
+    }
+
+    /// Append an event to a run's event log (async)
+    pub async fn record_run_event(&self, run_id: &Uuid, event_type: &str, step_id: Option<&str>, actor: &str, mut detail: serde_json::Value) -> CoreResult<()> {
+        secrets::mask_value(&mut detail);
+        let event = RunEvent {
+            run_id: run_id.to_string(),
+            event_type: event_type.to_string(),
+            step_id: step_id.map(str::to_string),
+            actor: actor.to_string(),
+            detail,
+            created_at: Utc::now(),
+        };
+        self.db.save_run_event(&event).await?;
+        run_stream::publish(&event);
+        Ok(())
+    }
+
+    /// Snapshot the context state visible to the steps of a run (async)
+    pub async fn context_state(&self, run_id: &str, workflow_id: &str) -> CoreResult<ContextState> {
+        let now = Utc::now();
+        let values = |entries: Vec<StateEntry>| entries.into_iter().map(|entry| (entry.key, entry.value)).collect();
+        Ok(ContextState {
+            run: values(self.db.get_state_entries(StateScope::Run, run_id.to_string(), now).await?),
+            workflow: values(self.db.get_state_entries(StateScope::Workflow, workflow_id.to_string(), now).await?),
+        })
+    }
+
+    /// Get the labels of a run (async)
+    pub async fn get_run_labels(&self, run_id: &str) -> CoreResult<RunLabels> {
+        self.db.get_run_labels(run_id.to_string()).await
+    }
+
+    /// Decrypt the secrets the steps of a workflow receive in their context (async)
+    pub async fn workflow_secrets(&self, workflow_id: &str) -> CoreResult<HashMap<String, String>> {
+        let Some(cipher) = &self.secret_cipher else {
+            return Ok(HashMap::new());
+        };
+        let stored = self.db.get_secrets(vec![secrets::GLOBAL_SCOPE.to_string(), workflow_id.to_string()]).await?;
+        secrets::decrypt_for_workflow(cipher, &stored, workflow_id)
+    }
+
+    /// Build the execution details of a step's context from its run's trigger and history (async)
+    pub async fn execution_info(&self, run_id: &str, step_id: &str, results: &[StepResult], queued_at: Option<DateTime<Utc>>) -> CoreResult<ExecutionInfo> {
+        let trigger = self.db.get_run_trigger(run_id.to_string()).await?;
+        let events = self.db.get_run_events(run_id.to_string()).await?;
+        Ok(ExecutionInfo::for_step(step_id, trigger, &events, results, queued_at))
+    }
+
+    /// Remember the checksum of a context issued to Bun.js (async)
+    pub async fn record_issued_context(&self, context: &Context) -> CoreResult<String> {
+        let checksum = context.generate_checksum();
+        self.db.save_step_context_checksum(&StepContextChecksum {
+            run_id: context.run_id.clone(),
+            step_id: context.step_name.clone(),
+            checksum: checksum.clone(),
+            schema_version: context.metadata.schema_version,
+            attempt: context.metadata.retry_count,
+            issued_at: Utc::now(),
+        }).await?;
+        Ok(checksum)
+    }
+
     /// Update run status (async)
     pub async fn update_run_status(&self, run_id: &Uuid, status: RunStatus) -> CoreResult<()> {
         let mut active_runs = self.active_runs.lock().await;
         if let Some(run) = active_runs.get_mut(run_id) {
-            run.status = status.clone();
-            
-            if matches!(status, RunStatus::Completed | RunStatus::Failed) {
-                run.completed_at = Some(Utc::now());
+            let mut changed = false;
+            self.write_run(run, |run| {
+                changed = run.status != status;
+                run.status = status.clone();
+                if matches!(status, RunStatus::Completed | RunStatus::Failed) {
+                    run.completed_at = Some(Utc::now());
+                }
+                true
+            }).await?;
+            if changed {
+                self.record_run_event(run_id, RunEvent::for_run_status(&status), None, RunEvent::ACTOR_ENGINE, serde_json::json!({})).await?;
             }
-
-            self.db.save_run(run).await?;
             log::info!("Updated run {} status to {:?}", run_id, status);
         }
 
@@ -243,8 +2484,33 @@ impl AsyncStateManager {
     }
 
     /// Save step result (async)
-    pub async fn save_step_result(&self, run_id: &Uuid, result: StepResult) -> CoreResult<()> {
-        self.db.save_step_result(&result, run_id.to_string()).await
+    pub async fn save_step_result(&self, run_id: &Uuid, mut result: StepResult) -> CoreResult<()> {
+        if result.status == StepStatus::Completed {
+            if let Some(run) = self.get_run(run_id).await? {
+                let workflow = self.get_workflow(&run.workflow_id).await?;
+                if let Some(step) = workflow.as_ref().and_then(|workflow| workflow.get_step(&result.step_id)) {
+                    result = output_schemas::enforce(step, result);
+                }
+            }
+        }
+        let (result, blob) = payload_offload::offload(result, self.output_limits)?;
+        if let Some(blob) = blob {
+            log::debug!("Offloading {} byte output of step {}", blob.size_bytes, result.step_id);
+            self.db.save_payload_blob(&blob).await?;
+        }
+        self.db.save_step_result(&result, run_id.to_string()).await?;
+        if let (Some(output), Some(run)) = (&result.output, self.get_run(run_id).await?) {
+            if let Err(e) = self.db.index_run_document(&run, run_search::step_source(&result.step_id), output.clone()).await {
+                log::warn!("Failed to index output of step {} of run {} for search: {}", result.step_id, run.id, e);
+            }
+        }
+        self.record_run_event(
+            run_id,
+            RunEvent::for_step_status(&result.status),
+            Some(&result.step_id),
+            RunEvent::ACTOR_ENGINE,
+            RunEvent::step_result_detail(&result),
+        ).await
     }
 
     /// Get all active runs (async)
@@ -277,6 +2543,19 @@ impl AsyncStateManager {
         self.db.get_step_results(run_id.to_string()).await
     }
 
+    /// Get completed steps for a run with offloaded outputs loaded back (async)
+    pub async fn get_completed_steps_with_outputs(&self, run_id: &Uuid) -> CoreResult<Vec<StepResult>> {
+        let mut results = self.get_completed_steps(run_id).await?;
+        let mut blobs = HashMap::new();
+        for id in payload_offload::blob_ids(&results) {
+            if let Some(data) = self.db.get_payload_blob(id.clone()).await? {
+                blobs.insert(id, data);
+            }
+        }
+        payload_offload::resolve(&mut results, &blobs)?;
+        Ok(results)
+    }
+
     /// Update run with step results (async)
     pub async fn update_run_with_steps(&self, run_id: &Uuid, completed_steps: &[StepResult]) -> CoreResult<()> {
         // Save each step result
@@ -286,27 +2565,1229 @@ impl AsyncStateManager {
         
         let mut active_runs = self.active_runs.lock().await;
         if let Some(run) = active_runs.get_mut(run_id) {
-            if run.status == RunStatus::Pending {
-                run.status = RunStatus::Running;
-                self.db.save_run(run).await?;
+            let started = self.write_run(run, |run| {
+                let pending = run.status == RunStatus::Pending;
+                if pending {
+                    run.status = RunStatus::Running;
+                }
+                pending
+            }).await?;
+            if started {
+                self.record_run_event(run_id, RunEvent::for_run_status(&RunStatus::Running), None, RunEvent::ACTOR_ENGINE, serde_json::json!({})).await?;
             }
         }
         
         Ok(())
     }
 
+    /// Rewrite a run unless it changed since it was read, hashing its payload if the workflow samples it out (async)
+    async fn persist_run(db: &AsyncDatabase, run: &WorkflowRun) -> CoreResult<bool> {
+        if run.status == RunStatus::Completed {
+            if let Some(workflow) = db.get_workflow(run.workflow_id.clone()).await? {
+                if let Some(sampled) = payload_sampling::sampled_run(&workflow, run) {
+                    log::debug!("Storing only the payload hash of run {}", run.id);
+                    return db.save_run_if_version(&sampled).await;
+                }
+            }
+        }
+        db.save_run_if_version(run).await
+    }
+
+    /// Apply a change to a cached run and write it, unless another writer got there first (async)
+    ///
+    /// Retries like `StateManager::write_run`, and leaves the cached run as written.
+    /// Returns false when `change` left the run alone.
+    async fn write_run(&self, cached: &mut WorkflowRun, mut change: impl FnMut(&mut WorkflowRun) -> bool) -> CoreResult<bool> {
+        let mut run = cached.clone();
+        for _ in 0..RUN_WRITE_ATTEMPTS {
+            if !change(&mut run) {
+                return Ok(false);
+            }
+            if Self::persist_run(&self.db, &run).await? {
+                run.version += 1;
+                *cached = run;
+                return Ok(true);
+            }
+            log::debug!("Run {} was written concurrently, updating it again", run.id);
+            run = self.db.get_run(run.id.to_string()).await?
+                .ok_or_else(|| CoreError::RunNotFound(cached.id.to_string()))?;
+        }
+        Err(CoreError::Conflict(format!(
+            "Run {} kept being written concurrently, gave up after {} attempts", cached.id, RUN_WRITE_ATTEMPTS
+        )))
+    }
+
     /// Complete a run with final status (async)
     pub async fn complete_run(&self, run_id: &Uuid, status: RunStatus, error: Option<String>) -> CoreResult<()> {
         let mut active_runs = self.active_runs.lock().await;
         if let Some(run) = active_runs.get_mut(run_id) {
-            run.status = status.clone();
-            run.completed_at = Some(Utc::now());
-            run.error = error;
-            
-            self.db.save_run(run).await?;
+            self.write_run(run, |run| {
+                run.status = status.clone();
+                run.completed_at = Some(Utc::now());
+                run.error = error.clone();
+                true
+            }).await?;
+            let detail = serde_json::json!({"error": run.error});
+            self.record_run_event(run_id, RunEvent::for_run_status(&status), None, RunEvent::ACTOR_ENGINE, detail).await?;
             log::info!("Completed run {} with status {:?}", run_id, status);
         }
         
         Ok(())
     }
-} 
\ No newline at end of file
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{StepDefinition, StepStatus};
+    use crate::job::{JobPriority, JobState};
+
+    fn test_step(id: &str) -> StepDefinition {
+        StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: "test_action".to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: vec![],
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        }
+    }
+
+    fn setup(dir: &tempfile::TempDir) -> (StateManager, Uuid) {
+        let mut state_manager = StateManager::new(dir.path().join("state.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "batch-workflow".to_string(),
+            name: "Batch Workflow".to_string(),
+            description: None,
+            steps: vec![test_step("a"), test_step("b")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        (state_manager, run_id)
+    }
+
+    fn completion(run_id: &Uuid, step_id: &str) -> StepCompletion {
+        StepCompletion {
+            run_id: run_id.to_string(),
+            result: StepResult {
+                step_id: step_id.to_string(),
+                status: StepStatus::Completed,
+                output: Some(serde_json::json!({"ok": true})),
+                error: None,
+                started_at: Utc::now(),
+                completed_at: Some(Utc::now()),
+                duration_ms: Some(1),
+                cache_hit: None,
+                resources: None,
+            },
+            context_checksum: None,
+            context_schema_version: None,
+        }
+    }
+
+    #[test]
+    fn test_complete_steps_batch_completes_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+
+        let summary = state_manager.complete_steps_batch(vec![
+            completion(&run_id, "a"),
+            completion(&run_id, "b"),
+        ]).unwrap();
+
+        assert_eq!(summary.persisted_steps, 2);
+        assert_eq!(summary.runs.get(&run_id.to_string()), Some(&RunStatus::Completed));
+        assert_eq!(state_manager.get_completed_steps(&run_id).unwrap().len(), 2);
+        assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Completed);
+    }
+
+    #[test]
+    fn test_complete_run_directive_skips_remaining_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+
+        let mut early_exit = completion(&run_id, "a");
+        early_exit.result.output = Some(serde_json::json!({"__control": "complete_run", "reason": "nothing to do"}));
+        assert_eq!(early_exit.result.control(), Some(StepControl::CompleteRun));
+        let summary = state_manager.complete_steps_batch(vec![early_exit]).unwrap();
+
+        assert_eq!(summary.persisted_steps, 2);
+        assert_eq!(summary.runs.get(&run_id.to_string()), Some(&RunStatus::Completed));
+        let results = state_manager.get_completed_steps(&run_id).unwrap();
+        assert!(results.iter().any(|r| r.step_id == "b" && r.status == StepStatus::Skipped));
+        let explanation = state_manager.explain_run(&run_id).unwrap();
+        assert!(serde_json::to_string(&explanation).unwrap().contains("completed the run early"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+        state_manager.save_step_result(&run_id, completion(&run_id, "a").result).unwrap();
+        assert_eq!(state_manager.complete_run_early(&run_id, "a").unwrap(), 1);
+        assert_eq!(state_manager.complete_run_early(&run_id, "a").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_run_updates_retry_instead_of_overwriting_concurrent_writes() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+        let other = StateManager::new(dir.path().join("state.db").to_str().unwrap()).unwrap();
+        let cached = state_manager.get_run(&run_id).unwrap().unwrap();
+
+        // Another writer changes the run behind the cached copy
+        let mut patched = other.get_run(&run_id).unwrap().unwrap();
+        patched.payload = serde_json::json!({"patched": true});
+        other.store().save_run(&patched).unwrap();
+        assert!(!state_manager.store().save_run_if_version(&cached).unwrap());
+
+        // The update is made again on the stored run rather than clobbering the payload
+        state_manager.update_run_status(&run_id, RunStatus::Running).unwrap();
+        let stored = other.get_run(&run_id).unwrap().unwrap();
+        assert_eq!((stored.status.clone(), stored.payload.clone(), stored.version), (RunStatus::Running, serde_json::json!({"patched": true}), 2));
+        assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().version, 2);
+
+        // A batch computed from a stale run is computed again
+        other.store().save_run(&stored).unwrap();
+        let summary = state_manager.complete_steps_batch(vec![completion(&run_id, "a"), completion(&run_id, "b")]).unwrap();
+        assert_eq!(summary.runs.get(&run_id.to_string()), Some(&RunStatus::Completed));
+        let stored = other.get_run(&run_id).unwrap().unwrap();
+        assert_eq!((stored.status, stored.version), (RunStatus::Completed, 4));
+    }
+
+    #[test]
+    fn test_complete_steps_batch_rejects_whole_batch_on_invalid_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+
+        let result = state_manager.complete_steps_batch(vec![
+            completion(&run_id, "a"),
+            completion(&run_id, "missing"),
+        ]);
+
+        assert!(result.is_err());
+        assert!(state_manager.get_completed_steps(&run_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_runs_filters_and_paginates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, first_run) = setup(&dir);
+        state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        state_manager.complete_steps_batch(vec![
+            completion(&first_run, "a"),
+            completion(&first_run, "b"),
+        ]).unwrap();
+
+        let page = state_manager.list_runs(&RunQuery {
+            workflow_id: Some("batch-workflow".to_string()),
+            limit: Some(2),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(page.runs.len(), 2);
+
+        let page = state_manager.list_runs(&RunQuery {
+            limit: Some(2),
+            offset: Some(2),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.runs.len(), 1);
+
+        let page = state_manager.list_runs(&RunQuery {
+            status: Some(RunStatus::Completed),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.runs[0].run.id, first_run);
+
+        let page = state_manager.list_runs(&RunQuery {
+            workflow_id: Some("other-workflow".to_string()),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.total, 0);
+    }
+
+    #[test]
+    fn test_pin_run_visible_in_listing_and_audited() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+
+        assert!(state_manager.pin_run(&run_id, "  ", None).is_err());
+        assert!(state_manager.pin_run(&Uuid::new_v4(), "evidence", None).is_err());
+
+        state_manager.pin_run(&run_id, "incident evidence", Some("alice")).unwrap();
+        let page = state_manager.list_runs(&RunQuery {
+            pinned: Some(true),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.total, 1);
+        let pin = page.runs[0].pin.as_ref().unwrap();
+        assert_eq!(pin.reason, "incident evidence");
+        assert_eq!(pin.pinned_by.as_deref(), Some("alice"));
+
+        assert!(state_manager.unpin_run(&run_id, Some("bob")).unwrap());
+        assert!(!state_manager.unpin_run(&run_id, Some("bob")).unwrap());
+        let page = state_manager.list_runs(&RunQuery::default()).unwrap();
+        assert!(page.runs[0].pin.is_none());
+
+        let audit = state_manager.get_run_pin_audit(&run_id).unwrap();
+        assert_eq!(audit.len(), 2);
+        assert_eq!(audit[0].action, "pin");
+        assert_eq!(audit[0].actor.as_deref(), Some("alice"));
+        assert_eq!(audit[1].action, "unpin");
+        assert_eq!(audit[1].actor.as_deref(), Some("bob"));
+    }
+
+    #[test]
+    fn test_runs_are_labelled_and_listed_by_label() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+        let other_run = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        let labels = |pairs: &[(&str, &str)]| pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect::<RunLabels>();
+
+        state_manager.set_run_labels(&run_id, &labels(&[("customer", "acme"), ("env", "staging")])).unwrap();
+        let merged = state_manager.set_run_labels(&run_id, &labels(&[("env", "prod")])).unwrap();
+        assert_eq!(merged, labels(&[("customer", "acme"), ("env", "prod")]));
+        state_manager.set_run_labels(&other_run, &labels(&[("customer", "globex"), ("env", "prod")])).unwrap();
+        assert!(state_manager.set_run_labels(&run_id, &labels(&[("bad key", "x")])).is_err());
+        assert!(state_manager.set_run_labels(&Uuid::new_v4(), &labels(&[("env", "prod")])).is_err());
+
+        let page = state_manager.list_runs_by_label("customer", "acme", None, None).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!((page.runs[0].run.id, &page.runs[0].labels), (run_id, &merged));
+        let page = state_manager.list_runs(&RunQuery {
+            labels: labels(&[("env", "prod"), ("customer", "globex")]),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(page.runs.iter().map(|entry| entry.run.id).collect::<Vec<_>>(), vec![other_run]);
+        assert_eq!(state_manager.list_runs_by_label("env", "prod", None, None).unwrap().total, 2);
+
+        assert!(state_manager.remove_run_label(&run_id, "env").unwrap());
+        assert!(!state_manager.remove_run_label(&run_id, "env").unwrap());
+        assert_eq!(state_manager.get_run_labels(&run_id).unwrap(), labels(&[("customer", "acme")]));
+        assert_eq!(state_manager.list_runs_by_label("env", "prod", None, None).unwrap().total, 1);
+    }
+
+    #[test]
+    fn test_explain_run_uses_recorded_trace_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+
+        assert!(state_manager.explain_run(&Uuid::new_v4()).is_err());
+
+        state_manager.save_step_result(&run_id, completion(&run_id, "a").result).unwrap();
+        state_manager.record_step_trace_event(
+            &run_id,
+            "b",
+            crate::explainer::EVENT_CONDITION_EVALUATED,
+            serde_json::json!({"expression": "steps.a.ok", "met": false}),
+        ).unwrap();
+
+        let explanation = state_manager.explain_run(&run_id).unwrap();
+        assert_eq!(explanation.steps[0].outcome, "completed");
+        assert_eq!(explanation.steps[1].events.len(), 1);
+        assert!(explanation.narrative[2].contains("condition `steps.a.ok` evaluated to false"));
+    }
+
+    #[test]
+    fn test_run_timeline_records_transitions_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+
+        state_manager.update_run_status(&run_id, RunStatus::Running).unwrap();
+        state_manager.update_run_status(&run_id, RunStatus::Running).unwrap();
+        state_manager.save_step_result(&run_id, completion(&run_id, "a").result).unwrap();
+        state_manager.record_run_event(&run_id, RunEvent::HOOK_FIRED, None, RunEvent::ACTOR_API, serde_json::json!({"hook_type": "onSuccess"})).unwrap();
+        assert!(state_manager.cancel_run(&run_id, "stopped by test").unwrap());
+
+        let timeline = state_manager.get_run_timeline(&run_id).unwrap();
+        let events: Vec<&str> = timeline.iter().map(|e| e.event_type.as_str()).collect();
+        assert_eq!(events, vec!["run_created", "run_started", "step_completed", "hook_fired", "run_cancelled"]);
+        assert_eq!(timeline[2].step_id.as_deref(), Some("a"));
+        assert_eq!(timeline[3].actor, RunEvent::ACTOR_API);
+        assert_eq!(timeline[4].detail["reason"], "stopped by test");
+
+        assert!(state_manager.get_run_timeline(&Uuid::new_v4()).is_err());
+    }
+
+    #[test]
+    fn test_run_recovery_requires_confirmation_for_non_idempotent_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+
+        state_manager.record_run_event(&run_id, RunEvent::STEP_STARTED, Some("a"), "worker-1", serde_json::json!({})).unwrap();
+        let plan = state_manager.plan_run_recovery(&run_id, false, NonIdempotentRecovery::Confirm).unwrap();
+        assert_eq!(plan.steps.len(), 1);
+        assert!(!plan.is_ready());
+
+        state_manager.confirm_step_recovery(&run_id, "a", "operator").unwrap();
+        let plan = state_manager.plan_run_recovery(&run_id, false, NonIdempotentRecovery::Confirm).unwrap();
+        assert!(plan.is_ready());
+
+        assert!(state_manager.confirm_step_recovery(&run_id, "missing", "operator").is_err());
+    }
+
+    #[test]
+    fn test_step_results_must_echo_latest_context_checksum() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+        let run = state_manager.get_run(&run_id).unwrap().unwrap();
+
+        // Steps without an issued context are accepted as before
+        state_manager.verify_step_context(&completion(&run_id, "b")).unwrap();
+
+        let mut context = Context::new(run_id.to_string(), run.workflow_id.clone(), "a".to_string(), run.payload.clone(), run.clone(), vec![]).unwrap();
+        let first = state_manager.record_issued_context(&context).unwrap();
+        context.metadata.retry_count = 1;
+        let second = state_manager.record_issued_context(&context).unwrap();
+        assert_ne!(first, second);
+
+        let mut submission = completion(&run_id, "a");
+        let missing = state_manager.complete_steps_batch(vec![submission.clone()]).unwrap_err();
+        assert!(matches!(missing, CoreError::ContextMismatch(_)));
+
+        submission.context_checksum = Some(first);
+        let stale = state_manager.complete_steps_batch(vec![submission.clone()]).unwrap_err();
+        assert!(stale.to_string().contains("attempt 1 is current"));
+
+        submission.context_checksum = Some(second);
+        submission.context_schema_version = Some(crate::context::CONTEXT_SCHEMA_VERSION + 1);
+        assert!(matches!(state_manager.verify_step_context(&submission), Err(CoreError::ContextMismatch(_))));
+
+        submission.context_schema_version = Some(crate::context::CONTEXT_SCHEMA_VERSION);
+        assert_eq!(state_manager.complete_steps_batch(vec![submission]).unwrap().persisted_steps, 1);
+    }
+
+    #[test]
+    fn test_workflow_json_strict_and_lenient_registration() {
+        let json = serde_json::json!({
+            "id": "typo-workflow",
+            "name": "Typo Workflow",
+            "steps": [{"id": "a", "name": "a", "action": "a", "depends_on": [], "is_control_flow": false, "dependsOn": ["b"], "retry": null}],
+            "triggers": ["Manual"],
+            "created_at": Utc::now(),
+            "updated_at": Utc::now(),
+            "timeOut": 5000
+        }).to_string();
+
+        let (workflow, report) = WorkflowDefinition::from_json(&json, false).unwrap();
+        assert_eq!(report.ignored_fields, vec!["steps[0].dependsOn".to_string(), "timeOut".to_string()]);
+        assert!(workflow.steps[0].depends_on.is_empty());
+        assert!(!report.ignored_fields.iter().any(|field| field.ends_with("retry")));
+
+        let err = WorkflowDefinition::from_json(&json, true).unwrap_err();
+        assert!(err.to_string().contains("steps[0].dependsOn"));
+
+        let mut clean: serde_json::Value = serde_json::from_str(&json).unwrap();
+        clean.as_object_mut().unwrap().remove("timeOut");
+        clean["steps"][0].as_object_mut().unwrap().remove("dependsOn");
+        let (_, report) = WorkflowDefinition::from_json(&clean.to_string(), true).unwrap();
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_workflow_json_empty_defaults_are_not_reported_as_unknown() {
+        let json = serde_json::json!({
+            "id": "empty-defaults",
+            "name": "Empty Defaults",
+            "steps": [{
+                "id": "a", "name": "a", "action": "a", "depends_on": [], "is_control_flow": false,
+                "worker_tags": [],
+                "retry": {"max_attempts": 3, "backoff_ms": 10, "no_retry_on": []}
+            }],
+            "triggers": [
+                "Manual",
+                {"Event": {"event_name": "order.created", "versions": []}},
+                {"Polling": {"url": "http://localhost/status", "every_seconds": 60, "headers": {}}}
+            ],
+            "created_at": Utc::now(),
+            "updated_at": Utc::now()
+        });
+
+        let (_, report) = WorkflowDefinition::from_json(&json.to_string(), true).unwrap();
+        assert!(report.is_clean(), "unexpected ignored fields: {:?}", report.ignored_fields);
+
+        let mut typo = json.clone();
+        typo["triggers"][1]["Event"]["version"] = serde_json::json!([2]);
+        let (_, report) = WorkflowDefinition::from_json(&typo.to_string(), false).unwrap();
+        assert_eq!(report.ignored_fields, vec!["triggers[1].Event.version".to_string()]);
+    }
+
+    #[test]
+    fn test_context_state_get_set_incr_with_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+        let run_id = run_id.to_string();
+
+        assert_eq!(state_manager.state_get(StateScope::Run, &run_id, "seen").unwrap(), None);
+        assert_eq!(state_manager.state_incr(StateScope::Run, &run_id, "seen", 1, None).unwrap(), 1);
+        assert_eq!(state_manager.state_incr(StateScope::Run, &run_id, "seen", 4, None).unwrap(), 5);
+
+        state_manager.state_set(StateScope::Workflow, "batch-workflow", "flag", serde_json::json!(true), None).unwrap();
+        state_manager.state_set(StateScope::Workflow, "batch-workflow", "token", serde_json::json!("abc"), Some(0)).unwrap();
+        assert_eq!(state_manager.state_get(StateScope::Workflow, "batch-workflow", "token").unwrap(), None);
+        assert!(state_manager.state_incr(StateScope::Workflow, "batch-workflow", "flag", 1, None).is_err());
+
+        let snapshot = state_manager.context_state(&run_id, "batch-workflow").unwrap();
+        assert_eq!(snapshot.run.get("seen"), Some(&serde_json::json!(5)));
+        assert_eq!(snapshot.workflow.get("flag"), Some(&serde_json::json!(true)));
+        assert!(!snapshot.workflow.contains_key("token"));
+    }
+
+    #[test]
+    fn test_payload_sampling_hashes_successful_runs_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("sampling.db");
+        let mut state_manager = StateManager::new(db_path.to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "high-volume".to_string(),
+            name: "High Volume".to_string(),
+            description: None,
+            steps: vec![test_step("a")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: Some(0.0),
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let payload = serde_json::json!({"order": 42});
+        let succeeded = state_manager.create_run("high-volume", payload.clone()).unwrap();
+        let failed = state_manager.create_run("high-volume", payload.clone()).unwrap();
+        state_manager.complete_run(&succeeded, RunStatus::Completed, None).unwrap();
+        state_manager.complete_run(&failed, RunStatus::Failed, Some("boom".to_string())).unwrap();
+
+        let reopened = StateManager::new(db_path.to_str().unwrap()).unwrap();
+        let stored = reopened.get_run(&succeeded).unwrap().unwrap();
+        assert!(payload_sampling::is_sampled_out(&stored.payload));
+        assert_eq!(stored.payload, payload_sampling::payload_digest(&payload));
+        assert_eq!(reopened.get_run(&failed).unwrap().unwrap().payload, payload);
+    }
+
+    #[test]
+    fn test_large_step_outputs_are_offloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("offload.db").to_str().unwrap()).unwrap();
+        state_manager.output_limits = OutputLimits { max_bytes: 10_000, offload_bytes: 1_000 };
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "api".to_string(),
+            name: "API".to_string(),
+            description: None,
+            steps: vec![test_step("a"), test_step("b")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("api", serde_json::json!({})).unwrap();
+        let step_result = |step_id: &str, output: serde_json::Value| StepResult {
+            step_id: step_id.to_string(),
+            status: StepStatus::Completed,
+            output: Some(output),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(1),
+            cache_hit: None,
+            resources: None,
+        };
+
+        let response = serde_json::json!({"body": "x".repeat(5_000)});
+        state_manager.save_step_result(&run_id, step_result("a", response.clone())).unwrap();
+        let err = state_manager.save_step_result(&run_id, step_result("b", serde_json::json!({"body": "x".repeat(20_000)})));
+        assert!(matches!(err, Err(CoreError::Validation(_))));
+
+        let stored = state_manager.get_completed_steps(&run_id).unwrap();
+        assert_eq!(stored.len(), 1);
+        assert!(payload_offload::blob_id(stored[0].output.as_ref().unwrap()).is_some());
+        let resolved = state_manager.get_completed_steps_with_outputs(&run_id).unwrap();
+        assert_eq!(resolved[0].output, Some(response));
+    }
+
+    #[test]
+    fn test_runs_are_addressable_by_correlation_id() {
+        struct FixedRunIds(Uuid);
+        impl RunIdGenerator for FixedRunIds {
+            fn next_run_id(&self, _workflow_id: &str) -> Uuid {
+                self.0
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, first) = setup(&dir);
+        assert_eq!(state_manager.get_run_correlation_id(&first).unwrap(), None);
+
+        let ordered = state_manager.create_correlated_run("batch-workflow", serde_json::json!({}), RunEvent::ACTOR_API, Some("order-42")).unwrap();
+        let found = state_manager.get_run_by_correlation_id("batch-workflow", "order-42").unwrap().unwrap();
+        assert_eq!(found.id, ordered);
+        assert_eq!(state_manager.get_run_correlation_id(&ordered).unwrap().as_deref(), Some("order-42"));
+        assert_eq!(state_manager.get_run_timeline(&ordered).unwrap()[0].detail["correlation_id"], "order-42");
+        assert!(state_manager.get_run_by_correlation_id("other-workflow", "order-42").unwrap().is_none());
+
+        let reused = state_manager.create_correlated_run("batch-workflow", serde_json::json!({}), RunEvent::ACTOR_API, Some("order-42"));
+        assert!(matches!(reused, Err(CoreError::DuplicateCorrelationId(_))));
+
+        let fixed = Uuid::new_v4();
+        state_manager.set_run_id_generator(Box::new(FixedRunIds(fixed)));
+        assert_eq!(state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap(), fixed);
+    }
+
+    #[test]
+    fn test_adaptive_timeout_learns_from_recent_durations() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("state.db").to_str().unwrap()).unwrap();
+        let mut step = test_step("fetch");
+        step.timeout = Some(60_000);
+        step.adaptive_timeout = Some(AdaptiveTimeout { percentile: 99.0, factor: 2.0, min_ms: 500, max_ms: 10_000, min_samples: 5 });
+        assert!(step.validate().is_ok());
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "adaptive".to_string(),
+            name: "Adaptive".to_string(),
+            description: None,
+            steps: vec![step.clone()],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("adaptive", serde_json::json!({})).unwrap();
+        let result = |status: StepStatus, duration_ms: u64| StepResult {
+            step_id: "fetch".to_string(),
+            status,
+            output: None,
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(duration_ms),
+            cache_hit: None,
+            resources: None,
+        };
+
+        // The static timeout applies until enough durations are known
+        for duration_ms in [100, 200, 300, 400] {
+            state_manager.save_step_result(&run_id, result(StepStatus::Completed, duration_ms)).unwrap();
+        }
+        state_manager.save_step_result(&run_id, result(StepStatus::Failed, 50_000)).unwrap();
+        assert_eq!(state_manager.step_timeout_ms("adaptive", "fetch").unwrap(), Some(60_000));
+
+        state_manager.save_step_result(&run_id, result(StepStatus::Completed, 1_000)).unwrap();
+        let stats = state_manager.get_step_duration_stats("adaptive", "fetch").unwrap();
+        assert_eq!((stats.samples, stats.p50_ms, stats.p99_ms), (5, Some(300), Some(1_000)));
+        assert_eq!(stats.timeout_ms, Some(2_000));
+        assert_eq!(state_manager.step_timeout_ms("adaptive", "fetch").unwrap(), Some(2_000));
+
+        // Learned timeouts stay within the configured bounds
+        state_manager.save_step_result(&run_id, result(StepStatus::Completed, 20_000)).unwrap();
+        assert_eq!(state_manager.step_timeout_ms("adaptive", "fetch").unwrap(), Some(10_000));
+
+        step.adaptive_timeout = Some(AdaptiveTimeout { min_ms: 20_000, ..step.adaptive_timeout.unwrap() });
+        assert!(step.validate().is_err());
+    }
+
+    #[test]
+    fn test_runs_keep_the_workflow_version_they_started_on() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, first_run) = setup(&dir);
+        let mut workflow = state_manager.get_workflow("batch-workflow").unwrap().unwrap();
+
+        workflow.updated_at = Utc::now() + chrono::Duration::seconds(5);
+        state_manager.register_workflow(workflow.clone()).unwrap();
+        assert_eq!(state_manager.list_workflow_versions("batch-workflow").unwrap().len(), 1);
+
+        workflow.steps.push(test_step("c"));
+        state_manager.register_workflow(workflow).unwrap();
+        let versions = state_manager.list_workflow_versions("batch-workflow").unwrap();
+        assert_eq!(versions.iter().map(|v| v.version).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(state_manager.get_workflow_version("batch-workflow", 2).unwrap().unwrap().definition.steps.len(), 3);
+        assert!(state_manager.get_workflow_version("batch-workflow", 3).unwrap().is_none());
+
+        let second_run = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        assert_eq!(state_manager.get_run_workflow_version(&first_run).unwrap(), Some(1));
+        assert_eq!(state_manager.get_run_workflow_version(&second_run).unwrap(), Some(2));
+        assert_eq!(state_manager.get_run_timeline(&second_run).unwrap()[0].detail["workflow_version"], 2);
+        assert_eq!(state_manager.get_run_workflow("batch-workflow", &first_run).unwrap().unwrap().steps.len(), 2);
+
+        // The in-flight run does not pick up the step added after it started
+        let added_step = state_manager.complete_steps_batch(vec![completion(&first_run, "c")]);
+        assert!(matches!(added_step, Err(CoreError::StepNotFound(_))));
+        state_manager.complete_steps_batch(vec![completion(&second_run, "c")]).unwrap();
+    }
+
+    #[test]
+    fn test_batches_commit_together_or_not_at_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, _) = setup(&dir);
+        let workflow = |id: &str, triggers: Vec<TriggerDefinition>| WorkflowDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            steps: vec![test_step("a")],
+            triggers,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        assert_eq!(state_manager.register_workflows(vec![workflow("first", vec![]), workflow("second", vec![])]).unwrap(), 2);
+        assert!(state_manager.get_workflow("second").unwrap().is_some());
+
+        state_manager.register_event_schema("order.created", 1, serde_json::json!({"type": "object"})).unwrap();
+        let subscriber = workflow("subscriber", vec![TriggerDefinition::Event { event_name: "order.created".to_string(), versions: vec![9], dedup: None }]);
+        assert!(state_manager.register_workflows(vec![workflow("third", vec![]), subscriber]).is_err());
+        assert!(state_manager.get_workflow("third").unwrap().is_none());
+
+        let request = |workflow_id: &str, correlation_id: &str| RunRequest {
+            workflow_id: workflow_id.to_string(),
+            payload: serde_json::json!({"n": 1}),
+            correlation_id: Some(correlation_id.to_string()),
+        };
+        let run_ids = state_manager.create_runs_batch(vec![request("first", "a-1"), request("second", "b-1")], RunEvent::ACTOR_API).unwrap();
+        assert_eq!(state_manager.get_run(&run_ids[1]).unwrap().unwrap().workflow_id, "second");
+
+        let failed = state_manager.create_runs_batch(vec![request("first", "a-2"), request("missing", "m-1")], RunEvent::ACTOR_API);
+        assert!(matches!(failed, Err(CoreError::WorkflowNotFound(_))));
+        assert_eq!(state_manager.get_unfinished_runs("first").unwrap(), vec![run_ids[0]]);
+        assert!(state_manager.get_run_by_correlation_id("first", "a-2").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_purge_deletes_finished_unpinned_runs_with_their_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, _) = setup(&dir);
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "purged".to_string(),
+            name: "Purged".to_string(),
+            description: None,
+            steps: vec![test_step("a")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+
+        let finished = state_manager.create_run("purged", serde_json::json!({})).unwrap();
+        let pinned = state_manager.create_run("purged", serde_json::json!({})).unwrap();
+        let running = state_manager.create_run("purged", serde_json::json!({})).unwrap();
+        for run_id in [&finished, &pinned] {
+            state_manager.save_step_result(run_id, StepResult {
+                step_id: "a".to_string(),
+                status: StepStatus::Completed,
+                output: Some(serde_json::json!({"ok": true})),
+                error: None,
+                started_at: Utc::now(),
+                completed_at: Some(Utc::now()),
+                duration_ms: Some(1),
+                cache_hit: None,
+                resources: None,
+            }).unwrap();
+            state_manager.complete_run(run_id, RunStatus::Completed, None).unwrap();
+        }
+        state_manager.pin_run(&pinned, "incident", None).unwrap();
+
+        let summary = state_manager.purge_runs(Utc::now() + chrono::Duration::seconds(1)).unwrap();
+        assert_eq!(summary, RunPurgeSummary { runs_deleted: 1, pinned_kept: 1 });
+        assert!(state_manager.get_run(&finished).unwrap().is_none());
+        assert!(state_manager.get_completed_steps(&finished).unwrap().is_empty());
+        assert!(!state_manager.db.get_run_events(&pinned.to_string()).unwrap().is_empty());
+        assert!(state_manager.db.get_run_events(&finished.to_string()).unwrap().is_empty());
+        assert_eq!(state_manager.get_completed_steps(&pinned).unwrap().len(), 1);
+        assert!(state_manager.get_run(&running).unwrap().is_some());
+
+        let config = RetentionConfig { max_runs_per_workflow: Some(1), ..RetentionConfig::default() };
+        assert_eq!(state_manager.apply_retention(&config, Utc::now()).unwrap(), RunPurgeSummary::default());
+        let config = RetentionConfig { max_run_age_days: Some(0), ..RetentionConfig::default() };
+        let summary = state_manager.apply_retention(&config, Utc::now() + chrono::Duration::seconds(1)).unwrap();
+        assert_eq!(summary, RunPurgeSummary { runs_deleted: 0, pinned_kept: 1 });
+        assert!(state_manager.get_run(&pinned).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_orphaned_runs_are_requeued_or_failed_per_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, _) = setup(&dir);
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "orphans".to_string(),
+            name: "Orphans".to_string(),
+            description: None,
+            steps: vec![StepDefinition { idempotent: true, ..test_step("fetch") }, test_step("charge")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let start = |state_manager: &mut StateManager, step_id: &str| {
+            let run_id = state_manager.create_run("orphans", serde_json::json!({})).unwrap();
+            state_manager.update_run_status(&run_id, RunStatus::Running).unwrap();
+            state_manager.record_run_event(&run_id, RunEvent::STEP_STARTED, Some(step_id), "worker-0", serde_json::json!({})).unwrap();
+            run_id
+        };
+        let fetching = start(&mut state_manager, "fetch");
+        let charging = start(&mut state_manager, "charge");
+
+        let recover = |state_manager: &mut StateManager, policy: OrphanRunPolicy, lease_secs: i64| {
+            let report = state_manager.recover_orphaned_runs(policy, NonIdempotentRecovery::Confirm, chrono::Duration::seconds(lease_secs), Utc::now()).unwrap();
+            report.runs.into_iter().map(|run| (run.run_id, run.outcome)).collect::<HashMap<_, _>>()
+        };
+        assert!(recover(&mut state_manager, OrphanRunPolicy::Requeue, 60).is_empty());
+
+        let outcomes = recover(&mut state_manager, OrphanRunPolicy::Requeue, 0);
+        assert_eq!(outcomes[&fetching.to_string()], OrphanRunOutcome::Requeued);
+        assert_eq!(outcomes[&charging.to_string()], OrphanRunOutcome::AwaitingConfirmation);
+        assert_eq!(state_manager.get_run(&charging).unwrap().unwrap().status, RunStatus::Running);
+
+        let outcomes = recover(&mut state_manager, OrphanRunPolicy::Fail, 0);
+        assert_eq!(outcomes[&charging.to_string()], OrphanRunOutcome::Failed);
+        assert_eq!(state_manager.get_run(&charging).unwrap().unwrap().status, RunStatus::Failed);
+        let steps = state_manager.get_completed_steps(&charging).unwrap();
+        assert_eq!((steps[0].step_id.as_str(), &steps[0].status), ("charge", &StepStatus::Failed));
+        assert!(recover(&mut state_manager, OrphanRunPolicy::Fail, 0).is_empty());
+    }
+
+    #[test]
+    fn test_step_logs_are_read_after_a_sequence_number() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, _) = setup(&dir);
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "logged".to_string(),
+            name: "Logged".to_string(),
+            description: None,
+            steps: vec![test_step("fetch"), test_step("store")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("logged", serde_json::json!({})).unwrap();
+        
+        let first = state_manager.append_step_log(&run_id, "fetch", StepLogLevel::Info, "fetching page 1", serde_json::json!({"page": 1})).unwrap();
+        state_manager.append_step_log(&run_id, "store", StepLogLevel::Debug, "storing", serde_json::json!({})).unwrap();
+        let second = state_manager.append_step_log(&run_id, "fetch", StepLogLevel::Warn, "page 2 was slow", serde_json::json!({"ms": 900})).unwrap();
+        assert!(state_manager.append_step_log(&run_id, "missing", StepLogLevel::Info, "nope", serde_json::json!({})).is_err());
+        
+        let logs = state_manager.get_step_logs(&run_id, "fetch", 0).unwrap();
+        assert_eq!(logs.iter().map(|entry| entry.seq).collect::<Vec<_>>(), vec![first.seq, second.seq]);
+        assert_eq!(logs[1].level, StepLogLevel::Warn);
+        assert_eq!(logs[1].fields["ms"], 900);
+        
+        let newer = state_manager.get_step_logs(&run_id, "fetch", first.seq).unwrap();
+        assert_eq!(newer.len(), 1);
+        assert_eq!(newer[0].message, "page 2 was slow");
+        assert!(state_manager.get_step_logs(&run_id, "fetch", second.seq).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_runs_past_their_run_timeout_fail_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, untimed_run) = setup(&dir);
+        let mut workflow = state_manager.get_workflow("batch-workflow").unwrap().unwrap();
+        workflow.run_timeout_ms = Some(60_000);
+        workflow.updated_at = Utc::now() + chrono::Duration::seconds(5);
+        state_manager.register_workflow(workflow).unwrap();
+        let run_id = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        state_manager.complete_steps_batch(vec![completion(&run_id, "a")]).unwrap();
+
+        assert!(state_manager.timed_out_runs(Utc::now()).unwrap().is_empty());
+        let later = Utc::now() + chrono::Duration::minutes(2);
+        // The first run started on the version without a timeout
+        let timed_out = state_manager.timed_out_runs(later).unwrap();
+        assert_eq!(timed_out.iter().map(|(run, _)| run.id).collect::<Vec<_>>(), vec![run_id]);
+
+        let (run, definition) = &timed_out[0];
+        let context = state_manager.time_out_run(run, definition, later).unwrap().unwrap();
+        assert_eq!(context.status, RunStatus::Failed);
+        assert_eq!(context.error.as_deref(), Some("Run exceeded its timeout of 60000ms"));
+        assert_eq!(context.completed_step_count(), 1);
+        assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Failed);
+        let events: Vec<String> = state_manager.get_run_timeline(&run_id).unwrap().into_iter().map(|event| event.event_type).collect();
+        assert!(events.ends_with(&["run_timed_out".to_string(), "run_failed".to_string()]));
+
+        assert!(state_manager.time_out_run(run, definition, later).unwrap().is_none());
+        assert!(state_manager.timed_out_runs(later).unwrap().is_empty());
+        assert!(!state_manager.get_run(&untimed_run).unwrap().unwrap().status.is_terminal());
+    }
+
+    #[test]
+    fn test_job_leases_exclude_other_owners_until_they_expire() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+        let job = Job::new("batch-workflow".to_string(), run_id.to_string(), "a".to_string(), serde_json::json!({}), JobPriority::Normal);
+        let queued = Job::new("batch-workflow".to_string(), run_id.to_string(), "b".to_string(), serde_json::json!({}), JobPriority::Normal);
+        state_manager.save_job(&job).unwrap();
+        state_manager.save_job(&queued).unwrap();
+        let lease = chrono::Duration::seconds(30);
+
+        assert!(state_manager.claim_job(&job.id, "node-a/worker-0", lease).unwrap());
+        assert!(!state_manager.claim_job(&job.id, "node-b/worker-0", lease).unwrap());
+        let claimable: Vec<String> = state_manager.claimable_jobs(10).unwrap().into_iter().map(|job| job.id).collect();
+        assert_eq!(claimable, vec![queued.id.clone()]);
+
+        // node-a stops renewing, so its lease runs out and node-b reclaims the job
+        assert!(state_manager.renew_job_lease(&job.id, "node-a/worker-0", Utc::now() - chrono::Duration::seconds(1)).unwrap());
+        assert_eq!(state_manager.claimable_jobs(10).unwrap().len(), 2);
+        assert!(state_manager.claim_job(&job.id, "node-b/worker-0", lease).unwrap());
+        assert!(!state_manager.renew_job_lease(&job.id, "node-a/worker-0", Utc::now() + lease).unwrap());
+
+        let mut finished = job.clone();
+        finished.state = JobState::Completed;
+        state_manager.save_job(&finished).unwrap();
+        state_manager.release_job(&job.id, "node-b/worker-0").unwrap();
+        assert!(!state_manager.claim_job(&job.id, "node-a/worker-0", lease).unwrap());
+        assert_eq!(state_manager.claimable_jobs(10).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_runs_are_found_by_payload_and_output_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("search.db").to_str().unwrap()).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "orders".to_string(),
+            name: "Orders".to_string(),
+            description: None,
+            steps: vec![test_step("charge")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let first = state_manager.create_run("orders", serde_json::json!({"order": {"id": 12345, "items": [{"sku": "A-1"}]}})).unwrap();
+        let second = state_manager.create_run("orders", serde_json::json!({"order": {"id": "12345"}})).unwrap();
+        state_manager.create_run("orders", serde_json::json!({"order": {"id": 999}})).unwrap();
+        state_manager.save_step_result(&first, StepResult {
+            step_id: "charge".to_string(),
+            status: StepStatus::Completed,
+            output: Some(serde_json::json!({"charge_id": "ch_42"})),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(1),
+            cache_hit: None,
+            resources: None,
+        }).unwrap();
+
+        // Numbers and strings holding the same text both match
+        let found = state_manager.find_runs("orders", "$.order.id", "12345", None).unwrap();
+        assert_eq!(found.iter().map(|r| r.id).collect::<Vec<_>>(), vec![second, first]);
+        assert_eq!(state_manager.find_runs("orders", "order.items[0].sku", "A-1", None).unwrap()[0].id, first);
+        assert_eq!(state_manager.find_runs("orders", "$.charge_id", "ch_42", None).unwrap()[0].id, first);
+        assert!(state_manager.find_runs("orders", "$.order.id", "1", None).unwrap().is_empty());
+        assert!(matches!(state_manager.find_runs("orders", "$..", "1", None), Err(CoreError::Validation(_))));
+
+        state_manager.db.delete_run_records(&[first.to_string()]).unwrap();
+        assert_eq!(state_manager.find_runs("orders", "$.order.id", "12345", None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_isolated_workflows_keep_runs_in_their_own_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let main_path = dir.path().join("main.db").to_str().unwrap().to_string();
+        let billing_path = dir.path().join("billing.db").to_str().unwrap().to_string();
+        let routes = crate::config::StoreRoutes::parse_list(&format!("namespace:billing={}", billing_path)).unwrap();
+        let open = || {
+            let stores = IsolatedStores::open(&main_path, &routes).unwrap().unwrap();
+            StateManager::with_storage(&main_path, Some(Box::new(stores))).unwrap()
+        };
+        let mut state_manager = open();
+        for (id, namespace) in [("invoices", Some("billing")), ("emails", None)] {
+            state_manager.register_workflow(WorkflowDefinition {
+                id: id.to_string(),
+                name: id.to_string(),
+                description: None,
+                steps: vec![test_step("a")],
+                triggers: vec![],
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                concurrency: None,
+                namespace: namespace.map(str::to_string),
+                payload_sample_rate: None,
+                priority: None,
+                run_timeout_ms: None,
+                run_timeout_outcome: Default::default(),
+                serialize_on: None,
+                resource_quota: None,
+                lane: None,
+                defaults: None,
+            }).unwrap();
+        }
+        let invoice = state_manager.create_run("invoices", serde_json::json!({})).unwrap();
+        let email = state_manager.create_run("emails", serde_json::json!({})).unwrap();
+        state_manager.save_step_result(&invoice, StepResult {
+            step_id: "a".to_string(),
+            status: StepStatus::Completed,
+            output: Some(serde_json::json!({"total": 10})),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(1),
+            cache_hit: None,
+            resources: None,
+        }).unwrap();
+
+        let (main, billing) = (Database::new(&main_path).unwrap(), Database::new(&billing_path).unwrap());
+        assert!(main.get_run(&invoice.to_string()).unwrap().is_none());
+        assert_eq!(billing.get_step_results(&invoice.to_string()).unwrap().len(), 1);
+        assert!(billing.get_run(&email.to_string()).unwrap().is_none());
+        assert!(main.get_workflow("invoices").unwrap().is_some());
+
+        // The registry keeps routing after a restart without the route configured
+        let restarted = StateManager::with_storage(&main_path, IsolatedStores::open(&main_path, &Default::default()).unwrap()
+            .map(|stores| Box::new(stores) as Box<dyn StorageBackend>)).unwrap();
+        assert_eq!(restarted.get_completed_steps(&invoice).unwrap().len(), 1);
+        let page = restarted.list_runs(&RunQuery { workflow_id: None, status: None, since: None, pinned: None, labels: RunLabels::new(), limit: None, offset: None }).unwrap();
+        assert_eq!(page.total, 2);
+        assert_eq!(page.runs.iter().map(|entry| entry.run.id).collect::<Vec<_>>(), vec![email, invoice]);
+
+        // Cold storage only reads the local database, which misses the routed runs
+        let exporter = ColdStorageExporter::new(Default::default()).unwrap();
+        assert!(matches!(restarted.export_cold_storage(&exporter), Err(CoreError::Configuration(_))));
+        assert!(matches!(
+            restarted.export_cold_storage_range(&exporter, Utc::now() - chrono::Duration::days(1), Utc::now()),
+            Err(CoreError::Configuration(_))
+        ));
+    }
+
+    #[test]
+    fn test_runs_are_cancelled_and_retried_in_bulk() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, pending) = setup(&dir);
+        let running = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        state_manager.update_run_status(&running, RunStatus::Running).unwrap();
+        let failed = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        state_manager.save_step_result(&failed, completion(&failed, "a").result).unwrap();
+        state_manager.save_step_result(&failed, StepResult {
+            status: StepStatus::Failed,
+            output: None,
+            error: Some("bad deploy".to_string()),
+            ..completion(&failed, "b").result
+        }).unwrap();
+        state_manager.update_run_status(&failed, RunStatus::Failed).unwrap();
+
+        assert!(state_manager.cancel_runs("batch-workflow", Some(RunStatus::Completed), None, "incident").is_err());
+        let summary = state_manager.cancel_runs("batch-workflow", Some(RunStatus::Running), None, "incident").unwrap();
+        assert_eq!(summary.run_ids, vec![running.to_string()]);
+        let started_at = state_manager.get_run(&pending).unwrap().unwrap().started_at;
+        assert_eq!(state_manager.cancel_runs("batch-workflow", None, Some(started_at), "incident").unwrap().count, 0);
+        assert_eq!(state_manager.get_run(&pending).unwrap().unwrap().status, RunStatus::Pending);
+        assert_eq!(state_manager.get_run(&running).unwrap().unwrap().status, RunStatus::Cancelled);
+
+        let summary = state_manager.retry_failed_runs("batch-workflow", None).unwrap();
+        assert_eq!(summary.run_ids, vec![failed.to_string()]);
+        let run = state_manager.get_run(&failed).unwrap().unwrap();
+        assert_eq!(run.status, RunStatus::Pending);
+        assert!(run.error.is_none() && run.completed_at.is_none());
+        // The failed step runs again while the completed one keeps its result
+        let results = state_manager.get_completed_steps(&failed).unwrap();
+        let latest: Vec<(&str, &StepStatus)> = results.iter().rev().take(2).map(|r| (r.step_id.as_str(), &r.status)).collect();
+        assert_eq!(latest, vec![("b", &StepStatus::Pending), ("b", &StepStatus::Failed)]);
+        assert_eq!(results[0].status, StepStatus::Completed);
+        assert_eq!(state_manager.retry_failed_runs("batch-workflow", None).unwrap().count, 0);
+    }
+
+    #[test]
+    fn test_runs_started_with_a_traceparent_join_the_callers_trace() {
+        let dir = tempfile::tempdir().unwrap();
+        let (state_manager, run_id) = setup(&dir);
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let mut headers = HashMap::new();
+        headers.insert("Traceparent".to_string(), header.to_string());
+        let request = crate::triggers::WebhookRequest::new("POST".to_string(), "/webhook/batch".to_string()).with_headers(headers);
+        let trigger = RunTrigger::new("webhook", Some("/webhook/batch")).with_trace_parent(request.trace_parent());
+        state_manager.record_run_trigger(&run_id, &trigger).unwrap();
+
+        let stored = state_manager.db.get_run_trigger(&run_id.to_string()).unwrap().unwrap();
+        assert_eq!(stored.trace_parent.as_deref(), Some(header));
+        let trace = RunTrace::new(run_id, stored.trace_parent.as_deref());
+        assert_eq!(trace.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(trace.traceparent().starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+        assert!(trace.traceparent().ends_with("-01"));
+
+        // Runs started without a trace context get a trace of their own
+        let own = RunTrace::new(run_id, None);
+        assert_eq!(own.trace_id().to_string(), run_id.simple().to_string());
+        assert_ne!(own.span_id(), opentelemetry::trace::SpanId::INVALID);
+        for malformed in ["", "00-4bf92f3577b34da6a3ce929d0e0e4736-0000000000000000-01", "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01", "garbage"] {
+            assert_eq!(RunTrigger::new("webhook", None).with_trace_parent(Some(malformed)).trace_parent, None);
+        }
+    }
+
+    #[test]
+    fn test_errors_carry_machine_readable_codes_and_details() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = StateManager::new(dir.path().join("codes.db").to_str().unwrap()).unwrap();
+        let missing = state_manager.create_run("ghost", serde_json::json!({})).unwrap_err();
+        assert_eq!(missing.code(), "WORKFLOW_NOT_FOUND");
+        assert_eq!(missing.details()["context"], "ghost");
+
+        let busy = CoreError::Database(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_BUSY), None));
+        assert_eq!(busy.code(), "DB_LOCKED");
+        assert_eq!(busy.details()["sqlite_code"], rusqlite::ffi::SQLITE_BUSY);
+        assert_eq!(CoreError::Conflict("run-1".to_string()).code(), "RUN_CONFLICT");
+        let limited = CoreError::RateLimited { message: "slow down".to_string(), retry_after_ms: 250 };
+        assert_eq!(limited.details(), serde_json::json!({"code": "RATE_LIMITED", "context": "slow down", "retry_after_ms": 250}));
+        let parse = CoreError::from(serde_json::from_str::<serde_json::Value>("{\n  oops").unwrap_err());
+        assert_eq!((parse.code(), parse.details()["line"].as_u64()), ("SERIALIZATION_FAILED", Some(2)));
+    }
+
+    #[test]
+    fn test_restoring_a_snapshot_brings_back_the_state_it_was_taken_at() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, kept_run) = setup(&dir);
+        let snapshot_path = dir.path().join("backups").join("snapshot.db");
+        let snapshot_path = snapshot_path.to_str().unwrap();
+        let snapshot = state_manager.create_snapshot(snapshot_path).unwrap();
+        assert!(snapshot.pages > 0 && snapshot.size_bytes > 0);
+        assert!(matches!(state_manager.create_snapshot(snapshot_path), Err(CoreError::Validation(_))));
+
+        let lost_run = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        assert!(matches!(state_manager.restore_snapshot(snapshot_path), Err(CoreError::InvalidState(_))));
+        state_manager.set_engine_paused(true).unwrap();
+        state_manager.restore_snapshot(snapshot_path).unwrap();
+
+        assert!(state_manager.get_run(&kept_run).unwrap().is_some());
+        assert!(state_manager.get_run(&lost_run).unwrap().is_none());
+        assert!(state_manager.is_engine_paused().unwrap());
+        let other = StateManager::new(dir.path().join("state.db").to_str().unwrap()).unwrap();
+        assert!(other.get_run(&lost_run).unwrap().is_none());
+        assert!(matches!(state_manager.restore_snapshot(dir.path().join("missing.db").to_str().unwrap()), Err(CoreError::Validation(_))));
+    }
+
+    #[test]
+    fn test_cancelled_runs_get_an_on_cancel_context_with_their_interrupted_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_manager, run_id) = setup(&dir);
+        for step_id in ["a", "b"] {
+            state_manager.record_run_event(&run_id, RunEvent::STEP_STARTED, Some(step_id), "worker-1", serde_json::json!({})).unwrap();
+        }
+        state_manager.complete_steps_batch(vec![completion(&run_id, "a")]).unwrap();
+        state_manager.cancel_run(&run_id, "Reservation no longer needed").unwrap();
+
+        let context = state_manager.completion_context(&run_id).unwrap().unwrap();
+        assert_eq!(context.hook_type(), "onCancel");
+        assert_eq!(context.completed_step_count(), 1);
+        assert_eq!(context.cancellation, Some(crate::models::RunCancellation {
+            reason: crate::models::CancelReason::Manual,
+            message: Some("Reservation no longer needed".to_string()),
+            interrupted_steps: vec!["b".to_string()],
+        }));
+
+        let mut workflow = state_manager.get_workflow("batch-workflow").unwrap().unwrap();
+        workflow.run_timeout_ms = Some(1_000);
+        workflow.run_timeout_outcome = crate::models::RunTimeoutOutcome::Cancelled;
+        let timed_out_run = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        let run = state_manager.get_run(&timed_out_run).unwrap().unwrap();
+        let context = state_manager.time_out_run(&run, &workflow, Utc::now()).unwrap().unwrap();
+        assert_eq!(context.hook_type(), "onCancel");
+        assert_eq!(context.cancellation.map(|cancellation| cancellation.reason), Some(crate::models::CancelReason::Timeout));
+        let failed = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        state_manager.complete_run(&failed, RunStatus::Failed, Some("boom".to_string())).unwrap();
+        let context = state_manager.completion_context(&failed).unwrap().unwrap();
+        assert_eq!((context.hook_type(), context.cancellation), ("onFailure", None));
+    }
+}
@@ -0,0 +1,201 @@
+//! Step input mappings rendered by the core
+//!
+//! A step's `input` is a JSON value whose strings may hold `{{ path }}`
+//! placeholders. Before the step executes, the mapping is rendered against the
+//! step's context and handed to the step as `ctx.input`, so steps get the data
+//! they need without glue code for each mapping.
+//!
+//! Paths start at `payload`, `steps`, `last`, `state`, `run`, `execution` or
+//! `labels`, optionally prefixed with `ctx.`, and are dot-separated; numeric
+//! segments and `[n]` index arrays, e.g.
+//! `{{ steps.fetch.output.items[0].id }}`. A step is
+//! referenced as `steps.<id>.output`, `steps.<id>.status` or
+//! `steps.<id>.error`, and `last` is the output of the step that completed
+//! most recently. A string that is a single placeholder renders to the
+//! referenced value, keeping its type; placeholders inside longer strings are
+//! interpolated as text. Paths resolving to nothing render as null, or as
+//! nothing inside text.
+
+use serde_json::{json, Map, Value};
+use crate::context::Context;
+use crate::error::{CoreError, CoreResult};
+use crate::triggers::{lookup_json_path, split_json_path};
+
+/// Parts of the context a placeholder path can start at
+pub const ROOTS: [&str; 7] = ["payload", "steps", "last", "state", "run", "execution", "labels"];
+
+/// Check every placeholder of an input mapping is well formed
+pub fn validate(input: &Value) -> Result<(), String> {
+    for_each_string(input, &mut |text| {
+        placeholders(text)?.into_iter().try_for_each(|path| parse_path(path).map(drop))
+    })
+}
+
+/// Render an input mapping against a step's context
+pub fn render(input: &Value, context: &Context) -> CoreResult<Value> {
+    let scope = scope(context);
+    render_value(input, &scope).map_err(|e| CoreError::Validation(format!("Invalid input mapping of step {}: {}", context.step_name, e)))
+}
+
+fn render_value(value: &Value, scope: &Value) -> Result<Value, String> {
+    match value {
+        Value::String(text) => render_string(text, scope),
+        Value::Array(items) => items.iter().map(|item| render_value(item, scope)).collect::<Result<_, _>>().map(Value::Array),
+        Value::Object(fields) => fields.iter()
+            .map(|(name, field)| Ok((name.clone(), render_value(field, scope)?)))
+            .collect::<Result<Map<_, _>, String>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
+
+fn render_string(text: &str, scope: &Value) -> Result<Value, String> {
+    let paths = placeholders(text)?;
+    let trimmed = text.trim();
+    if let [path] = paths.as_slice() {
+        if trimmed.starts_with("{{") && trimmed.find("}}") == Some(trimmed.len() - 2) {
+            return Ok(lookup_json_path(scope, &parse_path(path)?));
+        }
+    }
+    if paths.is_empty() {
+        return Ok(Value::String(text.to_string()));
+    }
+
+    let mut rendered = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}").map(|end| start + end) else {
+            break;
+        };
+        rendered.push_str(&rest[..start]);
+        match lookup_json_path(scope, &parse_path(rest[start + 2..end].trim())?) {
+            Value::Null => {}
+            Value::String(value) => rendered.push_str(&value),
+            value => rendered.push_str(&value.to_string()),
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    Ok(Value::String(rendered))
+}
+
+/// The paths of a string's placeholders
+fn placeholders(text: &str) -> Result<Vec<&str>, String> {
+    let mut paths = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..].find("}}")
+            .ok_or_else(|| format!("unterminated placeholder in {:?}", text))?;
+        paths.push(rest[start + 2..start + end].trim());
+        rest = &rest[start + end + 2..];
+    }
+    Ok(paths)
+}
+
+/// Split a placeholder path into its segments, checking where it starts
+fn parse_path(path: &str) -> Result<Vec<String>, String> {
+    let segments = split_json_path(path.strip_prefix("ctx.").unwrap_or(path))
+        .ok_or_else(|| format!("invalid placeholder path {:?}", path))?;
+    match segments.first() {
+        Some(root) if ROOTS.contains(&root.as_str()) => Ok(segments),
+        _ => Err(format!("placeholder path {} must start at one of {}", path, ROOTS.join(", "))),
+    }
+}
+
+/// The strings of an input mapping that hold placeholders
+pub fn templates(input: &Value) -> Vec<&str> {
+    match input {
+        Value::String(text) if text.contains("{{") => vec![text.as_str()],
+        Value::Array(items) => items.iter().flat_map(templates).collect(),
+        Value::Object(fields) => fields.values().flat_map(templates).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn for_each_string(value: &Value, check: &mut dyn FnMut(&str) -> Result<(), String>) -> Result<(), String> {
+    match value {
+        Value::String(text) => check(text),
+        Value::Array(items) => items.iter().try_for_each(|item| for_each_string(item, check)),
+        Value::Object(fields) => fields.values().try_for_each(|field| for_each_string(field, check)),
+        _ => Ok(()),
+    }
+}
+
+/// The values placeholder paths resolve against
+fn scope(context: &Context) -> Value {
+    let steps: Map<String, Value> = context.steps.iter()
+        .map(|(step_id, result)| (step_id.clone(), json!({
+            "output": result.output,
+            "status": result.status.as_str(),
+            "error": result.error,
+        })))
+        .collect();
+    let last = context.steps.values()
+        .filter(|result| result.output.is_some())
+        .max_by_key(|result| result.completed_at)
+        .and_then(|result| result.output.clone());
+    json!({
+        "payload": context.payload,
+        "steps": steps,
+        "last": last,
+        "state": context.state,
+        "run": {
+            "id": context.run_id,
+            "workflow_id": context.workflow_id,
+            "started_at": context.run.started_at,
+        },
+        "execution": context.execution,
+        "labels": context.labels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use crate::models::{RunStatus, StepResult, StepStatus, WorkflowRun};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_input_mappings_render_typed_values_and_interpolated_text() {
+        let run = WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: "orders".to_string(),
+            status: RunStatus::Running,
+            payload: json!({"order": {"id": 42, "items": [{"sku": "A-1"}]}}),
+            started_at: Utc::now(),
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        let fetched = StepResult {
+            step_id: "fetch".to_string(),
+            status: StepStatus::Completed,
+            output: Some(json!({"total": 99.5})),
+            error: None,
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: Some(5),
+            cache_hit: None,
+            resources: None,
+        };
+        let context = Context::new(run.id.to_string(), "orders".to_string(), "charge".to_string(), run.payload.clone(), run, vec![fetched]).unwrap();
+
+        let input = json!({
+            "orderId": "{{ payload.order.id }}",
+            "prev": "{{ ctx.steps.fetch.output.total }}",
+            "label": "Order {{ payload.order.id }}: {{payload.order.items[0].sku}} ({{ steps.fetch.status }})",
+            "nested": [{"last": "{{ last.total }}", "missing": "{{ payload.coupon }}"}, 7],
+        });
+        assert!(validate(&input).is_ok());
+        assert_eq!(render(&input, &context).unwrap(), json!({
+            "orderId": 42,
+            "prev": 99.5,
+            "label": "Order 42: A-1 (completed)",
+            "nested": [{"last": 99.5, "missing": null}, 7],
+        }));
+
+        assert!(validate(&json!({"id": "{{ order.id }}"})).unwrap_err().contains("must start at one of"));
+        assert!(validate(&json!(["{{ payload.id"])).unwrap_err().contains("unterminated"));
+    }
+}
@@ -5,9 +5,10 @@
 
 use crate::error::{CoreError, CoreResult};
 use crate::state::StateManager;
-use crate::models::{WorkflowDefinition, WorkflowRun, StepResult, StepStatus};
+use crate::models::{WorkflowDefinition, WorkflowRun, RunStatus, StepResult, StepStatus, AdhocStepExecution, StepControl, StepCacheHit, CachedStepOutput, StepCompletion, StepBatchCompletionSummary};
 use crate::context::Context;
 use crate::workflow_state_machine::{WorkflowStateMachine, WorkflowExecutionState};
+use crate::dispatcher::Dispatcher;
 use chrono::Utc;
 use log;
 use std::sync::{Arc, Mutex};
@@ -17,6 +18,7 @@ use serde_json;
 /// Step execution orchestrator
 pub struct StepOrchestrator {
     state_manager: Arc<Mutex<StateManager>>,
+    job_dispatcher: Option<Arc<tokio::sync::Mutex<Dispatcher>>>,
 }
 
 impl StepOrchestrator {
@@ -24,11 +26,54 @@ impl StepOrchestrator {
     pub fn new(state_manager: Arc<Mutex<StateManager>>) -> Self {
         Self {
             state_manager,
+            job_dispatcher: None,
         }
     }
 
+    /// Use a job dispatcher to execute parallel step groups
+    pub fn with_dispatcher(mut self, job_dispatcher: Arc<tokio::sync::Mutex<Dispatcher>>) -> Self {
+        self.job_dispatcher = Some(job_dispatcher);
+        self
+    }
+
     /// Start step execution for a workflow run
+    ///
+    /// A child run that finishes here wakes the parent run waiting on it.
     pub fn start_step_execution(&self, run_id: &Uuid, workflow_id: &str) -> CoreResult<()> {
+        self.execute_run(run_id, workflow_id)?;
+        self.wake_parent(run_id)
+    }
+
+    /// Persist a batch of step completions, then continue every run it touched
+    ///
+    /// The batch is written in one transaction. Each affected run is then
+    /// executed like a woken run, so the steps the batch unblocked are dispatched
+    /// and finished runs are finalized and wake their parent run.
+    pub fn complete_steps_batch(&self, completions: Vec<StepCompletion>) -> CoreResult<StepBatchCompletionSummary> {
+        let (summary, runs) = {
+            let mut state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            let summary = state_manager.complete_steps_batch(completions)?;
+            let mut runs = Vec::with_capacity(summary.runs.len());
+            for run_id in summary.runs.keys() {
+                if let Some(run) = state_manager.get_run(&Uuid::parse_str(run_id)?)? {
+                    runs.push((run.id, run.workflow_id));
+                }
+            }
+            (summary, runs)
+        }; // Lock released here
+        
+        // The completions are already persisted, so a run that fails to continue does not fail the batch
+        for (run_id, workflow_id) in runs {
+            if let Err(e) = self.start_step_execution(&run_id, &workflow_id) {
+                log::error!("Failed to continue run {} after step batch: {}", run_id, e);
+            }
+        }
+        Ok(summary)
+    }
+
+    /// Execute the steps of a run that are not finished yet
+    fn execute_run(&self, run_id: &Uuid, workflow_id: &str) -> CoreResult<()> {
         log::info!("Starting step execution for run: {} workflow: {}", run_id, workflow_id);
         
         let mut state_machine = WorkflowStateMachine::new(
@@ -39,6 +84,14 @@ impl StepOrchestrator {
         
         state_machine.initialize()?;
         
+        // Continue after the steps that already finished, e.g. when a delayed run is woken
+        let persisted_results = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            state_manager.get_completed_steps(run_id)?
+        };
+        state_machine.restore_step_results(&persisted_results)?;
+        
         // Execute steps using the state machine
         self.execute_steps_with_state_machine(state_machine)?;
         
@@ -60,6 +113,11 @@ impl StepOrchestrator {
         
         // Execute steps until completion
         while !state_machine.check_workflow_completion()? {
+            if self.is_run_cancelled(&run.id)? {
+                state_machine.cancel(Some("Run was cancelled".to_string()))?;
+                break;
+            }
+            
             let ready_steps = state_machine.get_ready_steps();
             
             if ready_steps.is_empty() {
@@ -67,28 +125,69 @@ impl StepOrchestrator {
                 break;
             }
             
-            let parallel_groups = state_machine.detect_parallel_groups();
+            // Only groups whose steps are all ready can run now
+            let parallel_groups: Vec<_> = state_machine.detect_parallel_groups()
+                .into_iter()
+                .filter(|group| group.step_ids.iter().all(|step_id| ready_steps.contains(step_id)))
+                .collect();
+            
+            let race_groups: Vec<_> = state_machine.detect_race_groups()
+                .into_iter()
+                .filter(|group| group.step_ids.iter().all(|step_id| ready_steps.contains(step_id)))
+                .collect();
             
-            if !parallel_groups.is_empty() {
-                // Execute parallel groups
+            if !race_groups.is_empty() {
+                let job_dispatcher = self.job_dispatcher.clone()
+                    .ok_or_else(|| CoreError::Internal("Race step groups require a job dispatcher".to_string()))?;
+                let rt = tokio::runtime::Handle::try_current()
+                    .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+                
+                for group in race_groups {
+                    let race_results = rt.block_on(async {
+                        let dispatcher = job_dispatcher.lock().await;
+                        state_machine.execute_race_group(&group, &dispatcher).await
+                    })?;
+                    
+                    let aggregated_result = state_machine.aggregate_race_results(&group.group_id, &race_results)?;
+                    log::info!("Race group {} finished: {}", group.group_id, aggregated_result);
+                    
+                    // Save state to database
+                    state_machine.save_state()?;
+                }
+            } else if !parallel_groups.is_empty() {
+                let job_dispatcher = self.job_dispatcher.clone()
+                    .ok_or_else(|| CoreError::Internal("Parallel step groups require a job dispatcher".to_string()))?;
+                let rt = tokio::runtime::Handle::try_current()
+                    .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+                
                 for group in parallel_groups {
                     log::info!("Executing parallel group: {} with {} steps", group.group_id, group.step_ids.len());
                     
-                    // Execute the parallel group
-                    let parallel_results = state_machine.execute_parallel_group(&group)?;
+                    let parallel_results = rt.block_on(async {
+                        let dispatcher = job_dispatcher.lock().await;
+                        state_machine.execute_parallel_group(&group, &dispatcher).await
+                    })?;
                     
-                    // Aggregate the results
                     let aggregated_result = state_machine.aggregate_parallel_results(parallel_results)?;
+                    log::info!("Parallel group {} finished: {}", group.group_id, aggregated_result);
                     
-                    // Mark the parallel group as a single completed step
-                    let group_step_id = format!("parallel_group_{}", group.group_id);
-                    state_machine.mark_step_completed(&group_step_id, aggregated_result)?;
-                    
-                    log::info!("Parallel group {} completed successfully", group.group_id);
+                    // Save state to database
+                    state_machine.save_state()?;
                 }
             } else {
                 // Execute each ready step sequentially
                 for step_id in ready_steps {
+                    // Stop dispatching ready steps once the run is cancelled
+                    if self.is_run_cancelled(&run.id)? {
+                        break;
+                    }
+                    
+                    // Steps whose skip_if expression holds are skipped rather than dispatched
+                    if state_machine.apply_skip_if(&step_id)? {
+                        state_machine.save_state()?;
+                        continue;
+                    }
+                    
                     log::info!("Executing ready step: {}", step_id);
                     
                     // Mark step as running
@@ -98,6 +197,66 @@ impl StepOrchestrator {
                         .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?
                         .clone();
                     
+                    if step_def.is_delay_step() {
+                        match self.wait_for_delay(&mut state_machine, &run, &step_id) {
+                            Ok(true) => {
+                                log::info!("Run {} is waiting on delay step: {}", run.id, step_id);
+                                return Ok(());
+                            }
+                            Ok(false) => {}
+                            Err(error) => {
+                                state_machine.mark_step_failed(&step_id, error.to_string())?;
+                                state_machine.save_state()?;
+                                log::error!("Delay step {} failed: {}", step_id, error);
+                                break;
+                            }
+                        }
+                        
+                        // Save state to database
+                        state_machine.save_state()?;
+                        continue;
+                    }
+                    
+                    if step_def.is_signal_step() {
+                        match self.wait_for_signal(&mut state_machine, &run, &step_def) {
+                            Ok(true) => {
+                                log::info!("Run {} is waiting on signal step: {}", run.id, step_id);
+                                return Ok(());
+                            }
+                            Ok(false) => {}
+                            Err(error) => {
+                                state_machine.mark_step_failed(&step_id, error.to_string())?;
+                                state_machine.save_state()?;
+                                log::error!("Signal step {} failed: {}", step_id, error);
+                                break;
+                            }
+                        }
+                        
+                        // Save state to database
+                        state_machine.save_state()?;
+                        continue;
+                    }
+                    
+                    if step_def.is_subworkflow_step() {
+                        match self.run_subworkflow(&mut state_machine, &run, &step_def) {
+                            Ok(true) => {
+                                log::info!("Run {} is waiting on sub-workflow step: {}", run.id, step_id);
+                                return Ok(());
+                            }
+                            Ok(false) => {}
+                            Err(error) => {
+                                state_machine.mark_step_failed(&step_id, error.to_string())?;
+                                state_machine.save_state()?;
+                                log::error!("Sub-workflow step {} failed: {}", step_id, error);
+                                break;
+                            }
+                        }
+                        
+                        // Save state to database
+                        state_machine.save_state()?;
+                        continue;
+                    }
+                    
                     if step_def.is_pause_step() {
                         log::info!("Pause step detected: {}", step_id);
                         
@@ -121,24 +280,98 @@ impl StepOrchestrator {
                         
                         return Ok(());
                     }
-                    
+
+                    if step_def.is_for_each() {
+                        let job_dispatcher = self.job_dispatcher.clone()
+                            .ok_or_else(|| CoreError::Internal("forEach steps require a job dispatcher".to_string()))?;
+                        let rt = tokio::runtime::Handle::try_current()
+                            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+
+                        let for_each_result = rt.block_on(async {
+                            let dispatcher = job_dispatcher.lock().await;
+                            state_machine.execute_for_each_step(&step_id, &dispatcher).await
+                        });
+
+                        let failed = match for_each_result {
+                            Ok(result) => result.status == StepStatus::Failed,
+                            Err(error) => {
+                                log::error!("forEach step {} failed: {}", step_id, error);
+                                if state_machine.get_step_state(&step_id).map(|s| s.status.clone()) != Some(StepStatus::Failed) {
+                                    state_machine.mark_step_failed(&step_id, error.to_string())?;
+                                }
+                                true
+                            }
+                        };
+
+                        // Save state to database
+                        state_machine.save_state()?;
+
+                        if failed && !step_def.can_retry() {
+                            log::error!("forEach step {} cannot be retried, stopping workflow", step_id);
+                            break;
+                        }
+                        continue;
+                    }
+
                     let completed_steps = state_machine.get_completed_steps().to_vec();
                     
+                    // Cached steps reuse the output of an earlier execution with the same key
+                    let cache_key = state_machine.step_cache_key(&step_id).unwrap_or_else(|error| {
+                        log::warn!("Cache key of step {} could not be resolved, executing it uncached: {}", step_id, error);
+                        None
+                    });
+                    let cached = match &cache_key {
+                        Some(key) => self.state_manager.lock()
+                            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?
+                            .get_cached_step_output(&workflow.id, &step_id, key)?,
+                        None => None,
+                    };
+                    let outcome = match cached {
+                        Some(cached) => Ok((cached.output, Some(StepCacheHit { key: cached.key, cached_at: cached.cached_at }))),
+                        None => self.execute_step_with_state_machine(&workflow, &run, &step_def, &completed_steps, 0)
+                            .map(|output| (output, None)),
+                    };
+                    
                     // Execute the step using the state machine context
-                    match self.execute_step_with_state_machine(&workflow, &run, &step_def, &completed_steps, 0) {
-                        Ok(output) => {
+                    match outcome {
+                        Ok((output, cache_hit)) => {
+                            let control = StepControl::from_output(&output);
+                            
                             // Mark step as completed in state machine
-                            state_machine.mark_step_completed(&step_id, output)?;
+                            match cache_hit {
+                                Some(hit) => {
+                                    log::info!("Step {} reused its cached output from {}", step_id, hit.cached_at);
+                                    state_machine.mark_step_cached(&step_id, output, hit)?;
+                                }
+                                None => {
+                                    if let (Some(key), Some(cache)) = (cache_key, &step_def.cache) {
+                                        self.cache_step_output(&workflow.id, &step_id, key, &output, cache.ttl_ms);
+                                    }
+                                    state_machine.mark_step_completed(&step_id, output)?;
+                                }
+                            }
                             log::info!("Step {} completed successfully", step_id);
+                            
+                            if control == Some(StepControl::CompleteRun) {
+                                state_machine.skip_remaining_steps(&step_id)?;
+                                state_machine.save_state()?;
+                                break;
+                            }
                         }
                         Err(error) => {
                             // Mark step as failed in state machine
                             state_machine.mark_step_failed(&step_id, error.to_string())?;
                             log::error!("Step {} failed: {}", step_id, error);
                             
-                            if !step_def.can_retry() {
-                                log::error!("Step {} cannot be retried, stopping workflow", step_id);
-                                break;
+                            match state_machine.retry_step(&step_id)? {
+                                Some(delay_ms) => {
+                                    log::info!("Retrying step {} in {}ms", step_id, delay_ms);
+                                    std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+                                }
+                                None => {
+                                    log::error!("Step {} cannot be retried, stopping workflow", step_id);
+                                    break;
+                                }
                             }
                         }
                     }
@@ -150,12 +383,12 @@ impl StepOrchestrator {
         }
         
         let is_complete = state_machine.check_workflow_completion()?;
-        if is_complete {
+        if is_complete && state_machine.get_execution_state() != &WorkflowExecutionState::Cancelled {
             // Determine error message if any steps failed
             let error_message = if state_machine.get_stats().failed_steps > 0 {
                 let failed_steps: Vec<_> = state_machine.get_completed_steps()
                     .iter()
-                    .filter(|step| matches!(step.status, crate::models::StepStatus::Failed))
+                    .filter(|step| matches!(step.status, StepStatus::Failed))
                     .map(|step| format!("{}: {}", step.step_id, step.error.as_deref().unwrap_or("Unknown error")))
                     .collect();
                 Some(format!("Workflow failed: {}", failed_steps.join(", ")))
@@ -174,6 +407,271 @@ impl StepOrchestrator {
         Ok(())
     }
 
+    /// Execute a single step outside any run
+    ///
+    /// The step gets a synthetic run and context built from the payload; nothing
+    /// is written to run history. The result is kept in the ad hoc scratch area
+    /// for `ttl` so developers can iterate on one step at a time.
+    pub fn execute_adhoc_step(
+        &self,
+        workflow_id: &str,
+        step_id: &str,
+        payload: serde_json::Value,
+        ttl: chrono::Duration,
+    ) -> CoreResult<AdhocStepExecution> {
+        let workflow = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            state_manager.get_workflow(workflow_id)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(workflow_id.to_string()))?
+        }; // Lock released here
+        
+        let step_index = workflow.steps.iter()
+            .position(|step| step.id == step_id)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step '{}' not found in workflow '{}'", step_id, workflow_id)))?;
+        let step_def = workflow.steps[step_index].clone();
+        
+        let started_at = Utc::now();
+        let run = WorkflowRun {
+            id: Uuid::new_v4(),
+            workflow_id: workflow_id.to_string(),
+            status: RunStatus::Running,
+            payload: payload.clone(),
+            started_at,
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        log::info!("Executing step {} of workflow {} ad hoc", step_id, workflow_id);
+        
+        let outcome = self.execute_step_with_state_machine(&workflow, &run, &step_def, &[], step_index);
+        let completed_at = Utc::now();
+        let (status, output, error) = match outcome {
+            Ok(output) => (StepStatus::Completed, Some(output), None),
+            Err(error) => (StepStatus::Failed, None, Some(error.to_string())),
+        };
+        
+        let execution = AdhocStepExecution {
+            id: run.id.to_string(),
+            workflow_id: workflow_id.to_string(),
+            step_id: step_id.to_string(),
+            payload,
+            result: StepResult {
+                step_id: step_id.to_string(),
+                status,
+                output,
+                error,
+                started_at,
+                completed_at: Some(completed_at),
+                duration_ms: Some((completed_at - started_at).num_milliseconds().max(0) as u64),
+                cache_hit: None,
+                resources: None,
+            },
+            created_at: completed_at,
+            expires_at: completed_at + ttl,
+        };
+        
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        state_manager.save_adhoc_step_execution(&execution)?;
+        Ok(execution)
+    }
+
+    /// Store the output of a cached step for later executions with the same key
+    ///
+    /// A failure to store only costs a later cache miss, so it is logged.
+    fn cache_step_output(&self, workflow_id: &str, step_id: &str, key: String, output: &serde_json::Value, ttl_ms: u64) {
+        let cached_at = Utc::now();
+        let entry = CachedStepOutput {
+            workflow_id: workflow_id.to_string(),
+            step_id: step_id.to_string(),
+            key,
+            output: output.clone(),
+            cached_at,
+            expires_at: cached_at + chrono::Duration::milliseconds(ttl_ms as i64),
+        };
+        let stored = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))
+            .and_then(|state_manager| state_manager.cache_step_output(&entry));
+        if let Err(e) = stored {
+            log::warn!("Failed to cache the output of step {}: {}", step_id, e);
+        }
+    }
+
+    /// Wait on a delay step without holding the worker
+    ///
+    /// The first time the step is reached its wake-up is persisted and the run
+    /// is left paused for the scheduler to wake; returns whether it is still
+    /// waiting. Once the wake-up has passed the step completes.
+    fn wait_for_delay(&self, state_machine: &mut WorkflowStateMachine, run: &WorkflowRun, step_id: &str) -> CoreResult<bool> {
+        let now = Utc::now();
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        
+        let wake_at = match state_manager.get_step_delay(&run.id, step_id)? {
+            Some(delay) => delay.wake_at,
+            None => {
+                let wake_at = state_machine.delay_wake_at(step_id, now)?;
+                if wake_at > now {
+                    state_manager.delay_step(&run.id, &run.workflow_id, step_id, wake_at)?;
+                }
+                wake_at
+            }
+        };
+        
+        if wake_at > now {
+            drop(state_manager);
+            state_machine.pause()?;
+            state_machine.save_state()?;
+            return Ok(true);
+        }
+        
+        state_manager.clear_step_delay(&run.id, step_id)?;
+        drop(state_manager);
+        state_machine.mark_step_completed(step_id, serde_json::json!({
+            "delayed": true,
+            "wake_at": wake_at.to_rfc3339(),
+            "step_id": step_id,
+        }))?;
+        Ok(false)
+    }
+
+    /// Wait on a signal step without holding the worker
+    ///
+    /// A signal already sent to the run completes the step with its payload.
+    /// Otherwise the first time the step is reached its wait is persisted and
+    /// the run is left paused until the signal is sent; returns whether it is
+    /// still waiting. A wait past its timeout fails the step.
+    fn wait_for_signal(&self, state_machine: &mut WorkflowStateMachine, run: &WorkflowRun, step_def: &crate::models::StepDefinition) -> CoreResult<bool> {
+        let signal_name = step_def.wait_for_signal.as_deref()
+            .ok_or_else(|| CoreError::Validation(format!("Step {} is not a signal step", step_def.id)))?;
+        let now = Utc::now();
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        
+        if let Some(signal) = state_manager.take_signal(&run.id, signal_name)? {
+            state_manager.clear_signal_wait(&run.id, &step_def.id)?;
+            drop(state_manager);
+            state_machine.mark_step_completed(&step_def.id, serde_json::json!({
+                "signal": signal_name,
+                "payload": signal.payload,
+                "received_at": signal.received_at.to_rfc3339(),
+            }))?;
+            return Ok(false);
+        }
+        
+        let wait = match state_manager.get_signal_wait(&run.id, &step_def.id)? {
+            Some(wait) => wait,
+            None => {
+                let timeout_at = step_def.signal_timeout_ms.map(|timeout_ms| now + chrono::Duration::milliseconds(timeout_ms as i64));
+                state_manager.wait_for_signal(&run.id, &run.workflow_id, &step_def.id, signal_name, timeout_at)?
+            }
+        };
+        if wait.is_timed_out(now) {
+            state_manager.clear_signal_wait(&run.id, &step_def.id)?;
+            return Err(CoreError::StepExecution(format!("Step {} timed out waiting for signal {}", step_def.id, signal_name)));
+        }
+        
+        drop(state_manager);
+        state_machine.pause()?;
+        state_machine.save_state()?;
+        Ok(true)
+    }
+
+    /// Run the child run of a sub-workflow step
+    ///
+    /// The first time the step is reached the child run is created and executed
+    /// in place. A child that has not finished, e.g. because it waits on a delay,
+    /// leaves the parent paused until the child finishes and wakes it; returns
+    /// whether the parent is still waiting. A completed child completes the step
+    /// with its final output, while a failed or cancelled one fails the step.
+    fn run_subworkflow(&self, state_machine: &mut WorkflowStateMachine, run: &WorkflowRun, step_def: &crate::models::StepDefinition) -> CoreResult<bool> {
+        let subworkflow_id = step_def.subworkflow_id.as_deref()
+            .ok_or_else(|| CoreError::Validation(format!("Step {} is not a sub-workflow step", step_def.id)))?;
+        let existing_child = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            state_manager.get_child_run(&run.id, &step_def.id)?
+        }; // Lock released here
+        
+        let child_id = match existing_child {
+            Some(child_id) => child_id,
+            None => {
+                let payload = state_machine.subworkflow_payload(&step_def.id)?;
+                let child_id = {
+                    let mut state_manager = self.state_manager.lock()
+                        .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+                    state_manager.create_child_run(&run.id, &step_def.id, subworkflow_id, payload)?
+                }; // Lock released here
+                log::info!("Step {} of run {} started child run {} of workflow {}", step_def.id, run.id, child_id, subworkflow_id);
+                self.execute_run(&child_id, subworkflow_id)?;
+                child_id
+            }
+        };
+        
+        let (child, output) = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            let child = state_manager.get_run(&child_id)?
+                .ok_or_else(|| CoreError::RunNotFound(child_id.to_string()))?;
+            let output = match child.status {
+                RunStatus::Completed => state_manager.get_run_output(&child_id)?,
+                _ => None,
+            };
+            (child, output)
+        }; // Lock released here
+        
+        match child.status {
+            RunStatus::Completed => {
+                state_machine.mark_step_completed(&step_def.id, output.unwrap_or(serde_json::Value::Null))?;
+                Ok(false)
+            }
+            RunStatus::Failed | RunStatus::Cancelled => Err(CoreError::StepExecution(format!(
+                "Child run {} of workflow {} finished as {:?}: {}",
+                child_id, subworkflow_id, child.status, child.error.as_deref().unwrap_or("no error recorded")
+            ))),
+            _ => {
+                state_machine.pause()?;
+                state_machine.save_state()?;
+                Ok(true)
+            }
+        }
+    }
+
+    /// Continue the parent run waiting on a child run that has finished
+    fn wake_parent(&self, run_id: &Uuid) -> CoreResult<()> {
+        let parent = {
+            let state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            let finished = state_manager.get_run(run_id)?.is_some_and(|run| run.status.is_terminal());
+            match state_manager.get_run_parent(run_id)? {
+                Some(parent) if finished => {
+                    let parent_id = Uuid::parse_str(&parent.parent_run_id)?;
+                    state_manager.get_run(&parent_id)?
+                        .filter(|parent_run| !parent_run.status.is_terminal())
+                }
+                _ => None,
+            }
+        }; // Lock released here
+        
+        match parent {
+            Some(parent_run) => {
+                log::info!("Child run {} finished, waking parent run {}", run_id, parent_run.id);
+                self.start_step_execution(&parent_run.id, &parent_run.workflow_id)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Check whether the run was cancelled while its steps were executing
+    fn is_run_cancelled(&self, run_id: &Uuid) -> CoreResult<bool> {
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        Ok(state_manager.get_run(run_id)?
+            .map(|run| run.status == RunStatus::Cancelled)
+            .unwrap_or(false))
+    }
+
     /// Execute a single step with state machine integration
     fn execute_step_with_state_machine(
         &self,
@@ -327,7 +825,7 @@ impl StepOrchestrator {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{WorkflowDefinition, StepDefinition, TriggerDefinition, RunStatus};
+    use crate::models::{WorkflowDefinition, StepDefinition, TriggerDefinition, RunStatus, StepCache};
     use chrono::Utc;
     use uuid::Uuid;
 
@@ -355,11 +853,38 @@ mod tests {
                     timeout: None,
                     retry: None,
                     depends_on: vec![],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 }
             ],
             triggers: vec![],
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
         };
         
         let run = WorkflowRun {
@@ -370,6 +895,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
         
         let step_def = &workflow.steps[0];
@@ -385,4 +911,675 @@ mod tests {
         assert_eq!(context.metadata.step_index, 0);
         assert_eq!(context.metadata.total_steps, 1);
     }
+
+    #[test]
+    fn test_adhoc_step_runs_outside_any_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("adhoc.db").to_str().unwrap()).unwrap();
+        let step = |id: &str| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: vec![],
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "adhoc-workflow".to_string(),
+            name: "Adhoc Workflow".to_string(),
+            description: None,
+            steps: vec![step("fetch"), step("transform")],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+
+        let execution = orchestrator.execute_adhoc_step(
+            "adhoc-workflow",
+            "transform",
+            serde_json::json!({"value": 42}),
+            chrono::Duration::minutes(5),
+        ).unwrap();
+        assert_eq!(execution.result.status, StepStatus::Completed);
+        assert_eq!(execution.payload, serde_json::json!({"value": 42}));
+
+        let state_manager = state_manager.lock().unwrap();
+        let run_id = Uuid::parse_str(&execution.id).unwrap();
+        assert!(state_manager.get_run(&run_id).unwrap().is_none());
+        let stored = state_manager.get_adhoc_step_executions("adhoc-workflow").unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].step_id, "transform");
+        drop(state_manager);
+
+        assert!(matches!(
+            orchestrator.execute_adhoc_step("adhoc-workflow", "missing", serde_json::json!({}), chrono::Duration::minutes(5)),
+            Err(CoreError::StepNotFound(_))
+        ));
+
+        // Expired scratch results are no longer returned
+        orchestrator.execute_adhoc_step("adhoc-workflow", "fetch", serde_json::json!({}), chrono::Duration::zero()).unwrap();
+        let remaining = orchestrator.state_manager.lock().unwrap().get_adhoc_step_executions("adhoc-workflow").unwrap();
+        assert_eq!(remaining.len(), 1);
+    }
+
+    #[test]
+    fn test_delay_steps_release_the_run_and_resume_after_wake_up() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("delay.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: &[&str]| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        let mut wait = step("wait", &["fetch"]);
+        wait.delay_ms = Some(300);
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "delay-workflow".to_string(),
+            name: "Delay Workflow".to_string(),
+            description: None,
+            steps: vec![step("fetch", &[]), wait, step("notify", &["wait"])],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("delay-workflow", serde_json::json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+
+        orchestrator.start_step_execution(&run_id, "delay-workflow").unwrap();
+        {
+            let state_manager = state_manager.lock().unwrap();
+            let delay = state_manager.get_step_delay(&run_id, "wait").unwrap().expect("delay was not persisted");
+            assert_eq!(state_manager.get_next_delay_wake_at().unwrap(), Some(delay.wake_at));
+            assert!(state_manager.get_due_step_delays(Utc::now()).unwrap().is_empty());
+            assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Running);
+            let steps: Vec<String> = state_manager.get_completed_steps(&run_id).unwrap().into_iter().map(|r| r.step_id).collect();
+            assert_eq!(steps, vec!["fetch".to_string()]);
+        }
+
+        // Executing before the wake-up keeps waiting without repeating finished steps
+        orchestrator.start_step_execution(&run_id, "delay-workflow").unwrap();
+        assert_eq!(state_manager.lock().unwrap().get_completed_steps(&run_id).unwrap().len(), 1);
+
+        std::thread::sleep(std::time::Duration::from_millis(350));
+        assert_eq!(state_manager.lock().unwrap().get_due_step_delays(Utc::now()).unwrap().len(), 1);
+        orchestrator.start_step_execution(&run_id, "delay-workflow").unwrap();
+
+        let state_manager = state_manager.lock().unwrap();
+        assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Completed);
+        assert!(state_manager.get_step_delay(&run_id, "wait").unwrap().is_none());
+        let results = state_manager.get_completed_steps(&run_id).unwrap();
+        let steps: Vec<&str> = results.iter().map(|r| r.step_id.as_str()).collect();
+        assert_eq!(steps, vec!["fetch", "wait", "notify"]);
+        assert_eq!(results[1].output.as_ref().unwrap()["delayed"], true);
+        let events = state_manager.get_run_timeline(&run_id).unwrap();
+        assert!(events.iter().any(|e| e.event_type == crate::models::RunEvent::STEP_DELAYED && e.step_id.as_deref() == Some("wait")));
+    }
+
+    #[test]
+    fn test_step_batch_completion_dispatches_unblocked_steps() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("batch.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: &[&str]| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "batch-workflow".to_string(),
+            name: "Batch Workflow".to_string(),
+            description: None,
+            steps: vec![step("fetch", &[]), step("notify", &["fetch"])],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("batch-workflow", serde_json::json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+
+        let summary = orchestrator.complete_steps_batch(vec![StepCompletion {
+            run_id: run_id.to_string(),
+            result: StepResult {
+                step_id: "fetch".to_string(),
+                status: StepStatus::Completed,
+                output: Some(serde_json::json!({"items": 3})),
+                error: None,
+                started_at: Utc::now(),
+                completed_at: Some(Utc::now()),
+                duration_ms: Some(5),
+                cache_hit: None,
+                resources: None,
+            },
+            context_checksum: None,
+            context_schema_version: None,
+        }]).unwrap();
+        assert_eq!(summary.runs.get(&run_id.to_string()), Some(&RunStatus::Running));
+
+        // The completed step unblocked its dependent, which ran and finished the run
+        let state_manager = state_manager.lock().unwrap();
+        let results = state_manager.get_completed_steps(&run_id).unwrap();
+        let steps: Vec<&str> = results.iter().map(|r| r.step_id.as_str()).collect();
+        assert_eq!(steps, vec!["fetch", "notify"]);
+        assert_eq!(results[0].output, Some(serde_json::json!({"items": 3})));
+        assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Completed);
+    }
+
+    #[test]
+    fn test_skip_if_skips_steps_before_dispatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("skip.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: &[&str]| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        let workflow = |id: &str, skip_dependents: bool| {
+            let mut audit = step("audit", &["fetch"]);
+            audit.skip_if = Some("ctx.payload.dry_run == true".to_string());
+            audit.skip_dependents = skip_dependents;
+            WorkflowDefinition {
+                id: id.to_string(),
+                name: id.to_string(),
+                description: None,
+                steps: vec![step("fetch", &[]), audit, step("notify", &["audit"])],
+                triggers: vec![],
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                concurrency: None,
+                namespace: None,
+                payload_sample_rate: None,
+                priority: None,
+                run_timeout_ms: None,
+                run_timeout_outcome: Default::default(),
+                serialize_on: None,
+                resource_quota: None,
+                lane: None,
+                defaults: None,
+            }
+        };
+        state_manager.register_workflow(workflow("skip-workflow", false)).unwrap();
+        state_manager.register_workflow(workflow("skip-cascade-workflow", true)).unwrap();
+        let skipped_id = state_manager.create_run("skip-workflow", serde_json::json!({"dry_run": true})).unwrap();
+        let executed_id = state_manager.create_run("skip-workflow", serde_json::json!({"dry_run": false})).unwrap();
+        let cascade_id = state_manager.create_run("skip-cascade-workflow", serde_json::json!({"dry_run": true})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+        orchestrator.start_step_execution(&skipped_id, "skip-workflow").unwrap();
+        orchestrator.start_step_execution(&executed_id, "skip-workflow").unwrap();
+        orchestrator.start_step_execution(&cascade_id, "skip-cascade-workflow").unwrap();
+
+        let state_manager = state_manager.lock().unwrap();
+        let latest = |run_id: &Uuid, step_id: &str| state_manager.get_completed_steps(run_id).unwrap()
+            .into_iter()
+            .rev()
+            .find(|r| r.step_id == step_id)
+            .unwrap();
+        // Dependents of a skipped step treat it as satisfied by default
+        let audit = latest(&skipped_id, "audit");
+        assert_eq!(audit.status, StepStatus::Skipped);
+        assert_eq!(audit.output.as_ref().unwrap()["expression"], "ctx.payload.dry_run == true");
+        assert_eq!(latest(&skipped_id, "notify").status, StepStatus::Completed);
+        assert_eq!(state_manager.get_run(&skipped_id).unwrap().unwrap().status, RunStatus::Completed);
+        assert_eq!(latest(&executed_id, "audit").status, StepStatus::Completed);
+        // or skip with it when the step skips its dependents
+        assert_eq!(latest(&cascade_id, "notify").status, StepStatus::Skipped);
+        assert_eq!(state_manager.get_run(&cascade_id).unwrap().unwrap().status, RunStatus::Completed);
+    }
+
+    #[test]
+    fn test_signal_steps_wait_for_their_signal_or_time_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("signal.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: &[&str]| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        let workflow = |id: &str, steps: Vec<StepDefinition>| WorkflowDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            steps,
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let mut approve = step("approve", &["fetch"]);
+        approve.wait_for_signal = Some("payment_confirmed".to_string());
+        let mut expire = step("expire", &[]);
+        expire.wait_for_signal = Some("payment_confirmed".to_string());
+        expire.signal_timeout_ms = Some(1);
+        state_manager.register_workflow(workflow("signal-workflow", vec![step("fetch", &[]), approve, step("notify", &["approve"])])).unwrap();
+        state_manager.register_workflow(workflow("signal-timeout-workflow", vec![expire])).unwrap();
+        let run_id = state_manager.create_run("signal-workflow", serde_json::json!({})).unwrap();
+        let timed_out_id = state_manager.create_run("signal-timeout-workflow", serde_json::json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+
+        orchestrator.start_step_execution(&run_id, "signal-workflow").unwrap();
+        {
+            let state_manager = state_manager.lock().unwrap();
+            let wait = state_manager.get_signal_wait(&run_id, "approve").unwrap().expect("wait was not persisted");
+            assert_eq!(wait.timeout_at, None);
+            assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Running);
+            // Signals of other names are kept without resuming the run
+            assert!(state_manager.send_signal(&run_id, "refunded", serde_json::json!({})).unwrap().is_none());
+            let wait = state_manager.send_signal(&run_id, "payment_confirmed", serde_json::json!({"amount": 42})).unwrap();
+            assert_eq!(wait.map(|w| w.step_id), Some("approve".to_string()));
+        }
+
+        orchestrator.start_step_execution(&run_id, "signal-workflow").unwrap();
+        {
+            let state_manager = state_manager.lock().unwrap();
+            assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Completed);
+            assert!(state_manager.get_signal_wait(&run_id, "approve").unwrap().is_none());
+            let results = state_manager.get_completed_steps(&run_id).unwrap();
+            let steps: Vec<&str> = results.iter().map(|r| r.step_id.as_str()).collect();
+            assert_eq!(steps, vec!["fetch", "approve", "notify"]);
+            assert_eq!(results[1].output.as_ref().unwrap()["payload"]["amount"], 42);
+            assert!(state_manager.send_signal(&run_id, "payment_confirmed", serde_json::json!({})).is_err());
+        }
+
+        orchestrator.start_step_execution(&timed_out_id, "signal-timeout-workflow").unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        assert_eq!(state_manager.lock().unwrap().get_timed_out_signal_waits(Utc::now()).unwrap().len(), 1);
+        orchestrator.start_step_execution(&timed_out_id, "signal-timeout-workflow").unwrap();
+        let state_manager = state_manager.lock().unwrap();
+        assert_eq!(state_manager.get_run(&timed_out_id).unwrap().unwrap().status, RunStatus::Failed);
+        assert!(state_manager.get_timed_out_signal_waits(Utc::now()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_subworkflow_steps_wait_for_the_child_run_and_take_its_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("subworkflow.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: &[&str]| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        let workflow = |id: &str, steps: Vec<StepDefinition>| WorkflowDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            description: None,
+            steps,
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let mut wait = step("wait", &[]);
+        wait.delay_ms = Some(300);
+        state_manager.register_workflow(workflow("shipping", vec![wait, step("ship", &["wait"])])).unwrap();
+        let mut fulfil = step("fulfil", &["fetch"]);
+        fulfil.subworkflow_id = Some("shipping".to_string());
+        fulfil.subworkflow_input = Some([("order_run".to_string(), "ctx.steps.fetch.output.run_id".to_string())].into());
+        state_manager.register_workflow(workflow("order", vec![step("fetch", &[]), fulfil, step("notify", &["fulfil"])])).unwrap();
+        let run_id = state_manager.create_run("order", serde_json::json!({})).unwrap();
+        let cancelled_run_id = state_manager.create_run("order", serde_json::json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+
+        orchestrator.start_step_execution(&run_id, "order").unwrap();
+        let child_id = {
+            let state_manager = state_manager.lock().unwrap();
+            let child_id = state_manager.get_child_run(&run_id, "fulfil").unwrap().expect("child run was not started");
+            let child = state_manager.get_run(&child_id).unwrap().unwrap();
+            assert_eq!(child.payload, serde_json::json!({"order_run": run_id.to_string()}));
+            assert_eq!(child.status, RunStatus::Running);
+            assert_eq!(state_manager.get_run_parent(&child_id).unwrap().unwrap().parent_step_id, "fulfil");
+            assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Running);
+            child_id
+        };
+
+        // The child finishing its delay wakes the parent, which completes with the child's output
+        std::thread::sleep(std::time::Duration::from_millis(350));
+        orchestrator.start_step_execution(&child_id, "shipping").unwrap();
+        {
+            let state_manager = state_manager.lock().unwrap();
+            assert_eq!(state_manager.get_run(&child_id).unwrap().unwrap().status, RunStatus::Completed);
+            assert_eq!(state_manager.get_run(&run_id).unwrap().unwrap().status, RunStatus::Completed);
+            let results = state_manager.get_completed_steps(&run_id).unwrap();
+            let steps: Vec<&str> = results.iter().map(|r| r.step_id.as_str()).collect();
+            assert_eq!(steps, vec!["fetch", "fulfil", "notify"]);
+            let output = results[1].output.as_ref().unwrap();
+            assert_eq!((output["step_name"].as_str(), output["run_id"].as_str()), (Some("ship"), Some(child_id.to_string().as_str())));
+        }
+
+        orchestrator.start_step_execution(&cancelled_run_id, "order").unwrap();
+        let mut state_manager = state_manager.lock().unwrap();
+        let child_id = state_manager.get_child_run(&cancelled_run_id, "fulfil").unwrap().unwrap();
+        assert!(state_manager.cancel_run(&cancelled_run_id, "Order withdrawn").unwrap());
+        assert_eq!(state_manager.get_run(&child_id).unwrap().unwrap().status, RunStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_cached_steps_reuse_the_output_stored_under_their_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut state_manager = crate::state::StateManager::new(dir.path().join("cache.db").to_str().unwrap()).unwrap();
+        let step = |id: &str, depends_on: &[&str]| StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: id.to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: None,
+            parallel_group_id: None,
+            parallel_step_count: None,
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        };
+        let mut lookup = step("lookup", &[]);
+        lookup.cache = Some(StepCache { key_expression: "ctx.payload.customer".to_string(), ttl_ms: 60_000 });
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "cache-workflow".to_string(),
+            name: "Cache Workflow".to_string(),
+            description: None,
+            steps: vec![lookup, step("notify", &["lookup"])],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let runs: Vec<Uuid> = [("acme", 1), ("acme", 2), ("globex", 3)].iter()
+            .map(|(customer, attempt)| state_manager.create_run("cache-workflow", serde_json::json!({"customer": customer, "attempt": attempt})).unwrap())
+            .collect();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        let orchestrator = StepOrchestrator::new(state_manager.clone());
+        for run_id in &runs {
+            orchestrator.start_step_execution(run_id, "cache-workflow").unwrap();
+        }
+
+        let state_manager = state_manager.lock().unwrap();
+        let lookups: Vec<StepResult> = runs.iter()
+            .map(|run_id| state_manager.get_completed_steps(run_id).unwrap().into_iter().find(|r| r.step_id == "lookup").unwrap())
+            .collect();
+        assert!(lookups[0].cache_hit.is_none());
+        let hit = lookups[1].cache_hit.as_ref().expect("second run did not reuse the cached output");
+        assert_eq!(hit.key, StepCache::key_for(&serde_json::json!("acme")));
+        assert_eq!(lookups[1].output, lookups[0].output);
+        assert!(lookups[2].cache_hit.is_none());
+        assert_eq!(state_manager.get_run(&runs[1]).unwrap().unwrap().status, RunStatus::Completed);
+
+        // Keys do not depend on the order of object fields
+        assert_eq!(
+            StepCache::key_for(&serde_json::json!({"a": 1, "b": [{"c": 2, "d": 3}]})),
+            StepCache::key_for(&serde_json::json!({"b": [{"d": 3, "c": 2}], "a": 1}))
+        );
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,1024 @@
+//! Pluggable state store backends
+//!
+//! Workflows, runs, step results and their offloaded outputs, triggers and
+//! jobs are persisted through the `StorageBackend` trait. The local SQLite
+//! database is the default backend; multi-instance deployments point
+//! `CRONFLOW_DATABASE_URL` at a Postgres database (built with the `postgres`
+//! feature) so every instance shares them.
+//! Node-local data such as pins, events and worker diagnostics always stays in
+//! the SQLite file.
+
+use crate::database::Database;
+use crate::error::CoreResult;
+use crate::job::Job;
+use crate::models::{DisabledWorkflow, FinishedRun, PayloadBlob, RunPage, RunQuery, StepResult, StoredTrigger, WorkflowDefinition, WorkflowRun};
+use crate::record_compat::CompatibilityReport;
+use chrono::{DateTime, Utc};
+
+/// Persistence of the state shared by all engine instances
+pub trait StorageBackend: Send {
+    /// Name of the backend, for logs and diagnostics
+    fn name(&self) -> &'static str;
+
+    fn save_workflow(&self, workflow: &WorkflowDefinition) -> CoreResult<()>;
+    fn get_workflow(&self, id: &str) -> CoreResult<Option<WorkflowDefinition>>;
+    fn get_all_workflows(&self) -> CoreResult<Vec<WorkflowDefinition>>;
+    fn delete_workflow(&self, id: &str) -> CoreResult<()>;
+    /// Disable a workflow, or enable it with None, returning false if it does not exist
+    fn set_workflow_disabled(&self, workflow_id: &str, disabled: Option<&DisabledWorkflow>) -> CoreResult<bool>;
+    /// Get how a workflow was disabled, None if it is enabled
+    fn get_disabled_workflow(&self, workflow_id: &str) -> CoreResult<Option<DisabledWorkflow>>;
+
+    /// Save a run, counting the write in its stored version
+    fn save_run(&self, run: &WorkflowRun) -> CoreResult<()>;
+    /// Rewrite a run unless it was written since `run.version` was read, returning false on a conflict
+    fn save_run_if_version(&self, run: &WorkflowRun) -> CoreResult<bool>;
+    fn get_run(&self, run_id: &str) -> CoreResult<Option<WorkflowRun>>;
+    fn get_runs_for_workflow(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowRun>>;
+    fn list_runs(&self, query: &RunQuery) -> CoreResult<RunPage>;
+    /// Get every completed, failed or cancelled run, newest first
+    fn get_finished_runs(&self) -> CoreResult<Vec<FinishedRun>>;
+    /// Delete runs with their step results, jobs and orphaned payload blobs atomically
+    fn delete_runs(&self, run_ids: &[String]) -> CoreResult<usize>;
+
+    fn save_step_result(&self, result: &StepResult, run_id: &str) -> CoreResult<()>;
+    fn get_step_results(&self, run_id: &str) -> CoreResult<Vec<StepResult>>;
+    /// Persist step results and the runs they affect atomically
+    ///
+    /// Fails with a conflict, writing nothing, if any run was written since it was read.
+    fn save_step_results_batch(&self, results: &[(String, StepResult)], runs: &[WorkflowRun]) -> CoreResult<()>;
+    /// Store a large step output of a run; blobs with the same ID hold the same data
+    fn save_payload_blob(&self, run_id: &str, blob: &PayloadBlob) -> CoreResult<()>;
+    fn get_payload_blob(&self, id: &str) -> CoreResult<Option<String>>;
+
+    fn save_trigger(&self, trigger: &StoredTrigger) -> CoreResult<()>;
+    fn get_triggers(&self, workflow_id: &str) -> CoreResult<Vec<StoredTrigger>>;
+    fn delete_triggers(&self, workflow_id: &str) -> CoreResult<usize>;
+
+    fn save_job(&self, job: &Job) -> CoreResult<()>;
+    fn get_job(&self, job_id: &str) -> CoreResult<Option<Job>>;
+    fn get_jobs_for_run(&self, run_id: &str) -> CoreResult<Vec<Job>>;
+    fn delete_job(&self, job_id: &str) -> CoreResult<()>;
+    /// Lease a job to an owner, unless another owner holds a lease on it that has not expired
+    fn claim_job(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>, now: &DateTime<Utc>) -> CoreResult<bool>;
+    /// Extend a job lease, returning false if the owner no longer holds it
+    fn renew_job_lease(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>) -> CoreResult<bool>;
+    fn release_job(&self, job_id: &str, owner: &str) -> CoreResult<()>;
+    /// Get unfinished jobs of unfinished runs that no unexpired lease holds, oldest first
+    fn get_claimable_jobs(&self, now: &DateTime<Utc>, limit: usize) -> CoreResult<Vec<Job>>;
+
+    /// Report the stored workflows, runs and step results this engine cannot read
+    fn verify_record_compatibility(&self) -> CoreResult<CompatibilityReport>;
+}
+
+impl StorageBackend for Database {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn save_workflow(&self, workflow: &WorkflowDefinition) -> CoreResult<()> {
+        Database::save_workflow(self, workflow)
+    }
+
+    fn get_workflow(&self, id: &str) -> CoreResult<Option<WorkflowDefinition>> {
+        Database::get_workflow(self, id)
+    }
+
+    fn get_all_workflows(&self) -> CoreResult<Vec<WorkflowDefinition>> {
+        Database::get_all_workflows(self)
+    }
+
+    fn delete_workflow(&self, id: &str) -> CoreResult<()> {
+        Database::delete_workflow(self, id)
+    }
+
+    fn set_workflow_disabled(&self, workflow_id: &str, disabled: Option<&DisabledWorkflow>) -> CoreResult<bool> {
+        Database::set_workflow_disabled(self, workflow_id, disabled)
+    }
+
+    fn get_disabled_workflow(&self, workflow_id: &str) -> CoreResult<Option<DisabledWorkflow>> {
+        Database::get_disabled_workflow(self, workflow_id)
+    }
+
+    fn save_run(&self, run: &WorkflowRun) -> CoreResult<()> {
+        Database::save_run(self, run)
+    }
+
+    fn save_run_if_version(&self, run: &WorkflowRun) -> CoreResult<bool> {
+        Database::save_run_if_version(self, run)
+    }
+
+    fn get_run(&self, run_id: &str) -> CoreResult<Option<WorkflowRun>> {
+        Database::get_run(self, run_id)
+    }
+
+    fn get_runs_for_workflow(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowRun>> {
+        Database::get_runs_for_workflow(self, workflow_id)
+    }
+
+    fn list_runs(&self, query: &RunQuery) -> CoreResult<RunPage> {
+        Database::list_runs(self, query)
+    }
+
+    fn get_finished_runs(&self) -> CoreResult<Vec<FinishedRun>> {
+        Database::get_finished_runs(self)
+    }
+
+    fn delete_runs(&self, run_ids: &[String]) -> CoreResult<usize> {
+        Database::delete_runs(self, run_ids)
+    }
+
+    fn save_step_result(&self, result: &StepResult, run_id: &str) -> CoreResult<()> {
+        Database::save_step_result(self, result, run_id)
+    }
+
+    fn get_step_results(&self, run_id: &str) -> CoreResult<Vec<StepResult>> {
+        Database::get_step_results(self, run_id)
+    }
+
+    fn save_step_results_batch(&self, results: &[(String, StepResult)], runs: &[WorkflowRun]) -> CoreResult<()> {
+        Database::save_step_results_batch(self, results, runs)
+    }
+
+    fn save_payload_blob(&self, _run_id: &str, blob: &PayloadBlob) -> CoreResult<()> {
+        Database::save_payload_blob(self, blob)
+    }
+
+    fn get_payload_blob(&self, id: &str) -> CoreResult<Option<String>> {
+        Database::get_payload_blob(self, id)
+    }
+
+    fn save_trigger(&self, trigger: &StoredTrigger) -> CoreResult<()> {
+        Database::save_trigger(self, trigger)
+    }
+
+    fn get_triggers(&self, workflow_id: &str) -> CoreResult<Vec<StoredTrigger>> {
+        Database::get_triggers(self, workflow_id)
+    }
+
+    fn delete_triggers(&self, workflow_id: &str) -> CoreResult<usize> {
+        Database::delete_triggers(self, workflow_id)
+    }
+
+    fn save_job(&self, job: &Job) -> CoreResult<()> {
+        Database::save_job(self, job)
+    }
+
+    fn get_job(&self, job_id: &str) -> CoreResult<Option<Job>> {
+        Database::get_job(self, job_id)
+    }
+
+    fn get_jobs_for_run(&self, run_id: &str) -> CoreResult<Vec<Job>> {
+        Database::get_jobs_for_run(self, run_id)
+    }
+
+    fn delete_job(&self, job_id: &str) -> CoreResult<()> {
+        Database::delete_job(self, job_id)
+    }
+
+    fn claim_job(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>, now: &DateTime<Utc>) -> CoreResult<bool> {
+        Database::claim_job(self, job_id, owner, expires_at, now)
+    }
+
+    fn renew_job_lease(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>) -> CoreResult<bool> {
+        Database::renew_job_lease(self, job_id, owner, expires_at)
+    }
+
+    fn release_job(&self, job_id: &str, owner: &str) -> CoreResult<()> {
+        Database::release_job(self, job_id, owner)
+    }
+
+    fn get_claimable_jobs(&self, now: &DateTime<Utc>, limit: usize) -> CoreResult<Vec<Job>> {
+        Database::get_claimable_jobs(self, now, limit)
+    }
+
+    fn verify_record_compatibility(&self) -> CoreResult<CompatibilityReport> {
+        Database::verify_record_compatibility(self)
+    }
+}
+
+/// Whether a database URL points at a Postgres server
+pub fn is_postgres_url(url: &str) -> bool {
+    url.starts_with("postgres://") || url.starts_with("postgresql://")
+}
+
+/// Open the backend a database URL points at
+///
+/// `postgres://` and `postgresql://` URLs select Postgres; anything else is
+/// taken as a SQLite path, optionally prefixed with `sqlite://`.
+pub fn open_backend(url: &str) -> CoreResult<Box<dyn StorageBackend>> {
+    if is_postgres_url(url) {
+        open_postgres(url)
+    } else {
+        let path = url.strip_prefix("sqlite://").unwrap_or(url);
+        Ok(Box::new(Database::new(path)?))
+    }
+}
+
+#[cfg(feature = "postgres")]
+fn open_postgres(url: &str) -> CoreResult<Box<dyn StorageBackend>> {
+    Ok(Box::new(postgres_backend::PostgresBackend::connect(url)?))
+}
+
+#[cfg(not(feature = "postgres"))]
+fn open_postgres(_url: &str) -> CoreResult<Box<dyn StorageBackend>> {
+    Err(crate::error::CoreError::Configuration(
+        "A Postgres database URL was given, but the engine was built without the `postgres` feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "postgres")]
+pub use postgres_backend::PostgresBackend;
+
+#[cfg(feature = "postgres")]
+mod postgres_backend {
+    use super::StorageBackend;
+    use crate::error::{CoreError, CoreResult};
+    use crate::record_compat::{self, CompatibilityReport};
+    use crate::job::Job;
+    use crate::models::{
+        DisabledWorkflow, FinishedRun, PayloadBlob, RunListEntry, RunPage, RunQuery, StepResult, StoredTrigger, WorkflowDefinition,
+        WorkflowRun,
+    };
+    use postgres::types::ToSql;
+    use postgres::{Client, NoTls, Row};
+    use std::sync::mpsc;
+    use chrono::{DateTime, Utc};
+
+    type ClientTask = Box<dyn FnOnce(&mut Client) + Send>;
+
+    /// Postgres state store shared by several engine instances
+    ///
+    /// The blocking client cannot run inside the tokio runtime the engine is
+    /// driven from, so it lives on a dedicated thread and every call is sent
+    /// to that thread.
+    pub struct PostgresBackend {
+        tasks: mpsc::Sender<ClientTask>,
+    }
+
+    impl PostgresBackend {
+        /// Connect to a Postgres database and create the schema if needed
+        pub fn connect(url: &str) -> CoreResult<Self> {
+            let (tasks, receiver) = mpsc::channel::<ClientTask>();
+            let (ready_sender, ready) = mpsc::channel();
+            let url = url.to_string();
+
+            std::thread::Builder::new()
+                .name("cronflow-postgres".to_string())
+                .spawn(move || {
+                    let connected = Client::connect(&url, NoTls).and_then(|mut client| {
+                        client.batch_execute(include_str!("schema_postgres.sql"))?;
+                        Ok(client)
+                    });
+                    let mut client = match connected {
+                        Ok(client) => {
+                            let _ = ready_sender.send(Ok(()));
+                            client
+                        }
+                        Err(e) => {
+                            let _ = ready_sender.send(Err(e));
+                            return;
+                        }
+                    };
+                    for task in receiver {
+                        task(&mut client);
+                    }
+                })?;
+
+            ready.recv()
+                .map_err(|_| CoreError::Internal("Postgres connection thread exited during startup".to_string()))??;
+            log::info!("Connected to the Postgres state store");
+            Ok(PostgresBackend { tasks })
+        }
+
+        /// Run a call on the connection thread and wait for its result
+        fn call<T, F>(&self, f: F) -> CoreResult<T>
+        where
+            T: Send + 'static,
+            F: FnOnce(&mut Client) -> CoreResult<T> + Send + 'static,
+        {
+            let (reply, result) = mpsc::sync_channel(1);
+            self.tasks
+                .send(Box::new(move |client| {
+                    let _ = reply.send(f(client));
+                }))
+                .map_err(|_| CoreError::Internal("Postgres connection thread stopped".to_string()))?;
+            result.recv()
+                .map_err(|_| CoreError::Internal("Postgres connection thread stopped".to_string()))?
+        }
+    }
+
+    const RUN_COLUMNS: &str = "id, workflow_id, status, payload, started_at, completed_at, error, version";
+    const STEP_COLUMNS: &str = "step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources";
+    const INSERT_STEP: &str = "INSERT INTO step_results (run_id, step_id, status, output, error, started_at, completed_at, duration_ms, cache_hit, resources) \
+                               VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)";
+
+    fn run_from_row(row: &Row) -> CoreResult<WorkflowRun> {
+        let id: String = row.try_get(0)?;
+        let status: String = row.try_get(2)?;
+        Ok(WorkflowRun {
+            id: uuid::Uuid::parse_str(&id)?,
+            workflow_id: row.try_get(1)?,
+            status: record_compat::read_run_status(&status),
+            payload: row.try_get(3)?,
+            started_at: row.try_get(4)?,
+            completed_at: row.try_get(5)?,
+            error: row.try_get(6)?,
+            version: row.try_get::<_, i64>(7)? as u64,
+        })
+    }
+
+    fn step_result_from_row(row: &Row) -> CoreResult<StepResult> {
+        let status: String = row.try_get(1)?;
+        let duration_ms: Option<i64> = row.try_get(6)?;
+        let cache_hit: Option<serde_json::Value> = row.try_get(7)?;
+        let resources: Option<serde_json::Value> = row.try_get(8)?;
+        Ok(StepResult {
+            step_id: row.try_get(0)?,
+            status: record_compat::read_step_status(&status),
+            output: row.try_get(2)?,
+            error: row.try_get(3)?,
+            started_at: row.try_get(4)?,
+            completed_at: row.try_get(5)?,
+            duration_ms: duration_ms.map(|ms| ms as u64),
+            cache_hit: cache_hit.map(serde_json::from_value).transpose()?,
+            resources: resources.map(serde_json::from_value).transpose()?,
+        })
+    }
+
+    fn insert_step_result(client: &mut impl postgres::GenericClient, run_id: &str, result: &StepResult) -> CoreResult<()> {
+        client.execute(
+            INSERT_STEP,
+            &[
+                &run_id,
+                &result.step_id,
+                &format!("{:?}", result.status),
+                &result.output,
+                &result.error,
+                &result.started_at,
+                &result.completed_at,
+                &result.duration_ms.map(|ms| ms as i64),
+                &result.cache_hit.as_ref().map(serde_json::to_value).transpose()?,
+                &result.resources.as_ref().map(serde_json::to_value).transpose()?,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn upsert_run(client: &mut impl postgres::GenericClient, run: &WorkflowRun) -> CoreResult<()> {
+        client.execute(
+            "INSERT INTO workflow_runs (id, workflow_id, status, payload, started_at, completed_at, error) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7) \
+             ON CONFLICT (id) DO UPDATE SET workflow_id = EXCLUDED.workflow_id, status = EXCLUDED.status, \
+             payload = EXCLUDED.payload, started_at = EXCLUDED.started_at, completed_at = EXCLUDED.completed_at, \
+             error = EXCLUDED.error, version = workflow_runs.version + 1",
+            &[
+                &run.id.to_string(),
+                &run.workflow_id,
+                &format!("{:?}", run.status),
+                &run.payload,
+                &run.started_at,
+                &run.completed_at,
+                &run.error,
+            ],
+        )?;
+        Ok(())
+    }
+
+    impl StorageBackend for PostgresBackend {
+        fn name(&self) -> &'static str {
+            "postgres"
+        }
+
+        fn save_workflow(&self, workflow: &WorkflowDefinition) -> CoreResult<()> {
+            let workflow = workflow.clone();
+            let definition = record_compat::workflow_to_record(&workflow)?;
+            self.call(move |client| {
+                client.execute(
+                    "INSERT INTO workflows (id, name, description, definition, created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6) \
+                     ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, description = EXCLUDED.description, \
+                     definition = EXCLUDED.definition, created_at = EXCLUDED.created_at, updated_at = EXCLUDED.updated_at",
+                    &[&workflow.id, &workflow.name, &workflow.description, &definition, &workflow.created_at, &workflow.updated_at],
+                )?;
+                Ok(())
+            })
+        }
+
+        fn get_workflow(&self, id: &str) -> CoreResult<Option<WorkflowDefinition>> {
+            let id = id.to_string();
+            self.call(move |client| {
+                match client.query_opt("SELECT definition FROM workflows WHERE id = $1", &[&id])? {
+                    Some(row) => Ok(Some(record_compat::workflow_from_record(row.try_get(0)?)?)),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        fn get_all_workflows(&self) -> CoreResult<Vec<WorkflowDefinition>> {
+            self.call(|client| {
+                client.query("SELECT definition FROM workflows ORDER BY created_at DESC", &[])?
+                    .iter()
+                    .map(|row| record_compat::workflow_from_record(row.try_get(0)?))
+                    .collect()
+            })
+        }
+
+        fn delete_workflow(&self, id: &str) -> CoreResult<()> {
+            let id = id.to_string();
+            self.call(move |client| {
+                client.execute("DELETE FROM workflows WHERE id = $1", &[&id])?;
+                Ok(())
+            })
+        }
+
+        fn set_workflow_disabled(&self, workflow_id: &str, disabled: Option<&DisabledWorkflow>) -> CoreResult<bool> {
+            let workflow_id = workflow_id.to_string();
+            let disabled = disabled.cloned();
+            self.call(move |client| {
+                let updated = client.execute(
+                    "UPDATE workflows SET disabled = $1, disabled_reason = $2, disabled_at = $3, disabled_until = $4 WHERE id = $5",
+                    &[
+                        &disabled.is_some(),
+                        &disabled.as_ref().and_then(|disabled| disabled.reason.clone()),
+                        &disabled.as_ref().map(|disabled| disabled.disabled_at),
+                        &disabled.as_ref().and_then(|disabled| disabled.until),
+                        &workflow_id,
+                    ],
+                )?;
+                Ok(updated > 0)
+            })
+        }
+
+        fn get_disabled_workflow(&self, workflow_id: &str) -> CoreResult<Option<DisabledWorkflow>> {
+            let workflow_id = workflow_id.to_string();
+            self.call(move |client| {
+                let row = client.query_opt(
+                    "SELECT disabled_reason, disabled_at, disabled_until FROM workflows WHERE id = $1 AND disabled",
+                    &[&workflow_id],
+                )?;
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let disabled_at: Option<DateTime<Utc>> = row.try_get(1)?;
+                Ok(Some(DisabledWorkflow {
+                    workflow_id,
+                    reason: row.try_get(0)?,
+                    disabled_at: disabled_at.unwrap_or_else(Utc::now),
+                    until: row.try_get(2)?,
+                }))
+            })
+        }
+
+        fn save_run(&self, run: &WorkflowRun) -> CoreResult<()> {
+            let run = run.clone();
+            self.call(move |client| upsert_run(client, &run))
+        }
+
+        fn save_run_if_version(&self, run: &WorkflowRun) -> CoreResult<bool> {
+            let run = run.clone();
+            self.call(move |client| {
+                let updated = client.execute(
+                    "UPDATE workflow_runs SET status = $1, payload = $2, completed_at = $3, error = $4, version = version + 1 \
+                     WHERE id = $5 AND version = $6",
+                    &[&format!("{:?}", run.status), &run.payload, &run.completed_at, &run.error, &run.id.to_string(), &(run.version as i64)],
+                )?;
+                Ok(updated > 0)
+            })
+        }
+
+        fn get_run(&self, run_id: &str) -> CoreResult<Option<WorkflowRun>> {
+            let run_id = run_id.to_string();
+            self.call(move |client| {
+                let query = format!("SELECT {} FROM workflow_runs WHERE id = $1", RUN_COLUMNS);
+                client.query_opt(&query, &[&run_id])?.as_ref().map(run_from_row).transpose()
+            })
+        }
+
+        fn get_runs_for_workflow(&self, workflow_id: &str) -> CoreResult<Vec<WorkflowRun>> {
+            let workflow_id = workflow_id.to_string();
+            self.call(move |client| {
+                let query = format!("SELECT {} FROM workflow_runs WHERE workflow_id = $1 ORDER BY started_at DESC", RUN_COLUMNS);
+                client.query(&query, &[&workflow_id])?.iter().map(run_from_row).collect()
+            })
+        }
+
+        fn list_runs(&self, query: &RunQuery) -> CoreResult<RunPage> {
+            if query.pinned.is_some() {
+                return Err(CoreError::Validation(
+                    "Run pins are kept per instance and cannot filter runs listed from Postgres".to_string(),
+                ));
+            }
+            if !query.labels.is_empty() {
+                return Err(CoreError::Validation(
+                    "Run labels are kept per instance and cannot filter runs listed from Postgres".to_string(),
+                ));
+            }
+            let query = query.clone();
+            self.call(move |client| {
+                let mut conditions = Vec::new();
+                let mut params: Vec<Box<dyn ToSql + Sync + Send>> = Vec::new();
+                if let Some(workflow_id) = &query.workflow_id {
+                    params.push(Box::new(workflow_id.clone()));
+                    conditions.push(format!("workflow_id = ${}", params.len()));
+                }
+                if let Some(status) = &query.status {
+                    params.push(Box::new(format!("{:?}", status)));
+                    conditions.push(format!("status = ${}", params.len()));
+                }
+                if let Some(since) = query.since {
+                    params.push(Box::new(since));
+                    conditions.push(format!("started_at >= ${}", params.len()));
+                }
+                let where_clause = if conditions.is_empty() {
+                    String::new()
+                } else {
+                    format!(" WHERE {}", conditions.join(" AND "))
+                };
+                let params: Vec<&(dyn ToSql + Sync)> = params.iter().map(|p| &**p as &(dyn ToSql + Sync)).collect();
+
+                let total: i64 = client
+                    .query_one(&format!("SELECT COUNT(*) FROM workflow_runs{}", where_clause), &params)?
+                    .try_get(0)?;
+                let limit = query.effective_limit();
+                let offset = query.offset.unwrap_or(0);
+                let runs = client
+                    .query(
+                        &format!(
+                            "SELECT {} FROM workflow_runs{} ORDER BY started_at DESC LIMIT {} OFFSET {}",
+                            RUN_COLUMNS, where_clause, limit, offset
+                        ),
+                        &params,
+                    )?
+                    .iter()
+                    .map(|row| Ok(RunListEntry { run: run_from_row(row)?, pin: None, labels: Default::default() }))
+                    .collect::<CoreResult<Vec<_>>>()?;
+
+                Ok(RunPage {
+                    runs,
+                    total: total as usize,
+                    limit,
+                    offset,
+                })
+            })
+        }
+
+        fn get_finished_runs(&self) -> CoreResult<Vec<FinishedRun>> {
+            self.call(|client| {
+                let mut runs = Vec::new();
+                for row in client.query(
+                    "SELECT id, workflow_id, status, started_at, completed_at FROM workflow_runs \
+                     WHERE status NOT IN ('Pending', 'Running') ORDER BY started_at DESC",
+                    &[],
+                )? {
+                    let status: String = row.try_get(2)?;
+                    let Some(status) = record_compat::run_status(&status).filter(|s| s.is_terminal()) else {
+                        continue;
+                    };
+                    let completed_at: Option<chrono::DateTime<chrono::Utc>> = row.try_get(4)?;
+                    runs.push(FinishedRun {
+                        run_id: row.try_get(0)?,
+                        workflow_id: row.try_get(1)?,
+                        status,
+                        finished_at: completed_at.map_or_else(|| row.try_get(3), Ok)?,
+                    });
+                }
+                Ok(runs)
+            })
+        }
+
+        fn delete_runs(&self, run_ids: &[String]) -> CoreResult<usize> {
+            let run_ids = run_ids.to_vec();
+            self.call(move |client| {
+                let mut tx = client.transaction()?;
+                tx.execute("DELETE FROM step_results WHERE run_id = ANY($1)", &[&run_ids])?;
+                tx.execute("DELETE FROM job_leases WHERE job_id IN (SELECT id FROM jobs WHERE run_id = ANY($1))", &[&run_ids])?;
+                tx.execute("DELETE FROM jobs WHERE run_id = ANY($1)", &[&run_ids])?;
+                let deleted = tx.execute("DELETE FROM workflow_runs WHERE id = ANY($1)", &[&run_ids])?;
+                tx.execute(
+                    "DELETE FROM payload_blobs WHERE id NOT IN ( \
+                         SELECT output->>'_payload_blob' FROM step_results WHERE output ? '_payload_blob')",
+                    &[],
+                )?;
+                tx.commit()?;
+                Ok(deleted as usize)
+            })
+        }
+
+        fn save_step_result(&self, result: &StepResult, run_id: &str) -> CoreResult<()> {
+            let result = result.clone();
+            let run_id = run_id.to_string();
+            self.call(move |client| insert_step_result(client, &run_id, &result))
+        }
+
+        fn get_step_results(&self, run_id: &str) -> CoreResult<Vec<StepResult>> {
+            let run_id = run_id.to_string();
+            self.call(move |client| {
+                let query = format!("SELECT {} FROM step_results WHERE run_id = $1 ORDER BY started_at ASC, id ASC", STEP_COLUMNS);
+                client.query(&query, &[&run_id])?.iter().map(step_result_from_row).collect()
+            })
+        }
+
+        fn save_step_results_batch(&self, results: &[(String, StepResult)], runs: &[WorkflowRun]) -> CoreResult<()> {
+            let results = results.to_vec();
+            let runs = runs.to_vec();
+            self.call(move |client| {
+                let mut tx = client.transaction()?;
+                for (run_id, result) in &results {
+                    insert_step_result(&mut tx, run_id, result)?;
+                }
+                for run in &runs {
+                    let updated = tx.execute(
+                        "UPDATE workflow_runs SET status = $1, completed_at = $2, error = $3, version = version + 1 WHERE id = $4 AND version = $5",
+                        &[&format!("{:?}", run.status), &run.completed_at, &run.error, &run.id.to_string(), &(run.version as i64)],
+                    )?;
+                    if updated == 0 {
+                        return Err(CoreError::Conflict(format!("Run {} was written since it was read", run.id)));
+                    }
+                }
+                tx.commit()?;
+                Ok(())
+            })
+        }
+
+        fn save_payload_blob(&self, _run_id: &str, blob: &PayloadBlob) -> CoreResult<()> {
+            let blob = blob.clone();
+            self.call(move |client| {
+                client.execute(
+                    "INSERT INTO payload_blobs (id, data, size_bytes, created_at) VALUES ($1, $2, $3, $4) \
+                     ON CONFLICT (id) DO NOTHING",
+                    &[&blob.id, &blob.data, &(blob.size_bytes as i64), &blob.created_at],
+                )?;
+                Ok(())
+            })
+        }
+
+        fn get_payload_blob(&self, id: &str) -> CoreResult<Option<String>> {
+            let id = id.to_string();
+            self.call(move |client| {
+                match client.query_opt("SELECT data FROM payload_blobs WHERE id = $1", &[&id])? {
+                    Some(row) => Ok(Some(row.try_get(0)?)),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        fn save_trigger(&self, trigger: &StoredTrigger) -> CoreResult<()> {
+            let trigger = trigger.clone();
+            let config = serde_json::to_value(&trigger.trigger)?;
+            self.call(move |client| {
+                client.execute(
+                    "INSERT INTO triggers (id, workflow_id, trigger_type, config, created_at) VALUES ($1, $2, $3, $4, $5) \
+                     ON CONFLICT (id) DO UPDATE SET workflow_id = EXCLUDED.workflow_id, trigger_type = EXCLUDED.trigger_type, \
+                     config = EXCLUDED.config, created_at = EXCLUDED.created_at",
+                    &[&trigger.id, &trigger.workflow_id, &trigger.trigger.get_type(), &config, &trigger.created_at],
+                )?;
+                Ok(())
+            })
+        }
+
+        fn get_triggers(&self, workflow_id: &str) -> CoreResult<Vec<StoredTrigger>> {
+            let workflow_id = workflow_id.to_string();
+            self.call(move |client| {
+                client.query("SELECT id, config, created_at FROM triggers WHERE workflow_id = $1 ORDER BY id ASC", &[&workflow_id])?
+                    .iter()
+                    .map(|row| Ok(StoredTrigger {
+                        id: row.try_get(0)?,
+                        workflow_id: workflow_id.clone(),
+                        trigger: serde_json::from_value(row.try_get(1)?)?,
+                        created_at: row.try_get(2)?,
+                    }))
+                    .collect()
+            })
+        }
+
+        fn delete_triggers(&self, workflow_id: &str) -> CoreResult<usize> {
+            let workflow_id = workflow_id.to_string();
+            self.call(move |client| {
+                Ok(client.execute("DELETE FROM triggers WHERE workflow_id = $1", &[&workflow_id])? as usize)
+            })
+        }
+
+        fn save_job(&self, job: &Job) -> CoreResult<()> {
+            let job = job.clone();
+            let value = serde_json::to_value(&job)?;
+            self.call(move |client| {
+                client.execute(
+                    "INSERT INTO jobs (id, workflow_id, run_id, step_name, state, job, created_at, updated_at) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8) \
+                     ON CONFLICT (id) DO UPDATE SET state = EXCLUDED.state, job = EXCLUDED.job, updated_at = EXCLUDED.updated_at",
+                    &[
+                        &job.id,
+                        &job.workflow_id,
+                        &job.run_id,
+                        &job.step_name,
+                        &format!("{:?}", job.state),
+                        &value,
+                        &job.metadata.created_at,
+                        &job.metadata.updated_at,
+                    ],
+                )?;
+                Ok(())
+            })
+        }
+
+        fn get_job(&self, job_id: &str) -> CoreResult<Option<Job>> {
+            let job_id = job_id.to_string();
+            self.call(move |client| {
+                match client.query_opt("SELECT job FROM jobs WHERE id = $1", &[&job_id])? {
+                    Some(row) => Ok(Some(serde_json::from_value(row.try_get(0)?)?)),
+                    None => Ok(None),
+                }
+            })
+        }
+
+        fn get_jobs_for_run(&self, run_id: &str) -> CoreResult<Vec<Job>> {
+            let run_id = run_id.to_string();
+            self.call(move |client| {
+                client.query("SELECT job FROM jobs WHERE run_id = $1 ORDER BY created_at ASC, id ASC", &[&run_id])?
+                    .iter()
+                    .map(|row| Ok(serde_json::from_value(row.try_get(0)?)?))
+                    .collect()
+            })
+        }
+
+        fn delete_job(&self, job_id: &str) -> CoreResult<()> {
+            let job_id = job_id.to_string();
+            self.call(move |client| {
+                client.execute("DELETE FROM job_leases WHERE job_id = $1", &[&job_id])?;
+                client.execute("DELETE FROM jobs WHERE id = $1", &[&job_id])?;
+                Ok(())
+            })
+        }
+
+        fn claim_job(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>, now: &DateTime<Utc>) -> CoreResult<bool> {
+            let (job_id, owner) = (job_id.to_string(), owner.to_string());
+            let (expires_at, now) = (expires_at.timestamp_millis(), now.timestamp_millis());
+            self.call(move |client| {
+                let claimed = client.execute(
+                    "INSERT INTO job_leases (job_id, owner, expires_at) SELECT $1, $2, $3 \
+                     WHERE NOT EXISTS (SELECT 1 FROM jobs WHERE id = $1 AND state NOT IN ('Pending', 'Running', 'Retrying')) \
+                     ON CONFLICT (job_id) DO UPDATE SET owner = EXCLUDED.owner, expires_at = EXCLUDED.expires_at \
+                     WHERE job_leases.owner = EXCLUDED.owner OR job_leases.expires_at <= $4",
+                    &[&job_id, &owner, &expires_at, &now],
+                )?;
+                Ok(claimed > 0)
+            })
+        }
+
+        fn renew_job_lease(&self, job_id: &str, owner: &str, expires_at: &DateTime<Utc>) -> CoreResult<bool> {
+            let (job_id, owner, expires_at) = (job_id.to_string(), owner.to_string(), expires_at.timestamp_millis());
+            self.call(move |client| {
+                let renewed = client.execute(
+                    "UPDATE job_leases SET expires_at = $1 WHERE job_id = $2 AND owner = $3",
+                    &[&expires_at, &job_id, &owner],
+                )?;
+                Ok(renewed > 0)
+            })
+        }
+
+        fn release_job(&self, job_id: &str, owner: &str) -> CoreResult<()> {
+            let (job_id, owner) = (job_id.to_string(), owner.to_string());
+            self.call(move |client| {
+                client.execute("DELETE FROM job_leases WHERE job_id = $1 AND owner = $2", &[&job_id, &owner])?;
+                Ok(())
+            })
+        }
+
+        fn get_claimable_jobs(&self, now: &DateTime<Utc>, limit: usize) -> CoreResult<Vec<Job>> {
+            let (now, limit) = (now.timestamp_millis(), limit as i64);
+            self.call(move |client| {
+                client.query(
+                    "SELECT j.job FROM jobs j \
+                     JOIN workflow_runs r ON r.id = j.run_id \
+                     LEFT JOIN job_leases l ON l.job_id = j.id \
+                     WHERE j.state IN ('Pending', 'Running', 'Retrying') AND r.status IN ('Pending', 'Running') \
+                     AND (l.job_id IS NULL OR l.expires_at <= $1) \
+                     ORDER BY j.created_at ASC, j.id ASC LIMIT $2",
+                    &[&now, &limit],
+                )?
+                    .iter()
+                    .map(|row| Ok(serde_json::from_value(row.try_get(0)?)?))
+                    .collect()
+            })
+        }
+
+        fn verify_record_compatibility(&self) -> CoreResult<CompatibilityReport> {
+            self.call(|client| {
+                let mut report = CompatibilityReport::default();
+                for row in client.query("SELECT id, definition FROM workflows ORDER BY id", &[])? {
+                    let id: String = row.try_get(0)?;
+                    report.check_workflow(&id, row.try_get(1).map_err(CoreError::from));
+                }
+                // Timestamps are typed columns here, so only statuses can be out of date
+                for row in client.query("SELECT id, status FROM workflow_runs ORDER BY started_at", &[])? {
+                    let id: String = row.try_get(0)?;
+                    let status: String = row.try_get(1)?;
+                    report.check_run(&id, &status, &[]);
+                }
+                for row in client.query("SELECT run_id, step_id, status FROM step_results ORDER BY id", &[])? {
+                    let run_id: String = row.try_get(0)?;
+                    let step_id: String = row.try_get(1)?;
+                    let status: String = row.try_get(2)?;
+                    report.check_step_result(&run_id, &step_id, &status, &[]);
+                }
+                Ok(report)
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::job::{JobPriority, JobState};
+    use crate::models::TriggerDefinition;
+    use chrono::Utc;
+
+    #[test]
+    fn test_sqlite_backend_stores_triggers_and_jobs() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = format!("sqlite://{}", dir.path().join("store.db").display());
+        let store = open_backend(&url).unwrap();
+        assert_eq!(store.name(), "sqlite");
+
+        let now = Utc::now();
+        let mut workflow = WorkflowDefinition {
+            id: "stored".to_string(),
+            name: "Stored".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![
+                TriggerDefinition::Manual,
+                TriggerDefinition::Webhook { path: "/hooks/stored".to_string(), method: "POST".to_string(), validation: None, respond_with: None, response: None, payload_mapping: None, dedup: None, auth: None, lane: None, request_fields: None },
+            ],
+            created_at: now,
+            updated_at: now,
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        store.save_workflow(&workflow).unwrap();
+        for trigger in StoredTrigger::for_workflow(&workflow) {
+            store.save_trigger(&trigger).unwrap();
+        }
+        let triggers = store.get_triggers("stored").unwrap();
+        assert_eq!(triggers.len(), 2);
+        assert_eq!(triggers[0].id, "stored:0");
+        assert_eq!(triggers[1].trigger.get_type(), "webhook");
+
+        workflow.triggers.truncate(1);
+        assert_eq!(store.delete_triggers("stored").unwrap(), 2);
+        for trigger in StoredTrigger::for_workflow(&workflow) {
+            store.save_trigger(&trigger).unwrap();
+        }
+        assert_eq!(store.get_triggers("stored").unwrap().len(), 1);
+
+        let first = Job::new("stored".to_string(), "run-1".to_string(), "fetch".to_string(), serde_json::json!({}), JobPriority::Normal);
+        let mut second = Job::new("stored".to_string(), "run-1".to_string(), "save".to_string(), serde_json::json!({}), JobPriority::High);
+        second.metadata.created_at = first.metadata.created_at + chrono::Duration::milliseconds(1);
+        store.save_job(&first).unwrap();
+        store.save_job(&second).unwrap();
+
+        let mut started = first.clone();
+        started.start().unwrap();
+        store.save_job(&started).unwrap();
+        let jobs = store.get_jobs_for_run("run-1").unwrap();
+        assert_eq!(jobs.iter().map(|j| j.step_name.as_str()).collect::<Vec<_>>(), vec!["fetch", "save"]);
+        assert_eq!(jobs[0].state, JobState::Running);
+        assert_eq!(store.get_job(&second.id).unwrap().unwrap().priority, JobPriority::High);
+
+        store.delete_job(&second.id).unwrap();
+        assert!(store.get_job(&second.id).unwrap().is_none());
+        assert!(store.get_jobs_for_run("run-2").unwrap().is_empty());
+    }
+
+    /// Runs against the Postgres database at `CRONFLOW_TEST_DATABASE_URL`, and is skipped without one
+    #[cfg(feature = "postgres")]
+    #[test]
+    fn test_postgres_backend_stores_runs_steps_and_job_leases() {
+        use crate::models::{RunStatus, StepStatus};
+
+        let Ok(url) = std::env::var("CRONFLOW_TEST_DATABASE_URL") else {
+            eprintln!("CRONFLOW_TEST_DATABASE_URL is not set, skipping the Postgres backend test");
+            return;
+        };
+        let store = open_backend(&url).unwrap();
+        assert_eq!(store.name(), "postgres");
+
+        // Records get unique IDs so the test can share a database with earlier runs of it
+        let now = Utc::now();
+        let workflow_id = format!("pg-{}", uuid::Uuid::new_v4());
+        store.save_workflow(&WorkflowDefinition {
+            id: workflow_id.clone(),
+            name: "Postgres".to_string(),
+            description: None,
+            steps: vec![],
+            triggers: vec![TriggerDefinition::Manual],
+            created_at: now,
+            updated_at: now,
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        assert_eq!(store.get_workflow(&workflow_id).unwrap().unwrap().name, "Postgres");
+
+        let run = WorkflowRun {
+            id: uuid::Uuid::new_v4(),
+            workflow_id: workflow_id.clone(),
+            status: RunStatus::Running,
+            payload: serde_json::json!({"order": 42}),
+            started_at: now,
+            completed_at: None,
+            error: None,
+            version: 0,
+        };
+        let run_id = run.id.to_string();
+        store.save_run(&run).unwrap();
+        let stored = store.get_run(&run_id).unwrap().unwrap();
+        assert_eq!((stored.status.clone(), stored.payload.clone(), stored.version), (RunStatus::Running, serde_json::json!({"order": 42}), 0));
+        let page = store.list_runs(&RunQuery {
+            workflow_id: Some(workflow_id.clone()),
+            status: None,
+            since: None,
+            pinned: None,
+            labels: Default::default(),
+            limit: None,
+            offset: None,
+        }).unwrap();
+        assert_eq!(page.total, 1);
+        assert_eq!(page.runs[0].run.id, run.id);
+
+        // A write from a stale copy of the run is refused
+        let completed = WorkflowRun { status: RunStatus::Completed, completed_at: Some(Utc::now()), ..stored.clone() };
+        assert!(store.save_run_if_version(&completed).unwrap());
+        assert!(!store.save_run_if_version(&WorkflowRun { error: Some("stale".to_string()), ..stored }).unwrap());
+        let stored = store.get_run(&run_id).unwrap().unwrap();
+        assert_eq!((stored.status, stored.error, stored.version), (RunStatus::Completed, None, 1));
+
+        let result = |step_id: &str| StepResult {
+            step_id: step_id.to_string(),
+            status: StepStatus::Completed,
+            output: Some(serde_json::json!({"step": step_id})),
+            error: None,
+            started_at: now,
+            completed_at: Some(now),
+            duration_ms: Some(3),
+            cache_hit: None,
+            resources: None,
+        };
+        store.save_step_result(&result("fetch"), &run_id).unwrap();
+        store.save_step_results_batch(&[(run_id.clone(), result("save"))], &[]).unwrap();
+        let steps = store.get_step_results(&run_id).unwrap();
+        assert_eq!(steps.iter().map(|r| r.step_id.as_str()).collect::<Vec<_>>(), vec!["fetch", "save"]);
+        assert_eq!(steps[1].output, Some(serde_json::json!({"step": "save"})));
+
+        // Jobs of unfinished runs can be claimed by one owner at a time until the lease expires
+        let leased = WorkflowRun { id: uuid::Uuid::new_v4(), status: RunStatus::Running, completed_at: None, ..run };
+        store.save_run(&leased).unwrap();
+        let job = Job::new(workflow_id.clone(), leased.id.to_string(), "fetch".to_string(), serde_json::json!({}), JobPriority::Normal);
+        store.save_job(&job).unwrap();
+        let claimable = |at: DateTime<Utc>| store.get_claimable_jobs(&at, 1000).unwrap().iter().any(|j| j.id == job.id);
+        assert!(claimable(now));
+
+        let expires_at = now + chrono::Duration::seconds(30);
+        assert!(store.claim_job(&job.id, "node-a", &expires_at, &now).unwrap());
+        assert!(!store.claim_job(&job.id, "node-b", &expires_at, &now).unwrap());
+        assert!(!claimable(now));
+        assert!(store.renew_job_lease(&job.id, "node-a", &(expires_at + chrono::Duration::seconds(30))).unwrap());
+        assert!(!store.renew_job_lease(&job.id, "node-b", &expires_at).unwrap());
+
+        let after_expiry = expires_at + chrono::Duration::seconds(60);
+        assert!(claimable(after_expiry));
+        assert!(store.claim_job(&job.id, "node-b", &(after_expiry + chrono::Duration::seconds(30)), &after_expiry).unwrap());
+        store.release_job(&job.id, "node-b").unwrap();
+        assert!(claimable(now));
+
+        assert_eq!(store.delete_runs(&[run_id.clone(), leased.id.to_string()]).unwrap(), 2);
+        assert!(store.get_run(&run_id).unwrap().is_none());
+        assert!(store.get_job(&job.id).unwrap().is_none());
+        store.delete_workflow(&workflow_id).unwrap();
+    }
+
+    #[cfg(not(feature = "postgres"))]
+    #[test]
+    fn test_postgres_url_needs_the_postgres_feature() {
+        assert!(is_postgres_url("postgresql://cronflow@db/cronflow"));
+        let err = open_backend("postgres://cronflow@db/cronflow").err().unwrap();
+        assert!(matches!(err, crate::error::CoreError::Configuration(_)));
+    }
+}
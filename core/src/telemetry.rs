@@ -0,0 +1,224 @@
+//! OpenTelemetry tracing of runs and steps
+//!
+//! Runs outlive any single call into the engine and may be resumed by another
+//! process, so spans are not held open while they run: each span is exported
+//! once what it covers has finished, with its recorded start and end times.
+//! A run's span ID is derived from its run ID, and so is its trace ID unless
+//! the run was started by a request carrying a W3C `traceparent` header, in
+//! which case the run joins the caller's trace. Step, queue wait and database
+//! spans are children of the run span.
+//!
+//! Tracing is off, and costs nothing, until an OTLP endpoint is configured.
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use chrono::{DateTime, Utc};
+use opentelemetry::trace::{Span, SpanContext, SpanId, SpanKind, Status, TraceContextExt, TraceFlags, TraceId, TraceState, Tracer};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use uuid::Uuid;
+use crate::config::TelemetryConfig;
+use crate::error::{CoreError, CoreResult};
+use crate::models::{RunStatus, StepResult, WorkflowRun};
+
+/// Name of the W3C trace context header
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+const TRACER_NAME: &str = "cronflow";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Install the OTLP exporter, when an endpoint is configured
+///
+/// Must be called from within a Tokio runtime, which exports spans in batches.
+/// Later calls are ignored once tracing is on.
+pub fn init(config: &TelemetryConfig) -> CoreResult<()> {
+    let Some(endpoint) = &config.otlp_endpoint else {
+        return Ok(());
+    };
+    if ENABLED.load(Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    let exporter = opentelemetry_otlp::new_exporter()
+        .http()
+        .with_endpoint(endpoint.clone());
+    let trace_config = opentelemetry_sdk::trace::config()
+        .with_resource(opentelemetry_sdk::Resource::new(vec![
+            KeyValue::new("service.name", config.service_name.clone()),
+            KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ]));
+    opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace_config)
+        .install_batch(opentelemetry_sdk::runtime::Tokio)
+        .map_err(|e| CoreError::Configuration(format!("Failed to start the OTLP trace exporter: {}", e)))?;
+
+    ENABLED.store(true, Ordering::SeqCst);
+    log::info!("Exporting traces to {} as {}", endpoint, config.service_name);
+    Ok(())
+}
+
+/// Check whether spans are exported
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::SeqCst)
+}
+
+/// Export the spans still buffered, when tracing is on
+pub fn shutdown() {
+    if ENABLED.swap(false, Ordering::SeqCst) {
+        global::shutdown_tracer_provider();
+    }
+}
+
+/// Parse a W3C `traceparent` header into the remote span context it names
+///
+/// Returns None for malformed headers, unknown versions and invalid IDs.
+pub fn parse_traceparent(header: &str) -> Option<SpanContext> {
+    let parts: Vec<&str> = header.trim().split('-').collect();
+    let [version, trace_id, span_id, flags] = parts[..] else {
+        return None;
+    };
+    if version != "00" {
+        return None;
+    }
+    if trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+        return None;
+    }
+    let trace_id = TraceId::from_hex(trace_id).ok().filter(|id| *id != TraceId::INVALID)?;
+    let span_id = SpanId::from_hex(span_id).ok().filter(|id| *id != SpanId::INVALID)?;
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    Some(SpanContext::new(trace_id, span_id, TraceFlags::new(flags), true, TraceState::default()))
+}
+
+/// Trace a run's spans belong to
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunTrace {
+    run_id: Uuid,
+    /// Span of the caller the run was started by, if any
+    parent: Option<SpanContext>,
+}
+
+impl RunTrace {
+    /// Trace of a run, joining the caller's trace when the run was started with a `traceparent` header
+    pub fn new(run_id: Uuid, traceparent: Option<&str>) -> Self {
+        RunTrace {
+            run_id,
+            parent: traceparent.and_then(parse_traceparent),
+        }
+    }
+
+    /// Trace ID of the run's spans
+    pub fn trace_id(&self) -> TraceId {
+        match &self.parent {
+            Some(parent) => parent.trace_id(),
+            None => TraceId::from_bytes(*self.run_id.as_bytes()),
+        }
+    }
+
+    /// Span ID of the run span
+    pub fn span_id(&self) -> SpanId {
+        let bytes = self.run_id.as_bytes();
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&bytes[8..]);
+        SpanId::from_bytes(id)
+    }
+
+    /// Whether the run's spans are recorded, as decided by the caller for joined traces
+    fn sampled(&self) -> bool {
+        self.parent.as_ref().is_none_or(SpanContext::is_sampled)
+    }
+
+    /// `traceparent` header of the run span, for steps to pass on to the services they call
+    pub fn traceparent(&self) -> String {
+        let flags = if self.sampled() { TraceFlags::SAMPLED } else { TraceFlags::default() };
+        format!("00-{}-{}-{:02x}", self.trace_id(), self.span_id(), flags.to_u8())
+    }
+
+    /// Context the run's child spans are started in
+    fn child_context(&self) -> Context {
+        let flags = if self.sampled() { TraceFlags::SAMPLED } else { TraceFlags::default() };
+        let run_span = SpanContext::new(self.trace_id(), self.span_id(), flags, true, TraceState::default());
+        Context::new().with_remote_span_context(run_span)
+    }
+
+    /// Export a finished span covering `(start, end)`
+    fn export(&self, name: &'static str, parent: Context, span_id: Option<SpanId>, (start, end): (SystemTime, SystemTime), mut attributes: Vec<KeyValue>, error: Option<String>) {
+        attributes.push(KeyValue::new("cronflow.run_id", self.run_id.to_string()));
+        let tracer = global::tracer(TRACER_NAME);
+        let mut builder = tracer.span_builder(name)
+            .with_kind(SpanKind::Internal)
+            .with_trace_id(self.trace_id())
+            .with_start_time(start)
+            .with_attributes(attributes);
+        if let Some(span_id) = span_id {
+            builder = builder.with_span_id(span_id);
+        }
+        let mut span = tracer.build_with_context(builder, &parent);
+        if let Some(error) = error {
+            span.set_status(Status::Error { description: Cow::Owned(error) });
+        }
+        span.end_with_timestamp(end);
+    }
+}
+
+/// Export the span of a finished run
+pub fn export_run(trace: &RunTrace, run: &WorkflowRun) {
+    if !is_enabled() || !run.status.is_terminal() {
+        return;
+    }
+    let parent = match &trace.parent {
+        Some(parent) => Context::new().with_remote_span_context(parent.clone()),
+        None => Context::new(),
+    };
+    let end = run.completed_at.unwrap_or_else(Utc::now);
+    let error = match run.status {
+        RunStatus::Completed => None,
+        _ => Some(run.error.clone().unwrap_or_else(|| run.status.as_str().to_string())),
+    };
+    let attributes = vec![
+        KeyValue::new("cronflow.workflow_id", run.workflow_id.clone()),
+        KeyValue::new("cronflow.run.status", run.status.as_str()),
+    ];
+    trace.export("cronflow.run", parent, Some(trace.span_id()), (run.started_at.into(), end.into()), attributes, error);
+}
+
+/// Export the span of a finished step
+pub fn export_step(trace: &RunTrace, workflow_id: &str, result: &StepResult) {
+    if !is_enabled() || !result.status.is_terminal() {
+        return;
+    }
+    let end = result.completed_at.unwrap_or_else(Utc::now);
+    let error = result.status.is_failure()
+        .then(|| result.error.clone().unwrap_or_else(|| format!("{:?}", result.status)));
+    let attributes = vec![
+        KeyValue::new("cronflow.workflow_id", workflow_id.to_string()),
+        KeyValue::new("cronflow.step_id", result.step_id.clone()),
+        KeyValue::new("cronflow.step.status", format!("{:?}", result.status).to_lowercase()),
+        KeyValue::new("cronflow.step.cache_hit", result.cache_hit.is_some()),
+    ];
+    trace.export("cronflow.step", trace.child_context(), None, (result.started_at.into(), end.into()), attributes, error);
+}
+
+/// Export the span of the time a step's job waited in the queue before a worker took it
+pub fn export_queue_wait(trace: &RunTrace, step_id: &str, queued_at: DateTime<Utc>, dispatched_at: DateTime<Utc>) {
+    if !is_enabled() {
+        return;
+    }
+    let attributes = vec![KeyValue::new("cronflow.step_id", step_id.to_string())];
+    trace.export("cronflow.queue_wait", trace.child_context(), None, (queued_at.into(), dispatched_at.into()), attributes, None);
+}
+
+/// Export the span of a state store operation made for a run
+pub fn export_db(trace: &RunTrace, store: &'static str, operation: &'static str, started_at: SystemTime) {
+    if !is_enabled() {
+        return;
+    }
+    let attributes = vec![
+        KeyValue::new("cronflow.store", store),
+        KeyValue::new("db.operation", operation),
+    ];
+    trace.export("cronflow.db", trace.child_context(), None, (started_at, SystemTime::now()), attributes, None);
+}
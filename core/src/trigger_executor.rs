@@ -5,13 +5,18 @@
 use crate::error::{CoreError, CoreResult};
 use crate::state::StateManager;
 use crate::triggers::{TriggerManager, WebhookRequest};
-use crate::models::WorkflowDefinition;
-use crate::step_orchestrator::StepOrchestrator;
+use crate::models::{OverlapPolicy, RunLabels, RunTrigger, TriggerCounterRecord, TriggerCounters, TriggerDedup, TriggerDefinition, TriggerSchedule, WorkflowDefinition};
 use crate::dispatcher::Dispatcher;
 use crate::job::Job;
-use chrono::Utc;
+use crate::backfill::{Backfill, BackfillProgress};
+use crate::config::{RateLimit, TriggerRateLimitConfig, TriggerStatsConfig};
+use chrono::{DateTime, Utc};
 use log;
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tokio::sync::Mutex as TokioMutex;
 use uuid::Uuid;
 use serde::Serialize;
 
@@ -22,6 +27,18 @@ pub struct TriggerExecutionResult {
     pub run_id: Option<Uuid>,
     pub workflow_id: Option<String>,
     pub message: String,
+    /// Whether the run was buffered because the engine is paused
+    pub buffered: bool,
+    /// Whether a schedule firing was dropped by the overlap policy
+    pub skipped: bool,
+    /// Whether the trigger was rejected by a rate limit
+    pub rate_limited: bool,
+    /// How long to wait before the trigger would be accepted, when rate limited
+    pub retry_after_ms: Option<u64>,
+    /// Whether the trigger duplicated a recent run, which `run_id` points to
+    pub suppressed: bool,
+    /// Whether the trigger was rejected because its workflow is disabled
+    pub disabled: bool,
 }
 
 impl TriggerExecutionResult {
@@ -32,6 +49,92 @@ impl TriggerExecutionResult {
             run_id: Some(run_id),
             workflow_id: Some(workflow_id),
             message: format!("Trigger executed successfully, created run: {}", run_id),
+            buffered: false,
+            skipped: false,
+            rate_limited: false,
+            retry_after_ms: None,
+            suppressed: false,
+            disabled: false,
+        }
+    }
+
+    /// Create a result for a run buffered while the engine is paused
+    pub fn buffered(run_id: Uuid, workflow_id: String) -> Self {
+        Self {
+            success: true,
+            run_id: Some(run_id),
+            workflow_id: Some(workflow_id),
+            message: format!("Engine is paused, buffered run: {}", run_id),
+            buffered: true,
+            skipped: false,
+            rate_limited: false,
+            retry_after_ms: None,
+            suppressed: false,
+            disabled: false,
+        }
+    }
+
+    /// Create a result for a schedule firing skipped because earlier runs are unfinished
+    pub fn skipped(workflow_id: String, message: String) -> Self {
+        Self {
+            success: true,
+            run_id: None,
+            workflow_id: Some(workflow_id),
+            message,
+            buffered: false,
+            skipped: true,
+            rate_limited: false,
+            retry_after_ms: None,
+            suppressed: false,
+            disabled: false,
+        }
+    }
+
+    /// Create a result for a trigger rejected by a rate limit
+    pub fn rate_limited(workflow_id: String, message: String, retry_after_ms: u64) -> Self {
+        Self {
+            success: false,
+            run_id: None,
+            workflow_id: Some(workflow_id),
+            message,
+            buffered: false,
+            skipped: false,
+            rate_limited: true,
+            retry_after_ms: Some(retry_after_ms),
+            suppressed: false,
+            disabled: false,
+        }
+    }
+
+    /// Create a result for a trigger rejected because its workflow is disabled
+    pub fn disabled(workflow_id: String, message: String) -> Self {
+        Self {
+            success: false,
+            run_id: None,
+            workflow_id: Some(workflow_id),
+            message,
+            buffered: false,
+            skipped: false,
+            rate_limited: false,
+            retry_after_ms: None,
+            suppressed: false,
+            disabled: true,
+        }
+    }
+
+    /// Create a result for a trigger suppressed as a duplicate of a recent run
+    pub fn suppressed(run_id: Uuid, workflow_id: String) -> Self {
+        Self {
+            success: true,
+            run_id: Some(run_id),
+            workflow_id: Some(workflow_id),
+            message: format!("Duplicate trigger suppressed, run {} was created within the dedup window", run_id),
+            buffered: false,
+            skipped: false,
+            rate_limited: false,
+            retry_after_ms: None,
+            suppressed: true,
+            disabled: false,
         }
     }
 
@@ -42,72 +145,848 @@ impl TriggerExecutionResult {
             run_id: None,
             workflow_id: None,
             message,
+            buffered: false,
+            skipped: false,
+            rate_limited: false,
+            retry_after_ms: None,
+            suppressed: false,
+            disabled: false,
         }
     }
 }
 
+/// Token bucket refilled continuously at a fixed rate
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl TokenBucket {
+    /// Create a full bucket
+    pub fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            refilled_at: now,
+        }
+    }
+
+    /// Add the tokens accrued since the last refill
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_sec).min(self.limit.burst as f64);
+        self.refilled_at = now;
+    }
+
+    /// Milliseconds until a token is available, zero if one is available now
+    fn wait_ms(&mut self, now: Instant) -> u64 {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            0
+        } else {
+            ((1.0 - self.tokens) / self.limit.per_sec * 1000.0).ceil() as u64
+        }
+    }
+
+    /// Take a token, which must be available
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+/// Global and per-workflow token buckets limiting how fast triggers create runs
+#[derive(Debug)]
+pub struct TriggerRateLimiter {
+    config: TriggerRateLimitConfig,
+    global: Option<TokenBucket>,
+    workflows: HashMap<String, TokenBucket>,
+    /// Per-workflow limits replacing the configured default
+    overrides: HashMap<String, RateLimit>,
+}
+
+impl TriggerRateLimiter {
+    /// Create a rate limiter from configured limits
+    pub fn new(config: TriggerRateLimitConfig) -> Self {
+        Self {
+            global: config.global.map(|limit| TokenBucket::new(limit, Instant::now())),
+            config,
+            workflows: HashMap::new(),
+            overrides: HashMap::new(),
+        }
+    }
+
+    /// Set or clear the limit of a single workflow
+    pub fn set_workflow_limit(&mut self, workflow_id: &str, limit: Option<RateLimit>) {
+        match limit {
+            Some(limit) => { self.overrides.insert(workflow_id.to_string(), limit); }
+            None => { self.overrides.remove(workflow_id); }
+        }
+        self.workflows.remove(workflow_id);
+    }
+
+    /// Take a token for a trigger of a workflow
+    ///
+    /// Tokens are only taken when both the workflow and global buckets have one,
+    /// otherwise a RateLimited error reports how long to wait.
+    pub fn acquire(&mut self, workflow_id: &str, now: Instant) -> CoreResult<()> {
+        let limit = self.overrides.get(workflow_id).or(self.config.per_workflow.as_ref()).copied();
+        let mut workflow_bucket = limit.map(|limit| {
+            self.workflows.entry(workflow_id.to_string()).or_insert_with(|| TokenBucket::new(limit, now))
+        });
+        
+        let workflow_wait = workflow_bucket.as_mut().map_or(0, |bucket| bucket.wait_ms(now));
+        if workflow_wait > 0 {
+            return Err(CoreError::RateLimited {
+                message: format!("Too many triggers for workflow {}", workflow_id),
+                retry_after_ms: workflow_wait,
+            });
+        }
+        let global_wait = self.global.as_mut().map_or(0, |bucket| bucket.wait_ms(now));
+        if global_wait > 0 {
+            return Err(CoreError::RateLimited {
+                message: "Too many triggers across all workflows".to_string(),
+                retry_after_ms: global_wait,
+            });
+        }
+        
+        if let Some(bucket) = workflow_bucket {
+            bucket.take();
+        }
+        if let Some(bucket) = self.global.as_mut() {
+            bucket.take();
+        }
+        Ok(())
+    }
+}
+
+/// Most latencies kept per trigger, however many firings fall in the window
+const MAX_LATENCY_SAMPLES: usize = 10_000;
+
+/// What became of a single trigger firing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerOutcome {
+    Succeeded,
+    Rejected,
+    AuthFailed,
+    RateLimited,
+}
+
+impl TriggerOutcome {
+    /// Classify the result of handling a firing
+    pub fn of(result: &CoreResult<TriggerExecutionResult>) -> Self {
+        match result {
+            Ok(result) if result.rate_limited => TriggerOutcome::RateLimited,
+            Ok(result) if result.success => TriggerOutcome::Succeeded,
+            Ok(_) => TriggerOutcome::Rejected,
+            Err(CoreError::Unauthorized(_) | CoreError::InvalidSignature(_)) => TriggerOutcome::AuthFailed,
+            Err(CoreError::RateLimited { .. }) => TriggerOutcome::RateLimited,
+            Err(_) => TriggerOutcome::Rejected,
+        }
+    }
+
+    /// Classify a webhook firing by the status of its reply
+    pub fn of_status(status: u16) -> Self {
+        match status {
+            200..=399 => TriggerOutcome::Succeeded,
+            401 => TriggerOutcome::AuthFailed,
+            429 => TriggerOutcome::RateLimited,
+            _ => TriggerOutcome::Rejected,
+        }
+    }
+}
+
+/// Latency distribution of a trigger's firings within the latency window
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TriggerLatency {
+    pub samples: usize,
+    pub p50_ms: Option<u64>,
+    pub p95_ms: Option<u64>,
+    pub p99_ms: Option<u64>,
+    pub max_ms: Option<u64>,
+}
+
+/// Firing counts and recent latency of one trigger of a workflow
+#[derive(Debug, Clone, Serialize)]
+pub struct TriggerStatsEntry {
+    pub trigger: String,
+    pub workflow_id: String,
+    /// Persisted counts plus the ones not persisted yet
+    #[serde(flatten)]
+    pub counters: TriggerCounters,
+    pub latency: TriggerLatency,
+    /// Last firing seen by this process
+    pub last_fired_at: Option<DateTime<Utc>>,
+}
+
+/// Firings of one trigger seen by this process
+#[derive(Debug, Default)]
+struct TriggerActivity {
+    /// Counts not persisted yet
+    pending: TriggerCounters,
+    /// Firing times and latencies within the window, oldest first
+    latencies: VecDeque<(DateTime<Utc>, u64)>,
+    last_fired_at: Option<DateTime<Utc>>,
+}
+
+/// Per-trigger firing counts and latencies, shared by the trigger executor and webhook server
+///
+/// Counts build up in memory and are handed out for persisting once per
+/// persist interval, so the database gets one write per interval rather than
+/// per firing. Latencies are only kept in memory, for the latency window.
+#[derive(Debug)]
+pub struct TriggerMetrics {
+    config: TriggerStatsConfig,
+    /// Activity by trigger and workflow
+    triggers: HashMap<(String, String), TriggerActivity>,
+    persisted_at: DateTime<Utc>,
+}
+
+impl TriggerMetrics {
+    /// Create an empty tracker, with the first persist one interval from `now`
+    pub fn new(config: TriggerStatsConfig, now: DateTime<Utc>) -> Self {
+        Self {
+            config,
+            triggers: HashMap::new(),
+            persisted_at: now,
+        }
+    }
+
+    /// Count a firing of a workflow's trigger, keyed as `type:source` or `type`
+    ///
+    /// Returns the counts to persist once the persist interval has passed.
+    pub fn record(
+        &mut self,
+        trigger: &str,
+        workflow_id: &str,
+        outcome: TriggerOutcome,
+        latency_ms: u64,
+        now: DateTime<Utc>,
+    ) -> Option<Vec<TriggerCounterRecord>> {
+        let window_start = now - chrono::Duration::seconds(self.config.latency_window_secs as i64);
+        let activity = self.triggers.entry((trigger.to_string(), workflow_id.to_string())).or_default();
+        activity.pending.fired += 1;
+        match outcome {
+            TriggerOutcome::Succeeded => activity.pending.succeeded += 1,
+            TriggerOutcome::Rejected => activity.pending.rejected += 1,
+            TriggerOutcome::AuthFailed => activity.pending.auth_failed += 1,
+            TriggerOutcome::RateLimited => activity.pending.rate_limited += 1,
+        }
+        activity.latencies.push_back((now, latency_ms));
+        while activity.latencies.front().is_some_and(|(at, _)| *at < window_start)
+            || activity.latencies.len() > MAX_LATENCY_SAMPLES
+        {
+            activity.latencies.pop_front();
+        }
+        activity.last_fired_at = Some(now);
+        
+        let persist_interval = chrono::Duration::seconds(self.config.persist_interval_secs as i64);
+        (now - self.persisted_at >= persist_interval).then(|| self.take_pending(now))
+    }
+
+    /// Take the counts not persisted yet
+    pub fn take_pending(&mut self, now: DateTime<Utc>) -> Vec<TriggerCounterRecord> {
+        self.persisted_at = now;
+        self.triggers.iter_mut()
+            .filter(|(_, activity)| activity.pending.fired > 0)
+            .map(|((trigger, workflow_id), activity)| TriggerCounterRecord {
+                trigger: trigger.clone(),
+                workflow_id: workflow_id.clone(),
+                counters: std::mem::take(&mut activity.pending),
+            })
+            .collect()
+    }
+
+    /// Put back counts that could not be persisted, to go out with the next persist
+    pub fn requeue(&mut self, records: Vec<TriggerCounterRecord>) {
+        for record in records {
+            self.triggers.entry((record.trigger, record.workflow_id)).or_default()
+                .pending.add(&record.counters);
+        }
+    }
+
+    /// Get the stats of every trigger from its persisted counts and this process's activity
+    pub fn entries(&self, persisted: Vec<TriggerCounterRecord>, now: DateTime<Utc>) -> Vec<TriggerStatsEntry> {
+        let window_start = now - chrono::Duration::seconds(self.config.latency_window_secs as i64);
+        let mut counters: HashMap<(String, String), TriggerCounters> = persisted.into_iter()
+            .map(|record| ((record.trigger, record.workflow_id), record.counters))
+            .collect();
+        for (key, activity) in &self.triggers {
+            counters.entry(key.clone()).or_default().add(&activity.pending);
+        }
+        
+        let mut entries: Vec<TriggerStatsEntry> = counters.into_iter()
+            .map(|((trigger, workflow_id), counters)| {
+                let activity = self.triggers.get(&(trigger.clone(), workflow_id.clone()));
+                let latencies: Vec<u64> = activity.into_iter()
+                    .flat_map(|activity| activity.latencies.iter())
+                    .filter(|(at, _)| *at >= window_start)
+                    .map(|(_, latency_ms)| *latency_ms)
+                    .collect();
+                TriggerStatsEntry {
+                    latency: TriggerLatency {
+                        samples: latencies.len(),
+                        p50_ms: crate::models::percentile_ms(&latencies, 50.0),
+                        p95_ms: crate::models::percentile_ms(&latencies, 95.0),
+                        p99_ms: crate::models::percentile_ms(&latencies, 99.0),
+                        max_ms: latencies.iter().max().copied(),
+                    },
+                    last_fired_at: activity.and_then(|activity| activity.last_fired_at),
+                    trigger,
+                    workflow_id,
+                    counters,
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.trigger, &a.workflow_id).cmp(&(&b.trigger, &b.workflow_id)));
+        entries
+    }
+}
+
 /// Trigger executor for handling trigger-to-workflow connections
+///
+/// The state manager is usually the dispatcher's, so the executor never holds
+/// it while calling into the dispatcher, which locks it to submit jobs.
 pub struct TriggerExecutor {
-    state_manager: Arc<Mutex<StateManager>>,
-    trigger_manager: Arc<Mutex<TriggerManager>>,
-    step_orchestrator: StepOrchestrator,
-    job_dispatcher: Arc<Mutex<Arc<tokio::sync::Mutex<Dispatcher>>>>, // Wrapper Arc for async dispatcher
+    state_manager: Arc<TokioMutex<StateManager>>,
+    trigger_manager: Arc<TokioMutex<TriggerManager>>,
+    job_dispatcher: Arc<TokioMutex<Dispatcher>>,
+    rate_limiter: Arc<Mutex<TriggerRateLimiter>>,
+    trigger_metrics: Arc<Mutex<TriggerMetrics>>,
+    accepting_triggers: AtomicBool,
+    /// Backfills started since the engine started, by ID
+    backfills: Mutex<HashMap<String, Backfill>>,
 }
 
 impl TriggerExecutor {
     /// Create a new trigger executor
     pub fn new(
-        state_manager: Arc<Mutex<StateManager>>, 
-        trigger_manager: Arc<Mutex<TriggerManager>>,
-        job_dispatcher: Arc<Mutex<Arc<tokio::sync::Mutex<Dispatcher>>>>
+        state_manager: Arc<TokioMutex<StateManager>>,
+        trigger_manager: Arc<TokioMutex<TriggerManager>>,
+        job_dispatcher: Arc<TokioMutex<Dispatcher>>,
     ) -> Self {
-        let step_orchestrator = StepOrchestrator::new(state_manager.clone());
+        let config = crate::config::CoreConfig::default();
         Self {
             state_manager,
             trigger_manager,
-            step_orchestrator,
             job_dispatcher,
+            rate_limiter: Arc::new(Mutex::new(TriggerRateLimiter::new(config.trigger_rate_limit))),
+            trigger_metrics: Arc::new(Mutex::new(TriggerMetrics::new(config.trigger_stats, Utc::now()))),
+            accepting_triggers: AtomicBool::new(true),
+            backfills: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Stop starting runs from triggers and firing schedules, e.g. while the engine shuts down
+    pub fn stop_accepting_triggers(&self) {
+        self.accepting_triggers.store(false, Ordering::SeqCst);
+    }
+
+    /// Get the rate limiter shared with the webhook server
+    pub fn rate_limiter(&self) -> Arc<Mutex<TriggerRateLimiter>> {
+        Arc::clone(&self.rate_limiter)
+    }
+
+    /// Get the per-trigger stats shared with the webhook server
+    pub fn trigger_metrics(&self) -> Arc<Mutex<TriggerMetrics>> {
+        Arc::clone(&self.trigger_metrics)
+    }
+
+    /// Count a firing in the per-trigger stats, persisting the counts once they are due
+    async fn record_firing(&self, trigger: &str, workflow_id: &str, result: &CoreResult<TriggerExecutionResult>, started: Instant) {
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let due = match self.trigger_metrics.lock() {
+            Ok(mut metrics) => metrics.record(trigger, workflow_id, TriggerOutcome::of(result), latency_ms, Utc::now()),
+            Err(e) => {
+                log::error!("Failed to acquire trigger metrics lock: {}", e);
+                return;
+            }
+        };
+        let Some(due) = due else {
+            return;
+        };
+        
+        let persisted = self.state_manager.lock().await.add_trigger_counters(&due);
+        if let Err(e) = persisted {
+            log::warn!("Failed to persist trigger stats, keeping them for the next attempt: {}", e);
+            if let Ok(mut metrics) = self.trigger_metrics.lock() {
+                metrics.requeue(due);
+            }
+        }
+    }
+
+    /// Set or clear the trigger rate limit of a single workflow
+    pub fn set_workflow_rate_limit(&self, workflow_id: &str, limit: Option<RateLimit>) -> CoreResult<()> {
+        if let Some(limit) = &limit {
+            limit.validate().map_err(CoreError::Validation)?;
         }
+        self.rate_limiter.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire rate limiter lock: {}", e)))?
+            .set_workflow_limit(workflow_id, limit);
+        Ok(())
     }
 
     /// Execute a webhook trigger
-    pub fn execute_webhook_trigger(&self, request: WebhookRequest) -> CoreResult<TriggerExecutionResult> {
+    pub async fn execute_webhook_trigger(&self, request: WebhookRequest) -> CoreResult<TriggerExecutionResult> {
         log::info!("Executing webhook trigger for path: {}", request.path);
         
-        let trigger_manager = self.trigger_manager.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
-        
-        let workflow_id = trigger_manager.get_workflow_id_for_webhook(&request.path)
-            .ok_or_else(|| CoreError::TriggerNotFound(format!("Webhook trigger not found: {}", request.path)))?
-            .clone();
+        let started = Instant::now();
+        let fired = self.trigger_manager.lock().await.resolve_webhook(&request.path)
+            .map(|(trigger, workflow_id, _)| (format!("webhook:{}", trigger.path), workflow_id.clone()));
+        let result = self.run_webhook_trigger(request).await;
+        if let Some((trigger, workflow_id)) = fired {
+            self.record_firing(&trigger, &workflow_id, &result, started).await;
+        }
+        result
+    }
+
+    /// Start the run of a webhook trigger
+    async fn run_webhook_trigger(&self, request: WebhookRequest) -> CoreResult<TriggerExecutionResult> {
+        let (workflow_id, path_params, webhook, dedup, lane) = {
+            let trigger_manager = self.trigger_manager.lock().await;
+            let (trigger, workflow_id, path_params) = trigger_manager.resolve_webhook(&request.path)
+                .ok_or_else(|| CoreError::TriggerNotFound(format!("Webhook trigger not found: {}", request.path)))?;
+            let workflow_id = workflow_id.clone();
+            let webhook = trigger.clone();
+            let dedup = trigger.dedup.clone();
+            let lane = trigger.lane.clone();
+            
+            let body = request.body.as_deref().unwrap_or("");
+            trigger_manager.verify_webhook_signature(&request, body.as_bytes())?;
+            (workflow_id, path_params, webhook, dedup, lane)
+        }; // Lock released here
         
         // Execute the workflow
-        let payload = if let Some(body) = &request.body {
-            serde_json::from_str(body).unwrap_or_else(|_| serde_json::json!({}))
-        } else {
-            serde_json::json!({})
-        };
-        let result = self.execute_workflow(&workflow_id, payload)?;
+        let payload = webhook.build_payload(&request, &path_params, || {
+            let mut payload = if let Some(body) = &request.body {
+                serde_json::from_str(body).unwrap_or_else(|_| serde_json::json!({}))
+            } else {
+                serde_json::json!({})
+            };
+            if let (Some(object), false) = (payload.as_object_mut(), path_params.is_empty()) {
+                object.insert("path_params".to_string(), serde_json::json!(path_params));
+            }
+            Ok(payload)
+        })?;
+        let trigger = RunTrigger::new("webhook", Some(&request.path))
+            .with_trace_parent(request.trace_parent())
+            .with_lane(lane.as_deref());
+        let result = self.execute_workflow(&workflow_id, payload, request.correlation_id(), trigger, dedup.as_ref()).await?;
         
         log::info!("Webhook trigger executed successfully for workflow: {}", workflow_id);
         Ok(result)
     }
 
     /// Execute a manual trigger
-    pub fn execute_manual_trigger(&self, workflow_id: &str, payload: serde_json::Value) -> CoreResult<TriggerExecutionResult> {
+    pub async fn execute_manual_trigger(&self, workflow_id: &str, payload: serde_json::Value) -> CoreResult<TriggerExecutionResult> {
+        self.execute_labeled_manual_trigger(workflow_id, payload, RunLabels::new()).await
+    }
+
+    /// Execute a manual trigger, labelling the run it starts
+    pub async fn execute_labeled_manual_trigger(&self, workflow_id: &str, payload: serde_json::Value, labels: RunLabels) -> CoreResult<TriggerExecutionResult> {
         log::info!("Executing manual trigger for workflow: {}", workflow_id);
         
         // Execute the workflow
-        let result = self.execute_workflow(workflow_id, payload)?;
+        let started = Instant::now();
+        let trigger = RunTrigger::new("manual", None).with_labels(labels);
+        let result = self.execute_workflow(workflow_id, payload, None, trigger, None).await;
+        self.record_firing("manual", workflow_id, &result, started).await;
+        let result = result?;
         
         log::info!("Manual trigger executed successfully for workflow: {}", workflow_id);
         Ok(result)
     }
 
-    /// Execute a workflow run
-    fn execute_workflow(&self, workflow_id: &str, payload: serde_json::Value) -> CoreResult<TriggerExecutionResult> {
-        let mut state_manager = self.state_manager.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+    /// Execute a schedule trigger, applying the overlap policy of the workflow's schedule
+    pub async fn execute_schedule_trigger(&self, workflow_id: &str, payload: serde_json::Value) -> CoreResult<TriggerExecutionResult> {
+        log::info!("Executing schedule trigger for workflow: {}", workflow_id);
+        let started = Instant::now();
+        let result = self.fire_schedule(workflow_id, payload, RunTrigger::new("schedule", None)).await;
+        self.record_firing("schedule", workflow_id, &result, started).await;
+        result
+    }
+
+    /// Start a scheduled run, applying the overlap policy of the workflow's schedule
+    async fn fire_schedule(&self, workflow_id: &str, payload: serde_json::Value, trigger: RunTrigger) -> CoreResult<TriggerExecutionResult> {
+        let (policy, unfinished_runs) = {
+            let state_manager = self.state_manager.lock().await;
+            let workflow = state_manager.get_workflow(workflow_id)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(format!("Workflow not found: {}", workflow_id)))?;
+            (workflow.overlap_policy(), state_manager.get_unfinished_runs(workflow_id)?)
+        };
+        
+        if !unfinished_runs.is_empty() {
+            match policy {
+                OverlapPolicy::Skip => {
+                    log::info!("Skipping schedule firing for workflow {}: {} runs still unfinished", workflow_id, unfinished_runs.len());
+                    return Ok(TriggerExecutionResult::skipped(
+                        workflow_id.to_string(),
+                        format!("Skipped, {} earlier runs are still unfinished", unfinished_runs.len()),
+                    ));
+                }
+                OverlapPolicy::CancelPrevious => {
+                    for run_id in &unfinished_runs {
+                        self.cancel_overlapping_run(workflow_id, run_id).await?;
+                    }
+                }
+                // Queued runs wait in the dispatcher behind a concurrency limit of one
+                OverlapPolicy::Allow | OverlapPolicy::Queue => {}
+            }
+        }
+        
+        self.execute_workflow(workflow_id, payload, None, trigger, None).await
+    }
+
+    /// Fire the interval and one-time triggers due at `now`
+    ///
+    /// Each schedule is moved to its next firing before its run is created, so a
+    /// crash in between drops that firing rather than repeating it. Interval
+    /// firings missed while the engine was down start the runs their catch-up
+    /// policy asks for, each marked with a `catch_up` field in its payload.
+    pub async fn fire_due_schedules(&self, now: DateTime<Utc>) -> CoreResult<Vec<TriggerExecutionResult>> {
+        // Leave schedules where they are so the firing happens after the next start
+        if !self.accepting_triggers.load(Ordering::SeqCst) {
+            return Ok(Vec::new());
+        }
+        
+        let due = {
+            let state_manager = self.state_manager.lock().await;
+            state_manager.get_due_trigger_schedules(now)?
+        }; // Lock released here
+        
+        let mut results = Vec::with_capacity(due.len());
+        for schedule in due {
+            {
+                let state_manager = self.state_manager.lock().await;
+                state_manager.advance_trigger_schedule(&schedule, now)?;
+            } // Lock released here
+            
+            let stats_key = format!("{}:{}", schedule.trigger.get_type(), schedule.trigger_id);
+            if let TriggerDefinition::Polling { .. } = &schedule.trigger {
+                let started = Instant::now();
+                let result = self.poll_trigger(&schedule, now).await;
+                self.record_firing(&stats_key, &schedule.workflow_id, &result, started).await;
+                let result = result
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to poll trigger {}: {}", schedule.trigger_id, e);
+                        TriggerExecutionResult::failure(format!("Failed to poll trigger {}: {}", schedule.trigger_id, e))
+                    });
+                results.push(result);
+                continue;
+            }
+            
+            let (firings, missed) = schedule.due_firings(now);
+            if missed > 0 {
+                log::warn!("Trigger {} of workflow {} missed {} firings since {:?}, catching up with {} runs",
+                    schedule.trigger_id, schedule.workflow_id, missed, schedule.last_fired_at, firings.len());
+            }
+            for scheduled_at in firings {
+                log::info!("Firing {} trigger {} for workflow: {}", schedule.trigger.get_type(), schedule.trigger_id, schedule.workflow_id);
+                let mut payload = serde_json::json!({
+                    "trigger_id": schedule.trigger_id,
+                    "scheduled_at": scheduled_at,
+                });
+                if missed > 0 {
+                    payload["catch_up"] = serde_json::json!({
+                        "missed_firings": missed,
+                        "last_fired_at": schedule.last_fired_at,
+                    });
+                }
+                let trigger = RunTrigger::new(schedule.trigger.get_type(), Some(&schedule.trigger_id));
+                let started = Instant::now();
+                let result = self.fire_schedule(&schedule.workflow_id, payload, trigger).await;
+                self.record_firing(&stats_key, &schedule.workflow_id, &result, started).await;
+                let result = result
+                    .unwrap_or_else(|e| {
+                        log::error!("Failed to fire trigger {}: {}", schedule.trigger_id, e);
+                        TriggerExecutionResult::failure(format!("Failed to fire trigger {}: {}", schedule.trigger_id, e))
+                    });
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Poll the URL of a polling trigger, starting a run when its response changed
+    ///
+    /// The first poll only records a baseline. The run payload carries the
+    /// whole response along with the compared value before and after.
+    async fn poll_trigger(&self, schedule: &TriggerSchedule, now: DateTime<Utc>) -> CoreResult<TriggerExecutionResult> {
+        let TriggerDefinition::Polling { url, headers, auth_secret, compare_path, .. } = &schedule.trigger else {
+            return Err(CoreError::InvalidTrigger(format!("Trigger {} is not a polling trigger", schedule.trigger_id)));
+        };
+        
+        let (previous, auth_token) = {
+            let state_manager = self.state_manager.lock().await;
+            let auth_token = match auth_secret {
+                Some(key) => Some(state_manager.workflow_secrets(&schedule.workflow_id)?
+                    .remove(key)
+                    .ok_or_else(|| CoreError::InvalidTrigger(format!("Polling auth secret not found: {}", key)))?),
+                None => None,
+            };
+            (state_manager.get_trigger_poll_state(&schedule.trigger_id)?, auth_token)
+        }; // Lock released here
+        
+        let response = crate::polling::fetch(url, headers, auth_token.as_deref()).await?;
+        let value = crate::polling::compared_value(&response, compare_path.as_deref())?;
+        let (state, outcome) = crate::polling::observe(&schedule.trigger_id, &schedule.workflow_id, previous.as_ref(), value, now);
+        {
+            let state_manager = self.state_manager.lock().await;
+            state_manager.save_trigger_poll_state(&state)?;
+        } // Lock released here
+        
+        let previous = match outcome {
+            crate::polling::PollOutcome::Baseline => {
+                log::info!("Recorded baseline of polling trigger {} for workflow: {}", schedule.trigger_id, schedule.workflow_id);
+                return Ok(TriggerExecutionResult::skipped(schedule.workflow_id.clone(), format!("Recorded baseline of polling trigger {}", schedule.trigger_id)));
+            }
+            crate::polling::PollOutcome::Unchanged => {
+                return Ok(TriggerExecutionResult::skipped(schedule.workflow_id.clone(), format!("Response of polling trigger {} is unchanged", schedule.trigger_id)));
+            }
+            crate::polling::PollOutcome::Changed { previous } => previous,
+        };
+        
+        log::info!("Firing polling trigger {} for workflow: {}, response of {} changed", schedule.trigger_id, schedule.workflow_id, url);
+        let payload = serde_json::json!({
+            "trigger_id": schedule.trigger_id,
+            "url": url,
+            "data": response,
+            "value": state.value,
+            "previous": previous,
+            "polled_at": now,
+        });
+        self.fire_schedule(&schedule.workflow_id, payload, RunTrigger::new("polling", Some(&schedule.trigger_id))).await
+    }
+
+    /// Start a backfill creating runs of a workflow for its firings between `from` and `to`
+    ///
+    /// Runs are created right away up to `max_concurrent`, the rest as earlier
+    /// ones finish, each time `advance_backfills` is called.
+    pub async fn start_backfill(&self, workflow_id: &str, from: DateTime<Utc>, to: DateTime<Utc>, interval_secs: Option<u64>, max_concurrent: usize) -> CoreResult<BackfillProgress> {
+        if !self.accepting_triggers.load(Ordering::SeqCst) {
+            return Err(CoreError::State(format!("Engine is shutting down, not backfilling workflow {}", workflow_id)));
+        }
+        
+        let workflow = {
+            let state_manager = self.state_manager.lock().await;
+            state_manager.get_workflow(workflow_id)?
+                .ok_or_else(|| CoreError::WorkflowNotFound(format!("Workflow not found: {}", workflow_id)))?
+        }; // Lock released here
+        
+        let backfill = Backfill::new(&workflow, from, to, interval_secs, max_concurrent)?;
+        let backfill_id = backfill.progress().id.clone();
+        log::info!("Starting backfill {} of workflow {} from {} to {}: {} runs, at most {} at once",
+            backfill_id, workflow_id, from, to, backfill.progress().total, max_concurrent);
+        self.lock_backfills()?.insert(backfill_id.clone(), backfill);
+        
+        self.advance_backfills().await?;
+        self.get_backfill(&backfill_id)
+    }
+
+    /// Create the runs running backfills have room for, returning their progress
+    ///
+    /// Firings rejected by a rate limit, a disabled workflow or an error are
+    /// tried again on the next call.
+    pub async fn advance_backfills(&self) -> CoreResult<Vec<BackfillProgress>> {
+        if !self.accepting_triggers.load(Ordering::SeqCst) {
+            return Ok(Vec::new());
+        }
+        
+        let in_flight: Vec<(String, Vec<Uuid>)> = self.lock_backfills()?.iter()
+            .filter(|(_, backfill)| backfill.is_running())
+            .map(|(id, backfill)| (id.clone(), backfill.in_flight().to_vec()))
+            .collect();
+        let mut finished = Vec::with_capacity(in_flight.len());
+        {
+            let state_manager = self.state_manager.lock().await;
+            for (backfill_id, run_ids) in &in_flight {
+                let mut runs = Vec::new();
+                for run_id in run_ids {
+                    match state_manager.get_run(run_id)? {
+                        Some(run) if run.status.is_terminal() => runs.push((*run_id, run.status)),
+                        Some(_) => {}
+                        // A deleted run will not finish
+                        None => runs.push((*run_id, crate::models::RunStatus::Cancelled)),
+                    }
+                }
+                finished.push((backfill_id.clone(), runs));
+            }
+        } // Lock released here
+        
+        let due: Vec<(String, String, Vec<DateTime<Utc>>)> = {
+            let mut backfills = self.lock_backfills()?;
+            finished.iter()
+                .filter_map(|(backfill_id, runs)| {
+                    let backfill = backfills.get_mut(backfill_id)?;
+                    backfill.record_finished(runs);
+                    Some((backfill_id.clone(), backfill.progress().workflow_id.clone(), backfill.take_due()))
+                })
+                .collect()
+        };
+        
+        for (backfill_id, workflow_id, slots) in due {
+            let mut slots = slots.into_iter();
+            while let Some(scheduled_at) = slots.next() {
+                let payload = serde_json::json!({
+                    "backfill_id": backfill_id,
+                    "scheduled_at": scheduled_at,
+                });
+                let trigger = RunTrigger::new(crate::backfill::TRIGGER_TYPE, Some(&backfill_id));
+                let started = Instant::now();
+                let result = self.execute_workflow(&workflow_id, payload, None, trigger, None).await;
+                self.record_firing(&format!("{}:{}", crate::backfill::TRIGGER_TYPE, backfill_id), &workflow_id, &result, started).await;
+                
+                let mut backfills = self.lock_backfills()?;
+                let Some(backfill) = backfills.get_mut(&backfill_id) else {
+                    break;
+                };
+                match result {
+                    Ok(TriggerExecutionResult { run_id: Some(run_id), .. }) => backfill.record_created(run_id),
+                    outcome => {
+                        let reason = outcome.map(|result| result.message).unwrap_or_else(|e| e.to_string());
+                        log::warn!("Backfill {} of workflow {} could not create the run for {}, trying again later: {}",
+                            backfill_id, workflow_id, scheduled_at, reason);
+                        backfill.put_back(std::iter::once(scheduled_at).chain(slots).collect());
+                        break;
+                    }
+                }
+            }
+        }
+        
+        let backfills = self.lock_backfills()?;
+        Ok(in_flight.iter()
+            .filter_map(|(backfill_id, _)| backfills.get(backfill_id))
+            .map(|backfill| backfill.progress().clone())
+            .collect())
+    }
+
+    /// Get the progress of a backfill
+    pub fn get_backfill(&self, backfill_id: &str) -> CoreResult<BackfillProgress> {
+        self.lock_backfills()?.get(backfill_id)
+            .map(|backfill| backfill.progress().clone())
+            .ok_or_else(|| CoreError::Validation(format!("Backfill not found: {}", backfill_id)))
+    }
+
+    /// Stop a backfill from creating more runs, leaving the ones it created to finish
+    pub fn cancel_backfill(&self, backfill_id: &str) -> CoreResult<BackfillProgress> {
+        let mut backfills = self.lock_backfills()?;
+        let backfill = backfills.get_mut(backfill_id)
+            .ok_or_else(|| CoreError::Validation(format!("Backfill not found: {}", backfill_id)))?;
+        backfill.cancel();
+        log::info!("Cancelled backfill {} of workflow {}", backfill_id, backfill.progress().workflow_id);
+        Ok(backfill.progress().clone())
+    }
+
+    /// Check if any backfill still has runs to create or wait for
+    pub fn has_running_backfills(&self) -> CoreResult<bool> {
+        Ok(self.lock_backfills()?.values().any(Backfill::is_running))
+    }
+
+    fn lock_backfills(&self) -> CoreResult<std::sync::MutexGuard<'_, HashMap<String, Backfill>>> {
+        self.backfills.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire backfills lock: {}", e)))
+    }
+
+    /// Get when the next interval, polling or one-time trigger is due
+    pub async fn next_schedule_at(&self) -> CoreResult<Option<DateTime<Utc>>> {
+        let state_manager = self.state_manager.lock().await;
+        state_manager.get_next_trigger_fire_at()
+    }
+
+    /// Publish an event, starting a run of every subscribed workflow
+    ///
+    /// The payload is checked against the schema of the given version, or of
+    /// the latest one, and every subscriber must accept that version; nothing
+    /// runs when either check fails.
+    pub async fn publish_event(&self, event_name: &str, version: Option<u32>, payload: serde_json::Value) -> CoreResult<EventPublication> {
+        log::info!("Publishing event: {}", event_name);
+        
+        let schema = {
+            let state_manager = self.state_manager.lock().await;
+            state_manager.get_event_schema(event_name, version)?
+        }; // Lock released here
+        let version = schema.as_ref().map(|s| s.version);
+        
+        let subscribers = self.trigger_manager.lock().await.get_event_subscribers(event_name);
+        if let Some(schema) = &schema {
+            crate::event_schemas::validate_payload(schema, &payload)?;
+            crate::event_schemas::check_publication(event_name, schema.version, &subscribers)?;
+        }
+        
+        let dedups: Vec<Option<TriggerDedup>> = {
+            let state_manager = self.state_manager.lock().await;
+            subscribers.iter()
+                .map(|(workflow_id, _)| Ok(state_manager.get_workflow(workflow_id)?
+                    .and_then(|workflow| workflow.event_dedup(event_name).cloned())))
+                .collect::<CoreResult<_>>()?
+        }; // Lock released here
+        
+        let run_payload = crate::event_schemas::run_payload(event_name, version, &payload, Utc::now());
+        let mut results = Vec::with_capacity(subscribers.len());
+        let stats_key = format!("event:{}", event_name);
+        for ((workflow_id, _), dedup) in subscribers.iter().zip(&dedups) {
+            let started = Instant::now();
+            let result = self.execute_workflow(workflow_id, run_payload.clone(), None, RunTrigger::new("event", Some(event_name)), dedup.as_ref()).await;
+            self.record_firing(&stats_key, workflow_id, &result, started).await;
+            let result = result
+                .unwrap_or_else(|e| {
+                    log::error!("Failed to start workflow {} for event {}: {}", workflow_id, event_name, e);
+                    TriggerExecutionResult::failure(format!("Failed to start workflow {}: {}", workflow_id, e))
+                });
+            results.push(result);
+        }
+        
+        log::info!("Published event {} to {} workflows", event_name, results.len());
+        Ok(EventPublication {
+            event_name: event_name.to_string(),
+            version,
+            results,
+        })
+    }
+
+    /// Cancel a run that a new schedule firing replaces
+    async fn cancel_overlapping_run(&self, workflow_id: &str, run_id: &Uuid) -> CoreResult<()> {
+        self.job_dispatcher.lock().await.cancel_run(workflow_id, &run_id.to_string()).await;
+        
+        let mut state_manager = self.state_manager.lock().await;
+        state_manager.cancel_run(run_id, "Cancelled by a newer schedule firing")?;
+        Ok(())
+    }
+
+    /// Execute a workflow run started by `trigger`, optionally addressable by an external correlation ID
+    ///
+    /// With `dedup`, a trigger duplicating a recent run is suppressed before it
+    /// counts against the rate limits.
+    async fn execute_workflow(&self, workflow_id: &str, payload: serde_json::Value, correlation_id: Option<&str>, trigger: RunTrigger, dedup: Option<&TriggerDedup>) -> CoreResult<TriggerExecutionResult> {
+        if !self.accepting_triggers.load(Ordering::SeqCst) {
+            return Err(CoreError::State(format!("Engine is shutting down, not starting workflow {}", workflow_id)));
+        }
+        crate::models::validate_run_labels(&trigger.labels).map_err(CoreError::Validation)?;
+        
+        // Held until the run is created, so concurrent duplicates cannot both pass the dedup check
+        let mut state_manager = self.state_manager.lock().await;
+        match state_manager.check_workflow_enabled(workflow_id) {
+            Err(CoreError::WorkflowDisabled(message)) => {
+                log::warn!("Rejected trigger for workflow {}: {}", workflow_id, message);
+                return Ok(TriggerExecutionResult::disabled(workflow_id.to_string(), format!("Workflow {}", message)));
+            }
+            checked => checked?,
+        }
+        if let Some(dedup) = dedup {
+            if let Some(suppressed) = state_manager.find_duplicate_run(workflow_id, dedup, &payload, &trigger)? {
+                return Ok(TriggerExecutionResult::suppressed(suppressed.run_id, workflow_id.to_string()));
+            }
+        }
+        
+        let acquired = self.rate_limiter.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire rate limiter lock: {}", e)))?
+            .acquire(workflow_id, Instant::now());
+        if let Err(CoreError::RateLimited { message, retry_after_ms }) = acquired {
+            log::warn!("Rejected trigger for workflow {}: {}", workflow_id, message);
+            return Ok(TriggerExecutionResult::rate_limited(workflow_id.to_string(), message, retry_after_ms));
+        }
+        acquired?;
         
         // Verify workflow exists
         let workflow = state_manager.get_workflow(workflow_id)?
@@ -116,11 +995,26 @@ impl TriggerExecutor {
         workflow.validate()
             .map_err(|e| CoreError::InvalidWorkflow(e))?;
         
-        let run_id = state_manager.create_run(workflow_id, payload.clone())?;
+        let run_id = state_manager.create_correlated_run(workflow_id, payload.clone(), crate::models::RunEvent::ACTOR_TRIGGER, correlation_id)?;
+        state_manager.record_run_trigger(&run_id, &trigger)?;
+        if !trigger.labels.is_empty() {
+            state_manager.set_run_labels(&run_id, &trigger.labels)?;
+        }
+        if let Some(dedup) = dedup {
+            state_manager.record_dedup_key(workflow_id, dedup, &payload, &run_id, &trigger)?;
+        }
         
         log::info!("Created workflow run: {} for workflow: {}", run_id, workflow_id);
         
-        match self.create_and_submit_jobs(&workflow, &run_id, &payload) {
+        if state_manager.is_engine_paused()? {
+            state_manager.buffer_run(&run_id, workflow_id)?;
+            log::info!("Engine is paused, buffered run: {}", run_id);
+            return Ok(TriggerExecutionResult::buffered(run_id, workflow_id.to_string()));
+        }
+        // The dispatcher locks the same state manager when jobs are submitted
+        drop(state_manager);
+        
+        match self.create_and_submit_jobs(&workflow, &run_id, &payload).await {
             Ok(job_count) => {
                 log::info!("Successfully submitted {} jobs for workflow run: {}", job_count, run_id);
             }
@@ -133,8 +1027,79 @@ impl TriggerExecutor {
         Ok(TriggerExecutionResult::success(run_id, workflow_id.to_string()))
     }
 
+    /// Pause the engine: workers stop dequeuing and trigger-created runs are buffered
+    pub async fn pause_engine(&self) -> CoreResult<()> {
+        {
+            let state_manager = self.state_manager.lock().await;
+            state_manager.set_engine_paused(true)?;
+        }
+        
+        self.set_dispatcher_paused(true).await;
+        log::info!("Engine paused");
+        Ok(())
+    }
+
+    /// Resume the engine, releasing buffered runs in the order they were created
+    ///
+    /// Returns the number of released runs.
+    pub async fn resume_engine(&self) -> CoreResult<usize> {
+        // The engine stays paused until the buffer is empty, so runs triggered
+        // during the release are buffered behind it and released in order
+        let mut released = 0;
+        loop {
+            let next = {
+                let mut state_manager = self.state_manager.lock().await;
+                match state_manager.get_buffered_runs()?.first().copied() {
+                    Some(run_id) => {
+                        let run = state_manager.get_run(&run_id)?
+                            .ok_or_else(|| CoreError::RunNotFound(run_id.to_string()))?;
+                        let workflow = state_manager.get_run_workflow(&run.workflow_id, &run.id)?;
+                        if workflow.is_none() {
+                            log::warn!("Workflow {} of buffered run {} no longer exists", run.workflow_id, run_id);
+                            state_manager.complete_run(&run_id, crate::models::RunStatus::Failed, Some(format!("Workflow not found: {}", run.workflow_id)))?;
+                        }
+                        Some((run, workflow))
+                    }
+                    None => {
+                        state_manager.set_engine_paused(false)?;
+                        None
+                    }
+                }
+            }; // Lock released here
+            
+            let Some((run, workflow)) = next else {
+                break;
+            };
+            if let Some(workflow) = workflow {
+                let job_count = self.create_and_submit_jobs(&workflow, &run.id, &run.payload).await?;
+                log::info!("Released buffered run {} with {} jobs", run.id, job_count);
+            }
+            self.state_manager.lock().await.remove_buffered_run(&run.id)?;
+            released += 1;
+        }
+        
+        self.set_dispatcher_paused(false).await;
+        log::info!("Engine resumed, released {} buffered runs", released);
+        Ok(released)
+    }
+
+    /// Get whether the engine is paused and how many runs are buffered
+    pub async fn get_engine_status(&self) -> CoreResult<EngineStatus> {
+        let state_manager = self.state_manager.lock().await;
+        
+        Ok(EngineStatus {
+            paused: state_manager.is_engine_paused()?,
+            buffered_runs: state_manager.get_buffered_runs()?.len(),
+        })
+    }
+
+    /// Stop or restart job dequeuing in the dispatcher
+    async fn set_dispatcher_paused(&self, paused: bool) {
+        self.job_dispatcher.lock().await.set_paused(paused).await;
+    }
+
     /// Create and submit jobs for workflow steps
-    fn create_and_submit_jobs(&self, workflow: &WorkflowDefinition, run_id: &Uuid, payload: &serde_json::Value) -> CoreResult<usize> {
+    async fn create_and_submit_jobs(&self, workflow: &WorkflowDefinition, run_id: &Uuid, payload: &serde_json::Value) -> CoreResult<usize> {
         log::info!("Creating jobs for workflow: {} run: {}", workflow.id, run_id);
         
         let run = crate::models::WorkflowRun {
@@ -145,32 +1110,30 @@ impl TriggerExecutor {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         };
         
         let jobs = Job::create_workflow_jobs(workflow, &run, payload.clone())?;
         
-        // Submit all jobs to the dispatcher (async call from sync context)
-        let rt = tokio::runtime::Handle::try_current()
-            .map_err(|_| CoreError::Internal("No tokio runtime available".to_string()))?;
+        let dispatcher = self.job_dispatcher.lock().await;
+        
+        // Apply the workflow's run concurrency limit, namespace and resource quotas before its jobs can be dequeued
+        let concurrency = match workflow.overlap_policy() {
+            OverlapPolicy::Queue => Some(1),
+            _ => workflow.concurrency,
+        };
+        dispatcher.set_workflow_concurrency(&workflow.id, concurrency).await;
+        dispatcher.set_workflow_namespace(&workflow.id, workflow.namespace.as_deref()).await;
+        dispatcher.set_workflow_resource_quota(&workflow.id, workflow.resource_quota.clone()).await;
+        if let Some(key) = workflow.serialization_key(payload) {
+            dispatcher.serialize_run(&workflow.id, run_id, &key).await?;
+        }
         
         let job_count = jobs.len();
         for job in jobs {
             let job_id = job.id.clone();
             let step_name = job.step_name.clone();
-            
-            // Get dispatcher Arc from sync mutex, then call async submit_job
-            let dispatcher_arc = {
-                let guard = self.job_dispatcher.lock()
-                    .map_err(|e| CoreError::Internal(format!("Failed to acquire dispatcher lock: {}", e)))?;
-                // dispatcher inside is Arc<TokioMutex<Dispatcher>>, so we can clone the Arc
-                guard.clone()
-            }; // Sync mutex guard dropped here
-            
-            // Now call async submit_job (it uses tokio mutex internally)
-            rt.block_on(async {
-                let dispatcher_guard = dispatcher_arc.lock().await;
-                dispatcher_guard.submit_job(job).await
-            })?;
+            dispatcher.submit_job(job).await?;
             
             log::debug!("Submitted job: {} for step: {}", job_id, step_name);
         }
@@ -180,9 +1143,8 @@ impl TriggerExecutor {
     }
 
     /// Get all active triggers for a workflow
-    pub fn get_workflow_triggers(&self, workflow_id: &str) -> CoreResult<Vec<String>> {
-        let trigger_manager = self.trigger_manager.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
+    pub async fn get_workflow_triggers(&self, workflow_id: &str) -> CoreResult<Vec<String>> {
+        let trigger_manager = self.trigger_manager.lock().await;
         
         let mut triggers = Vec::new();
         
@@ -195,17 +1157,40 @@ impl TriggerExecutor {
     }
 
     /// Register triggers for a workflow
-    pub fn register_workflow_triggers(&self, workflow_id: &str, workflow: &WorkflowDefinition) -> CoreResult<Vec<String>> {
+    pub async fn register_workflow_triggers(&self, workflow_id: &str, workflow: &WorkflowDefinition) -> CoreResult<Vec<String>> {
         log::info!("Registering triggers for workflow: {}", workflow_id);
         
         let mut trigger_ids = Vec::new();
-        let mut trigger_manager = self.trigger_manager.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
+        let mut trigger_manager = self.trigger_manager.lock().await;
         
         for trigger_def in &workflow.triggers {
             match trigger_def {
-                crate::models::TriggerDefinition::Webhook { path, method } => {
-                    let webhook_trigger = crate::triggers::WebhookTrigger::new(path.clone(), method.clone());
+                crate::models::TriggerDefinition::Webhook { path, method, validation, respond_with, response, payload_mapping, dedup, auth, lane, request_fields } => {
+                    let mut webhook_trigger = crate::triggers::WebhookTrigger::new(path.clone(), method.clone());
+                    if let Some(validation) = validation {
+                        webhook_trigger = webhook_trigger.with_validation(validation.clone());
+                    }
+                    if let Some(respond_with) = respond_with {
+                        webhook_trigger = webhook_trigger.with_reply(respond_with.clone());
+                    }
+                    if let Some(response) = response {
+                        webhook_trigger = webhook_trigger.with_response(response.as_ref().clone());
+                    }
+                    if let Some(payload_mapping) = payload_mapping {
+                        webhook_trigger = webhook_trigger.with_payload_mapping(payload_mapping.as_ref().clone());
+                    }
+                    if let Some(dedup) = dedup {
+                        webhook_trigger = webhook_trigger.with_dedup(dedup.clone());
+                    }
+                    if let Some(auth) = auth {
+                        webhook_trigger = webhook_trigger.with_auth(auth.as_ref().clone());
+                    }
+                    if let Some(lane) = lane {
+                        webhook_trigger = webhook_trigger.with_lane(lane.clone());
+                    }
+                    if let Some(request_fields) = request_fields {
+                        webhook_trigger = webhook_trigger.with_request_fields(request_fields.as_ref().clone());
+                    }
                     
                     trigger_manager.register_webhook_trigger(workflow_id, webhook_trigger)?;
                     trigger_ids.push(format!("webhook:{}", path));
@@ -213,6 +1198,35 @@ impl TriggerExecutor {
                     log::info!("Registered webhook trigger: {} {} for workflow: {}", method, path, workflow_id);
                 }
                 
+                crate::models::TriggerDefinition::Schedule { cron_expression, overlap_policy } => {
+                    // Firing is driven by the SDK scheduler through execute_schedule_trigger
+                    trigger_ids.push(format!("schedule:{}", cron_expression));
+                    log::info!("Registered schedule trigger: {} ({:?} overlap) for workflow: {}", cron_expression, overlap_policy, workflow_id);
+                }
+                
+                crate::models::TriggerDefinition::Interval { every_seconds, overlap_policy, catch_up } => {
+                    // Firing is driven by fire_due_schedules from the persisted schedule
+                    trigger_ids.push(format!("interval:{}s", every_seconds));
+                    log::info!("Registered interval trigger: every {}s ({:?} overlap, {:?} catch-up) for workflow: {}", every_seconds, overlap_policy, catch_up, workflow_id);
+                }
+                
+                crate::models::TriggerDefinition::Polling { url, every_seconds, .. } => {
+                    // Polled by fire_due_schedules from the persisted schedule
+                    trigger_ids.push(format!("polling:{}", url));
+                    log::info!("Registered polling trigger: {} every {}s for workflow: {}", url, every_seconds, workflow_id);
+                }
+                
+                crate::models::TriggerDefinition::At { run_at } => {
+                    trigger_ids.push(format!("at:{}", run_at.to_rfc3339()));
+                    log::info!("Registered one-time trigger at {} for workflow: {}", run_at, workflow_id);
+                }
+                
+                crate::models::TriggerDefinition::Event { event_name, versions, .. } => {
+                    trigger_manager.register_event_subscription(workflow_id, event_name, versions.clone());
+                    trigger_ids.push(format!("event:{}", event_name));
+                    log::info!("Registered event trigger: {} (versions {:?}) for workflow: {}", event_name, versions, workflow_id);
+                }
+                
                 crate::models::TriggerDefinition::Manual => {
                     // Manual triggers don't need registration
                     trigger_ids.push("manual".to_string());
@@ -226,32 +1240,28 @@ impl TriggerExecutor {
     }
 
     /// Unregister all triggers for a workflow
-    pub fn unregister_workflow_triggers(&self, workflow_id: &str) -> CoreResult<()> {
+    pub async fn unregister_workflow_triggers(&self, workflow_id: &str) -> CoreResult<()> {
         log::info!("Unregistering triggers for workflow: {}", workflow_id);
         
-        let mut trigger_manager = self.trigger_manager.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
+        let mut trigger_manager = self.trigger_manager.lock().await;
         
-        // Remove webhook triggers
-        let webhook_paths: Vec<String> = trigger_manager.webhook_triggers
-            .iter()
-            .filter(|(_, (_, wf_id))| wf_id == workflow_id)
-            .map(|(path, _)| path.clone())
-            .collect();
-        
-        for path in webhook_paths {
-            trigger_manager.webhook_triggers.remove(&path);
+        // Remove webhook triggers, which the running webhook server stops routing to at once
+        for path in trigger_manager.remove_webhook_triggers(workflow_id) {
             log::info!("Removed webhook trigger: {} for workflow: {}", path, workflow_id);
         }
+        trigger_manager.remove_event_subscriptions(workflow_id);
         
         log::info!("Successfully unregistered all triggers for workflow: {}", workflow_id);
         Ok(())
     }
 
     /// Get trigger statistics
-    pub fn get_trigger_stats(&self) -> CoreResult<TriggerStats> {
-        let trigger_manager = self.trigger_manager.lock()
-            .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
+    pub async fn get_trigger_stats(&self) -> CoreResult<TriggerStats> {
+        let persisted = self.state_manager.lock().await.get_trigger_counters()?;
+        let triggers = self.trigger_metrics.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger metrics lock: {}", e)))?
+            .entries(persisted, Utc::now());
+        let trigger_manager = self.trigger_manager.lock().await;
         
         let webhook_count = trigger_manager.webhook_triggers.len();
         let total_triggers = webhook_count;
@@ -260,16 +1270,42 @@ impl TriggerExecutor {
             total_triggers,
             webhook_triggers: webhook_count,
             schedule_triggers: 0, // No longer using Rust scheduler
+            webhook_auth_failures: trigger_manager.auth_failures.values().sum(),
+            auth_failures_by_path: trigger_manager.auth_failures.clone(),
+            triggers,
         })
     }
 }
 
+/// Global pause state of the engine
+#[derive(Debug, Clone, Serialize)]
+pub struct EngineStatus {
+    pub paused: bool,
+    /// Number of runs waiting for the engine to resume
+    pub buffered_runs: usize,
+}
+
+/// Outcome of publishing an event
+#[derive(Debug, Clone, Serialize)]
+pub struct EventPublication {
+    pub event_name: String,
+    /// Schema version the payload was checked against, None for untyped events
+    pub version: Option<u32>,
+    /// One result per subscribed workflow
+    pub results: Vec<TriggerExecutionResult>,
+}
+
 /// Statistics about triggers
 #[derive(Debug, Clone, Serialize)]
 pub struct TriggerStats {
     pub total_triggers: usize,
     pub webhook_triggers: usize,
     pub schedule_triggers: usize,
+    /// Webhook requests rejected for missing or wrong credentials
+    pub webhook_auth_failures: u64,
+    pub auth_failures_by_path: HashMap<String, u64>,
+    /// Firing counts and latency of each trigger of each workflow
+    pub triggers: Vec<TriggerStatsEntry>,
 }
 
 impl TriggerStats {
@@ -279,6 +1315,9 @@ impl TriggerStats {
             total_triggers: 0,
             webhook_triggers: 0,
             schedule_triggers: 0,
+            webhook_auth_failures: 0,
+            auth_failures_by_path: HashMap::new(),
+            triggers: Vec::new(),
         }
     }
 }
@@ -286,7 +1325,7 @@ impl TriggerStats {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::{WorkflowDefinition, TriggerDefinition, StepDefinition};
+    use crate::models::{WorkflowDefinition, TriggerDefinition, TriggerDedup, StepDefinition, CatchUpPolicy};
     use chrono::Utc;
 
     #[test]
@@ -311,4 +1350,659 @@ mod tests {
         assert_eq!(stats.webhook_triggers, 0);
         assert_eq!(stats.schedule_triggers, 0);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_rate_limiter_token_buckets() {
+        let start = Instant::now();
+        let mut limiter = TriggerRateLimiter::new(TriggerRateLimitConfig {
+            global: Some(RateLimit { per_sec: 10.0, burst: 3 }),
+            per_workflow: Some(RateLimit { per_sec: 1.0, burst: 2 }),
+        });
+
+        assert!(limiter.acquire("noisy", start).is_ok());
+        assert!(limiter.acquire("noisy", start).is_ok());
+        match limiter.acquire("noisy", start) {
+            Err(CoreError::RateLimited { retry_after_ms, .. }) => assert_eq!(retry_after_ms, 1000),
+            other => panic!("expected workflow rate limit, got {:?}", other),
+        }
+
+        // The rejected trigger took no global token, so another workflow still fits
+        assert!(limiter.acquire("quiet", start).is_ok());
+        assert!(matches!(limiter.acquire("other", start), Err(CoreError::RateLimited { .. })));
+
+        // Tokens refill over time
+        assert!(limiter.acquire("noisy", start + std::time::Duration::from_secs(1)).is_ok());
+
+        limiter.set_workflow_limit("noisy", Some(RateLimit { per_sec: 100.0, burst: 100 }));
+        let later = start + std::time::Duration::from_secs(2);
+        assert!(limiter.acquire("noisy", later).is_ok());
+        assert!(limiter.acquire("noisy", later).is_ok());
+
+        let result = TriggerExecutionResult::rate_limited("noisy".to_string(), "Too many triggers".to_string(), 250);
+        assert!(!result.success && result.rate_limited);
+        assert_eq!(result.retry_after_ms, Some(250));
+    }
+
+    #[tokio::test]
+    async fn test_paused_engine_buffers_runs_until_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("pause.db").to_str().unwrap().to_string();
+
+        let mut state_manager = StateManager::new(&db_path).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "paused-workflow".to_string(),
+            name: "Paused Workflow".to_string(),
+            description: None,
+            steps: vec![StepDefinition {
+                id: "only".to_string(),
+                name: "only".to_string(),
+                title: None,
+                description: None,
+                action: "test_action".to_string(),
+                timeout: None,
+                retry: None,
+                depends_on: vec![],
+                condition_type: None,
+                condition_expression: None,
+                control_flow_block: None,
+                is_control_flow: false,
+                parallel: None,
+                parallel_group_id: None,
+                parallel_step_count: None,
+                race: None,
+                for_each: None,
+                for_each_items: None,
+                for_each_concurrency: None,
+                pause: None,
+                idempotent: false,
+                adaptive_timeout: None,
+                delay_ms: None,
+                delay_until: None,
+                subworkflow_id: None,
+                subworkflow_input: None,
+                wait_for_signal: None,
+                signal_timeout_ms: None,
+                skip_if: None,
+                skip_dependents: false,
+                output_schema: None,
+                priority: None,
+                cache: None,
+                worker_tags: Vec::new(),
+                input: None,
+            }],
+            triggers: vec![TriggerDefinition::Manual],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+
+        // The executor shares the dispatcher's state manager, as in the bridges
+        let state_manager = Arc::new(TokioMutex::new(state_manager));
+        let dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(
+            crate::dispatcher::WorkerPoolConfig::default(),
+            Arc::clone(&state_manager),
+        )));
+        let executor = TriggerExecutor::new(
+            state_manager,
+            Arc::new(TokioMutex::new(TriggerManager::new())),
+            Arc::clone(&dispatcher),
+        );
+
+        executor.pause_engine().await.unwrap();
+        let first = executor.execute_manual_trigger("paused-workflow", serde_json::json!({})).await.unwrap();
+        let second = executor.execute_manual_trigger("paused-workflow", serde_json::json!({})).await.unwrap();
+        assert!(first.buffered && second.buffered);
+
+        let status = executor.get_engine_status().await.unwrap();
+        assert!(status.paused);
+        assert_eq!(status.buffered_runs, 2);
+        assert!(dispatcher.lock().await.is_paused().await);
+        assert_eq!(dispatcher.lock().await.get_stats().await.unwrap().queue_depth, 0);
+
+        assert_eq!(executor.resume_engine().await.unwrap(), 2);
+        let status = executor.get_engine_status().await.unwrap();
+        assert!(!status.paused);
+        assert_eq!(status.buffered_runs, 0);
+        assert!(!dispatcher.lock().await.is_paused().await);
+        assert_eq!(dispatcher.lock().await.get_stats().await.unwrap().queue_depth, 2);
+
+        let third = executor.execute_manual_trigger("paused-workflow", serde_json::json!({})).await.unwrap();
+        assert!(!third.buffered);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_workflows_reject_triggers_until_enabled() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("disabled.db").to_str().unwrap().to_string();
+        let workflow = |description: &str| {
+            let json = serde_json::json!({
+                "id": "billing",
+                "name": "Billing",
+                "description": description,
+                "steps": [{"id": "charge", "name": "charge", "action": "charge", "depends_on": [], "is_control_flow": false}],
+                "triggers": ["Manual"],
+                "created_at": Utc::now(),
+                "updated_at": Utc::now(),
+            }).to_string();
+            WorkflowDefinition::from_json(&json, true).unwrap().0
+        };
+
+        let mut state_manager = StateManager::new(&db_path).unwrap();
+        state_manager.register_workflow(workflow("v1")).unwrap();
+        assert!(matches!(state_manager.disable_workflow("missing", None, None), Err(CoreError::WorkflowNotFound(_))));
+        assert!(state_manager.disable_workflow("billing", None, Some(Utc::now() - chrono::Duration::seconds(1))).is_err());
+        state_manager.disable_workflow("billing", Some("card processor migration"), None).unwrap();
+
+        // Definition updates keep the workflow disabled
+        state_manager.register_workflow(workflow("v2")).unwrap();
+        assert_eq!(state_manager.get_workflow("billing").unwrap().unwrap().description.as_deref(), Some("v2"));
+
+        let dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(
+            crate::dispatcher::WorkerPoolConfig::default(),
+            Arc::new(TokioMutex::new(StateManager::new(&db_path).unwrap())),
+        )));
+        let state_manager = Arc::new(TokioMutex::new(state_manager));
+        let executor = TriggerExecutor::new(
+            Arc::clone(&state_manager),
+            Arc::new(TokioMutex::new(TriggerManager::new())),
+            dispatcher,
+        );
+
+        let rejected = executor.execute_manual_trigger("billing", serde_json::json!({})).await.unwrap();
+        assert!(!rejected.success && rejected.disabled && rejected.run_id.is_none());
+        assert!(rejected.message.contains("card processor migration"));
+        assert!(state_manager.lock().await.get_unfinished_runs("billing").unwrap().is_empty());
+
+        assert!(state_manager.lock().await.enable_workflow("billing").unwrap());
+        assert!(!state_manager.lock().await.enable_workflow("billing").unwrap());
+        let accepted = executor.execute_manual_trigger("billing", serde_json::json!({})).await.unwrap();
+        assert!(accepted.success && !accepted.disabled);
+
+        // A maintenance window ends on its own
+        let until = Utc::now() + chrono::Duration::milliseconds(50);
+        state_manager.lock().await.disable_workflow("billing", None, Some(until)).unwrap();
+        assert!(executor.execute_manual_trigger("billing", serde_json::json!({})).await.unwrap().disabled);
+        tokio::time::sleep(std::time::Duration::from_millis(80)).await;
+        assert!(executor.execute_manual_trigger("billing", serde_json::json!({})).await.unwrap().success);
+        assert!(state_manager.lock().await.get_disabled_workflow("billing").unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_schedule_overlap_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("overlap.db").to_str().unwrap().to_string();
+
+        let mut state_manager = StateManager::new(&db_path).unwrap();
+        for (id, overlap_policy) in [("skip-workflow", OverlapPolicy::Skip), ("cancel-workflow", OverlapPolicy::CancelPrevious)] {
+            state_manager.register_workflow(WorkflowDefinition {
+                id: id.to_string(),
+                name: id.to_string(),
+                description: None,
+                steps: vec![StepDefinition {
+                    id: "only".to_string(),
+                    name: "only".to_string(),
+                    title: None,
+                    description: None,
+                    action: "test_action".to_string(),
+                    timeout: None,
+                    retry: None,
+                    depends_on: vec![],
+                    condition_type: None,
+                    condition_expression: None,
+                    control_flow_block: None,
+                    is_control_flow: false,
+                    parallel: None,
+                    parallel_group_id: None,
+                    parallel_step_count: None,
+                    race: None,
+                    for_each: None,
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    pause: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
+                }],
+                triggers: vec![TriggerDefinition::Schedule {
+                    cron_expression: "* * * * *".to_string(),
+                    overlap_policy,
+                }],
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+                concurrency: None,
+                namespace: None,
+                payload_sample_rate: None,
+                priority: None,
+                run_timeout_ms: None,
+                run_timeout_outcome: Default::default(),
+                serialize_on: None,
+                resource_quota: None,
+                lane: None,
+                defaults: None,
+            }).unwrap();
+        }
+
+        let dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(
+            crate::dispatcher::WorkerPoolConfig::default(),
+            Arc::new(TokioMutex::new(StateManager::new(&db_path).unwrap())),
+        )));
+        let state_manager = Arc::new(TokioMutex::new(state_manager));
+        let executor = TriggerExecutor::new(
+            Arc::clone(&state_manager),
+            Arc::new(TokioMutex::new(TriggerManager::new())),
+            Arc::clone(&dispatcher),
+        );
+
+        // No worker is running, so the first run of each workflow stays unfinished
+        let first = executor.execute_schedule_trigger("skip-workflow", serde_json::json!({})).await.unwrap();
+        assert!(first.run_id.is_some() && !first.skipped);
+        let second = executor.execute_schedule_trigger("skip-workflow", serde_json::json!({})).await.unwrap();
+        assert!(second.skipped);
+        assert!(second.run_id.is_none());
+
+        let replaced = executor.execute_schedule_trigger("cancel-workflow", serde_json::json!({})).await.unwrap().run_id.unwrap();
+        let replacement = executor.execute_schedule_trigger("cancel-workflow", serde_json::json!({})).await.unwrap().run_id.unwrap();
+        let state_manager = state_manager.lock().await;
+        assert_eq!(state_manager.get_run(&replaced).unwrap().unwrap().status, crate::models::RunStatus::Cancelled);
+        assert_eq!(state_manager.get_unfinished_runs("cancel-workflow").unwrap(), vec![replacement]);
+
+        // The cancelled run's queued job was removed, leaving one job per workflow
+        assert_eq!(dispatcher.lock().await.get_stats().await.unwrap().queue_depth, 2);
+    }
+
+    #[tokio::test]
+    async fn test_interval_and_one_time_triggers_survive_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("schedules.db").to_str().unwrap().to_string();
+
+        let registered_at = Utc::now();
+        let workflow = WorkflowDefinition {
+            id: "timed-workflow".to_string(),
+            name: "Timed Workflow".to_string(),
+            description: None,
+            steps: vec![StepDefinition {
+                id: "only".to_string(),
+                name: "only".to_string(),
+                title: None,
+                description: None,
+                action: "test_action".to_string(),
+                timeout: None,
+                retry: None,
+                depends_on: vec![],
+                condition_type: None,
+                condition_expression: None,
+                control_flow_block: None,
+                is_control_flow: false,
+                parallel: None,
+                parallel_group_id: None,
+                parallel_step_count: None,
+                race: None,
+                for_each: None,
+                for_each_items: None,
+                for_each_concurrency: None,
+                pause: None,
+                idempotent: false,
+                adaptive_timeout: None,
+                delay_ms: None,
+                delay_until: None,
+                subworkflow_id: None,
+                subworkflow_input: None,
+                wait_for_signal: None,
+                signal_timeout_ms: None,
+                skip_if: None,
+                skip_dependents: false,
+                output_schema: None,
+                priority: None,
+                cache: None,
+                worker_tags: Vec::new(),
+                input: None,
+            }],
+            triggers: vec![
+                TriggerDefinition::Interval { every_seconds: 60, overlap_policy: OverlapPolicy::Allow, catch_up: CatchUpPolicy::FireOnce },
+                TriggerDefinition::At { run_at: registered_at - chrono::Duration::seconds(1) },
+            ],
+            created_at: registered_at,
+            updated_at: registered_at,
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        assert!(TriggerDefinition::Interval { every_seconds: 0, overlap_policy: OverlapPolicy::Allow, catch_up: CatchUpPolicy::FireOnce }.validate().is_err());
+        assert!(TriggerDefinition::Interval { every_seconds: 60, overlap_policy: OverlapPolicy::Allow, catch_up: CatchUpPolicy::FireAll { max: 0 } }.validate().is_err());
+
+        let state_manager = StateManager::new(&db_path).unwrap();
+        state_manager.register_workflow(workflow.clone()).unwrap();
+        let dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(
+            crate::dispatcher::WorkerPoolConfig::default(),
+            Arc::new(TokioMutex::new(StateManager::new(&db_path).unwrap())),
+        )));
+        let executor = TriggerExecutor::new(
+            Arc::new(TokioMutex::new(state_manager)),
+            Arc::new(TokioMutex::new(TriggerManager::new())),
+            Arc::clone(&dispatcher),
+        );
+
+        // Only the one-time trigger is due right away
+        let fired = executor.fire_due_schedules(registered_at).await.unwrap();
+        assert_eq!(fired.len(), 1);
+        assert!(fired[0].run_id.is_some());
+        let interval_at = executor.next_schedule_at().await.unwrap().unwrap();
+        assert!(interval_at > registered_at);
+
+        // Re-registering the unchanged workflow after a restart keeps both schedules
+        let restarted = StateManager::new(&db_path).unwrap();
+        restarted.register_workflow(workflow.clone()).unwrap();
+        let schedules = restarted.get_trigger_schedules("timed-workflow").unwrap();
+        assert_eq!(schedules.len(), 2);
+        assert_eq!(schedules[0].next_fire_at, Some(interval_at));
+        assert_eq!(schedules[1].next_fire_at, None);
+        assert!(schedules[1].last_fired_at.is_some());
+
+        // Missed interval firings collapse into one catch-up run
+        let late = interval_at + chrono::Duration::seconds(150);
+        let fired = executor.fire_due_schedules(late).await.unwrap();
+        assert_eq!(fired.len(), 1);
+        let run = restarted.get_run(&fired[0].run_id.unwrap()).unwrap().unwrap();
+        assert_eq!(run.payload["catch_up"]["missed_firings"], 3);
+        assert_eq!(executor.next_schedule_at().await.unwrap(), Some(interval_at + chrono::Duration::seconds(180)));
+        assert!(executor.fire_due_schedules(late).await.unwrap().is_empty());
+
+        // Changing the interval reschedules it from the new registration
+        let mut changed = workflow;
+        changed.triggers.truncate(1);
+        changed.triggers[0] = TriggerDefinition::Interval { every_seconds: 3600, overlap_policy: OverlapPolicy::Allow, catch_up: CatchUpPolicy::FireAll { max: 2 } };
+        restarted.register_workflow(changed.clone()).unwrap();
+        let schedules = restarted.get_trigger_schedules("timed-workflow").unwrap();
+        assert_eq!(schedules.len(), 1);
+        let hourly_at = schedules[0].next_fire_at.unwrap();
+        assert!(hourly_at > late);
+
+        // A firing on time is not a catch-up, however late the tick
+        assert_eq!(schedules[0].due_firings(hourly_at + chrono::Duration::seconds(3599)), (vec![hourly_at], 0));
+
+        // The most recent missed firings each get a run, the older ones are dropped
+        let hour = chrono::Duration::hours(1);
+        let fired = executor.fire_due_schedules(hourly_at + hour * 3).await.unwrap();
+        assert_eq!(fired.len(), 2);
+        let scheduled: Vec<serde_json::Value> = fired.iter()
+            .map(|result| restarted.get_run(&result.run_id.unwrap()).unwrap().unwrap().payload)
+            .inspect(|payload| assert_eq!(payload["catch_up"]["missed_firings"], 4))
+            .map(|payload| payload["scheduled_at"].clone())
+            .collect();
+        assert_eq!(scheduled, vec![serde_json::json!(hourly_at + hour * 2), serde_json::json!(hourly_at + hour * 3)]);
+
+        // Without catch-up, missed firings start nothing
+        let mut schedule = restarted.get_trigger_schedules("timed-workflow").unwrap().remove(0);
+        schedule.trigger = TriggerDefinition::Interval { every_seconds: 3600, overlap_policy: OverlapPolicy::Allow, catch_up: CatchUpPolicy::None };
+        let next_at = schedule.next_fire_at.unwrap();
+        assert_eq!(schedule.due_firings(next_at + hour * 2), (vec![], 3));
+    }
+
+    #[tokio::test]
+    async fn test_published_events_are_checked_against_schemas() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("events.db").to_str().unwrap().to_string();
+
+        let state_manager = StateManager::new(&db_path).unwrap();
+        state_manager.register_event_schema("order.created", 1, serde_json::json!({
+            "type": "object",
+            "required": ["order_id"],
+        })).unwrap();
+        state_manager.register_event_schema("order.created", 2, serde_json::json!({
+            "type": "object",
+            "required": ["order_id", "currency"],
+        })).unwrap();
+        assert!(state_manager.register_event_schema("order.created", 1, serde_json::json!({"type": "object"})).is_err());
+
+        let workflow = |versions: Vec<u32>| WorkflowDefinition {
+            id: "billing".to_string(),
+            name: "Billing".to_string(),
+            description: None,
+            steps: vec![StepDefinition {
+                id: "charge".to_string(),
+                name: "charge".to_string(),
+                title: None,
+                description: None,
+                action: "charge".to_string(),
+                timeout: None,
+                retry: None,
+                depends_on: vec![],
+                condition_type: None,
+                condition_expression: None,
+                control_flow_block: None,
+                is_control_flow: false,
+                parallel: None,
+                parallel_group_id: None,
+                parallel_step_count: None,
+                race: None,
+                for_each: None,
+                for_each_items: None,
+                for_each_concurrency: None,
+                pause: None,
+                idempotent: false,
+                adaptive_timeout: None,
+                delay_ms: None,
+                delay_until: None,
+                subworkflow_id: None,
+                subworkflow_input: None,
+                wait_for_signal: None,
+                signal_timeout_ms: None,
+                skip_if: None,
+                skip_dependents: false,
+                output_schema: None,
+                priority: None,
+                cache: None,
+                worker_tags: Vec::new(),
+                input: None,
+            }],
+            triggers: vec![TriggerDefinition::Event { event_name: "order.created".to_string(), versions, dedup: None }],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        // Subscribing to an unregistered version fails at registration
+        assert!(matches!(state_manager.register_workflow(workflow(vec![3])), Err(CoreError::IncompatibleEventSchema(_))));
+        let billing = workflow(vec![1]);
+        state_manager.register_workflow(billing.clone()).unwrap();
+
+        let dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(
+            crate::dispatcher::WorkerPoolConfig::default(),
+            Arc::new(TokioMutex::new(StateManager::new(&db_path).unwrap())),
+        )));
+        let executor = TriggerExecutor::new(
+            Arc::new(TokioMutex::new(state_manager)),
+            Arc::new(TokioMutex::new(TriggerManager::new())),
+            Arc::clone(&dispatcher),
+        );
+        executor.register_workflow_triggers("billing", &billing).await.unwrap();
+
+        // Bad payloads and versions the subscriber does not accept start nothing
+        assert!(matches!(
+            executor.publish_event("order.created", Some(1), serde_json::json!({"total": 5})).await,
+            Err(CoreError::Validation(_))
+        ));
+        assert!(matches!(
+            executor.publish_event("order.created", None, serde_json::json!({"order_id": "o-1", "currency": "EUR"})).await,
+            Err(CoreError::IncompatibleEventSchema(_))
+        ));
+        assert_eq!(dispatcher.lock().await.get_stats().await.unwrap().queue_depth, 0);
+
+        let published = executor.publish_event("order.created", Some(1), serde_json::json!({"order_id": "o-1"})).await.unwrap();
+        assert_eq!(published.version, Some(1));
+        assert_eq!(published.results.len(), 1);
+        assert!(published.results[0].run_id.is_some());
+
+        let untyped = executor.publish_event("order.shipped", None, serde_json::json!({})).await.unwrap();
+        assert!(untyped.version.is_none() && untyped.results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_events_within_the_dedup_window_are_suppressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("dedup.db").to_str().unwrap().to_string();
+
+        let state_manager = StateManager::new(&db_path).unwrap();
+        let workflow = WorkflowDefinition {
+            id: "fulfilment".to_string(),
+            name: "Fulfilment".to_string(),
+            description: None,
+            steps: vec![StepDefinition {
+                id: "charge".to_string(),
+                name: "charge".to_string(),
+                title: None,
+                description: None,
+                action: "charge".to_string(),
+                timeout: None,
+                retry: None,
+                depends_on: vec![],
+                condition_type: None,
+                condition_expression: None,
+                control_flow_block: None,
+                is_control_flow: false,
+                parallel: None,
+                parallel_group_id: None,
+                parallel_step_count: None,
+                race: None,
+                for_each: None,
+                for_each_items: None,
+                for_each_concurrency: None,
+                pause: None,
+                idempotent: false,
+                adaptive_timeout: None,
+                delay_ms: None,
+                delay_until: None,
+                subworkflow_id: None,
+                subworkflow_input: None,
+                wait_for_signal: None,
+                signal_timeout_ms: None,
+                skip_if: None,
+                skip_dependents: false,
+                output_schema: None,
+                priority: None,
+                cache: None,
+                worker_tags: Vec::new(),
+                input: None,
+            }],
+            triggers: vec![TriggerDefinition::Event {
+                event_name: "order.paid".to_string(),
+                versions: vec![],
+                dedup: Some(TriggerDedup { key: "payload.order_id".to_string(), window_secs: 60 }),
+            }],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        state_manager.register_workflow(workflow.clone()).unwrap();
+
+        let dispatcher = Arc::new(TokioMutex::new(Dispatcher::new(
+            crate::dispatcher::WorkerPoolConfig::default(),
+            Arc::new(TokioMutex::new(StateManager::new(&db_path).unwrap())),
+        )));
+        let executor = TriggerExecutor::new(
+            Arc::new(TokioMutex::new(state_manager)),
+            Arc::new(TokioMutex::new(TriggerManager::new())),
+            dispatcher,
+        );
+        executor.register_workflow_triggers("fulfilment", &workflow).await.unwrap();
+
+        let first = executor.publish_event("order.paid", None, serde_json::json!({"order_id": "o-1"})).await.unwrap();
+        let first = &first.results[0];
+        assert!(first.success && !first.suppressed);
+
+        // The same key within the window points at the existing run instead of starting one
+        let duplicate = executor.publish_event("order.paid", None, serde_json::json!({"order_id": "o-1", "retry": true})).await.unwrap();
+        let duplicate = &duplicate.results[0];
+        assert!(duplicate.success && duplicate.suppressed);
+        assert_eq!(duplicate.run_id, first.run_id);
+
+        let other = executor.publish_event("order.paid", None, serde_json::json!({"order_id": "o-2"})).await.unwrap();
+        assert!(!other.results[0].suppressed);
+        assert_ne!(other.results[0].run_id, first.run_id);
+
+        let state_manager = executor.state_manager.lock().await;
+        let suppressed = state_manager.get_suppressed_triggers("fulfilment", 10).unwrap();
+        assert_eq!(suppressed.len(), 1);
+        assert_eq!(suppressed[0].dedup_key, "o-1");
+        assert_eq!(Some(suppressed[0].run_id), first.run_id);
+        assert_eq!(state_manager.get_unfinished_runs("fulfilment").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_trigger_metrics_count_outcomes_and_persist_once_per_interval() {
+        let dir = tempfile::tempdir().unwrap();
+        let state_manager = StateManager::new(dir.path().join("stats.db").to_str().unwrap()).unwrap();
+        let start = Utc::now();
+        let at = |secs: i64| start + chrono::Duration::seconds(secs);
+        let mut metrics = TriggerMetrics::new(TriggerStatsConfig { latency_window_secs: 60, persist_interval_secs: 30 }, start);
+
+        assert!(metrics.record("webhook:/webhook/orders", "orders", TriggerOutcome::Succeeded, 40, at(0)).is_none());
+        metrics.record("webhook:/webhook/orders", "orders", TriggerOutcome::of_status(401), 5, at(1));
+        metrics.record("webhook:/webhook/orders", "orders", TriggerOutcome::of_status(429), 1, at(2));
+        let disabled = Ok(TriggerExecutionResult::disabled("billing".to_string(), "Workflow is disabled".to_string()));
+        metrics.record("event:order.created", "billing", TriggerOutcome::of(&disabled), 10, at(3));
+        let due = metrics.record("webhook:/webhook/orders", "orders", TriggerOutcome::Succeeded, 120, at(90)).unwrap();
+        assert_eq!(due.len(), 2);
+        state_manager.add_trigger_counters(&due).unwrap();
+
+        let entries = metrics.entries(state_manager.get_trigger_counters().unwrap(), at(90));
+        assert_eq!((entries[0].trigger.as_str(), entries[0].counters.rejected), ("event:order.created", 1));
+        let orders = &entries[1];
+        assert_eq!(orders.counters, TriggerCounters { fired: 4, succeeded: 2, rejected: 0, auth_failed: 1, rate_limited: 1 });
+        // Only the last firing is still within the latency window
+        assert_eq!((orders.latency.samples, orders.latency.p99_ms), (1, Some(120)));
+        assert_eq!(orders.last_fired_at, Some(at(90)));
+
+        // Counts that failed to persist are added to the stats until the next persist
+        metrics.record("webhook:/webhook/orders", "orders", TriggerOutcome::Succeeded, 10, at(91));
+        let failed = metrics.take_pending(at(91));
+        metrics.requeue(failed);
+        let entries = metrics.entries(state_manager.get_trigger_counters().unwrap(), at(91));
+        assert_eq!((entries[1].counters.fired, entries[1].latency.p50_ms), (5, Some(10)));
+    }
+}
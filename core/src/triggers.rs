@@ -4,12 +4,26 @@
 //! - Webhook triggers (HTTP-based)
 //! - Manual triggers (programmatic)
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 use crate::error::{CoreError, CoreResult};
+use crate::models::{RunStatus, StepResult, StepStatus, TriggerDedup, WorkflowRun};
 use log;
 use std::str::FromStr;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use sha1::Sha1;
+
+/// Signature header checked when a secret is set without an explicit header
+pub const DEFAULT_SIGNATURE_HEADER: &str = "x-hub-signature-256";
+
+/// Header carrying the API key when an API key trigger does not name one
+pub const DEFAULT_API_KEY_HEADER: &str = "x-api-key";
+
+/// Header carrying the caller's correlation ID for the run a request creates
+pub const CORRELATION_ID_HEADER: &str = "x-correlation-id";
 
 /// Webhook trigger configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +32,25 @@ pub struct WebhookTrigger {
     pub method: String,
     pub headers: Option<HashMap<String, String>>,
     pub validation: Option<WebhookValidation>,
+    #[serde(default)]
+    pub respond_with: Option<WebhookReply>,
+    #[serde(default)]
+    pub response: Option<WebhookResponseOptions>,
+    /// Builds the run payload from the request instead of the whole HTTP envelope
+    #[serde(default)]
+    pub payload_mapping: Option<WebhookPayloadMapping>,
+    /// Suppresses requests duplicating a recent run instead of starting another
+    #[serde(default)]
+    pub dedup: Option<TriggerDedup>,
+    /// Credentials requests must present before a run is created
+    #[serde(default)]
+    pub auth: Option<WebhookAuth>,
+    /// Dispatcher lane of the runs the webhook starts, the workflow's when unset
+    #[serde(default)]
+    pub lane: Option<String>,
+    /// Adds the request's parts to the run payload as structured `request` fields
+    #[serde(default)]
+    pub request_fields: Option<WebhookRequestFields>,
 }
 
 impl WebhookTrigger {
@@ -28,6 +61,13 @@ impl WebhookTrigger {
             method: method.to_uppercase(),
             headers: None,
             validation: None,
+            respond_with: None,
+            response: None,
+            payload_mapping: None,
+            dedup: None,
+            auth: None,
+            lane: None,
+            request_fields: None,
         }
     }
 
@@ -43,6 +83,48 @@ impl WebhookTrigger {
         self
     }
 
+    /// Set how the webhook trigger replies to requests
+    pub fn with_reply(mut self, respond_with: WebhookReply) -> Self {
+        self.respond_with = Some(respond_with);
+        self
+    }
+
+    /// Set the status code, headers and error body of the trigger's replies
+    pub fn with_response(mut self, response: WebhookResponseOptions) -> Self {
+        self.response = Some(response);
+        self
+    }
+
+    /// Build run payloads from the request with a mapping
+    pub fn with_payload_mapping(mut self, payload_mapping: WebhookPayloadMapping) -> Self {
+        self.payload_mapping = Some(payload_mapping);
+        self
+    }
+
+    /// Suppress requests duplicating a run created within the dedup window
+    pub fn with_dedup(mut self, dedup: TriggerDedup) -> Self {
+        self.dedup = Some(dedup);
+        self
+    }
+
+    /// Require requests to authenticate
+    pub fn with_auth(mut self, auth: WebhookAuth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Run the started runs in a dispatcher lane
+    pub fn with_lane(mut self, lane: String) -> Self {
+        self.lane = Some(lane);
+        self
+    }
+
+    /// Add the request's parts to run payloads as structured fields
+    pub fn with_request_fields(mut self, request_fields: WebhookRequestFields) -> Self {
+        self.request_fields = Some(request_fields);
+        self
+    }
+
     /// Validate the webhook trigger configuration
     pub fn validate(&self) -> CoreResult<()> {
         if self.path.is_empty() {
@@ -53,13 +135,534 @@ impl WebhookTrigger {
             return Err(CoreError::InvalidTrigger("Webhook path must start with /".to_string()));
         }
 
+        let mut params = Vec::new();
+        for segment in self.path.split('/') {
+            if let Some(name) = segment.strip_prefix(':') {
+                if name.is_empty() {
+                    return Err(CoreError::InvalidTrigger(format!("Webhook path {} has an unnamed parameter", self.path)));
+                }
+                if params.contains(&name) {
+                    return Err(CoreError::InvalidTrigger(format!("Webhook path {} repeats parameter :{}", self.path, name)));
+                }
+                params.push(name);
+            }
+        }
+
         let valid_methods = ["GET", "POST", "PUT", "DELETE", "PATCH"];
         if !valid_methods.contains(&self.method.as_str()) {
             return Err(CoreError::InvalidTrigger(format!("Invalid HTTP method: {}", self.method)));
         }
 
+        if let Some(validation) = &self.validation {
+            validation.validate()?;
+        }
+
+        if let Some(respond_with) = &self.respond_with {
+            respond_with.validate()?;
+        }
+
+        if let Some(response) = &self.response {
+            response.validate()?;
+        }
+
+        if let Some(payload_mapping) = &self.payload_mapping {
+            payload_mapping.validate()?;
+        }
+
+        if let Some(dedup) = &self.dedup {
+            dedup.validate().map_err(CoreError::InvalidTrigger)?;
+        }
+
+        if let Some(auth) = &self.auth {
+            auth.validate()?;
+        }
+
+        if let Some(request_fields) = &self.request_fields {
+            request_fields.validate()?;
+            if let Some(field) = self.payload_mapping.iter()
+                .flat_map(|mapping| mapping.fields.keys())
+                .find(|field| field.split('.').next() == Some(WebhookRequestFields::PAYLOAD_FIELD))
+            {
+                return Err(CoreError::InvalidTrigger(format!("Webhook payload field {} conflicts with the request fields", field)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Build the run payload of a request with the parameters of its path
+    ///
+    /// The payload mapping builds it when set. Otherwise it is the request's
+    /// method and path when the request fields are set, the whole HTTP
+    /// envelope from `envelope` when not. The request fields are then added.
+    pub fn build_payload(
+        &self,
+        request: &WebhookRequest,
+        path_params: &HashMap<String, String>,
+        envelope: impl FnOnce() -> CoreResult<serde_json::Value>,
+    ) -> CoreResult<serde_json::Value> {
+        let mut payload = match (&self.payload_mapping, &self.request_fields) {
+            (Some(payload_mapping), _) => payload_mapping.apply(request, path_params)?,
+            (None, Some(_)) => serde_json::json!({"method": request.method, "path": request.path}),
+            (None, None) => envelope()?,
+        };
+        if let (Some(request_fields), Some(object)) = (&self.request_fields, payload.as_object_mut()) {
+            object.insert(WebhookRequestFields::PAYLOAD_FIELD.to_string(), request_fields.build(request, path_params));
+        }
+        Ok(payload)
+    }
+
+    /// Match a request path against the trigger's path, extracting `:name` parameters
+    ///
+    /// Returns `None` when the path does not match.
+    pub fn match_path(&self, path: &str) -> Option<HashMap<String, String>> {
+        let mut pattern = self.path.split('/');
+        let mut segments = path.split('/');
+        let mut params = HashMap::new();
+        loop {
+            match (pattern.next(), segments.next()) {
+                (None, None) => return Some(params),
+                (Some(expected), Some(segment)) => match expected.strip_prefix(':') {
+                    Some(name) if !segment.is_empty() => {
+                        params.insert(name.to_string(), segment.to_string());
+                    }
+                    Some(_) => return None,
+                    None if expected == segment => {}
+                    None => return None,
+                },
+                _ => return None,
+            }
+        }
+    }
+
+    /// The trigger's path with parameter names erased, equal for paths matching the same requests
+    fn route_shape(path: &str) -> String {
+        path.split('/')
+            .map(|segment| if segment.starts_with(':') { ":" } else { segment })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+
+    /// Whether requests wait for the run and get its output as the reply
+    pub fn replies_sync(&self) -> bool {
+        self.respond_with.as_ref().is_some_and(|r| r.mode == WebhookReplyMode::Sync)
+    }
+}
+
+/// When a webhook trigger replies to the request
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookReplyMode {
+    /// Reply as soon as the run is created
+    #[default]
+    Async,
+    /// Wait for a step, or the whole run, and reply with its output
+    Sync,
+}
+
+/// How a webhook trigger replies to requests
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WebhookReply {
+    #[serde(default)]
+    pub mode: WebhookReplyMode,
+    /// Step whose output is the reply; the whole run when unset
+    #[serde(default)]
+    pub step_id: Option<String>,
+    /// How long to wait before replying that the run is still in progress
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+impl WebhookReply {
+    /// Wait used when no timeout is set
+    pub const DEFAULT_TIMEOUT_MS: u64 = 30_000;
+    /// Longest wait a trigger may ask for
+    pub const MAX_TIMEOUT_MS: u64 = 300_000;
+
+    /// Validate the reply configuration
+    pub fn validate(&self) -> CoreResult<()> {
+        if let Some(timeout_ms) = self.timeout_ms {
+            if timeout_ms == 0 || timeout_ms > Self::MAX_TIMEOUT_MS {
+                return Err(CoreError::InvalidTrigger(format!(
+                    "Webhook reply timeout must be between 1 and {}ms", Self::MAX_TIMEOUT_MS
+                )));
+            }
+        }
+        if self.step_id.as_deref().is_some_and(str::is_empty) {
+            return Err(CoreError::InvalidTrigger("Webhook reply step ID cannot be empty".to_string()));
+        }
+        Ok(())
+    }
+
+    /// How long a request waits for its reply
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_ms.unwrap_or(Self::DEFAULT_TIMEOUT_MS))
+    }
+
+    /// Build the reply from the state of a run, or None while the awaited output is not ready
+    ///
+    /// A completed step or run replies 200 with the output as body: the awaited
+    /// step's output, or the output of the run's last completed step. Failures
+    /// reply 500 and cancelled runs 409.
+    pub fn response_for(&self, run: &WorkflowRun, results: &[StepResult]) -> Option<WebhookResponse> {
+        if let Some(step_id) = &self.step_id {
+            match results.iter().rev().find(|r| &r.step_id == step_id && r.status.is_terminal()) {
+                Some(result) if result.status == StepStatus::Failed => {
+                    let message = result.error.clone().unwrap_or_else(|| "Step failed".to_string());
+                    return Some(Self::error_response(500, run, &message));
+                }
+                Some(result) => return Some(Self::output_response(result.output.as_ref())),
+                None if !run.status.is_terminal() => return None,
+                None => {}
+            }
+        }
+
+        match run.status {
+            RunStatus::Completed => {
+                let output = results.iter().rev()
+                    .find(|r| r.status == StepStatus::Completed)
+                    .and_then(|r| r.output.as_ref());
+                Some(Self::output_response(output))
+            }
+            RunStatus::Failed => {
+                let message = run.error.clone().unwrap_or_else(|| "Run failed".to_string());
+                Some(Self::error_response(500, run, &message))
+            }
+            RunStatus::Cancelled => Some(Self::error_response(409, run, "Run was cancelled")),
+            RunStatus::Pending | RunStatus::Running => None,
+        }
+    }
+
+    /// Reply given when the awaited output is not ready in time
+    pub fn timeout_response(run_id: &str) -> WebhookResponse {
+        WebhookResponse::new(202).with_body(serde_json::json!({
+            "status": "accepted",
+            "message": "Run is still in progress",
+            "run_id": run_id,
+        }).to_string())
+    }
+
+    fn output_response(output: Option<&serde_json::Value>) -> WebhookResponse {
+        let body = output.cloned().unwrap_or(serde_json::Value::Null);
+        WebhookResponse::new(200).with_body(body.to_string())
+    }
+
+    fn error_response(status_code: u16, run: &WorkflowRun, message: &str) -> WebhookResponse {
+        WebhookResponse::new(status_code).with_body(serde_json::json!({
+            "status": "error",
+            "message": message,
+            "run_id": run.id.to_string(),
+        }).to_string())
+    }
+}
+
+/// Status code, headers and error body a webhook trigger replies with
+///
+/// Header values and the error body are templates: `{{status}}`,
+/// `{{message}}` and `{{path}}` are replaced with the reply's status code,
+/// error message and request path, and `{{header.<name>}}` with a request
+/// header, so headers a sender expects back can be echoed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WebhookResponseOptions {
+    /// Status of successful replies, e.g. 202; 200 when unset
+    #[serde(default)]
+    pub success_status: Option<u16>,
+    /// Headers added to every reply
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Body of error replies; the default JSON error body when unset
+    #[serde(default)]
+    pub error_body: Option<String>,
+    /// Content type of the error body template; application/json when unset
+    #[serde(default)]
+    pub error_content_type: Option<String>,
+}
+
+impl WebhookResponseOptions {
+    /// Validate the response options
+    pub fn validate(&self) -> CoreResult<()> {
+        if let Some(status) = self.success_status {
+            if !(200..300).contains(&status) {
+                return Err(CoreError::InvalidTrigger(format!("Webhook success status must be 2xx, got {}", status)));
+            }
+        }
+        for name in self.headers.keys() {
+            let valid = !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+            if !valid {
+                return Err(CoreError::InvalidTrigger(format!("Invalid webhook response header name: {:?}", name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Status of a successful reply, given the status the engine would use
+    pub fn success_status(&self, status_code: u16) -> u16 {
+        match self.success_status {
+            Some(status) if status_code == 200 => status,
+            _ => status_code,
+        }
+    }
+
+    /// The configured headers, rendered for a reply
+    pub fn render_headers(&self, status_code: u16, message: &str, request: &WebhookRequest) -> HashMap<String, String> {
+        self.headers.iter()
+            .map(|(name, value)| (name.clone(), Self::render(value, status_code, message, request)))
+            .collect()
+    }
+
+    /// The error body template rendered for a reply, with its content type
+    pub fn render_error_body(&self, status_code: u16, message: &str, request: &WebhookRequest) -> Option<(String, String)> {
+        let template = self.error_body.as_ref()?;
+        let content_type = self.error_content_type.clone().unwrap_or_else(|| "application/json".to_string());
+        Some((Self::render(template, status_code, message, request), content_type))
+    }
+
+    fn render(template: &str, status_code: u16, message: &str, request: &WebhookRequest) -> String {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            rendered.push_str(&rest[..start]);
+            let name = rest[start + 2..start + end].trim();
+            match name {
+                "status" => rendered.push_str(&status_code.to_string()),
+                "message" => rendered.push_str(message),
+                "path" => rendered.push_str(&request.path),
+                _ => match name.strip_prefix("header.") {
+                    Some(header) => rendered.push_str(request.header(header).unwrap_or_default()),
+                    None => rendered.push_str(&rest[start..start + end + 2]),
+                },
+            }
+            rest = &rest[start + end + 2..];
+        }
+        rendered.push_str(rest);
+        rendered
+    }
+}
+
+/// Parts of a webhook request passed to its runs as structured fields
+///
+/// The run payload gets a `request` object holding the path parameters, the
+/// query parameters, the allowed headers and the body, parsed as JSON when it
+/// is. Only headers on the allowlist are passed, so credentials such as
+/// `Authorization` stay out of stored payloads; without a payload mapping the
+/// payload then carries no other copy of the headers either.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WebhookRequestFields {
+    /// Headers passed in `request.headers_allowed`, matched case-insensitively
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+}
+
+impl WebhookRequestFields {
+    /// Payload field holding the request parts
+    pub const PAYLOAD_FIELD: &'static str = "request";
+
+    /// Validate the allowlist
+    pub fn validate(&self) -> CoreResult<()> {
+        for name in &self.allowed_headers {
+            let valid = !name.is_empty() && name.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b));
+            if !valid {
+                return Err(CoreError::InvalidTrigger(format!("Invalid allowed webhook header name: {:?}", name)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the `request` object of a run payload, with allowed header names lowercased
+    pub fn build(&self, request: &WebhookRequest, path_params: &HashMap<String, String>) -> serde_json::Value {
+        let headers_allowed: serde_json::Map<String, serde_json::Value> = self.allowed_headers.iter()
+            .filter_map(|name| request.header(name).map(|value| (name.to_lowercase(), serde_json::json!(value))))
+            .collect();
+        serde_json::json!({
+            "path_params": path_params,
+            "query": request.query_params,
+            "headers_allowed": headers_allowed,
+            "body": request.json_body(),
+        })
+    }
+}
+
+/// Mapping that builds a run payload from the parts of a webhook request
+///
+/// Each payload field is taken from a source path or rendered from a template.
+/// Paths start at `body`, `headers`, `query`, `params`, `method` or `path`,
+/// optionally prefixed with `$.`, and are dot-separated; numeric segments and
+/// `[n]` index arrays, e.g. `$.body.items[0].sku`. Header names match
+/// case-insensitively. A source containing `{{<path>}}` placeholders is a
+/// template rendered to a string. Dotted field names build nested objects, and
+/// paths resolving to nothing map to null.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct WebhookPayloadMapping {
+    /// Payload field name -> source path or template
+    pub fields: BTreeMap<String, String>,
+    /// Fields a request is rejected without
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+impl WebhookPayloadMapping {
+    /// Parts of a request a source path can start at
+    pub const SOURCES: [&'static str; 6] = ["body", "headers", "query", "params", "method", "path"];
+
+    /// Validate the mapping
+    pub fn validate(&self) -> CoreResult<()> {
+        if self.fields.is_empty() {
+            return Err(CoreError::InvalidTrigger("Webhook payload mapping has no fields".to_string()));
+        }
+        for (field, source) in &self.fields {
+            if field.split('.').any(str::is_empty) {
+                return Err(CoreError::InvalidTrigger(format!("Invalid webhook payload field name: {:?}", field)));
+            }
+            if let Some(parent) = self.fields.keys().find(|other| field.starts_with(&format!("{}.", other))) {
+                return Err(CoreError::InvalidTrigger(format!("Webhook payload field {} is nested in field {}", field, parent)));
+            }
+            match Self::placeholders(source) {
+                Some(paths) => paths.into_iter().try_for_each(|path| Self::parse_path(path).map(drop))?,
+                None => {
+                    Self::parse_path(source)?;
+                }
+            }
+        }
+        if let Some(field) = self.required.iter().find(|field| !self.fields.contains_key(*field)) {
+            return Err(CoreError::InvalidTrigger(format!("Required webhook payload field {} is not mapped", field)));
+        }
         Ok(())
     }
+
+    /// Build the run payload from a request and the parameters of its path
+    pub fn apply(&self, request: &WebhookRequest, path_params: &HashMap<String, String>) -> CoreResult<serde_json::Value> {
+        let headers: serde_json::Map<String, serde_json::Value> = request.headers.iter()
+            .map(|(name, value)| (name.to_lowercase(), serde_json::Value::String(value.clone())))
+            .collect();
+        let envelope = serde_json::json!({
+            "body": request.json_body(),
+            "headers": headers,
+            "query": request.query_params,
+            "params": path_params,
+            "method": request.method,
+            "path": request.path,
+        });
+
+        let mut payload = serde_json::json!({});
+        for (field, source) in &self.fields {
+            let value = match Self::placeholders(source) {
+                Some(_) => serde_json::Value::String(Self::render(source, &envelope)?),
+                None => Self::lookup(&envelope, &Self::parse_path(source)?),
+            };
+            if value.is_null() && self.required.contains(field) {
+                return Err(CoreError::InvalidTrigger(format!("Webhook request has no value for payload field {} ({})", field, source)));
+            }
+            let mut target = &mut payload;
+            let mut segments = field.split('.').peekable();
+            while let Some(segment) = segments.next() {
+                if segments.peek().is_none() {
+                    target[segment] = value;
+                    break;
+                }
+                if !target[segment].is_object() {
+                    target[segment] = serde_json::json!({});
+                }
+                target = &mut target[segment];
+            }
+        }
+        Ok(payload)
+    }
+
+    /// The paths of a template's placeholders, or None when the source is a plain path
+    fn placeholders(source: &str) -> Option<Vec<&str>> {
+        let mut paths = Vec::new();
+        let mut rest = source;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            paths.push(rest[start + 2..start + end].trim());
+            rest = &rest[start + end + 2..];
+        }
+        (!paths.is_empty()).then_some(paths)
+    }
+
+    fn render(template: &str, envelope: &serde_json::Value) -> CoreResult<String> {
+        let mut rendered = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find("{{") {
+            let Some(end) = rest[start..].find("}}") else {
+                break;
+            };
+            rendered.push_str(&rest[..start]);
+            match Self::lookup(envelope, &Self::parse_path(rest[start + 2..start + end].trim())?) {
+                serde_json::Value::Null => {}
+                serde_json::Value::String(text) => rendered.push_str(&text),
+                value => rendered.push_str(&value.to_string()),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    /// Split a source path into its segments, checking where it starts
+    fn parse_path(path: &str) -> CoreResult<Vec<String>> {
+        let mut segments = split_json_path(path)
+            .ok_or_else(|| CoreError::InvalidTrigger(format!("Invalid webhook payload source: {:?}", path)))?;
+        match segments.first() {
+            Some(source) if Self::SOURCES.contains(&source.as_str()) => {}
+            _ => return Err(CoreError::InvalidTrigger(format!(
+                "Webhook payload source {} must start at one of {}", path, Self::SOURCES.join(", ")
+            ))),
+        }
+        if segments[0] == "headers" {
+            if let Some(name) = segments.get_mut(1) {
+                *name = name.to_lowercase();
+            }
+        }
+        Ok(segments)
+    }
+
+    fn lookup(envelope: &serde_json::Value, segments: &[String]) -> serde_json::Value {
+        lookup_json_path(envelope, segments)
+    }
+}
+
+/// Split a JSON path like `$.items[0].id` into its segments, or None when it is malformed
+pub(crate) fn split_json_path(path: &str) -> Option<Vec<String>> {
+    let trimmed = path.trim();
+    let trimmed = trimmed.strip_prefix("$.").unwrap_or(trimmed);
+    let mut segments = Vec::new();
+    for part in trimmed.split('.') {
+        let (name, indexes) = part.split_once('[').map_or((part, ""), |(name, rest)| (name, rest));
+        if !name.is_empty() {
+            segments.push(name.to_string());
+        }
+        for index in indexes.split('[').filter(|index| !index.is_empty()) {
+            let index = index.strip_suffix(']').filter(|index| index.parse::<usize>().is_ok())?;
+            segments.push(index.to_string());
+        }
+        if name.is_empty() && indexes.is_empty() {
+            return None;
+        }
+    }
+    Some(segments)
+}
+
+/// Get the value at the segments of a JSON path, or null when there is none
+pub(crate) fn lookup_json_path(value: &serde_json::Value, segments: &[String]) -> serde_json::Value {
+    let mut value = value;
+    for segment in segments {
+        let next = match value {
+            serde_json::Value::Object(fields) => fields.get(segment),
+            serde_json::Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get(index)),
+            _ => None,
+        };
+        match next {
+            Some(next) => value = next,
+            None => return serde_json::Value::Null,
+        }
+    }
+    value.clone()
 }
 
 /// Webhook validation configuration
@@ -100,6 +703,201 @@ impl WebhookValidation {
         self.required_fields = Some(fields);
         self
     }
+
+    /// Validate the signature configuration
+    pub fn validate(&self) -> CoreResult<()> {
+        if let Some(secret) = &self.secret {
+            if secret.is_empty() {
+                return Err(CoreError::InvalidTrigger("Webhook secret cannot be empty".to_string()));
+            }
+        }
+
+        if let Some(algorithm) = &self.signature_algorithm {
+            if !matches!(algorithm.to_lowercase().as_str(), "sha256" | "sha1") {
+                return Err(CoreError::InvalidTrigger(format!("Unsupported signature algorithm: {}", algorithm)));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Verify the request signature against the configured secret
+    pub fn verify_signature(&self, headers: &HashMap<String, String>, body: &[u8]) -> CoreResult<()> {
+        let secret = match &self.secret {
+            Some(secret) => secret,
+            None => return Ok(()),
+        };
+
+        let signature_header = self.signature_header.as_deref().unwrap_or(DEFAULT_SIGNATURE_HEADER);
+        let signature_algorithm = self.signature_algorithm.as_deref().unwrap_or("sha256");
+
+        // HTTP header names are case-insensitive
+        let received_signature = headers.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(signature_header))
+            .map(|(_, value)| value.as_str())
+            .ok_or_else(|| CoreError::InvalidSignature(format!("Missing signature header: {}", signature_header)))?;
+
+        verify_hmac_signature(signature_algorithm, secret, body, received_signature)
+    }
+}
+
+/// Credentials a webhook trigger requires of its requests
+///
+/// Keys, passwords and tokens are not part of the trigger: each names a secret
+/// of the workflow in the secret store, read when a request comes in, so they
+/// can be rotated without registering the workflow again. A request is
+/// rejected when the secret it is checked against is missing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum WebhookAuth {
+    /// An API key sent in a request header
+    ApiKey {
+        #[serde(default)]
+        header: Option<String>,
+        key_secret: String,
+    },
+    /// HTTP basic credentials
+    Basic {
+        username: String,
+        password_secret: String,
+    },
+    /// A bearer token in the `Authorization` header
+    Bearer {
+        token_secret: String,
+    },
+}
+
+impl WebhookAuth {
+    /// Validate the auth configuration
+    pub fn validate(&self) -> CoreResult<()> {
+        let (secret, what) = match self {
+            WebhookAuth::ApiKey { header, key_secret } => {
+                if header.as_ref().is_some_and(|header| header.trim().is_empty()) {
+                    return Err(CoreError::InvalidTrigger("API key header cannot be empty".to_string()));
+                }
+                (key_secret, "API key")
+            }
+            WebhookAuth::Basic { username, password_secret } => {
+                if username.is_empty() || username.contains(':') {
+                    return Err(CoreError::InvalidTrigger("Basic auth username cannot be empty or contain ':'".to_string()));
+                }
+                (password_secret, "basic auth password")
+            }
+            WebhookAuth::Bearer { token_secret } => (token_secret, "bearer token"),
+        };
+        if secret.trim().is_empty() {
+            return Err(CoreError::InvalidTrigger(format!("Webhook {} secret name cannot be empty", what)));
+        }
+        Ok(())
+    }
+
+    /// Name of the secret the request's credentials are checked against
+    pub fn secret_name(&self) -> &str {
+        match self {
+            WebhookAuth::ApiKey { key_secret, .. } => key_secret,
+            WebhookAuth::Basic { password_secret, .. } => password_secret,
+            WebhookAuth::Bearer { token_secret } => token_secret,
+        }
+    }
+
+    /// Value of the `WWW-Authenticate` header sent with rejections, if the scheme has one
+    pub fn challenge(&self) -> Option<&'static str> {
+        match self {
+            WebhookAuth::ApiKey { .. } => None,
+            WebhookAuth::Basic { .. } => Some("Basic realm=\"cronflow\""),
+            WebhookAuth::Bearer { .. } => Some("Bearer realm=\"cronflow\""),
+        }
+    }
+
+    /// Check the credentials of a request against the workflow's secrets
+    pub fn verify(&self, request: &WebhookRequest, secrets: &HashMap<String, String>) -> CoreResult<()> {
+        let expected = secrets.get(self.secret_name()).ok_or_else(|| {
+            log::error!("Webhook auth secret {} is not set for trigger {}", self.secret_name(), request.path);
+            CoreError::Unauthorized("Credentials cannot be checked".to_string())
+        })?;
+        match self {
+            WebhookAuth::ApiKey { header, .. } => {
+                let header = header.as_deref().unwrap_or(DEFAULT_API_KEY_HEADER);
+                let key = request.header(header)
+                    .ok_or_else(|| CoreError::Unauthorized(format!("Missing API key header: {}", header)))?;
+                check_credential(key.trim(), expected, "Invalid API key")
+            }
+            WebhookAuth::Basic { username, .. } => {
+                let encoded = authorization(request, "Basic")?;
+                let decoded = base64::engine::general_purpose::STANDARD.decode(encoded)
+                    .ok()
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .ok_or_else(|| CoreError::Unauthorized("Malformed basic credentials".to_string()))?;
+                let (given_username, password) = decoded.split_once(':')
+                    .ok_or_else(|| CoreError::Unauthorized("Malformed basic credentials".to_string()))?;
+                // Both parts are always compared, so a wrong username takes as long as a wrong password
+                let username_matches = check_credential(given_username, username, "").is_ok();
+                let password_matches = check_credential(password, expected, "").is_ok();
+                if username_matches && password_matches {
+                    Ok(())
+                } else {
+                    Err(CoreError::Unauthorized("Invalid username or password".to_string()))
+                }
+            }
+            WebhookAuth::Bearer { .. } => check_credential(authorization(request, "Bearer")?, expected, "Invalid bearer token"),
+        }
+    }
+}
+
+/// Credentials of an `Authorization` header using a scheme
+fn authorization<'a>(request: &'a WebhookRequest, scheme: &str) -> CoreResult<&'a str> {
+    let value = request.header("authorization")
+        .ok_or_else(|| CoreError::Unauthorized("Missing Authorization header".to_string()))?
+        .trim();
+    value.split_once(' ')
+        .filter(|(given, _)| given.eq_ignore_ascii_case(scheme))
+        .map(|(_, credentials)| credentials.trim())
+        .ok_or_else(|| CoreError::Unauthorized(format!("Authorization header must use the {} scheme", scheme)))
+}
+
+/// Compare a credential in time independent of where it differs
+fn check_credential(given: &str, expected: &str, error: &str) -> CoreResult<()> {
+    let differences = given.bytes().zip(expected.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b));
+    if given.len() == expected.len() && differences == 0 {
+        Ok(())
+    } else {
+        Err(CoreError::Unauthorized(error.to_string()))
+    }
+}
+
+/// Verify an HMAC signature given as `<algorithm>=<hex>` or raw hex
+pub fn verify_hmac_signature(algorithm: &str, secret: &str, body: &[u8], received_signature: &str) -> CoreResult<()> {
+    let algorithm = algorithm.to_lowercase();
+    let received_signature = received_signature.trim();
+    let received_hex = received_signature
+        .strip_prefix(&format!("{}=", algorithm))
+        .unwrap_or(received_signature);
+
+    let received_bytes = hex::decode(received_hex)
+        .map_err(|_| CoreError::InvalidSignature("Signature is not valid hex".to_string()))?;
+
+    // verify_slice compares in constant time
+    let verified = match algorithm.as_str() {
+        "sha256" => {
+            let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .map_err(|e| CoreError::Internal(format!("Invalid HMAC key: {}", e)))?;
+            mac.update(body);
+            mac.verify_slice(&received_bytes).is_ok()
+        }
+        "sha1" => {
+            let mut mac = Hmac::<Sha1>::new_from_slice(secret.as_bytes())
+                .map_err(|e| CoreError::Internal(format!("Invalid HMAC key: {}", e)))?;
+            mac.update(body);
+            mac.verify_slice(&received_bytes).is_ok()
+        }
+        _ => return Err(CoreError::InvalidTrigger(format!("Unsupported signature algorithm: {}", algorithm))),
+    };
+
+    if verified {
+        Ok(())
+    } else {
+        Err(CoreError::InvalidSignature("HMAC signature mismatch".to_string()))
+    }
 }
 
 /// Webhook request payload
@@ -142,6 +940,30 @@ impl WebhookRequest {
         self
     }
 
+    /// Get the body parsed as JSON, or as a string when it is not JSON
+    pub fn json_body(&self) -> Option<serde_json::Value> {
+        self.body.as_deref().map(|body| serde_json::from_str(body).unwrap_or_else(|_| serde_json::json!(body)))
+    }
+
+    /// Get a request header, matching its name case-insensitively
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Get the correlation ID the caller attached to the request, if any
+    pub fn correlation_id(&self) -> Option<&str> {
+        self.header(CORRELATION_ID_HEADER)
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Get the W3C trace context the caller sent with the request, if any
+    pub fn trace_parent(&self) -> Option<&str> {
+        self.header(crate::telemetry::TRACEPARENT_HEADER)
+    }
+
     /// Validate the webhook request
     pub fn validate(&self) -> CoreResult<()> {
         if self.path.is_empty() {
@@ -202,6 +1024,8 @@ impl WebhookResponse {
 #[derive(Debug)]
 pub struct TriggerManager {
     pub webhook_triggers: HashMap<String, (WebhookTrigger, String)>, // path -> (trigger, workflow_id)
+    pub event_subscriptions: HashMap<String, Vec<(String, Vec<u32>)>>, // event name -> [(workflow_id, accepted versions)]
+    pub auth_failures: HashMap<String, u64>, // trigger path -> rejected requests
 }
 
 impl TriggerManager {
@@ -209,6 +1033,8 @@ impl TriggerManager {
     pub fn new() -> Self {
         Self {
             webhook_triggers: HashMap::new(),
+            event_subscriptions: HashMap::new(),
+            auth_failures: HashMap::new(),
         }
     }
 
@@ -218,8 +1044,9 @@ impl TriggerManager {
         
         trigger.validate()?;
         
-        if self.webhook_triggers.contains_key(&trigger.path) {
-            return Err(CoreError::InvalidTrigger(format!("Webhook path {} is already registered", trigger.path)));
+        let shape = WebhookTrigger::route_shape(&trigger.path);
+        if let Some(existing) = self.webhook_triggers.keys().find(|path| WebhookTrigger::route_shape(path) == shape) {
+            return Err(CoreError::InvalidTrigger(format!("Webhook path {} is already registered as {}", trigger.path, existing)));
         }
         
         let path = trigger.path.clone();
@@ -235,7 +1062,7 @@ impl TriggerManager {
         
         request.validate()?;
         
-        let (trigger, workflow_id) = self.webhook_triggers.get(&request.path)
+        let (trigger, workflow_id, path_params) = self.resolve_webhook(&request.path)
             .ok_or_else(|| CoreError::TriggerNotFound(format!("No webhook trigger found for path: {}", request.path)))?;
         
         if trigger.method != request.method {
@@ -249,7 +1076,7 @@ impl TriggerManager {
         }
         
         // Prepare payload for workflow
-        let payload = self.prepare_workflow_payload(&request)?;
+        let payload = trigger.build_payload(&request, &path_params, || self.prepare_workflow_payload(&request, path_params.clone()))?;
         
         log::info!("Webhook request validated, triggering workflow: {}", workflow_id);
         Ok((workflow_id.clone(), payload))
@@ -257,6 +1084,9 @@ impl TriggerManager {
 
     /// Validate webhook request based on validation rules
     fn validate_webhook(&self, request: &WebhookRequest, validation: &WebhookValidation) -> CoreResult<()> {
+        let body = request.body.as_deref().unwrap_or("");
+        validation.verify_signature(&request.headers, body.as_bytes())?;
+        
         if let Some(required_fields) = &validation.required_fields {
             if let Some(body) = &request.body {
                 let body_json: serde_json::Value = serde_json::from_str(body)
@@ -272,16 +1102,15 @@ impl TriggerManager {
             }
         }
         
-        // Current validation covers required fields, headers, and basic request structure
-        
         Ok(())
     }
 
     /// Prepare payload for workflow execution
-    fn prepare_workflow_payload(&self, request: &WebhookRequest) -> CoreResult<serde_json::Value> {
+    fn prepare_workflow_payload(&self, request: &WebhookRequest, path_params: HashMap<String, String>) -> CoreResult<serde_json::Value> {
         let mut payload = serde_json::json!({
             "method": request.method,
             "path": request.path,
+            "path_params": path_params,
             "headers": request.headers,
             "query_params": request.query_params,
         });
@@ -311,14 +1140,76 @@ impl TriggerManager {
         self.webhook_triggers.contains_key(path)
     }
 
-    /// Get workflow ID for a webhook path
+    /// Get workflow ID for a webhook request path
     pub fn get_workflow_id_for_webhook(&self, path: &str) -> Option<&String> {
-        self.webhook_triggers.get(path).map(|(_, workflow_id)| workflow_id)
+        self.resolve_webhook(path).map(|(_, workflow_id, _)| workflow_id)
     }
     
-    /// Get webhook trigger details for a path
+    /// Get webhook trigger details for a request path
     pub fn get_webhook_trigger(&self, path: &str) -> Option<(&WebhookTrigger, &String)> {
-        self.webhook_triggers.get(path).map(|(trigger, workflow_id)| (trigger, workflow_id))
+        self.resolve_webhook(path).map(|(trigger, workflow_id, _)| (trigger, workflow_id))
+    }
+
+    /// Find the trigger a request path is routed to, with the path parameters it carries
+    ///
+    /// An exact path wins; otherwise literal segments win over parameters from
+    /// left to right, so `/webhook/orders/latest` is preferred over `/webhook/orders/:id`.
+    pub fn resolve_webhook(&self, path: &str) -> Option<(&WebhookTrigger, &String, HashMap<String, String>)> {
+        if let Some((trigger, workflow_id)) = self.webhook_triggers.get(path) {
+            return Some((trigger, workflow_id, HashMap::new()));
+        }
+        self.webhook_triggers.values()
+            .filter_map(|(trigger, workflow_id)| trigger.match_path(path).map(|params| (trigger, workflow_id, params)))
+            .min_by_key(|(trigger, _, _)| trigger.path.split('/').map(|segment| segment.starts_with(':')).collect::<Vec<_>>())
+    }
+
+    /// Remove the webhook triggers of a workflow, returning their paths
+    pub fn remove_webhook_triggers(&mut self, workflow_id: &str) -> Vec<String> {
+        let paths: Vec<String> = self.webhook_triggers
+            .iter()
+            .filter(|(_, (_, wf_id))| wf_id == workflow_id)
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in &paths {
+            self.webhook_triggers.remove(path);
+            self.auth_failures.remove(path);
+        }
+        paths
+    }
+
+    /// Count a request rejected by the auth of the trigger at a path
+    pub fn record_auth_failure(&mut self, trigger_path: &str) {
+        *self.auth_failures.entry(trigger_path.to_string()).or_default() += 1;
+    }
+
+    /// Verify the signature of a request against its trigger, if one is configured
+    pub fn verify_webhook_signature(&self, request: &WebhookRequest, body: &[u8]) -> CoreResult<()> {
+        match self.resolve_webhook(&request.path) {
+            Some((WebhookTrigger { validation: Some(validation), .. }, _, _)) => {
+                validation.verify_signature(&request.headers, body)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Subscribe a workflow to an event, replacing its earlier subscription
+    pub fn register_event_subscription(&mut self, workflow_id: &str, event_name: &str, versions: Vec<u32>) {
+        let subscribers = self.event_subscriptions.entry(event_name.to_string()).or_default();
+        subscribers.retain(|(id, _)| id != workflow_id);
+        subscribers.push((workflow_id.to_string(), versions));
+    }
+
+    /// Remove all event subscriptions of a workflow
+    pub fn remove_event_subscriptions(&mut self, workflow_id: &str) {
+        for subscribers in self.event_subscriptions.values_mut() {
+            subscribers.retain(|(id, _)| id != workflow_id);
+        }
+        self.event_subscriptions.retain(|_, subscribers| !subscribers.is_empty());
+    }
+
+    /// Get the workflows subscribed to an event with the versions they accept
+    pub fn get_event_subscribers(&self, event_name: &str) -> Vec<(String, Vec<u32>)> {
+        self.event_subscriptions.get(event_name).cloned().unwrap_or_default()
     }
 }
 
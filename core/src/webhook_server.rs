@@ -4,19 +4,23 @@
 //! and trigger workflows based on the incoming requests.
 
 use actix_web::{web, App, HttpServer, HttpRequest, HttpResponse, Responder, middleware};
+use actix_web::http::StatusCode;
+use actix_web::http::header::{HeaderValue, RETRY_AFTER, WWW_AUTHENTICATE};
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::signal;
+use tokio::sync::Mutex as TokioMutex;
 use log;
-use hmac::{Hmac, Mac};
-use sha2::Sha256;
-use sha1::Sha1;
-use hex;
 
 use crate::error::{CoreError, CoreResult};
-use crate::triggers::{TriggerManager, WebhookRequest, WebhookResponse};
+use crate::triggers::{verify_hmac_signature, TriggerManager, WebhookReply, WebhookRequest, WebhookResponse, WebhookResponseOptions};
 use crate::state::StateManager;
+use crate::models::{RunEvent, RunTrigger};
+use crate::trigger_executor::{TriggerMetrics, TriggerOutcome, TriggerRateLimiter};
+
+/// How often a sync webhook reply checks on its run
+const SYNC_REPLY_POLL_INTERVAL: Duration = Duration::from_millis(50);
 
 /// Webhook server configuration
 #[derive(Debug, Clone)]
@@ -43,8 +47,10 @@ impl Default for WebhookServerConfig {
 /// Webhook server instance with graceful shutdown support
 pub struct WebhookServer {
     config: WebhookServerConfig,
-    trigger_manager: Arc<Mutex<TriggerManager>>,
+    trigger_manager: Arc<TokioMutex<TriggerManager>>,
     state_manager: Arc<Mutex<StateManager>>,
+    rate_limiter: Arc<Mutex<TriggerRateLimiter>>,
+    trigger_metrics: Arc<Mutex<TriggerMetrics>>,
     shutdown_flag: Arc<AtomicBool>,
     server_handle: Option<tokio::task::JoinHandle<Result<(), std::io::Error>>>,
 }
@@ -53,24 +59,41 @@ impl WebhookServer {
     /// Create a new webhook server
     pub fn new(
         config: WebhookServerConfig,
-        trigger_manager: Arc<Mutex<TriggerManager>>,
+        trigger_manager: Arc<TokioMutex<TriggerManager>>,
         state_manager: Arc<Mutex<StateManager>>,
     ) -> Self {
+        let core_config = crate::config::CoreConfig::default();
         Self {
             config,
             trigger_manager,
             state_manager,
+            rate_limiter: Arc::new(Mutex::new(TriggerRateLimiter::new(core_config.trigger_rate_limit))),
+            trigger_metrics: Arc::new(Mutex::new(TriggerMetrics::new(core_config.trigger_stats, chrono::Utc::now()))),
             shutdown_flag: Arc::new(AtomicBool::new(false)),
             server_handle: None,
         }
     }
 
+    /// Share a trigger rate limiter, e.g. the trigger executor's
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<Mutex<TriggerRateLimiter>>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Share the per-trigger stats, e.g. the trigger executor's
+    pub fn with_trigger_metrics(mut self, trigger_metrics: Arc<Mutex<TriggerMetrics>>) -> Self {
+        self.trigger_metrics = trigger_metrics;
+        self
+    }
+
     /// Start the webhook server with graceful shutdown support
     pub async fn start(&mut self) -> CoreResult<()> {
         log::info!("Starting webhook server on {}:{}", self.config.host, self.config.port);
         
         let trigger_manager = self.trigger_manager.clone();
         let state_manager = self.state_manager.clone();
+        let rate_limiter = self.rate_limiter.clone();
+        let trigger_metrics = self.trigger_metrics.clone();
         let shutdown_flag = self.shutdown_flag.clone();
         let graceful_timeout = self.config.graceful_shutdown_timeout;
         
@@ -79,7 +102,11 @@ impl WebhookServer {
                 .wrap(middleware::Logger::default())
                 .app_data(web::Data::new(trigger_manager.clone()))
                 .app_data(web::Data::new(state_manager.clone()))
-                .route("/webhook/{path:.*}", web::post().to(webhook_handler))
+                .app_data(web::Data::new(rate_limiter.clone()))
+                .app_data(web::Data::new(trigger_metrics.clone()))
+                // Routed per request through the trigger manager, so triggers
+                // registered or removed while the server runs apply at once
+                .route("/webhook/{path:.*}", web::route().to(webhook_handler))
                 .route("/health", web::get().to(health_check))
                 .route("/shutdown", web::post().to(shutdown_handler))
         })
@@ -200,13 +227,66 @@ async fn shutdown_handler() -> impl Responder {
     }))
 }
 
-/// Main webhook handler with signature validation
+/// Main webhook handler, counting each request to a known trigger in the per-trigger stats
 async fn webhook_handler(
     req: HttpRequest,
     body: web::Bytes,
-    trigger_manager: web::Data<Arc<Mutex<TriggerManager>>>,
+    trigger_manager: web::Data<Arc<TokioMutex<TriggerManager>>>,
     state_manager: web::Data<Arc<Mutex<StateManager>>>,
+    rate_limiter: web::Data<Arc<Mutex<TriggerRateLimiter>>>,
+    trigger_metrics: web::Data<Arc<Mutex<TriggerMetrics>>>,
 ) -> impl Responder {
+    let started = Instant::now();
+    let fired = trigger_manager.lock().await.resolve_webhook(req.path())
+        .map(|(trigger, workflow_id, _)| (format!("webhook:{}", trigger.path), workflow_id.clone()));
+    let reply = respond_to_webhook(req, body, trigger_manager, state_manager.clone(), rate_limiter).await;
+    if let Some((trigger, workflow_id)) = fired {
+        let outcome = TriggerOutcome::of_status(reply.status().as_u16());
+        record_firing(&trigger_metrics, &state_manager, &trigger, &workflow_id, outcome, started);
+    }
+    reply
+}
+
+/// Count a webhook request in the per-trigger stats, persisting the counts once they are due
+fn record_firing(
+    trigger_metrics: &Mutex<TriggerMetrics>,
+    state_manager: &Mutex<StateManager>,
+    trigger: &str,
+    workflow_id: &str,
+    outcome: TriggerOutcome,
+    started: Instant,
+) {
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let due = match trigger_metrics.lock() {
+        Ok(mut metrics) => metrics.record(trigger, workflow_id, outcome, latency_ms, chrono::Utc::now()),
+        Err(e) => {
+            log::error!("Failed to acquire trigger metrics lock: {}", e);
+            return;
+        }
+    };
+    let Some(due) = due else {
+        return;
+    };
+    
+    let persisted = state_manager.lock()
+        .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))
+        .and_then(|state_manager| state_manager.add_trigger_counters(&due));
+    if let Err(e) = persisted {
+        log::warn!("Failed to persist trigger stats, keeping them for the next attempt: {}", e);
+        if let Ok(mut metrics) = trigger_metrics.lock() {
+            metrics.requeue(due);
+        }
+    }
+}
+
+/// Authenticate, validate and handle a webhook request
+async fn respond_to_webhook(
+    req: HttpRequest,
+    body: web::Bytes,
+    trigger_manager: web::Data<Arc<TokioMutex<TriggerManager>>>,
+    state_manager: web::Data<Arc<Mutex<StateManager>>>,
+    rate_limiter: web::Data<Arc<Mutex<TriggerRateLimiter>>>,
+) -> HttpResponse {
     let path = req.path().to_string();
     let method = req.method().as_str().to_string();
     
@@ -230,166 +310,312 @@ async fn webhook_handler(
         }
     }
     
+    let webhook_request = WebhookRequest::new(method.clone(), path.clone())
+        .with_headers(headers)
+        .with_query_params(query_params);
+    
+    // Status code, headers and error body the trigger asks for, if any
+    let options = trigger_manager.lock().await
+        .get_webhook_trigger(&path)
+        .and_then(|(trigger, _)| trigger.response.clone());
+    let error_reply = |status: StatusCode, message: String, request: &WebhookRequest| {
+        reply_error(status, message, serde_json::Map::new(), options.as_ref(), request)
+    };
+    
     // Convert body to string
-    let body_str = match String::from_utf8(body.to_vec()) {
-        Ok(s) => s,
+    let webhook_request = match String::from_utf8(body.to_vec()) {
+        Ok(body_str) => webhook_request.with_body(body_str),
         Err(e) => {
             log::error!("Invalid UTF-8 in request body: {}", e);
-            return HttpResponse::BadRequest().json(serde_json::json!({
-                "status": "error",
-                "message": "Invalid request body encoding",
-                "workflow_triggered": false,
-            }));
+            return error_reply(StatusCode::BAD_REQUEST, "Invalid request body encoding".to_string(), &webhook_request);
         }
     };
     
-    let webhook_request = WebhookRequest::new(method.clone(), path.clone())
-        .with_headers(headers.clone())
-        .with_body(body_str.clone())
-        .with_query_params(query_params);
+    // Authenticate before anything else about the request is looked at
+    if let Err((auth_error, challenge)) = authenticate_webhook_request(&webhook_request, &trigger_manager, &state_manager).await {
+        log::warn!("Webhook authentication failed: {} {} - {}", method, path, auth_error);
+        let mut reply = error_reply(StatusCode::UNAUTHORIZED, auth_error.to_string(), &webhook_request);
+        if let Some(challenge) = challenge {
+            reply.headers_mut().insert(WWW_AUTHENTICATE, HeaderValue::from_static(challenge));
+        }
+        return reply;
+    }
     
     // Validate signature if configured
-    if let Err(signature_error) = validate_webhook_signature(&webhook_request, &body.to_vec(), &trigger_manager).await {
+    if let Err(signature_error) = validate_webhook_signature(&webhook_request, &body, &trigger_manager).await {
         log::error!("Webhook signature validation failed: {} {} - {}", method, path, signature_error);
-        return HttpResponse::Unauthorized().json(serde_json::json!({
-            "status": "error",
-            "message": format!("Signature validation failed: {}", signature_error),
-            "workflow_triggered": false,
-        }));
+        return error_reply(StatusCode::UNAUTHORIZED, format!("Signature validation failed: {}", signature_error), &webhook_request);
     }
     
     // Handle the webhook request
-    match handle_webhook_request(webhook_request, trigger_manager, state_manager).await {
-        Ok(_response) => {
-            log::info!("Webhook request processed successfully: {} {}", method, path);
-            HttpResponse::Ok().json(serde_json::json!({
-                "status": "success",
-                "message": "Webhook processed successfully",
-                "workflow_triggered": true,
-            }))
+    match handle_webhook_request(webhook_request.clone(), trigger_manager, state_manager, rate_limiter).await {
+        Ok(response) => {
+            log::info!("Webhook request processed successfully: {} {} ({})", method, path, response.status_code);
+            reply_with(response, options.as_ref(), &webhook_request)
+        }
+        Err(e @ CoreError::InvalidSignature(_)) => {
+            log::error!("Webhook signature validation failed: {} {} - {}", method, path, e);
+            error_reply(StatusCode::UNAUTHORIZED, e.to_string(), &webhook_request)
+        }
+        Err(e @ CoreError::DuplicateCorrelationId(_)) => {
+            log::warn!("Webhook request reused a correlation ID: {} {} - {}", method, path, e);
+            error_reply(StatusCode::CONFLICT, e.to_string(), &webhook_request)
+        }
+        Err(e @ CoreError::WorkflowDisabled(_)) => {
+            log::warn!("Webhook request for a disabled workflow: {} {} - {}", method, path, e);
+            let mut extra = serde_json::Map::new();
+            extra.insert("disabled".to_string(), true.into());
+            reply_error(StatusCode::SERVICE_UNAVAILABLE, e.to_string(), extra, options.as_ref(), &webhook_request)
+        }
+        Err(e @ CoreError::RateLimited { .. }) => {
+            log::warn!("Webhook request rate limited: {} {} - {}", method, path, e);
+            let retry_after_ms = match &e {
+                CoreError::RateLimited { retry_after_ms, .. } => *retry_after_ms,
+                _ => 0,
+            };
+            let mut extra = serde_json::Map::new();
+            extra.insert("retry_after_ms".to_string(), retry_after_ms.into());
+            let mut reply = reply_error(StatusCode::TOO_MANY_REQUESTS, e.to_string(), extra, options.as_ref(), &webhook_request);
+            if let Ok(value) = HeaderValue::from_str(&retry_after_ms.div_ceil(1000).to_string()) {
+                reply.headers_mut().insert(RETRY_AFTER, value);
+            }
+            reply
         }
         Err(e) => {
             log::error!("Webhook request failed: {} {} - {}", method, path, e);
-            HttpResponse::BadRequest().json(serde_json::json!({
+            error_reply(StatusCode::BAD_REQUEST, e.to_string(), &webhook_request)
+        }
+    }
+}
+
+/// Turn the engine's reply into an HTTP response, applying the trigger's response options
+fn reply_with(response: WebhookResponse, options: Option<&WebhookResponseOptions>, request: &WebhookRequest) -> HttpResponse {
+    if response.status_code >= 400 {
+        if let Some(options) = options.filter(|options| options.error_body.is_some()) {
+            let body: serde_json::Value = response.body.as_deref()
+                .and_then(|body| serde_json::from_str(body).ok())
+                .unwrap_or_default();
+            let message = body.get("message").and_then(|m| m.as_str()).unwrap_or_default().to_string();
+            let status = StatusCode::from_u16(response.status_code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return reply_error(status, message, serde_json::Map::new(), Some(options), request);
+        }
+    }
+    
+    let status_code = options.map_or(response.status_code, |options| options.success_status(response.status_code));
+    let status = StatusCode::from_u16(status_code).unwrap_or(StatusCode::OK);
+    let mut reply = HttpResponse::build(status);
+    reply.content_type("application/json");
+    for (name, value) in &response.headers {
+        reply.insert_header((name.as_str(), value.as_str()));
+    }
+    if let Some(options) = options {
+        for (name, value) in options.render_headers(status_code, "", request) {
+            reply.insert_header((name, value));
+        }
+    }
+    reply.body(response.body.unwrap_or_default())
+}
+
+/// Build an error response, with the trigger's error body template and headers when set
+///
+/// Without a template the body is the engine's JSON error, with `extra` fields added.
+fn reply_error(
+    status: StatusCode,
+    message: String,
+    extra: serde_json::Map<String, serde_json::Value>,
+    options: Option<&WebhookResponseOptions>,
+    request: &WebhookRequest,
+) -> HttpResponse {
+    let mut reply = HttpResponse::build(status);
+    if let Some(options) = options {
+        for (name, value) in options.render_headers(status.as_u16(), &message, request) {
+            reply.insert_header((name, value));
+        }
+    }
+    match options.and_then(|options| options.render_error_body(status.as_u16(), &message, request)) {
+        Some((body, content_type)) => reply.content_type(content_type).body(body),
+        None => {
+            let mut body = serde_json::json!({
                 "status": "error",
-                "message": e.to_string(),
+                "message": message,
                 "workflow_triggered": false,
-            }))
+            });
+            if let Some(fields) = body.as_object_mut() {
+                fields.extend(extra);
+            }
+            reply.json(body)
         }
     }
 }
 
 /// Handle webhook request and trigger workflow
+///
+/// Triggers replying in sync mode wait for the awaited output before replying.
 async fn handle_webhook_request(
     request: WebhookRequest,
-    trigger_manager: web::Data<Arc<Mutex<TriggerManager>>>,
+    trigger_manager: web::Data<Arc<TokioMutex<TriggerManager>>>,
     state_manager: web::Data<Arc<Mutex<StateManager>>>,
+    rate_limiter: web::Data<Arc<Mutex<TriggerRateLimiter>>>,
 ) -> CoreResult<WebhookResponse> {
-    let trigger_manager_guard = trigger_manager.lock()
-        .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
-    
-    // Handle the webhook request
-    let (workflow_id, payload) = trigger_manager_guard.handle_webhook_request(request)?;
+    let mut trigger = RunTrigger::new("webhook", Some(&request.path)).with_trace_parent(request.trace_parent());
+    let (workflow_id, payload, respond_with, dedup, correlation_id) = {
+        let trigger_manager_guard = trigger_manager.lock().await;
+        
+        if let Some((webhook, _)) = trigger_manager_guard.get_webhook_trigger(&request.path) {
+            trigger = trigger.with_lane(webhook.lane.as_deref());
+        }
+        
+        let respond_with = trigger_manager_guard.get_webhook_trigger(&request.path)
+            .filter(|(trigger, _)| trigger.replies_sync())
+            .and_then(|(trigger, _)| trigger.respond_with.clone());
+        let dedup = trigger_manager_guard.get_webhook_trigger(&request.path)
+            .and_then(|(trigger, _)| trigger.dedup.clone());
+        
+        let correlation_id = request.correlation_id().map(str::to_string);
+        
+        // Handle the webhook request
+        let (workflow_id, payload) = trigger_manager_guard.handle_webhook_request(request)?;
+        (workflow_id, payload, respond_with, dedup, correlation_id)
+    }; // Lock released here
     
-    let mut state_manager_guard = state_manager.lock()
-        .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+    // Duplicates are suppressed before they count against the rate limits
+    let created = {
+        let mut state_manager = state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        state_manager.check_workflow_enabled(&workflow_id)?;
+        match dedup.as_ref().map(|dedup| state_manager.find_duplicate_run(&workflow_id, dedup, &payload, &trigger)).transpose()?.flatten() {
+            Some(suppressed) => Err(suppressed.run_id),
+            None => {
+                rate_limiter.lock()
+                    .map_err(|e| CoreError::Internal(format!("Failed to acquire rate limiter lock: {}", e)))?
+                    .acquire(&workflow_id, std::time::Instant::now())?;
+                let run_id = state_manager.create_correlated_run(&workflow_id, payload.clone(), RunEvent::ACTOR_ENGINE, correlation_id.as_deref())?;
+                state_manager.record_run_trigger(&run_id, &trigger)?;
+                if let Some(dedup) = &dedup {
+                    state_manager.record_dedup_key(&workflow_id, dedup, &payload, &run_id, &trigger)?;
+                }
+                Ok(run_id)
+            }
+        }
+    }; // Lock released here
     
-    let run_id = state_manager_guard.create_run(&workflow_id, payload)?;
+    // A suppressed duplicate gets the reply of the run it duplicates
+    let run_id = match created {
+        Ok(run_id) => {
+            log::info!("Created workflow run {} for webhook-triggered workflow {}", run_id, workflow_id);
+            run_id
+        }
+        Err(existing_run_id) => {
+            return match respond_with {
+                Some(respond_with) => wait_for_reply(&respond_with, &existing_run_id, &state_manager).await,
+                None => Ok(WebhookResponse::new(200).with_body(serde_json::json!({
+                    "status": "success",
+                    "message": "Duplicate webhook suppressed",
+                    "workflow_triggered": false,
+                    "suppressed": true,
+                    "run_id": existing_run_id.to_string(),
+                }).to_string())),
+            };
+        }
+    };
     
-    log::info!("Created workflow run {} for webhook-triggered workflow {}", run_id, workflow_id);
+    match respond_with {
+        Some(respond_with) => wait_for_reply(&respond_with, &run_id, &state_manager).await,
+        None => Ok(WebhookResponse::new(200).with_body(serde_json::json!({
+            "status": "success",
+            "message": "Webhook processed successfully",
+            "workflow_triggered": true,
+            "run_id": run_id.to_string(),
+        }).to_string())),
+    }
+}
+
+/// Wait for the output a sync webhook reply is made of, up to the trigger's timeout
+async fn wait_for_reply(
+    respond_with: &WebhookReply,
+    run_id: &uuid::Uuid,
+    state_manager: &Arc<Mutex<StateManager>>,
+) -> CoreResult<WebhookResponse> {
+    let deadline = tokio::time::Instant::now() + respond_with.timeout();
+    loop {
+        let response = {
+            let state_manager_guard = state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            let (run, results) = state_manager_guard.get_run_progress(run_id)?;
+            respond_with.response_for(&run, &results)
+        }; // Lock released here
+        
+        if let Some(response) = response {
+            return Ok(response);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            log::info!("Run {} did not produce its webhook reply in time", run_id);
+            return Ok(WebhookReply::timeout_response(&run_id.to_string()));
+        }
+        tokio::time::sleep(SYNC_REPLY_POLL_INTERVAL).await;
+    }
+}
+
+/// Check a request's credentials against the auth of its trigger, counting rejections
+///
+/// Rejections come with the challenge of the trigger's auth scheme, if it has one.
+async fn authenticate_webhook_request(
+    request: &WebhookRequest,
+    trigger_manager: &web::Data<Arc<TokioMutex<TriggerManager>>>,
+    state_manager: &web::Data<Arc<Mutex<StateManager>>>,
+) -> Result<(), (CoreError, Option<&'static str>)> {
+    let (auth, trigger_path, workflow_id) = {
+        let trigger_manager_guard = trigger_manager.lock().await;
+        // Unknown paths are reported by the request handler, not as an auth failure
+        match trigger_manager_guard.resolve_webhook(&request.path) {
+            Some((trigger, workflow_id, _)) => match &trigger.auth {
+                Some(auth) => (auth.clone(), trigger.path.clone(), workflow_id.clone()),
+                None => return Ok(()),
+            },
+            None => return Ok(()),
+        }
+    }; // Lock released here
     
-    Ok(WebhookResponse::success())
+    let verified = state_manager.lock()
+        .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))
+        .and_then(|state_manager| state_manager.workflow_secrets(&workflow_id))
+        .and_then(|secrets| auth.verify(request, &secrets));
+    match verified {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            trigger_manager.lock().await.record_auth_failure(&trigger_path);
+            let e = match e {
+                CoreError::Unauthorized(_) => e,
+                other => {
+                    log::error!("Failed to check credentials of webhook {}: {}", request.path, other);
+                    CoreError::Unauthorized("Credentials cannot be checked".to_string())
+                }
+            };
+            Err((e, auth.challenge()))
+        }
+    }
 }
 
 /// Validate webhook signature using HMAC
 async fn validate_webhook_signature(
     request: &WebhookRequest,
     body: &[u8],
-    trigger_manager: &web::Data<Arc<Mutex<TriggerManager>>>,
+    trigger_manager: &web::Data<Arc<TokioMutex<TriggerManager>>>,
 ) -> CoreResult<()> {
-    let trigger_manager_guard = trigger_manager.lock()
-        .map_err(|e| CoreError::Internal(format!("Failed to acquire trigger manager lock: {}", e)))?;
-    
-    // Get the webhook trigger configuration
-    let (trigger, _workflow_id) = trigger_manager_guard.get_webhook_trigger(&request.path)
-        .ok_or_else(|| CoreError::TriggerNotFound(format!("No webhook trigger found for path: {}", request.path)))?;
+    let trigger_manager_guard = trigger_manager.lock().await;
     
-    // If no validation is configured, skip signature validation
-    let validation = match &trigger.validation {
-        Some(v) => v,
-        None => return Ok(()),
-    };
-    
-    // If no secret is configured, skip signature validation
-    let secret = match &validation.secret {
-        Some(s) => s,
-        None => return Ok(()),
-    };
-    
-    // Get signature header (default to X-Hub-Signature-256 for GitHub compatibility)
-    let signature_header = validation.signature_header.as_deref().unwrap_or("x-hub-signature-256");
-    let signature_algorithm = validation.signature_algorithm.as_deref().unwrap_or("sha256");
-    
-    // Get the signature from headers
-    let received_signature = request.headers.get(signature_header)
-        .ok_or_else(|| CoreError::InvalidTrigger(format!("Missing signature header: {}", signature_header)))?;
-    
-    // Validate signature based on algorithm
-    match signature_algorithm.to_lowercase().as_str() {
-        "sha256" => validate_hmac_sha256(secret, body, received_signature),
-        "sha1" => validate_hmac_sha1(secret, body, received_signature),
-        _ => Err(CoreError::InvalidTrigger(format!("Unsupported signature algorithm: {}", signature_algorithm))),
-    }
+    // Unknown paths are reported by the request handler, not as a signature failure
+    trigger_manager_guard.verify_webhook_signature(request, body)
 }
 
 /// Validate HMAC SHA-256 signature
 pub fn validate_hmac_sha256(secret: &str, body: &[u8], received_signature: &str) -> CoreResult<()> {
-    type HmacSha256 = Hmac<Sha256>;
-    
-    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-        .map_err(|e| CoreError::Internal(format!("Invalid HMAC key: {}", e)))?;
-    
-    mac.update(body);
-    let expected_signature = mac.finalize().into_bytes();
-    let expected_hex = hex::encode(expected_signature);
-    
-    // Support both GitHub format (sha256=...) and raw hex
-    let received_hex = if received_signature.starts_with("sha256=") {
-        &received_signature[7..]
-    } else {
-        received_signature
-    };
-    
-    if expected_hex.eq_ignore_ascii_case(received_hex) {
-        Ok(())
-    } else {
-        Err(CoreError::InvalidTrigger("HMAC signature mismatch".to_string()))
-    }
+    verify_hmac_signature("sha256", secret, body, received_signature)
 }
 
 /// Validate HMAC SHA-1 signature
 pub fn validate_hmac_sha1(secret: &str, body: &[u8], received_signature: &str) -> CoreResult<()> {
-    type HmacSha1 = Hmac<Sha1>;
-    
-    let mut mac = HmacSha1::new_from_slice(secret.as_bytes())
-        .map_err(|e| CoreError::Internal(format!("Invalid HMAC key: {}", e)))?;
-    
-    mac.update(body);
-    let expected_signature = mac.finalize().into_bytes();
-    let expected_hex = hex::encode(expected_signature);
-    
-    // Support both GitHub format (sha1=...) and raw hex
-    let received_hex = if received_signature.starts_with("sha1=") {
-        &received_signature[6..]
-    } else {
-        received_signature
-    };
-    
-    if expected_hex.eq_ignore_ascii_case(received_hex) {
-        Ok(())
-    } else {
-        Err(CoreError::InvalidTrigger("HMAC signature mismatch".to_string()))
-    }
+    verify_hmac_signature("sha1", secret, body, received_signature)
 }
 
 /// Webhook server builder for easy configuration
@@ -432,7 +658,7 @@ impl WebhookServerBuilder {
     /// Build the webhook server
     pub fn build(
         self,
-        trigger_manager: Arc<Mutex<TriggerManager>>,
+        trigger_manager: Arc<TokioMutex<TriggerManager>>,
         state_manager: Arc<Mutex<StateManager>>,
     ) -> WebhookServer {
         WebhookServer::new(self.config, trigger_manager, state_manager)
@@ -448,11 +674,15 @@ impl Default for WebhookServerBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::triggers::{WebhookTrigger, TriggerManager};
+    use crate::triggers::{WebhookTrigger, TriggerManager, WebhookPayloadMapping, WebhookRequestFields};
+    use std::collections::BTreeMap;
     use crate::state::StateManager;
     use std::sync::Arc;
     use std::sync::Mutex;
     use tempfile::NamedTempFile;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use sha1::Sha1;
 
     #[test]
     fn test_webhook_server_config() {
@@ -471,7 +701,7 @@ mod tests {
             .max_connections(2000)
             .graceful_shutdown_timeout(Duration::from_secs(60))
             .build(
-                Arc::new(Mutex::new(TriggerManager::new())),
+                Arc::new(TokioMutex::new(TriggerManager::new())),
                 Arc::new(Mutex::new(StateManager::new("test.db").unwrap())),
             );
         
@@ -567,4 +797,342 @@ mod tests {
         assert_eq!(validation.signature_header.as_ref().unwrap(), "x-hub-signature-256");
         assert_eq!(validation.signature_algorithm.as_ref().unwrap(), "sha256");
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_trigger_manager_rejects_bad_signatures() {
+        use crate::triggers::WebhookValidation;
+        
+        let mut trigger_manager = TriggerManager::new();
+        let validation = WebhookValidation::new()
+            .with_secret("test-secret".to_string())
+            .with_signature_header("X-Signature".to_string(), "sha256".to_string());
+        let trigger = WebhookTrigger::new("/webhook/signed".to_string(), "POST".to_string())
+            .with_validation(validation);
+        trigger_manager.register_webhook_trigger("signed-workflow", trigger).unwrap();
+        
+        let body = r#"{"event":"push"}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"test-secret").unwrap();
+        mac.update(body.as_bytes());
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        
+        // Header names are matched case-insensitively
+        let mut headers = HashMap::new();
+        headers.insert("x-signature".to_string(), signature);
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/signed".to_string())
+            .with_headers(headers)
+            .with_body(body.to_string());
+        let (workflow_id, _) = trigger_manager.handle_webhook_request(request).unwrap();
+        assert_eq!(workflow_id, "signed-workflow");
+        
+        let mut headers = HashMap::new();
+        headers.insert("x-signature".to_string(), "sha256=deadbeef".to_string());
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/signed".to_string())
+            .with_headers(headers)
+            .with_body(body.to_string());
+        assert!(matches!(
+            trigger_manager.handle_webhook_request(request),
+            Err(CoreError::InvalidSignature(_))
+        ));
+        
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/signed".to_string())
+            .with_body(body.to_string());
+        assert!(matches!(
+            trigger_manager.verify_webhook_signature(&request, body.as_bytes()),
+            Err(CoreError::InvalidSignature(_))
+        ));
+    }
+
+    #[test]
+    fn test_webhook_trigger_definition_validation() {
+        use crate::models::TriggerDefinition;
+        
+        let trigger: TriggerDefinition = serde_json::from_value(serde_json::json!({
+            "Webhook": {
+                "path": "/webhook/stripe",
+                "method": "POST",
+                "validation": {
+                    "secret": "whsec",
+                    "signature_header": "Stripe-Signature",
+                    "signature_algorithm": "sha256",
+                    "required_fields": null
+                }
+            }
+        })).unwrap();
+        assert!(trigger.validate().is_ok());
+        
+        let legacy: TriggerDefinition = serde_json::from_value(serde_json::json!({
+            "Webhook": { "path": "/webhook/plain", "method": "POST" }
+        })).unwrap();
+        assert!(matches!(legacy, TriggerDefinition::Webhook { validation: None, .. }));
+        
+        let unsupported: TriggerDefinition = serde_json::from_value(serde_json::json!({
+            "Webhook": {
+                "path": "/webhook/md5",
+                "method": "POST",
+                "validation": { "secret": "s", "signature_header": null, "signature_algorithm": "md5", "required_fields": null }
+            }
+        })).unwrap();
+        assert!(unsupported.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_webhook_reply_waits_for_step_output() {
+        use crate::models::{StepDefinition, StepResult, StepStatus, TriggerDefinition, WorkflowDefinition};
+        
+        let trigger: TriggerDefinition = serde_json::from_value(serde_json::json!({
+            "Webhook": {
+                "path": "/webhook/quote",
+                "method": "POST",
+                "respond_with": { "mode": "sync", "step_id": "price", "timeout_ms": 2000 }
+            }
+        })).unwrap();
+        assert!(trigger.validate().is_ok());
+        let TriggerDefinition::Webhook { respond_with: Some(respond_with), .. } = trigger else {
+            panic!("expected a webhook trigger with a reply");
+        };
+        assert_eq!(respond_with.mode, crate::triggers::WebhookReplyMode::Sync);
+        assert!(WebhookReply { timeout_ms: Some(0), ..respond_with.clone() }.validate().is_err());
+        
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut state_manager = StateManager::new(temp_file.path().to_str().unwrap()).unwrap();
+        let step: StepDefinition = serde_json::from_value(serde_json::json!({
+            "id": "price", "name": "price", "action": "price", "depends_on": [], "is_control_flow": false
+        })).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "quotes".to_string(),
+            name: "Quotes".to_string(),
+            description: None,
+            steps: vec![step],
+            triggers: vec![],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("quotes", serde_json::json!({})).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+        
+        let quick = WebhookReply { timeout_ms: Some(100), ..respond_with.clone() };
+        let pending = wait_for_reply(&quick, &run_id, &state_manager).await.unwrap();
+        assert_eq!(pending.status_code, 202);
+        
+        let completer = Arc::clone(&state_manager);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            completer.lock().unwrap().save_step_result(&run_id, StepResult {
+                step_id: "price".to_string(),
+                status: StepStatus::Completed,
+                output: Some(serde_json::json!({"total": 42})),
+                error: None,
+                started_at: chrono::Utc::now(),
+                completed_at: Some(chrono::Utc::now()),
+                duration_ms: Some(3),
+                cache_hit: None,
+                resources: None,
+            }).unwrap();
+        });
+        let reply = wait_for_reply(&respond_with, &run_id, &state_manager).await.unwrap();
+        assert_eq!(reply.status_code, 200);
+        assert_eq!(reply.body.as_deref(), Some(r#"{"total":42}"#));
+    }
+
+    #[test]
+    fn test_webhook_routes_with_path_parameters_apply_while_running() {
+        let mut trigger_manager = TriggerManager::new();
+        trigger_manager.register_webhook_trigger("orders", WebhookTrigger::new("/webhook/orders/:id".to_string(), "POST".to_string())).unwrap();
+        trigger_manager.register_webhook_trigger("latest", WebhookTrigger::new("/webhook/orders/latest".to_string(), "POST".to_string())).unwrap();
+        assert!(trigger_manager.register_webhook_trigger("other", WebhookTrigger::new("/webhook/orders/:order".to_string(), "POST".to_string())).is_err());
+        assert!(WebhookTrigger::new("/webhook/:id/items/:id".to_string(), "POST".to_string()).validate().is_err());
+        
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/orders/42".to_string())
+            .with_body(r#"{"total":10}"#.to_string());
+        let (workflow_id, payload) = trigger_manager.handle_webhook_request(request).unwrap();
+        assert_eq!(workflow_id, "orders");
+        assert_eq!(payload["path_params"], serde_json::json!({"id": "42"}));
+        assert_eq!(payload["body"]["total"], 10);
+        assert_eq!(trigger_manager.get_workflow_id_for_webhook("/webhook/orders/latest"), Some(&"latest".to_string()));
+        assert!(trigger_manager.get_webhook_trigger("/webhook/orders/42/items").is_none());
+        assert!(trigger_manager.get_webhook_trigger("/webhook/orders/").is_none());
+        
+        assert_eq!(trigger_manager.remove_webhook_triggers("orders"), vec!["/webhook/orders/:id".to_string()]);
+        assert!(trigger_manager.get_webhook_trigger("/webhook/orders/42").is_none());
+        trigger_manager.register_webhook_trigger("other", WebhookTrigger::new("/webhook/orders/:order".to_string(), "POST".to_string())).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trigger_response_options_shape_replies() {
+        let options: WebhookResponseOptions = serde_json::from_value(serde_json::json!({
+            "success_status": 202,
+            "headers": { "X-Echo-Id": "{{header.x-request-id}}" },
+            "error_body": "<error code=\"{{status}}\">{{message}}</error>",
+            "error_content_type": "application/xml"
+        })).unwrap();
+        assert!(options.validate().is_ok());
+        assert!(WebhookResponseOptions { success_status: Some(500), ..options.clone() }.validate().is_err());
+        
+        let mut headers = HashMap::new();
+        headers.insert("X-Request-Id".to_string(), "req-7".to_string());
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/picky".to_string()).with_headers(headers);
+        
+        let accepted = reply_with(WebhookResponse::new(200).with_body("{}".to_string()), Some(&options), &request);
+        assert_eq!(accepted.status(), StatusCode::ACCEPTED);
+        assert_eq!(accepted.headers().get("x-echo-id").unwrap(), "req-7");
+        
+        let rejected = reply_error(StatusCode::UNAUTHORIZED, "bad signature".to_string(), serde_json::Map::new(), Some(&options), &request);
+        assert_eq!(rejected.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(rejected.headers().get("content-type").unwrap(), "application/xml");
+        let body = actix_web::body::to_bytes(rejected.into_body()).await.unwrap();
+        assert_eq!(body, r#"<error code="401">bad signature</error>"#);
+        
+        let failed_run = WebhookResponse::new(500).with_body(r#"{"status":"error","message":"boom"}"#.to_string());
+        let body = actix_web::body::to_bytes(reply_with(failed_run, Some(&options), &request).into_body()).await.unwrap();
+        assert_eq!(body, r#"<error code="500">boom</error>"#);
+        
+        let plain = reply_with(WebhookResponse::new(200), None, &request);
+        assert_eq!(plain.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_trigger_auth_rejects_bad_credentials_and_counts_failures() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut state_manager = StateManager::new(temp_file.path().to_str().unwrap()).unwrap();
+        state_manager.set_secret_cipher(Some(crate::secrets::SecretCipher::from_hex(&"2a".repeat(32)).unwrap()));
+        state_manager.set_secret("BILLING_TOKEN", "tok-4242", Some("billing")).unwrap();
+        let state_manager = web::Data::new(Arc::new(Mutex::new(state_manager)));
+        
+        let mut trigger_manager = TriggerManager::new();
+        let bearer = WebhookTrigger::new("/webhook/billing".to_string(), "POST".to_string())
+            .with_auth(serde_json::from_value(serde_json::json!({"type": "bearer", "token_secret": "BILLING_TOKEN"})).unwrap());
+        trigger_manager.register_webhook_trigger("billing", bearer).unwrap();
+        let basic = WebhookTrigger::new("/webhook/:tenant/basic".to_string(), "POST".to_string())
+            .with_auth(crate::triggers::WebhookAuth::Basic { username: "ops".to_string(), password_secret: "BILLING_TOKEN".to_string() });
+        trigger_manager.register_webhook_trigger("billing", basic).unwrap();
+        let trigger_manager = web::Data::new(Arc::new(TokioMutex::new(trigger_manager)));
+        
+        let request = |path: &str, authorization: Option<&str>| {
+            let headers = authorization.map(|value| HashMap::from([("Authorization".to_string(), value.to_string())])).unwrap_or_default();
+            WebhookRequest::new("POST".to_string(), path.to_string()).with_headers(headers)
+        };
+        assert!(authenticate_webhook_request(&request("/webhook/billing", Some("Bearer tok-4242")), &trigger_manager, &state_manager).await.is_ok());
+        let (error, challenge) = authenticate_webhook_request(&request("/webhook/billing", Some("Bearer tok-4243")), &trigger_manager, &state_manager).await.unwrap_err();
+        assert!(matches!(error, CoreError::Unauthorized(_)));
+        assert_eq!(challenge, Some("Bearer realm=\"cronflow\""));
+        assert!(authenticate_webhook_request(&request("/webhook/billing", None), &trigger_manager, &state_manager).await.is_err());
+        
+        // ops:tok-4242, then a wrong username with the right password
+        assert!(authenticate_webhook_request(&request("/webhook/acme/basic", Some("Basic b3BzOnRvay00MjQy")), &trigger_manager, &state_manager).await.is_ok());
+        assert!(authenticate_webhook_request(&request("/webhook/acme/basic", Some("Basic ZGV2OnRvay00MjQy")), &trigger_manager, &state_manager).await.is_err());
+        assert!(authenticate_webhook_request(&request("/webhook/unknown", None), &trigger_manager, &state_manager).await.is_ok());
+        
+        let failures = trigger_manager.lock().await.auth_failures.clone();
+        assert_eq!(failures.get("/webhook/billing"), Some(&2));
+        assert_eq!(failures.get("/webhook/:tenant/basic"), Some(&1));
+        
+        // Triggers whose secret is missing reject every request
+        let api_key = crate::triggers::WebhookAuth::ApiKey { header: None, key_secret: "MISSING".to_string() };
+        let keyed = request("/webhook/keyed", None).with_headers(HashMap::from([("X-Api-Key".to_string(), "anything".to_string())]));
+        assert!(api_key.verify(&keyed, &HashMap::new()).is_err());
+        assert!(api_key.verify(&keyed, &HashMap::from([("MISSING".to_string(), "anything".to_string())])).is_ok());
+    }
+
+    #[test]
+    fn test_payload_mappings_build_the_run_payload_from_request_parts() {
+        let mapping: WebhookPayloadMapping = serde_json::from_value(serde_json::json!({
+            "fields": {
+                "order.id": "params.id",
+                "order.sku": "$.body.items[0].sku",
+                "customer": "body.customer.email",
+                "source": "headers.X-Source",
+                "page": "query.page",
+                "summary": "{{method}} order {{params.id}} x{{body.items.0.quantity}}"
+            },
+            "required": ["customer"]
+        })).unwrap();
+        assert!(mapping.validate().is_ok());
+        
+        let mut trigger_manager = TriggerManager::new();
+        let trigger = WebhookTrigger::new("/webhook/orders/:id".to_string(), "POST".to_string()).with_payload_mapping(mapping.clone());
+        trigger_manager.register_webhook_trigger("orders", trigger).unwrap();
+        
+        let mut headers = HashMap::new();
+        headers.insert("x-source".to_string(), "shop".to_string());
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/orders/42".to_string())
+            .with_headers(headers)
+            .with_query_params(HashMap::from([("page".to_string(), "2".to_string())]))
+            .with_body(r#"{"customer":{"email":"ada@example.com"},"items":[{"sku":"A-1","quantity":3}]}"#.to_string());
+        let (_, payload) = trigger_manager.handle_webhook_request(request.clone()).unwrap();
+        assert_eq!(payload, serde_json::json!({
+            "order": {"id": "42", "sku": "A-1"},
+            "customer": "ada@example.com",
+            "source": "shop",
+            "page": "2",
+            "summary": "POST order 42 x3",
+        }));
+        
+        let anonymous = request.with_body(r#"{"items":[]}"#.to_string());
+        assert!(matches!(trigger_manager.handle_webhook_request(anonymous), Err(CoreError::InvalidTrigger(_))));
+        
+        let mut invalid = mapping.clone();
+        invalid.fields.insert("order".to_string(), "body".to_string());
+        assert!(invalid.validate().is_err());
+        assert!(WebhookPayloadMapping { required: vec!["missing".to_string()], ..mapping.clone() }.validate().is_err());
+        let unknown_source = WebhookPayloadMapping { fields: BTreeMap::from([("x".to_string(), "cookies.session".to_string())]), required: vec![] };
+        assert!(unknown_source.validate().is_err());
+    }
+    
+    #[test]
+    fn test_request_fields_pass_request_parts_with_allowed_headers_only() {
+        let definition: crate::models::TriggerDefinition = serde_json::from_value(serde_json::json!({
+            "Webhook": {
+                "path": "/webhook/tickets/:id",
+                "method": "POST",
+                "request_fields": {"allowed_headers": ["X-Tenant"]}
+            }
+        })).unwrap();
+        assert!(definition.validate().is_ok());
+        
+        let mut trigger_manager = TriggerManager::new();
+        let request_fields = WebhookRequestFields { allowed_headers: vec!["X-Tenant".to_string()] };
+        let trigger = WebhookTrigger::new("/webhook/tickets/:id".to_string(), "POST".to_string()).with_request_fields(request_fields.clone());
+        trigger_manager.register_webhook_trigger("tickets", trigger).unwrap();
+        
+        let headers = HashMap::from([
+            ("x-tenant".to_string(), "acme".to_string()),
+            ("Authorization".to_string(), "Bearer secret".to_string()),
+        ]);
+        let request = WebhookRequest::new("POST".to_string(), "/webhook/tickets/7".to_string())
+            .with_headers(headers)
+            .with_query_params(HashMap::from([("priority".to_string(), "high".to_string())]))
+            .with_body(r#"{"subject":"Broken"}"#.to_string());
+        let (_, payload) = trigger_manager.handle_webhook_request(request).unwrap();
+        assert_eq!(payload, serde_json::json!({
+            "method": "POST",
+            "path": "/webhook/tickets/7",
+            "request": {
+                "path_params": {"id": "7"},
+                "query": {"priority": "high"},
+                "headers_allowed": {"x-tenant": "acme"},
+                "body": {"subject": "Broken"},
+            },
+        }));
+        
+        // Mapped payloads keep their fields next to the request fields, which they cannot replace
+        let mapping = WebhookPayloadMapping { fields: BTreeMap::from([("ticket".to_string(), "params.id".to_string())]), required: vec![] };
+        let mapped = WebhookTrigger::new("/webhook/mapped/:id".to_string(), "POST".to_string())
+            .with_payload_mapping(mapping)
+            .with_request_fields(request_fields.clone());
+        let payload = mapped.build_payload(&WebhookRequest::new("POST".to_string(), "/webhook/mapped/9".to_string()), &HashMap::from([("id".to_string(), "9".to_string())]), || unreachable!()).unwrap();
+        assert_eq!((&payload["ticket"], &payload["request"]["body"]), (&serde_json::json!("9"), &serde_json::Value::Null));
+        let conflicting = WebhookPayloadMapping { fields: BTreeMap::from([("request.id".to_string(), "params.id".to_string())]), required: vec![] };
+        assert!(mapped.with_payload_mapping(conflicting).validate().is_err());
+        assert!(WebhookRequestFields { allowed_headers: vec!["bad header".to_string()] }.validate().is_err());
+    }
+}
@@ -0,0 +1,500 @@
+//! Static analysis of workflow step graphs
+//!
+//! Workflows are analyzed when they are registered, so structural problems are
+//! reported up front instead of surfacing in a run. Errors reject the workflow:
+//! dependencies on unknown steps, dependency cycles, control flow markers that
+//! do not nest, and steps claimed by more than one parallel or race group.
+//! Warnings are returned with the registration result: steps that can never
+//! run because of the control flow structure, such as a step depending on a
+//! step in another branch of the same if block, or a branch following an else.
+//!
+//! Expressions reading `ctx.steps.<id>.output.<field>` are checked against the
+//! step graph too: reading a field the step's output schema does not declare
+//! is an error, and reading the output of a step that is not upstream of the
+//! reader is a warning, as that step may not have run yet. Steps without
+//! dependencies run in definition order, so every step before them is upstream.
+
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use crate::models::{ConditionType, StepDefinition, WorkflowDefinition};
+use crate::output_schemas;
+
+/// How serious a diagnostic is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticSeverity {
+    /// The workflow cannot be registered
+    Error,
+    /// The workflow is registered, but part of it never runs or may not see what it reads
+    Warning,
+}
+
+/// A problem found in a workflow's step graph
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkflowDiagnostic {
+    pub severity: DiagnosticSeverity,
+    /// Kind of problem, e.g. "dependency_cycle"
+    pub code: String,
+    pub message: String,
+    /// Steps the problem is about
+    pub step_ids: Vec<String>,
+}
+
+impl WorkflowDiagnostic {
+    pub const MISSING_DEPENDENCY: &'static str = "missing_dependency";
+    pub const DEPENDENCY_CYCLE: &'static str = "dependency_cycle";
+    pub const INVALID_CONTROL_FLOW: &'static str = "invalid_control_flow";
+    pub const DUPLICATE_GROUP_MEMBERSHIP: &'static str = "duplicate_group_membership";
+    pub const UNREACHABLE_STEP: &'static str = "unreachable_step";
+    pub const UNDECLARED_OUTPUT_FIELD: &'static str = "undeclared_output_field";
+    pub const OUTPUT_NOT_UPSTREAM: &'static str = "output_not_upstream";
+
+    fn error(code: &str, message: String, step_ids: Vec<String>) -> Self {
+        Self { severity: DiagnosticSeverity::Error, code: code.to_string(), message, step_ids }
+    }
+
+    fn warning(code: &str, message: String, step_ids: Vec<String>) -> Self {
+        Self { severity: DiagnosticSeverity::Warning, code: code.to_string(), message, step_ids }
+    }
+
+    /// Whether the diagnostic rejects the workflow
+    pub fn is_error(&self) -> bool {
+        self.severity == DiagnosticSeverity::Error
+    }
+}
+
+/// Analyze the step graph of a workflow, errors first
+pub fn analyze(workflow: &WorkflowDefinition) -> Vec<WorkflowDiagnostic> {
+    let mut diagnostics = Vec::new();
+    check_dependencies(workflow, &mut diagnostics);
+    check_cycles(workflow, &mut diagnostics);
+    check_group_memberships(workflow, &mut diagnostics);
+    let layout = check_control_flow(workflow, &mut diagnostics);
+    check_reachability(workflow, &layout, &mut diagnostics);
+    check_output_references(workflow, &mut diagnostics);
+    diagnostics.sort_by_key(|diagnostic| !diagnostic.is_error());
+    diagnostics
+}
+
+fn check_dependencies(workflow: &WorkflowDefinition, diagnostics: &mut Vec<WorkflowDiagnostic>) {
+    let step_ids: HashSet<&str> = workflow.steps.iter().map(|step| step.id.as_str()).collect();
+    for step in &workflow.steps {
+        for dependency in step.depends_on.iter().filter(|dependency| !step_ids.contains(dependency.as_str())) {
+            diagnostics.push(WorkflowDiagnostic::error(
+                WorkflowDiagnostic::MISSING_DEPENDENCY,
+                format!("Step {} depends on non-existent step {}", step.id, dependency),
+                vec![step.id.clone()],
+            ));
+        }
+    }
+}
+
+/// Report every dependency cycle once, in the order its steps depend on each other
+fn check_cycles(workflow: &WorkflowDefinition, diagnostics: &mut Vec<WorkflowDiagnostic>) {
+    #[derive(Clone, Copy, PartialEq)]
+    enum Visit {
+        InProgress,
+        Done,
+    }
+
+    fn visit<'a>(
+        step_id: &'a str,
+        graph: &HashMap<&'a str, Vec<&'a str>>,
+        visits: &mut HashMap<&'a str, Visit>,
+        path: &mut Vec<&'a str>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        visits.insert(step_id, Visit::InProgress);
+        path.push(step_id);
+        for &dependency in graph.get(step_id).into_iter().flatten() {
+            match visits.get(dependency) {
+                None => visit(dependency, graph, visits, path, cycles),
+                Some(Visit::InProgress) => {
+                    let start = path.iter().position(|id| *id == dependency).unwrap_or_default();
+                    cycles.push(path[start..].iter().map(|id| id.to_string()).collect());
+                }
+                Some(Visit::Done) => {}
+            }
+        }
+        path.pop();
+        visits.insert(step_id, Visit::Done);
+    }
+
+    let graph: HashMap<&str, Vec<&str>> = workflow.steps.iter()
+        .map(|step| (step.id.as_str(), step.depends_on.iter().map(String::as_str).collect()))
+        .collect();
+    let mut visits = HashMap::new();
+    let mut cycles = Vec::new();
+    for step in &workflow.steps {
+        if !visits.contains_key(step.id.as_str()) {
+            visit(&step.id, &graph, &mut visits, &mut Vec::new(), &mut cycles);
+        }
+    }
+
+    for mut cycle in cycles {
+        // Steps depend on the next one in the path; list them in execution order instead
+        cycle.reverse();
+        let mut chain = cycle.clone();
+        chain.push(cycle[0].clone());
+        diagnostics.push(WorkflowDiagnostic::error(
+            WorkflowDiagnostic::DEPENDENCY_CYCLE,
+            format!("Steps form a dependency cycle: {}", chain.join(" -> ")),
+            cycle,
+        ));
+    }
+}
+
+/// Report steps claimed by both a parallel and a race group, and group IDs split into several groups
+fn check_group_memberships(workflow: &WorkflowDefinition, diagnostics: &mut Vec<WorkflowDiagnostic>) {
+    for step in workflow.steps.iter().filter(|step| step.is_parallel() && step.is_race()) {
+        diagnostics.push(WorkflowDiagnostic::error(
+            WorkflowDiagnostic::DUPLICATE_GROUP_MEMBERSHIP,
+            format!("Step {} is marked both parallel and race, so two groups would claim it", step.id),
+            vec![step.id.clone()],
+        ));
+    }
+
+    // Groups are made of consecutive steps, so an ID used again after other steps starts a second group
+    let mut closed_groups: HashSet<&str> = HashSet::new();
+    let mut current: Option<&str> = None;
+    for step in &workflow.steps {
+        let group_id = step.parallel_group_id.as_deref().filter(|_| step.is_parallel() || step.is_race());
+        if group_id != current {
+            if let Some(previous) = current {
+                closed_groups.insert(previous);
+            }
+            if let Some(group_id) = group_id.filter(|group_id| closed_groups.contains(group_id)) {
+                diagnostics.push(WorkflowDiagnostic::error(
+                    WorkflowDiagnostic::DUPLICATE_GROUP_MEMBERSHIP,
+                    format!("Step {} joins group {} after the group ended, so the group's steps would form two groups", step.id, group_id),
+                    vec![step.id.clone()],
+                ));
+            }
+            current = group_id;
+        }
+    }
+}
+
+/// Branch of an if block a step runs in: the block and the index of the branch
+type Branch = (String, usize);
+
+/// Where the control flow structure places each step
+#[derive(Default)]
+struct ControlFlowLayout {
+    /// Branches each step runs in, outermost first
+    branches: HashMap<String, Vec<Branch>>,
+    /// Steps in a branch following an else, which is never taken
+    after_else: Vec<(String, String)>,
+}
+
+/// Check that control flow markers nest and place every step in its branches
+fn check_control_flow(workflow: &WorkflowDefinition, diagnostics: &mut Vec<WorkflowDiagnostic>) -> ControlFlowLayout {
+    struct OpenBlock {
+        block_id: String,
+        branch: usize,
+        /// Whether an else was seen, so later branches are never taken
+        closed_by_else: bool,
+    }
+
+    let mut open: Vec<OpenBlock> = Vec::new();
+    let mut layout = ControlFlowLayout::default();
+    for step in &workflow.steps {
+        let condition_type = step.condition_type.as_ref().filter(|_| step.is_control_flow_step());
+        let Some(marker) = condition_type else {
+            if let Some(block) = open.iter().find(|block| block.closed_by_else && block.branch > 0) {
+                layout.after_else.push((step.id.clone(), block.block_id.clone()));
+            }
+            layout.branches.insert(step.id.clone(), open.iter().map(|block| (block.block_id.clone(), block.branch)).collect());
+            continue;
+        };
+        if *marker == ConditionType::If {
+            layout.branches.insert(step.id.clone(), open.iter().map(|block| (block.block_id.clone(), block.branch)).collect());
+            let block_id = step.control_flow_block.clone().unwrap_or_else(|| step.id.clone());
+            open.push(OpenBlock { block_id, branch: 0, closed_by_else: false });
+            continue;
+        }
+
+        let name = marker.as_str();
+        let mismatch = match (open.last(), &step.control_flow_block) {
+            (None, _) => Some(format!("Step {} is an {} step without a matching if", step.id, name)),
+            (Some(block), Some(block_id)) if *block_id != block.block_id => Some(format!(
+                "Step {} is an {} step of block {}, but block {} is still open", step.id, name, block_id, block.block_id
+            )),
+            _ => None,
+        };
+        if let Some(message) = mismatch {
+            diagnostics.push(WorkflowDiagnostic::error(WorkflowDiagnostic::INVALID_CONTROL_FLOW, message, vec![step.id.clone()]));
+            continue;
+        }
+
+        // Markers are evaluated in the scope enclosing their block
+        let block = match marker {
+            ConditionType::EndIf => open.pop(),
+            _ => None,
+        };
+        layout.branches.insert(step.id.clone(), open.iter()
+            .take(open.len() - usize::from(block.is_none()))
+            .map(|block| (block.block_id.clone(), block.branch))
+            .collect());
+        if let Some(block) = open.last_mut().filter(|_| block.is_none()) {
+            if block.closed_by_else {
+                layout.after_else.push((step.id.clone(), block.block_id.clone()));
+            }
+            block.branch += 1;
+            block.closed_by_else |= *marker == ConditionType::Else;
+        }
+    }
+
+    for block in &open {
+        diagnostics.push(WorkflowDiagnostic::error(
+            WorkflowDiagnostic::INVALID_CONTROL_FLOW,
+            format!("If block {} is never closed with an endif step", block.block_id),
+            vec![],
+        ));
+    }
+    layout
+}
+
+/// Report steps that never run: steps after an else, steps depending on a step
+/// in another branch of the same if block, and steps depending on those
+fn check_reachability(workflow: &WorkflowDefinition, layout: &ControlFlowLayout, diagnostics: &mut Vec<WorkflowDiagnostic>) {
+    let mut unreachable: HashSet<&str> = HashSet::new();
+    for (step_id, block_id) in &layout.after_else {
+        unreachable.insert(step_id.as_str());
+        diagnostics.push(WorkflowDiagnostic::warning(
+            WorkflowDiagnostic::UNREACHABLE_STEP,
+            format!("Step {} follows the else of block {} and never runs", step_id, block_id),
+            vec![step_id.clone()],
+        ));
+    }
+
+    let no_branches = Vec::new();
+    let branches_of = |step_id: &str| layout.branches.get(step_id).unwrap_or(&no_branches);
+    for step in &workflow.steps {
+        let branches = branches_of(&step.id);
+        let exclusive = step.depends_on.iter().find_map(|dependency| {
+            branches_of(dependency).iter()
+                .find(|(block_id, branch)| branches.iter().any(|(id, other)| id == block_id && other != branch))
+                .map(|(block_id, _)| (dependency, block_id))
+        });
+        if let Some((dependency, block_id)) = exclusive.filter(|_| !unreachable.contains(step.id.as_str())) {
+            unreachable.insert(&step.id);
+            diagnostics.push(WorkflowDiagnostic::warning(
+                WorkflowDiagnostic::UNREACHABLE_STEP,
+                format!("Step {} depends on step {} in another branch of block {} and never runs", step.id, dependency, block_id),
+                vec![step.id.clone(), dependency.clone()],
+            ));
+        }
+    }
+
+    // Anything waiting on a step that never runs never runs either
+    loop {
+        let blocked: Vec<(&str, &str)> = workflow.steps.iter()
+            .filter(|step| !unreachable.contains(step.id.as_str()))
+            .filter_map(|step| step.depends_on.iter()
+                .find(|dependency| unreachable.contains(dependency.as_str()))
+                .map(|dependency| (step.id.as_str(), dependency.as_str())))
+            .collect();
+        if blocked.is_empty() {
+            break;
+        }
+        for (step_id, dependency) in blocked {
+            unreachable.insert(step_id);
+            diagnostics.push(WorkflowDiagnostic::warning(
+                WorkflowDiagnostic::UNREACHABLE_STEP,
+                format!("Step {} depends on unreachable step {} and never runs", step_id, dependency),
+                vec![step_id.to_string(), dependency.to_string()],
+            ));
+        }
+    }
+}
+
+/// Expressions of a step that may read other steps' outputs
+fn step_expressions(step: &StepDefinition) -> Vec<&str> {
+    let mut expressions: Vec<&str> = [&step.condition_expression, &step.skip_if, &step.for_each_items, &step.delay_until]
+        .into_iter()
+        .filter_map(|expression| expression.as_deref())
+        .collect();
+    expressions.extend(step.subworkflow_input.iter().flat_map(|input| input.values().map(String::as_str)));
+    expressions.extend(step.input.iter().flat_map(crate::step_input::templates));
+    expressions
+}
+
+/// Report reads of step output fields that are undeclared, or of steps that are not upstream
+fn check_output_references(workflow: &WorkflowDefinition, diagnostics: &mut Vec<WorkflowDiagnostic>) {
+    let steps: HashMap<&str, &StepDefinition> = workflow.steps.iter().map(|step| (step.id.as_str(), step)).collect();
+    let upstream_of = |index: usize| {
+        let mut upstream: HashSet<&str> = HashSet::new();
+        let depends_on = &workflow.steps[index].depends_on;
+        let mut pending: Vec<&str> = match depends_on.is_empty() {
+            true => workflow.steps[..index].iter().map(|step| step.id.as_str()).collect(),
+            false => depends_on.iter().map(String::as_str).collect(),
+        };
+        while let Some(step_id) = pending.pop() {
+            if upstream.insert(step_id) {
+                pending.extend(steps.get(step_id).into_iter().flat_map(|step| step.depends_on.iter().map(String::as_str)));
+            }
+        }
+        upstream
+    };
+
+    for (index, step) in workflow.steps.iter().enumerate() {
+        let mut references = step_expressions(step).into_iter()
+            .flat_map(output_schemas::output_references)
+            .collect::<Vec<_>>();
+        references.sort();
+        references.dedup();
+        let mut upstream = None;
+        let mut reported_sources = HashSet::new();
+        for (source_id, field) in references {
+            let Some(source) = steps.get(source_id.as_str()) else {
+                if reported_sources.insert(source_id.clone()) {
+                    diagnostics.push(WorkflowDiagnostic::error(
+                        WorkflowDiagnostic::MISSING_DEPENDENCY,
+                        format!("Step {} reads the output of non-existent step {}", step.id, source_id),
+                        vec![step.id.clone()],
+                    ));
+                }
+                continue;
+            };
+            if let (Some(schema), Some(field)) = (&source.output_schema, &field) {
+                if !output_schemas::declares_field(schema, field) {
+                    diagnostics.push(WorkflowDiagnostic::error(
+                        WorkflowDiagnostic::UNDECLARED_OUTPUT_FIELD,
+                        format!("Step {} reads output field {} that step {} does not declare", step.id, field, source_id),
+                        vec![step.id.clone(), source_id.clone()],
+                    ));
+                }
+            }
+            let upstream = upstream.get_or_insert_with(|| upstream_of(index));
+            if !upstream.contains(source_id.as_str()) && reported_sources.insert(source_id.clone()) {
+                diagnostics.push(WorkflowDiagnostic::warning(
+                    WorkflowDiagnostic::OUTPUT_NOT_UPSTREAM,
+                    format!("Step {} reads the output of step {} without depending on it", step.id, source_id),
+                    vec![step.id.clone(), source_id.clone()],
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{StepDefinition, TriggerDefinition};
+
+    fn workflow(steps: serde_json::Value) -> WorkflowDefinition {
+        let steps: Vec<StepDefinition> = serde_json::from_value(steps).unwrap();
+        WorkflowDefinition {
+            id: "graph".to_string(),
+            name: "Graph".to_string(),
+            description: None,
+            steps,
+            triggers: vec![TriggerDefinition::Manual],
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }
+    }
+
+    fn step(id: &str, depends_on: &[&str]) -> serde_json::Value {
+        serde_json::json!({"id": id, "name": id, "action": id, "depends_on": depends_on, "is_control_flow": false})
+    }
+
+    fn marker(id: &str, condition_type: &str) -> serde_json::Value {
+        serde_json::json!({
+            "id": id, "name": id, "action": id, "depends_on": [], "is_control_flow": true,
+            "condition_type": condition_type, "condition_expression": "approved", "control_flow_block": "approval",
+        })
+    }
+
+    #[test]
+    fn test_cycles_and_unreachable_steps_are_diagnosed() {
+        let branching = workflow(serde_json::json!([
+            step("fetch", &[]),
+            marker("if_approved", "If"),
+            step("notify", &["fetch"]),
+            marker("otherwise", "Else"),
+            step("report", &["notify"]),
+            marker("end", "EndIf"),
+            step("archive", &["report"]),
+        ]));
+        let diagnostics = analyze(&branching);
+        assert!(diagnostics.iter().all(|diagnostic| diagnostic.code == WorkflowDiagnostic::UNREACHABLE_STEP && !diagnostic.is_error()));
+        let unreachable: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.step_ids[0].as_str()).collect();
+        assert_eq!(unreachable, vec!["report", "archive"]);
+        assert!(branching.validate().is_ok());
+
+        let tangled = workflow(serde_json::json!([
+            step("a", &["c"]),
+            step("b", &["a"]),
+            step("c", &["b", "ghost"]),
+            marker("stray_end", "EndIf"),
+        ]));
+        let diagnostics = analyze(&tangled);
+        let codes: Vec<&str> = diagnostics.iter().map(|diagnostic| diagnostic.code.as_str()).collect();
+        assert_eq!(codes, vec![
+            WorkflowDiagnostic::MISSING_DEPENDENCY,
+            WorkflowDiagnostic::DEPENDENCY_CYCLE,
+            WorkflowDiagnostic::INVALID_CONTROL_FLOW,
+        ]);
+        assert_eq!(diagnostics[1].step_ids.len(), 3);
+        let error = tangled.validate().unwrap_err();
+        assert!(error.contains("ghost") && error.contains("dependency cycle"));
+    }
+
+    #[test]
+    fn test_output_reads_are_checked_against_declared_schemas() {
+        let mut fetch = step("fetch", &[]);
+        fetch["output_schema"] = serde_json::json!({
+            "type": "object",
+            "properties": {"items": {"type": "array"}},
+            "required": ["items"],
+        });
+        let mut notify = step("notify", &["fetch"]);
+        notify["skip_if"] = serde_json::json!("length(ctx.steps.fetch.output.items) == 0 || ctx.steps.fetch.output.count > 9");
+        let mut audit = step("audit", &["notify"]);
+        audit["skip_if"] = serde_json::json!("ctx.steps.report.output.total > 0");
+        let mut report = step("report", &["fetch"]);
+        report["skip_if"] = serde_json::json!("ctx.steps.fetch.output.items");
+        let wired = workflow(serde_json::json!([fetch, notify, audit, report]));
+
+        let diagnostics = analyze(&wired);
+        let found: Vec<(&str, &str)> = diagnostics.iter()
+            .map(|diagnostic| (diagnostic.code.as_str(), diagnostic.step_ids[0].as_str()))
+            .collect();
+        assert_eq!(found, vec![
+            (WorkflowDiagnostic::UNDECLARED_OUTPUT_FIELD, "notify"),
+            (WorkflowDiagnostic::OUTPUT_NOT_UPSTREAM, "audit"),
+        ]);
+        assert!(wired.validate().unwrap_err().contains("count"));
+
+        // Completed results are failed when their output breaks the schema
+        let result = |output: serde_json::Value| crate::models::StepResult {
+            step_id: "fetch".to_string(),
+            status: crate::models::StepStatus::Completed,
+            output: Some(output),
+            error: None,
+            started_at: chrono::Utc::now(),
+            completed_at: Some(chrono::Utc::now()),
+            duration_ms: Some(1),
+            cache_hit: None,
+            resources: None,
+        };
+        let fetch = &wired.steps[0];
+        let checked = output_schemas::enforce(fetch, result(serde_json::json!({"items": []})));
+        assert_eq!(checked.status, crate::models::StepStatus::Completed);
+        let checked = output_schemas::enforce(fetch, result(serde_json::json!({"items": "none"})));
+        assert_eq!(checked.status, crate::models::StepStatus::Failed);
+        assert!(checked.error.unwrap().contains("/items"));
+    }
+}
@@ -0,0 +1,179 @@
+//! Portable workflow bundles
+//!
+//! A bundle carries everything needed to register a workflow in another
+//! environment: the definition, its triggers and the version it had where it
+//! was exported. Bundles are tagged with a schema version and read through the
+//! same kind of migration shims as stored records, so bundles exported by
+//! older engines can still be imported. A bare workflow definition, as written
+//! before bundles existed, is read as schema version 0.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use crate::error::{CoreError, CoreResult};
+use crate::models::{TriggerDefinition, WorkflowDefinition, WorkflowVersion};
+use crate::record_compat::{workflow_from_record, workflow_to_record};
+
+/// Schema version of bundles written by this engine
+pub const BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+/// Version a workflow had in the environment it was exported from
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BundleSourceVersion {
+    pub version: u32,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Self-contained export of a workflow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowBundle {
+    pub schema_version: u32,
+    /// Engine version that wrote the bundle
+    pub engine_version: String,
+    pub exported_at: DateTime<Utc>,
+    /// Workflow definition record, without its triggers
+    pub workflow: serde_json::Value,
+    pub triggers: Vec<TriggerDefinition>,
+    pub source_version: Option<BundleSourceVersion>,
+}
+
+impl WorkflowBundle {
+    /// Bundle a workflow definition with the version it is registered as
+    pub fn export(workflow: &WorkflowDefinition, version: Option<&WorkflowVersion>) -> CoreResult<Self> {
+        let mut record = workflow_to_record(workflow)?;
+        if let Some(fields) = record.as_object_mut() {
+            fields.remove("triggers");
+        }
+        Ok(WorkflowBundle {
+            schema_version: BUNDLE_SCHEMA_VERSION,
+            engine_version: crate::VERSION.to_string(),
+            exported_at: Utc::now(),
+            workflow: record,
+            triggers: workflow.triggers.clone(),
+            source_version: version.map(|version| BundleSourceVersion {
+                version: version.version,
+                created_at: version.created_at,
+            }),
+        })
+    }
+
+    /// Read a bundle, upgrading it from older schema versions
+    ///
+    /// Returns the bundle with the schema version it was written with.
+    pub fn parse(bundle_json: &str) -> CoreResult<(Self, u32)> {
+        let mut document: serde_json::Value = serde_json::from_str(bundle_json)?;
+        let version = match document.get("schema_version") {
+            Some(version) => version.as_u64()
+                .ok_or_else(|| CoreError::Validation("Bundle schema_version must be a number".to_string()))? as u32,
+            None => 0,
+        };
+        if version > BUNDLE_SCHEMA_VERSION {
+            return Err(CoreError::Validation(format!(
+                "Workflow bundle schema version {} was written by a newer engine, this engine reads up to version {}",
+                version, BUNDLE_SCHEMA_VERSION
+            )));
+        }
+        if version < 1 {
+            document = upgrade_bundle_v0(document);
+        }
+        Ok((serde_json::from_value(document)?, version))
+    }
+
+    /// The workflow definition in the bundle, with its triggers, validated
+    pub fn definition(&self) -> CoreResult<WorkflowDefinition> {
+        let mut record = self.workflow.clone();
+        let fields = record.as_object_mut()
+            .ok_or_else(|| CoreError::Validation("Bundle workflow must be an object".to_string()))?;
+        fields.insert("triggers".to_string(), serde_json::to_value(&self.triggers)?);
+
+        let workflow = workflow_from_record(record)?;
+        workflow.validate().map_err(CoreError::InvalidWorkflow)?;
+        Ok(workflow)
+    }
+}
+
+/// Wrap a bare workflow definition in a bundle envelope
+fn upgrade_bundle_v0(mut definition: serde_json::Value) -> serde_json::Value {
+    let triggers = definition.as_object_mut()
+        .and_then(|fields| fields.remove("triggers"))
+        .unwrap_or_else(|| serde_json::json!([]));
+    serde_json::json!({
+        "schema_version": 1,
+        "engine_version": "unknown",
+        "exported_at": Utc::now(),
+        "workflow": definition,
+        "triggers": triggers,
+        "source_version": null,
+    })
+}
+
+/// Outcome of importing a bundle
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WorkflowImport {
+    pub workflow_id: String,
+    /// Schema version the bundle was written with, before any migration
+    pub bundle_schema_version: u32,
+    pub source_version: Option<u32>,
+    /// Version the workflow is registered as in this environment
+    pub version: Option<u32>,
+    pub trigger_count: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::StepDefinition;
+
+    #[test]
+    fn test_bundles_round_trip_and_migrate_bare_definitions() {
+        let step: StepDefinition = serde_json::from_value(serde_json::json!({
+            "id": "sync", "name": "sync", "action": "sync", "depends_on": [], "is_control_flow": false
+        })).unwrap();
+        let workflow = WorkflowDefinition {
+            id: "nightly".to_string(),
+            name: "Nightly".to_string(),
+            description: None,
+            steps: vec![step],
+            triggers: vec![TriggerDefinition::Manual],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        };
+        let version = WorkflowVersion { workflow_id: "nightly".to_string(), version: 3, definition: workflow.clone(), created_at: Utc::now() };
+
+        let bundle = WorkflowBundle::export(&workflow, Some(&version)).unwrap();
+        assert!(bundle.workflow.get("triggers").is_none());
+        let (imported, schema_version) = WorkflowBundle::parse(&serde_json::to_string(&bundle).unwrap()).unwrap();
+        assert_eq!(schema_version, BUNDLE_SCHEMA_VERSION);
+        assert_eq!(imported.source_version.as_ref().map(|v| v.version), Some(3));
+        let definition = imported.definition().unwrap();
+        assert_eq!(definition.id, "nightly");
+        assert_eq!(definition.triggers.len(), 1);
+
+        // A bare version 1 definition record, predating bundles and record versions
+        let bare = serde_json::json!({
+            "id": "legacy", "name": "Legacy", "description": null,
+            "steps": [{"id": "a", "name": "a"}],
+            "triggers": [{"Webhook": {"path": "/webhook/legacy", "method": "POST"}}],
+            "created_at": "2024-01-01T00:00:00Z",
+        });
+        let (legacy, schema_version) = WorkflowBundle::parse(&bare.to_string()).unwrap();
+        assert_eq!(schema_version, 0);
+        assert_eq!(legacy.schema_version, BUNDLE_SCHEMA_VERSION);
+        assert_eq!(legacy.triggers.len(), 1);
+        assert_eq!(legacy.definition().unwrap().steps[0].action, "a");
+
+        let future = serde_json::json!({"schema_version": BUNDLE_SCHEMA_VERSION + 1});
+        assert!(WorkflowBundle::parse(&future.to_string()).is_err());
+        let invalid = WorkflowBundle { workflow: serde_json::json!({"id": "", "name": "x", "steps": [], "created_at": "2024-01-01T00:00:00Z"}), ..bundle };
+        assert!(invalid.definition().is_err());
+    }
+}
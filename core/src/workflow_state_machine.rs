@@ -6,13 +6,17 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use crate::error::{CoreError, CoreResult};
 use crate::state::StateManager;
-use crate::models::{WorkflowDefinition, WorkflowRun, StepDefinition, StepResult, StepStatus, RunStatus, ControlFlowBlock, ConditionType, ConditionResult, ParallelStepGroup, ParallelGroupStatus};
+use crate::models::{WorkflowDefinition, WorkflowRun, StepDefinition, StepResult, StepStatus, StepCache, StepCacheHit, RunStatus, ControlFlowBlock, ConditionType, ConditionResult, ParallelStepGroup, ParallelGroupStatus, RunEvent};
 use crate::condition_evaluator::ConditionEvaluator;
+use crate::explainer;
 use crate::context::Context;
+use crate::dispatcher::{Dispatcher, JobExecutionResult};
+use tokio::sync::oneshot;
+use crate::job::Job;
 
 /// Parallel execution configuration
 #[derive(Debug, Clone)]
@@ -193,14 +197,24 @@ impl StepExecutionState {
     }
     
     /// Check if step can be retried
+    ///
+    /// Attempts must be left, and the last failure must be of a class the
+    /// step's retry rules allow.
     pub fn can_retry(&self) -> bool {
         if let Some(retry_config) = &self.step.retry {
             self.retry_count < retry_config.max_attempts
+                && self.last_error.as_deref().is_none_or(|error| retry_config.rules.allows(error))
         } else {
             false
         }
     }
     
+    /// Delay before the next retry, following the step's backoff strategy
+    pub fn retry_delay_ms(&self) -> u64 {
+        self.step.retry.as_ref()
+            .map_or(0, |retry_config| retry_config.strategy.delay_ms(retry_config.backoff_ms, self.retry_count))
+    }
+    
     /// Reset step for retry
     pub fn reset_for_retry(&mut self) {
         self.status = StepStatus::Pending;
@@ -258,7 +272,7 @@ impl WorkflowExecutionStats {
         for state in step_states.values() {
             match state.status {
                 StepStatus::Completed => self.completed_steps += 1,
-                StepStatus::Failed => self.failed_steps += 1,
+                StepStatus::Failed | StepStatus::TimedOut => self.failed_steps += 1,
                 StepStatus::Pending => self.pending_steps += 1,
                 StepStatus::Running => self.running_steps += 1,
                 StepStatus::Skipped => self.skipped_steps += 1,
@@ -302,6 +316,8 @@ pub struct WorkflowStateMachine {
     step_states: HashMap<String, StepExecutionState>,
     /// Completed step results
     completed_steps: Vec<StepResult>,
+    /// Number of leading completed step results already in the database
+    saved_steps: usize,
     /// Current step index
     current_step_index: usize,
     /// Total number of steps
@@ -342,6 +358,7 @@ impl WorkflowStateMachine {
             execution_state: WorkflowExecutionState::Pending,
             step_states: HashMap::new(),
             completed_steps: Vec::new(),
+            saved_steps: 0,
             current_step_index: 0,
             total_steps: 0,
             stats: WorkflowExecutionStats::new(0),
@@ -365,7 +382,7 @@ impl WorkflowStateMachine {
             let state_manager = self.state_manager.lock()
                 .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
             
-            state_manager.get_workflow(&self.workflow_id)?
+            state_manager.get_run_workflow(&self.workflow_id, &self.run_id)?
                 .ok_or_else(|| CoreError::WorkflowNotFound(self.workflow_id.clone()))?
         };
         
@@ -377,6 +394,11 @@ impl WorkflowStateMachine {
                 .ok_or_else(|| CoreError::RunNotFound(format!("Run not found: {}", self.run_id)))?
         };
         
+        self.initialize_from(workflow, run)
+    }
+    
+    /// Initialize the state machine with a workflow definition and run that are already loaded
+    pub fn initialize_from(&mut self, workflow: WorkflowDefinition, run: WorkflowRun) -> CoreResult<()> {
         // Store workflow and run
         self.workflow_definition = Some(workflow.clone());
         self.workflow_run = Some(run.clone());
@@ -402,6 +424,51 @@ impl WorkflowStateMachine {
         Ok(())
     }
     
+    /// Restore the latest persisted result of each step, so a resumed run continues where it stopped
+    ///
+    /// Running and pending results are left out, so those steps run again.
+    /// Restored results are not saved again by `save_state`.
+    pub fn restore_step_results(&mut self, results: &[StepResult]) -> CoreResult<usize> {
+        let mut latest: HashMap<&str, &StepResult> = HashMap::new();
+        for result in results {
+            latest.insert(result.step_id.as_str(), result);
+        }
+        let step_ids: Vec<String> = self.workflow_definition.as_ref()
+            .ok_or_else(|| CoreError::Internal("Workflow definition not loaded".to_string()))?
+            .steps.iter()
+            .map(|step| step.id.clone())
+            .collect();
+        
+        for step_id in &step_ids {
+            let Some(result) = latest.get(step_id.as_str()).copied() else { continue };
+            let Some(step_state) = self.step_states.get_mut(step_id) else { continue };
+            match result.status {
+                StepStatus::Completed => step_state.mark_completed(result.clone()),
+                StepStatus::Failed | StepStatus::TimedOut => {
+                    step_state.mark_failed(result.error.clone().unwrap_or_else(|| "Unknown error".to_string()));
+                }
+                StepStatus::Skipped => {
+                    step_state.status = StepStatus::Skipped;
+                    step_state.result = Some(result.clone());
+                    self.skipped_steps.insert(step_id.clone());
+                }
+                StepStatus::Pending | StepStatus::Running => continue,
+            }
+            self.completed_steps.push(result.clone());
+            if result.status == StepStatus::Completed {
+                self.update_control_flow_state(step_id)?;
+                self.update_dependencies(step_id);
+            } else if result.status == StepStatus::Skipped && step_state.step.satisfies_dependents_when_skipped() {
+                self.update_dependencies(step_id);
+            }
+        }
+        self.saved_steps = self.completed_steps.len();
+        self.update_stats();
+        
+        log::debug!("Restored {} step results for run: {}", self.saved_steps, self.run_id);
+        Ok(self.saved_steps)
+    }
+    
     /// Initialize step states from workflow definition
     fn initialize_step_states(&mut self, workflow: &WorkflowDefinition) -> CoreResult<()> {
         self.step_states.clear();
@@ -623,7 +690,95 @@ impl WorkflowStateMachine {
             .ok_or_else(|| CoreError::Internal("Condition context not available".to_string()))?;
         
         let evaluator = ConditionEvaluator::new(context.clone(), self.completed_steps.clone());
-        evaluator.evaluate_condition(condition_expr)
+        let result = evaluator.evaluate_condition(condition_expr);
+        
+        let detail = match &result {
+            Ok(condition_result) => serde_json::json!({
+                "expression": condition_expr,
+                "met": condition_result.met,
+                "error": condition_result.error,
+                "metadata": condition_result.metadata,
+            }),
+            Err(e) => serde_json::json!({ "expression": condition_expr, "met": false, "error": e.to_string() }),
+        };
+        self.record_trace_event(step_id, explainer::EVENT_CONDITION_EVALUATED, detail);
+        
+        result
+    }
+    
+    /// Skip a step whose `skip_if` expression holds, returning whether it was skipped
+    ///
+    /// The skipped result records the expression. Dependents of the step run
+    /// as if it had completed, or are skipped with it when it skips dependents.
+    /// An expression that cannot be evaluated does not skip the step.
+    pub fn apply_skip_if(&mut self, step_id: &str) -> CoreResult<bool> {
+        let step = self.step_states.get(step_id)
+            .map(|state| state.step.clone())
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?;
+        let Some(expression) = &step.skip_if else {
+            return Ok(false);
+        };
+        
+        let context = self.condition_context.as_ref()
+            .ok_or_else(|| CoreError::Internal("Condition context not available".to_string()))?;
+        let evaluator = ConditionEvaluator::new(context.clone(), self.completed_steps.clone());
+        let result = evaluator.evaluate_condition(expression)?;
+        self.record_trace_event(step_id, explainer::EVENT_CONDITION_EVALUATED, serde_json::json!({
+            "expression": expression,
+            "met": result.met,
+            "error": result.error,
+            "metadata": result.metadata,
+        }));
+        if let Some(error) = &result.error {
+            log::warn!("skip_if expression of step {} could not be evaluated, running the step: {}", step_id, error);
+        }
+        if !result.met {
+            return Ok(false);
+        }
+        
+        let reason = format!("its skip_if expression '{}' was true", expression);
+        self.skip_step_with_output(step_id, &reason, serde_json::json!({
+            "skipped": true,
+            "reason": reason,
+            "expression": expression,
+        }))?;
+        if step.skip_dependents {
+            self.skip_dependents_of(step_id)?;
+        } else {
+            self.update_dependencies(step_id);
+        }
+        self.update_stats();
+        
+        log::info!("Skipped step {} as its skip_if expression was true", step_id);
+        Ok(true)
+    }
+    
+    /// Skip the pending steps that depend on a skipped step, directly or transitively
+    fn skip_dependents_of(&mut self, skipped_step_id: &str) -> CoreResult<()> {
+        let mut dependents: Vec<String> = self.step_states.iter()
+            .filter(|(_, state)| state.status == StepStatus::Pending && state.step.depends_on.iter().any(|dep| dep == skipped_step_id))
+            .map(|(step_id, _)| step_id.clone())
+            .collect();
+        dependents.sort();
+        
+        let reason = format!("it depends on skipped step '{}'", skipped_step_id);
+        for step_id in dependents {
+            if self.step_states.get(&step_id).map(|state| &state.status) == Some(&StepStatus::Pending) {
+                self.mark_step_skipped(&step_id, &reason)?;
+                self.skip_dependents_of(&step_id)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// Record a trace event for run explanations, logging rather than failing on errors
+    fn record_trace_event(&self, step_id: &str, event: &str, detail: serde_json::Value) {
+        let recorded = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))
+            .and_then(|state_manager| state_manager.record_step_trace_event(&self.run_id, step_id, event, detail));
+        if let Err(e) = recorded {
+            log::warn!("Failed to record {} trace event for step {}: {}", event, step_id, e);
+        }
     }
     
     /// Handle control flow step execution
@@ -717,14 +872,20 @@ impl WorkflowStateMachine {
         let block_id = current_step.get_control_flow_block_id()
             .ok_or_else(|| CoreError::Validation("Control flow step without block ID".to_string()))?;
         
+        let mut newly_skipped = Vec::new();
         for step in &workflow.steps {
             if let Some(step_block_id) = step.get_control_flow_block_id() {
-                if step_block_id == block_id {
-                    self.skipped_steps.insert(step.id.clone());
+                if step_block_id == block_id && self.skipped_steps.insert(step.id.clone()) {
+                    newly_skipped.push(step.id.clone());
                 }
             }
         }
         
+        let reason = format!("the condition of step '{}' in block '{}' was not met", current_step_id, block_id);
+        for step_id in newly_skipped {
+            self.record_trace_event(&step_id, explainer::EVENT_SKIPPED, serde_json::json!({ "reason": reason }));
+        }
+        
         Ok(())
     }
     
@@ -758,15 +919,30 @@ impl WorkflowStateMachine {
     
     /// Mark a step as completed
     pub fn mark_step_completed(&mut self, step_id: &str, output: serde_json::Value) -> CoreResult<()> {
+        self.mark_step_completed_at(step_id, output, None, Utc::now())
+    }
+    
+    /// Mark a step as completed at a given time
+    ///
+    /// The duration is only known when the start time is given.
+    pub fn mark_step_completed_at(
+        &mut self,
+        step_id: &str,
+        output: serde_json::Value,
+        started_at: Option<DateTime<Utc>>,
+        completed_at: DateTime<Utc>,
+    ) -> CoreResult<()> {
         if let Some(step_state) = self.step_states.get_mut(step_id) {
             let result = StepResult {
                 step_id: step_id.to_string(),
                 status: StepStatus::Completed,
                 output: Some(output),
                 error: None,
-                started_at: Utc::now(), // This should be updated with actual start time
-                completed_at: Some(Utc::now()),
-                duration_ms: None, // This should be calculated from actual start time
+                started_at: started_at.unwrap_or(completed_at),
+                completed_at: Some(completed_at),
+                duration_ms: started_at.map(|started| (completed_at - started).num_milliseconds().max(0) as u64),
+                cache_hit: None,
+                resources: None,
             };
             
             step_state.mark_completed(result.clone());
@@ -785,8 +961,49 @@ impl WorkflowStateMachine {
         }
     }
     
+    /// Mark a step as completed with an output reused from the step cache
+    pub fn mark_step_cached(&mut self, step_id: &str, output: serde_json::Value, hit: StepCacheHit) -> CoreResult<()> {
+        self.mark_step_completed(step_id, output)?;
+        if let Some(result) = self.completed_steps.last_mut() {
+            result.cache_hit = Some(hit);
+            if let Some(step_state) = self.step_states.get_mut(step_id) {
+                step_state.result = Some(result.clone());
+            }
+        }
+        Ok(())
+    }
+    
+    /// Resolve the cache key of a cached step against the run context
+    ///
+    /// Returns None for steps without caching.
+    pub fn step_cache_key(&self, step_id: &str) -> CoreResult<Option<String>> {
+        let step = self.step_states.get(step_id)
+            .map(|state| &state.step)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?;
+        let Some(cache) = &step.cache else {
+            return Ok(None);
+        };
+        let context = self.condition_context.as_ref()
+            .ok_or_else(|| CoreError::Internal("Condition context not available".to_string()))?;
+        
+        let evaluator = ConditionEvaluator::new(context.clone(), self.completed_steps.clone());
+        let value = evaluator.resolve_value(&cache.key_expression)?;
+        Ok(Some(StepCache::key_for(&value)))
+    }
+    
     /// Mark a step as failed
     pub fn mark_step_failed(&mut self, step_id: &str, error: String) -> CoreResult<()> {
+        self.mark_step_failed_at(step_id, error, None, Utc::now())
+    }
+    
+    /// Mark a step as failed at a given time
+    pub fn mark_step_failed_at(
+        &mut self,
+        step_id: &str,
+        error: String,
+        started_at: Option<DateTime<Utc>>,
+        failed_at: DateTime<Utc>,
+    ) -> CoreResult<()> {
         if let Some(step_state) = self.step_states.get_mut(step_id) {
             step_state.mark_failed(error.clone());
             
@@ -795,9 +1012,11 @@ impl WorkflowStateMachine {
                 status: StepStatus::Failed,
                 output: None,
                 error: Some(error),
-                started_at: Utc::now(), // This should be updated with actual start time
-                completed_at: Some(Utc::now()),
-                duration_ms: None, // This should be calculated from actual start time
+                started_at: started_at.unwrap_or(failed_at),
+                completed_at: Some(failed_at),
+                duration_ms: started_at.map(|started| (failed_at - started).num_milliseconds().max(0) as u64),
+                cache_hit: None,
+                resources: None,
             };
             
             self.completed_steps.push(result);
@@ -811,6 +1030,61 @@ impl WorkflowStateMachine {
         }
     }
     
+    /// Prepare a failed step for another attempt, if its retry configuration allows one
+    ///
+    /// Returns the delay to wait before the attempt, or None when the step is
+    /// out of attempts or its failure is of a class it does not retry. The
+    /// failed result is dropped unless it was already saved.
+    pub fn retry_step(&mut self, step_id: &str) -> CoreResult<Option<u64>> {
+        let step_state = self.step_states.get_mut(step_id)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?;
+        if step_state.status != StepStatus::Failed || !step_state.can_retry() {
+            return Ok(None);
+        }
+        
+        let delay_ms = step_state.retry_delay_ms();
+        let detail = serde_json::json!({
+            "attempt": step_state.retry_count,
+            "max_attempts": step_state.step.retry.as_ref().map(|retry_config| retry_config.max_attempts),
+            "error": step_state.last_error,
+            "delay_ms": delay_ms,
+        });
+        step_state.reset_for_retry();
+        if let Some(index) = self.completed_steps.iter()
+            .rposition(|result| result.step_id == step_id && result.status == StepStatus::Failed)
+            .filter(|index| *index >= self.saved_steps)
+        {
+            self.completed_steps.remove(index);
+        }
+        self.update_stats();
+        
+        let recorded = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))
+            .and_then(|state_manager| state_manager.record_run_event(&self.run_id, RunEvent::STEP_RETRIED, Some(step_id), RunEvent::ACTOR_ENGINE, detail));
+        if let Err(e) = recorded {
+            log::warn!("Failed to record retry of step {}: {}", step_id, e);
+        }
+        Ok(Some(delay_ms))
+    }
+    
+    /// Skip every step that has not finished yet, after a step completed the run early
+    pub fn skip_remaining_steps(&mut self, by_step: &str) -> CoreResult<usize> {
+        let mut remaining: Vec<String> = self.step_states.iter()
+            .filter(|(_, state)| matches!(state.status, StepStatus::Pending | StepStatus::Running))
+            .map(|(step_id, _)| step_id.clone())
+            .collect();
+        remaining.sort();
+        
+        let reason = format!("step '{}' completed the run early", by_step);
+        for step_id in &remaining {
+            self.mark_step_skipped(step_id, &reason)?;
+        }
+        self.update_stats();
+        
+        log::info!("Step {} completed the run early, skipped {} steps", by_step, remaining.len());
+        Ok(remaining.len())
+    }
+    
     /// Update dependencies when a step is completed
     fn update_dependencies(&mut self, completed_step_id: &str) {
         for step_state in self.step_states.values_mut() {
@@ -902,7 +1176,7 @@ impl WorkflowStateMachine {
     }
     
     /// Save state to database
-    pub fn save_state(&self) -> CoreResult<()> {
+    pub fn save_state(&mut self) -> CoreResult<()> {
         let mut state_manager = self.state_manager.lock()
             .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
         
@@ -916,29 +1190,26 @@ impl WorkflowStateMachine {
         
         state_manager.update_run_status(&self.run_id, run_status)?;
         
-        // Save completed steps
-        for step_result in &self.completed_steps {
+        // Save the step results completed since the last save
+        for step_result in self.completed_steps.iter().skip(self.saved_steps) {
             state_manager.save_step_result(&self.run_id, step_result.clone())?;
         }
+        self.saved_steps = self.completed_steps.len();
         
         log::debug!("Saved workflow state to database");
         Ok(())
     }
 
-    /// Execute completion hooks (onSuccess or onFailure)
+    /// Execute completion hooks (onSuccess, onFailure or onCancel)
     pub fn execute_completion_hooks(&self, context: &crate::models::WorkflowCompletionContext) -> CoreResult<()> {
         log::info!("Executing completion hooks for workflow: {} run: {}", context.workflow_id, context.run_id);
         
         // Determine which hook to execute based on final status
-        let hook_type = if context.is_success() {
-            "onSuccess"
-        } else {
-            "onFailure"
-        };
+        let hook_type = context.hook_type();
         
         log::info!("Executing {} hook for workflow: {}", hook_type, context.workflow_id);
         
-        // In the next phase, this will call the N-API function to execute hooks in Bun.js
+        // The hook itself is delivered to Node once the final run status is recorded
         match hook_type {
             "onSuccess" => {
                 log::info!("✅ Workflow {} completed successfully in {}ms", 
@@ -959,6 +1230,16 @@ impl WorkflowStateMachine {
                     log::error!("   - Error: {}", error);
                 }
             },
+            "onCancel" => {
+                log::warn!("Workflow {} was cancelled after {}ms",
+                    context.workflow_id,
+                    context.duration_ms.unwrap_or(0)
+                );
+                log::warn!("   - Completed steps: {}", context.completed_step_count());
+                if let Some(cancellation) = &context.cancellation {
+                    log::warn!("   - Interrupted steps: {:?}", cancellation.interrupted_steps);
+                }
+            },
             _ => {
                 log::warn!("Unknown hook type: {}", hook_type);
             }
@@ -1008,9 +1289,12 @@ impl WorkflowStateMachine {
             RunStatus::Failed => WorkflowExecutionState::Failed,
             _ => WorkflowExecutionState::Failed, // Default to failed for unexpected status
         };
-        let new_state = self.execution_state.transition_to(target_state)?;
-        self.execution_state = new_state;
-        
+        // check_workflow_completion may already have moved to the final state
+        if self.execution_state != target_state {
+            let new_state = self.execution_state.transition_to(target_state)?;
+            self.execution_state = new_state;
+        }
+
         // Mark stats as completed
         self.stats.mark_completed();
         
@@ -1042,7 +1326,7 @@ impl WorkflowStateMachine {
         
         if let Some(workflow) = &self.workflow_definition {
             for step in &workflow.steps {
-                if step.is_parallel() {
+                if step.is_parallel() && !step.is_race() {
                     // Start or continue a parallel group
                     if let Some(ref mut group) = current_group {
                         group.step_ids.push(step.id.clone());
@@ -1071,10 +1355,21 @@ impl WorkflowStateMachine {
         groups
     }
     
-    /// Execute a parallel step group
-    pub fn execute_parallel_group(&mut self, group: &ParallelStepGroup) -> CoreResult<Vec<StepResult>> {
+    /// Execute a parallel step group through the job dispatcher
+    ///
+    /// At most `max_concurrent_steps` steps of the group are in flight at once.
+    /// With `fail_fast`, the first failure tells the dispatcher to cancel the
+    /// in-flight siblings, which stop at their next cancellation check, and the
+    /// steps that were not submitted yet. Cancelled steps are skipped with the
+    /// reason in their result, and a step cancelled event is recorded for each.
+    pub async fn execute_parallel_group(&mut self, group: &ParallelStepGroup, dispatcher: &Dispatcher) -> CoreResult<Vec<StepResult>> {
         log::info!("Executing parallel group: {} with {} steps", group.group_id, group.step_ids.len());
         
+        let workflow = self.workflow_definition.clone()
+            .ok_or_else(|| CoreError::Internal("Workflow definition not loaded".to_string()))?;
+        let run = self.workflow_run.clone()
+            .ok_or_else(|| CoreError::Internal("Workflow run not loaded".to_string()))?;
+        
         // Mark group as running
         let mut group = group.clone();
         group.mark_running();
@@ -1083,43 +1378,148 @@ impl WorkflowStateMachine {
         self.parallel_groups.insert(group.group_id.clone(), group.clone());
         self.running_parallel_groups.insert(group.group_id.clone());
         
-        // In a real implementation, this would use the job dispatcher for concurrent execution
+        let max_in_flight = self.parallel_config.max_concurrent_steps.max(1);
+        let mut pending: VecDeque<String> = group.step_ids.iter().cloned().collect();
+        let mut in_flight: HashMap<String, String> = HashMap::new();
+        let mut receivers: tokio::task::JoinSet<(String, Result<JobExecutionResult, oneshot::error::RecvError>)> = tokio::task::JoinSet::new();
         let mut results = Vec::new();
+        let mut cancellation: Option<String> = None;
+        let deadline = group.timeout_ms.map(|ms| tokio::time::Instant::now() + std::time::Duration::from_millis(ms));
         
-        for step_id in &group.step_ids {
-            // Simulate step execution first (before any mutable borrows)
-            let result = self.simulate_parallel_step_execution(step_id)?;
-            let result_clone = result.clone();
+        loop {
+            while cancellation.is_none() && in_flight.len() < max_in_flight {
+                let Some(step_id) = pending.pop_front() else { break };
+                
+                let mut job = Job::from_workflow_step(&workflow, &run, &step_id, run.payload.clone())?;
+                // Siblings are independent and earlier steps already completed in the state machine
+                job.id = Job::get_job_id(&workflow.id, &run.id.to_string(), &step_id);
+                job.dependencies.clear();
+                
+                let job_id = job.id.clone();
+                let receiver = dispatcher.submit_job_with_result(job).await?;
+                if let Some(step_state) = self.step_states.get_mut(&step_id) {
+                    step_state.mark_running();
+                }
+                
+                in_flight.insert(step_id.clone(), job_id);
+                receivers.spawn(async move { (step_id, receiver.await) });
+            }
+            
+            if in_flight.is_empty() {
+                break;
+            }
+            
+            let next = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, receivers.join_next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        cancellation = Some(format!("Parallel group {} timed out", group.group_id));
+                        break;
+                    }
+                },
+                None => receivers.join_next().await,
+            };
+            let Some(joined) = next else { break };
+            let (step_id, received) = joined
+                .map_err(|e| CoreError::Internal(format!("Parallel step waiter failed: {}", e)))?;
+            in_flight.remove(&step_id);
+            
+            let result = match received {
+                Ok(JobExecutionResult { result: Some(result), .. }) => result,
+                Ok(execution) => Self::failed_step_result(
+                    &step_id,
+                    execution.error.unwrap_or_else(|| "Step failed without an error message".to_string()),
+                ),
+                Err(_) => Self::failed_step_result(&step_id, "Job was cancelled before completing".to_string()),
+            };
+            
+            if result.status.is_failure() && group.fail_fast && cancellation.is_none() {
+                cancellation = Some(format!("Cancelled because sibling step {} failed", step_id));
+            }
+            self.record_parallel_step_result(&group.group_id, result.clone())?;
             results.push(result);
             
-            if let Some(step_state) = self.step_states.get_mut(step_id) {
-                // Mark step as running
-                step_state.mark_running();
-                
-                // Mark step as completed
-                step_state.mark_completed(result_clone.clone());
+            if cancellation.is_some() {
+                break;
             }
+        }
+        
+        if let Some(reason) = &cancellation {
+            log::warn!("Cancelling {} in-flight and {} pending steps of parallel group {}: {}",
+                in_flight.len(), pending.len(), group.group_id, reason);
             
-            if let Some(group) = self.parallel_groups.get_mut(&group.group_id) {
-                group.add_step_result(step_id.clone(), result_clone);
+            receivers.abort_all();
+            let mut cancelled: Vec<(String, bool)> = Vec::new();
+            for (step_id, job_id) in &in_flight {
+                let was_running = dispatcher.cancel_job(job_id).await?;
+                cancelled.push((step_id.clone(), was_running));
+            }
+            cancelled.sort();
+            cancelled.extend(pending.into_iter().map(|step_id| (step_id, false)));
+            
+            for (step_id, was_running) in cancelled {
+                let result = self.mark_step_skipped(&step_id, reason)?;
+                if let Some(tracked) = self.parallel_groups.get_mut(&group.group_id) {
+                    tracked.add_step_result(step_id.clone(), result.clone());
+                }
+                self.record_step_cancelled(&step_id, &group.group_id, reason, was_running);
+                results.push(result);
             }
         }
         
         // Mark group as completed
-        if let Some(group) = self.parallel_groups.get_mut(&group.group_id) {
-            if group.has_failures() {
-                group.mark_partially_failed("Some steps in parallel group failed".to_string());
+        if let Some(tracked) = self.parallel_groups.get_mut(&group.group_id) {
+            if let Some(reason) = cancellation {
+                tracked.mark_failed(reason);
+            } else if tracked.has_failures() {
+                tracked.mark_partially_failed("Some steps in parallel group failed".to_string());
             } else {
-                group.mark_completed();
+                tracked.mark_completed();
             }
         }
         
         self.running_parallel_groups.remove(&group.group_id);
+        self.update_stats();
         
         log::info!("Parallel group {} completed with {} results", group.group_id, results.len());
         Ok(results)
     }
     
+    /// Record the result of a step that ran as part of a parallel group
+    fn record_parallel_step_result(&mut self, group_id: &str, result: StepResult) -> CoreResult<()> {
+        let step_id = result.step_id.clone();
+        let step_state = self.step_states.get_mut(&step_id)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?;
+        
+        if result.status.is_failure() {
+            step_state.mark_failed(result.error.clone().unwrap_or_default());
+        } else {
+            step_state.mark_completed(result.clone());
+            self.update_dependencies(&step_id);
+        }
+        
+        if let Some(group) = self.parallel_groups.get_mut(group_id) {
+            group.add_step_result(step_id, result.clone());
+        }
+        self.completed_steps.push(result);
+        Ok(())
+    }
+    
+    /// Build a failed step result for a step that did not produce one
+    fn failed_step_result(step_id: &str, error: String) -> StepResult {
+        StepResult {
+            step_id: step_id.to_string(),
+            status: StepStatus::Failed,
+            output: None,
+            error: Some(error),
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: None,
+            cache_hit: None,
+            resources: None,
+        }
+    }
+    
     /// Aggregate results from parallel steps
     pub fn aggregate_parallel_results(&self, results: Vec<StepResult>) -> CoreResult<serde_json::Value> {
         let mut aggregated = serde_json::Map::new();
@@ -1174,37 +1574,424 @@ impl WorkflowStateMachine {
         
         Ok(())
     }
+
+    /// Detect race groups: runs of consecutive race steps sharing a group ID
+    pub fn detect_race_groups(&self) -> Vec<ParallelStepGroup> {
+        let mut groups: Vec<ParallelStepGroup> = Vec::new();
+        let mut current: Option<(Option<String>, ParallelStepGroup)> = None;
+        
+        if let Some(workflow) = &self.workflow_definition {
+            for step in &workflow.steps {
+                if !step.is_race() {
+                    groups.extend(current.take().map(|(_, group)| group));
+                    continue;
+                }
+                
+                match &mut current {
+                    Some((group_key, group)) if *group_key == step.parallel_group_id => {
+                        group.step_ids.push(step.id.clone());
+                    }
+                    _ => {
+                        groups.extend(current.take().map(|(_, group)| group));
+                        let mut group = ParallelStepGroup::new(format!("race_group_{}", step.id), vec![step.id.clone()]);
+                        group.timeout_ms = self.parallel_config.default_timeout_ms;
+                        current = Some((step.parallel_group_id.clone(), group));
+                    }
+                }
+            }
+            groups.extend(current.map(|(_, group)| group));
+        }
+        
+        groups
+    }
     
-    /// Simulate parallel step execution (placeholder for actual execution)
-    fn simulate_parallel_step_execution(&self, step_id: &str) -> CoreResult<StepResult> {
-        log::debug!("Simulating parallel step execution: {}", step_id);
+    /// Execute a race group through the job dispatcher
+    ///
+    /// All steps of the group run concurrently and the first successful result
+    /// wins. The remaining siblings are cancelled in the dispatcher and marked
+    /// as skipped. The group only fails when every step fails.
+    pub async fn execute_race_group(&mut self, group: &ParallelStepGroup, dispatcher: &Dispatcher) -> CoreResult<Vec<StepResult>> {
+        log::info!("Executing race group: {} with {} steps", group.group_id, group.step_ids.len());
+        
+        let workflow = self.workflow_definition.clone()
+            .ok_or_else(|| CoreError::Internal("Workflow definition not loaded".to_string()))?;
+        let run = self.workflow_run.clone()
+            .ok_or_else(|| CoreError::Internal("Workflow run not loaded".to_string()))?;
+        
+        let mut group = group.clone();
+        group.mark_running();
+        self.parallel_groups.insert(group.group_id.clone(), group.clone());
+        self.running_parallel_groups.insert(group.group_id.clone());
+        
+        let mut in_flight: HashMap<String, String> = HashMap::new();
+        let mut receivers: tokio::task::JoinSet<(String, Result<JobExecutionResult, oneshot::error::RecvError>)> = tokio::task::JoinSet::new();
+        for step_id in &group.step_ids {
+            let mut job = Job::from_workflow_step(&workflow, &run, step_id, run.payload.clone())?;
+            job.id = Job::get_job_id(&workflow.id, &run.id.to_string(), step_id);
+            job.dependencies.clear();
+            
+            let job_id = job.id.clone();
+            let receiver = dispatcher.submit_job_with_result(job).await?;
+            if let Some(step_state) = self.step_states.get_mut(step_id) {
+                step_state.mark_running();
+            }
+            
+            in_flight.insert(step_id.clone(), job_id);
+            let step_id = step_id.clone();
+            receivers.spawn(async move { (step_id, receiver.await) });
+        }
         
-        let start_time = Utc::now();
+        let deadline = group.timeout_ms.map(|ms| tokio::time::Instant::now() + std::time::Duration::from_millis(ms));
+        let mut results = Vec::new();
+        let mut winner: Option<String> = None;
+        let mut timed_out = false;
+        
+        while winner.is_none() && !in_flight.is_empty() {
+            let next = match deadline {
+                Some(deadline) => match tokio::time::timeout_at(deadline, receivers.join_next()).await {
+                    Ok(next) => next,
+                    Err(_) => {
+                        timed_out = true;
+                        break;
+                    }
+                },
+                None => receivers.join_next().await,
+            };
+            let Some(joined) = next else { break };
+            let (step_id, received) = joined
+                .map_err(|e| CoreError::Internal(format!("Race step waiter failed: {}", e)))?;
+            in_flight.remove(&step_id);
+            
+            let result = match received {
+                Ok(JobExecutionResult { result: Some(result), .. }) => result,
+                Ok(execution) => Self::failed_step_result(
+                    &step_id,
+                    execution.error.unwrap_or_else(|| "Step failed without an error message".to_string()),
+                ),
+                Err(_) => Self::failed_step_result(&step_id, "Job was cancelled before completing".to_string()),
+            };
+            
+            if result.status == StepStatus::Completed {
+                winner = Some(step_id.clone());
+            }
+            self.record_parallel_step_result(&group.group_id, result.clone())?;
+            results.push(result);
+        }
         
-        // Simulate some processing time
-        std::thread::sleep(std::time::Duration::from_millis(100));
+        receivers.abort_all();
+        for job_id in in_flight.values() {
+            dispatcher.cancel_job(job_id).await?;
+        }
         
-        let end_time = Utc::now();
-        let duration_ms = (end_time - start_time).num_milliseconds() as u64;
+        // Once a step wins, the branches that failed before it lost the race rather than failing the run
+        if let Some(winner) = &winner {
+            let failed: Vec<String> = results.iter()
+                .filter(|r| r.status.is_failure())
+                .map(|r| r.step_id.clone())
+                .collect();
+            for step_id in failed {
+                self.mark_step_skipped(&step_id, &format!("it failed before step '{}' won the race", winner))?;
+            }
+        }
         
-        let output = serde_json::json!({
-            "step_id": step_id,
-            "status": "completed",
-            "message": "Parallel step executed successfully (simulated)",
-            "timestamp": end_time.to_rfc3339(),
-            "duration_ms": duration_ms,
-        });
+        let losers: Vec<String> = in_flight.into_keys().collect();
+        for step_id in &losers {
+            let reason = match &winner {
+                Some(winner) => format!("step '{}' won the race", winner),
+                None => format!("race group {} timed out", group.group_id),
+            };
+            let result = self.mark_step_skipped(step_id, &reason)?;
+            if let Some(tracked) = self.parallel_groups.get_mut(&group.group_id) {
+                tracked.add_step_result(step_id.clone(), result.clone());
+            }
+            results.push(result);
+        }
         
-        Ok(StepResult {
+        if let Some(tracked) = self.parallel_groups.get_mut(&group.group_id) {
+            tracked.winner = winner.clone();
+            match &winner {
+                Some(_) => tracked.mark_completed(),
+                None if timed_out => tracked.mark_failed(format!("Race group {} timed out", group.group_id)),
+                None => tracked.mark_failed(format!("All steps of race group {} failed", group.group_id)),
+            }
+        }
+        
+        self.running_parallel_groups.remove(&group.group_id);
+        self.update_stats();
+        
+        log::info!("Race group {} finished, winner: {:?}, cancelled: {}", group.group_id, winner, losers.len());
+        Ok(results)
+    }
+    
+    /// Record that a step of a parallel group was cancelled, in the run's event log
+    fn record_step_cancelled(&self, step_id: &str, group_id: &str, reason: &str, was_running: bool) {
+        let detail = serde_json::json!({
+            "group_id": group_id,
+            "reason": reason,
+            "was_running": was_running,
+        });
+        let recorded = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))
+            .and_then(|state_manager| state_manager.record_run_event(&self.run_id, RunEvent::STEP_CANCELLED, Some(step_id), RunEvent::ACTOR_ENGINE, detail));
+        if let Err(e) = recorded {
+            log::warn!("Failed to record cancellation of step {}: {}", step_id, e);
+        }
+    }
+    
+    /// Mark a step as skipped and persist its result, e.g. a race step that lost
+    ///
+    /// The reason is kept in the result's output as `{"skipped": true, "reason": ...}`.
+    fn mark_step_skipped(&mut self, step_id: &str, reason: &str) -> CoreResult<StepResult> {
+        self.skip_step_with_output(step_id, reason, serde_json::json!({ "skipped": true, "reason": reason }))
+    }
+    
+    /// Mark a step as skipped with the given output and persist its result
+    fn skip_step_with_output(&mut self, step_id: &str, reason: &str, output: serde_json::Value) -> CoreResult<StepResult> {
+        let result = StepResult {
             step_id: step_id.to_string(),
-            status: StepStatus::Completed,
+            status: StepStatus::Skipped,
             output: Some(output),
             error: None,
-            started_at: start_time,
-            completed_at: Some(end_time),
-            duration_ms: Some(duration_ms),
+            started_at: Utc::now(),
+            completed_at: Some(Utc::now()),
+            duration_ms: None,
+            cache_hit: None,
+            resources: None,
+        };
+        
+        if let Some(step_state) = self.step_states.get_mut(step_id) {
+            step_state.status = StepStatus::Skipped;
+            step_state.result = Some(result.clone());
+        }
+        self.skipped_steps.insert(step_id.to_string());
+        self.completed_steps.push(result.clone());
+        self.record_trace_event(step_id, explainer::EVENT_SKIPPED, serde_json::json!({ "reason": reason }));
+        
+        let state_manager = self.state_manager.lock()
+            .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+        state_manager.save_step_result(&self.run_id, result.clone())?;
+        Ok(result)
+    }
+    
+    /// Aggregate the results of a race group, surfacing which branch won
+    pub fn aggregate_race_results(&self, group_id: &str, results: &[StepResult]) -> CoreResult<serde_json::Value> {
+        let group = self.parallel_groups.get(group_id)
+            .ok_or_else(|| CoreError::Internal(format!("Race group not found: {}", group_id)))?;
+        
+        let winner_output = group.winner.as_ref()
+            .and_then(|winner| results.iter().find(|r| &r.step_id == winner))
+            .and_then(|r| r.output.clone());
+        let cancelled: Vec<&str> = results.iter()
+            .filter(|r| r.status == StepStatus::Skipped)
+            .map(|r| r.step_id.as_str())
+            .collect();
+        let errors: serde_json::Map<String, serde_json::Value> = results.iter()
+            .filter(|r| r.status == StepStatus::Failed)
+            .map(|r| (r.step_id.clone(), serde_json::json!(r.error)))
+            .collect();
+        
+        Ok(serde_json::json!({
+            "winner": group.winner,
+            "output": winner_output,
+            "cancelled": cancelled,
+            "errors": errors,
+        }))
+    }
+
+    /// Execute a forEach step by fanning out one job per item through the job dispatcher
+    ///
+    /// The items expression is resolved against the run context. At most
+    /// `for_each_concurrency` items (or `max_concurrent_steps` when unset) are in
+    /// flight at once, and the per-item results are aggregated into the step output.
+    pub async fn execute_for_each_step(&mut self, step_id: &str, dispatcher: &Dispatcher) -> CoreResult<StepResult> {
+        let workflow = self.workflow_definition.clone()
+            .ok_or_else(|| CoreError::Internal("Workflow definition not loaded".to_string()))?;
+        let run = self.workflow_run.clone()
+            .ok_or_else(|| CoreError::Internal("Workflow run not loaded".to_string()))?;
+        let step = workflow.get_step(step_id)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?
+            .clone();
+        let items_expr = step.for_each_items.clone()
+            .filter(|_| step.is_for_each())
+            .ok_or_else(|| CoreError::Validation(format!("Step {} is not a forEach step", step_id)))?;
+
+        let items = match self.resolve_for_each_items(&items_expr) {
+            Ok(items) => items,
+            Err(e) => {
+                self.mark_step_failed(step_id, e.to_string())?;
+                return Err(e);
+            }
+        };
+        log::info!("Executing forEach step {} over {} items", step_id, items.len());
+
+        self.mark_step_running(step_id)?;
+        let started_at = Utc::now();
+
+        let max_in_flight = step.for_each_concurrency.unwrap_or(self.parallel_config.max_concurrent_steps).max(1);
+        let mut pending: VecDeque<usize> = (0..items.len()).collect();
+        let mut in_flight = 0;
+        let mut receivers: tokio::task::JoinSet<(usize, Result<JobExecutionResult, oneshot::error::RecvError>)> = tokio::task::JoinSet::new();
+        let mut item_results: Vec<Option<StepResult>> = vec![None; items.len()];
+
+        loop {
+            while in_flight < max_in_flight {
+                let Some(index) = pending.pop_front() else { break };
+
+                let item_step_id = Self::for_each_item_step_id(step_id, index);
+                let mut job = Job::from_workflow_step(&workflow, &run, step_id, items[index].clone())?;
+                // Each item runs as its own job; the step's dependencies already completed
+                job.id = Job::get_job_id(&workflow.id, &run.id.to_string(), &item_step_id);
+                job.step_name = item_step_id;
+                job.dependencies.clear();
+                job.add_context("for_each".to_string(), serde_json::json!({
+                    "step_id": step_id,
+                    "index": index,
+                    "total": items.len(),
+                }));
+
+                let receiver = dispatcher.submit_job_with_result(job).await?;
+                in_flight += 1;
+                receivers.spawn(async move { (index, receiver.await) });
+            }
+
+            let Some(joined) = receivers.join_next().await else { break };
+            let (index, received) = joined
+                .map_err(|e| CoreError::Internal(format!("forEach item waiter failed: {}", e)))?;
+            in_flight -= 1;
+
+            let item_step_id = Self::for_each_item_step_id(step_id, index);
+            item_results[index] = Some(match received {
+                Ok(JobExecutionResult { result: Some(result), .. }) => result,
+                Ok(execution) => Self::failed_step_result(
+                    &item_step_id,
+                    execution.error.unwrap_or_else(|| "Item failed without an error message".to_string()),
+                ),
+                Err(_) => Self::failed_step_result(&item_step_id, "Job was cancelled before completing".to_string()),
+            });
+        }
+
+        let item_results: Vec<StepResult> = item_results.into_iter().flatten().collect();
+        let output = Self::aggregate_for_each_results(&items, &item_results);
+        let failure_count = item_results.iter().filter(|r| r.status != StepStatus::Completed).count();
+
+        if failure_count > 0 {
+            self.mark_step_failed(step_id, format!("{} of {} forEach items failed", failure_count, items.len()))?;
+        } else {
+            self.mark_step_completed(step_id, output.clone())?;
+        }
+
+        let mut result = self.completed_steps.last().cloned()
+            .ok_or_else(|| CoreError::Internal(format!("No result recorded for step {}", step_id)))?;
+        result.output = Some(output);
+        result.started_at = started_at;
+        result.duration_ms = result.completed_at.map(|completed_at| (completed_at - started_at).num_milliseconds().max(0) as u64);
+        if let Some(last) = self.completed_steps.last_mut() {
+            *last = result.clone();
+        }
+
+        {
+            let state_manager = self.state_manager.lock()
+                .map_err(|e| CoreError::Internal(format!("Failed to acquire state manager lock: {}", e)))?;
+            state_manager.save_step_result(&self.run_id, result.clone())?;
+        }
+
+        log::info!("forEach step {} finished with {} of {} items failed", step_id, failure_count, items.len());
+        Ok(result)
+    }
+
+    /// Resolve the items expression of a forEach step against the run context
+    fn resolve_for_each_items(&self, items_expr: &str) -> CoreResult<Vec<serde_json::Value>> {
+        let context = self.condition_context.as_ref()
+            .ok_or_else(|| CoreError::Internal("Condition context not available".to_string()))?;
+
+        let evaluator = ConditionEvaluator::new(context.clone(), self.completed_steps.clone());
+        match evaluator.resolve_value(items_expr)? {
+            serde_json::Value::Array(items) => Ok(items),
+            other => Err(CoreError::Validation(format!(
+                "forEach items expression '{}' must resolve to an array, got {}", items_expr, other
+            ))),
+        }
+    }
+
+    /// Get when a delay step lets the run continue, if it waits from `now`
+    ///
+    /// `delay_until` may resolve to an RFC 3339 timestamp or to epoch milliseconds.
+    pub fn delay_wake_at(&self, step_id: &str, now: DateTime<Utc>) -> CoreResult<DateTime<Utc>> {
+        let step = self.step_states.get(step_id)
+            .map(|state| &state.step)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?;
+        if let Some(delay_ms) = step.delay_ms {
+            return Ok(now + chrono::Duration::milliseconds(delay_ms as i64));
+        }
+        let until_expr = step.delay_until.as_deref()
+            .ok_or_else(|| CoreError::Validation(format!("Step {} is not a delay step", step_id)))?;
+        
+        let context = self.condition_context.as_ref()
+            .ok_or_else(|| CoreError::Internal("Condition context not available".to_string()))?;
+        let evaluator = ConditionEvaluator::new(context.clone(), self.completed_steps.clone());
+        let value = evaluator.resolve_value(until_expr)?;
+        let wake_at = match &value {
+            serde_json::Value::String(timestamp) => DateTime::parse_from_rfc3339(timestamp)
+                .ok()
+                .map(|at| at.with_timezone(&Utc)),
+            serde_json::Value::Number(millis) => millis.as_i64().and_then(DateTime::from_timestamp_millis),
+            _ => None,
+        };
+        wake_at.ok_or_else(|| CoreError::Validation(format!(
+            "Delay expression '{}' must resolve to an RFC 3339 timestamp or epoch milliseconds, got {}", until_expr, value
+        )))
+    }
+
+    /// Build the payload a sub-workflow step starts its child run with
+    ///
+    /// Each input field is resolved from its expression; without an input
+    /// mapping the child gets the parent run's payload.
+    pub fn subworkflow_payload(&self, step_id: &str) -> CoreResult<serde_json::Value> {
+        let step = self.step_states.get(step_id)
+            .map(|state| &state.step)
+            .ok_or_else(|| CoreError::StepNotFound(format!("Step not found: {}", step_id)))?;
+        let context = self.condition_context.as_ref()
+            .ok_or_else(|| CoreError::Internal("Condition context not available".to_string()))?;
+        let Some(input) = &step.subworkflow_input else {
+            return Ok(context.payload.clone());
+        };
+        
+        let evaluator = ConditionEvaluator::new(context.clone(), self.completed_steps.clone());
+        let mut payload = serde_json::Map::new();
+        for (field, expr) in input {
+            payload.insert(field.clone(), evaluator.resolve_value(expr)?);
+        }
+        Ok(serde_json::Value::Object(payload))
+    }
+
+    /// Step ID under which a single forEach item is executed and persisted
+    fn for_each_item_step_id(step_id: &str, index: usize) -> String {
+        format!("{}[{}]", step_id, index)
+    }
+
+    /// Aggregate per-item results of a forEach step into the step output
+    fn aggregate_for_each_results(items: &[serde_json::Value], results: &[StepResult]) -> serde_json::Value {
+        let entries: Vec<serde_json::Value> = items.iter()
+            .zip(results)
+            .enumerate()
+            .map(|(index, (item, result))| serde_json::json!({
+                "index": index,
+                "item": item,
+                "status": result.status.as_str(),
+                "output": result.output,
+                "error": result.error,
+            }))
+            .collect();
+        let success_count = results.iter().filter(|r| r.status == StepStatus::Completed).count();
+
+        serde_json::json!({
+            "results": entries,
+            "total_items": items.len(),
+            "success_count": success_count,
+            "failure_count": results.len() - success_count,
         })
     }
+
 }
 
 
@@ -1228,6 +2015,23 @@ mod tests {
                     timeout: None,
                     retry: None,
                     depends_on: vec![],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 },
                 StepDefinition {
                     id: "step-2".to_string(),
@@ -1236,6 +2040,23 @@ mod tests {
                     timeout: None,
                     retry: None,
                     depends_on: vec!["step-1".to_string()],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 },
                 StepDefinition {
                     id: "step-3".to_string(),
@@ -1244,6 +2065,23 @@ mod tests {
                     timeout: None,
                     retry: None,
                     depends_on: vec!["step-1".to_string()],
+                    for_each_items: None,
+                    for_each_concurrency: None,
+                    idempotent: false,
+                    adaptive_timeout: None,
+                    delay_ms: None,
+                    delay_until: None,
+                    subworkflow_id: None,
+                    subworkflow_input: None,
+                    wait_for_signal: None,
+                    signal_timeout_ms: None,
+                    skip_if: None,
+                    skip_dependents: false,
+                    output_schema: None,
+                    priority: None,
+                    cache: None,
+                    worker_tags: Vec::new(),
+                    input: None,
                 },
             ],
             triggers: vec![],
@@ -1263,6 +2101,7 @@ mod tests {
             ],
             created_at: Utc::now(),
             updated_at: Utc::now(),
+            defaults: None,
         }
     }
 
@@ -1275,6 +2114,7 @@ mod tests {
             started_at: Utc::now(),
             completed_at: None,
             error: None,
+            version: 0,
         }
     }
 
@@ -1302,6 +2142,23 @@ mod tests {
             timeout: None,
             retry: None,
             depends_on: vec!["dependency-1".to_string(), "dependency-2".to_string()],
+            for_each_items: None,
+            for_each_concurrency: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
         };
         
         let step_state = StepExecutionState::new(step);
@@ -1321,6 +2178,23 @@ mod tests {
             timeout: None,
             retry: None,
             depends_on: vec!["dependency-1".to_string(), "dependency-2".to_string()],
+            for_each_items: None,
+            for_each_concurrency: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
         };
         
         let mut step_state = StepExecutionState::new(step);
@@ -1387,4 +2261,266 @@ mod tests {
         assert!(state.is_terminal());
         assert_eq!(state.as_str(), "cancelled");
     }
-} 
\ No newline at end of file
+
+    fn parallel_step(id: &str) -> StepDefinition {
+        StepDefinition {
+            id: id.to_string(),
+            name: id.to_string(),
+            title: None,
+            description: None,
+            action: "test_action".to_string(),
+            timeout: None,
+            retry: None,
+            depends_on: vec![],
+            condition_type: None,
+            condition_expression: None,
+            control_flow_block: None,
+            is_control_flow: false,
+            parallel: Some(true),
+            parallel_group_id: Some("group-1".to_string()),
+            parallel_step_count: Some(3),
+            race: None,
+            for_each: None,
+            for_each_items: None,
+            for_each_concurrency: None,
+            pause: None,
+            idempotent: false,
+            adaptive_timeout: None,
+            delay_ms: None,
+            delay_until: None,
+            subworkflow_id: None,
+            subworkflow_input: None,
+            wait_for_signal: None,
+            signal_timeout_ms: None,
+            skip_if: None,
+            skip_dependents: false,
+            output_schema: None,
+            priority: None,
+            cache: None,
+            worker_tags: Vec::new(),
+            input: None,
+        }
+    }
+
+    async fn setup_parallel(dir: &tempfile::TempDir, payload: serde_json::Value) -> (WorkflowStateMachine, Dispatcher) {
+        setup_with_steps(dir, vec![parallel_step("p1"), parallel_step("p2"), parallel_step("p3")], payload).await
+    }
+
+    async fn setup_with_steps(dir: &tempfile::TempDir, steps: Vec<StepDefinition>, payload: serde_json::Value) -> (WorkflowStateMachine, Dispatcher) {
+        let db_path = dir.path().join("parallel.db").to_str().unwrap().to_string();
+        let mut state_manager = crate::state::StateManager::new(&db_path).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "parallel-workflow".to_string(),
+            name: "Parallel Workflow".to_string(),
+            description: None,
+            steps,
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let run_id = state_manager.create_run("parallel-workflow", payload).unwrap();
+
+        let mut state_machine = WorkflowStateMachine::new(Arc::new(Mutex::new(state_manager)), "parallel-workflow".to_string(), run_id);
+        state_machine.initialize().unwrap();
+
+        let dispatcher_state = Arc::new(tokio::sync::Mutex::new(crate::state::StateManager::new(&db_path).unwrap()));
+        let mut dispatcher = Dispatcher::new(crate::dispatcher::WorkerPoolConfig::default(), dispatcher_state);
+        dispatcher.start().await.unwrap();
+        (state_machine, dispatcher)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_parallel_group_executes_through_dispatcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_machine, mut dispatcher) = setup_parallel(&dir, serde_json::json!({})).await;
+        state_machine.parallel_config.max_concurrent_steps = 2;
+
+        let group = state_machine.detect_parallel_groups().remove(0);
+        let results = state_machine.execute_parallel_group(&group, &dispatcher).await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|r| r.status == StepStatus::Completed));
+        // Real results come from the dispatcher workers, not a simulation
+        assert!(results.iter().all(|r| r.output.as_ref().unwrap()["job_id"].is_string()));
+        assert_eq!(state_machine.get_step_state("p3").unwrap().status, StepStatus::Completed);
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_parallel_group_fail_fast_cancels_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut state_machine, mut dispatcher) = setup_parallel(&dir, serde_json::json!({"should_fail": true})).await;
+        state_machine.parallel_config.max_concurrent_steps = 1;
+
+        let mut group = state_machine.detect_parallel_groups().remove(0);
+        group.fail_fast = true;
+        let results = state_machine.execute_parallel_group(&group, &dispatcher).await.unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].step_id, "p1");
+        assert!(results[0].error.as_deref().unwrap().contains("Test job failure"));
+        assert!(results[1..].iter().all(|r| r.status == StepStatus::Skipped));
+        assert!(results[1..].iter().all(|r| r.output.as_ref().unwrap()["reason"].as_str().unwrap().contains("sibling step p1 failed")));
+        assert_eq!(state_machine.get_step_state("p2").unwrap().status, StepStatus::Skipped);
+        assert_eq!(dispatcher.get_stats().await.unwrap().total_jobs_processed, 1);
+        
+        let state_manager = state_machine.state_manager.lock().unwrap();
+        let cancelled: Vec<String> = state_manager.get_run_timeline(&state_machine.run_id).unwrap().into_iter()
+            .filter(|e| e.event_type == crate::models::RunEvent::STEP_CANCELLED)
+            .filter_map(|e| e.step_id)
+            .collect();
+        assert_eq!(cancelled, vec!["p2".to_string(), "p3".to_string()]);
+        drop(state_manager);
+        dispatcher.stop().await.unwrap();
+    }
+    fn race_step(id: &str) -> StepDefinition {
+        let mut step = parallel_step(id);
+        step.parallel = None;
+        step.race = Some(true);
+        step
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_race_group_resolves_with_first_success_and_cancels_siblings() {
+        let dir = tempfile::tempdir().unwrap();
+        let steps = vec![race_step("r1"), race_step("r2"), race_step("r3")];
+        let (mut state_machine, mut dispatcher) = setup_with_steps(&dir, steps, serde_json::json!({})).await;
+
+        let groups = state_machine.detect_race_groups();
+        assert_eq!(groups.len(), 1);
+        assert!(state_machine.detect_parallel_groups().is_empty());
+
+        let results = state_machine.execute_race_group(&groups[0], &dispatcher).await.unwrap();
+        assert_eq!(results.len(), 3);
+        let aggregated = state_machine.aggregate_race_results(&groups[0].group_id, &results).unwrap();
+        let winner = aggregated["winner"].as_str().unwrap().to_string();
+        assert_eq!(aggregated["output"]["step_name"], winner.as_str());
+        assert_eq!(aggregated["cancelled"].as_array().unwrap().len(), 2);
+
+        assert_eq!(state_machine.get_step_state(&winner).unwrap().status, StepStatus::Completed);
+        let skipped = state_machine.get_step_states().values().filter(|s| s.status == StepStatus::Skipped).count();
+        assert_eq!(skipped, 2);
+        assert!(state_machine.get_ready_steps().is_empty());
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_race_group_fails_when_every_step_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let steps = vec![race_step("r1"), race_step("r2")];
+        let (mut state_machine, mut dispatcher) = setup_with_steps(&dir, steps, serde_json::json!({"should_fail": true})).await;
+
+        let group = state_machine.detect_race_groups().remove(0);
+        let results = state_machine.execute_race_group(&group, &dispatcher).await.unwrap();
+        assert!(results.iter().all(|r| r.status == StepStatus::Failed));
+
+        let aggregated = state_machine.aggregate_race_results(&group.group_id, &results).unwrap();
+        assert!(aggregated["winner"].is_null());
+        assert_eq!(aggregated["errors"].as_object().unwrap().len(), 2);
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_for_each_fans_out_items_and_aggregates_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("for_each.db").to_str().unwrap().to_string();
+        let mut step = parallel_step("each");
+        step.parallel = None;
+        step.for_each = Some(true);
+        step.for_each_items = Some("ctx.payload.orders".to_string());
+        step.for_each_concurrency = Some(2);
+
+        let mut state_manager = crate::state::StateManager::new(&db_path).unwrap();
+        state_manager.register_workflow(WorkflowDefinition {
+            id: "for-each-workflow".to_string(),
+            name: "ForEach Workflow".to_string(),
+            description: None,
+            steps: vec![step],
+            triggers: vec![],
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            concurrency: None,
+            namespace: None,
+            payload_sample_rate: None,
+            priority: None,
+            run_timeout_ms: None,
+            run_timeout_outcome: Default::default(),
+            serialize_on: None,
+            resource_quota: None,
+            lane: None,
+            defaults: None,
+        }).unwrap();
+        let payload = serde_json::json!({"orders": [{"id": 1}, {"id": 2, "should_fail": true}, {"id": 3}]});
+        let run_id = state_manager.create_run("for-each-workflow", payload).unwrap();
+        let state_manager = Arc::new(Mutex::new(state_manager));
+
+        let mut state_machine = WorkflowStateMachine::new(state_manager.clone(), "for-each-workflow".to_string(), run_id);
+        state_machine.initialize().unwrap();
+        let dispatcher_state = Arc::new(tokio::sync::Mutex::new(crate::state::StateManager::new(&db_path).unwrap()));
+        let mut dispatcher = Dispatcher::new(crate::dispatcher::WorkerPoolConfig::default(), dispatcher_state);
+        dispatcher.start().await.unwrap();
+
+        let result = state_machine.execute_for_each_step("each", &dispatcher).await.unwrap();
+        assert_eq!(result.status, StepStatus::Failed);
+        assert_eq!(result.error.as_deref(), Some("1 of 3 forEach items failed"));
+
+        let output = result.output.unwrap();
+        assert_eq!(output["total_items"], 3);
+        assert_eq!(output["success_count"], 2);
+        assert_eq!(output["results"][0]["output"]["test_data"]["id"], 1);
+        assert_eq!(output["results"][0]["output"]["step_name"], "each[0]");
+        assert_eq!(output["results"][1]["status"], "failed");
+        assert_eq!(state_machine.get_step_state("each").unwrap().status, StepStatus::Failed);
+
+        let persisted = state_manager.lock().unwrap().get_completed_steps(&run_id).unwrap();
+        assert!(persisted.iter().any(|r| r.step_id == "each" && r.output.is_some()));
+        dispatcher.stop().await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_failed_steps_retry_with_their_backoff_strategy_and_rules() {
+        use crate::models::{BackoffStrategy, RetryConfig, RetryErrorClass, RetryRules};
+
+        assert_eq!((1..=5).map(|retry| BackoffStrategy::Fibonacci.delay_ms(100, retry)).collect::<Vec<_>>(), vec![100, 100, 200, 300, 500]);
+        let capped = BackoffStrategy::Exponential { factor: 3.0, max_ms: Some(1000) };
+        assert_eq!((1..=4).map(|retry| capped.delay_ms(100, retry)).collect::<Vec<_>>(), vec![100, 300, 900, 1000]);
+        assert_eq!(BackoffStrategy::Fixed.delay_ms(100, 4), 100);
+        assert_eq!(RetryErrorClass::classify("Validation error: amount is required"), RetryErrorClass::Validation);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut step = parallel_step("flaky");
+        step.parallel = None;
+        step.parallel_group_id = None;
+        step.parallel_step_count = None;
+        step.retry = Some(RetryConfig {
+            max_attempts: 4,
+            backoff_ms: 100,
+            strategy: BackoffStrategy::Linear,
+            rules: RetryRules { retry_on: None, no_retry_on: vec![RetryErrorClass::Validation] },
+        });
+        let (mut state_machine, mut dispatcher) = setup_with_steps(&dir, vec![step], serde_json::json!({})).await;
+
+        state_machine.mark_step_failed("flaky", "Step flaky timed out after 50ms".to_string()).unwrap();
+        assert_eq!(state_machine.retry_step("flaky").unwrap(), Some(100));
+        assert_eq!(state_machine.get_step_state("flaky").unwrap().status, StepStatus::Pending);
+        assert!(state_machine.get_completed_steps().is_empty());
+        state_machine.mark_step_failed("flaky", "Step execution failed: upstream returned 503".to_string()).unwrap();
+        assert_eq!(state_machine.retry_step("flaky").unwrap(), Some(200));
+
+        // Validation failures are not retried, even with attempts left
+        state_machine.mark_step_failed("flaky", "Validation error: amount is required".to_string()).unwrap();
+        assert_eq!(state_machine.retry_step("flaky").unwrap(), None);
+        assert_eq!(state_machine.get_step_state("flaky").unwrap().status, StepStatus::Failed);
+        dispatcher.stop().await.unwrap();
+    }
+}
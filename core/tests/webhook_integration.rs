@@ -27,7 +27,7 @@ fn test_webhook_server_configuration() {
         .max_connections(2000)
         .graceful_shutdown_timeout(Duration::from_secs(60))
         .build(
-            Arc::new(Mutex::new(TriggerManager::new())),
+            Arc::new(tokio::sync::Mutex::new(TriggerManager::new())),
             Arc::new(Mutex::new(StateManager::new(":memory:").unwrap())),
         );
     
@@ -193,7 +193,7 @@ fn test_webhook_server_is_not_running_initially() {
         .host("127.0.0.1".to_string())
         .port(0) // Use port 0 for automatic assignment
         .build(
-            Arc::new(Mutex::new(TriggerManager::new())),
+            Arc::new(tokio::sync::Mutex::new(TriggerManager::new())),
             Arc::new(Mutex::new(StateManager::new(":memory:").unwrap())),
         );
     
@@ -453,8 +453,11 @@ export async function executeWebhookTrigger(request: any): Promise<any> {
   return await executeWebhookTriggerFromModule(request);
 }
 
-export async function executeScheduleTrigger(triggerId: string): Promise<any> {
-  return await executeScheduleTriggerFromModule(triggerId);
+export async function executeScheduleTrigger(
+  workflowId: string,
+  payload: any = {}
+): Promise<any> {
+  return await executeScheduleTriggerFromModule(workflowId, payload);
 }
 
 export async function getTriggerStats(): Promise<any> {
@@ -38,14 +38,13 @@ export function convertToRustFormat(workflow: WorkflowDefinition): any {
     name: workflow.name || workflow.id,
     description: workflow.description || '',
     concurrency: workflow.concurrency || null,
+    payload_sample_rate: workflow.payloadSampleRate ?? null,
     steps: workflow.steps.map(step => ({
       id: step.id,
       name: step.name,
       title: step.title,
       description: step.description,
       action: step.handler.toString(),
-      type: step.type,
-      handler: step.handler.toString(),
       timeout: step.options?.timeout
         ? typeof step.options.timeout === 'string'
           ? parseDuration(step.options.timeout)
@@ -68,8 +67,11 @@ export function convertToRustFormat(workflow: WorkflowDefinition): any {
       parallel_group_id: null,
       parallel_step_count: null,
       race: null,
-      for_each: null,
+      for_each: step.options?.forEachItems ? true : null,
+      for_each_items: step.options?.forEachItems ?? null,
+      for_each_concurrency: step.options?.forEachConcurrency ?? null,
       pause: null,
+      idempotent: step.options?.idempotent ?? false,
     })),
     triggers: workflow.triggers.map(trigger => {
       if (trigger.type === 'webhook') {
@@ -80,7 +82,12 @@ export function convertToRustFormat(workflow: WorkflowDefinition): any {
           },
         };
       } else if (trigger.type === 'schedule') {
-        return 'Manual';
+        return {
+          Schedule: {
+            cron_expression: trigger.cron_expression,
+            overlap_policy: trigger.overlap_policy || 'allow',
+          },
+        };
       } else if (trigger.type === 'event') {
         return 'Manual';
       } else {
@@ -66,19 +66,24 @@ export async function executeWebhookTrigger(request: any): Promise<any> {
   }
 }
 
-export async function executeScheduleTrigger(triggerId: string): Promise<any> {
+export async function executeScheduleTrigger(
+  workflowId: string,
+  payload: any = {}
+): Promise<any> {
   if (!core) {
     return {
       success: true,
       run_id: 'simulation-schedule-run-id',
-      workflow_id: 'simulation-workflow',
+      workflow_id: workflowId,
       message: 'Schedule trigger executed in simulation mode',
     };
   }
 
   try {
+    const payloadJson = JSON.stringify(payload);
     const result = core.executeScheduleTrigger(
-      triggerId,
+      workflowId,
+      payloadJson,
       getCurrentStateFunction().dbPath
     );
 
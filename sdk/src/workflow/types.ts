@@ -12,6 +12,7 @@ export interface WorkflowDefinition {
   };
   timeout?: string | number;
   concurrency?: number;
+  payloadSampleRate?: number;
   rateLimit?: {
     count: number;
     per: string;
@@ -74,6 +75,8 @@ export interface StepOptions {
   workflowId?: string;
   input?: any;
   forEach?: boolean;
+  forEachItems?: string;
+  forEachConcurrency?: number;
   batch?: boolean;
   batchSize?: number;
   humanInTheLoop?: boolean;
@@ -88,6 +91,7 @@ export interface StepOptions {
   onError?: (ctx: Context) => any;
   background?: boolean;
   pause?: boolean;
+  idempotent?: boolean;
 }
 
 export interface CacheConfig {
@@ -95,9 +99,19 @@ export interface CacheConfig {
   ttl: string;
 }
 
+export type ScheduleOverlapPolicy =
+  | 'allow'
+  | 'skip'
+  | 'queue'
+  | 'cancel_previous';
+
 export type TriggerDefinition =
   | { type: 'webhook'; path: string; options?: WebhookOptions }
-  | { type: 'schedule'; cron_expression: string }
+  | {
+      type: 'schedule';
+      cron_expression: string;
+      overlap_policy?: ScheduleOverlapPolicy;
+    }
   | { type: 'event'; eventName: string }
   | { type: 'manual' };
 
@@ -14,6 +14,7 @@ export const WorkflowDefinitionSchema = z.object({
     .optional(),
   timeout: z.union([z.string(), z.number()]).optional(),
   concurrency: z.number().optional(),
+  payloadSampleRate: z.number().min(0).max(100).optional(),
   rateLimit: z
     .object({
       count: z.number(),
@@ -49,6 +50,9 @@ export const WorkflowDefinitionSchema = z.object({
               })
               .optional(),
             delay: z.union([z.string(), z.number()]).optional(),
+            idempotent: z.boolean().optional(),
+            forEachItems: z.string().optional(),
+            forEachConcurrency: z.number().int().positive().optional(),
           })
           .optional(),
       })
@@ -84,6 +88,9 @@ export const WorkflowDefinitionSchema = z.object({
       z.object({
         type: z.literal('schedule'),
         cron_expression: z.string().min(1, 'Cron expression cannot be empty'),
+        overlap_policy: z
+          .enum(['allow', 'skip', 'queue', 'cancel_previous'])
+          .optional(),
       }),
       z.object({ type: z.literal('manual') }),
     ])